@@ -1,9 +1,11 @@
 fn main() {
     // Link macOS frameworks for ScreenCaptureKit system audio capture
+    // and EventKit calendar access
     #[cfg(target_os = "macos")]
     {
         println!("cargo:rustc-link-lib=framework=CoreMedia");
         println!("cargo:rustc-link-lib=framework=ScreenCaptureKit");
+        println!("cargo:rustc-link-lib=framework=EventKit");
     }
 
     tauri_build::build()
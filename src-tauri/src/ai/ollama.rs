@@ -83,6 +83,9 @@ impl OllamaClient {
 
     /// List available models
     pub async fn list_models(&self) -> Result<Vec<OllamaModel>, OllamaError> {
+        // Spans here are never entered, as they are held across awaits; traces time them
+        // from creation to drop
+        let _span = tracing::info_span!("ollama.list_models");
         let url = format!("{}/api/tags", self.base_url);
 
         let response = self
@@ -121,6 +124,7 @@ impl OllamaClient {
         temperature: f32,
         context_length: Option<u32>,
     ) -> Result<String, OllamaError> {
+        let _span = tracing::info_span!("ollama.generate", model, prompt_chars = prompt.len());
         let url = format!("{}/api/generate", self.base_url);
 
         let request = GenerateRequest {
@@ -177,6 +181,7 @@ impl OllamaClient {
         context_length: Option<u32>,
         tx: mpsc::Sender<String>,
     ) -> Result<String, OllamaError> {
+        let _span = tracing::info_span!("ollama.generate_stream", model, prompt_chars = prompt.len());
         let url = format!("{}/api/generate", self.base_url);
 
         let request = GenerateRequest {
@@ -224,14 +229,14 @@ impl OllamaClient {
                 Ok(bytes) => {
                     // Parse each line (newline-delimited JSON)
                     let text = String::from_utf8_lossy(&bytes);
-                    eprintln!("[ollama] Raw chunk bytes: {} bytes", bytes.len());
+                    tracing::debug!("Raw chunk bytes: {} bytes", bytes.len());
                     for line in text.lines() {
                         if line.is_empty() {
                             continue;
                         }
                         if let Ok(gen_response) = serde_json::from_str::<GenerateResponse>(line) {
                             if !gen_response.response.is_empty() {
-                                eprintln!("[ollama] Parsed token: {:?}", &gen_response.response);
+                                tracing::debug!("Parsed token: {:?}", &gen_response.response);
                                 full_response.push_str(&gen_response.response);
                                 // Send chunk to channel
                                 let _ = tx.send(gen_response.response).await;
@@ -251,6 +256,7 @@ impl OllamaClient {
     /// Pull (download) a model
     #[allow(dead_code)]
     pub async fn pull_model(&self, model: &str) -> Result<(), OllamaError> {
+        let _span = tracing::info_span!("ollama.pull_model", model);
         let url = format!("{}/api/pull", self.base_url);
 
         #[derive(Serialize)]
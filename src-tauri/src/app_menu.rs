@@ -0,0 +1,82 @@
+//! The macOS application menu, with Hide instead of Quit on Cmd+Q. Other platforms have
+//! no application menu, so these are no-ops there.
+
+use tauri::AppHandle;
+
+/// Set the menu and handle its custom items (call from setup)
+#[cfg(target_os = "macos")]
+pub fn install(app: &AppHandle) -> tauri::Result<()> {
+    use tauri::Manager;
+
+    app.set_menu(build(app)?)?;
+
+    // Handle custom menu events
+    app.on_menu_event(move |app_handle, event| match event.id().as_ref() {
+        "hide_window" => {
+            if let Some(window) = app_handle.get_webview_window("main") {
+                let _ = window.hide();
+            }
+        }
+        "quit_app" => crate::shutdown::exit(app_handle),
+        _ => {}
+    });
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn install(_app: &AppHandle) -> tauri::Result<()> {
+    Ok(())
+}
+
+/// Rebuild the menu after its shortcuts changed
+pub fn rebuild(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    match build(app) {
+        Ok(menu) => {
+            let _ = app.set_menu(menu);
+        }
+        Err(e) => tracing::warn!("Failed to rebuild the app menu: {}", e),
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = app;
+}
+
+#[cfg(target_os = "macos")]
+fn build(app: &AppHandle) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{MenuBuilder, PredefinedMenuItem, SubmenuBuilder};
+
+    use crate::shortcuts::menu_item;
+
+    let hide_window = menu_item(app, "hide_window", "Hide Window", true, "menu.hide_window")?;
+    let quit = menu_item(app, "quit_app", "Quit Note67", true, "menu.quit_app")?;
+
+    let app_submenu = SubmenuBuilder::new(app, "Note67")
+        .item(&PredefinedMenuItem::about(app, Some("About Note67"), None)?)
+        .separator()
+        .item(&hide_window)
+        .item(&quit)
+        .build()?;
+
+    let edit_submenu = SubmenuBuilder::new(app, "Edit")
+        .item(&PredefinedMenuItem::undo(app, None)?)
+        .item(&PredefinedMenuItem::redo(app, None)?)
+        .separator()
+        .item(&PredefinedMenuItem::cut(app, None)?)
+        .item(&PredefinedMenuItem::copy(app, None)?)
+        .item(&PredefinedMenuItem::paste(app, None)?)
+        .item(&PredefinedMenuItem::select_all(app, None)?)
+        .build()?;
+
+    let window_submenu = SubmenuBuilder::new(app, "Window")
+        .item(&PredefinedMenuItem::minimize(app, None)?)
+        .item(&PredefinedMenuItem::maximize(app, None)?)
+        .separator()
+        .item(&PredefinedMenuItem::close_window(app, None)?)
+        .build()?;
+
+    MenuBuilder::new(app)
+        .item(&app_submenu)
+        .item(&edit_submenu)
+        .item(&window_submenu)
+        .build()
+}
@@ -1,12 +1,42 @@
 //! Acoustic Echo Cancellation (AEC) module
 //!
-//! Currently disabled - using post-processing deduplication instead.
-//! The complex NLMS filter was too slow and not effective enough.
+//! Removes system audio (the far end, played through the speakers) from the mic signal
+//! with a partitioned-block frequency-domain adaptive filter: the echo path is modelled by
+//! `TAIL_MS` of filter split into block-sized partitions, each adapted in the frequency
+//! domain with a step normalized per bin. Work per sample is a few FFTs per block rather
+//! than a pass over the whole filter, and the per-bin normalization converges on speech
+//! where time-domain NLMS stalls.
+//!
+//! The mic output lags the input by one block. Duplicates that still get through are
+//! caught by post-processing deduplication in live.rs.
 
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use realfft::num_complex::Complex32;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
 
 /// Global flag to enable/disable AEC
-static AEC_ENABLED: AtomicBool = AtomicBool::new(false); // Disabled by default now
+static AEC_ENABLED: AtomicBool = AtomicBool::new(false); // Off by default; headphones need none
+
+/// The canceller `apply_aec` runs, created by `init_aec`
+static PROCESSOR: Mutex<Option<AecProcessor>> = Mutex::new(None);
+
+/// Length of echo the filter can model
+const TAIL_MS: u32 = 250;
+
+/// Adaptation step (0..1); lower is slower but steadier through double talk
+const STEP: f32 = 0.5;
+
+/// Per-block decay of the per-bin far-end power
+const POWER_DECAY: f32 = 0.9;
+
+/// Far-end blocks quieter than this (mean square) don't adapt the filter
+const MIN_FAR_POWER: f32 = 1e-7;
+
+/// Far-end audio kept waiting for mic audio, beyond which the oldest is dropped
+const MAX_PENDING_BLOCKS: usize = 50;
 
 /// Check if AEC is enabled
 pub fn is_aec_enabled() -> bool {
@@ -18,22 +48,261 @@ pub fn set_aec_enabled(enabled: bool) {
     AEC_ENABLED.store(enabled, Ordering::SeqCst);
 }
 
-/// Initialize the global AEC processor (no-op now)
-#[allow(dead_code)]
-pub fn init_aec(_sample_rate: u32) {
-    // No-op - AEC disabled
+/// Initialize the global AEC processor for mic and reference audio at `sample_rate`
+pub fn init_aec(sample_rate: u32) {
+    if let Ok(mut processor) = PROCESSOR.lock() {
+        *processor = Some(AecProcessor::new(sample_rate));
+    }
 }
 
-/// Apply AEC to mic samples - now just returns original samples
-/// Echo removal is handled by post-processing deduplication in live.rs
-#[allow(dead_code)]
-pub fn apply_aec(mic_samples: &[f32], _reference_samples: &[f32]) -> Vec<f32> {
-    // Just return original samples - deduplication handles echo removal
-    mic_samples.to_vec()
+/// Apply AEC to mic samples (mono), with the system audio played meanwhile as reference.
+/// Returns as many samples as were passed in. Without `init_aec`, or with AEC off, the
+/// mic samples come back unchanged.
+pub fn apply_aec(mic_samples: &[f32], reference_samples: &[f32]) -> Vec<f32> {
+    if !is_aec_enabled() {
+        return mic_samples.to_vec();
+    }
+    match PROCESSOR
+        .lock()
+        .ok()
+        .as_deref_mut()
+        .and_then(Option::as_mut)
+    {
+        Some(processor) => processor.process(mic_samples, reference_samples),
+        None => mic_samples.to_vec(),
+    }
 }
 
-/// Reset the AEC processor (no-op now)
-#[allow(dead_code)]
+/// Reset the AEC processor, forgetting the echo path it learned
 pub fn reset_aec() {
-    // No-op
+    if let Ok(mut processor) = PROCESSOR.lock()
+        && let Some(processor) = processor.as_mut()
+    {
+        *processor = AecProcessor::new(processor.sample_rate);
+    }
+}
+
+/// Partitioned-block frequency-domain echo canceller (overlap-save, constrained updates)
+pub struct AecProcessor {
+    sample_rate: u32,
+    /// Samples per block; the FFT is twice this
+    block: usize,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    /// Filter partitions, newest echo first, as spectra
+    weights: Vec<Vec<Complex32>>,
+    /// Far-end spectra of the last blocks, newest first, one per partition
+    history: VecDeque<Vec<Complex32>>,
+    /// Smoothed far-end power per bin, summed over the partitions
+    power: Vec<f32>,
+    /// Previous far-end block, the first half of each FFT frame
+    last_far: Vec<f32>,
+    pending_mic: Vec<f32>,
+    pending_far: VecDeque<f32>,
+    /// Processed mic audio not yet returned; starts with one block of silence
+    output: VecDeque<f32>,
+    /// One FFT frame of samples, and one spectrum
+    frame: Vec<f32>,
+    scratch: Vec<Complex32>,
+}
+
+impl AecProcessor {
+    pub fn new(sample_rate: u32) -> Self {
+        // Blocks of 16 to 21 ms: 256 samples at 16 kHz, 1024 at 48 kHz
+        let block = ((sample_rate / 64) as usize).next_power_of_two().max(64);
+        let tail = (sample_rate * TAIL_MS / 1000) as usize;
+        let partitions = tail.div_ceil(block).max(1);
+        let mut planner = RealFftPlanner::new();
+        let forward = planner.plan_fft_forward(block * 2);
+        let inverse = planner.plan_fft_inverse(block * 2);
+        let bins = block + 1;
+
+        Self {
+            sample_rate,
+            block,
+            weights: vec![vec![Complex32::default(); bins]; partitions],
+            history: (0..partitions)
+                .map(|_| vec![Complex32::default(); bins])
+                .collect(),
+            power: vec![0.0; bins],
+            last_far: vec![0.0; block],
+            pending_mic: Vec::with_capacity(block),
+            pending_far: VecDeque::new(),
+            output: std::iter::repeat_n(0.0, block).collect(),
+            frame: forward.make_input_vec(),
+            scratch: forward.make_output_vec(),
+            forward,
+            inverse,
+        }
+    }
+
+    /// Samples the output lags the mic by
+    pub fn latency(&self) -> usize {
+        self.block
+    }
+
+    /// Cancel echo of `far` from `mic`, returning `mic.len()` samples. Far-end audio that
+    /// runs ahead of the mic is kept for the next call; missing far-end audio counts as
+    /// silence.
+    pub fn process(&mut self, mic: &[f32], far: &[f32]) -> Vec<f32> {
+        self.pending_far.extend(far);
+        for &sample in mic {
+            self.pending_mic.push(sample);
+            if self.pending_mic.len() == self.block {
+                let far_block: Vec<f32> = (0..self.block)
+                    .map(|_| self.pending_far.pop_front().unwrap_or(0.0))
+                    .collect();
+                let near = std::mem::replace(&mut self.pending_mic, Vec::with_capacity(self.block));
+                self.process_block(&near, &far_block);
+            }
+        }
+
+        let max_pending = self.block * MAX_PENDING_BLOCKS;
+        if self.pending_far.len() > max_pending {
+            let excess = self.pending_far.len() - max_pending;
+            self.pending_far.drain(..excess);
+        }
+
+        (0..mic.len())
+            .map(|_| self.output.pop_front().unwrap_or(0.0))
+            .collect()
+    }
+
+    fn process_block(&mut self, near: &[f32], far: &[f32]) {
+        let block = self.block;
+        // realfft leaves both transforms unscaled
+        let scale = 1.0 / (block * 2) as f32;
+
+        // Far-end spectrum of the previous and current block
+        let mut far_spectrum = self
+            .history
+            .pop_back()
+            .unwrap_or_else(|| self.forward.make_output_vec());
+        self.frame[..block].copy_from_slice(&self.last_far);
+        self.frame[block..].copy_from_slice(far);
+        transform(self.forward.process(&mut self.frame, &mut far_spectrum));
+        self.history.push_front(far_spectrum);
+        self.last_far.copy_from_slice(far);
+
+        // Echo estimate: the filtered far end, last half of the inverse transform
+        let echo = &mut self.scratch;
+        echo.fill(Complex32::default());
+        for (weights, spectrum) in self.weights.iter().zip(&self.history) {
+            for ((e, w), x) in echo.iter_mut().zip(weights).zip(spectrum) {
+                *e += w * x;
+            }
+        }
+        transform(self.inverse.process(echo, &mut self.frame));
+        let error: Vec<f32> = near
+            .iter()
+            .zip(&self.frame[block..])
+            .map(|(d, y)| d - y * scale)
+            .collect();
+        self.output.extend(&error);
+
+        // Adapt only while the far end is playing
+        let far_power = far.iter().map(|x| x * x).sum::<f32>() / block as f32;
+        if far_power < MIN_FAR_POWER {
+            return;
+        }
+        // Over every partition, so a bin the far end has only just left (a sweep, or
+        // speech moving between formants) still steps gently for the older partitions.
+        // Rising at once and falling slowly keeps the step from overshooting on onsets.
+        for (bin, p) in self.power.iter_mut().enumerate() {
+            let total: f32 = self.history.iter().map(|x| x[bin].norm_sqr()).sum();
+            *p = (POWER_DECAY * *p).max(total);
+        }
+
+        let mut error_spectrum = self.forward.make_output_vec();
+        self.frame[..block].fill(0.0);
+        self.frame[block..].copy_from_slice(&error);
+        transform(self.forward.process(&mut self.frame, &mut error_spectrum));
+
+        // Normalized per bin; the regularization keeps quiet bins from blowing up
+        let floor = self.power.iter().sum::<f32>() / self.power.len() as f32 * 0.01 + 1e-6;
+        let gradient = &mut self.scratch;
+        for (weights, spectrum) in self.weights.iter_mut().zip(&self.history) {
+            for (((g, x), e), p) in gradient
+                .iter_mut()
+                .zip(spectrum)
+                .zip(&error_spectrum)
+                .zip(&self.power)
+            {
+                *g = x.conj() * e * (STEP / (p + floor));
+            }
+            // Keep each partition a causal block-length filter
+            transform(self.inverse.process(gradient, &mut self.frame));
+            self.frame[..block].iter_mut().for_each(|g| *g *= scale);
+            self.frame[block..].fill(0.0);
+            transform(self.forward.process(&mut self.frame, gradient));
+            for (w, g) in weights.iter_mut().zip(gradient.iter()) {
+                *w += g;
+            }
+        }
+    }
+}
+
+/// Buffers are made by the plans, so a transform can only complain about a DC or Nyquist
+/// bin with an imaginary part, which rounding can leave on a filter spectrum and the
+/// inverse transform drops anyway
+fn transform(result: Result<(), realfft::FftError>) {
+    if let Err(e) = result {
+        tracing::trace!("AEC transform: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(seed: &mut u32) -> f32 {
+        *seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        ((*seed >> 16) as f32 / 32768.0 - 1.0) * 0.3
+    }
+
+    fn power(samples: &[f32]) -> f32 {
+        samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32
+    }
+
+    #[test]
+    fn test_cancels_echo() {
+        let rate = 16000;
+        let mut seed = 1;
+        let far: Vec<f32> = (0..rate * 6).map(|_| noise(&mut seed)).collect();
+        // Echo path: 30 ms delay with a decaying tail
+        let path: Vec<(usize, f32)> = vec![(480, 0.6), (520, -0.3), (700, 0.15), (1200, 0.05)];
+        let mic: Vec<f32> = (0..far.len())
+            .map(|i| {
+                path.iter()
+                    .filter(|(delay, _)| i >= *delay)
+                    .map(|(delay, gain)| far[i - delay] * gain)
+                    .sum()
+            })
+            .collect();
+
+        let mut aec = AecProcessor::new(rate as u32);
+        let mut out = Vec::new();
+        // Uneven chunks, as live transcription hands them over
+        for (mic, far) in mic.chunks(13000).zip(far.chunks(13000)) {
+            out.extend(aec.process(mic, far));
+        }
+        assert_eq!(out.len(), mic.len());
+
+        // Compare the last second, allowing for the one-block delay
+        let block = aec.block;
+        let tail = mic.len() - rate;
+        let erle = 10.0 * (power(&mic[tail..]) / power(&out[tail + block..])).log10();
+        assert!(erle > 20.0, "ERLE {:.1} dB", erle);
+    }
+
+    #[test]
+    fn test_keeps_near_end_without_far_end() {
+        let mut aec = AecProcessor::new(16000);
+        let near: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let out = aec.process(&near, &[]);
+        let block = aec.block;
+        for (a, b) in out[block..].iter().zip(&near) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
 }
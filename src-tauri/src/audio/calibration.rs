@@ -0,0 +1,363 @@
+//! AEC self-test. Rising chirps are played through the default output while the microphone
+//! records; comparing the two tells how loud the echo is over the room's background noise,
+//! how late it arrives, and how much of it the canceller takes out (ERLE, echo return loss
+//! enhancement). From that the UI can recommend turning AEC on or off, or using
+//! headphones.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Serialize;
+
+use crate::audio::aec::AecProcessor;
+use crate::audio::{converter, devices, AudioError};
+
+/// Rate the recording is analysed at, the same as live transcription's
+const RATE: u32 = 16000;
+
+/// Silence before the chirp, to measure the background noise
+const LEAD_MS: u32 = 500;
+
+/// One slow sweep, which the echo is timed by
+const PROBE_MS: u32 = 1000;
+
+/// Then short sweeps over and over, which visit every frequency often enough for the
+/// canceller to learn the echo; it is measured over the last `MEASURE_MS`
+const BURST_MS: u32 = 100;
+const BURSTS: u32 = 30;
+const MEASURE_MS: u32 = 1000;
+
+/// Silence after the chirp, for the echo to arrive
+const TRAIL_MS: u32 = 500;
+
+const LOW_HZ: f32 = 200.0;
+const HIGH_HZ: f32 = 7000.0;
+
+/// Chirp amplitude, loud enough to measure without startling anyone
+const LEVEL: f32 = 0.25;
+
+/// Echo this far over the background counts as heard, in dB
+const MIN_ECHO_DB: f32 = 6.0;
+
+/// Cancellation worth having, in dB
+const GOOD_ERLE_DB: f32 = 10.0;
+
+/// Longest delay looked for
+const MAX_DELAY_MS: u32 = 500;
+
+/// Longest delay the canceller can follow
+const AEC_TAIL_MS: f32 = 250.0;
+
+/// Delay is found at a quarter of `RATE`, which is plenty for milliseconds
+const DECIMATION: usize = 4;
+
+/// What the UI should suggest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AecRecommendation {
+    /// No echo reaches the mic (headphones, or speakers far away); AEC isn't needed
+    Disable,
+    /// There is echo and the canceller removes it
+    Enable,
+    /// There is echo the canceller can't remove well enough
+    UseHeadphones,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationReport {
+    /// Mic level during the chirp over the background, in dB
+    pub echo_db: f32,
+    pub echo_detected: bool,
+    /// From playing the chirp to hearing it, when it was heard
+    pub delay_ms: Option<f32>,
+    /// How much of the echo the canceller removed, in dB, when there was echo
+    pub erle_db: Option<f32>,
+    pub recommendation: AecRecommendation,
+}
+
+fn ms_to_samples(ms: u32, rate: u32) -> usize {
+    (ms as u64 * rate as u64 / 1000) as usize
+}
+
+/// Exponential sweep: equal time per octave, like speech's spread of energy
+fn sweep(rate: u32, ms: u32) -> Vec<f32> {
+    let len = ms_to_samples(ms, rate);
+    let fade = ms_to_samples(5, rate);
+    let high = HIGH_HZ.min(rate as f32 * 0.45);
+    let duration = ms as f32 / 1000.0;
+    let k = (high / LOW_HZ).ln();
+
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            let phase = 2.0 * std::f32::consts::PI * LOW_HZ * duration / k
+                * ((t / duration * k).exp() - 1.0);
+            let edge = i.min(len - 1 - i);
+            let gain = (edge as f32 / fade as f32).min(1.0);
+            phase.sin() * LEVEL * gain
+        })
+        .collect()
+}
+
+/// The test signal at `rate`: lead silence, the probe, the bursts, then trailing silence
+pub fn chirp(rate: u32) -> Vec<f32> {
+    let mut signal = vec![0.0; ms_to_samples(LEAD_MS, rate)];
+    signal.extend(sweep(rate, PROBE_MS));
+    let burst = sweep(rate, BURST_MS);
+    for _ in 0..BURSTS {
+        signal.extend(&burst);
+    }
+    signal.resize(signal.len() + ms_to_samples(TRAIL_MS, rate), 0.0);
+    signal
+}
+
+fn power(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32
+}
+
+fn db(ratio: f32) -> f32 {
+    10.0 * ratio.max(1e-10).log10()
+}
+
+fn decimate(samples: &[f32]) -> Vec<f32> {
+    samples
+        .chunks(DECIMATION)
+        .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+        .collect()
+}
+
+/// Lag (at `RATE`) where the mic best matches the probe
+fn estimate_delay(reference: &[f32], mic: &[f32]) -> usize {
+    let start = ms_to_samples(LEAD_MS, RATE);
+    let probe = ms_to_samples(PROBE_MS, RATE);
+    let reference = decimate(&reference[start..start + probe]);
+    let mic = decimate(&mic[start.min(mic.len())..]);
+    let max_lag = ms_to_samples(MAX_DELAY_MS, RATE) / DECIMATION;
+
+    (0..=max_lag)
+        .map(|lag| {
+            let corr: f32 = reference
+                .iter()
+                .zip(mic.iter().skip(lag))
+                .map(|(r, m)| r * m)
+                .sum();
+            (lag, corr.abs())
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(lag, _)| lag * DECIMATION)
+}
+
+/// Measure the echo of `reference` (the chirp at `RATE`) in `mic`, recorded from when the
+/// chirp started playing
+pub fn analyze(reference: &[f32], mic: &[f32]) -> CalibrationReport {
+    let lead = ms_to_samples(LEAD_MS, RATE);
+    let sound = ms_to_samples(PROBE_MS + BURST_MS * BURSTS, RATE);
+    let mut mic = mic.to_vec();
+    mic.resize(reference.len(), 0.0);
+
+    // Skip the start of the lead, where the device may still be settling
+    let background = power(&mic[lead / 4..lead]);
+    let delay = estimate_delay(reference, &mic);
+    let heard_from = (lead + delay).min(mic.len());
+    let heard_to = (lead + sound + delay).min(mic.len());
+    let echo_db = db(power(&mic[heard_from..heard_to]) / background.max(1e-10));
+    let echo_detected = echo_db >= MIN_ECHO_DB;
+
+    if !echo_detected {
+        return CalibrationReport {
+            echo_db,
+            echo_detected,
+            delay_ms: None,
+            erle_db: None,
+            recommendation: AecRecommendation::Disable,
+        };
+    }
+
+    let mut aec = AecProcessor::new(RATE);
+    let mut cancelled = Vec::with_capacity(mic.len());
+    // In pieces, as live transcription would hand them over
+    for (mic, reference) in mic
+        .chunks(RATE as usize)
+        .zip(reference.chunks(RATE as usize))
+    {
+        cancelled.extend(aec.process(mic, reference));
+    }
+    let latency = aec.latency();
+    let measured_from = heard_to
+        .saturating_sub(ms_to_samples(MEASURE_MS, RATE))
+        .max(heard_from);
+    let before = power(&mic[measured_from..heard_to]);
+    let end = (heard_to + latency).min(cancelled.len());
+    let after = power(&cancelled[(measured_from + latency).min(end)..end]);
+    let erle_db = db(before / after.max(1e-10));
+
+    let delay_ms = delay as f32 * 1000.0 / RATE as f32;
+    let recommendation = if erle_db >= GOOD_ERLE_DB && delay_ms <= AEC_TAIL_MS {
+        AecRecommendation::Enable
+    } else {
+        AecRecommendation::UseHeadphones
+    };
+    CalibrationReport {
+        echo_db,
+        echo_detected,
+        delay_ms: Some(delay_ms),
+        erle_db: Some(erle_db),
+        recommendation,
+    }
+}
+
+/// Play the chirp on the default output while recording the chosen microphone, then
+/// analyse it. Blocks for the length of the chirp, about five seconds.
+pub fn run() -> Result<CalibrationReport, AudioError> {
+    let host = cpal::default_host();
+    let output = host
+        .default_output_device()
+        .ok_or(AudioError::NoOutputDevice)?;
+    let input = devices::input_device()?;
+
+    let input_config = input.default_input_config()?;
+    let input_rate = input_config.sample_rate().0;
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let input_stream = match input_config.sample_format() {
+        SampleFormat::F32 => record::<f32>(&input, &input_config.config(), &recorded)?,
+        SampleFormat::I16 => record::<i16>(&input, &input_config.config(), &recorded)?,
+        SampleFormat::U16 => record::<u16>(&input, &input_config.config(), &recorded)?,
+        _ => return Err(AudioError::UnsupportedFormat),
+    };
+
+    let output_config = output.default_output_config()?;
+    let signal = Arc::new(chirp(output_config.sample_rate().0));
+    let position = Arc::new(AtomicUsize::new(0));
+    let config = output_config.config();
+    let output_stream = match output_config.sample_format() {
+        SampleFormat::F32 => play::<f32>(&output, &config, &signal, &position)?,
+        SampleFormat::I16 => play::<i16>(&output, &config, &signal, &position)?,
+        SampleFormat::U16 => play::<u16>(&output, &config, &signal, &position)?,
+        _ => return Err(AudioError::UnsupportedFormat),
+    };
+
+    // Only the mic audio from when the chirp starts counts
+    input_stream.play()?;
+    recorded
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+    output_stream.play()?;
+
+    let length = Duration::from_secs_f32(signal.len() as f32 / config.sample_rate.0 as f32);
+    let deadline = Instant::now() + length + Duration::from_secs(2);
+    while position.load(Ordering::Relaxed) < signal.len() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(output_stream);
+    drop(input_stream);
+
+    let recorded = std::mem::take(&mut *recorded.lock().unwrap_or_else(PoisonError::into_inner));
+    if recorded.is_empty() {
+        return Err(AudioError::NoInputDevice);
+    }
+    let mic = converter::resample(&recorded, input_rate, RATE);
+    Ok(analyze(&chirp(RATE), &mic))
+}
+
+/// Record the device's first channel into `recorded`
+fn record<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    recorded: &Arc<Mutex<Vec<f32>>>,
+) -> Result<Stream, AudioError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let recorded = recorded.clone();
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            let mut recorded = recorded.lock().unwrap_or_else(PoisonError::into_inner);
+            recorded.extend(data.iter().step_by(channels).map(|&s| f32::from_sample(s)));
+        },
+        |err| tracing::error!("Calibration input error: {}", err),
+        None,
+    )?)
+}
+
+/// Play `signal` on every channel, then silence
+fn play<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    signal: &Arc<Vec<f32>>,
+    position: &Arc<AtomicUsize>,
+) -> Result<Stream, AudioError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let signal = signal.clone();
+    let position = position.clone();
+    Ok(device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            let mut index = position.load(Ordering::Relaxed);
+            for frame in data.chunks_mut(channels) {
+                let sample = T::from_sample(signal.get(index).copied().unwrap_or(0.0));
+                frame.fill(sample);
+                index += 1;
+            }
+            position.store(index, Ordering::Relaxed);
+        },
+        |err| tracing::error!("Calibration output error: {}", err),
+        None,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(seed: &mut u32) -> f32 {
+        *seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        ((*seed >> 16) as f32 / 32768.0 - 1.0) * 0.001
+    }
+
+    #[test]
+    fn test_speaker_echo() {
+        let reference = chirp(RATE);
+        let mut seed = 7;
+        // 40 ms away, with a weaker reflection
+        let mic: Vec<f32> = (0..reference.len())
+            .map(|i| {
+                let direct = i.checked_sub(640).map_or(0.0, |j| reference[j] * 0.3);
+                let reflection = i.checked_sub(900).map_or(0.0, |j| reference[j] * -0.1);
+                direct + reflection + noise(&mut seed)
+            })
+            .collect();
+
+        let report = analyze(&reference, &mic);
+        assert!(report.echo_detected);
+        let delay = report.delay_ms.unwrap();
+        assert!((delay - 40.0).abs() <= 1.0, "delay {}", delay);
+        assert!(report.erle_db.unwrap() >= GOOD_ERLE_DB, "{:?}", report);
+        assert_eq!(report.recommendation, AecRecommendation::Enable);
+    }
+
+    #[test]
+    fn test_headphones() {
+        let reference = chirp(RATE);
+        let mut seed = 7;
+        let mic: Vec<f32> = (0..reference.len()).map(|_| noise(&mut seed)).collect();
+
+        let report = analyze(&reference, &mic);
+        assert!(!report.echo_detected);
+        assert_eq!(report.delay_ms, None);
+        assert_eq!(report.recommendation, AecRecommendation::Disable);
+    }
+}
@@ -0,0 +1,185 @@
+//! Recording quality presets: the rate and channels the mic and system audio are written
+//! in. "device" (the default) records the mic as the device delivers it and system audio
+//! at 48kHz stereo; "voice" writes both at 16kHz mono, a sixth of the space and all
+//! transcription needs; "archival" writes both at 48kHz stereo.
+//!
+//! A note keeps the preset it was first recorded with, stored on the note with the rates
+//! it gives, so every segment of it matches even if the setting changes in between. The
+//! captures follow the preset of the recording being made (`active`), converting what the
+//! devices deliver; the mixer writes the playback file at the mic's rate, so it follows
+//! as well.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use hound::WavSpec;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::db::Database;
+
+/// One of `PRESETS`
+pub const SETTING_PRESET: &str = "recording_quality";
+pub const PRESETS: &[&str] = &["device", "voice", "archival"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl CaptureFormat {
+    /// The 16-bit spec recordings in this format are written with
+    pub fn spec(self) -> WavSpec {
+        WavSpec {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        }
+    }
+}
+
+const VOICE: CaptureFormat = CaptureFormat {
+    sample_rate: 16000,
+    channels: 1,
+};
+
+const ARCHIVAL: CaptureFormat = CaptureFormat {
+    sample_rate: 48000,
+    channels: 2,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    #[default]
+    Device,
+    Voice,
+    Archival,
+}
+
+impl Preset {
+    pub fn as_str(self) -> &'static str {
+        PRESETS[self as usize]
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "device" => Some(Self::Device),
+            "voice" => Some(Self::Voice),
+            "archival" => Some(Self::Archival),
+            _ => None,
+        }
+    }
+
+    /// The mic's format; None to keep the input device's own
+    pub fn mic_format(self) -> Option<CaptureFormat> {
+        match self {
+            Self::Device => None,
+            Self::Voice => Some(VOICE),
+            Self::Archival => Some(ARCHIVAL),
+        }
+    }
+
+    pub fn system_format(self) -> CaptureFormat {
+        match self {
+            Self::Device | Self::Archival => ARCHIVAL,
+            Self::Voice => VOICE,
+        }
+    }
+
+    fn from_index(index: u8) -> Self {
+        match index {
+            1 => Self::Voice,
+            2 => Self::Archival,
+            _ => Self::Device,
+        }
+    }
+}
+
+/// The setting, for notes not recorded yet
+static SETTING: AtomicU8 = AtomicU8::new(Preset::Device as u8);
+
+/// The preset of the recording being made
+static ACTIVE: AtomicU8 = AtomicU8::new(Preset::Device as u8);
+
+/// The preset the setting asks for
+pub fn setting() -> Preset {
+    Preset::from_index(SETTING.load(Ordering::Relaxed))
+}
+
+/// The preset the mic and system captures follow
+pub fn active() -> Preset {
+    Preset::from_index(ACTIVE.load(Ordering::Relaxed))
+}
+
+/// Settle the preset for recording `note_id`, before its captures start: the one the note
+/// was first recorded with, else the setting's, which is then stored on the note
+pub fn begin(app: &AppHandle, note_id: &str) -> Preset {
+    let db = app.state::<Database>();
+    let stored = db
+        .get_note_recording_quality(note_id)
+        .ok()
+        .flatten()
+        .and_then(|value| Preset::parse(&value));
+    let preset = stored.unwrap_or_else(setting);
+    if stored.is_none() {
+        let mic_rate = preset.mic_format().map(|f| f.sample_rate);
+        let system_rate = preset.system_format().sample_rate;
+        let saved = db.set_note_recording_quality(note_id, preset.as_str(), mic_rate, system_rate);
+        if let Err(e) = saved {
+            tracing::warn!(
+                "Failed to store the recording quality of {}: {}",
+                note_id,
+                e
+            );
+        }
+    }
+    ACTIVE.store(preset as u8, Ordering::Relaxed);
+    preset
+}
+
+fn apply(value: Option<&str>) {
+    let preset = value.and_then(Preset::parse).unwrap_or_default();
+    SETTING.store(preset as u8, Ordering::Relaxed);
+}
+
+/// Load the setting and follow changes to it. Call once the database is managed.
+pub fn init(app: &AppHandle) {
+    let preset = app
+        .state::<Database>()
+        .get_setting(SETTING_PRESET)
+        .ok()
+        .flatten();
+    apply(preset.as_deref());
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload())
+            && key == SETTING_PRESET
+        {
+            apply(value.as_ref().and_then(Value::as_str));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_round_trip() {
+        for value in PRESETS {
+            let preset = Preset::parse(value).unwrap();
+            assert_eq!(preset.as_str(), *value);
+            assert_eq!(Preset::from_index(preset as u8), preset);
+        }
+        assert_eq!(Preset::parse("studio"), None);
+        assert_eq!(Preset::Voice.mic_format(), Some(VOICE));
+        assert_eq!(Preset::Device.system_format(), ARCHIVAL);
+    }
+}
@@ -0,0 +1,420 @@
+//! Chapters embedded in exported recordings, so standard players can jump between the parts
+//! of a meeting. Chapters start at the note's markers; a note without markers is split
+//! where the transcript pauses for `TOPIC_PAUSE_MS` or more, the usual sign of a change of
+//! subject, each chapter titled with the words that open it.
+//!
+//! WAV files get an ID3v2.3 tag with a table of contents and CHAP frames, in an `id3 `
+//! chunk (read by most players that read ID3 at all); FLAC files get the Vorbis comment
+//! chapter tags (`CHAPTER001=00:00:00.000`, `CHAPTER001NAME=...`).
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::audio::encoder::RecordingFormat;
+use crate::audio::AudioError;
+
+/// A gap in the transcript at least this long starts a new chapter
+const TOPIC_PAUSE_MS: u64 = 20_000;
+
+/// Chapters from transcript pauses are at least this long
+const MIN_CHAPTER_MS: u64 = 60_000;
+
+/// Words of the transcript a chapter is titled with
+const TITLE_WORDS: usize = 6;
+
+/// An ID3 table of contents holds at most this many chapters
+const MAX_CHAPTERS: usize = 255;
+
+/// FLAC metadata block type of Vorbis comments
+const FLAC_VORBIS_COMMENT: u8 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub title: String,
+}
+
+/// Give each start the end of the next one (or the end of the audio)
+fn close(starts: Vec<(u64, String)>, duration_ms: u64) -> Vec<Chapter> {
+    let ends: Vec<u64> = starts
+        .iter()
+        .skip(1)
+        .map(|(start, _)| *start)
+        .chain([duration_ms])
+        .collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .take(MAX_CHAPTERS)
+        .map(|((start_ms, title), end_ms)| Chapter {
+            start_ms,
+            end_ms,
+            title,
+        })
+        .collect()
+}
+
+/// A chapter at each marker (`position_ms`, label), and one from the start up to the
+/// first. Markers past the end of the audio are left out.
+pub fn from_markers(markers: &[(u64, Option<String>)], duration_ms: u64) -> Vec<Chapter> {
+    let mut markers: Vec<&(u64, Option<String>)> = markers
+        .iter()
+        .filter(|(position, _)| *position < duration_ms)
+        .collect();
+    markers.sort_by_key(|(position, _)| *position);
+    markers.dedup_by_key(|(position, _)| *position);
+
+    let mut starts = Vec::new();
+    if markers.first().is_none_or(|(position, _)| *position > 0) {
+        starts.push((0, "Start".to_string()));
+    }
+    for (i, (position, label)) in markers.into_iter().enumerate() {
+        let title = label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map_or_else(|| format!("Marker {}", i + 1), str::to_string);
+        starts.push((*position, title));
+    }
+    close(starts, duration_ms)
+}
+
+/// The first words of `text`, for a chapter title
+fn title_from(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut title = words[..words.len().min(TITLE_WORDS)].join(" ");
+    if words.len() > TITLE_WORDS {
+        title.push('…');
+    }
+    title
+}
+
+/// Chapters split at long pauses in a transcript of (start ms, end ms, text), in order.
+/// Empty when the transcript doesn't split.
+pub fn from_transcript(segments: &[(u64, u64, String)], duration_ms: u64) -> Vec<Chapter> {
+    let mut starts: Vec<(u64, String)> = Vec::new();
+    let mut last_end = 0;
+    for (start, end, text) in segments {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let opens = match starts.last() {
+            None => true,
+            Some((chapter_start, _)) => {
+                start.saturating_sub(last_end) >= TOPIC_PAUSE_MS
+                    && start - chapter_start >= MIN_CHAPTER_MS
+            }
+        };
+        if opens {
+            // The first chapter covers any silence before the first words
+            let at = if starts.is_empty() { 0 } else { *start };
+            starts.push((at, title_from(text)));
+        }
+        last_end = last_end.max(*end);
+    }
+    if starts.len() < 2 {
+        return Vec::new();
+    }
+    close(starts, duration_ms)
+}
+
+/// An ID3v2.3 frame
+fn id3_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(10 + body.len());
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// A TIT2 (title) frame, in UTF-16 with a byte order mark as ID3v2.3 has no UTF-8
+fn id3_title(title: &str) -> Vec<u8> {
+    let mut body = vec![1, 0xFF, 0xFE];
+    for unit in title.encode_utf16() {
+        body.extend_from_slice(&unit.to_le_bytes());
+    }
+    body.extend_from_slice(&[0, 0]);
+    id3_frame(b"TIT2", &body)
+}
+
+/// A 28-bit size in four bytes of seven bits, as ID3 tag headers store it
+fn synchsafe(size: usize) -> [u8; 4] {
+    let size = size as u32;
+    [
+        ((size >> 21) & 0x7F) as u8,
+        ((size >> 14) & 0x7F) as u8,
+        ((size >> 7) & 0x7F) as u8,
+        (size & 0x7F) as u8,
+    ]
+}
+
+/// An ID3v2.3 tag with a table of contents and a CHAP frame per chapter
+pub fn id3_tag(chapters: &[Chapter]) -> Vec<u8> {
+    let element_id = |i: usize| format!("chp{}\0", i).into_bytes();
+
+    let mut toc = b"toc\0".to_vec();
+    // Top level and ordered
+    toc.push(0x03);
+    toc.push(chapters.len() as u8);
+    for i in 0..chapters.len() {
+        toc.extend(element_id(i));
+    }
+    let mut frames = id3_frame(b"CTOC", &toc);
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let mut chap = element_id(i);
+        chap.extend_from_slice(&(chapter.start_ms as u32).to_be_bytes());
+        chap.extend_from_slice(&(chapter.end_ms as u32).to_be_bytes());
+        // Byte offsets: unused
+        chap.extend_from_slice(&[0xFF; 8]);
+        chap.extend(id3_title(&chapter.title));
+        frames.extend(id3_frame(b"CHAP", &chap));
+    }
+
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    tag.extend_from_slice(&synchsafe(frames.len()));
+    tag.extend(frames);
+    tag
+}
+
+/// "HH:MM:SS.mmm", as Vorbis comment chapters are timed
+fn chapter_time(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// A FLAC VORBIS_COMMENT block body with the chapter tags
+fn vorbis_comment(chapters: &[Chapter]) -> Vec<u8> {
+    let comments: Vec<String> = chapters
+        .iter()
+        .enumerate()
+        .flat_map(|(i, chapter)| {
+            [
+                format!("CHAPTER{:03}={}", i + 1, chapter_time(chapter.start_ms)),
+                format!("CHAPTER{:03}NAME={}", i + 1, chapter.title),
+            ]
+        })
+        .collect();
+
+    let vendor = b"Note67";
+    let mut body = Vec::new();
+    body.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    body.extend_from_slice(vendor);
+    body.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        body.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        body.extend_from_slice(comment.as_bytes());
+    }
+    body
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Append an `id3 ` chunk and bring the RIFF size up to date
+fn embed_wav(path: &Path, chapters: &[Chapter]) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)?;
+    if &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err(invalid("Not a WAV file"));
+    }
+
+    let tag = id3_tag(chapters);
+    let mut end = file.seek(SeekFrom::End(0))?;
+    // Chunks start on even offsets
+    if end % 2 == 1 {
+        file.write_all(&[0])?;
+        end += 1;
+    }
+    file.write_all(b"id3 ")?;
+    file.write_all(&(tag.len() as u32).to_le_bytes())?;
+    file.write_all(&tag)?;
+    end += 8 + tag.len() as u64;
+    if tag.len() % 2 == 1 {
+        file.write_all(&[0])?;
+        end += 1;
+    }
+
+    let riff_size = u32::try_from(end - 8).map_err(|_| invalid("WAV file too large"))?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&riff_size.to_le_bytes())?;
+    file.flush()
+}
+
+/// Rewrite the metadata blocks with the chapters' Vorbis comments in place of any there
+fn embed_flac(path: &Path, chapters: &[Chapter]) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"fLaC" {
+        return Err(invalid("Not a FLAC file"));
+    }
+    let mut blocks: Vec<(u8, Vec<u8>)> = Vec::new();
+    loop {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut body = vec![0u8; len];
+        reader.read_exact(&mut body)?;
+        let block_type = header[0] & 0x7F;
+        if block_type != FLAC_VORBIS_COMMENT {
+            blocks.push((block_type, body));
+        }
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    blocks.push((FLAC_VORBIS_COMMENT, vorbis_comment(chapters)));
+
+    let temp = path.with_extension("flac.tmp");
+    let written = (|| {
+        let mut out = BufWriter::new(File::create(&temp)?);
+        out.write_all(b"fLaC")?;
+        let last = blocks.len() - 1;
+        for (i, (block_type, body)) in blocks.iter().enumerate() {
+            let flag = if i == last { 0x80 } else { 0 };
+            out.write_all(&[flag | block_type])?;
+            out.write_all(&(body.len() as u32).to_be_bytes()[1..])?;
+            out.write_all(body)?;
+        }
+        io::copy(&mut reader, &mut out)?;
+        out.flush()
+    })();
+    match written {
+        Ok(()) => fs::rename(&temp, path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+/// Embed `chapters` in the recording at `path`, a WAV or FLAC file
+pub fn embed(path: &Path, chapters: &[Chapter]) -> Result<(), AudioError> {
+    if chapters.is_empty() {
+        return Ok(());
+    }
+    match RecordingFormat::of_path(path) {
+        Some(RecordingFormat::Wav) => embed_wav(path, chapters)?,
+        Some(RecordingFormat::Flac) => embed_flac(path, chapters)?,
+        None => return Err(AudioError::UnsupportedFormat),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(start_ms: u64, end_ms: u64, title: &str) -> Chapter {
+        Chapter {
+            start_ms,
+            end_ms,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_from_markers() {
+        let markers = [
+            (90_000, None),
+            (30_000, Some("Budget".to_string())),
+            (500_000, Some("Too late".to_string())),
+        ];
+        assert_eq!(
+            from_markers(&markers, 120_000),
+            vec![
+                chapter(0, 30_000, "Start"),
+                chapter(30_000, 90_000, "Budget"),
+                chapter(90_000, 120_000, "Marker 2"),
+            ]
+        );
+        assert_eq!(
+            from_markers(&[(0, Some("Intro".to_string()))], 10_000),
+            vec![chapter(0, 10_000, "Intro")]
+        );
+    }
+
+    #[test]
+    fn test_from_transcript() {
+        let segments = [
+            (2_000, 8_000, "Hello everyone".to_string()),
+            (50_000, 58_000, "Too soon after the start".to_string()),
+            (
+                90_000,
+                95_000,
+                "Now on to the quarterly numbers for this year".to_string(),
+            ),
+            (97_000, 99_000, "Right".to_string()),
+        ];
+        assert_eq!(
+            from_transcript(&segments, 100_000),
+            vec![
+                chapter(0, 90_000, "Hello everyone"),
+                chapter(90_000, 100_000, "Now on to the quarterly numbers…"),
+            ]
+        );
+        assert!(from_transcript(&segments[..2], 60_000).is_empty());
+    }
+
+    #[test]
+    fn test_id3_tag() {
+        let tag = id3_tag(&[chapter(0, 1000, "A"), chapter(1000, 2500, "B")]);
+        assert_eq!(&tag[..5], b"ID3\x03\x00");
+        let size = tag[6..10]
+            .iter()
+            .fold(0usize, |size, b| (size << 7) | *b as usize);
+        assert_eq!(size, tag.len() - 10);
+        assert_eq!(&tag[10..14], b"CTOC");
+        let chap = tag.windows(4).position(|w| w == b"CHAP").unwrap();
+        // Element id, then start and end times
+        assert_eq!(&tag[chap + 10..chap + 15], b"chp0\0");
+        assert_eq!(tag[chap + 15..chap + 19], 0u32.to_be_bytes());
+        assert_eq!(tag[chap + 19..chap + 23], 1000u32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_embed_in_wav_and_flac() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let chapters = [chapter(0, 500, "One"), chapter(500, 1000, "Two")];
+        for ext in ["wav", "flac"] {
+            let path = dir.path().join(format!("note.{}", ext));
+            let mut writer = crate::audio::encoder::AudioFileWriter::create(&path, spec).unwrap();
+            for i in 0..8000 {
+                writer.write_sample((i % 100) as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+
+            embed(&path, &chapters).unwrap();
+            let decoded = crate::audio::converter::decode(&path).unwrap();
+            assert_eq!(decoded.samples.len(), 8000);
+            let bytes = fs::read(&path).unwrap();
+            let marker: &[u8] = if ext == "wav" {
+                b"CHAP"
+            } else {
+                b"CHAPTER002NAME=Two"
+            };
+            assert!(bytes.windows(marker.len()).any(|w| w == marker));
+            if ext == "wav" {
+                let riff_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+                assert_eq!(riff_size as usize, bytes.len() - 8);
+            }
+        }
+    }
+}
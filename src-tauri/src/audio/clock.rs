@@ -0,0 +1,155 @@
+//! Capture clocks, for lining up the mic and system recordings when they are merged. The
+//! two come from devices with clocks of their own, so over a long recording one runs a
+//! little fast against the other and the merged file drifts out of sync; a capture that
+//! takes longer to start shifts everything as well. Each capture path notes when its audio
+//! arrives and how much of it was written, and `alignment` works out from that where the
+//! second file starts against the first and how fast it really ran.
+
+use std::time::{Duration, Instant};
+
+/// Rates measured over less than this are too rough to use
+const MIN_SPAN: Duration = Duration::from_secs(30);
+
+/// A measured rate further than this from nominal means the capture stalled or skipped,
+/// not drift, so the nominal rate is used
+const MAX_DRIFT: f64 = 0.005;
+
+/// Start offsets beyond this are not believed
+const MAX_OFFSET: Duration = Duration::from_secs(5);
+
+/// When a capture's audio arrived, and how much was written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptureClock {
+    /// When the first written frame was captured
+    first: Option<Instant>,
+    /// When the latest buffer arrived
+    last: Option<Instant>,
+    frames: u64,
+    /// The rate the file is written at
+    rate: u32,
+}
+
+impl CaptureClock {
+    pub const fn new() -> Self {
+        Self {
+            first: None,
+            last: None,
+            frames: 0,
+            rate: 0,
+        }
+    }
+
+    /// Note that a buffer of `frames` frames, at nominal `rate`, was just written
+    pub fn record(&mut self, frames: usize, rate: u32) {
+        self.record_at(Instant::now(), frames, rate);
+    }
+
+    fn record_at(&mut self, now: Instant, frames: usize, rate: u32) {
+        if frames == 0 || rate == 0 {
+            return;
+        }
+        if self.first.is_none() {
+            // A buffer arrives once it is full, so its first frame is a buffer older
+            let length = Duration::from_secs_f64(frames as f64 / rate as f64);
+            self.first = Some(now.checked_sub(length).unwrap_or(now));
+        }
+        self.last = Some(now);
+        self.frames += frames as u64;
+        self.rate = rate;
+    }
+
+    /// Frames a second the capture really delivered, or the nominal rate when that can't
+    /// be measured well
+    fn rate(&self) -> f64 {
+        let nominal = self.rate as f64;
+        let (Some(first), Some(last)) = (self.first, self.last) else {
+            return nominal;
+        };
+        let span = last.duration_since(first);
+        if span < MIN_SPAN {
+            return nominal;
+        }
+        let measured = self.frames as f64 / span.as_secs_f64();
+        if (measured / nominal - 1.0).abs() > MAX_DRIFT {
+            return nominal;
+        }
+        measured
+    }
+}
+
+/// Where the second file's audio falls against the first's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alignment {
+    /// Frames of the second file per frame of the first
+    pub step: f64,
+    /// Frame of the second file at the first file's first frame; negative when the second
+    /// started later
+    pub start: f64,
+}
+
+/// How the capture timed by `b` lines up with the one timed by `a`; None unless both
+/// wrote audio
+pub fn alignment(a: &CaptureClock, b: &CaptureClock) -> Option<Alignment> {
+    let (first_a, first_b) = (a.first?, b.first?);
+    let rate_b = b.rate();
+    let offset = if first_a >= first_b {
+        first_a.duration_since(first_b).as_secs_f64()
+    } else {
+        -first_b.duration_since(first_a).as_secs_f64()
+    };
+    let offset = if offset.abs() > MAX_OFFSET.as_secs_f64() {
+        0.0
+    } else {
+        offset
+    };
+    Some(Alignment {
+        step: rate_b / a.rate(),
+        start: offset * rate_b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A capture of `secs` seconds in 10 ms buffers that really runs at `rate`, written
+    /// as `nominal`
+    fn capture(start: Instant, secs: u64, rate: f64, nominal: u32) -> CaptureClock {
+        let mut clock = CaptureClock::new();
+        let buffer = (rate / 100.0) as usize;
+        let buffers = secs * 100;
+        for i in 1..=buffers {
+            let at = start + Duration::from_secs_f64(i as f64 * buffer as f64 / rate);
+            clock.record_at(at, buffer, nominal);
+        }
+        clock
+    }
+
+    #[test]
+    fn test_alignment() {
+        let start = Instant::now();
+        // The mic runs 100 ppm fast; system audio starts 200 ms later
+        let mic = capture(start, 600, 48004.8, 48000);
+        let system = capture(start + Duration::from_millis(200), 600, 48000.0, 48000);
+        let alignment = alignment(&mic, &system).unwrap();
+        assert!(
+            (alignment.step - 48000.0 / 48004.8).abs() < 1e-6,
+            "{alignment:?}"
+        );
+        assert!((alignment.start + 9600.0).abs() < 5.0, "{alignment:?}");
+    }
+
+    #[test]
+    fn test_short_or_stalled_captures_use_nominal_rates() {
+        let start = Instant::now();
+        let mic = capture(start, 10, 48004.8, 48000);
+        let system = capture(start, 10, 44100.0, 44100);
+        assert_eq!(alignment(&mic, &system).unwrap().step, 44100.0 / 48000.0);
+
+        // Capture that delivered half what it should have
+        let mic = capture(start, 60, 48000.0, 48000);
+        let system = capture(start, 60, 24000.0, 48000);
+        assert_eq!(alignment(&mic, &system).unwrap().step, 1.0);
+        assert!(alignment(&mic, &CaptureClock::new()).is_none());
+    }
+}
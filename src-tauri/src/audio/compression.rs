@@ -0,0 +1,291 @@
+//! Shrinking playback files once a note has been transcribed. With the setting on, a
+//! finished `<note id>.wav` playback file is transcoded to FLAC by a low-priority conversion
+//! job when transcription completes; the database is pointed at the new file and the WAV
+//! is deleted. FLAC is lossless, so this only gives back space (about half). Mic and system
+//! files are left as they are, since retranscription reads them.
+//!
+//! Jobs in flight are kept for `get_compression_progress`, besides the usual "job-updated"
+//! events.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use hound::{SampleFormat, WavReader};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::flac::FlacWriter;
+use crate::audio::{converter, AudioError};
+use crate::commands::AudioState;
+use crate::db::Database;
+use crate::jobs::{Job, JobKind, JobManager, Priority, CANCELLED};
+use crate::storage::{self, NoteFolder};
+
+/// Compress playback files after transcription (off by default)
+pub const SETTING_ENABLED: &str = "compress_recordings";
+
+/// Samples transcoded between progress reports and cancellation checks; 10 seconds of
+/// 48kHz stereo
+const CHUNK_SAMPLES: usize = 48000 * 2 * 10;
+
+static PROGRESS: Mutex<BTreeMap<String, CompressionProgress>> = Mutex::new(BTreeMap::new());
+
+/// A playback file waiting for or being compressed
+#[derive(Debug, Clone, Serialize)]
+pub struct CompressionProgress {
+    pub note_id: String,
+    /// The file being compressed
+    pub path: String,
+    /// From 0.0 to 1.0
+    pub progress: f32,
+    /// False while queued behind other conversions
+    pub running: bool,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Compressions queued or running, by note
+pub fn progress() -> Vec<CompressionProgress> {
+    lock(&PROGRESS).values().cloned().collect()
+}
+
+fn set_progress(note_id: &str, progress: f32) {
+    if let Some(entry) = lock(&PROGRESS).get_mut(note_id) {
+        entry.progress = progress;
+        entry.running = true;
+    }
+}
+
+/// Compress the note's playback file in the background, if the setting is on and the
+/// note has a WAV playback file
+pub fn schedule(app: &AppHandle, note_id: &str) {
+    let enabled = app
+        .state::<Database>()
+        .get_setting(SETTING_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        return;
+    }
+    let Ok(dir) = storage::folder(app, note_id, NoteFolder::Recordings) else {
+        return;
+    };
+    let source = dir.join(format!("{}.wav", note_id));
+    if !source.is_file() {
+        return;
+    }
+    {
+        let mut progress = lock(&PROGRESS);
+        if progress.contains_key(note_id) {
+            return;
+        }
+        progress.insert(
+            note_id.to_string(),
+            CompressionProgress {
+                note_id: note_id.to_string(),
+                path: source.to_string_lossy().to_string(),
+                progress: 0.0,
+                running: false,
+            },
+        );
+    }
+
+    let app = app.clone();
+    let note_id = note_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let result = run(&app, &note_id, &source).await;
+        lock(&PROGRESS).remove(&note_id);
+        match result {
+            Ok(target) => {
+                tracing::info!("Compressed {} to {}", source.display(), target.display());
+                let _ = app.emit("note-updated", &note_id);
+            }
+            Err(e) if e == CANCELLED => {}
+            Err(e) => tracing::warn!("Failed to compress {}: {}", source.display(), e),
+        }
+    });
+}
+
+async fn run(app: &AppHandle, note_id: &str, source: &Path) -> Result<PathBuf, String> {
+    let job = app
+        .state::<JobManager>()
+        .enqueue(
+            JobKind::Conversion,
+            Priority::Low,
+            "Compress recording",
+            Some(note_id),
+        )
+        .await?;
+    let result = compress(app, note_id, source, &job).await;
+    job.finish(&result);
+    result
+}
+
+async fn compress(
+    app: &AppHandle,
+    note_id: &str,
+    source: &Path,
+    job: &Job,
+) -> Result<PathBuf, String> {
+    // Stopping a new recording in this note writes the playback file again
+    let recording = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    if recording.as_deref() == Some(note_id) {
+        return Err("The note is being recorded".to_string());
+    }
+
+    let target = source.with_extension("flac");
+    // Left behind by a crash, a `.tmp` file is deleted by recovery on the next launch
+    let temp = source.with_extension("flac.tmp");
+    let _cleanup = scopeguard::guard(temp.clone(), |path| {
+        let _ = fs::remove_file(path);
+    });
+
+    let mut transcode = Transcode::open(source, &temp).map_err(|e| e.to_string())?;
+    loop {
+        job.check_cancelled()?;
+        let (returned, more) = tokio::task::spawn_blocking(move || {
+            let more = transcode.step();
+            (transcode, more)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        transcode = returned;
+        let more = more.map_err(|e| e.to_string())?;
+
+        let fraction = transcode.progress();
+        job.progress(fraction);
+        set_progress(note_id, fraction);
+        if !more {
+            break;
+        }
+    }
+    transcode.finish().map_err(|e| e.to_string())?;
+
+    // Only give up the WAV for a file of the same length
+    let expected = converter::get_audio_duration_ms(source).map_err(|e| e.to_string())?;
+    let written = converter::get_audio_duration_ms(&temp).map_err(|e| e.to_string())?;
+    if written != expected {
+        return Err(format!(
+            "Compressed file is {} ms long, expected {} ms",
+            written, expected
+        ));
+    }
+
+    fs::rename(&temp, &target).map_err(|e| e.to_string())?;
+    let source_str = source.to_string_lossy();
+    let target_str = target.to_string_lossy();
+    if let Err(e) = app
+        .state::<Database>()
+        .relocate_file(&source_str, &target_str)
+    {
+        let _ = fs::remove_file(&target);
+        return Err(e.to_string());
+    }
+    if let Err(e) = fs::remove_file(source) {
+        tracing::warn!("Failed to delete {}: {}", source.display(), e);
+    }
+    Ok(target)
+}
+
+/// Copies a 16-bit WAV into a FLAC file a chunk at a time
+struct Transcode {
+    reader: WavReader<BufReader<File>>,
+    writer: FlacWriter<BufWriter<File>>,
+    /// Samples in the WAV
+    total: u64,
+    done: u64,
+}
+
+impl Transcode {
+    fn open(source: &Path, target: &Path) -> Result<Self, AudioError> {
+        let reader = WavReader::open(source)?;
+        let spec = reader.spec();
+        if spec.sample_format != SampleFormat::Int || spec.bits_per_sample != 16 {
+            return Err(AudioError::UnsupportedFormat);
+        }
+        let file = BufWriter::new(File::create(target)?);
+        let writer = FlacWriter::new(file, spec.sample_rate, spec.channels)?;
+        Ok(Self {
+            total: reader.len() as u64,
+            reader,
+            writer,
+            done: 0,
+        })
+    }
+
+    /// Copy the next chunk. Returns false once the whole WAV is copied.
+    fn step(&mut self) -> Result<bool, AudioError> {
+        for sample in self.reader.samples::<i16>().take(CHUNK_SAMPLES) {
+            self.writer.write_sample(sample?)?;
+            self.done += 1;
+        }
+        Ok(self.done < self.total)
+    }
+
+    fn progress(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+
+    fn finish(self) -> Result<(), AudioError> {
+        Ok(self.writer.finalize()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcode_is_lossless() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("n.wav");
+        let target = dir.path().join("n.flac");
+
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&source, spec).unwrap();
+        let samples: Vec<i16> = (0..CHUNK_SAMPLES + 1000)
+            .map(|i| ((i as f32 * 0.003).sin() * 8000.0) as i16)
+            .collect();
+        for sample in &samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let mut transcode = Transcode::open(&source, &target).unwrap();
+        assert!(transcode.step().unwrap());
+        assert!(!transcode.step().unwrap());
+        assert_eq!(transcode.progress(), 1.0);
+        transcode.finish().unwrap();
+
+        let decoded = converter::decode(&target).unwrap();
+        assert_eq!(decoded.channels, 2);
+        assert_eq!(decoded.samples.len(), samples.len());
+        let restored: Vec<i16> = decoded
+            .samples
+            .iter()
+            .map(|s| (s * 32768.0).round() as i16)
+            .collect();
+        assert_eq!(restored, samples);
+    }
+}
@@ -1,17 +1,19 @@
 //! Audio format conversion module for uploaded files.
 //!
-//! Converts various audio formats to 16-bit mono WAV at 16kHz for Whisper transcription.
+//! Converts various audio formats to 16-bit mono WAV at 16kHz for Whisper transcription,
+//! and decodes compressed recordings for mixing and transcription.
 //! Uses Symphonia for decoding (pure Rust, no external dependencies).
 
 use std::fs::File;
 use std::path::Path;
 
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
 
 use super::AudioError;
 
@@ -28,120 +30,25 @@ pub fn is_supported_format(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// Audio decoded into memory
+pub struct DecodedAudio {
+    /// Interleaved samples in [-1.0, 1.0]
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
 /// Convert an audio file to 16-bit mono WAV at 16kHz for Whisper.
 ///
 /// Uses Symphonia for decoding and hound for WAV output.
 /// Supports: MP3, M4A/AAC, ALAC, FLAC, OGG/Vorbis, WAV, WebM, MKV
 pub fn convert_to_wav(input_path: &Path, output_path: &Path) -> Result<(), AudioError> {
-    // Open the input file
-    let file = File::open(input_path).map_err(AudioError::IoError)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
-    // Create a hint to help format detection
-    let mut hint = Hint::new();
-    if let Some(ext) = input_path.extension().and_then(|e| e.to_str()) {
-        hint.with_extension(ext);
-    }
-
-    // Probe the format
-    let format_opts = FormatOptions::default();
-    let metadata_opts = MetadataOptions::default();
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &format_opts, &metadata_opts)
-        .map_err(|e| {
-            AudioError::IoError(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Unsupported audio format: {}", e),
-            ))
-        })?;
-
-    let mut format = probed.format;
-
-    // Find the first audio track
-    let track = format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .ok_or_else(|| {
-            AudioError::IoError(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "No audio track found",
-            ))
-        })?;
-
-    let track_id = track.id;
-    let codec_params = track.codec_params.clone();
-
-    // Get source sample rate for resampling calculation
-    let source_sample_rate = codec_params.sample_rate.unwrap_or(44100);
-
-    // Create decoder
-    let decoder_opts = DecoderOptions::default();
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&codec_params, &decoder_opts)
-        .map_err(|e| {
-            AudioError::IoError(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Failed to create decoder: {}", e),
-            ))
-        })?;
-
-    // Collect all samples
-    let mut all_samples: Vec<f32> = Vec::new();
-    let mut sample_buf: Option<SampleBuffer<f32>> = None;
-
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-            {
-                break; // End of stream
-            }
-            Err(e) => {
-                // Log but continue on decode errors
-                eprintln!("Error reading packet: {}", e);
-                continue;
-            }
-        };
-
-        // Skip packets from other tracks
-        if packet.track_id() != track_id {
-            continue;
-        }
-
-        // Decode the packet
-        let decoded = match decoder.decode(&packet) {
-            Ok(decoded) => decoded,
-            Err(e) => {
-                eprintln!("Error decoding packet: {}", e);
-                continue;
-            }
-        };
-
-        // Initialize sample buffer on first packet
-        if sample_buf.is_none() {
-            let spec = *decoded.spec();
-            let capacity = decoded.capacity() as u64;
-            sample_buf = Some(SampleBuffer::new(capacity, spec));
-        }
-
-        // Copy samples to buffer
-        if let Some(buf) = &mut sample_buf {
-            buf.copy_interleaved_ref(decoded);
-            all_samples.extend_from_slice(buf.samples());
-        }
-    }
-
-    if all_samples.is_empty() {
-        return Err(AudioError::IoError(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "No audio samples decoded",
-        )));
-    }
-
-    // Get channel count from decoder
-    let channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
+    let DecodedAudio {
+        samples: all_samples,
+        sample_rate: source_sample_rate,
+        channels,
+    } = decode(input_path)?;
+    let channels = channels as usize;
 
     // Convert to mono if stereo (average channels)
     let mono_samples: Vec<f32> = if channels > 1 {
@@ -178,8 +85,216 @@ pub fn convert_to_wav(input_path: &Path, output_path: &Path) -> Result<(), Audio
     Ok(())
 }
 
+/// Decode a whole audio file in any supported format, keeping its rate and channels
+pub fn decode(input_path: &Path) -> Result<DecodedAudio, AudioError> {
+    let mut samples = Vec::new();
+    let (sample_rate, channels) = decode_with(input_path, |decoded, _| {
+        samples.extend_from_slice(decoded);
+    })?;
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Decode an audio file a packet at a time, handing each packet's interleaved samples and
+/// channel count to `on_samples`, so long files needn't fit in memory. Returns the rate
+/// and channels.
+pub fn decode_with(
+    input_path: &Path,
+    mut on_samples: impl FnMut(&[f32], u16),
+) -> Result<(u32, u16), AudioError> {
+    let mut decoder = StreamDecoder::open(input_path)?;
+    let channels = decoder.channels();
+
+    let mut decoded_any = false;
+    while let Some(samples) = decoder.next_samples() {
+        decoded_any = true;
+        on_samples(samples, channels);
+    }
+
+    if !decoded_any {
+        return Err(AudioError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "No audio samples decoded",
+        )));
+    }
+
+    Ok((decoder.sample_rate(), channels))
+}
+
+/// An audio file open for decoding a packet at a time, which can seek
+pub struct StreamDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u16,
+    /// Frames in the file, when the container says
+    frames: Option<u64>,
+    sample_buf: Option<SampleBuffer<f32>>,
+    /// Frames still to drop after a seek landed short of its target
+    skip: u64,
+    /// Where the current packet's samples start in `sample_buf`
+    offset: usize,
+}
+
+impl StreamDecoder {
+    pub fn open(input_path: &Path) -> Result<Self, AudioError> {
+        // Open the input file
+        let file = File::open(input_path).map_err(AudioError::IoError)?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+        // Create a hint to help format detection
+        let mut hint = Hint::new();
+        if let Some(ext) = input_path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        // Probe the format
+        let format_opts = FormatOptions::default();
+        let metadata_opts = MetadataOptions::default();
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &format_opts, &metadata_opts)
+            .map_err(|e| {
+                AudioError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unsupported audio format: {}", e),
+                ))
+            })?;
+
+        let format = probed.format;
+
+        // Find the first audio track
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+            .ok_or_else(|| {
+                AudioError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "No audio track found",
+                ))
+            })?;
+
+        let track_id = track.id;
+        let codec_params = track.codec_params.clone();
+
+        // Create decoder
+        let decoder_opts = DecoderOptions::default();
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &decoder_opts)
+            .map_err(|e| {
+                AudioError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Failed to create decoder: {}", e),
+                ))
+            })?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate: codec_params.sample_rate.unwrap_or(44100),
+            channels: codec_params.channels.map(|c| c.count()).unwrap_or(2) as u16,
+            frames: codec_params.n_frames,
+            sample_buf: None,
+            skip: 0,
+            offset: 0,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Length of the file, when the container says
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.frames
+            .map(|frames| frames * 1000 / self.sample_rate.max(1) as u64)
+    }
+
+    /// The next packet's interleaved samples; None at the end of the file
+    pub fn next_samples(&mut self) -> Option<&[f32]> {
+        let channels = self.channels.max(1) as usize;
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return None; // End of stream
+                }
+                Err(e) => {
+                    // Log but continue on decode errors
+                    tracing::warn!("Error reading packet: {}", e);
+                    continue;
+                }
+            };
+
+            // Skip packets from other tracks
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            // Decode the packet
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    tracing::warn!("Error decoding packet: {}", e);
+                    continue;
+                }
+            };
+
+            // Initialize sample buffer on first packet
+            let buf = self.sample_buf.get_or_insert_with(|| {
+                SampleBuffer::new(decoded.capacity() as u64, *decoded.spec())
+            });
+            buf.copy_interleaved_ref(decoded);
+
+            let frames = (buf.samples().len() / channels) as u64;
+            let skipped = self.skip.min(frames);
+            self.skip -= skipped;
+            if skipped < frames {
+                self.offset = skipped as usize * channels;
+                break;
+            }
+        }
+        self.sample_buf
+            .as_ref()
+            .map(|buf| &buf.samples()[self.offset..])
+    }
+
+    /// Move to `ms` into the file; the next samples start there
+    pub fn seek(&mut self, ms: u64) -> Result<(), AudioError> {
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::from(ms as f64 / 1000.0),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .map_err(|e| {
+                AudioError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Failed to seek: {}", e),
+                ))
+            })?;
+        self.decoder.reset();
+        self.skip = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        Ok(())
+    }
+}
+
 /// Linear interpolation resampling
-fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+pub(crate) fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     if from_rate == to_rate {
         return samples.to_vec();
     }
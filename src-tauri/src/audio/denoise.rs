@@ -0,0 +1,194 @@
+//! Noise suppression for the microphone. RNNoise (through nnnoiseless, its Rust port)
+//! takes keyboard, fan and other background noise out of the mic audio before it reaches
+//! the file and live transcription. Off by default; a note can override the setting either
+//! way, and changes apply to a recording in progress within a tenth of a second.
+//!
+//! RNNoise works on 10 ms frames at 48 kHz, so the audio is delayed by up to one frame,
+//! and microphones below 44.1 kHz are recorded as they are.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use nnnoiseless::DenoiseState;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::db::Database;
+
+/// Suppress noise in new and ongoing recordings (off by default)
+pub const SETTING_ENABLED: &str = "noise_suppression_enabled";
+
+/// Lowest input rate RNNoise handles well
+const MIN_SAMPLE_RATE: u32 = 44100;
+
+/// RNNoise expects samples in the 16-bit range
+const SCALE: f32 = 32768.0;
+
+const FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Each note's choice, where it has one
+static OVERRIDES: Mutex<Option<HashMap<String, bool>>> = Mutex::new(None);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The note's own choice; None when it follows the setting
+pub fn note_override(note_id: &str) -> Option<bool> {
+    lock(&OVERRIDES).as_ref()?.get(note_id).copied()
+}
+
+/// Record a note's choice (already saved to the database)
+pub fn set_override(note_id: &str, enabled: Option<bool>) {
+    let mut overrides = lock(&OVERRIDES);
+    let overrides = overrides.get_or_insert_with(HashMap::new);
+    match enabled {
+        Some(enabled) => overrides.insert(note_id.to_string(), enabled),
+        None => overrides.remove(note_id),
+    };
+}
+
+/// Whether a recording in this note gets noise suppression
+pub fn enabled_for(note_id: Option<&str>) -> bool {
+    note_id.and_then(note_override).unwrap_or_else(is_enabled)
+}
+
+struct Channel {
+    state: Box<DenoiseState<'static>>,
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+/// Denoises interleaved audio, one RNNoise state per channel
+pub struct NoiseSuppressor {
+    channels: Vec<Channel>,
+}
+
+impl NoiseSuppressor {
+    /// None when the rate is too low for RNNoise
+    pub fn new(sample_rate: u32, channels: u16) -> Option<Self> {
+        if sample_rate < MIN_SAMPLE_RATE || channels == 0 {
+            return None;
+        }
+        let channels = (0..channels)
+            .map(|_| Channel {
+                state: DenoiseState::new(),
+                input: Vec::with_capacity(FRAME_SIZE),
+                output: vec![0.0; FRAME_SIZE],
+            })
+            .collect();
+        Some(Self { channels })
+    }
+
+    /// Denoise `data` into `out`. Audio comes out a frame at a time, so `out` gets nothing
+    /// until a frame is complete.
+    pub fn process(&mut self, data: &[f32], out: &mut Vec<f32>) {
+        for frame in data.chunks_exact(self.channels.len()) {
+            for (channel, sample) in self.channels.iter_mut().zip(frame) {
+                channel.input.push(sample * SCALE);
+            }
+            if self.channels[0].input.len() < FRAME_SIZE {
+                continue;
+            }
+            for channel in &mut self.channels {
+                channel
+                    .state
+                    .process_frame(&mut channel.output, &channel.input);
+                channel.input.clear();
+            }
+            for i in 0..FRAME_SIZE {
+                out.extend(
+                    self.channels
+                        .iter()
+                        .map(|c| (c.output[i] / SCALE).clamp(-1.0, 1.0)),
+                );
+            }
+        }
+    }
+
+    /// Hand back audio still waiting for a full frame, unprocessed, so turning
+    /// suppression off loses nothing
+    pub fn flush(&mut self, out: &mut Vec<f32>) {
+        let pending = self.channels[0].input.len();
+        for i in 0..pending {
+            out.extend(self.channels.iter().map(|c| c.input[i] / SCALE));
+        }
+        for channel in &mut self.channels {
+            channel.input.clear();
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.channels[0].input.is_empty()
+    }
+}
+
+/// Load the setting and the notes' choices, and follow changes to the setting. Call once
+/// the database is managed.
+pub fn init(app: &AppHandle) {
+    let db = app.state::<Database>();
+    let enabled = db
+        .get_setting(SETTING_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    ENABLED.store(enabled, Ordering::Relaxed);
+    match db.get_noise_suppression_overrides() {
+        Ok(overrides) => *lock(&OVERRIDES) = Some(overrides.into_iter().collect()),
+        Err(e) => tracing::warn!("Failed to load noise suppression choices: {}", e),
+    }
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload())
+            && key == SETTING_ENABLED
+        {
+            ENABLED.store(value == Some(Value::Bool(true)), Ordering::Relaxed);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_override_wins() {
+        set_override("denoise-test-on", Some(true));
+        set_override("denoise-test-off", Some(false));
+        assert!(enabled_for(Some("denoise-test-on")));
+        assert!(!enabled_for(Some("denoise-test-off")));
+        assert_eq!(enabled_for(Some("denoise-test-none")), is_enabled());
+        set_override("denoise-test-on", None);
+        assert_eq!(note_override("denoise-test-on"), None);
+    }
+
+    #[test]
+    fn test_no_samples_lost() {
+        let mut suppressor = NoiseSuppressor::new(48000, 2).unwrap();
+        let input: Vec<f32> = (0..2 * (FRAME_SIZE * 3 + 100))
+            .map(|i| ((i / 2) as f32 * 0.05).sin() * 0.1)
+            .collect();
+        let mut out = Vec::new();
+        for chunk in input.chunks(2 * 441) {
+            suppressor.process(chunk, &mut out);
+        }
+        assert_eq!(out.len(), 2 * FRAME_SIZE * 3);
+        assert!(suppressor.has_pending());
+        suppressor.flush(&mut out);
+        assert_eq!(out.len(), input.len());
+        assert!(!suppressor.has_pending());
+        assert!(NoiseSuppressor::new(16000, 1).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! The microphone to record from and, on Windows, the output device whose sound system
+//! audio recordings capture (on macOS, the virtual loopback device used when
+//! ScreenCaptureKit can't be). Each choice is stored in settings (the microphone and
+//! virtual device by name, the output by endpoint id); empty or unset means the system
+//! default. A chosen device
+//! that isn't connected when recording starts falls back to the default, so an unplugged
+//! headset never blocks a recording. A microphone lost mid-recording is replaced the same
+//! way, reported as "recording-device-changed".
+
+use std::sync::{Mutex, OnceLock};
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+
+use crate::audio::AudioError;
+use crate::db::Database;
+
+/// Name of the input device to record from; empty for the system default
+pub const SETTING_INPUT_DEVICE: &str = "input_device";
+
+/// Endpoint id of the output device loopback capture records (Windows); empty for the
+/// system default. Ignored while an excluded app is left out of the capture, as process
+/// loopback always records the whole mix. On macOS, the name of the virtual loopback
+/// device to fall back on; empty for the first installed.
+pub const SETTING_LOOPBACK_DEVICE: &str = "loopback_device";
+
+static INPUT_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+static LOOPBACK_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Payload of "recording-device-changed"
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceChangedEvent {
+    /// The microphone that went away
+    pub previous: String,
+    /// The one recording continues on
+    pub device: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDevice {
+    pub name: String,
+    pub is_default: bool,
+    /// The device recordings use
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderDevice {
+    /// Endpoint id, stable across renames
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    /// The device loopback capture records
+    pub selected: bool,
+}
+
+fn set(slot: &Mutex<Option<String>>, value: Option<&str>) {
+    *slot.lock().unwrap_or_else(|e| e.into_inner()) = value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+}
+
+fn get(slot: &Mutex<Option<String>>) -> Option<String> {
+    slot.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The chosen microphone's name; None for the system default
+pub fn selected_input_device() -> Option<String> {
+    get(&INPUT_DEVICE)
+}
+
+/// The chosen output device's endpoint id; None for the system default
+#[allow(dead_code)] // Used by the Windows and macOS virtual device captures
+pub fn selected_loopback_device() -> Option<String> {
+    get(&LOOPBACK_DEVICE)
+}
+
+/// Connected input devices, marking the default and the one recordings use
+pub fn list_input_devices() -> Result<Vec<InputDevice>, AudioError> {
+    let host = cpal::default_host();
+    let default = host.default_input_device().and_then(|d| d.name().ok());
+    let names: Vec<String> = host
+        .input_devices()?
+        .filter_map(|d| d.name().ok())
+        .collect();
+    // The default is used when nothing is chosen or the choice isn't connected
+    let selected = selected_input_device()
+        .filter(|name| names.contains(name))
+        .or_else(|| default.clone());
+
+    Ok(names
+        .into_iter()
+        .map(|name| InputDevice {
+            is_default: default.as_ref() == Some(&name),
+            selected: selected.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+/// The device to record from: the chosen one if connected, otherwise the default
+pub fn input_device() -> Result<cpal::Device, AudioError> {
+    let host = cpal::default_host();
+    if let Some(name) = selected_input_device() {
+        let found = host
+            .input_devices()?
+            .find(|d| d.name().is_ok_and(|n| n == name));
+        match found {
+            Some(device) => return Ok(device),
+            None => tracing::warn!(
+                "Input device \"{}\" isn't connected; using the default",
+                name
+            ),
+        }
+    }
+    host.default_input_device().ok_or(AudioError::NoInputDevice)
+}
+
+/// Tell the app that recording moved to another microphone
+pub(crate) fn report_input_change(previous: &str, device: &str) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit(
+            "recording-device-changed",
+            DeviceChangedEvent {
+                previous: previous.to_string(),
+                device: device.to_string(),
+            },
+        );
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use super::windows::list_render_devices;
+
+#[cfg(target_os = "macos")]
+pub use super::virtual_device::list_render_devices;
+
+/// Output devices, marking the default and the one loopback capture records
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn list_render_devices() -> Result<Vec<RenderDevice>, AudioError> {
+    Err(AudioError::UnsupportedPlatform)
+}
+
+/// Load the choices and follow changes to them. Call once the database is managed.
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+    let db = app.state::<Database>();
+    for (key, slot) in [
+        (SETTING_INPUT_DEVICE, &INPUT_DEVICE),
+        (SETTING_LOOPBACK_DEVICE, &LOOPBACK_DEVICE),
+    ] {
+        set(slot, db.get_setting(key).ok().flatten().as_deref());
+    }
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        let Ok(Changed { key, value }) = serde_json::from_str(event.payload()) else {
+            return;
+        };
+        let slot = match key.as_str() {
+            SETTING_INPUT_DEVICE => &INPUT_DEVICE,
+            SETTING_LOOPBACK_DEVICE => &LOOPBACK_DEVICE,
+            _ => return,
+        };
+        set(slot, value.as_ref().and_then(Value::as_str));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_means_default() {
+        let slot = Mutex::new(None);
+        set(&slot, Some(" USB Mic "));
+        assert_eq!(get(&slot).as_deref(), Some("USB Mic"));
+        set(&slot, Some(""));
+        assert_eq!(get(&slot), None);
+        set(&slot, None);
+        assert_eq!(get(&slot), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Cutting recordings: keeping a stretch of a file, or splitting it in two. Files are
+//! decoded and written back as 16-bit in their own format (WAV or FLAC, by extension). A
+//! cut is written to a `.tmp` file beside the original, so a failure leaves the original
+//! as it was; the caller renames it into place once every file of an edit is ready.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use cpal::Sample;
+use hound::WavSpec;
+
+use crate::audio::converter::StreamDecoder;
+use crate::audio::encoder::AudioFileWriter;
+use crate::audio::AudioError;
+
+/// Write the `from_ms..to_ms` stretch of `source` (to the end with None) into `file`, in
+/// the format `format_path` names. Returns the milliseconds written.
+fn write_range(
+    source: &Path,
+    file: File,
+    format_path: &Path,
+    from_ms: u64,
+    to_ms: Option<u64>,
+) -> Result<u64, AudioError> {
+    let mut decoder = StreamDecoder::open(source)?;
+    let rate = decoder.sample_rate().max(1);
+    let channels = decoder.channels().max(1);
+    let spec = WavSpec {
+        channels,
+        sample_rate: rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = AudioFileWriter::new(format_path, BufWriter::new(file), spec)?;
+
+    let frame = |ms: u64| ms * rate as u64 / 1000;
+    let (first, last) = (frame(from_ms), to_ms.map(frame));
+    let channels = channels as usize;
+    let mut position = 0_u64;
+    let mut written = 0_u64;
+    while let Some(samples) = decoder.next_samples() {
+        let frames = (samples.len() / channels) as u64;
+        let start = first.saturating_sub(position).min(frames);
+        let end = last.map_or(frames, |last| last.saturating_sub(position).min(frames));
+        for &sample in &samples[start as usize * channels..end.max(start) as usize * channels] {
+            writer.write_sample(i16::from_sample(sample))?;
+        }
+        written += end.saturating_sub(start);
+        position += frames;
+        if last.is_some_and(|last| position >= last) {
+            break;
+        }
+    }
+    writer.finalize()?;
+    Ok(written * 1000 / rate as u64)
+}
+
+/// Where the cut of `path` is written before it replaces the file
+pub fn temp_path(path: &Path) -> PathBuf {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+    path.with_extension(format!("{}.tmp", ext))
+}
+
+/// Write the `from_ms..to_ms` stretch of `path` to its `temp_path`, to be renamed over it.
+/// Returns the milliseconds kept.
+pub fn cut(path: &Path, from_ms: u64, to_ms: Option<u64>) -> Result<u64, AudioError> {
+    let temp = temp_path(path);
+    let result = write_range(path, File::create(&temp)?, path, from_ms, to_ms);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copy everything of `source` from `from_ms` on into a new file at `target`. Returns the
+/// milliseconds copied.
+pub fn copy_from(source: &Path, target: &Path, from_ms: u64) -> Result<u64, AudioError> {
+    let result = write_range(source, File::create(target)?, target, from_ms, None);
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_ramp(path: &Path, frames: usize) {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..frames {
+            writer.write_sample((i % 1000) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read(path: &Path) -> Vec<i16> {
+        hound::WavReader::open(path)
+            .unwrap()
+            .samples::<i16>()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn test_cut_and_copy_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ramp.wav");
+        write_ramp(&path, 8000);
+
+        assert_eq!(cut(&path, 250, Some(750)).unwrap(), 500);
+        let kept = read(&temp_path(&path));
+        assert_eq!(kept.len(), 4000);
+        assert_eq!(kept[0], 0);
+        assert_eq!(kept[1], 1);
+
+        let tail = dir.path().join("tail.wav");
+        assert_eq!(copy_from(&path, &tail, 500).unwrap(), 500);
+        assert_eq!(read(&tail).len(), 4000);
+    }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(
+            temp_path(Path::new("/a/n_mic_seg1.flac")),
+            Path::new("/a/n_mic_seg1.flac.tmp")
+        );
+    }
+}
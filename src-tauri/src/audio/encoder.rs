@@ -0,0 +1,201 @@
+//! The file format recordings are written in. WAV is the default; FLAC is lossless and
+//! takes about half the space for speech. The setting only affects new recordings: each
+//! file's format follows from its extension, so notes recorded before a change keep
+//! playing, transcribing and mixing as they were.
+//!
+//! Neither format gets its length into the header until the file is finished, so both
+//! bring it up to date every few seconds while recording: a file cut short by a crash
+//! or power loss still reads as nearly its full length, and startup recovery fills in
+//! the rest.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use hound::{WavSpec, WavWriter};
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::audio::flac::FlacWriter;
+use crate::audio::AudioError;
+use crate::db::Database;
+
+/// One of `FORMATS`
+pub const SETTING_FORMAT: &str = "recording_format";
+pub const FORMATS: &[&str] = &["wav", "flac"];
+
+static FLAC: AtomicBool = AtomicBool::new(false);
+
+/// Seconds of audio between WAV header updates
+const WAV_CHECKPOINT_SECS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Wav,
+    Flac,
+}
+
+impl RecordingFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+        }
+    }
+
+    /// The format of an existing recording, from its extension
+    pub fn of_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "wav" => Some(Self::Wav),
+            "flac" => Some(Self::Flac),
+            _ => None,
+        }
+    }
+}
+
+/// The format new recordings are written in
+pub fn recording_format() -> RecordingFormat {
+    if FLAC.load(Ordering::Relaxed) {
+        RecordingFormat::Flac
+    } else {
+        RecordingFormat::Wav
+    }
+}
+
+/// Extension for new recording files, without the dot
+pub fn recording_extension() -> &'static str {
+    recording_format().extension()
+}
+
+/// Writes 16-bit samples to a recording in the format its extension names
+pub enum AudioFileWriter {
+    Wav(WavWriter<BufWriter<File>>),
+    Flac(FlacWriter<BufWriter<File>>),
+}
+
+impl AudioFileWriter {
+    /// Start a recording at `path` on an already opened `file`
+    pub fn new(path: &Path, file: BufWriter<File>, spec: WavSpec) -> Result<Self, AudioError> {
+        match RecordingFormat::of_path(path) {
+            Some(RecordingFormat::Flac) => Ok(Self::Flac(FlacWriter::new(
+                file,
+                spec.sample_rate,
+                spec.channels,
+            )?)),
+            _ => Ok(Self::Wav(WavWriter::new(file, spec)?)),
+        }
+    }
+
+    pub fn create(path: &Path, spec: WavSpec) -> Result<Self, AudioError> {
+        Self::new(path, BufWriter::new(File::create(path)?), spec)
+    }
+
+    pub fn write_sample(&mut self, sample: i16) -> Result<(), AudioError> {
+        match self {
+            Self::Wav(writer) => {
+                writer.write_sample(sample)?;
+                let spec = writer.spec();
+                let checkpoint = spec.sample_rate * spec.channels as u32 * WAV_CHECKPOINT_SECS;
+                if writer.len() % checkpoint.max(1) == 0 {
+                    // Writes out the buffer and the header's sizes, keeping the position
+                    writer.flush()?;
+                }
+            }
+            Self::Flac(writer) => writer.write_sample(sample)?,
+        }
+        Ok(())
+    }
+
+    pub fn finalize(self) -> Result<(), AudioError> {
+        match self {
+            Self::Wav(writer) => writer.finalize()?,
+            Self::Flac(writer) => writer.finalize()?,
+        }
+        Ok(())
+    }
+}
+
+fn apply(value: Option<&str>) {
+    FLAC.store(value == Some("flac"), Ordering::Relaxed);
+}
+
+/// Load the setting and follow changes to it. Call once the database is managed.
+pub fn init(app: &AppHandle) {
+    let format = app
+        .state::<Database>()
+        .get_setting(SETTING_FORMAT)
+        .ok()
+        .flatten();
+    apply(format.as_deref());
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload())
+            && key == SETTING_FORMAT
+        {
+            apply(value.as_ref().and_then(Value::as_str));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_follows_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        for ext in FORMATS {
+            let path = dir.path().join(format!("note.{}", ext));
+            let mut writer = AudioFileWriter::create(&path, spec).unwrap();
+            for i in 0..1000 {
+                writer.write_sample(i).unwrap();
+            }
+            writer.finalize().unwrap();
+
+            let header = std::fs::read(&path).unwrap();
+            let magic: &[u8] = if *ext == "flac" { b"fLaC" } else { b"RIFF" };
+            assert_eq!(&header[..4], magic);
+        }
+        assert_eq!(
+            RecordingFormat::of_path(Path::new("a/b_mic.FLAC")),
+            Some(RecordingFormat::Flac)
+        );
+        assert_eq!(RecordingFormat::of_path(Path::new("a/b_mic.mp3")), None);
+    }
+
+    #[test]
+    fn test_unfinished_wav_reads_to_last_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.wav");
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = AudioFileWriter::create(&path, spec).unwrap();
+        let checkpoint = 8000 * 2 * WAV_CHECKPOINT_SECS;
+        for i in 0..checkpoint + 100 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        // As if the app died here: nothing finalizes the file
+        std::mem::forget(writer);
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.len(), checkpoint);
+    }
+}
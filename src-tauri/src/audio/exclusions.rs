@@ -0,0 +1,134 @@
+//! Apps whose sound never ends up in system audio recordings (music players, chat
+//! notification sounds). The list lives in settings as a JSON array of app names, macOS
+//! bundle ids or Windows executable names, and is applied when a capture starts:
+//!   macOS: the apps are excluded in the ScreenCaptureKit content filter
+//!   Windows: WASAPI process loopback can leave out one process tree, so the first listed
+//!            app that is running is excluded; with none running the whole mix is captured
+//!
+//! Alternatively system audio can be limited to one app (the meeting client), named the
+//! same way in `SETTING_CAPTURED_APP`:
+//!   macOS: the content filter includes only that app
+//!   Windows: process loopback captures only that app's process tree
+//! If it isn't running when capture starts, everything but the excluded apps is captured
+//! as usual.
+
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::db::Database;
+
+/// JSON array of app names / bundle ids / executable names
+pub const SETTING_EXCLUDED_APPS: &str = "system_audio_excluded_apps";
+
+/// App name / bundle id / executable name to capture alone; unset captures every app
+pub const SETTING_CAPTURED_APP: &str = "system_audio_captured_app";
+
+static EXCLUDED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+static CAPTURED: Mutex<Option<String>> = Mutex::new(None);
+
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    name.strip_suffix(".exe")
+        .map(str::to_string)
+        .unwrap_or(name)
+}
+
+/// Entries from the setting's value; anything that isn't a list of strings excludes nothing
+fn parse(value: &Value) -> Vec<String> {
+    serde_json::from_value::<Vec<String>>(value.clone())
+        .unwrap_or_default()
+        .iter()
+        .map(|name| normalize(name))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn set(excluded: Vec<String>) {
+    *EXCLUDED.lock().unwrap_or_else(|e| e.into_inner()) = excluded;
+}
+
+fn set_captured(name: Option<&str>) {
+    *CAPTURED.lock().unwrap_or_else(|e| e.into_inner()) =
+        name.map(normalize).filter(|name| !name.is_empty());
+}
+
+/// Load the list and the captured app, and follow changes to them. Call once the database
+/// is managed.
+pub fn init(app: &AppHandle) {
+    let db = app.state::<Database>();
+    let stored = db
+        .get_setting(SETTING_EXCLUDED_APPS)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<Value>(&json).ok());
+    if let Some(value) = stored {
+        set(parse(&value));
+    }
+    set_captured(db.get_setting(SETTING_CAPTURED_APP).ok().flatten().as_deref());
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        let Ok(Changed { key, value }) = serde_json::from_str(event.payload()) else {
+            return;
+        };
+        if key == SETTING_EXCLUDED_APPS {
+            set(value.as_ref().map(parse).unwrap_or_default());
+        } else if key == SETTING_CAPTURED_APP {
+            set_captured(value.as_ref().and_then(Value::as_str));
+        }
+    });
+}
+
+/// The normalized entries, in the order they were listed
+#[allow(dead_code)] // Used by the macOS and Windows captures
+pub fn excluded_apps() -> Vec<String> {
+    EXCLUDED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The normalized app system audio is limited to, if any
+#[allow(dead_code)] // Used by the macOS and Windows captures
+pub fn captured_app() -> Option<String> {
+    CAPTURED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Whether an app known by any of `names` (display name, bundle id, executable) is listed
+#[allow(dead_code)] // Used by the macOS and Windows captures
+pub fn is_listed(excluded: &[String], names: &[&str]) -> bool {
+    names
+        .iter()
+        .map(|name| normalize(name))
+        .any(|name| !name.is_empty() && excluded.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value =
+            serde_json::json!(["Spotify", " com.tinyspeck.slackmacgap ", "Discord.exe", ""]);
+        assert_eq!(
+            parse(&value),
+            vec!["spotify", "com.tinyspeck.slackmacgap", "discord"]
+        );
+        assert!(parse(&serde_json::json!({"app": "Spotify"})).is_empty());
+    }
+
+    #[test]
+    fn test_is_listed() {
+        let excluded = vec!["spotify".to_string(), "com.apple.music".to_string()];
+        assert!(is_listed(&excluded, &["Spotify.exe"]));
+        assert!(is_listed(&excluded, &["Music", "com.apple.Music"]));
+        assert!(!is_listed(&excluded, &["Zoom", "us.zoom.xos"]));
+        assert!(!is_listed(&excluded, &[""]));
+    }
+}
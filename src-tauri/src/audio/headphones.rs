@@ -0,0 +1,272 @@
+//! Echo cancellation off while listening on headphones. A monitor thread polls the default
+//! output's route: on macOS, Bluetooth transport or the built-in headphone jack; on
+//! Windows, the endpoint's form factor (headphones, headset or handset). Switching to
+//! headphones turns AEC off, as there is no echo to cancel; switching back to speakers
+//! turns it on again if this module was what turned it off. Either way "aec-auto-toggled"
+//! tells the UI. A choice made with `set_aec_enabled` stands until the route changes again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::aec;
+use crate::db::Database;
+
+/// "true" turns AEC off on headphones and back on on speakers
+pub const SETTING_ENABLED: &str = "aec_auto_headphones";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// AEC is off because headphones were plugged in, not by choice
+static AUTO_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// The default output device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputRoute {
+    pub name: String,
+    pub headphones: bool,
+}
+
+/// Payload of "aec-auto-toggled"
+#[derive(Debug, Clone, Serialize)]
+pub struct AecAutoToggledEvent {
+    pub enabled: bool,
+    /// The output device that prompted it
+    pub device: String,
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_ENABLED).ok().flatten())
+        .is_none_or(|v| v == "true")
+}
+
+/// AEC was set by hand; don't turn it back on when the headphones come out
+pub fn forget_auto_toggle() {
+    AUTO_DISABLED.store(false, Ordering::Relaxed);
+}
+
+/// What AEC should become when the output moves to `route`; None to leave it
+fn decide(route: &OutputRoute, aec_enabled: bool, auto_disabled: bool) -> Option<bool> {
+    match (route.headphones, aec_enabled) {
+        (true, true) => Some(false),
+        (false, false) if auto_disabled => Some(true),
+        _ => None,
+    }
+}
+
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut last: Option<OutputRoute> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            if !enabled(&app) {
+                last = None;
+                continue;
+            }
+            let Some(route) = platform::default_output_route() else {
+                continue;
+            };
+            // Only act on a change, so a choice made on the current route stands
+            if last.as_ref() == Some(&route) {
+                continue;
+            }
+            last = Some(route.clone());
+
+            let auto_disabled = AUTO_DISABLED.load(Ordering::Relaxed);
+            let Some(enable) = decide(&route, aec::is_aec_enabled(), auto_disabled) else {
+                continue;
+            };
+            if enable {
+                aec::reset_aec();
+            }
+            aec::set_aec_enabled(enable);
+            AUTO_DISABLED.store(!enable, Ordering::Relaxed);
+            tracing::info!(
+                "Echo cancellation {} for {}",
+                if enable { "on" } else { "off" },
+                route.name
+            );
+            let _ = app.emit(
+                "aec-auto-toggled",
+                AecAutoToggledEvent {
+                    enabled: enable,
+                    device: route.name,
+                },
+            );
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+
+    use core_foundation::base::TCFType;
+    use core_foundation::string::{CFString, CFStringRef};
+
+    use super::OutputRoute;
+
+    #[repr(C)]
+    struct AudioObjectPropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    unsafe extern "C" {
+        fn AudioObjectGetPropertyData(
+            object: u32,
+            address: *const AudioObjectPropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+
+    const fn code(tag: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*tag)
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    const SCOPE_GLOBAL: u32 = code(b"glob");
+    const SCOPE_OUTPUT: u32 = code(b"outp");
+    const DEFAULT_OUTPUT_DEVICE: u32 = code(b"dOut");
+    const NAME: u32 = code(b"lnam");
+    const TRANSPORT_TYPE: u32 = code(b"tran");
+    const DATA_SOURCE: u32 = code(b"ssrc");
+    const TRANSPORT_BLUETOOTH: u32 = code(b"blue");
+    const TRANSPORT_BLUETOOTH_LE: u32 = code(b"blea");
+    /// The built-in output's headphone jack
+    const SOURCE_HEADPHONES: u32 = code(b"hdpn");
+
+    fn property<T: Default>(object: u32, selector: u32, scope: u32) -> Option<T> {
+        let address = AudioObjectPropertyAddress {
+            selector,
+            scope,
+            element: 0,
+        };
+        let mut value = T::default();
+        let mut size = std::mem::size_of::<T>() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                &address,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut value as *mut T as *mut c_void,
+            )
+        };
+        (status == 0).then_some(value)
+    }
+
+    pub fn default_output_route() -> Option<OutputRoute> {
+        let device: u32 = property(SYSTEM_OBJECT, DEFAULT_OUTPUT_DEVICE, SCOPE_GLOBAL)?;
+        if device == 0 {
+            return None;
+        }
+        let transport: u32 = property(device, TRANSPORT_TYPE, SCOPE_GLOBAL).unwrap_or(0);
+        let source: u32 = property(device, DATA_SOURCE, SCOPE_OUTPUT).unwrap_or(0);
+        let name = property::<usize>(device, NAME, SCOPE_GLOBAL)
+            .filter(|&name| name != 0)
+            .map(|name| unsafe { CFString::wrap_under_create_rule(name as CFStringRef) })
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        Some(OutputRoute {
+            name,
+            headphones: matches!(transport, TRANSPORT_BLUETOOTH | TRANSPORT_BLUETOOTH_LE)
+                || source == SOURCE_HEADPHONES,
+        })
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::core::{GUID, PROPVARIANT};
+    use windows::Win32::Foundation::PROPERTYKEY;
+    use windows::Win32::Media::Audio::{
+        eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator,
+    };
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ,
+    };
+
+    use super::OutputRoute;
+
+    /// PKEY_AudioEndpoint_FormFactor
+    const FORM_FACTOR: PROPERTYKEY = PROPERTYKEY {
+        fmtid: GUID::from_u128(0x1da5d803_d492_4edd_8c23_e0c0ffee7f0e),
+        pid: 0,
+    };
+    /// PKEY_Device_FriendlyName
+    const FRIENDLY_NAME: PROPERTYKEY = PROPERTYKEY {
+        fmtid: GUID::from_u128(0xa45c254e_df1c_4efd_8020_67d146a850e0),
+        pid: 14,
+    };
+
+    /// EndpointFormFactor values worn on the head or held to the ear
+    const HEADPHONES: u32 = 3;
+    const HEADSET: u32 = 5;
+    const HANDSET: u32 = 6;
+
+    pub fn default_output_route() -> Option<OutputRoute> {
+        unsafe {
+            // Already initialized on this thread after the first poll
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+            let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole).ok()?;
+            let store = device.OpenPropertyStore(STGM_READ).ok()?;
+            let form_factor: PROPVARIANT = store.GetValue(&FORM_FACTOR).ok()?;
+            let form_factor = u32::try_from(&form_factor).unwrap_or(0);
+            let name = store
+                .GetValue(&FRIENDLY_NAME)
+                .map(|name| name.to_string())
+                .unwrap_or_default();
+            Some(OutputRoute {
+                name,
+                headphones: matches!(form_factor, HEADPHONES | HEADSET | HANDSET),
+            })
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::OutputRoute;
+
+    /// Not known here, so AEC is left as set
+    pub fn default_output_route() -> Option<OutputRoute> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(headphones: bool) -> OutputRoute {
+        OutputRoute {
+            name: "Output".to_string(),
+            headphones,
+        }
+    }
+
+    #[test]
+    fn test_only_restores_what_it_turned_off() {
+        assert_eq!(decide(&route(true), true, false), Some(false));
+        assert_eq!(decide(&route(true), false, false), None);
+        assert_eq!(decide(&route(false), false, true), Some(true));
+        // Off by choice stays off on speakers
+        assert_eq!(decide(&route(false), false, false), None);
+        assert_eq!(decide(&route(false), true, false), None);
+    }
+}
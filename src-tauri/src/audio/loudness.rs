@@ -0,0 +1,219 @@
+//! Loudness measurement after EBU R128 (ITU-R BS.1770): audio is K-weighted, measured in
+//! 400ms blocks overlapping by 75%, and blocks of silence and quiet passages are gated
+//! out, giving the integrated loudness in LUFS. Every channel is weighted alike, which is
+//! right for the mono and stereo recordings made here.
+
+/// Blocks quieter than this never count
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks this far below the loudness of the ungated blocks don't count
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Blocks are four steps of 100ms
+const STEPS_PER_BLOCK: usize = 4;
+
+/// A biquad filter, in direct form II transposed
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The K-weighting filter at `rate`: a high shelf for the head's effect, then a high pass.
+/// Coefficients are derived for any rate, matching the standard's at 48kHz.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+fn lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Measures integrated loudness as audio streams in, keeping only the energy of each
+/// 100ms step
+pub struct LoudnessMeter {
+    channels: usize,
+    step: usize,
+    filters: Vec<[Biquad; 2]>,
+    /// Sum of squared K-weighted samples in each finished step, over every channel
+    energy: Vec<f64>,
+    current: f64,
+    frames: usize,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: u16, rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            step: (rate as usize / 10).max(1),
+            filters: vec![k_weighting(rate); channels],
+            energy: Vec::new(),
+            current: 0.0,
+            frames: 0,
+        }
+    }
+
+    /// Measure more interleaved samples
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (sample, filters) in frame.iter().zip(&mut self.filters) {
+                let y = filters
+                    .iter_mut()
+                    .fold(*sample as f64, |x, filter| filter.process(x));
+                self.current += y * y;
+            }
+            self.frames += 1;
+            if self.frames == self.step {
+                self.energy.push(self.current);
+                self.current = 0.0;
+                self.frames = 0;
+            }
+        }
+    }
+
+    /// Integrated loudness so far in LUFS. None for audio shorter than a block, or silent
+    /// throughout.
+    pub fn integrated(&self) -> Option<f64> {
+        if self.energy.len() < STEPS_PER_BLOCK {
+            return None;
+        }
+        let block_len = (self.step * STEPS_PER_BLOCK) as f64;
+        let blocks: Vec<f64> = self
+            .energy
+            .windows(STEPS_PER_BLOCK)
+            .map(|w| w.iter().sum::<f64>() / block_len)
+            .filter(|z| *z > 0.0 && lufs(*z) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+        let threshold = lufs(mean(&blocks)) - RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|z| lufs(*z) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| lufs(mean(&gated)))
+    }
+}
+
+/// Integrated loudness of interleaved `samples` in LUFS. None for audio shorter than a
+/// block, or silent throughout.
+pub fn integrated_loudness(samples: &[f32], channels: u16, rate: u32) -> Option<f64> {
+    let mut meter = LoudnessMeter::new(channels, rate);
+    meter.push(samples);
+    meter.integrated()
+}
+
+/// Linear gain that brings audio at `loudness` to `target` LUFS, boosting by at most
+/// `max_boost_db`
+pub fn gain_to(loudness: f64, target: f64, max_boost_db: f64) -> f32 {
+    db_to_gain((target - loudness).min(max_boost_db))
+}
+
+pub fn db_to_gain(db: f64) -> f32 {
+    10f64.powf(db / 20.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, amplitude: f32, rate: u32, seconds: f64, channels: usize) -> Vec<f32> {
+        let frames = (rate as f64 * seconds) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f64 / rate as f64;
+                let s = (2.0 * std::f64::consts::PI * freq * t).sin() as f32 * amplitude;
+                std::iter::repeat_n(s, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_reference() {
+        // A 997Hz sine at -20dBFS in one channel measures -23 LUFS, in two -20 LUFS
+        let mono = sine(997.0, 0.1, 48000, 5.0, 1);
+        let loudness = integrated_loudness(&mono, 1, 48000).unwrap();
+        assert!((loudness + 23.0).abs() < 0.1, "{}", loudness);
+
+        let stereo = sine(997.0, 0.1, 16000, 5.0, 2);
+        let loudness = integrated_loudness(&stereo, 2, 16000).unwrap();
+        assert!((loudness + 20.0).abs() < 0.1, "{}", loudness);
+    }
+
+    #[test]
+    fn test_gates_out_silence() {
+        let mut samples = sine(997.0, 0.1, 16000, 3.0, 1);
+        samples.extend(std::iter::repeat_n(0.0, 16000 * 30));
+        let loudness = integrated_loudness(&samples, 1, 16000).unwrap();
+        assert!((loudness + 23.0).abs() < 0.2, "{}", loudness);
+    }
+
+    #[test]
+    fn test_meter_in_packets() {
+        let samples = sine(997.0, 0.1, 16000, 3.0, 2);
+        let mut meter = LoudnessMeter::new(2, 16000);
+        for packet in samples.chunks(2 * 1152) {
+            meter.push(packet);
+        }
+        let whole = integrated_loudness(&samples, 2, 16000).unwrap();
+        assert!((meter.integrated().unwrap() - whole).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_silence_and_short_audio() {
+        assert_eq!(integrated_loudness(&vec![0.0; 16000 * 2], 1, 16000), None);
+        assert_eq!(
+            integrated_loudness(&sine(997.0, 0.5, 16000, 0.3, 1), 1, 16000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_gain_to() {
+        assert!((gain_to(-26.0, -20.0, 20.0) - 2.0).abs() < 0.01);
+        assert!((gain_to(-60.0, -20.0, 12.0) - db_to_gain(12.0)).abs() < 1e-6);
+        assert!(gain_to(-10.0, -20.0, 20.0) < 1.0);
+    }
+}
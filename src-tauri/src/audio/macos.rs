@@ -3,24 +3,32 @@
 //! ScreenCaptureKit (available macOS 12.3+, audio capture macOS 13.0+) provides
 //! the ability to capture system audio output, which we use to record
 //! meeting participants' voices.
+//!
+//! The stream stops on its own when the display sleeps or the screen locks. Capture then
+//! reports "system-audio-interrupted", keeps trying to start a new stream into the same
+//! file, and pads the gap with silence once it does.
 
 #![cfg(target_os = "macos")]
 
 use std::ffi::c_void;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use hound::{WavSpec, WavWriter};
 use objc2::rc::Retained;
 use objc2::runtime::{AnyClass, AnyObject, Bool, Sel};
 use objc2::{class, msg_send, sel};
 // CMSampleBuffer is an opaque type, we use a raw pointer
 type CMSampleBufferRef = *mut c_void;
 
-use objc2_foundation::{NSArray, NSError, NSObject};
+use objc2_foundation::{NSArray, NSError, NSObject, NSString};
 
-use super::system_audio::{SystemAudioCapture, SystemAudioResult};
+use super::capture_format::{self, CaptureFormat};
+use super::encoder::AudioFileWriter;
+use super::exclusions;
+use super::system_audio::{self, CapturableApp, SystemAudioCapture, SystemAudioResult};
 use crate::audio::AudioError;
 
 // ScreenCaptureKit minimum version check (audio capture requires macOS 13.0+)
@@ -72,9 +80,11 @@ fn request_screen_capture_permission() -> bool {
 
 /// Shared state for audio writing, accessible from the callback
 struct AudioWriterState {
-    writer: Option<WavWriter<std::io::BufWriter<std::fs::File>>>,
+    writer: Option<AudioFileWriter>,
     output_path: PathBuf,
     is_active: bool,
+    /// What the file is written in, from the quality preset when the capture started
+    format: CaptureFormat,
 }
 
 /// Global state for the audio callback (needed because ObjC callbacks can't capture Rust state directly)
@@ -84,118 +94,284 @@ fn get_audio_writer() -> &'static Mutex<Option<AudioWriterState>> {
     AUDIO_WRITER.get_or_init(|| Mutex::new(None))
 }
 
-/// Global buffer for system audio samples (for live transcription)
-static SYSTEM_AUDIO_BUFFER: std::sync::OnceLock<Mutex<Vec<f32>>> = std::sync::OnceLock::new();
-
-fn get_system_audio_buffer() -> &'static Mutex<Vec<f32>> {
-    SYSTEM_AUDIO_BUFFER.get_or_init(|| Mutex::new(Vec::new()))
+/// Finalize the file being written and carry on in a new one at `output_path`
+fn switch_writer(output_path: PathBuf) -> Result<(), AudioError> {
+    let format = {
+        let guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+        guard
+            .as_ref()
+            .filter(|state| state.is_active)
+            .map(|state| state.format)
+            .ok_or(AudioError::NotRecording)?
+    };
+    let writer = AudioFileWriter::create(&output_path, format.spec())?;
+    let previous = {
+        let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+        let state = guard
+            .as_mut()
+            .filter(|state| state.is_active)
+            .ok_or(AudioError::NotRecording)?;
+        state.output_path = output_path;
+        system_audio::reset_system_clock();
+        state.writer.replace(writer)
+    };
+    match previous {
+        Some(previous) => previous.finalize(),
+        None => Ok(()),
+    }
 }
 
-/// Take all samples from the system audio buffer (clears the buffer)
-pub fn take_system_audio_samples() -> Vec<f32> {
-    match get_system_audio_buffer().lock() { Ok(mut buffer) => {
-        std::mem::take(&mut *buffer)
-    } _ => {
-        Vec::new()
-    }}
+/// Finalize the file being written, returning its path
+fn close_writer() -> Result<Option<PathBuf>, AudioError> {
+    let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+    Ok(guard.take().map(|mut state| {
+        state.is_active = false;
+        if let Some(writer) = state.writer.take() {
+            let _ = writer.finalize();
+        }
+        state.output_path
+    }))
 }
 
-/// Clear the system audio buffer
-#[allow(dead_code)]
-pub fn clear_system_audio_buffer() {
-    if let Ok(mut buffer) = get_system_audio_buffer().lock() {
-        buffer.clear();
+/// Set by the stream delegate when ScreenCaptureKit stops the stream on its own (display
+/// sleep, screen lock, a display change), with when and why
+static INTERRUPTION: Mutex<Option<(Instant, String)>> = Mutex::new(None);
+
+/// How often the capture checks for an interrupted stream
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Wait between attempts to restart an interrupted stream; it can't start again until
+/// the screen is unlocked
+const RESTART_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Fill the file with silence for audio missed while the stream was down, keeping it in
+/// time with the mic
+fn write_silence(duration: Duration) {
+    let Ok(mut guard) = get_audio_writer().lock() else {
+        return;
+    };
+    if let Some(state) = guard.as_mut()
+        && state.is_active
+        && let Some(writer) = state.writer.as_mut()
+    {
+        let CaptureFormat {
+            sample_rate,
+            channels,
+        } = state.format;
+        let frames = (duration.as_secs_f64() * sample_rate as f64) as usize;
+        for _ in 0..frames * channels as usize {
+            let _ = writer.write_sample(0_i16);
+        }
+        system_audio::record_system_frames(frames, sample_rate);
     }
 }
 
-/// Process audio samples from CMSampleBuffer and write to WAV file
-fn process_audio_buffer(sample_buffer: CMSampleBufferRef) {
+/// The localized description of an NSError
+fn describe_error(error: *mut NSError) -> String {
+    if error.is_null() {
+        return "Unknown error".to_string();
+    }
     unsafe {
+        let desc: *mut NSString = msg_send![error, localizedDescription];
+        if desc.is_null() {
+            "Unknown error".to_string()
+        } else {
+            (*desc).to_string()
+        }
+    }
+}
+
+/// CoreAudio's AudioStreamBasicDescription
+#[repr(C)]
+#[allow(dead_code)]
+struct AudioStreamBasicDescription {
+    sample_rate: f64,
+    format_id: u32,
+    format_flags: u32,
+    bytes_per_packet: u32,
+    frames_per_packet: u32,
+    bytes_per_frame: u32,
+    channels_per_frame: u32,
+    bits_per_channel: u32,
+    reserved: u32,
+}
+
+/// 'lpcm'
+const FORMAT_LINEAR_PCM: u32 = 0x6C70_636D;
+const FORMAT_FLAG_IS_FLOAT: u32 = 1 << 0;
+const FORMAT_FLAG_IS_NON_INTERLEAVED: u32 = 1 << 5;
+
+/// Layout of the PCM in a captured sample buffer
+#[derive(Debug, Clone, Copy)]
+struct BufferFormat {
+    sample_rate: u32,
+    channels: usize,
+    /// 32-bit float, otherwise 16-bit integer
+    is_float: bool,
+    /// One block per channel rather than interleaved frames
+    planar: bool,
+}
+
+impl BufferFormat {
+    /// What the stream is configured for, assumed when a buffer doesn't describe itself
+    const REQUESTED: Self = Self {
+        sample_rate: 48000,
+        channels: 2,
+        is_float: true,
+        planar: true,
+    };
+
+    /// The format the buffer describes; None for anything but 16-bit or float PCM
+    unsafe fn of(sample_buffer: CMSampleBufferRef) -> Option<Self> {
         unsafe extern "C" {
-            fn CMSampleBufferGetDataBuffer(sbuf: CMSampleBufferRef) -> *mut c_void;
-            #[allow(dead_code)]
-            fn CMBlockBufferGetDataLength(block_buffer: *mut c_void) -> usize;
-            fn CMBlockBufferGetDataPointer(
-                block_buffer: *mut c_void,
-                offset: usize,
-                length_at_offset_out: *mut usize,
-                total_length_out: *mut usize,
-                data_pointer_out: *mut *mut u8,
-            ) -> i32;
+            fn CMSampleBufferGetFormatDescription(sbuf: CMSampleBufferRef) -> *mut c_void;
+            fn CMAudioFormatDescriptionGetStreamBasicDescription(
+                desc: *mut c_void,
+            ) -> *const AudioStreamBasicDescription;
         }
 
-        // Get the data buffer from the sample buffer
-        let block_buffer = CMSampleBufferGetDataBuffer(sample_buffer);
-        if block_buffer.is_null() {
-            return;
+        let description = unsafe { CMSampleBufferGetFormatDescription(sample_buffer) };
+        if description.is_null() {
+            return Some(Self::REQUESTED);
         }
+        let asbd = unsafe { CMAudioFormatDescriptionGetStreamBasicDescription(description) };
+        let Some(asbd) = (unsafe { asbd.as_ref() }) else {
+            return Some(Self::REQUESTED);
+        };
 
-        // Get data pointer and length
-        let mut data_ptr: *mut u8 = std::ptr::null_mut();
-        let mut length_at_offset: usize = 0;
-        let mut total_length: usize = 0;
+        let is_float = asbd.format_flags & FORMAT_FLAG_IS_FLOAT != 0;
+        let supported = asbd.format_id == FORMAT_LINEAR_PCM
+            && asbd.channels_per_frame > 0
+            && asbd.sample_rate >= 1.0
+            && matches!((is_float, asbd.bits_per_channel), (true, 32) | (false, 16));
+        supported.then(|| Self {
+            sample_rate: asbd.sample_rate.round() as u32,
+            channels: asbd.channels_per_frame as usize,
+            is_float,
+            planar: asbd.format_flags & FORMAT_FLAG_IS_NON_INTERLEAVED != 0,
+        })
+    }
 
-        let status = CMBlockBufferGetDataPointer(
-            block_buffer,
-            0,
-            &mut length_at_offset,
-            &mut total_length,
-            &mut data_ptr,
-        );
+    /// The PCM in `data` as left and right channels; mono is copied to both and channels
+    /// past the second are dropped
+    fn decode(&self, data: &[u8]) -> (Vec<f32>, Vec<f32>) {
+        let samples: Vec<f32> = if self.is_float {
+            data.chunks_exact(4)
+                .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                .collect()
+        } else {
+            data.chunks_exact(2)
+                .map(|b| i16::from_ne_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect()
+        };
+        let frames = samples.len() / self.channels;
+        let channel = |index: usize| -> Vec<f32> {
+            if self.planar {
+                samples[index * frames..(index + 1) * frames].to_vec()
+            } else {
+                samples
+                    .chunks_exact(self.channels)
+                    .map(|frame| frame[index])
+                    .collect()
+            }
+        };
+        (channel(0), channel(self.channels.min(2) - 1))
+    }
+}
+
+/// Nearest-sample conversion from `from` Hz to `to` Hz
+pub(super) fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let len = (samples.len() as f64 / ratio) as usize;
+    (0..len)
+        .filter_map(|i| samples.get((i as f64 * ratio) as usize).copied())
+        .collect()
+}
 
-        if status != 0 || data_ptr.is_null() || total_length == 0 {
+/// Convert a captured CMSampleBuffer to 48kHz stereo for the recording file, and to 16kHz
+/// mono for live transcription
+fn process_audio_buffer(sample_buffer: CMSampleBufferRef) {
+    unsafe extern "C" {
+        fn CMSampleBufferGetDataBuffer(sbuf: CMSampleBufferRef) -> *mut c_void;
+        fn CMBlockBufferGetDataLength(block_buffer: *mut c_void) -> usize;
+        fn CMBlockBufferCopyDataBytes(
+            block_buffer: *mut c_void,
+            offset: usize,
+            length: usize,
+            destination: *mut c_void,
+        ) -> i32;
+    }
+
+    let Some(format) = (unsafe { BufferFormat::of(sample_buffer) }) else {
+        tracing::warn!("Skipping system audio in an unsupported format");
+        return;
+    };
+
+    // Copy the PCM out: the block buffer needn't be contiguous
+    let data = unsafe {
+        let block_buffer = CMSampleBufferGetDataBuffer(sample_buffer);
+        if block_buffer.is_null() {
             return;
         }
-
-        // ScreenCaptureKit provides audio as 32-bit float samples in NON-INTERLEAVED (planar) format
-        // First half is left channel, second half is right channel
-        let sample_count = total_length / std::mem::size_of::<f32>();
-        if sample_count == 0 {
+        let length = CMBlockBufferGetDataLength(block_buffer);
+        if length == 0 {
             return;
         }
-
-        let samples = std::slice::from_raw_parts(data_ptr as *const f32, sample_count);
-
-        // Split into left and right channels (non-interleaved/planar format)
-        let samples_per_channel = sample_count / 2;
-        let left_channel = &samples[..samples_per_channel];
-        let right_channel = &samples[samples_per_channel..];
-
-        // Write audio data to WAV file (interleaved stereo)
-        if let Ok(mut guard) = get_audio_writer().lock() {
-            if let Some(ref mut state) = *guard {
-                if state.is_active {
-                    if let Some(ref mut writer) = state.writer {
-                        // Interleave left and right channels
-                        for i in 0..samples_per_channel {
-                            let left = left_channel.get(i).copied().unwrap_or(0.0);
-                            let right = right_channel.get(i).copied().unwrap_or(0.0);
-
-                            // Convert f32 (-1.0 to 1.0) to i16
-                            let left_i16 = (left.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
-                            let right_i16 = (right.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
-
-                            let _ = writer.write_sample(left_i16);
-                            let _ = writer.write_sample(right_i16);
-                        }
-                    }
-                }
-            }
+        let mut data = vec![0u8; length];
+        let status =
+            CMBlockBufferCopyDataBytes(block_buffer, 0, length, data.as_mut_ptr() as *mut c_void);
+        if status != 0 {
+            return;
         }
+        data
+    };
 
-        // Also push to the system audio buffer for live transcription
-        // Downsample from 48kHz to 16kHz for Whisper (take every 3rd sample from left channel)
-        if let Ok(mut buffer) = get_system_audio_buffer().lock() {
-            for (i, &sample) in left_channel.iter().enumerate() {
-                if i % 3 == 0 {
-                    buffer.push(sample);
+    let (left, right) = format.decode(&data);
+    if left.is_empty() {
+        return;
+    }
+    let mono: Vec<f32> = left.iter().zip(&right).map(|(l, r)| (l + r) / 2.0).collect();
+    system_audio::update_system_audio_level(&mono);
+
+    // Write audio data to the recording file (interleaved stereo, or mono)
+    if let Ok(mut guard) = get_audio_writer().lock() {
+        if let Some(ref mut state) = *guard {
+            if state.is_active {
+                let rate = state.format.sample_rate;
+                let stereo = state.format.channels > 1;
+                if let Some(ref mut writer) = state.writer {
+                    // Convert f32 (-1.0 to 1.0) to i16
+                    let to_i16 = |s: f32| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                    let frames = if stereo {
+                        let left = resample(&left, format.sample_rate, rate);
+                        let right = resample(&right, format.sample_rate, rate);
+                        for (left, right) in left.iter().zip(&right) {
+                            let _ = writer.write_sample(to_i16(*left));
+                            let _ = writer.write_sample(to_i16(*right));
+                        }
+                        left.len().min(right.len())
+                    } else {
+                        let mono = resample(&mono, format.sample_rate, rate);
+                        for sample in &mono {
+                            let _ = writer.write_sample(to_i16(*sample));
+                        }
+                        mono.len()
+                    };
+                    system_audio::record_system_frames(frames, rate);
                 }
             }
         }
     }
+
+    // Also push to the system audio buffer for live transcription, at 16kHz for Whisper
+    system_audio::push_system_audio_samples(&resample(&mono, format.sample_rate, 16000));
 }
 
-/// Create and register a dynamic Objective-C class that implements SCStreamOutput protocol
+/// Create and register a dynamic Objective-C class that implements the SCStreamOutput and
+/// SCStreamDelegate protocols
 fn create_stream_output_class() -> *const AnyClass {
     use std::sync::Once;
     static REGISTER: Once = Once::new();
@@ -231,11 +407,12 @@ fn create_stream_output_class() -> *const AnyClass {
                 return;
             }
 
-            // Add SCStreamOutput protocol
-            let protocol_name = b"SCStreamOutput\0".as_ptr() as *const i8;
-            let protocol = objc_getProtocol(protocol_name);
-            if !protocol.is_null() {
-                class_addProtocol(new_class, protocol);
+            // Add SCStreamOutput and SCStreamDelegate protocols
+            for protocol_name in [b"SCStreamOutput\0", b"SCStreamDelegate\0"] {
+                let protocol = objc_getProtocol(protocol_name.as_ptr() as *const i8);
+                if !protocol.is_null() {
+                    class_addProtocol(new_class, protocol);
+                }
             }
 
             // Add the stream:didOutputSampleBuffer:ofType: method
@@ -262,6 +439,29 @@ fn create_stream_output_class() -> *const AnyClass {
                 method_types,
             );
 
+            // Add the stream:didStopWithError: method, called when ScreenCaptureKit stops
+            // the stream itself
+            extern "C" fn stream_did_stop_with_error(
+                _this: &NSObject,
+                _cmd: Sel,
+                _stream: *mut AnyObject,
+                error: *mut NSError,
+            ) {
+                let reason = describe_error(error);
+                tracing::warn!("ScreenCaptureKit stopped the stream: {}", reason);
+                *INTERRUPTION.lock().unwrap_or_else(PoisonError::into_inner) =
+                    Some((Instant::now(), reason));
+            }
+
+            // v = void, @ = object (self), : = SEL, @ = object (stream), @ = object (error)
+            let stop_types = b"v@:@@\0".as_ptr() as *const i8;
+            class_addMethod(
+                new_class,
+                sel!(stream:didStopWithError:),
+                stream_did_stop_with_error as *const c_void,
+                stop_types,
+            );
+
             objc_registerClassPair(new_class);
             CLASS = new_class as *const AnyClass;
         }
@@ -278,10 +478,14 @@ struct CaptureSession {
     output_delegate: Retained<AnyObject>,
 }
 
+// Safety: SCStream may be messaged from any thread; the session is only touched under
+// its mutex.
+unsafe impl Send for CaptureSession {}
+
 /// macOS system audio capture implementation using ScreenCaptureKit
 pub struct MacOSSystemAudioCapture {
-    is_capturing: AtomicBool,
-    session: Mutex<Option<CaptureSession>>,
+    is_capturing: Arc<AtomicBool>,
+    session: Arc<Mutex<Option<CaptureSession>>>,
 }
 
 // Safety: MacOSSystemAudioCapture uses atomic operations and mutex for thread safety.
@@ -291,8 +495,8 @@ unsafe impl Sync for MacOSSystemAudioCapture {}
 impl MacOSSystemAudioCapture {
     pub fn new() -> Self {
         Self {
-            is_capturing: AtomicBool::new(false),
-            session: Mutex::new(None),
+            is_capturing: Arc::new(AtomicBool::new(false)),
+            session: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -335,7 +539,7 @@ impl MacOSSystemAudioCapture {
                     } else {
                         "Failed to get shareable content (unknown error)".to_string()
                     };
-                    eprintln!("[Note67] {}", error_msg);
+                    tracing::warn!("{}", error_msg);
                     let _ = tx_clone.send(Err(AudioError::PermissionDenied(error_msg)));
                 } else if content.is_null() {
                     let _ = tx_clone.send(Err(AudioError::PermissionDenied(
@@ -386,25 +590,127 @@ impl MacOSSystemAudioCapture {
                 return Err(AudioError::PermissionDenied("No display found".to_string()));
             }
 
-            // Create content filter with display and empty excluded apps/windows
+            // Create content filter with display: only the captured app's audio when one
+            // is chosen and running, otherwise everything but the excluded apps'
             let filter_class = class!(SCContentFilter);
-            let empty_apps: Retained<NSArray<AnyObject>> = NSArray::new();
+            let captured_apps = Self::captured_applications(content);
             let empty_windows: Retained<NSArray<AnyObject>> = NSArray::new();
 
             // Allocate and initialize the filter
             let filter_alloc: *mut AnyObject = msg_send![filter_class, alloc];
-            let filter: *mut AnyObject = msg_send![
-                filter_alloc,
-                initWithDisplay: display,
-                excludingApplications: &*empty_apps,
-                exceptingWindows: &*empty_windows
-            ];
+            let filter: *mut AnyObject = match captured_apps {
+                Some(captured_apps) => msg_send![
+                    filter_alloc,
+                    initWithDisplay: display,
+                    includingApplications: &*captured_apps,
+                    exceptingWindows: &*empty_windows
+                ],
+                None => {
+                    let excluded_apps = Self::excluded_applications(content);
+                    msg_send![
+                        filter_alloc,
+                        initWithDisplay: display,
+                        excludingApplications: &*excluded_apps,
+                        exceptingWindows: &*empty_windows
+                    ]
+                }
+            };
 
             Retained::retain(filter)
                 .ok_or_else(|| AudioError::PermissionDenied("Failed to create content filter".to_string()))
         }
     }
 
+    /// The running applications in `content`, with their names, bundle ids and pids
+    fn running_applications(content: &AnyObject) -> Vec<(Retained<AnyObject>, CapturableApp)> {
+        let nsstring = |ptr: *mut NSString| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                unsafe { (*ptr).to_string() }
+            }
+        };
+        let mut running = Vec::new();
+        unsafe {
+            let apps: *mut NSArray<AnyObject> = msg_send![content, applications];
+            if apps.is_null() {
+                return running;
+            }
+            let count: usize = msg_send![apps, count];
+            for i in 0..count {
+                let app: *mut AnyObject = msg_send![apps, objectAtIndex: i];
+                if app.is_null() {
+                    continue;
+                }
+                let info = CapturableApp {
+                    name: nsstring(msg_send![app, applicationName]),
+                    id: nsstring(msg_send![app, bundleIdentifier]),
+                    pid: msg_send![app, processID],
+                };
+                if let Some(app) = Retained::retain(app) {
+                    running.push((app, info));
+                }
+            }
+        }
+        running
+    }
+
+    /// The running applications in `content` that are on the exclusion list
+    fn excluded_applications(content: &AnyObject) -> Retained<NSArray<AnyObject>> {
+        let excluded = exclusions::excluded_apps();
+        if excluded.is_empty() {
+            return NSArray::new();
+        }
+
+        let matched: Vec<Retained<AnyObject>> = Self::running_applications(content)
+            .into_iter()
+            .filter(|(_, info)| exclusions::is_listed(&excluded, &[&info.id, &info.name]))
+            .map(|(app, info)| {
+                tracing::info!("Excluding {} ({}) from system audio", info.name, info.id);
+                app
+            })
+            .collect();
+        NSArray::from_retained_slice(&matched)
+    }
+
+    /// The running instances of the app system audio is limited to; None when capturing
+    /// every app, or when that app isn't running
+    fn captured_applications(content: &AnyObject) -> Option<Retained<NSArray<AnyObject>>> {
+        let captured = exclusions::captured_app()?;
+
+        let matched: Vec<Retained<AnyObject>> = Self::running_applications(content)
+            .into_iter()
+            .filter(|(_, info)| {
+                exclusions::is_listed(std::slice::from_ref(&captured), &[&info.id, &info.name])
+            })
+            .map(|(app, info)| {
+                tracing::info!("Capturing system audio from {} ({}) only", info.name, info.id);
+                app
+            })
+            .collect();
+        if matched.is_empty() {
+            tracing::warn!("{} isn't running; capturing all system audio", captured);
+            return None;
+        }
+        Some(NSArray::from_retained_slice(&matched))
+    }
+
+    /// Running apps that system audio capture can be limited to or leave out, by name
+    pub fn capturable_apps() -> SystemAudioResult<Vec<CapturableApp>> {
+        let content = Self::get_shareable_content_sync()?;
+        let own_pid = std::process::id() as i32;
+        let mut apps: Vec<CapturableApp> = Self::running_applications(&content)
+            .into_iter()
+            .map(|(_, info)| info)
+            .filter(|info| !info.id.is_empty() && info.pid != own_pid)
+            .collect();
+        apps.sort_by(|a, b| {
+            (a.name.to_lowercase(), &a.id).cmp(&(b.name.to_lowercase(), &b.id))
+        });
+        apps.dedup_by(|a, b| a.id == b.id);
+        Ok(apps)
+    }
+
     /// Create stream configuration for audio-only capture
     fn create_stream_config() -> Result<Retained<AnyObject>, AudioError> {
         unsafe {
@@ -434,7 +740,7 @@ impl MacOSSystemAudioCapture {
             let _: () = msg_send![config, setSampleRate: 48000_i32];
             let _: () = msg_send![config, setChannelCount: 2_i32];
 
-            eprintln!("ScreenCaptureKit: Created stream configuration");
+            tracing::debug!("ScreenCaptureKit: Created stream configuration");
 
             Retained::retain(config)
                 .ok_or_else(|| AudioError::PermissionDenied("Failed to retain config".to_string()))
@@ -443,38 +749,15 @@ impl MacOSSystemAudioCapture {
 
     /// Create the stream output delegate and start capture
     fn start_capture_session(
-        &self,
         filter: &AnyObject,
         config: &AnyObject,
-        output_path: PathBuf,
     ) -> Result<CaptureSession, AudioError> {
         unsafe {
-            eprintln!("ScreenCaptureKit: Creating stream...");
-            let stream_class = class!(SCStream);
-
-            // Allocate and initialize the stream
-            let stream_alloc: *mut AnyObject = msg_send![stream_class, alloc];
-            let stream: *mut AnyObject = msg_send![
-                stream_alloc,
-                initWithFilter: filter,
-                configuration: config,
-                delegate: std::ptr::null::<AnyObject>()
-            ];
-
-            if stream.is_null() {
-                eprintln!("ScreenCaptureKit: Failed to create stream");
-                return Err(AudioError::PermissionDenied("Failed to create stream".to_string()));
-            }
-            eprintln!("ScreenCaptureKit: Stream created successfully");
-
-            let stream = Retained::retain(stream)
-                .ok_or_else(|| AudioError::PermissionDenied("Failed to retain stream".to_string()))?;
-
-            // Create the output delegate
-            eprintln!("ScreenCaptureKit: Creating output delegate...");
+            // Create the output delegate, which is also the stream's delegate
+            tracing::debug!("ScreenCaptureKit: Creating output delegate...");
             let output_class = create_stream_output_class();
             if output_class.is_null() {
-                eprintln!("ScreenCaptureKit: Failed to create output class");
+                tracing::error!("ScreenCaptureKit: Failed to create output class");
                 return Err(AudioError::PermissionDenied(
                     "Failed to create output class".to_string(),
                 ));
@@ -482,26 +765,47 @@ impl MacOSSystemAudioCapture {
 
             let output_delegate: *mut AnyObject = msg_send![output_class as *const AnyObject, new];
             if output_delegate.is_null() {
-                eprintln!("ScreenCaptureKit: Failed to create output delegate instance");
+                tracing::error!("ScreenCaptureKit: Failed to create output delegate instance");
                 return Err(AudioError::PermissionDenied(
                     "Failed to create output delegate".to_string(),
                 ));
             }
-            eprintln!("ScreenCaptureKit: Output delegate created");
+            tracing::debug!("ScreenCaptureKit: Output delegate created");
 
             let output_delegate = Retained::retain(output_delegate)
                 .ok_or_else(|| AudioError::PermissionDenied("Failed to retain delegate".to_string()))?;
 
+            tracing::debug!("ScreenCaptureKit: Creating stream...");
+            let stream_class = class!(SCStream);
+
+            // Allocate and initialize the stream
+            let stream_alloc: *mut AnyObject = msg_send![stream_class, alloc];
+            let stream: *mut AnyObject = msg_send![
+                stream_alloc,
+                initWithFilter: filter,
+                configuration: config,
+                delegate: &*output_delegate
+            ];
+
+            if stream.is_null() {
+                tracing::error!("ScreenCaptureKit: Failed to create stream");
+                return Err(AudioError::PermissionDenied("Failed to create stream".to_string()));
+            }
+            tracing::debug!("ScreenCaptureKit: Stream created successfully");
+
+            let stream = Retained::retain(stream)
+                .ok_or_else(|| AudioError::PermissionDenied("Failed to retain stream".to_string()))?;
+
             // Create a dispatch queue for audio callbacks
             let queue_label = b"com.note67.screencapture.audio\0".as_ptr() as *const i8;
             unsafe extern "C" {
                 fn dispatch_queue_create(label: *const i8, attr: *const c_void) -> *mut c_void;
             }
             let queue = dispatch_queue_create(queue_label, std::ptr::null());
-            eprintln!("ScreenCaptureKit: Dispatch queue created");
+            tracing::debug!("ScreenCaptureKit: Dispatch queue created");
 
             // Add output to stream - SCStreamOutputType.audio = 1
-            eprintln!("ScreenCaptureKit: Adding stream output...");
+            tracing::debug!("ScreenCaptureKit: Adding stream output...");
             let mut error: *mut NSError = std::ptr::null_mut();
             let success: Bool = msg_send![
                 &*stream,
@@ -527,33 +831,12 @@ impl MacOSSystemAudioCapture {
                 } else {
                     "Unknown".to_string()
                 };
-                eprintln!("ScreenCaptureKit: Failed to add stream output: {}", error_msg);
+                tracing::error!("ScreenCaptureKit: Failed to add stream output: {}", error_msg);
                 return Err(AudioError::PermissionDenied(
                     format!("Failed to add stream output: {}", error_msg),
                 ));
             }
-            eprintln!("ScreenCaptureKit: Stream output added successfully");
-
-            // Initialize the WAV writer
-            let spec = WavSpec {
-                channels: 2,
-                sample_rate: 48000,
-                bits_per_sample: 16,
-                sample_format: hound::SampleFormat::Int,
-            };
-
-            let writer = WavWriter::create(&output_path, spec)
-                .map_err(|e| AudioError::IoError(std::io::Error::other(e.to_string())))?;
-
-            // Set up global audio writer state
-            {
-                let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
-                *guard = Some(AudioWriterState {
-                    writer: Some(writer),
-                    output_path: output_path.clone(),
-                    is_active: true,
-                });
-            }
+            tracing::debug!("ScreenCaptureKit: Stream output added successfully");
 
             // Start capturing
             use std::sync::mpsc;
@@ -577,7 +860,7 @@ impl MacOSSystemAudioCapture {
                     } else {
                         "Unknown error".to_string()
                     };
-                    eprintln!("ScreenCaptureKit error: {}", error_msg);
+                    tracing::error!("ScreenCaptureKit error: {}", error_msg);
                     let _ = tx.send(Err(AudioError::PermissionDenied(format!(
                         "Failed to start capture: {}",
                         error_msg
@@ -590,7 +873,7 @@ impl MacOSSystemAudioCapture {
             rx.recv_timeout(std::time::Duration::from_secs(10))
                 .map_err(|_| AudioError::PermissionDenied("Timeout starting capture".to_string()))??;
 
-            eprintln!("ScreenCaptureKit: Capture started successfully!");
+            tracing::info!("ScreenCaptureKit: Capture started successfully!");
 
             Ok(CaptureSession {
                 stream,
@@ -606,7 +889,7 @@ impl MacOSSystemAudioCapture {
             guard.take()
         };
 
-        let output_path = if let Some(session) = session {
+        if let Some(session) = session {
             unsafe {
                 // Stop the stream
                 use std::sync::mpsc;
@@ -622,22 +905,77 @@ impl MacOSSystemAudioCapture {
                 let _ = rx.recv_timeout(std::time::Duration::from_secs(5));
             }
 
-            // Finalize WAV file and get path
-            let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
-            match guard.take() { Some(mut state) => {
-                state.is_active = false;
-                if let Some(writer) = state.writer.take() {
-                    let _ = writer.finalize();
-                }
-                Some(state.output_path)
-            } _ => {
-                None
-            }}
+            // Finalize the recording file and get path
+            close_writer()
         } else {
-            None
-        };
+            Ok(None)
+        }
+    }
 
-        Ok(output_path)
+    /// Set up a stream of the current shareable content and start it
+    fn open_session() -> Result<CaptureSession, AudioError> {
+        let content = Self::get_shareable_content_sync()?;
+        let filter = Self::create_audio_filter(&content)?;
+        let config = Self::create_stream_config()?;
+        Self::start_capture_session(&filter, &config)
+    }
+
+    /// Watch for ScreenCaptureKit stopping the stream on its own while capturing: report
+    /// it, start a new stream into the same file as soon as one will start, and fill the
+    /// gap with silence
+    fn supervise(session: Arc<Mutex<Option<CaptureSession>>>, is_capturing: Arc<AtomicBool>) {
+        let mut reported = false;
+        let mut last_attempt: Option<Instant> = None;
+        while is_capturing.load(Ordering::SeqCst) {
+            thread::sleep(SUPERVISE_INTERVAL);
+
+            let interruption = INTERRUPTION
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
+            let Some((since, reason)) = interruption else {
+                continue;
+            };
+            if !reported {
+                system_audio::report_interruption(&reason, true, 0);
+                reported = true;
+            }
+            if last_attempt.is_some_and(|at| at.elapsed() < RESTART_INTERVAL) {
+                continue;
+            }
+            last_attempt = Some(Instant::now());
+
+            let restarted = match Self::open_session() {
+                Ok(restarted) => restarted,
+                Err(e) => {
+                    tracing::debug!("ScreenCaptureKit: Stream can't restart yet: {}", e);
+                    continue;
+                }
+            };
+            let Ok(mut guard) = session.lock() else {
+                return;
+            };
+            if !is_capturing.load(Ordering::SeqCst) {
+                // Stopped meanwhile
+                unsafe {
+                    let block = block2::RcBlock::new(|_error: *mut NSError| {});
+                    let _: () =
+                        msg_send![&*restarted.stream, stopCaptureWithCompletionHandler: &*block];
+                }
+                return;
+            }
+            *guard = Some(restarted);
+            *INTERRUPTION.lock().unwrap_or_else(PoisonError::into_inner) = None;
+            let missed = since.elapsed();
+            write_silence(missed);
+            tracing::info!(
+                "ScreenCaptureKit: Stream restarted after {:.1}s",
+                missed.as_secs_f64()
+            );
+            system_audio::report_interruption(&reason, false, missed.as_secs());
+            reported = false;
+            last_attempt = None;
+        }
     }
 }
 
@@ -665,15 +1003,32 @@ impl SystemAudioCapture for MacOSSystemAudioCapture {
 
         Self::check_availability()?;
 
-        // Get shareable content
-        let content = Self::get_shareable_content_sync()?;
+        // Initialize the file writer
+        let format = capture_format::active().system_format();
+        let writer = AudioFileWriter::create(&output_path, format.spec())
+            .map_err(|e| AudioError::IoError(std::io::Error::other(e.to_string())))?;
 
-        // Create filter and configuration
-        let filter = Self::create_audio_filter(&content)?;
-        let config = Self::create_stream_config()?;
+        // Set up global audio writer state
+        system_audio::reset_system_clock();
+        {
+            let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+            *guard = Some(AudioWriterState {
+                writer: Some(writer),
+                output_path,
+                is_active: true,
+                format,
+            });
+        }
+        *INTERRUPTION.lock().unwrap_or_else(PoisonError::into_inner) = None;
 
         // Start capture session with output delegate
-        let session = self.start_capture_session(&filter, &config, output_path)?;
+        let session = match Self::open_session() {
+            Ok(session) => session,
+            Err(e) => {
+                let _ = close_writer();
+                return Err(e);
+            }
+        };
 
         // Store session
         {
@@ -682,18 +1037,27 @@ impl SystemAudioCapture for MacOSSystemAudioCapture {
         }
 
         self.is_capturing.store(true, Ordering::SeqCst);
+
+        let session = self.session.clone();
+        let is_capturing = self.is_capturing.clone();
+        thread::spawn(move || Self::supervise(session, is_capturing));
         Ok(())
     }
 
     fn stop(&self) -> SystemAudioResult<Option<PathBuf>> {
-        if !self.is_capturing.load(Ordering::SeqCst) {
+        // Cleared first, so an interrupted stream isn't restarted behind our back
+        if !self.is_capturing.swap(false, Ordering::SeqCst) {
             return Ok(None);
         }
 
-        let output_path = self.stop_capture_session()?;
+        self.stop_capture_session()
+    }
 
-        self.is_capturing.store(false, Ordering::SeqCst);
-        Ok(output_path)
+    fn rotate(&self, output_path: PathBuf) -> SystemAudioResult<()> {
+        if !self.is_capturing.load(Ordering::SeqCst) {
+            return Err(AudioError::NotRecording);
+        }
+        switch_writer(output_path)
     }
 
     fn is_capturing(&self) -> bool {
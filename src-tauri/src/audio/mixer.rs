@@ -1,219 +1,414 @@
-//! Audio mixing utilities for combining multiple WAV files.
+//! Audio mixing utilities for combining multiple recording files.
+//!
+//! System audio usually arrives much louder than the mic, so before mixing each source can
+//! be brought to the same loudness (measured after EBU R128, see `loudness`) and then
+//! raised or lowered by a gain of its own from the settings.
+//!
+//! Recordings are streamed through the mix a packet at a time rather than loaded whole, as
+//! a long meeting runs to gigabytes of samples.
 
 use std::path::Path;
 
-use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use hound::{SampleFormat, WavSpec};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
 
+use crate::audio::clock::Alignment;
+use crate::audio::converter;
+use crate::audio::encoder::AudioFileWriter;
+use crate::audio::loudness;
 use crate::audio::AudioError;
+use crate::db::Database;
 
-/// Simple linear interpolation resampling
-fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-    if from_rate == to_rate {
-        return samples.to_vec();
-    }
+/// "true" makes the playback file stereo, mic left and system audio right
+pub const SETTING_STEREO_SPLIT: &str = "stereo_split_playback";
 
-    let ratio = from_rate as f64 / to_rate as f64;
-    let new_len = (samples.len() as f64 / ratio).ceil() as usize;
-    let mut resampled = Vec::with_capacity(new_len);
-
-    for i in 0..new_len {
-        let src_idx = i as f64 * ratio;
-        let idx_floor = src_idx.floor() as usize;
-        let idx_ceil = (idx_floor + 1).min(samples.len() - 1);
-        let frac = src_idx - idx_floor as f64;
-
-        let sample = if idx_floor < samples.len() {
-            let s1 = samples[idx_floor];
-            let s2 = samples.get(idx_ceil).copied().unwrap_or(s1);
-            s1 + (s2 - s1) * frac as f32
-        } else {
-            0.0
-        };
-        resampled.push(sample);
-    }
+/// "true" brings the mic and system audio to the same loudness before they are mixed
+pub const SETTING_NORMALIZE: &str = "normalize_playback_loudness";
+
+/// Gain for the mic in the playback file, in dB
+pub const SETTING_MIC_GAIN: &str = "playback_mic_gain_db";
+
+/// Gain for system audio in the playback file, in dB
+pub const SETTING_SYSTEM_GAIN: &str = "playback_system_gain_db";
+
+/// Loudness each source is normalized to, in LUFS
+const TARGET_LUFS: f64 = -16.0;
 
-    resampled
+/// Normalizing never boosts a quiet source by more than this, so a mic that picked up
+/// little but room noise isn't turned into hiss
+const MAX_BOOST_DB: f64 = 20.0;
+
+/// Mixes of recordings at least this long (the two together) report their progress
+const LONG_MIX_MS: u64 = 10 * 60 * 1000;
+
+/// Payload of "audio-mix-progress"
+#[derive(Debug, Clone, Serialize)]
+pub struct MixProgress {
+    pub note_id: String,
+    /// Fraction done, from 0 to 1
+    pub progress: f32,
 }
 
-/// Mix two WAV files into a single output file.
-///
-/// Both input files should have the same sample rate and channel count.
-/// If they differ, the function will use the first file's format and resample
-/// or remix the second file as needed.
-///
-/// The mixing is done by averaging samples from both sources to prevent clipping.
-pub fn mix_wav_files(
-    file_a: &Path,
-    file_b: &Path,
-    output: &Path,
-) -> Result<(), AudioError> {
-    // Open both input files
-    let mut reader_a = WavReader::open(file_a)?;
-    let mut reader_b = WavReader::open(file_b)?;
+/// A progress callback for `mix_wav_files` that emits "audio-mix-progress" for a note
+pub fn progress_events(app: AppHandle, note_id: String) -> impl FnMut(f32) {
+    move |progress| {
+        let payload = MixProgress {
+            note_id: note_id.clone(),
+            progress,
+        };
+        let _ = app.emit("audio-mix-progress", payload);
+    }
+}
 
-    let spec_a = reader_a.spec();
-    let spec_b = reader_b.spec();
+/// How two recordings become one playback file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixMode {
+    /// Averaged together, in the first file's channels
+    Average,
+    /// Each mixed down to mono, the first on the left and the second on the right, so
+    /// the two sides of a call can be told apart
+    StereoSplit,
+}
 
-    // Use file A's spec for output, but ensure we handle format differences
-    let output_spec = WavSpec {
-        channels: spec_a.channels,
-        sample_rate: spec_a.sample_rate,
-        bits_per_sample: 16,
-        sample_format: SampleFormat::Int,
-    };
+/// The mode the settings ask for
+pub fn mix_mode(app: &AppHandle) -> MixMode {
+    let split = app
+        .try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_STEREO_SPLIT).ok().flatten())
+        .is_some_and(|v| v == "true");
+    if split {
+        MixMode::StereoSplit
+    } else {
+        MixMode::Average
+    }
+}
 
-    let mut writer = WavWriter::create(output, output_spec)?;
+/// How loud each of the two recordings goes into the mix
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MixLevels {
+    /// Bring both to `TARGET_LUFS` first
+    pub normalize: bool,
+    /// Gain for file A (the mic) in dB, after normalizing
+    pub gain_a_db: f32,
+    /// Gain for file B (system audio) in dB, after normalizing
+    pub gain_b_db: f32,
+}
 
-    // Read samples based on the format
-    match (spec_a.sample_format, spec_b.sample_format) {
-        (SampleFormat::Int, SampleFormat::Int) => {
-            mix_int_samples(&mut reader_a, &mut reader_b, &mut writer, spec_a, spec_b)?;
-        }
-        (SampleFormat::Float, SampleFormat::Float) => {
-            mix_float_samples(&mut reader_a, &mut reader_b, &mut writer, spec_a, spec_b)?;
-        }
-        _ => {
-            // Mixed formats - convert to float, mix, convert back
-            mix_mixed_samples(&mut reader_a, &mut reader_b, &mut writer, spec_a, spec_b)?;
+impl MixLevels {
+    /// Linear gain for a source measured at `loudness` (None when unmeasured or silent),
+    /// with `gain_db` on top
+    fn gain(&self, loudness: Option<f64>, gain_db: f32) -> f32 {
+        let gain = loudness::db_to_gain(gain_db as f64);
+        match loudness {
+            Some(measured) if self.normalize => {
+                gain * loudness::gain_to(measured, TARGET_LUFS, MAX_BOOST_DB)
+            }
+            _ => gain,
         }
     }
-
-    writer.finalize()?;
-    Ok(())
 }
 
-fn mix_int_samples<R1: std::io::Read, R2: std::io::Read, W: std::io::Write + std::io::Seek>(
-    reader_a: &mut WavReader<R1>,
-    reader_b: &mut WavReader<R2>,
-    writer: &mut WavWriter<W>,
-    spec_a: WavSpec,
-    spec_b: WavSpec,
-) -> Result<(), AudioError> {
-    // Calculate scale factor based on bit depth
-    let scale_a = (1 << (spec_a.bits_per_sample - 1)) as f32;
-    let scale_b = (1 << (spec_b.bits_per_sample - 1)) as f32;
-
-    // Convert to float for processing (normalized to -1.0 to 1.0)
-    let samples_a: Vec<f32> = reader_a
-        .samples::<i32>()
-        .filter_map(|s| s.ok())
-        .map(|s| s as f32 / scale_a)
-        .collect();
-    let samples_b: Vec<f32> = reader_b
-        .samples::<i32>()
-        .filter_map(|s| s.ok())
-        .map(|s| s as f32 / scale_b)
-        .collect();
-
-    // Handle different channel counts
-    let samples_a = normalize_channels_f32(&samples_a, spec_a.channels, spec_a.channels);
-    let samples_b = normalize_channels_f32(&samples_b, spec_b.channels, spec_a.channels);
-
-    // Resample if needed to match sample rates
-    let samples_b = resample(&samples_b, spec_b.sample_rate, spec_a.sample_rate);
-
-    let max_len = samples_a.len().max(samples_b.len());
-
-    for i in 0..max_len {
-        let a = samples_a.get(i).copied().unwrap_or(0.0);
-        let b = samples_b.get(i).copied().unwrap_or(0.0);
-
-        // Mix by averaging to prevent clipping
-        let mixed = (a + b) / 2.0;
-
-        // Convert to i16
-        let sample = (mixed * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-        writer.write_sample(sample)?;
+/// The levels the settings ask for
+pub fn mix_levels(app: &AppHandle) -> MixLevels {
+    let Some(db) = app.try_state::<Database>() else {
+        return MixLevels::default();
+    };
+    let setting = |key| db.get_setting(key).ok().flatten();
+    let gain_db = |key| {
+        setting(key)
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(0.0)
+    };
+    MixLevels {
+        normalize: setting(SETTING_NORMALIZE).is_none_or(|v| v == "true"),
+        gain_a_db: gain_db(SETTING_MIC_GAIN),
+        gain_b_db: gain_db(SETTING_SYSTEM_GAIN),
     }
+}
 
-    Ok(())
+/// One of the recordings being mixed, decoded a packet at a time in the mix's channels
+struct Source {
+    decoder: converter::StreamDecoder,
+    channels: u16,
 }
 
-fn mix_float_samples<R1: std::io::Read, R2: std::io::Read, W: std::io::Write + std::io::Seek>(
-    reader_a: &mut WavReader<R1>,
-    reader_b: &mut WavReader<R2>,
-    writer: &mut WavWriter<W>,
-    spec_a: WavSpec,
-    spec_b: WavSpec,
-) -> Result<(), AudioError> {
-    let samples_a: Vec<f32> = reader_a.samples::<f32>().filter_map(|s| s.ok()).collect();
-    let samples_b: Vec<f32> = reader_b.samples::<f32>().filter_map(|s| s.ok()).collect();
+impl Source {
+    fn open(path: &Path, channels: u16) -> Result<Self, AudioError> {
+        Ok(Self {
+            decoder: converter::StreamDecoder::open(path)?,
+            channels,
+        })
+    }
 
-    // Handle different channel counts
-    let samples_a = normalize_channels_f32(&samples_a, spec_a.channels, spec_a.channels);
-    let samples_b = normalize_channels_f32(&samples_b, spec_b.channels, spec_a.channels);
+    fn rate(&self) -> u32 {
+        self.decoder.sample_rate().max(1)
+    }
 
-    // Resample if needed to match sample rates
-    let samples_b = resample(&samples_b, spec_b.sample_rate, spec_a.sample_rate);
+    /// The next packet into `out`, returning the milliseconds it holds; None at the end
+    fn read(&mut self, out: &mut Vec<f32>) -> Option<f64> {
+        let (from, to, rate) = (self.decoder.channels(), self.channels, self.rate());
+        let samples = self.decoder.next_samples()?;
+        out.clear();
+        out.extend(normalize_channels_f32(samples, from, to));
+        Some((samples.len() / from.max(1) as usize) as f64 * 1000.0 / rate as f64)
+    }
+}
 
-    let max_len = samples_a.len().max(samples_b.len());
+/// Brings file B onto file A's timeline as it streams in: output frame `i` is input frame
+/// `start + i * step`, interpolated, or silence where that falls before the first frame.
+/// With an alignment from the two captures' clocks B is placed and stretched as it says,
+/// else just resampled to A's rate.
+struct Retimer {
+    channels: usize,
+    step: f64,
+    start: f64,
+    /// Resampling runs on into the last frame, where alignment stops at it
+    tail: bool,
+    /// The next output frame
+    next_out: u64,
+    /// Input frames still needed, the first being input frame `base`
+    pending: Vec<f32>,
+    base: u64,
+}
 
-    for i in 0..max_len {
-        let a = samples_a.get(i).copied().unwrap_or(0.0);
-        let b = samples_b.get(i).copied().unwrap_or(0.0);
+impl Retimer {
+    fn new(channels: u16, rate_b: u32, rate_a: u32, alignment: Option<Alignment>) -> Self {
+        let (step, start, tail) = match alignment {
+            Some(alignment) if alignment.step > 0.0 => (alignment.step, alignment.start, false),
+            _ => (rate_b as f64 / rate_a.max(1) as f64, 0.0, true),
+        };
+        Self {
+            channels: channels.max(1) as usize,
+            step,
+            start,
+            tail,
+            next_out: 0,
+            pending: Vec::new(),
+            base: 0,
+        }
+    }
 
-        // Mix by averaging
-        let mixed = (a + b) / 2.0;
+    /// Retime more of the input into `out`
+    fn push(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.pending.extend_from_slice(input);
+        self.emit(out, false);
+    }
 
-        // Convert to i16
-        let sample = (mixed * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-        writer.write_sample(sample)?;
+    /// Retime what's left once the input has ended
+    fn finish(&mut self, out: &mut Vec<f32>) {
+        self.emit(out, true);
     }
 
-    Ok(())
+    fn emit(&mut self, out: &mut Vec<f32>, ended: bool) {
+        let channels = self.channels;
+        let frames = self.base + (self.pending.len() / channels) as u64;
+        if frames == 0 {
+            return;
+        }
+        loop {
+            let position = self.start + self.next_out as f64 * self.step;
+            if position < 0.0 {
+                out.extend(std::iter::repeat_n(0.0, channels));
+                self.next_out += 1;
+                continue;
+            }
+            let index = position as u64;
+            let past_end = if !ended {
+                // Interpolating needs the frame after
+                index + 1 >= frames
+            } else if self.tail {
+                position >= frames as f64
+            } else {
+                position > (frames - 1) as f64
+            };
+            if past_end {
+                break;
+            }
+            let next = (index + 1).min(frames - 1);
+            let frac = (position - index as f64) as f32;
+            let at = |frame: u64| (frame - self.base) as usize * channels;
+            let (i, n) = (at(index), at(next));
+            for c in 0..channels {
+                let (s1, s2) = (self.pending[i + c], self.pending[n + c]);
+                out.push(s1 + (s2 - s1) * frac);
+            }
+            self.next_out += 1;
+        }
+
+        // Drop the frames no later output needs
+        let position = self.start + self.next_out as f64 * self.step;
+        let keep = (position.max(0.0) as u64).clamp(self.base, frames);
+        self.pending.drain(..(keep - self.base) as usize * channels);
+        self.base = keep;
+    }
 }
 
-fn mix_mixed_samples<R1: std::io::Read, R2: std::io::Read, W: std::io::Write + std::io::Seek>(
-    reader_a: &mut WavReader<R1>,
-    reader_b: &mut WavReader<R2>,
-    writer: &mut WavWriter<W>,
-    spec_a: WavSpec,
-    spec_b: WavSpec,
-) -> Result<(), AudioError> {
-    // Calculate scale factors based on bit depth
-    let scale_a = (1 << (spec_a.bits_per_sample - 1)) as f32;
-    let scale_b = (1 << (spec_b.bits_per_sample - 1)) as f32;
+/// Reports how much of a long mix's input has been decoded, a percent at a time
+struct Progress<'a> {
+    total_ms: f64,
+    done_ms: f64,
+    reported: f32,
+    report: &'a mut dyn FnMut(f32),
+}
 
-    // Convert both to float for mixing
-    let samples_a: Vec<f32> = if spec_a.sample_format == SampleFormat::Float {
-        reader_a.samples::<f32>().filter_map(|s| s.ok()).collect()
-    } else {
-        reader_a
-            .samples::<i32>()
-            .filter_map(|s| s.ok())
-            .map(|s| s as f32 / scale_a)
-            .collect()
-    };
+impl Progress<'_> {
+    fn advance(&mut self, ms: f64) {
+        self.done_ms += ms;
+        if self.total_ms < LONG_MIX_MS as f64 {
+            return;
+        }
+        let fraction = (self.done_ms / self.total_ms).min(1.0) as f32;
+        if fraction - self.reported >= 0.01 {
+            self.reported = fraction;
+            (self.report)(fraction);
+        }
+    }
+}
 
-    let samples_b: Vec<f32> = if spec_b.sample_format == SampleFormat::Float {
-        reader_b.samples::<f32>().filter_map(|s| s.ok()).collect()
-    } else {
-        reader_b
-            .samples::<i32>()
-            .filter_map(|s| s.ok())
-            .map(|s| s as f32 / scale_b)
-            .collect()
-    };
+/// Decode files A and B side by side in `channels` channels, B brought onto A's timeline,
+/// handing `each` equal runs of frames from both. The shorter is padded with silence to
+/// the end of the longer. Only a packet or so of either is held at a time.
+fn stream_pair(
+    file_a: &Path,
+    file_b: &Path,
+    channels: u16,
+    alignment: Option<Alignment>,
+    progress: &mut Progress,
+    mut each: impl FnMut(&[f32], &[f32]) -> Result<(), AudioError>,
+) -> Result<(), AudioError> {
+    let mut a = Source::open(file_a, channels)?;
+    let mut b = Source::open(file_b, channels)?;
+    let mut retimer = Retimer::new(channels, b.rate(), a.rate(), alignment);
+
+    let (mut pending_a, mut pending_b) = (Vec::new(), Vec::new());
+    let (mut ended_a, mut ended_b) = (false, false);
+    let mut packet = Vec::new();
+    loop {
+        // Top up whichever has less waiting
+        if !ended_a && (ended_b || pending_a.len() <= pending_b.len()) {
+            match a.read(&mut packet) {
+                Some(ms) => {
+                    pending_a.extend_from_slice(&packet);
+                    progress.advance(ms);
+                }
+                None => ended_a = true,
+            }
+        } else if !ended_b {
+            match b.read(&mut packet) {
+                Some(ms) => {
+                    retimer.push(&packet, &mut pending_b);
+                    progress.advance(ms);
+                }
+                None => {
+                    retimer.finish(&mut pending_b);
+                    ended_b = true;
+                }
+            }
+        }
 
-    // Handle different channel counts
-    let samples_a = normalize_channels_f32(&samples_a, spec_a.channels, spec_a.channels);
-    let samples_b = normalize_channels_f32(&samples_b, spec_b.channels, spec_a.channels);
+        let ready = match (ended_a, ended_b) {
+            (false, false) => pending_a.len().min(pending_b.len()),
+            (true, false) => pending_b.len(),
+            (false, true) => pending_a.len(),
+            (true, true) => pending_a.len().max(pending_b.len()),
+        };
+        if ready > 0 {
+            pending_a.resize(pending_a.len().max(ready), 0.0);
+            pending_b.resize(pending_b.len().max(ready), 0.0);
+            each(&pending_a[..ready], &pending_b[..ready])?;
+            pending_a.drain(..ready);
+            pending_b.drain(..ready);
+        }
+        if ended_a && ended_b {
+            return Ok(());
+        }
+    }
+}
 
-    // Resample if needed to match sample rates
-    let samples_b = resample(&samples_b, spec_b.sample_rate, spec_a.sample_rate);
+fn to_i16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
 
-    let max_len = samples_a.len().max(samples_b.len());
+/// Mix two recordings into a single output file, in file A's rate.
+///
+/// The mixing is done by averaging samples from both sources to prevent clipping, in the
+/// first file's channels, or with `MixMode::StereoSplit` by putting each on a channel of
+/// its own. Recordings may be WAV or FLAC, and the output is written in the format its
+/// extension names.
+///
+/// With an `alignment` from the two captures' clocks, file B is shifted and stretched to
+/// line up with file A, so recordings from devices whose clocks disagree stay in sync.
+/// Each file is scaled to its `levels` before mixing.
+///
+/// Both files are streamed, so memory use doesn't grow with their length; normalizing
+/// takes a first pass to measure them. Mixes longer than `LONG_MIX_MS` report the
+/// fraction done to `on_progress`.
+pub fn mix_wav_files(
+    file_a: &Path,
+    file_b: &Path,
+    output: &Path,
+    mode: MixMode,
+    levels: MixLevels,
+    alignment: Option<Alignment>,
+    on_progress: &mut dyn FnMut(f32),
+) -> Result<(), AudioError> {
+    let (rate, channels_a, duration_a) = {
+        let a = converter::StreamDecoder::open(file_a)?;
+        (a.sample_rate().max(1), a.channels().max(1), a.duration_ms())
+    };
+    let duration_b = converter::StreamDecoder::open(file_b)?.duration_ms();
+    let channels = match mode {
+        MixMode::Average => channels_a,
+        MixMode::StereoSplit => 1,
+    };
 
-    for i in 0..max_len {
-        let a = samples_a.get(i).copied().unwrap_or(0.0);
-        let b = samples_b.get(i).copied().unwrap_or(0.0);
+    let passes = if levels.normalize { 2.0 } else { 1.0 };
+    let mut progress = Progress {
+        total_ms: passes * (duration_a.unwrap_or(0) + duration_b.unwrap_or(0)) as f64,
+        done_ms: 0.0,
+        reported: 0.0,
+        report: on_progress,
+    };
 
-        let mixed = (a + b) / 2.0;
-        let sample = (mixed * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-        writer.write_sample(sample)?;
+    let (mut loudness_a, mut loudness_b) = (None, None);
+    if levels.normalize {
+        let mut meter_a = loudness::LoudnessMeter::new(channels, rate);
+        let mut meter_b = loudness::LoudnessMeter::new(channels, rate);
+        stream_pair(file_a, file_b, channels, alignment, &mut progress, |a, b| {
+            meter_a.push(a);
+            meter_b.push(b);
+            Ok(())
+        })?;
+        (loudness_a, loudness_b) = (meter_a.integrated(), meter_b.integrated());
     }
+    let gain_a = levels.gain(loudness_a, levels.gain_a_db);
+    let gain_b = levels.gain(loudness_b, levels.gain_b_db);
 
-    Ok(())
+    let output_spec = WavSpec {
+        channels: match mode {
+            MixMode::Average => channels,
+            MixMode::StereoSplit => 2,
+        },
+        sample_rate: rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = AudioFileWriter::create(output, output_spec)?;
+    stream_pair(file_a, file_b, channels, alignment, &mut progress, |a, b| {
+        for (a, b) in a.iter().zip(b) {
+            let (a, b) = (a * gain_a, b * gain_b);
+            match mode {
+                MixMode::Average => writer.write_sample(to_i16((a + b) / 2.0))?,
+                MixMode::StereoSplit => {
+                    writer.write_sample(to_i16(a))?;
+                    writer.write_sample(to_i16(b))?;
+                }
+            }
+        }
+        Ok(())
+    })?;
+    writer.finalize()
 }
 
 /// Normalize channel count - convert between mono/stereo as needed (i32 version)
@@ -297,4 +492,119 @@ mod tests {
         let mono = normalize_channels(&stereo, 2, 1);
         assert_eq!(mono, vec![150, 350]);
     }
+
+    #[test]
+    fn test_align() {
+        // B started two frames after A and runs a little slow
+        let b: Vec<f32> = (0..100).flat_map(|i| [i as f32, -(i as f32)]).collect();
+        let alignment = Alignment {
+            step: 0.9,
+            start: -2.0,
+        };
+        let mut retimer = Retimer::new(2, 16000, 16000, Some(alignment));
+        let mut aligned = Vec::new();
+        for packet in b.chunks(14) {
+            retimer.push(packet, &mut aligned);
+        }
+        retimer.finish(&mut aligned);
+        assert!(aligned[..6].iter().all(|s| *s == 0.0));
+        assert!((aligned[2 * 12] - 8.8).abs() < 1e-4);
+        assert!((aligned[2 * 12 + 1] + 8.8).abs() < 1e-4);
+        // Runs to B's last frame
+        assert_eq!(aligned.len(), 2 * 113);
+        assert!((aligned[aligned.len() - 2] - 98.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_streamed_resample() {
+        let b: Vec<f32> = (0..4410).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut retimer = Retimer::new(1, 44100, 16000, None);
+        let mut streamed = Vec::new();
+        for packet in b.chunks(1152) {
+            retimer.push(packet, &mut streamed);
+        }
+        retimer.finish(&mut streamed);
+        let whole = converter::resample(&b, 44100, 16000);
+        assert_eq!(streamed.len(), whole.len());
+        assert!(streamed.iter().zip(&whole).all(|(s, w)| (s - w).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_levels() {
+        let sine = |amplitude: f32| -> Vec<f32> {
+            (0..16000 * 3)
+                .map(|i| (i as f32 * 997.0 / 16000.0 * std::f32::consts::TAU).sin() * amplitude)
+                .collect()
+        };
+        let rms = |s: &[f32]| (s.iter().map(|s| s * s).sum::<f32>() / s.len() as f32).sqrt();
+        let apply = |levels: MixLevels, a: &mut [f32], b: &mut [f32]| {
+            for (samples, gain_db) in [(a, levels.gain_a_db), (b, levels.gain_b_db)] {
+                let measured = loudness::integrated_loudness(samples, 1, 16000);
+                let gain = levels.gain(measured, gain_db);
+                samples.iter_mut().for_each(|s| *s *= gain);
+            }
+        };
+        let (mut quiet, mut loud) = (sine(0.05), sine(0.5));
+
+        let levels = MixLevels {
+            normalize: true,
+            ..Default::default()
+        };
+        apply(levels, &mut quiet, &mut loud);
+        assert!((rms(&quiet) / rms(&loud) - 1.0).abs() < 0.01);
+
+        // Gain comes on top, 6dB being about double
+        let levels = MixLevels {
+            normalize: false,
+            gain_a_db: 6.0,
+            gain_b_db: 0.0,
+        };
+        let (mut a, mut b) = (sine(0.1), sine(0.1));
+        apply(levels, &mut a, &mut b);
+        assert!((rms(&a) / rms(&b) - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_mix_flac() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec = |channels| WavSpec {
+            channels,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mic = dir.path().join("n_mic.flac");
+        let system = dir.path().join("n_system.flac");
+        let mut writer = AudioFileWriter::create(&mic, spec(1)).unwrap();
+        for _ in 0..8000 {
+            writer.write_sample(10000).unwrap();
+        }
+        writer.finalize().unwrap();
+        let mut writer = AudioFileWriter::create(&system, spec(2)).unwrap();
+        for _ in 0..16000 {
+            writer.write_sample(-2000).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let output = dir.path().join("n.flac");
+        let levels = MixLevels::default();
+        let average = MixMode::Average;
+        mix_wav_files(&mic, &system, &output, average, levels, None, &mut |_| {}).unwrap();
+        let mixed = converter::decode(&output).unwrap();
+
+        let split = dir.path().join("n_split.wav");
+        let stereo = MixMode::StereoSplit;
+        mix_wav_files(&mic, &system, &split, stereo, levels, None, &mut |_| {}).unwrap();
+        let split = converter::decode(&split).unwrap();
+
+        assert_eq!(mixed.channels, 1);
+        assert_eq!(mixed.samples.len(), 8000);
+        assert!((mixed.samples[100] - 4000.0 / 32768.0).abs() < 0.001);
+
+        // Mic on the left, system on the right, each at full level
+        assert_eq!(split.channels, 2);
+        assert_eq!(split.samples.len(), 8000 * 2);
+        assert!((split.samples[200] - 10000.0 / 32768.0).abs() < 0.001);
+        assert!((split.samples[201] + 2000.0 / 32768.0).abs() < 0.001);
+    }
 }
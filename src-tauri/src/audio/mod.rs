@@ -1,34 +1,58 @@
 pub mod aec;
+pub mod calibration;
+pub mod capture_format;
+pub mod chapters;
+pub mod clock;
+pub mod compression;
 pub mod converter;
+pub mod denoise;
+pub mod devices;
+pub mod edit;
+pub mod encoder;
+pub mod exclusions;
+pub mod flac;
+pub mod headphones;
+pub mod loudness;
 pub mod mixer;
+pub mod monitoring;
+pub mod playback;
+pub mod quality;
 pub mod recorder;
+pub mod silence;
+pub mod stretch;
 pub mod system_audio;
+pub mod waveform;
+pub mod writer;
 
 #[cfg(target_os = "macos")]
 pub mod macos;
 
+#[cfg(target_os = "macos")]
+pub mod virtual_device;
+
 #[cfg(target_os = "windows")]
 pub mod windows;
 
 pub use mixer::mix_wav_files;
 pub use recorder::{
-    pause_recording, resume_recording, start_recording, stop_recording, RecordingPhase,
-    RecordingState,
+    pause_recording, resume_recording, rotate_recording, start_recording, stop_recording,
+    RecordingPhase, RecordingState,
+};
+pub use system_audio::{
+    create_system_audio_capture, is_system_audio_available, list_capturable_apps,
+    system_audio_level, CapturableApp, SystemAudioCapture,
 };
-pub use system_audio::{create_system_audio_capture, is_system_audio_available, SystemAudioCapture};
 
 // Re-export system audio buffer functions for live transcription
-#[cfg(target_os = "macos")]
-pub use macos::take_system_audio_samples;
-
-#[cfg(target_os = "windows")]
-pub use windows::take_system_audio_samples;
+pub use system_audio::{
+    system_audio_buffer_bytes, take_system_audio_dropped, take_system_audio_samples,
+};
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn take_system_audio_samples() -> Vec<f32> {
-    Vec::new()
-}
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
 
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -42,11 +66,94 @@ pub enum AudioSource {
     SystemAudio,
 }
 
+/// Bounded queue of samples handed from a capture callback to another thread: the two
+/// halves of a `ringbuf::HeapRb`. One thread pushes, so the producer's lock is never
+/// contended and pushing never waits or allocates; when the reader falls behind and the
+/// queue fills, new samples are dropped and counted rather than growing the buffer.
+pub struct SampleRing {
+    producer: Mutex<HeapProd<f32>>,
+    consumer: Mutex<HeapCons<f32>>,
+    capacity: usize,
+    /// Samples dropped because the queue was full, since `take_dropped`
+    dropped: AtomicU64,
+}
+
+impl SampleRing {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (producer, consumer) = HeapRb::new(capacity).split();
+        Self {
+            producer: Mutex::new(producer),
+            consumer: Mutex::new(consumer),
+            capacity,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Memory taken by the samples
+    pub fn bytes(&self) -> usize {
+        self.capacity * std::mem::size_of::<f32>()
+    }
+
+    /// Samples waiting to be taken
+    pub fn len(&self) -> usize {
+        self.consumer().occupied_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add as many of `samples` as fit, returning how many were dropped
+    pub fn push(&self, samples: &[f32]) -> usize {
+        let pushed = match self.producer.try_lock() {
+            Ok(mut producer) => producer.push_slice(samples),
+            Err(_) => 0,
+        };
+        let dropped = samples.len() - pushed;
+        if dropped > 0 {
+            self.dropped.fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+        dropped
+    }
+
+    /// Take every sample waiting
+    pub fn take(&self) -> Vec<f32> {
+        self.consumer().pop_iter().collect()
+    }
+
+    /// Take as many waiting samples as fit in `out`, returning how many. For output
+    /// callbacks: it neither allocates nor waits, taking nothing while the queue is cleared.
+    pub fn take_into(&self, out: &mut [f32]) -> usize {
+        match self.consumer.try_lock() {
+            Ok(mut consumer) => consumer.pop_slice(out),
+            Err(_) => 0,
+        }
+    }
+
+    /// Drop every sample waiting
+    pub fn clear(&self) {
+        self.consumer().clear();
+    }
+
+    /// Samples dropped since the last call
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    fn consumer(&self) -> std::sync::MutexGuard<'_, HeapCons<f32>> {
+        self.consumer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum AudioError {
     #[error("No input device available")]
     NoInputDevice,
 
+    #[error("No output device available")]
+    NoOutputDevice,
+
     #[error("Already recording")]
     AlreadyRecording,
 
@@ -86,3 +193,50 @@ pub enum AudioError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_ring() {
+        let ring = SampleRing::new(4);
+        assert_eq!(ring.push(&[1.0, 2.0, 3.0]), 0);
+        assert_eq!(ring.push(&[4.0, 5.0, 6.0]), 2);
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.take(), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(ring.take_dropped(), 2);
+        assert_eq!(ring.take_dropped(), 0);
+
+        ring.push(&[5.0, 6.0, 7.0]);
+        let mut out = [0.0; 2];
+        assert_eq!(ring.take_into(&mut out), 2);
+        assert_eq!(out, [5.0, 6.0]);
+        ring.clear();
+        assert!(ring.is_empty());
+        assert!(ring.take().is_empty());
+    }
+
+    #[test]
+    fn test_sample_ring_across_threads() {
+        let ring = std::sync::Arc::new(SampleRing::new(1024));
+        let producer = {
+            let ring = ring.clone();
+            std::thread::spawn(move || {
+                let mut pushed = 0;
+                while pushed < 10_000 {
+                    let chunk: Vec<f32> = (pushed..pushed + 100).map(|i| i as f32).collect();
+                    pushed += 100 - ring.push(&chunk);
+                    std::thread::yield_now();
+                }
+            })
+        };
+        let mut taken = Vec::new();
+        while taken.len() < 10_000 {
+            taken.extend(ring.take());
+        }
+        producer.join().unwrap();
+        // Dropped samples are pushed again, so everything arrives in order
+        assert!(taken.iter().enumerate().all(|(i, s)| *s == i as f32));
+    }
+}
@@ -0,0 +1,270 @@
+//! Input monitoring (sidetone): the mic played back to the default output while recording,
+//! for headsets that don't let the wearer hear their own voice. Off by default, and meant
+//! for headphones: on speakers the mic hears itself. The recording thread opens the output
+//! when monitoring is turned on and closes it when turned off, within a tenth of a second.
+//!
+//! Mic audio reaches the output through a `SampleRing`, mixed down to mono. The output
+//! callback converts the rate and, as the two devices' clocks drift apart, skips ahead
+//! whenever more than `MAX_LATENCY_MS` is waiting, so the delay stays short.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::audio::{AudioError, SampleRing};
+use crate::db::Database;
+
+/// Play the mic back while recording (off by default)
+pub const SETTING_ENABLED: &str = "input_monitoring_enabled";
+
+/// Most mic audio waiting to be played before the output skips ahead
+const MAX_LATENCY_MS: u32 = 60;
+
+/// Mono mic samples waiting; half a second at 48kHz, far more than is ever kept
+const RING_SAMPLES: usize = 24000;
+
+/// Samples the mic callback hands over at a time
+const PUSH_BATCH: usize = 256;
+
+/// Wait after failing to open the output before trying again
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether an output is open and taking mic audio
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static RING: LazyLock<SampleRing> = LazyLock::new(|| SampleRing::new(RING_SAMPLES));
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Hand interleaved mic audio to the output, when one is open. Called from the mic
+/// callback, so it never waits or allocates.
+pub(crate) fn push(data: &[f32], channels: usize) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let channels = channels.max(1);
+    let mut batch = [0.0_f32; PUSH_BATCH];
+    let mut len = 0;
+    for frame in data.chunks_exact(channels) {
+        batch[len] = frame.iter().sum::<f32>() / channels as f32;
+        len += 1;
+        if len == PUSH_BATCH {
+            RING.push(&batch);
+            len = 0;
+        }
+    }
+    RING.push(&batch[..len]);
+}
+
+/// Pulls mono mic audio from a ring at the output's rate, interpolating linearly
+struct Resampler {
+    /// Input samples per output frame
+    step: f64,
+    /// Position of the next output frame between `prev` (0) and `next` (1)
+    pos: f64,
+    prev: f32,
+    next: f32,
+    /// Samples taken from the ring and not yet used
+    buf: Vec<f32>,
+    at: usize,
+    len: usize,
+}
+
+impl Resampler {
+    fn new(input_rate: u32, output_rate: u32) -> Self {
+        Self {
+            step: input_rate as f64 / output_rate.max(1) as f64,
+            pos: 1.0,
+            prev: 0.0,
+            next: 0.0,
+            buf: vec![0.0; PUSH_BATCH * 4],
+            at: 0,
+            len: 0,
+        }
+    }
+
+    fn next_input(&mut self, ring: &SampleRing) -> Option<f32> {
+        if self.at == self.len {
+            self.len = ring.take_into(&mut self.buf);
+            self.at = 0;
+        }
+        let sample = self.buf[..self.len].get(self.at).copied()?;
+        self.at += 1;
+        Some(sample)
+    }
+
+    /// The next output sample; silence while the mic hasn't caught up
+    fn sample(&mut self, ring: &SampleRing) -> f32 {
+        while self.pos >= 1.0 {
+            let Some(sample) = self.next_input(ring) else {
+                return 0.0;
+            };
+            self.prev = self.next;
+            self.next = sample;
+            self.pos -= 1.0;
+        }
+        let value = self.prev + (self.next - self.prev) * self.pos as f32;
+        self.pos += self.step;
+        value
+    }
+
+    /// Drop what's waiting once it is more than `max` samples
+    fn skip_ahead(&mut self, ring: &SampleRing, max: usize) {
+        if ring.len() + (self.len - self.at) > max {
+            ring.clear();
+            self.at = self.len;
+        }
+    }
+}
+
+/// The open output. The stream has to stay on the thread that opened it.
+struct Sidetone {
+    _stream: Stream,
+}
+
+impl Sidetone {
+    /// Start playing mic audio recorded at `input_rate` on the default output
+    fn open(input_rate: u32) -> Result<Self, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoOutputDevice)?;
+        let config = device.default_output_config()?;
+        RING.clear();
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => open_stream::<f32>(&device, &config.config(), input_rate)?,
+            SampleFormat::I16 => open_stream::<i16>(&device, &config.config(), input_rate)?,
+            SampleFormat::U16 => open_stream::<u16>(&device, &config.config(), input_rate)?,
+            _ => return Err(AudioError::UnsupportedFormat),
+        };
+        stream.play()?;
+        ACTIVE.store(true, Ordering::Relaxed);
+        tracing::info!("Input monitoring on {}", device.name().unwrap_or_default());
+        Ok(Self { _stream: stream })
+    }
+}
+
+impl Drop for Sidetone {
+    fn drop(&mut self) {
+        ACTIVE.store(false, Ordering::Relaxed);
+        RING.clear();
+    }
+}
+
+fn open_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    input_rate: u32,
+) -> Result<Stream, AudioError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    let max_waiting = (input_rate * MAX_LATENCY_MS / 1000) as usize;
+    let mut resampler = Resampler::new(input_rate, config.sample_rate.0);
+    Ok(device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            resampler.skip_ahead(&RING, max_waiting);
+            for frame in data.chunks_mut(channels) {
+                let value = T::from_sample(resampler.sample(&RING).clamp(-1.0, 1.0));
+                frame.fill(value);
+            }
+        },
+        |err| tracing::error!("Input monitoring output error: {}", err),
+        None,
+    )?)
+}
+
+/// Keeps the output open while monitoring is on; owned by the recording thread
+#[derive(Default)]
+pub struct Monitor {
+    sidetone: Option<Sidetone>,
+    failed_at: Option<Instant>,
+}
+
+impl Monitor {
+    /// Open or close the output to follow the setting, for mic audio at `input_rate`
+    pub fn update(&mut self, input_rate: u32) {
+        if !is_enabled() {
+            self.sidetone = None;
+            self.failed_at = None;
+            return;
+        }
+        let waiting = self
+            .failed_at
+            .is_some_and(|at| at.elapsed() < RETRY_INTERVAL);
+        if self.sidetone.is_some() || waiting {
+            return;
+        }
+        match Sidetone::open(input_rate) {
+            Ok(sidetone) => self.sidetone = Some(sidetone),
+            Err(e) => {
+                tracing::warn!("Failed to start input monitoring: {}", e);
+                self.failed_at = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Load the setting and follow changes to it. Call once the database is managed.
+pub fn init(app: &AppHandle) {
+    let enabled = app
+        .state::<Database>()
+        .get_setting(SETTING_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload())
+            && key == SETTING_ENABLED
+        {
+            ENABLED.store(value == Some(Value::Bool(true)), Ordering::Relaxed);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resampler_doubles_rate() {
+        let ring = SampleRing::new(64);
+        ring.push(&[0.0, 1.0, 0.0]);
+        let mut resampler = Resampler::new(8000, 16000);
+        // Starting from silence, a sample behind
+        let out: Vec<f32> = (0..6).map(|_| resampler.sample(&ring)).collect();
+        assert_eq!(out, vec![0.0, 0.0, 0.0, 0.5, 1.0, 0.5]);
+        // Then silence until more arrives
+        assert_eq!(resampler.sample(&ring), 0.0);
+        assert_eq!(resampler.sample(&ring), 0.0);
+    }
+
+    #[test]
+    fn test_skip_ahead() {
+        let ring = SampleRing::new(64);
+        ring.push(&[0.5; 40]);
+        let mut resampler = Resampler::new(16000, 16000);
+        resampler.skip_ahead(&ring, 50);
+        assert_eq!(ring.len(), 40);
+        resampler.skip_ahead(&ring, 30);
+        assert!(ring.is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! Native playback of recordings, so the player no longer depends on the webview's audio
+//! element. A player thread owns the output stream and the file being played and takes
+//! commands over a channel. It decodes a little ahead into a queue the output callback
+//! drains: through `TimeStretch` at speeds other than 1x, then to the device's rate and
+//! channels. While playing, "playback-position" reports the position a few times a
+//! second, and "playback-ended" fires when the file, or the range asked for (such as one
+//! transcript segment), has played out.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::converter::StreamDecoder;
+use crate::audio::stretch::{TimeStretch, MAX_SPEED, MIN_SPEED};
+use crate::audio::AudioError;
+
+/// Audio decoded ahead of the output; also how late a seek or speed change is heard
+const QUEUE_MS: usize = 200;
+
+/// How often the player thread tops up the queue while playing
+const TICK: Duration = Duration::from_millis(10);
+
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where playback is; the payload of "playback-position" and "playback-ended"
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackStatus {
+    pub path: Option<String>,
+    pub position_ms: u64,
+    /// 0 when the file doesn't say
+    pub duration_ms: u64,
+    pub playing: bool,
+    pub speed: f32,
+    /// Where playback of a range stops
+    pub end_ms: Option<u64>,
+}
+
+static STATUS: Mutex<PlaybackStatus> = Mutex::new(PlaybackStatus {
+    path: None,
+    position_ms: 0,
+    duration_ms: 0,
+    playing: false,
+    speed: 1.0,
+    end_ms: None,
+});
+
+static COMMANDS: Mutex<Option<Sender<Command>>> = Mutex::new(None);
+
+enum Command {
+    Play {
+        path: PathBuf,
+        start_ms: u64,
+        end_ms: Option<u64>,
+        reply: Sender<Result<(), AudioError>>,
+    },
+    Pause,
+    Resume,
+    Seek(u64),
+    Speed(f32),
+    Stop,
+}
+
+/// Play the file at `path` from `start_ms`, stopping at `end_ms` if given. Replaces
+/// whatever was playing.
+pub fn play(
+    app: &AppHandle,
+    path: &Path,
+    start_ms: u64,
+    end_ms: Option<u64>,
+) -> Result<(), AudioError> {
+    let (reply, result) = mpsc::channel();
+    let command = Command::Play {
+        path: path.to_path_buf(),
+        start_ms,
+        end_ms,
+        reply,
+    };
+    let mut commands = COMMANDS.lock().unwrap_or_else(PoisonError::into_inner);
+    // Start the player thread on first use, or again if it has gone
+    let command = match commands.as_ref().map(|sender| sender.send(command)) {
+        Some(Ok(())) => None,
+        Some(Err(mpsc::SendError(command))) => Some(command),
+        None => Some(command),
+    };
+    if let Some(command) = command {
+        let (sender, receiver) = mpsc::channel();
+        let app = app.clone();
+        thread::spawn(move || run(app, receiver));
+        sender.send(command).map_err(|_| AudioError::LockError)?;
+        *commands = Some(sender);
+    }
+    drop(commands);
+    result.recv().map_err(|_| AudioError::LockError)?
+}
+
+pub fn pause() {
+    send(Command::Pause);
+}
+
+pub fn resume() {
+    send(Command::Resume);
+}
+
+pub fn seek(position_ms: u64) {
+    send(Command::Seek(position_ms));
+}
+
+/// Change the speed, clamped to `MIN_SPEED`..=`MAX_SPEED`, keeping the pitch
+pub fn set_speed(speed: f32) {
+    send(Command::Speed(speed.clamp(MIN_SPEED, MAX_SPEED)));
+}
+
+pub fn stop() {
+    send(Command::Stop);
+}
+
+pub fn status() -> PlaybackStatus {
+    STATUS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Nothing happens when the player hasn't been started
+fn send(command: Command) {
+    if let Some(sender) = COMMANDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        let _ = sender.send(command);
+    }
+}
+
+/// State shared with the output callback
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<VecDeque<f32>>,
+    playing: AtomicBool,
+    /// Samples played since the queue was last cleared
+    played: AtomicU64,
+    /// The device has failed, so the stream needs opening again
+    failed: AtomicBool,
+}
+
+struct Output {
+    _stream: Stream,
+    rate: u32,
+    channels: u16,
+    shared: Arc<Shared>,
+}
+
+impl Output {
+    /// Open the default output device
+    fn open() -> Result<Self, AudioError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::NoOutputDevice)?;
+        let config = device.default_output_config()?;
+        let shared = Arc::new(Shared::default());
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => open_stream::<f32>(&device, &config.config(), &shared)?,
+            SampleFormat::I16 => open_stream::<i16>(&device, &config.config(), &shared)?,
+            SampleFormat::U16 => open_stream::<u16>(&device, &config.config(), &shared)?,
+            _ => return Err(AudioError::UnsupportedFormat),
+        };
+        stream.play()?;
+        Ok(Self {
+            _stream: stream,
+            rate: config.sample_rate().0,
+            channels: config.channels(),
+            shared,
+        })
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<f32>> {
+        self.shared
+            .queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Milliseconds of the file played since the queue was last cleared
+    fn played_ms(&self, speed: f32) -> u64 {
+        let frames = self.shared.played.load(Ordering::Relaxed) / self.channels.max(1) as u64;
+        (frames as f64 * 1000.0 * speed as f64 / self.rate.max(1) as f64) as u64
+    }
+
+    fn clear(&self) {
+        let mut queue = self.queue();
+        queue.clear();
+        self.shared.played.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Play from the queue while `playing`, silence otherwise
+fn open_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    shared: &Arc<Shared>,
+) -> Result<Stream, AudioError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let shared = shared.clone();
+    let on_error = {
+        let shared = shared.clone();
+        move |err: cpal::StreamError| {
+            tracing::error!("Playback output error: {}", err);
+            shared.failed.store(true, Ordering::Relaxed);
+        }
+    };
+    Ok(device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            if !shared.playing.load(Ordering::Relaxed) {
+                data.fill(T::EQUILIBRIUM);
+                return;
+            }
+            let mut queue = shared.queue.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut played = 0;
+            for sample in data.iter_mut() {
+                *sample = match queue.pop_front() {
+                    Some(value) => {
+                        played += 1;
+                        T::from_sample(value)
+                    }
+                    None => T::EQUILIBRIUM,
+                };
+            }
+            shared.played.fetch_add(played, Ordering::Relaxed);
+        },
+        on_error,
+        None,
+    )?)
+}
+
+/// Converts the file's audio to the device's channels and rate, interpolating linearly
+/// and carrying the position across packets
+struct Reformat {
+    channels_in: usize,
+    channels_out: usize,
+    /// Input frames per output frame
+    step: f64,
+    /// Position of the next output frame between the previous input frame (0) and the
+    /// current one (1)
+    pos: f64,
+    prev: Vec<f32>,
+    frame: Vec<f32>,
+}
+
+impl Reformat {
+    fn new(from: (u32, u16), to: (u32, u16)) -> Self {
+        let step = from.0 as f64 / to.0.max(1) as f64;
+        let channels_out = to.1.max(1) as usize;
+        Self {
+            channels_in: from.1.max(1) as usize,
+            channels_out,
+            step,
+            pos: step,
+            prev: vec![0.0; channels_out],
+            frame: vec![0.0; channels_out],
+        }
+    }
+
+    fn apply(&mut self, data: &[f32], out: &mut VecDeque<f32>) {
+        for frame in data.chunks(self.channels_in) {
+            // Each channel plays its own, or the last there is, so mono goes to every channel
+            for (c, value) in self.frame.iter_mut().enumerate() {
+                *value = frame[c.min(frame.len() - 1)];
+            }
+            // Mix down to mono
+            if self.channels_out == 1 {
+                self.frame[0] = frame.iter().sum::<f32>() / frame.len() as f32;
+            }
+            while self.pos <= 1.0 {
+                for (prev, current) in self.prev.iter().zip(&self.frame) {
+                    out.push_back(prev + (current - prev) * self.pos as f32);
+                }
+                self.pos += self.step;
+            }
+            self.pos -= 1.0;
+            std::mem::swap(&mut self.prev, &mut self.frame);
+        }
+    }
+}
+
+/// The stretcher for `speed`, if it isn't 1x, and the conversion to the output's format
+fn converters(
+    decoder: &StreamDecoder,
+    output: &Output,
+    speed: f32,
+) -> (Option<TimeStretch>, Reformat) {
+    let (rate, channels) = (decoder.sample_rate(), decoder.channels());
+    (
+        (speed != 1.0).then(|| TimeStretch::new(rate, channels, speed)),
+        Reformat::new((rate, channels), (output.rate, output.channels)),
+    )
+}
+
+/// The file being played
+struct Source {
+    path: PathBuf,
+    decoder: StreamDecoder,
+    stretch: Option<TimeStretch>,
+    reformat: Reformat,
+    /// Position in the file where the queue's audio began when it was last cleared
+    base_ms: u64,
+    duration_ms: u64,
+    end_ms: Option<u64>,
+    /// The decoder has reached the end of the file
+    finished: bool,
+}
+
+impl Source {
+    fn open(path: &Path, output: &Output, speed: f32) -> Result<Self, AudioError> {
+        let decoder = StreamDecoder::open(path)?;
+        let (stretch, reformat) = converters(&decoder, output, speed);
+        output.clear();
+        Ok(Self {
+            path: path.to_path_buf(),
+            duration_ms: decoder.duration_ms().unwrap_or(0),
+            decoder,
+            stretch,
+            reformat,
+            base_ms: 0,
+            end_ms: None,
+            finished: false,
+        })
+    }
+
+    /// Start converting afresh, after a seek or a speed change
+    fn restart(&mut self, output: &Output, speed: f32) {
+        (self.stretch, self.reformat) = converters(&self.decoder, output, speed);
+        self.finished = false;
+        output.clear();
+    }
+
+    fn seek(&mut self, position_ms: u64, output: &Output, speed: f32) -> Result<(), AudioError> {
+        let position_ms = match self.duration_ms {
+            0 => position_ms,
+            duration => position_ms.min(duration),
+        };
+        self.decoder.seek(position_ms)?;
+        self.base_ms = position_ms;
+        self.restart(output, speed);
+        Ok(())
+    }
+
+    fn position_ms(&self, output: &Output, speed: f32) -> u64 {
+        self.base_ms + output.played_ms(speed)
+    }
+
+    /// Decode until the queue holds `QUEUE_MS` of audio or the file ends
+    fn fill(&mut self, output: &Output) {
+        let target = output.rate as usize * output.channels as usize * QUEUE_MS / 1000;
+        let mut stretched = Vec::new();
+        while !self.finished && output.queue().len() < target {
+            let Some(samples) = self.decoder.next_samples() else {
+                self.finished = true;
+                break;
+            };
+            let samples = match self.stretch.as_mut() {
+                Some(stretch) => {
+                    stretched.clear();
+                    stretch.process(samples, &mut stretched);
+                    &stretched[..]
+                }
+                None => samples,
+            };
+            let mut queue = output.queue();
+            self.reformat.apply(samples, &mut queue);
+        }
+    }
+}
+
+struct Player {
+    app: AppHandle,
+    output: Option<Output>,
+    source: Option<Source>,
+    speed: f32,
+    last_emit: Instant,
+}
+
+impl Player {
+    fn playing(&self) -> bool {
+        self.output
+            .as_ref()
+            .is_some_and(|output| output.shared.playing.load(Ordering::Relaxed))
+    }
+
+    fn set_playing(&self, playing: bool) {
+        if let Some(output) = &self.output {
+            output.shared.playing.store(playing, Ordering::Relaxed);
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Play {
+                path,
+                start_ms,
+                end_ms,
+                reply,
+            } => {
+                let _ = reply.send(self.start(&path, start_ms, end_ms));
+            }
+            Command::Pause => self.set_playing(false),
+            Command::Resume => {
+                // Played out: start again from the top
+                if let (Some(source), Some(output)) = (&mut self.source, &self.output) {
+                    if source.finished
+                        && output.queue().is_empty()
+                        && let Err(e) = source.seek(0, output, self.speed)
+                    {
+                        tracing::warn!("Failed to restart playback: {}", e);
+                    }
+                    output.shared.playing.store(true, Ordering::Relaxed);
+                }
+            }
+            Command::Seek(position_ms) => self.seek(position_ms),
+            Command::Speed(speed) if speed != self.speed => {
+                let position = self.position_ms();
+                self.speed = speed;
+                self.seek(position);
+            }
+            Command::Speed(_) => {}
+            Command::Stop => {
+                self.set_playing(false);
+                if let Some(output) = &self.output {
+                    output.clear();
+                }
+                self.source = None;
+            }
+        }
+        self.update(true);
+    }
+
+    fn start(&mut self, path: &Path, start_ms: u64, end_ms: Option<u64>) -> Result<(), AudioError> {
+        self.set_playing(false);
+        self.source = None;
+        if self
+            .output
+            .as_ref()
+            .is_none_or(|output| output.shared.failed.load(Ordering::Relaxed))
+        {
+            self.output = None;
+            self.output = Some(Output::open()?);
+        }
+        let Some(output) = &self.output else {
+            return Err(AudioError::NoOutputDevice);
+        };
+        let mut source = Source::open(path, output, self.speed)?;
+        if start_ms > 0 {
+            source.seek(start_ms, output, self.speed)?;
+        }
+        source.end_ms = end_ms.filter(|&end| end > start_ms);
+        self.source = Some(source);
+        self.set_playing(true);
+        Ok(())
+    }
+
+    fn seek(&mut self, position_ms: u64) {
+        let (Some(source), Some(output)) = (&mut self.source, &self.output) else {
+            return;
+        };
+        // Leaving the range plays on to the end of the file
+        if source.end_ms.is_some_and(|end| position_ms >= end) {
+            source.end_ms = None;
+        }
+        if let Err(e) = source.seek(position_ms, output, self.speed) {
+            tracing::warn!("Failed to seek playback: {}", e);
+        }
+    }
+
+    fn position_ms(&self) -> u64 {
+        match (&self.source, &self.output) {
+            (Some(source), Some(output)) => source.position_ms(output, self.speed),
+            _ => 0,
+        }
+    }
+
+    /// Keep the queue topped up and stop at the end of the range or file
+    fn tick(&mut self) {
+        let (Some(source), Some(output)) = (&mut self.source, &self.output) else {
+            return;
+        };
+        source.fill(output);
+
+        let position = source.position_ms(output, self.speed);
+        let range_done = source.end_ms.is_some_and(|end| position >= end);
+        let file_done = source.finished && output.queue().is_empty();
+        if range_done || file_done {
+            output.shared.playing.store(false, Ordering::Relaxed);
+            if range_done {
+                // Resuming carries on past the range
+                source.end_ms = None;
+            }
+            self.update(true);
+            let _ = self.app.emit("playback-ended", status());
+            return;
+        }
+        self.update(false);
+    }
+
+    /// Refresh the shared status, reporting it if `force`d or due
+    fn update(&mut self, force: bool) {
+        let status = PlaybackStatus {
+            path: self
+                .source
+                .as_ref()
+                .map(|source| source.path.to_string_lossy().to_string()),
+            position_ms: self.position_ms(),
+            duration_ms: self.source.as_ref().map_or(0, |source| source.duration_ms),
+            playing: self.playing(),
+            speed: self.speed,
+            end_ms: self.source.as_ref().and_then(|source| source.end_ms),
+        };
+        *STATUS.lock().unwrap_or_else(PoisonError::into_inner) = status.clone();
+        if force || self.last_emit.elapsed() >= POSITION_INTERVAL {
+            self.last_emit = Instant::now();
+            let _ = self.app.emit("playback-position", status);
+        }
+    }
+}
+
+/// The player thread: the output stream can't leave the thread that opened it
+fn run(app: AppHandle, commands: Receiver<Command>) {
+    let mut player = Player {
+        app,
+        output: None,
+        source: None,
+        speed: 1.0,
+        last_emit: Instant::now(),
+    };
+    loop {
+        let command = if player.playing() {
+            match commands.recv_timeout(TICK) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+        if let Some(command) = command {
+            player.handle(command);
+        }
+        if player.playing() {
+            player.tick();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reformat_channels_and_rate() {
+        // Stereo at 8 kHz to four channels at 16 kHz: twice the frames, channels kept
+        let mut reformat = Reformat::new((8000, 2), (16000, 4));
+        let mut out = VecDeque::new();
+        reformat.apply(&[0.2, -0.2, 0.4, -0.4, 0.6, -0.6], &mut out);
+        let out: Vec<f32> = out.into_iter().collect();
+        assert_eq!(out.len(), 3 * 2 * 4);
+        let last = &out[out.len() - 4..];
+        assert!((last[0] - 0.6).abs() < 1e-6);
+        assert!((last[1] + 0.6).abs() < 1e-6);
+        assert!((last[3] + 0.6).abs() < 1e-6);
+
+        // Stereo to mono mixes down
+        let mut reformat = Reformat::new((8000, 2), (8000, 1));
+        let mut out = VecDeque::new();
+        reformat.apply(&[0.2, 0.4, 0.6, 0.8], &mut out);
+        assert_eq!(out.len(), 2);
+        assert!((out[1] - 0.7).abs() < 1e-6);
+    }
+}
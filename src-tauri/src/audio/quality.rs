@@ -0,0 +1,220 @@
+//! Mic signal checks while recording, so a muted or overdriven microphone is noticed during
+//! the meeting rather than after it. The audio is judged a second at a time: clipping for
+//! `CLIPPING_SECS` seconds in a row, or next to no signal for `SILENCE_SECS`, raises
+//! "recording-quality-warning"; the same event with `active` false says it cleared. Each
+//! problem is reported once until it clears.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::RecordingState;
+
+/// Samples at least this loud count as clipped
+const CLIP_LEVEL: f32 = 0.99;
+
+/// Share of clipped samples that makes a clipped second
+const CLIP_RATIO: f32 = 0.001;
+
+const CLIPPING_SECS: u32 = 3;
+
+/// Clipping has cleared after this many clean seconds
+const CLEAR_SECS: u32 = 5;
+
+/// RMS below this (about -66 dBFS) is silence; a quiet room is well above it
+const SILENCE_RMS: f32 = 0.0005;
+
+const SILENCE_SECS: u32 = 30;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    Clipping,
+    Silence,
+}
+
+/// Payload of "recording-quality-warning"
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityWarning {
+    pub note_id: Option<String>,
+    pub issue: QualityIssue,
+    /// False once the problem has cleared
+    pub active: bool,
+    /// How long it has lasted
+    pub seconds: u32,
+}
+
+/// A problem starting (`active`) or clearing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QualityChange {
+    pub issue: QualityIssue,
+    pub active: bool,
+    pub seconds: u32,
+}
+
+fn change(issue: QualityIssue, active: bool, seconds: u32) -> QualityChange {
+    QualityChange {
+        issue,
+        active,
+        seconds,
+    }
+}
+
+/// Judges mic audio a second at a time
+#[derive(Debug, Default)]
+pub struct QualityMonitor {
+    samples: usize,
+    clipped: usize,
+    sum_squares: f64,
+    clipped_secs: u32,
+    clean_secs: u32,
+    clipping: bool,
+    silent_secs: u32,
+    silent: bool,
+}
+
+impl QualityMonitor {
+    /// Take in interleaved audio with `samples_per_sec` samples (all channels) a second.
+    /// Returns the problems that started or cleared.
+    pub fn process(&mut self, data: &[f32], samples_per_sec: usize) -> Vec<QualityChange> {
+        let mut changes = Vec::new();
+        if samples_per_sec == 0 {
+            return changes;
+        }
+        for &sample in data {
+            self.samples += 1;
+            self.sum_squares += (sample * sample) as f64;
+            if sample.abs() >= CLIP_LEVEL {
+                self.clipped += 1;
+            }
+            if self.samples == samples_per_sec {
+                self.end_second(&mut changes);
+            }
+        }
+        changes
+    }
+
+    fn end_second(&mut self, changes: &mut Vec<QualityChange>) {
+        let clipped = self.clipped as f32 / self.samples as f32 >= CLIP_RATIO;
+        let rms = (self.sum_squares / self.samples as f64).sqrt() as f32;
+        self.samples = 0;
+        self.clipped = 0;
+        self.sum_squares = 0.0;
+
+        if clipped {
+            self.clipped_secs += 1;
+            self.clean_secs = 0;
+        } else {
+            self.clean_secs += 1;
+            if !self.clipping {
+                self.clipped_secs = 0;
+            }
+        }
+        if !self.clipping && self.clipped_secs >= CLIPPING_SECS {
+            self.clipping = true;
+            changes.push(change(QualityIssue::Clipping, true, self.clipped_secs));
+        } else if self.clipping && self.clean_secs >= CLEAR_SECS {
+            self.clipping = false;
+            changes.push(change(QualityIssue::Clipping, false, self.clipped_secs));
+            self.clipped_secs = 0;
+        }
+
+        if rms < SILENCE_RMS {
+            self.silent_secs += 1;
+            if !self.silent && self.silent_secs >= SILENCE_SECS {
+                self.silent = true;
+                changes.push(change(QualityIssue::Silence, true, self.silent_secs));
+            }
+        } else {
+            if self.silent {
+                self.silent = false;
+                changes.push(change(QualityIssue::Silence, false, self.silent_secs));
+            }
+            self.silent_secs = 0;
+        }
+    }
+}
+
+/// Remember the app to report to. Call once at startup.
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+/// Tell the app about problems starting or clearing in the recording
+pub(crate) fn report(state: &RecordingState, changes: &[QualityChange]) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let note_id = state.current_note_id.lock().ok().and_then(|id| id.clone());
+    for change in changes {
+        if change.active {
+            tracing::warn!("Recording {:?} for {} s", change.issue, change.seconds);
+        }
+        let _ = app.emit(
+            "recording-quality-warning",
+            QualityWarning {
+                note_id: note_id.clone(),
+                issue: change.issue,
+                active: change.active,
+                seconds: change.seconds,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: usize = 1000;
+
+    fn seconds(monitor: &mut QualityMonitor, level: f32, secs: usize) -> Vec<QualityChange> {
+        let data: Vec<f32> = (0..RATE * secs)
+            .map(|i| if i % 2 == 0 { level } else { -level })
+            .collect();
+        // In uneven pieces, as the device delivers them
+        data.chunks(333)
+            .flat_map(|chunk| monitor.process(chunk, RATE))
+            .collect()
+    }
+
+    #[test]
+    fn test_clipping() {
+        let mut monitor = QualityMonitor::default();
+        assert!(seconds(&mut monitor, 1.0, CLIPPING_SECS as usize - 1).is_empty());
+        let started = seconds(&mut monitor, 1.0, 1);
+        assert_eq!(
+            started,
+            vec![QualityChange {
+                issue: QualityIssue::Clipping,
+                active: true,
+                seconds: CLIPPING_SECS,
+            }]
+        );
+        // Reported once
+        assert!(seconds(&mut monitor, 1.0, 10).is_empty());
+        let cleared = seconds(&mut monitor, 0.1, CLEAR_SECS as usize);
+        assert_eq!(cleared.len(), 1);
+        assert!(!cleared[0].active);
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut monitor = QualityMonitor::default();
+        assert!(seconds(&mut monitor, 0.0, SILENCE_SECS as usize - 1).is_empty());
+        let started = seconds(&mut monitor, 0.0, 1);
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].issue, QualityIssue::Silence);
+        assert!(started[0].active);
+
+        let cleared = seconds(&mut monitor, 0.05, 1);
+        assert_eq!(cleared.len(), 1);
+        assert!(!cleared[0].active);
+        assert_eq!(cleared[0].seconds, SILENCE_SECS);
+        // Speech now and then keeps it quiet
+        assert!(seconds(&mut monitor, 0.0, 20).is_empty());
+    }
+}
@@ -1,15 +1,26 @@
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::traits::{DeviceTrait, StreamTrait};
 use cpal::{Sample, SampleFormat};
-use hound::{WavSpec, WavWriter};
+use hound::WavSpec;
 use serde::{Deserialize, Serialize};
 
-use crate::audio::AudioError;
+use crate::audio::capture_format;
+use crate::audio::clock::CaptureClock;
+use crate::audio::denoise::{self, NoiseSuppressor};
+use crate::audio::devices;
+use crate::audio::monitoring::{self, Monitor};
+use crate::audio::quality::{self, QualityMonitor};
+use crate::audio::waveform;
+use crate::audio::writer::{SampleSender, SampleWriter};
+use crate::audio::{AudioError, SampleRing};
+
+/// Mic samples kept for live transcription: 30 seconds of 48kHz stereo, a few passes' worth
+const LIVE_BUFFER_SAMPLES: usize = 48000 * 2 * 30;
 
 /// Recording phase for pause/resume functionality
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,8 +46,11 @@ pub struct RecordingState {
     pub is_recording: AtomicBool,
     pub audio_level: AtomicU32,
     pub output_path: std::sync::Mutex<Option<PathBuf>>,
-    /// Buffer for live transcription - stores raw f32 samples
-    pub audio_buffer: std::sync::Mutex<Vec<f32>>,
+    /// Buffer for live transcription - stores raw f32 samples. Filled from the stream
+    /// callback, so pushing never waits.
+    pub audio_buffer: SampleRing,
+    /// Mic peaks for the live waveform, also filled from the stream callback
+    pub live_peaks: SampleRing,
     /// Sample rate of the recorded audio (set when recording starts)
     pub sample_rate: AtomicU32,
     /// Number of channels (set when recording starts)
@@ -55,6 +69,12 @@ pub struct RecordingState {
     pub current_note_id: std::sync::Mutex<Option<String>>,
     /// Current segment ID in database (for updating duration)
     pub current_segment_db_id: AtomicI64,
+    /// Clipping and silence checks on the mic
+    pub quality: std::sync::Mutex<QualityMonitor>,
+    /// When the current segment's mic audio arrived, for lining it up with system audio
+    pub mic_clock: std::sync::Mutex<CaptureClock>,
+    /// File the recording thread should move on to, set by `rotate_recording`
+    pub next_output_path: std::sync::Mutex<Option<PathBuf>>,
 }
 
 impl RecordingState {
@@ -63,7 +83,8 @@ impl RecordingState {
             is_recording: AtomicBool::new(false),
             audio_level: AtomicU32::new(0),
             output_path: std::sync::Mutex::new(None),
-            audio_buffer: std::sync::Mutex::new(Vec::new()),
+            audio_buffer: SampleRing::new(LIVE_BUFFER_SAMPLES),
+            live_peaks: SampleRing::new(waveform::LIVE_PEAKS),
             sample_rate: AtomicU32::new(0),
             channels: AtomicU32::new(0),
             // Pause/Resume/Continue fields
@@ -73,6 +94,9 @@ impl RecordingState {
             segment_start_time: std::sync::Mutex::new(None),
             current_note_id: std::sync::Mutex::new(None),
             current_segment_db_id: AtomicI64::new(0),
+            quality: std::sync::Mutex::new(QualityMonitor::default()),
+            mic_clock: std::sync::Mutex::new(CaptureClock::new()),
+            next_output_path: std::sync::Mutex::new(None),
         }
     }
 
@@ -96,6 +120,12 @@ impl RecordingState {
         0
     }
 
+    /// How far into the note's audio the recording is: the segment's start plus its
+    /// elapsed time
+    pub fn note_position_ms(&self) -> i64 {
+        self.segment_start_offset_ms.load(Ordering::SeqCst) + self.get_segment_elapsed_ms()
+    }
+
     /// Reset state for a new recording session
     pub fn reset_for_new_session(&self) {
         self.current_segment_index.store(0, Ordering::SeqCst);
@@ -111,21 +141,13 @@ impl RecordingState {
 
     /// Take all samples from the buffer (clears the buffer)
     pub fn take_audio_buffer(&self) -> Vec<f32> {
-        match self.audio_buffer.lock() { Ok(mut buffer) => {
-            std::mem::take(&mut *buffer)
-        } _ => {
-            Vec::new()
-        }}
+        self.audio_buffer.take()
     }
 
     /// Get the current buffer length without clearing
     #[allow(dead_code)]
     pub fn buffer_len(&self) -> usize {
-        match self.audio_buffer.lock() { Ok(buffer) => {
-            buffer.len()
-        } _ => {
-            0
-        }}
+        self.audio_buffer.len()
     }
 }
 
@@ -154,6 +176,12 @@ pub fn start_recording(state: Arc<RecordingState>, output_path: PathBuf) -> Resu
         let mut start_time = state.segment_start_time.lock().map_err(|_| AudioError::LockError)?;
         *start_time = Some(Instant::now());
     }
+    if let Ok(mut quality) = state.quality.lock() {
+        *quality = QualityMonitor::default();
+    }
+    if let Ok(mut clock) = state.mic_clock.lock() {
+        *clock = CaptureClock::new();
+    }
 
     state.is_recording.store(true, Ordering::SeqCst);
     state.set_phase(RecordingPhase::Recording);
@@ -163,7 +191,7 @@ pub fn start_recording(state: Arc<RecordingState>, output_path: PathBuf) -> Resu
     // Spawn recording thread
     thread::spawn(move || {
         if let Err(e) = run_recording(state_clone, output_path) {
-            eprintln!("Recording error: {}", e);
+            tracing::error!("Recording error: {}", e);
         }
     });
 
@@ -203,6 +231,25 @@ pub fn resume_recording(state: Arc<RecordingState>, output_path: PathBuf) -> Res
     start_recording(state, output_path)
 }
 
+/// Carry the recording on in a new file, for a new segment, without stopping capture.
+/// The recording thread switches files on its next check, within about 100 ms.
+pub fn rotate_recording(state: &RecordingState, output_path: PathBuf) -> Result<(), AudioError> {
+    if state.get_phase() != RecordingPhase::Recording {
+        return Err(AudioError::NotRecording);
+    }
+    {
+        let mut path = state.output_path.lock().map_err(|_| AudioError::LockError)?;
+        *path = Some(output_path.clone());
+    }
+    {
+        let mut start_time = state.segment_start_time.lock().map_err(|_| AudioError::LockError)?;
+        *start_time = Some(Instant::now());
+    }
+    let mut next = state.next_output_path.lock().map_err(|_| AudioError::LockError)?;
+    *next = Some(output_path);
+    Ok(())
+}
+
 /// Stop recording completely - resets all state
 pub fn stop_recording(state: &RecordingState) -> Result<Option<PathBuf>, AudioError> {
     state.is_recording.store(false, Ordering::SeqCst);
@@ -230,102 +277,320 @@ pub fn stop_recording_preserving_state(state: &RecordingState) -> Result<(Option
     Ok((path.clone(), duration_ms))
 }
 
-fn run_recording(state: Arc<RecordingState>, output_path: PathBuf) -> Result<(), AudioError> {
-    let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .ok_or(AudioError::NoInputDevice)?;
+/// A stream that has delivered nothing for this long is treated as lost, for hosts that
+/// stop calling back without reporting an error
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
 
-    let config = device.default_input_config()?;
-    let sample_rate = config.sample_rate().0;
-    let channels = config.channels();
+/// Time between attempts to reopen a lost microphone
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
 
-    // Store sample rate and channels for live transcription
-    state.sample_rate.store(sample_rate, Ordering::SeqCst);
-    state.channels.store(channels as u32, Ordering::SeqCst);
+/// Whether the open input stream is still delivering
+struct StreamHealth {
+    started: Instant,
+    /// Milliseconds from `started` to the last callback
+    last_data_ms: AtomicU64,
+    lost: AtomicBool,
+}
 
-    // Clear the audio buffer at start
-    if let Ok(mut buffer) = state.audio_buffer.lock() {
-        buffer.clear();
+impl StreamHealth {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_data_ms: AtomicU64::new(0),
+            lost: AtomicBool::new(false),
+        }
     }
 
-    let spec = WavSpec {
-        channels,
-        sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+    fn touch(&self) {
+        self.last_data_ms
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn since_last_data(&self) -> Duration {
+        let last_data = Duration::from_millis(self.last_data_ms.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last_data)
+    }
 
-    let writer = WavWriter::create(&output_path, spec)?;
-    let writer = Arc::new(std::sync::Mutex::new(Some(writer)));
+    fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed) || self.since_last_data() > STALL_TIMEOUT
+    }
+}
 
-    let state_for_callback = state.clone();
-    let writer_clone = writer.clone();
+/// Turns a replacement device's audio into the format the file was started with:
+/// channels are mixed down and spread back out, and the rate is converted by linear
+/// interpolation, carrying the position across callbacks
+struct Reformat {
+    channels_in: usize,
+    channels_out: usize,
+    /// Input frames per output frame
+    step: f64,
+    /// Position of the next output frame between the previous input frame (0) and the
+    /// current one (1)
+    pos: f64,
+    prev: f32,
+}
 
-    let err_fn = |err| eprintln!("Audio stream error: {}", err);
+impl Reformat {
+    /// None when the formats match and samples can pass through
+    fn new(from: (u32, u16), to: (u32, u16)) -> Option<Self> {
+        (from != to).then(|| Self {
+            channels_in: from.1.max(1) as usize,
+            channels_out: to.1.max(1) as usize,
+            step: from.0 as f64 / to.0.max(1) as f64,
+            // The first output frame falls one step in
+            pos: from.0 as f64 / to.0.max(1) as f64,
+            prev: 0.0,
+        })
+    }
+
+    fn apply(&mut self, data: &[f32], out: &mut Vec<f32>) {
+        for frame in data.chunks(self.channels_in) {
+            let sample = frame.iter().sum::<f32>() / frame.len() as f32;
+            while self.pos <= 1.0 {
+                let value = self.prev + (sample - self.prev) * self.pos as f32;
+                out.extend(std::iter::repeat_n(value, self.channels_out));
+                self.pos += self.step;
+            }
+            self.pos -= 1.0;
+            self.prev = sample;
+        }
+    }
+}
+
+/// Open `device` and feed its audio, in the file's format, to `process_audio`, after
+/// `silence` samples of silence. Noise is suppressed while `denoise` is set.
+fn open_stream(
+    device: &cpal::Device,
+    spec: WavSpec,
+    state: &Arc<RecordingState>,
+    writer: &SampleSender,
+    health: &Arc<StreamHealth>,
+    denoise: &Arc<AtomicBool>,
+    mut silence: usize,
+) -> Result<cpal::Stream, AudioError> {
+    let config = device.default_input_config()?;
+    let mut reformat = Reformat::new(
+        (config.sample_rate().0, config.channels()),
+        (spec.sample_rate, spec.channels),
+    );
+    let mut suppressor = NoiseSuppressor::new(spec.sample_rate, spec.channels);
+    let mut reformatted = Vec::new();
+    let mut denoised = Vec::new();
+    let mut handle = {
+        let state = state.clone();
+        let writer = writer.clone();
+        let health = health.clone();
+        let denoise = denoise.clone();
+        move |data: &[f32]| {
+            health.touch();
+            if silence > 0 {
+                process_audio(&vec![0.0; silence], &state, &writer);
+                silence = 0;
+            }
+            let data = match reformat.as_mut() {
+                Some(reformat) => {
+                    reformatted.clear();
+                    reformat.apply(data, &mut reformatted);
+                    &reformatted[..]
+                }
+                None => data,
+            };
+            match suppressor.as_mut() {
+                Some(suppressor) if denoise.load(Ordering::Relaxed) => {
+                    denoised.clear();
+                    suppressor.process(data, &mut denoised);
+                    process_audio(&denoised, &state, &writer);
+                }
+                Some(suppressor) if suppressor.has_pending() => {
+                    denoised.clear();
+                    suppressor.flush(&mut denoised);
+                    denoised.extend_from_slice(data);
+                    process_audio(&denoised, &state, &writer);
+                }
+                _ => process_audio(data, &state, &writer),
+            }
+        }
+    };
+
+    let err_fn = {
+        let health = health.clone();
+        move |err: cpal::StreamError| {
+            tracing::error!("Audio stream error: {}", err);
+            if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                health.lost.store(true, Ordering::Relaxed);
+            }
+        }
+    };
 
     let stream = match config.sample_format() {
         SampleFormat::F32 => device.build_input_stream(
             &config.into(),
-            move |data: &[f32], _| {
-                process_audio(data, &state_for_callback, &writer_clone);
+            move |data: &[f32], _| handle(data),
+            err_fn,
+            None,
+        )?,
+        SampleFormat::I16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[i16], _| {
+                let float_data: Vec<f32> = data.iter().map(|&s| s.to_float_sample()).collect();
+                handle(&float_data);
+            },
+            err_fn,
+            None,
+        )?,
+        SampleFormat::U16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[u16], _| {
+                let float_data: Vec<f32> = data.iter().map(|&s| s.to_float_sample()).collect();
+                handle(&float_data);
             },
             err_fn,
             None,
         )?,
-        SampleFormat::I16 => {
-            let state_for_callback = state.clone();
-            let writer_clone = writer.clone();
-            device.build_input_stream(
-                &config.into(),
-                move |data: &[i16], _| {
-                    let float_data: Vec<f32> = data.iter().map(|&s| s.to_float_sample()).collect();
-                    process_audio(&float_data, &state_for_callback, &writer_clone);
-                },
-                err_fn,
-                None,
-            )?
-        }
-        SampleFormat::U16 => {
-            let state_for_callback = state.clone();
-            let writer_clone = writer.clone();
-            device.build_input_stream(
-                &config.into(),
-                move |data: &[u16], _| {
-                    let float_data: Vec<f32> = data.iter().map(|&s| s.to_float_sample()).collect();
-                    process_audio(&float_data, &state_for_callback, &writer_clone);
-                },
-                err_fn,
-                None,
-            )?
-        }
         _ => return Err(AudioError::UnsupportedFormat),
     };
 
     stream.play()?;
+    Ok(stream)
+}
+
+/// Reopen the input after the device went away, on the chosen device if it is back or
+/// else the default, padding the file with silence for the time without audio so it
+/// stays in step with system audio
+fn reopen_stream(
+    spec: WavSpec,
+    state: &Arc<RecordingState>,
+    writer: &SampleSender,
+    lost: &StreamHealth,
+    denoise: &Arc<AtomicBool>,
+    previous: &str,
+) -> Option<(cpal::Stream, Arc<StreamHealth>, String)> {
+    let device = devices::input_device().ok()?;
+    let name = device.name().unwrap_or_default();
+    let health = Arc::new(StreamHealth::new());
+    let gap_frames = lost.since_last_data().as_secs_f64() * spec.sample_rate as f64;
+    let silence = gap_frames as usize * spec.channels as usize;
+    let stream = match open_stream(&device, spec, state, writer, &health, denoise, silence) {
+        Ok(stream) => stream,
+        Err(e) => {
+            tracing::warn!("Failed to reopen the microphone on {}: {}", name, e);
+            return None;
+        }
+    };
+
+    tracing::warn!(
+        "Microphone {} went away; recording continues on {}",
+        previous,
+        name
+    );
+    devices::report_input_change(previous, &name);
+    Some((stream, health, name))
+}
+
+fn run_recording(state: Arc<RecordingState>, output_path: PathBuf) -> Result<(), AudioError> {
+    let open_span = tracing::info_span!("recording.open_device").entered();
+    let device = devices::input_device()?;
+    let mut device_name = device.name().unwrap_or_default();
+
+    // The quality preset's format, else the device's own. The device's audio is converted
+    // to it, as is a replacement device's.
+    let (sample_rate, channels) = match capture_format::active().mic_format() {
+        Some(format) => (format.sample_rate, format.channels),
+        None => {
+            let config = device.default_input_config()?;
+            (config.sample_rate().0, config.channels())
+        }
+    };
+
+    // Store sample rate and channels for live transcription; they hold for the whole
+    // segment.
+    state.sample_rate.store(sample_rate, Ordering::SeqCst);
+    state.channels.store(channels as u32, Ordering::SeqCst);
 
-    // Keep thread alive while recording
+    // Clear the audio buffer at start
+    state.audio_buffer.clear();
+
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    if let Ok(mut next) = state.next_output_path.lock() {
+        *next = None;
+    }
+    let writer = SampleWriter::create(&output_path, spec)?;
+    let sender = writer.sender();
+    let mut health = Arc::new(StreamHealth::new());
+    let denoise = Arc::new(AtomicBool::new(false));
+    update_denoise(&state, &denoise);
+    let mut stream = Some(open_stream(&device, spec, &state, &sender, &health, &denoise, 0)?);
+    drop(open_span);
+    let mut last_reopen: Option<Instant> = None;
+    let mut monitor = Monitor::default();
+    monitor.update(sample_rate);
+
+    // Keep thread alive while recording, replacing the stream if its device goes away
     while state.is_recording.load(Ordering::SeqCst) {
-        thread::sleep(std::time::Duration::from_millis(100));
+        thread::sleep(Duration::from_millis(100));
+        update_denoise(&state, &denoise);
+        monitor.update(sample_rate);
+        switch_file(&state, &writer);
+        if !health.is_lost() || last_reopen.is_some_and(|at| at.elapsed() < REOPEN_INTERVAL) {
+            continue;
+        }
+        // Release the dead stream before opening another; retried until a device answers
+        stream = None;
+        last_reopen = Some(Instant::now());
+        if let Some((new_stream, new_health, name)) =
+            reopen_stream(spec, &state, &sender, &health, &denoise, &device_name)
+        {
+            stream = Some(new_stream);
+            health = new_health;
+            device_name = name;
+        }
     }
 
-    // Finalize the WAV file
+    // Finalize the WAV file once the callback (and its sender) is gone. A rotation asked
+    // for just before stopping still gets its file, as the segment was recorded with it.
+    let _span = tracing::info_span!("recording.finalize").entered();
+    switch_file(&state, &writer);
     drop(stream);
-    if let Ok(mut guard) = writer.lock() {
-        if let Some(w) = guard.take() {
-            let _ = w.finalize();
+    drop(sender);
+    writer.finish();
+
+    Ok(())
+}
+
+/// Move on to the file `rotate_recording` asked for, if any
+fn switch_file(state: &RecordingState, writer: &SampleWriter) {
+    let Some(path) = state.next_output_path.lock().ok().and_then(|mut next| next.take()) else {
+        return;
+    };
+    match writer.switch_to(&path) {
+        Ok(()) => {
+            if let Ok(mut clock) = state.mic_clock.lock() {
+                *clock = CaptureClock::new();
+            }
         }
+        Err(e) => tracing::error!("Failed to start recording {}: {}", path.display(), e),
     }
+}
 
-    Ok(())
+/// Follow the setting and the note's choice, which can change mid-recording (and the
+/// note is only known once the recording has started)
+fn update_denoise(state: &RecordingState, denoise: &AtomicBool) {
+    let note_id = state.current_note_id.lock().ok().and_then(|id| id.clone());
+    denoise.store(denoise::enabled_for(note_id.as_deref()), Ordering::Relaxed);
 }
 
 fn process_audio(
     data: &[f32],
     state: &Arc<RecordingState>,
-    writer: &Arc<std::sync::Mutex<Option<WavWriter<std::io::BufWriter<std::fs::File>>>>>,
+    writer: &SampleSender,
 ) {
-    if !state.is_recording.load(Ordering::SeqCst) {
+    if !state.is_recording.load(Ordering::SeqCst) || data.is_empty() {
         return;
     }
 
@@ -334,18 +599,54 @@ fn process_audio(
     let rms = (sum / data.len() as f32).sqrt();
     state.audio_level.store(rms.to_bits(), Ordering::SeqCst);
 
+    // Watch for a muted or overdriven mic
+    let samples_per_sec = state.sample_rate.load(Ordering::SeqCst) as usize
+        * state.channels.load(Ordering::SeqCst) as usize;
+    let changes = match state.quality.lock() {
+        Ok(mut quality) => quality.process(data, samples_per_sec),
+        Err(_) => Vec::new(),
+    };
+    if !changes.is_empty() {
+        quality::report(state, &changes);
+    }
+
     // Copy samples to buffer for live transcription
-    if let Ok(mut buffer) = state.audio_buffer.lock() {
-        buffer.extend_from_slice(data);
+    state.audio_buffer.push(data);
+    waveform::push_live_peaks(&state.live_peaks, data, samples_per_sec);
+    monitoring::push(data, state.channels.load(Ordering::SeqCst) as usize);
+
+    // Queue for the writer thread
+    writer.send(data);
+    if let Ok(mut clock) = state.mic_clock.lock() {
+        let channels = state.channels.load(Ordering::SeqCst).max(1) as usize;
+        clock.record(data.len() / channels, state.sample_rate.load(Ordering::SeqCst));
     }
+}
 
-    // Write to WAV file
-    if let Ok(mut guard) = writer.lock() {
-        if let Some(ref mut w) = *guard {
-            for &sample in data {
-                let sample_i16 = (sample * i16::MAX as f32) as i16;
-                let _ = w.write_sample(sample_i16);
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reformat_matching_formats_pass_through() {
+        assert!(Reformat::new((48000, 2), (48000, 2)).is_none());
+    }
+
+    #[test]
+    fn test_reformat_rate_and_channels() {
+        // 24 kHz mono into a 48 kHz stereo file: twice the frames, each sample on both sides
+        let mut reformat = Reformat::new((24000, 1), (48000, 2)).unwrap();
+        let mut out = Vec::new();
+        reformat.apply(&[0.5, 0.5], &mut out);
+        reformat.apply(&[0.5, 0.5], &mut out);
+        assert_eq!(out.len(), 4 * 2 * 2);
+        assert!(out[2..].iter().all(|s| (*s - 0.5).abs() < 1e-6));
+
+        // 48 kHz stereo into 16 kHz mono: a third of the frames, channels averaged
+        let mut reformat = Reformat::new((48000, 2), (16000, 1)).unwrap();
+        let mut out = Vec::new();
+        reformat.apply(&[0.2, 0.4].repeat(300), &mut out);
+        assert_eq!(out.len(), 100);
+        assert!(out.iter().all(|s| (*s - 0.3).abs() < 1e-6));
     }
 }
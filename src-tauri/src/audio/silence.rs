@@ -0,0 +1,186 @@
+//! Silence maps for skipping dead air in playback. A note's audio is measured in short
+//! windows, and runs of quiet windows at least `MIN_SILENCE_MS` long become regions the
+//! player can jump over. Quiet is judged against the recording's own noise floor, so a
+//! steady hum in the room still counts as silence. Where a note has separate mic and
+//! system files, a window is only quiet when both are.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audio::converter::StreamDecoder;
+use crate::audio::AudioError;
+
+const WINDOW_MS: u64 = 20;
+
+/// Windows below this RMS (about -46 dBFS) are always quiet
+const MIN_THRESHOLD: f32 = 0.005;
+
+/// ...and above this (about -30 dBFS) never, however noisy the recording
+const MAX_THRESHOLD: f32 = 0.03;
+
+/// Quiet is up to this many times the noise floor
+const FLOOR_MARGIN: f32 = 2.0;
+
+/// Shortest gap worth skipping
+const MIN_SILENCE_MS: u64 = 1500;
+
+/// Left either side of a gap, so skipping doesn't clip the words around it
+const PADDING_MS: u64 = 250;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SilenceRegion {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SilenceMap {
+    pub regions: Vec<SilenceRegion>,
+    pub duration_ms: u64,
+    /// Total length of the regions
+    pub silent_ms: u64,
+}
+
+/// Audio that plays from `offset_ms` into the note; its files play at once
+pub struct SilenceSource {
+    pub offset_ms: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+/// RMS of each `WINDOW_MS` window of the file, over all channels
+fn window_levels(path: &Path) -> Result<Vec<f32>, AudioError> {
+    let mut decoder = StreamDecoder::open(path)?;
+    let channels = decoder.channels().max(1) as usize;
+    let window = (decoder.sample_rate() as u64 * WINDOW_MS / 1000).max(1) as usize * channels;
+
+    let mut levels = Vec::new();
+    let (mut sum_squares, mut count) = (0.0f64, 0);
+    while let Some(samples) = decoder.next_samples() {
+        for &sample in samples {
+            sum_squares += (sample * sample) as f64;
+            count += 1;
+            if count == window {
+                levels.push((sum_squares / count as f64).sqrt() as f32);
+                (sum_squares, count) = (0.0, 0);
+            }
+        }
+    }
+    if count > 0 {
+        levels.push((sum_squares / count as f64).sqrt() as f32);
+    }
+    Ok(levels)
+}
+
+/// The level below which windows are quiet: a margin over the tenth-percentile window
+fn threshold(levels: &[f32]) -> f32 {
+    if levels.is_empty() {
+        return MIN_THRESHOLD;
+    }
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let floor = sorted[sorted.len() / 10];
+    (floor * FLOOR_MARGIN).clamp(MIN_THRESHOLD, MAX_THRESHOLD)
+}
+
+/// Skippable regions in `levels`, with times offset by `offset_ms`
+fn find_silence(levels: &[f32], threshold: f32, offset_ms: u64) -> Vec<SilenceRegion> {
+    let mut regions = Vec::new();
+    let mut run_start = None;
+    // A sentinel loud window closes a run at the end
+    for (i, &level) in levels.iter().chain([&f32::MAX]).enumerate() {
+        match (level < threshold, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                run_start = None;
+                let start_ms = start as u64 * WINDOW_MS;
+                let end_ms = i as u64 * WINDOW_MS;
+                if end_ms - start_ms >= MIN_SILENCE_MS {
+                    regions.push(SilenceRegion {
+                        start_ms: offset_ms + start_ms + PADDING_MS,
+                        end_ms: offset_ms + end_ms - PADDING_MS,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    regions
+}
+
+/// Map the silence in `sources`. Missing files are passed over.
+pub fn silence_map(sources: &[SilenceSource]) -> Result<SilenceMap, AudioError> {
+    let mut regions = Vec::new();
+    let mut duration_ms = 0;
+    for source in sources {
+        // A window is as loud as the loudest file in it
+        let mut levels: Vec<f32> = Vec::new();
+        for path in source.paths.iter().filter(|path| path.exists()) {
+            let file_levels = window_levels(path)?;
+            if file_levels.len() > levels.len() {
+                levels.resize(file_levels.len(), 0.0);
+            }
+            for (level, file_level) in levels.iter_mut().zip(file_levels) {
+                *level = level.max(file_level);
+            }
+        }
+        if levels.is_empty() {
+            continue;
+        }
+        regions.extend(find_silence(&levels, threshold(&levels), source.offset_ms));
+        duration_ms = duration_ms.max(source.offset_ms + levels.len() as u64 * WINDOW_MS);
+    }
+    regions.sort_by_key(|region| region.start_ms);
+    Ok(SilenceMap {
+        silent_ms: regions.iter().map(|r| r.end_ms - r.start_ms).sum(),
+        regions,
+        duration_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows(level: f32, ms: u64) -> Vec<f32> {
+        vec![level; (ms / WINDOW_MS) as usize]
+    }
+
+    #[test]
+    fn test_find_silence() {
+        let levels = [
+            windows(0.1, 1000),
+            windows(0.001, 3000),
+            windows(0.1, 1000),
+            // Too short to skip
+            windows(0.001, 1000),
+            windows(0.1, 1000),
+            windows(0.001, 2000),
+        ]
+        .concat();
+        let regions = find_silence(&levels, threshold(&levels), 10_000);
+        assert_eq!(
+            regions,
+            vec![
+                SilenceRegion {
+                    start_ms: 11_250,
+                    end_ms: 13_750,
+                },
+                SilenceRegion {
+                    start_ms: 17_250,
+                    end_ms: 18_750,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_threshold_follows_noise_floor() {
+        // A steady hum at 0.01 is the floor; speech is well above it
+        let levels = [windows(0.01, 5000), windows(0.2, 5000)].concat();
+        let quiet = threshold(&levels);
+        assert!(quiet > 0.01 && quiet < 0.2);
+        assert_eq!(find_silence(&levels, quiet, 0).len(), 1);
+        assert_eq!(threshold(&windows(0.5, 1000)), MAX_THRESHOLD);
+    }
+}
@@ -0,0 +1,181 @@
+//! Playback speed changes that keep the pitch (WSOLA). The output is built from
+//! half-overlapping Hann-windowed slices of the input, one per `hop` output frames, each
+//! taken from where the speed puts it but nudged by up to `tolerance` frames to where it
+//! best lines up with the audio the previous slice was followed by, so voices stay clear
+//! instead of warbling.
+
+/// Slowest speed playback allows
+pub const MIN_SPEED: f32 = 0.5;
+
+/// Fastest speed playback allows
+pub const MAX_SPEED: f32 = 3.0;
+
+/// Slice length in milliseconds
+const WINDOW_MS: usize = 20;
+
+/// Correlation is measured on every this-many frames, to keep the search cheap
+const SEARCH_STRIDE: usize = 2;
+
+pub struct TimeStretch {
+    channels: usize,
+    /// Slice length in frames
+    window: usize,
+    /// Output frames per slice, half a window
+    hop: usize,
+    /// Furthest a slice may be moved from where the speed puts it, in frames
+    tolerance: usize,
+    speed: f64,
+    hann: Vec<f32>,
+    /// Interleaved input, from frame `base` on
+    input: Vec<f32>,
+    base: usize,
+    /// Where the next slice would start at exactly the speed
+    next: f64,
+    /// Start of the previous slice; None before the first
+    prev: Option<usize>,
+    /// Windowed second half of the previous slice, for the next to overlap
+    overlap: Vec<f32>,
+}
+
+impl TimeStretch {
+    pub fn new(sample_rate: u32, channels: u16, speed: f32) -> Self {
+        let channels = channels.max(1) as usize;
+        let window = (sample_rate as usize * WINDOW_MS / 1000).max(32) & !1;
+        let hop = window / 2;
+        // Periodic Hann, whose halves sum to one when overlapped by a hop
+        let hann = (0..window)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / window as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        Self {
+            channels,
+            window,
+            hop,
+            tolerance: window / 4,
+            speed: speed.clamp(MIN_SPEED, MAX_SPEED) as f64,
+            hann,
+            input: Vec::new(),
+            base: 0,
+            next: 0.0,
+            prev: None,
+            overlap: vec![0.0; hop * channels],
+        }
+    }
+
+    /// Take in interleaved audio, adding whatever output it completes to `out`
+    pub fn process(&mut self, data: &[f32], out: &mut Vec<f32>) {
+        self.input.extend_from_slice(data);
+        let ch = self.channels;
+        loop {
+            let nominal = self.next as usize;
+            let available = self.base + self.input.len() / ch;
+            if nominal + self.tolerance + self.window > available {
+                break;
+            }
+            let start = match self.prev {
+                Some(prev) => self.best_start(prev + self.hop, nominal),
+                None => nominal,
+            };
+
+            let slice =
+                &self.input[(start - self.base) * ch..(start - self.base + self.window) * ch];
+            for i in 0..self.hop {
+                for c in 0..ch {
+                    out.push(self.overlap[i * ch + c] + slice[i * ch + c] * self.hann[i]);
+                }
+            }
+            for i in 0..self.hop {
+                for c in 0..ch {
+                    self.overlap[i * ch + c] =
+                        slice[(self.hop + i) * ch + c] * self.hann[self.hop + i];
+                }
+            }
+            self.prev = Some(start);
+            self.next += self.hop as f64 * self.speed;
+
+            // Keep what the next search and its natural continuation can still reach
+            let keep = (self.next as usize)
+                .saturating_sub(self.tolerance)
+                .min(start + self.hop);
+            if keep > self.base {
+                self.input.drain(..(keep - self.base) * ch);
+                self.base = keep;
+            }
+        }
+    }
+
+    /// The start within `tolerance` of `nominal` whose first half best matches the input
+    /// at `natural`, where the previous slice's audio carries on
+    fn best_start(&self, natural: usize, nominal: usize) -> usize {
+        let lo = nominal.saturating_sub(self.tolerance).max(self.base);
+        let hi = nominal + self.tolerance;
+        let target = self.mono(natural, self.hop);
+        let mut best = (f32::MIN, nominal);
+        for start in lo..=hi {
+            let candidate = self.mono(start, self.hop);
+            let (mut dot, mut energy) = (0.0, 1e-9);
+            for (a, b) in candidate.zip(target.clone()).step_by(SEARCH_STRIDE) {
+                dot += a * b;
+                energy += a * a;
+            }
+            let score = dot / energy.sqrt();
+            if score > best.0 {
+                best = (score, start);
+            }
+        }
+        best.1
+    }
+
+    /// `len` frames from `start`, mixed down
+    fn mono(&self, start: usize, len: usize) -> impl Iterator<Item = f32> + Clone + '_ {
+        let from = (start - self.base) * self.channels;
+        self.input[from..from + len * self.channels]
+            .chunks(self.channels)
+            .map(|frame| frame.iter().sum::<f32>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    fn stretch(speed: f32) -> Vec<f32> {
+        let tone: Vec<f32> = (0..RATE * 4)
+            .map(|i| (2.0 * std::f32::consts::PI * 300.0 * i as f32 / RATE as f32).sin() * 0.5)
+            .collect();
+        let mut stretch = TimeStretch::new(RATE, 1, speed);
+        let mut out = Vec::new();
+        for chunk in tone.chunks(1000) {
+            stretch.process(chunk, &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn test_keeps_pitch() {
+        for speed in [0.5, 1.5, 2.0] {
+            let out = stretch(speed);
+            // Four seconds in take 4 / speed out, less a slice or so at the end
+            let expected = RATE as f32 * 4.0 / speed;
+            assert!(
+                (out.len() as f32 - expected).abs() < RATE as f32 * 0.05,
+                "{speed}"
+            );
+            // Still 300 Hz, skipping the fade-in
+            let second = &out[RATE as usize / 10..RATE as usize * 11 / 10];
+            let hz = crossings(second);
+            assert!((295..=305).contains(&hz), "{speed}: {hz} Hz");
+        }
+    }
+}
@@ -4,13 +4,144 @@
 //! which is used to capture meeting participants' voices.
 
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 
-use crate::audio::AudioError;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::clock::CaptureClock;
+use crate::audio::{waveform, AudioError, SampleRing};
 
 /// Result type for system audio operations
 pub type SystemAudioResult<T> = Result<T, AudioError>;
 
+/// How long a system audio level stands without new audio
+const LEVEL_HOLD: Duration = Duration::from_millis(500);
+
+/// RMS of the latest captured system audio, and when it arrived
+static SYSTEM_LEVEL: Mutex<Option<(f32, Instant)>> = Mutex::new(None);
+
+/// Note the level of system audio just captured, for `system_audio_level`
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub(crate) fn update_system_audio_level(samples: &[f32]) {
+    if samples.is_empty() {
+        return;
+    }
+    let sum: f32 = samples.iter().map(|s| s * s).sum();
+    let rms = (sum / samples.len() as f32).sqrt();
+    *SYSTEM_LEVEL.lock().unwrap_or_else(PoisonError::into_inner) = Some((rms, Instant::now()));
+}
+
+/// RMS of the latest system audio; 0.0 once capture has delivered nothing for a moment,
+/// so a stalled capture reads as silence
+pub fn system_audio_level() -> f32 {
+    match *SYSTEM_LEVEL.lock().unwrap_or_else(PoisonError::into_inner) {
+        Some((rms, at)) if at.elapsed() < LEVEL_HOLD => rms,
+        _ => 0.0,
+    }
+}
+
+/// When the system audio being written to file arrived
+static SYSTEM_CLOCK: Mutex<CaptureClock> = Mutex::new(CaptureClock::new());
+
+/// Start timing a new system audio file
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub(crate) fn reset_system_clock() {
+    *SYSTEM_CLOCK.lock().unwrap_or_else(PoisonError::into_inner) = CaptureClock::new();
+}
+
+/// Note that `frames` frames at `rate` were just written to the system audio file
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub(crate) fn record_system_frames(frames: usize, rate: u32) {
+    SYSTEM_CLOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .record(frames, rate);
+}
+
+/// Timing of the latest system audio file, for lining it up with the mic
+pub fn system_capture_clock() -> CaptureClock {
+    *SYSTEM_CLOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 16kHz mono system audio waiting for live transcription; 30 seconds, a few passes' worth
+static SYSTEM_AUDIO_BUFFER: LazyLock<SampleRing> = LazyLock::new(|| SampleRing::new(16000 * 30));
+
+/// Peaks of the system audio for the live waveform
+static SYSTEM_LIVE_PEAKS: LazyLock<SampleRing> =
+    LazyLock::new(|| SampleRing::new(waveform::LIVE_PEAKS));
+
+/// Add 16kHz mono system audio for live transcription and the live waveform
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+pub(crate) fn push_system_audio_samples(samples: &[f32]) {
+    SYSTEM_AUDIO_BUFFER.push(samples);
+    waveform::push_live_peaks(&SYSTEM_LIVE_PEAKS, samples, 16000);
+}
+
+/// Take all samples from the system audio buffer (clears the buffer)
+pub fn take_system_audio_samples() -> Vec<f32> {
+    SYSTEM_AUDIO_BUFFER.take()
+}
+
+/// System audio samples dropped since the last call because live transcription fell behind
+pub fn take_system_audio_dropped() -> u64 {
+    SYSTEM_AUDIO_BUFFER.take_dropped()
+}
+
+/// System audio peaks for the live waveform since the last call
+pub fn take_system_live_peaks() -> Vec<f32> {
+    SYSTEM_LIVE_PEAKS.take()
+}
+
+/// Bytes held by the system audio buffer between live transcription passes
+pub fn system_audio_buffer_bytes() -> usize {
+    SYSTEM_AUDIO_BUFFER.bytes()
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Payload of "system-audio-interrupted"
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemAudioInterruption {
+    /// Why capture stopped
+    pub reason: String,
+    /// False once capture has resumed
+    pub active: bool,
+    /// How long system audio was missing, once resumed
+    pub seconds: u64,
+}
+
+/// Remember the app to report to. Call once at startup.
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+/// Tell the app that system audio capture stopped on its own, or resumed after `seconds`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn report_interruption(reason: &str, active: bool, seconds: u64) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit(
+            "system-audio-interrupted",
+            SystemAudioInterruption {
+                reason: reason.to_string(),
+                active,
+                seconds,
+            },
+        );
+    }
+}
+
+/// A running app whose audio system audio capture can be limited to or leave out
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturableApp {
+    pub name: String,
+    /// macOS bundle id or Windows executable name; what the captured app and exclusion
+    /// settings are best set to
+    pub id: String,
+    pub pid: i32,
+}
+
 /// Platform-agnostic interface for system audio capture
 pub trait SystemAudioCapture: Send + Sync {
     /// Check if system audio capture is supported on this platform
@@ -32,6 +163,10 @@ pub trait SystemAudioCapture: Send + Sync {
     /// Returns the path to the recorded file
     fn stop(&self) -> SystemAudioResult<Option<PathBuf>>;
 
+    /// Finish the current file and carry on capturing into `output_path`, for a new
+    /// segment, without a gap
+    fn rotate(&self, output_path: PathBuf) -> SystemAudioResult<()>;
+
     /// Check if currently capturing
     fn is_capturing(&self) -> bool;
 }
@@ -40,7 +175,19 @@ pub trait SystemAudioCapture: Send + Sync {
 #[cfg(target_os = "macos")]
 pub fn create_system_audio_capture() -> SystemAudioResult<Arc<dyn SystemAudioCapture>> {
     use super::macos::MacOSSystemAudioCapture;
-    Ok(Arc::new(MacOSSystemAudioCapture::new()))
+    use super::virtual_device::VirtualDeviceCapture;
+
+    // Without ScreenCaptureKit audio (macOS 12) or Screen Recording permission, record
+    // through a virtual loopback device when one is installed. Permission changes take a
+    // restart, so the choice holds for the session.
+    let capture = MacOSSystemAudioCapture::new();
+    let usable =
+        MacOSSystemAudioCapture::is_supported() && capture.has_permission().unwrap_or(false);
+    if !usable && VirtualDeviceCapture::is_supported() {
+        tracing::info!("Capturing system audio from a virtual device instead of ScreenCaptureKit");
+        return Ok(Arc::new(VirtualDeviceCapture::new()));
+    }
+    Ok(Arc::new(capture))
 }
 
 #[cfg(target_os = "windows")]
@@ -59,6 +206,7 @@ pub fn is_system_audio_available() -> bool {
     #[cfg(target_os = "macos")]
     {
         super::macos::MacOSSystemAudioCapture::is_supported()
+            || super::virtual_device::VirtualDeviceCapture::is_supported()
     }
     #[cfg(target_os = "windows")]
     {
@@ -69,3 +217,19 @@ pub fn is_system_audio_available() -> bool {
         false
     }
 }
+
+/// Running apps that system audio capture can be limited to or leave out, by name
+#[cfg(target_os = "macos")]
+pub fn list_capturable_apps() -> SystemAudioResult<Vec<CapturableApp>> {
+    super::macos::MacOSSystemAudioCapture::capturable_apps()
+}
+
+#[cfg(target_os = "windows")]
+pub fn list_capturable_apps() -> SystemAudioResult<Vec<CapturableApp>> {
+    super::windows::capturable_apps()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn list_capturable_apps() -> SystemAudioResult<Vec<CapturableApp>> {
+    Err(AudioError::UnsupportedPlatform)
+}
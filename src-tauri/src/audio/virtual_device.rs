@@ -0,0 +1,322 @@
+//! Fallback system audio capture on macOS through a virtual loopback device (BlackHole,
+//! Rogue Amoeba's Loopback, Soundflower), for when ScreenCaptureKit can't be used: macOS 12
+//! has no ScreenCaptureKit audio, and Screen Recording permission may be refused. The user
+//! sends their output through the device (usually a multi-output device alongside their
+//! speakers), and it is recorded like a microphone. The device is chosen by name in
+//! `devices::SETTING_LOOPBACK_DEVICE`; otherwise the first one installed is used. App
+//! exclusions don't apply: the device only ever carries the mix.
+
+#![cfg(target_os = "macos")]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Sample, SampleFormat};
+use super::capture_format::{self, CaptureFormat};
+use super::devices::{self, RenderDevice};
+use super::encoder::AudioFileWriter;
+use super::macos;
+use super::system_audio::{self, SystemAudioCapture, SystemAudioResult};
+use crate::audio::AudioError;
+
+/// Lowercase name fragments of the virtual loopback devices people install
+const KNOWN_DEVICES: &[&str] = &[
+    "blackhole",
+    "loopback audio",
+    "soundflower",
+    "background music",
+    "vb-cable",
+];
+
+/// How often the capture thread checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The file being written, and its path
+type Output = Arc<Mutex<Option<(AudioFileWriter, PathBuf)>>>;
+
+/// Whether an input device of this name is a virtual loopback device
+fn is_virtual_device(name: &str) -> bool {
+    let name = name.to_lowercase();
+    KNOWN_DEVICES.iter().any(|known| name.contains(known))
+}
+
+/// Installed virtual loopback devices with their names
+fn virtual_devices() -> Vec<(String, cpal::Device)> {
+    let Ok(inputs) = cpal::default_host().input_devices() else {
+        return Vec::new();
+    };
+    inputs
+        .filter_map(|device| Some((device.name().ok()?, device)))
+        .filter(|(name, _)| is_virtual_device(name))
+        .collect()
+}
+
+/// The device to capture: the chosen one when installed, otherwise the first found
+fn capture_device() -> Option<(String, cpal::Device)> {
+    let mut found = virtual_devices();
+    let chosen = devices::selected_loopback_device()
+        .and_then(|chosen| found.iter().position(|(name, _)| *name == chosen));
+    match chosen {
+        Some(index) => Some(found.swap_remove(index)),
+        None => found.into_iter().next(),
+    }
+}
+
+/// Installed virtual loopback devices, marking the one system audio is captured from
+pub fn list_render_devices() -> Result<Vec<RenderDevice>, AudioError> {
+    let selected = capture_device().map(|(name, _)| name);
+    Ok(virtual_devices()
+        .into_iter()
+        .map(|(name, _)| RenderDevice {
+            id: name.clone(),
+            is_default: false,
+            selected: selected.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Write a callback's audio to the file in `format`, and pass it on to live transcription
+fn write(data: &[f32], rate: u32, channels: usize, format: CaptureFormat, output: &Output) {
+    let (left, right): (Vec<f32>, Vec<f32>) = data
+        .chunks_exact(channels)
+        .map(|frame| (frame[0], frame[channels.min(2) - 1]))
+        .unzip();
+    if left.is_empty() {
+        return;
+    }
+    let mono: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .map(|(l, r)| (l + r) / 2.0)
+        .collect();
+    system_audio::update_system_audio_level(&mono);
+
+    if let Some((writer, _)) = output
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+    {
+        let to_i16 = |s: f32| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        let frames = if format.channels > 1 {
+            let left = macos::resample(&left, rate, format.sample_rate);
+            let right = macos::resample(&right, rate, format.sample_rate);
+            for (left, right) in left.iter().zip(&right) {
+                let _ = writer.write_sample(to_i16(*left));
+                let _ = writer.write_sample(to_i16(*right));
+            }
+            left.len().min(right.len())
+        } else {
+            let mono = macos::resample(&mono, rate, format.sample_rate);
+            for sample in &mono {
+                let _ = writer.write_sample(to_i16(*sample));
+            }
+            mono.len()
+        };
+        system_audio::record_system_frames(frames, format.sample_rate);
+    }
+
+    system_audio::push_system_audio_samples(&macos::resample(&mono, rate, 16000));
+}
+
+/// Open `device` and write its audio to `output` in `format`
+fn open_stream(
+    device: &cpal::Device,
+    output: &Output,
+    format: CaptureFormat,
+) -> Result<cpal::Stream, AudioError> {
+    let config = device.default_input_config()?;
+    let rate = config.sample_rate().0;
+    let channels = config.channels().max(1) as usize;
+    let output = output.clone();
+    let mut handle = move |data: &[f32]| write(data, rate, channels, format, &output);
+    let err_fn = |err: cpal::StreamError| {
+        tracing::error!("Virtual device stream error: {}", err);
+    };
+
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => device.build_input_stream(
+            &config.into(),
+            move |data: &[f32], _| handle(data),
+            err_fn,
+            None,
+        )?,
+        SampleFormat::I16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[i16], _| {
+                let float_data: Vec<f32> = data.iter().map(|&s| s.to_float_sample()).collect();
+                handle(&float_data)
+            },
+            err_fn,
+            None,
+        )?,
+        _ => return Err(AudioError::UnsupportedFormat),
+    };
+    stream.play()?;
+    Ok(stream)
+}
+
+/// System audio capture from a virtual loopback device
+pub struct VirtualDeviceCapture {
+    is_capturing: Arc<AtomicBool>,
+    output: Output,
+    /// What the files are written in, from the quality preset when the capture started
+    format: Mutex<CaptureFormat>,
+    /// Holds the stream, which has to stay on the thread that opened it
+    capture_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl VirtualDeviceCapture {
+    pub fn new() -> Self {
+        Self {
+            is_capturing: Arc::new(AtomicBool::new(false)),
+            output: Arc::new(Mutex::new(None)),
+            format: Mutex::new(capture_format::Preset::default().system_format()),
+            capture_thread: Mutex::new(None),
+        }
+    }
+
+    fn take_output(&self) -> Option<(AudioFileWriter, PathBuf)> {
+        self.output
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+}
+
+impl Default for VirtualDeviceCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemAudioCapture for VirtualDeviceCapture {
+    fn is_supported() -> bool {
+        capture_device().is_some()
+    }
+
+    /// Recording from the device needs only microphone access, which recording already has
+    fn has_permission(&self) -> SystemAudioResult<bool> {
+        Ok(true)
+    }
+
+    fn request_permission(&self) -> SystemAudioResult<bool> {
+        Ok(true)
+    }
+
+    fn start(&self, output_path: PathBuf) -> SystemAudioResult<()> {
+        if self.is_capturing.load(Ordering::SeqCst) {
+            return Err(AudioError::AlreadyRecording);
+        }
+        if let Some(chosen) = devices::selected_loopback_device()
+            && !virtual_devices().iter().any(|(name, _)| *name == chosen)
+        {
+            tracing::warn!(
+                "Virtual device \"{}\" isn't installed; using another",
+                chosen
+            );
+        }
+        let (name, device) = capture_device().ok_or(AudioError::NoInputDevice)?;
+
+        let format = capture_format::active().system_format();
+        *self.format.lock().unwrap_or_else(PoisonError::into_inner) = format;
+        let writer = AudioFileWriter::create(&output_path, format.spec())?;
+        *self.output.lock().unwrap_or_else(PoisonError::into_inner) = Some((writer, output_path));
+        system_audio::reset_system_clock();
+        self.is_capturing.store(true, Ordering::SeqCst);
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let is_capturing = self.is_capturing.clone();
+        let output = self.output.clone();
+        let handle = thread::spawn(move || {
+            let stream = match open_stream(&device, &output, format) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+            while is_capturing.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+            }
+            drop(stream);
+        });
+
+        let opened = ready_rx.recv().unwrap_or(Err(AudioError::NoInputDevice));
+        if let Err(e) = opened {
+            self.is_capturing.store(false, Ordering::SeqCst);
+            let _ = handle.join();
+            if let Some((writer, _)) = self.take_output() {
+                let _ = writer.finalize();
+            }
+            return Err(e);
+        }
+        tracing::info!("Capturing system audio from virtual device \"{}\"", name);
+        *self
+            .capture_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(handle);
+        Ok(())
+    }
+
+    fn stop(&self) -> SystemAudioResult<Option<PathBuf>> {
+        if !self.is_capturing.swap(false, Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let handle = self
+            .capture_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        match self.take_output() {
+            Some((writer, path)) => {
+                writer.finalize()?;
+                Ok(Some(path))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn rotate(&self, output_path: PathBuf) -> SystemAudioResult<()> {
+        if !self.is_capturing.load(Ordering::SeqCst) {
+            return Err(AudioError::NotRecording);
+        }
+        let format = *self.format.lock().unwrap_or_else(PoisonError::into_inner);
+        let writer = AudioFileWriter::create(&output_path, format.spec())?;
+        let previous = {
+            let mut output = self.output.lock().unwrap_or_else(PoisonError::into_inner);
+            system_audio::reset_system_clock();
+            output.replace((writer, output_path))
+        };
+        match previous {
+            Some((previous, _)) => previous.finalize(),
+            None => Ok(()),
+        }
+    }
+
+    fn is_capturing(&self) -> bool {
+        self.is_capturing.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_virtual_device() {
+        assert!(is_virtual_device("BlackHole 2ch"));
+        assert!(is_virtual_device("Loopback Audio"));
+        assert!(is_virtual_device("Soundflower (64ch)"));
+        assert!(!is_virtual_device("MacBook Pro Microphone"));
+        assert!(!is_virtual_device("Loopback"));
+    }
+}
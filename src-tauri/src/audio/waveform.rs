@@ -0,0 +1,205 @@
+//! Waveform peaks for the audio player. The file is decoded a packet at a time into
+//! short fine bins (min and max over all channels), which are then merged into the number
+//! of buckets the player asked for, so the frontend can draw a waveform and seek bar from a
+//! few kilobytes instead of the whole recording.
+//!
+//! While recording, the capture callbacks also reduce their audio to peaks every
+//! `LIVE_BIN_MS`, and `start_monitor` sends them on about 30 times a second as
+//! "audio-chunk" events, for a live scrolling waveform.
+
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::{converter, system_audio, AudioError, RecordingPhase, SampleRing};
+use crate::commands::AudioState;
+
+/// Frames per fine bin; about 5 ms at 48 kHz
+const FINE_BIN_FRAMES: usize = 256;
+
+/// Most buckets a caller can ask for
+pub const MAX_BUCKETS: usize = 100_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformPeaks {
+    /// Lowest sample in each bucket, from -1.0 to 1.0
+    pub min: Vec<f32>,
+    /// Highest sample in each bucket
+    pub max: Vec<f32>,
+    pub duration_ms: u64,
+}
+
+/// Min/max peaks of the audio at `path` in up to `buckets` equal slices of time (fewer
+/// for audio shorter than `buckets` fine bins)
+pub fn generate_peaks(path: &Path, buckets: usize) -> Result<WaveformPeaks, AudioError> {
+    let mut bins: Vec<(f32, f32)> = Vec::new();
+    let mut current = (f32::MAX, f32::MIN);
+    let mut in_bin = 0;
+    let mut frames = 0u64;
+
+    let (sample_rate, _) = converter::decode_with(path, |samples, channels| {
+        for frame in samples.chunks(channels.max(1) as usize) {
+            for &sample in frame {
+                current = (current.0.min(sample), current.1.max(sample));
+            }
+            frames += 1;
+            in_bin += 1;
+            if in_bin == FINE_BIN_FRAMES {
+                bins.push(current);
+                current = (f32::MAX, f32::MIN);
+                in_bin = 0;
+            }
+        }
+    })?;
+    if in_bin > 0 {
+        bins.push(current);
+    }
+
+    let (min, max) = merge(&bins, buckets).into_iter().unzip();
+    Ok(WaveformPeaks {
+        min,
+        max,
+        duration_ms: frames * 1000 / sample_rate.max(1) as u64,
+    })
+}
+
+/// Milliseconds of audio behind each live waveform peak
+pub const LIVE_BIN_MS: usize = 10;
+
+/// Live peaks held between chunks; a few seconds' worth, in case the monitor is held up
+pub const LIVE_PEAKS: usize = 1000 / LIVE_BIN_MS * 5;
+
+/// How often "audio-chunk" is sent while recording
+const CHUNK_INTERVAL: Duration = Duration::from_millis(33);
+
+/// How often the monitor checks for a recording otherwise
+const IDLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Payload of "audio-chunk"
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioChunk {
+    /// Peak of each `LIVE_BIN_MS` of mic audio since the last chunk, from 0.0 to 1.0
+    pub mic: Vec<f32>,
+    /// The same for system audio; empty when it isn't being captured
+    pub system: Vec<f32>,
+    pub bin_ms: usize,
+}
+
+/// Add the peaks of interleaved `samples`, `samples_per_sec` of them a second, to `ring`.
+/// Called from capture callbacks, so it never allocates.
+pub fn push_live_peaks(ring: &SampleRing, samples: &[f32], samples_per_sec: usize) {
+    let bin = (samples_per_sec * LIVE_BIN_MS / 1000).max(1);
+    for chunk in samples.chunks(bin) {
+        let peak = chunk.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        ring.push(&[peak.min(1.0)]);
+    }
+}
+
+/// Send the live peaks to the frontend while recording
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        loop {
+            let Some(state) = app.try_state::<AudioState>() else {
+                thread::sleep(IDLE_INTERVAL);
+                continue;
+            };
+            // Taken either way, so peaks from before a pause aren't sent on resume
+            let mic = state.recording.live_peaks.take();
+            let system = system_audio::take_system_live_peaks();
+            if state.recording.get_phase() != RecordingPhase::Recording {
+                thread::sleep(IDLE_INTERVAL);
+                continue;
+            }
+            if !mic.is_empty() || !system.is_empty() {
+                let _ = app.emit(
+                    "audio-chunk",
+                    AudioChunk {
+                        mic,
+                        system,
+                        bin_ms: LIVE_BIN_MS,
+                    },
+                );
+            }
+            thread::sleep(CHUNK_INTERVAL);
+        }
+    });
+}
+
+/// Merge fine bins into `buckets` buckets, each covering an equal share of them
+fn merge(bins: &[(f32, f32)], buckets: usize) -> Vec<(f32, f32)> {
+    let buckets = buckets.clamp(1, MAX_BUCKETS).min(bins.len());
+    (0..buckets)
+        .map(|i| {
+            let start = i * bins.len() / buckets;
+            let end = (i + 1) * bins.len() / buckets;
+            bins[start..end]
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(lo, hi), &(min, max)| {
+                    (lo.min(min), hi.max(max))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peaks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // One second of silence, then one of a loud square wave on the right channel only
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..32000 {
+            let right = if i < 16000 {
+                0
+            } else if i % 2 == 0 {
+                16384
+            } else {
+                -16384
+            };
+            writer.write_sample(0i16).unwrap();
+            writer.write_sample(right as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let peaks = generate_peaks(&path, 4).unwrap();
+        assert_eq!(peaks.duration_ms, 2000);
+        assert_eq!(peaks.min.len(), 4);
+        assert_eq!(peaks.max.len(), 4);
+        assert_eq!(peaks.max[0], 0.0);
+        assert_eq!(peaks.min[1], 0.0);
+        assert!((peaks.max[3] - 0.5).abs() < 1e-3);
+        assert!((peaks.min[2] + 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_live_peaks() {
+        let ring = SampleRing::new(16);
+        // 25ms of 16kHz stereo: two full bins and a half one
+        let samples: Vec<f32> = (0..800)
+            .map(|i| if i == 330 { -0.8 } else { 0.1 })
+            .collect();
+        push_live_peaks(&ring, &samples, 32000);
+        assert_eq!(ring.take(), vec![0.1, 0.8, 0.1]);
+    }
+
+    #[test]
+    fn test_fewer_bins_than_buckets() {
+        let bins = vec![(-0.1, 0.1), (-0.2, 0.3)];
+        assert_eq!(merge(&bins, 10), bins);
+        assert_eq!(merge(&bins, 1), vec![(-0.2, 0.3)]);
+        assert!(merge(&[], 10).is_empty());
+    }
+}
@@ -2,6 +2,15 @@
 //!
 //! WASAPI loopback recording allows capturing all audio output from the system,
 //! which we use to record meeting participants' voices.
+//!
+//! The capture follows the output: when the default device changes (speakers to a
+//! Bluetooth headset mid-meeting) or the endpoint goes away, the loopback is started again
+//! on the current device and the same file carries on. wasapi doesn't expose endpoint
+//! change notifications, so the default is checked once a second.
+//!
+//! On Windows 10 2004+ process loopback narrows the capture to one app's process tree (the
+//! chosen meeting app) or leaves one out (an excluded music player); see `exclusions`.
+//! That mode follows the default output on its own, so it is never restarted.
 
 #![cfg(target_os = "windows")]
 
@@ -10,19 +19,30 @@ use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
-
-use hound::{WavSpec, WavWriter};
-use wasapi::{Device, Direction, SampleType, ShareMode};
-
-use super::system_audio::{SystemAudioCapture, SystemAudioResult};
+use std::time::{Duration, Instant};
+
+use wasapi::{
+    AudioCaptureClient, AudioClient, Device, DeviceCollection, Direction, Handle, SampleType,
+    ShareMode, WaveFormat,
+};
+
+use super::capture_format::{self, CaptureFormat};
+use super::devices::{self, RenderDevice};
+use super::encoder::AudioFileWriter;
+use super::exclusions;
+use super::system_audio::{self, CapturableApp, SystemAudioCapture, SystemAudioResult};
 use crate::audio::AudioError;
 
+/// How often the capture checks that it is on the current output device
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Shared state for audio writing, accessible from the capture thread
 struct AudioWriterState {
-    writer: Option<WavWriter<std::io::BufWriter<std::fs::File>>>,
+    writer: Option<AudioFileWriter>,
     output_path: PathBuf,
     is_active: bool,
+    /// What the file is written in, from the quality preset when the capture started
+    format: CaptureFormat,
 }
 
 /// Global state for the audio writer
@@ -32,26 +52,30 @@ fn get_audio_writer() -> &'static Mutex<Option<AudioWriterState>> {
     AUDIO_WRITER.get_or_init(|| Mutex::new(None))
 }
 
-/// Global buffer for system audio samples (for live transcription)
-static SYSTEM_AUDIO_BUFFER: OnceLock<Mutex<Vec<f32>>> = OnceLock::new();
-
-fn get_system_audio_buffer() -> &'static Mutex<Vec<f32>> {
-    SYSTEM_AUDIO_BUFFER.get_or_init(|| Mutex::new(Vec::new()))
-}
-
-/// Take all samples from the system audio buffer (clears the buffer)
-pub fn take_system_audio_samples() -> Vec<f32> {
-    match get_system_audio_buffer().lock() {
-        Ok(mut buffer) => std::mem::take(&mut *buffer),
-        _ => Vec::new(),
-    }
-}
-
-/// Clear the system audio buffer
-#[allow(dead_code)]
-pub fn clear_system_audio_buffer() {
-    if let Ok(mut buffer) = get_system_audio_buffer().lock() {
-        buffer.clear();
+/// Finalize the file being written and carry on in a new one at `output_path`
+fn switch_writer(output_path: PathBuf) -> Result<(), AudioError> {
+    let format = {
+        let guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+        guard
+            .as_ref()
+            .filter(|state| state.is_active)
+            .map(|state| state.format)
+            .ok_or(AudioError::NotRecording)?
+    };
+    let writer = AudioFileWriter::create(&output_path, format.spec())?;
+    let previous = {
+        let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+        let state = guard
+            .as_mut()
+            .filter(|state| state.is_active)
+            .ok_or(AudioError::NotRecording)?;
+        state.output_path = output_path;
+        system_audio::reset_system_clock();
+        state.writer.replace(writer)
+    };
+    match previous {
+        Some(previous) => previous.finalize(),
+        None => Ok(()),
     }
 }
 
@@ -75,6 +99,131 @@ fn get_default_render_device() -> Result<Device, AudioError> {
     })
 }
 
+/// Active render devices with their endpoint ids
+fn render_devices() -> Result<Vec<(String, Device)>, AudioError> {
+    ensure_com_initialized();
+    let collection = DeviceCollection::new(&Direction::Render).map_err(|e| {
+        AudioError::PermissionDenied(format!("Failed to list output devices: {}", e))
+    })?;
+    let count = collection.get_nbr_devices().map_err(|e| {
+        AudioError::PermissionDenied(format!("Failed to list output devices: {}", e))
+    })?;
+    Ok((0..count)
+        .filter_map(|index| collection.get_device_at_index(index).ok())
+        .filter_map(|device| Some((device.get_id().ok()?, device)))
+        .collect())
+}
+
+/// Output devices, marking the default and the one loopback capture records
+pub fn list_render_devices() -> Result<Vec<RenderDevice>, AudioError> {
+    let default_id = get_default_render_device()
+        .ok()
+        .and_then(|device| device.get_id().ok());
+    let devices = render_devices()?;
+    // The default is used when nothing is chosen or the choice isn't connected
+    let selected = devices::selected_loopback_device()
+        .filter(|id| devices.iter().any(|(other, _)| other == id))
+        .or_else(|| default_id.clone());
+
+    Ok(devices
+        .into_iter()
+        .map(|(id, device)| RenderDevice {
+            name: device.get_friendlyname().unwrap_or_else(|_| id.clone()),
+            is_default: default_id.as_ref() == Some(&id),
+            selected: selected.as_ref() == Some(&id),
+            id,
+        })
+        .collect())
+}
+
+/// The render device to capture: the chosen one if connected, otherwise the default
+fn get_render_device() -> Result<Device, AudioError> {
+    if let Some(id) = devices::selected_loopback_device() {
+        let found = render_devices()?.into_iter().find(|(other, _)| *other == id);
+        match found {
+            Some((_, device)) => return Ok(device),
+            None => tracing::warn!("Output device {} isn't connected; using the default", id),
+        }
+    }
+    get_default_render_device()
+}
+
+/// Running processes as (pid, image name, session number)
+fn running_processes() -> Option<Vec<(u32, String, u32)>> {
+    // CSV: "Image Name","PID","Session Name","Session#","Mem Usage"
+    let output = crate::process::no_window(&mut std::process::Command::new("tasklist"))
+        .args(["/fo", "csv", "/nh"])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    Some(
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split("\",\"").map(|f| f.trim_matches('"'));
+                let name = fields.next()?.to_string();
+                let pid = fields.next()?.parse().ok()?;
+                let session = fields.nth(1)?.parse().ok()?;
+                Some((pid, name, session))
+            })
+            .collect(),
+    )
+}
+
+/// The first running process of the app listed as `entry`, as (pid, image name)
+fn find_process(running: &[(u32, String, u32)], entry: &String) -> Option<(u32, String)> {
+    running
+        .iter()
+        .find(|(_, name, _)| exclusions::is_listed(std::slice::from_ref(entry), &[name]))
+        .map(|(pid, name, _)| (*pid, name.clone()))
+}
+
+/// The first app on the exclusion list that is running, as (pid, image name)
+fn excluded_process() -> Option<(u32, String)> {
+    let excluded = exclusions::excluded_apps();
+    if excluded.is_empty() {
+        return None;
+    }
+    let running = running_processes()?;
+    excluded.iter().find_map(|entry| find_process(&running, entry))
+}
+
+/// The app system audio is limited to, as (pid, image name), when it is running. Its
+/// first process found is taken as the root of its process tree; tasklist lists processes
+/// in about the order they started.
+fn captured_process() -> Option<(u32, String)> {
+    let captured = exclusions::captured_app()?;
+    let found = find_process(&running_processes()?, &captured);
+    if found.is_none() {
+        tracing::warn!("{} isn't running; capturing all system audio", captured);
+    }
+    found
+}
+
+/// Apps running in the user's session, one per executable, that system audio capture can
+/// be limited to or leave out
+pub fn capturable_apps() -> Result<Vec<CapturableApp>, AudioError> {
+    let own_pid = std::process::id();
+    let running = running_processes()
+        .ok_or_else(|| AudioError::IoError(std::io::Error::other("Failed to list running apps")))?;
+    // Session 0 holds the services
+    let mut apps: Vec<CapturableApp> = running
+        .into_iter()
+        .filter(|(pid, _, session)| *session != 0 && *pid != own_pid)
+        .map(|(pid, id, _)| CapturableApp {
+            name: std::path::Path::new(&id)
+                .file_stem()
+                .map_or_else(|| id.clone(), |stem| stem.to_string_lossy().into_owned()),
+            pid: pid as i32,
+            id,
+        })
+        .collect();
+    // Stable, so the first process of each app is the one kept
+    apps.sort_by_key(|app| app.id.to_lowercase());
+    apps.dedup_by(|a, b| a.id.eq_ignore_ascii_case(&b.id));
+    Ok(apps)
+}
+
 /// Downsample audio from source rate to 16kHz mono for Whisper
 fn downsample_to_16k_mono(samples: &[f32], src_rate: u32, channels: u16) -> Vec<f32> {
     // Convert stereo to mono by averaging channels
@@ -122,21 +271,13 @@ impl WindowsSystemAudioCapture {
         get_default_render_device().is_ok()
     }
 
-    /// Run the capture loop in a separate thread
-    fn run_capture_loop(
-        is_capturing: Arc<AtomicBool>,
-        output_path: PathBuf,
-    ) -> Result<(), AudioError> {
-        // Initialize COM for this thread (get_default_render_device also does this,
-        // but we call it explicitly here for the capture thread)
-        if !ensure_com_initialized() {
-            return Err(AudioError::PermissionDenied(
-                "Failed to initialize COM for capture thread".to_string(),
-            ));
-        }
-
-        // Get default render device
-        let device = get_default_render_device()?;
+    /// Loopback client on the chosen (or default) render device, capturing its whole mix,
+    /// with the device's endpoint id
+    fn device_loopback_client() -> Result<(AudioClient, WaveFormat, String), AudioError> {
+        let device = get_render_device()?;
+        let device_id = device.get_id().map_err(|e| {
+            AudioError::PermissionDenied(format!("Failed to get device id: {}", e))
+        })?;
 
         // Get the audio client for loopback capture
         let mut audio_client = device.get_iaudioclient().map_err(|e| {
@@ -148,9 +289,6 @@ impl WindowsSystemAudioCapture {
             AudioError::PermissionDenied(format!("Failed to get mix format: {}", e))
         })?;
 
-        let sample_rate = wave_format.get_samplespersec();
-        let channels = wave_format.get_nchannels();
-
         // Get the default device period for buffer sizing
         let default_period = audio_client.get_periods().map_err(|e| {
             AudioError::PermissionDenied(format!("Failed to get device periods: {}", e))
@@ -170,8 +308,70 @@ impl WindowsSystemAudioCapture {
                 AudioError::PermissionDenied(format!("Failed to initialize audio client: {}", e))
             })?;
 
+        Ok((audio_client, wave_format, device_id))
+    }
+
+    /// Loopback client capturing only `pid` and its children with `include_tree`, otherwise
+    /// everything except them (Windows 10 2004+). The mix format isn't available in this
+    /// mode, so 48kHz stereo float is requested and converted to.
+    fn process_loopback_client(
+        pid: u32,
+        include_tree: bool,
+    ) -> Result<(AudioClient, WaveFormat), AudioError> {
+        // PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE or
+        // PROCESS_LOOPBACK_MODE_EXCLUDE_TARGET_PROCESS_TREE
+        let mut audio_client =
+            AudioClient::new_application_loopback_client(pid, include_tree).map_err(|e| {
+                AudioError::PermissionDenied(format!("Failed to get audio client: {}", e))
+            })?;
+        let wave_format = WaveFormat::new(32, 32, &SampleType::Float, 48000, 2, None);
+        audio_client
+            .initialize_client(
+                &wave_format,
+                200_000, // 20ms; the period is ignored in this mode
+                &Direction::Capture,
+                &ShareMode::Shared,
+                true, // Convert to the requested format
+            )
+            .map_err(|e| {
+                AudioError::PermissionDenied(format!("Failed to initialize audio client: {}", e))
+            })?;
+        Ok((audio_client, wave_format))
+    }
+
+    /// Start a loopback capture: of the captured app alone when one is chosen and running,
+    /// otherwise of the whole mix, leaving out an excluded app when one is running. Process
+    /// loopback can only leave out a single process tree, so that's the first listed app
+    /// found.
+    fn open_loopback() -> Result<Loopback, AudioError> {
+        let target = match captured_process() {
+            Some((pid, name)) => Some((pid, name, true)),
+            None => excluded_process().map(|(pid, name)| (pid, name, false)),
+        };
+        let (audio_client, wave_format, device_id) = match target {
+            Some((pid, name, include)) => match Self::process_loopback_client(pid, include) {
+                Ok((client, format)) => {
+                    if include {
+                        tracing::info!("Capturing system audio from {} (pid {}) only", name, pid);
+                    } else {
+                        tracing::info!("Excluding {} (pid {}) from system audio", name, pid);
+                    }
+                    (client, format, None)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to start process loopback for {}: {}", name, e);
+                    let (client, format, id) = Self::device_loopback_client()?;
+                    (client, format, Some(id))
+                }
+            },
+            None => {
+                let (client, format, id) = Self::device_loopback_client()?;
+                (client, format, Some(id))
+            }
+        };
+
         // Set up event handle for event-driven capture (required when using EVENTCALLBACK flag)
-        let _event_handle = audio_client.set_get_eventhandle().map_err(|e| {
+        let event = audio_client.set_get_eventhandle().map_err(|e| {
             AudioError::PermissionDenied(format!("Failed to set event handle: {}", e))
         })?;
 
@@ -180,28 +380,6 @@ impl WindowsSystemAudioCapture {
             AudioError::PermissionDenied(format!("Failed to get capture client: {}", e))
         })?;
 
-        // Create WAV writer with standard format (48kHz stereo 16-bit)
-        let spec = WavSpec {
-            channels: 2,
-            sample_rate: 48000,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
-
-        let writer = WavWriter::create(&output_path, spec).map_err(|e| {
-            AudioError::IoError(std::io::Error::other(format!("Failed to create WAV file: {}", e)))
-        })?;
-
-        // Set up global audio writer state
-        {
-            let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
-            *guard = Some(AudioWriterState {
-                writer: Some(writer),
-                output_path: output_path.clone(),
-                is_active: true,
-            });
-        }
-
         // Ensure stream is in clean state before starting
         let _ = audio_client.stop_stream(); // Ignore error if not running
         let _ = audio_client.reset_stream(); // Reset to clean state
@@ -216,8 +394,57 @@ impl WindowsSystemAudioCapture {
             AudioError::PermissionDenied(format!("Failed to get sample format: {}", e))
         })?;
 
+        Ok(Loopback {
+            client: audio_client,
+            capture: capture_client,
+            _event: event,
+            sample_rate: wave_format.get_samplespersec(),
+            channels: wave_format.get_nchannels(),
+            sample_type,
+            device_id,
+        })
+    }
+
+    /// Run the capture loop in a separate thread
+    fn run_capture_loop(
+        is_capturing: Arc<AtomicBool>,
+        output_path: PathBuf,
+    ) -> Result<(), AudioError> {
+        // Initialize COM for this thread (get_default_render_device also does this,
+        // but we call it explicitly here for the capture thread)
+        if !ensure_com_initialized() {
+            return Err(AudioError::PermissionDenied(
+                "Failed to initialize COM for capture thread".to_string(),
+            ));
+        }
+
+        let mut loopback = Self::open_loopback()?;
+
+        // Create the file writer in the quality preset's format
+        let format = capture_format::active().system_format();
+        let writer = AudioFileWriter::create(&output_path, format.spec()).map_err(|e| {
+            AudioError::IoError(std::io::Error::other(format!(
+                "Failed to create audio file: {}",
+                e
+            )))
+        })?;
+
+        // Set up global audio writer state
+        system_audio::reset_system_clock();
+        {
+            let mut guard = get_audio_writer().lock().map_err(|_| AudioError::LockError)?;
+            *guard = Some(AudioWriterState {
+                writer: Some(writer),
+                output_path: output_path.clone(),
+                is_active: true,
+                format,
+            });
+        }
+
         // Buffer for reading audio data
         let mut audio_data: VecDeque<u8> = VecDeque::new();
+        let mut last_device_check = Instant::now();
+        let mut device_lost = false;
 
         // Capture loop - use polling mode (event-driven may not work well with loopback)
         while is_capturing.load(Ordering::Relaxed) {
@@ -226,10 +453,11 @@ impl WindowsSystemAudioCapture {
             thread::sleep(Duration::from_millis(10));
 
             // Read available frames
-            if let Ok(Some(frames)) = capture_client.get_next_nbr_frames() {
-                if frames > 0 {
+            match loopback.capture.get_next_nbr_frames() {
+                Ok(Some(frames)) if frames > 0 => {
                     // Read the audio data into the buffer
-                    if capture_client
+                    if loopback
+                        .capture
                         .read_from_device_to_deque(&mut audio_data)
                         .is_ok()
                     {
@@ -237,15 +465,41 @@ impl WindowsSystemAudioCapture {
                         let data: Vec<u8> = audio_data.drain(..).collect();
                         if !data.is_empty() {
                             // Process the audio data
-                            process_audio_data(&data, sample_rate, channels, &sample_type);
+                            process_audio_data(
+                                &data,
+                                loopback.sample_rate,
+                                loopback.channels,
+                                &loopback.sample_type,
+                            );
                         }
                     }
                 }
+                Ok(_) => {}
+                // The endpoint was removed or reconfigured
+                Err(_) => device_lost = true,
+            }
+
+            if last_device_check.elapsed() < DEVICE_CHECK_INTERVAL {
+                continue;
+            }
+            last_device_check = Instant::now();
+            if !device_lost && !loopback.is_stale() {
+                continue;
+            }
+            // Samples are converted per buffer, so the file carries on in the same format
+            match Self::open_loopback() {
+                Ok(new_loopback) => {
+                    let _ = loopback.client.stop_stream();
+                    loopback = new_loopback;
+                    device_lost = false;
+                    tracing::info!("System audio capture moved to the current output device");
+                }
+                Err(e) => tracing::warn!("Failed to move system audio capture: {}", e),
             }
         }
 
         // Stop the stream
-        let _ = audio_client.stop_stream();
+        let _ = loopback.client.stop_stream();
 
         // Finalize WAV file
         {
@@ -262,6 +516,35 @@ impl WindowsSystemAudioCapture {
     }
 }
 
+/// A started loopback capture
+struct Loopback {
+    client: AudioClient,
+    capture: AudioCaptureClient,
+    /// Kept alive with the client
+    _event: Handle,
+    sample_rate: u32,
+    channels: u16,
+    sample_type: SampleType,
+    /// Endpoint captured; None for process loopback, which follows the default output
+    /// on its own
+    device_id: Option<String>,
+}
+
+impl Loopback {
+    /// Whether the capture should move: the default output changed (the user switched to
+    /// a headset) or a chosen device was reconnected
+    fn is_stale(&self) -> bool {
+        let Some(id) = &self.device_id else {
+            return false;
+        };
+        get_render_device()
+            .ok()
+            .and_then(|device| device.get_id().ok())
+            .is_some_and(|current| current != *id)
+    }
+}
+
+
 /// Process audio data from WASAPI and write to file/buffer
 /// Data is interleaved: [L0, R0, L1, R1, ...] for stereo
 fn process_audio_data(data: &[u8], sample_rate: u32, channels: u16, sample_type: &SampleType) {
@@ -294,10 +577,16 @@ fn process_audio_data(data: &[u8], sample_rate: u32, channels: u16, sample_type:
             .collect(),
     };
 
+    system_audio::update_system_audio_level(&float_samples);
+
     // Write to WAV file
     if let Ok(mut guard) = get_audio_writer().lock() {
         if let Some(ref mut state) = *guard {
             if state.is_active {
+                let CaptureFormat {
+                    sample_rate: rate,
+                    channels: file_channels,
+                } = state.format;
                 if let Some(ref mut writer) = state.writer {
                     // Extract left and right channels from interleaved data
                     let mut left_samples = Vec::with_capacity(num_frames);
@@ -314,9 +603,9 @@ fn process_audio_data(data: &[u8], sample_rate: u32, channels: u16, sample_type:
                         right_samples.push(right);
                     }
 
-                    // Resample if needed (device might not be 48kHz)
-                    let (left_resampled, right_resampled) = if sample_rate != 48000 {
-                        let ratio = sample_rate as f32 / 48000.0;
+                    // Resample if needed (device might not be at the file's rate)
+                    let (left_resampled, right_resampled) = if sample_rate != rate {
+                        let ratio = sample_rate as f32 / rate as f32;
                         let new_len = (num_frames as f32 / ratio) as usize;
 
                         let resample = |src: &[f32]| -> Vec<f32> {
@@ -333,27 +622,32 @@ fn process_audio_data(data: &[u8], sample_rate: u32, channels: u16, sample_type:
                         (left_samples, right_samples)
                     };
 
-                    // Write interleaved stereo samples
-                    for i in 0..left_resampled.len().min(right_resampled.len()) {
+                    // Write interleaved stereo samples, or both sides averaged for mono
+                    let frames = left_resampled.len().min(right_resampled.len());
+                    for i in 0..frames {
                         let left_sample = left_resampled[i];
                         let right_sample = right_resampled[i];
 
+                        if file_channels < 2 {
+                            let mono = (left_sample + right_sample) / 2.0;
+                            let _ = writer.write_sample((mono.clamp(-1.0, 1.0) * 32767.0) as i16);
+                            continue;
+                        }
                         let left_i16 = (left_sample.clamp(-1.0, 1.0) * 32767.0) as i16;
                         let right_i16 = (right_sample.clamp(-1.0, 1.0) * 32767.0) as i16;
 
                         let _ = writer.write_sample(left_i16);
                         let _ = writer.write_sample(right_i16);
                     }
+                    system_audio::record_system_frames(frames, rate);
                 }
             }
         }
     }
 
     // Push to system audio buffer for live transcription (downsampled to 16kHz mono)
-    if let Ok(mut buffer) = get_system_audio_buffer().lock() {
-        let downsampled = downsample_to_16k_mono(&float_samples, sample_rate, channels);
-        buffer.extend(downsampled);
-    }
+    let downsampled = downsample_to_16k_mono(&float_samples, sample_rate, channels);
+    system_audio::push_system_audio_samples(&downsampled);
 }
 
 impl SystemAudioCapture for WindowsSystemAudioCapture {
@@ -430,6 +724,13 @@ impl SystemAudioCapture for WindowsSystemAudioCapture {
         Ok(output_path)
     }
 
+    fn rotate(&self, output_path: PathBuf) -> SystemAudioResult<()> {
+        if !self.is_capturing.load(Ordering::SeqCst) {
+            return Err(AudioError::NotRecording);
+        }
+        switch_writer(output_path)
+    }
+
     fn is_capturing(&self) -> bool {
         self.is_capturing.load(Ordering::Relaxed)
     }
@@ -0,0 +1,268 @@
+//! Writing recorded samples to disk away from the audio callback. The callback only hands
+//! each buffer to a bounded queue; a writer thread per recording drains it into the
+//! recording file (WAV or FLAC) through a large buffer, so a slow or busy disk never
+//! stalls capture. If the queue fills up the callback drops the buffer instead of
+//! waiting, and the writer reports the gap. Write failures are logged and emitted as
+//! "recording-write-error". A recording can move on to a new file mid-stream, for segment
+//! rotation; the switch goes through the same queue, so no samples are lost or misfiled.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, JoinHandle};
+
+use hound::WavSpec;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::encoder::AudioFileWriter;
+use crate::audio::AudioError;
+
+/// Buffers waiting to be written; audio callbacks usually deliver 10-20 ms each, so this
+/// rides out several seconds of disk stalls
+const QUEUE_BUFFERS: usize = 512;
+
+/// Bytes collected before each write to disk
+const WRITE_BUFFER_BYTES: usize = 256 * 1024;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Payload of "recording-write-error"
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteErrorEvent {
+    pub path: String,
+    pub error: String,
+}
+
+/// Let writer threads emit events. Call from setup.
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+enum Message {
+    Samples(Vec<f32>),
+    /// Finish the current file and carry on in this one
+    Switch(AudioFileWriter, PathBuf),
+}
+
+/// Hands samples from the audio callback to the writer thread without blocking
+#[derive(Clone)]
+pub struct SampleSender {
+    tx: SyncSender<Message>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SampleSender {
+    pub fn send(&self, data: &[f32]) {
+        if let Err(TrySendError::Full(Message::Samples(data))) =
+            self.tx.try_send(Message::Samples(data.to_vec()))
+        {
+            self.dropped.fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The writer thread of one recording, writing to one file at a time
+pub struct SampleWriter {
+    sender: Option<SampleSender>,
+    thread: Option<JoinHandle<()>>,
+    spec: WavSpec,
+}
+
+impl SampleWriter {
+    /// Create the file and start its writer thread
+    pub fn create(path: &Path, spec: WavSpec) -> Result<Self, AudioError> {
+        let writer = open(path, spec)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_BUFFERS);
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        let path = path.to_path_buf();
+        let thread = {
+            let dropped = dropped.clone();
+            thread::Builder::new()
+                .name("wav-writer".to_string())
+                .spawn(move || write_samples(writer, rx, &dropped, path, spec))?
+        };
+
+        Ok(Self {
+            sender: Some(SampleSender { tx, dropped }),
+            thread: Some(thread),
+            spec,
+        })
+    }
+
+    pub fn sender(&self) -> SampleSender {
+        self.sender.clone().expect("writer already finished")
+    }
+
+    /// Create `path` and write everything sent from now on there, finalizing the current
+    /// file once what was sent before is written
+    pub fn switch_to(&self, path: &Path) -> Result<(), AudioError> {
+        let writer = open(path, self.spec)?;
+        let sender = self.sender.as_ref().ok_or(AudioError::NotRecording)?;
+        sender
+            .tx
+            .send(Message::Switch(writer, path.to_path_buf()))
+            .map_err(|_| AudioError::NotRecording)
+    }
+
+    /// Write what is still queued and finalize the file. Every sender must be dropped
+    /// first (by dropping the stream whose callback holds it), or this waits for them.
+    pub fn finish(mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn open(path: &Path, spec: WavSpec) -> Result<AudioFileWriter, AudioError> {
+    let file = BufWriter::with_capacity(WRITE_BUFFER_BYTES, File::create(path)?);
+    AudioFileWriter::new(path, file, spec)
+}
+
+fn write_samples(
+    mut writer: AudioFileWriter,
+    rx: Receiver<Message>,
+    dropped: &AtomicU64,
+    mut path: PathBuf,
+    spec: WavSpec,
+) {
+    let mut failed = false;
+    let mut dropped_total = 0;
+
+    // Ends once the stream and the `SampleWriter` have let go of their senders
+    for message in rx {
+        let data = match message {
+            Message::Samples(data) => data,
+            Message::Switch(next, next_path) => {
+                let done = std::mem::replace(&mut writer, next);
+                finish_file(done, &path, dropped_total, spec);
+                path = next_path;
+                (failed, dropped_total) = (false, 0);
+                continue;
+            }
+        };
+        // Keep draining after a failure so the callback never finds the queue full
+        if failed {
+            continue;
+        }
+        for sample in data {
+            if let Err(e) = writer.write_sample(to_i16(sample)) {
+                report(&path, format!("Failed to write recording: {}", e));
+                failed = true;
+                break;
+            }
+        }
+
+        let newly_dropped = dropped.swap(0, Ordering::Relaxed);
+        if newly_dropped > 0 && dropped_total == 0 {
+            report(
+                &path,
+                "The disk can't keep up; some audio was lost".to_string(),
+            );
+        }
+        dropped_total += newly_dropped;
+    }
+
+    finish_file(writer, &path, dropped_total, spec);
+}
+
+/// Finalize a file, noting how much audio it lost
+fn finish_file(writer: AudioFileWriter, path: &Path, dropped_total: u64, spec: WavSpec) {
+    if dropped_total > 0 {
+        let samples_per_sec = spec.sample_rate as u64 * spec.channels as u64;
+        tracing::warn!(
+            "Recording {} lost {} ms of audio to a slow disk",
+            path.display(),
+            dropped_total * 1000 / samples_per_sec.max(1)
+        );
+    }
+    if let Err(e) = writer.finalize() {
+        report(path, format!("Failed to finish recording: {}", e));
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32) as i16
+}
+
+fn report(path: &Path, error: String) {
+    tracing::error!("{}: {}", path.display(), error);
+    if let Some(app) = APP.get() {
+        let _ = app.emit(
+            "recording-write-error",
+            WriteErrorEvent {
+                path: path.to_string_lossy().to_string(),
+                error,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_everything_sent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.wav");
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let writer = SampleWriter::create(&path, spec).unwrap();
+        let sender = writer.sender();
+        for _ in 0..10 {
+            sender.send(&[0.0, 0.5, -0.5]);
+        }
+        drop(sender);
+        writer.finish();
+
+        let samples: Vec<i16> = hound::WavReader::open(&path)
+            .unwrap()
+            .into_samples()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples.len(), 30);
+        assert_eq!(&samples[..3], &[0, to_i16(0.5), to_i16(-0.5)]);
+    }
+
+    #[test]
+    fn test_switch_keeps_samples_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.wav");
+        let second = dir.path().join("b.wav");
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let writer = SampleWriter::create(&first, spec).unwrap();
+        let sender = writer.sender();
+        sender.send(&[0.25; 100]);
+        writer.switch_to(&second).unwrap();
+        sender.send(&[0.5; 50]);
+        drop(sender);
+        writer.finish();
+
+        let read = |path: &Path| -> Vec<i16> {
+            hound::WavReader::open(path)
+                .unwrap()
+                .into_samples()
+                .map(|s| s.unwrap())
+                .collect()
+        };
+        assert_eq!(read(&first), vec![to_i16(0.25); 100]);
+        assert_eq!(read(&second), vec![to_i16(0.5); 50]);
+    }
+}
@@ -0,0 +1,386 @@
+//! Pause recording while the machine is locked or asleep, so locked-laptop hours stay out of
+//! recordings and the transcript's timestamps don't jump across a suspend. A monitor thread
+//! polls the session lock state (only while something is recording) and hears about sleep
+//! and wake from the OS (NSWorkspace on macOS, suspend/resume notifications on Windows);
+//! elsewhere it notices a suspend from the wall clock jumping past its sleep. On unlock/wake
+//! the recording is resumed, or the user is asked to, depending on `SETTING_ON_RETURN`.
+//! Live transcription follows the recording phase, so it pauses and resumes with it.
+//!
+//! Audio streams don't survive a suspend, so sleep is handled even with `SETTING_ENABLED`
+//! off: the recording is paused as the machine goes to sleep, which finishes the segment's
+//! files, and resumed on wake into a new segment without asking.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{self, AudioState};
+use crate::db::Database;
+use crate::notifications;
+use crate::recorder_widget::PauseToggledEvent;
+
+/// "true" pauses recording on lock and sleep; off, sleep still pauses and wake resumes
+pub const SETTING_ENABLED: &str = "auto_pause_on_lock";
+/// What to do on unlock/wake: "resume" or "prompt"
+pub const SETTING_ON_RETURN: &str = "auto_pause_on_return";
+
+pub const ON_RETURN: &[&str] = &["resume", "prompt"];
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// A wall clock jump this far past the poll interval means the machine was suspended
+const SUSPEND_GAP: Duration = Duration::from_secs(20);
+
+/// The recording this module paused and hasn't resumed yet
+static AUTO_PAUSED: Mutex<Option<AutoPauseEvent>> = Mutex::new(None);
+
+/// What the OS reports about sleep
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PowerEvent {
+    Sleep,
+    Wake,
+}
+
+/// Payload of "recording-auto-paused", "recording-auto-resumed" and "recording-resume-prompt"
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoPauseEvent {
+    pub note_id: String,
+    /// "lock" or "sleep"
+    pub reason: &'static str,
+}
+
+fn setting(app: &AppHandle, key: &str) -> Option<String> {
+    app.try_state::<Database>()?.get_setting(key).ok().flatten()
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    setting(app, SETTING_ENABLED).is_none_or(|v| v == "true")
+}
+
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    let (power_tx, power_rx) = mpsc::channel();
+    let notified = platform::watch_power(power_tx);
+    thread::spawn(move || {
+        let mut last_tick = SystemTime::now();
+        let mut was_locked = false;
+
+        loop {
+            let power = match power_rx.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(POLL_INTERVAL);
+                    None
+                }
+            };
+
+            let now = SystemTime::now();
+            let clock_jumped = now
+                .duration_since(last_tick)
+                .is_ok_and(|gap| gap > POLL_INTERVAL + SUSPEND_GAP);
+            last_tick = now;
+
+            let Some(state) = app.try_state::<AudioState>() else {
+                continue;
+            };
+            let auto_paused = AUTO_PAUSED.lock().is_ok_and(|p| p.is_some());
+            if state.recording.get_phase() == RecordingPhase::Idle && !auto_paused {
+                was_locked = false;
+                continue;
+            }
+            let enabled = enabled(&app);
+            let locked = (enabled || auto_paused) && platform::is_session_locked();
+
+            // With notifications, a wake also pauses, in case the machine went to sleep
+            // before the pause was done
+            let slept = match power {
+                Some(PowerEvent::Sleep) => {
+                    pause(&app, "sleep");
+                    false
+                }
+                Some(PowerEvent::Wake) => true,
+                None => clock_jumped && !notified,
+            };
+            if slept {
+                pause(&app, "sleep");
+                if !locked {
+                    on_return(&app);
+                }
+            } else if enabled && locked && !was_locked {
+                pause(&app, "lock");
+            } else if !locked && was_locked {
+                on_return(&app);
+            }
+            was_locked = locked;
+        }
+    });
+}
+
+fn pause(app: &AppHandle, reason: &'static str) {
+    if app.state::<AudioState>().recording.get_phase() != RecordingPhase::Recording {
+        return;
+    }
+    let note_id = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+
+    match commands::toggle_pause_active_recording(app) {
+        Ok(Some(true)) => {
+            let _ = app.emit(
+                "recording-pause-toggled",
+                PauseToggledEvent {
+                    note_id: note_id.clone(),
+                    paused: true,
+                },
+            );
+            if let Some(note_id) = note_id {
+                let event = AutoPauseEvent { note_id, reason };
+                tracing::info!("Paused recording of {} ({})", event.note_id, reason);
+                let _ = app.emit("recording-auto-paused", &event);
+                *AUTO_PAUSED.lock().unwrap_or_else(|e| e.into_inner()) = Some(event);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to pause recording on {}: {}", reason, e),
+    }
+}
+
+fn on_return(app: &AppHandle) {
+    let Some(event) = AUTO_PAUSED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    else {
+        return;
+    };
+    // Paused for sleep with auto-pause off, the recording was never meant to stop
+    let resume_now = (event.reason == "sleep" && !enabled(app))
+        || setting(app, SETTING_ON_RETURN).as_deref() == Some("resume");
+    if resume_now {
+        if let Err(e) = resume(app) {
+            tracing::warn!("Failed to resume recording: {}", e);
+        }
+        return;
+    }
+
+    let _ = app.emit("recording-resume-prompt", &event);
+    notifications::notify(
+        "Recording paused",
+        match event.reason {
+            "sleep" => "Paused while the computer was asleep. Open Note67 to resume.",
+            _ => "Paused while the screen was locked. Open Note67 to resume.",
+        },
+    );
+}
+
+/// Resume the recording this module paused. Returns false when there is nothing to resume
+/// (it was resumed, stopped or replaced in the meantime).
+fn resume(app: &AppHandle) -> Result<bool, String> {
+    let Some(event) = AUTO_PAUSED.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return Ok(false);
+    };
+
+    let state = app.state::<AudioState>();
+    let current = state
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    if state.recording.get_phase() != RecordingPhase::Paused
+        || current.as_deref() != Some(event.note_id.as_str())
+    {
+        return Ok(false);
+    }
+
+    if commands::toggle_pause_active_recording(app)? != Some(false) {
+        return Ok(false);
+    }
+    let _ = app.emit(
+        "recording-pause-toggled",
+        PauseToggledEvent {
+            note_id: Some(event.note_id.clone()),
+            paused: false,
+        },
+    );
+    let _ = app.emit("recording-auto-resumed", &event);
+    Ok(true)
+}
+
+/// Answer to "recording-resume-prompt": resume the recording that was paused on lock/sleep
+#[tauri::command]
+pub fn resume_auto_paused_recording(app: AppHandle) -> Result<bool, String> {
+    resume(&app)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use block2::RcBlock;
+    use core_foundation::base::TCFType;
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+    use objc2_foundation::NSString;
+
+    use super::PowerEvent;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+    }
+
+    /// Send NSWorkspace's will-sleep and did-wake notifications to `tx`
+    pub fn watch_power(tx: Sender<PowerEvent>) -> bool {
+        unsafe {
+            let workspace: *mut AnyObject = msg_send![class!(NSWorkspace), sharedWorkspace];
+            if workspace.is_null() {
+                return false;
+            }
+            let center: *mut AnyObject = msg_send![workspace, notificationCenter];
+            if center.is_null() {
+                return false;
+            }
+            for (name, event) in [
+                ("NSWorkspaceWillSleepNotification", PowerEvent::Sleep),
+                ("NSWorkspaceDidWakeNotification", PowerEvent::Wake),
+            ] {
+                let name = NSString::from_str(name);
+                let tx = tx.clone();
+                let block = RcBlock::new(move |_notification: *mut AnyObject| {
+                    let _ = tx.send(event);
+                });
+                // The notification center keeps the observer and its block for good
+                let _: *mut AnyObject = msg_send![
+                    center,
+                    addObserverForName: &*name,
+                    object: std::ptr::null::<AnyObject>(),
+                    queue: std::ptr::null::<AnyObject>(),
+                    usingBlock: &*block
+                ];
+            }
+        }
+        true
+    }
+
+    pub fn is_session_locked() -> bool {
+        let dict = unsafe { CGSessionCopyCurrentDictionary() };
+        if dict.is_null() {
+            return false;
+        }
+        let dict: CFDictionary<CFString> = unsafe { CFDictionary::wrap_under_create_rule(dict) };
+        dict.find(CFString::from_static_string("CGSSessionScreenIsLocked"))
+            .and_then(|value| {
+                let value = unsafe { core_foundation::base::CFType::wrap_under_get_rule(*value) };
+                value.downcast::<CFBoolean>()
+            })
+            .is_some_and(bool::from)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::c_void;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Mutex, OnceLock};
+
+    use windows_sys::Win32::System::Power::{
+        DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, HPOWERNOTIFY, PowerRegisterSuspendResumeNotification,
+    };
+    use windows_sys::Win32::System::StationsAndDesktops::{
+        CloseDesktop, DESKTOP_SWITCHDESKTOP, OpenInputDesktop,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND,
+    };
+
+    use super::PowerEvent;
+
+    static POWER_TX: OnceLock<Mutex<Sender<PowerEvent>>> = OnceLock::new();
+
+    unsafe extern "system" fn on_power(
+        _context: *const c_void,
+        kind: u32,
+        _setting: *const c_void,
+    ) -> u32 {
+        let event = match kind {
+            PBT_APMSUSPEND => PowerEvent::Sleep,
+            PBT_APMRESUMEAUTOMATIC => PowerEvent::Wake,
+            _ => return 0,
+        };
+        if let Some(tx) = POWER_TX.get()
+            && let Ok(tx) = tx.lock()
+        {
+            let _ = tx.send(event);
+        }
+        0
+    }
+
+    /// Send suspend and resume notifications to `tx`
+    pub fn watch_power(tx: Sender<PowerEvent>) -> bool {
+        if POWER_TX.set(Mutex::new(tx)).is_err() {
+            return false;
+        }
+        // Windows keeps a pointer to the parameters for as long as the registration lasts
+        let params = Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(on_power),
+            Context: std::ptr::null_mut(),
+        }));
+        let mut handle: HPOWERNOTIFY = std::ptr::null_mut();
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as *mut c_void,
+                &mut handle,
+            )
+        };
+        if result != 0 {
+            tracing::warn!("Failed to register for suspend notifications: {}", result);
+        }
+        result == 0
+    }
+
+    /// The input desktop can't be opened while the lock screen (Winlogon desktop) is up
+    pub fn is_session_locked() -> bool {
+        let desktop = unsafe { OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP) };
+        if desktop.is_null() {
+            return true;
+        }
+        unsafe { CloseDesktop(desktop) };
+        false
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use super::PowerEvent;
+
+    /// No sleep notifications here; the wall clock tells
+    pub fn watch_power(_tx: Sender<PowerEvent>) -> bool {
+        false
+    }
+
+    /// logind's LockedHint, set by the screen locker
+    pub fn is_session_locked() -> bool {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        std::process::Command::new("loginctl")
+            .args(["show-session", &session, "-p", "LockedHint", "--value"])
+            .output()
+            .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).trim() == "yes")
+    }
+}
@@ -0,0 +1,206 @@
+//! AppleScript support (macOS)
+//!
+//! `Note67.sdef` defines the scripting dictionary; each command names one of the
+//! `NSScriptCommand` subclasses registered here, which Cocoa scripting instantiates when
+//! a script (or a Shortcuts "Run AppleScript" action) sends the command:
+//!
+//! ```applescript
+//! tell application "Note67" to start recording titled "Standup"
+//! tell application "Note67" to stop recording
+//! tell application "Note67" to create note "Ideas"
+//! tell application "Note67" to get last summary
+//! ```
+//!
+//! Recording is driven by the frontend, so start/stop are forwarded as
+//! "automation-request" events, the same way scheduled recordings are.
+
+use std::sync::OnceLock;
+
+use chrono::Local;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands;
+use crate::db::Database;
+use crate::db::models::NewNote;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Event payload emitted as "automation-request"
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AutomationRequest {
+    /// Start recording into a note created for it
+    StartRecording {
+        note_id: String,
+    },
+    StopRecording,
+}
+
+/// Register the scripting command classes. Must run before the first Apple Event is
+/// dispatched, i.e. during setup.
+pub fn install(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+    macos::register_classes();
+}
+
+fn app() -> Result<&'static AppHandle, String> {
+    APP.get()
+        .ok_or_else(|| "Note67 is still starting".to_string())
+}
+
+fn create_note(title: Option<String>) -> Result<String, String> {
+    let app = app()?;
+    let title = title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| format!("Note {}", Local::now().format("%Y-%m-%d %H:%M")));
+    let note = commands::create_note(
+        app.clone(),
+        app.state::<Database>(),
+        NewNote {
+            title,
+            description: None,
+            participants: None,
+        },
+    )?;
+    Ok(note.id)
+}
+
+fn start_recording(title: Option<String>) -> Result<String, String> {
+    let note_id = create_note(title)?;
+    let app = app()?;
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit(
+        "automation-request",
+        AutomationRequest::StartRecording {
+            note_id: note_id.clone(),
+        },
+    );
+    Ok(note_id)
+}
+
+fn stop_recording() -> Result<(), String> {
+    let _ = app()?.emit("automation-request", AutomationRequest::StopRecording);
+    Ok(())
+}
+
+/// Content of the most recently generated summary, across all notes
+fn last_summary() -> Result<Option<String>, String> {
+    let db = app()?.state::<Database>();
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    Ok(conn
+        .query_row(
+            "SELECT content FROM summaries ORDER BY created_at DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok())
+}
+
+mod macos {
+    use objc2::rc::Retained;
+    use objc2::{ClassType, define_class};
+    use objc2_foundation::{NSObject, NSScriptCommand, NSString, ns_string};
+
+    /// errOSAGeneralError
+    const GENERAL_ERROR: isize = -2700;
+
+    pub fn register_classes() {
+        // Cocoa scripting looks the classes up by name, so they must exist up front
+        let _ = StartRecordingCommand::class();
+        let _ = StopRecordingCommand::class();
+        let _ = CreateNoteCommand::class();
+        let _ = LastSummaryCommand::class();
+    }
+
+    fn string_argument(command: &NSScriptCommand, key: &NSString) -> Option<String> {
+        let arguments = command.evaluatedArguments()?;
+        let value = arguments.objectForKey(key)?;
+        value.downcast::<NSString>().ok().map(|s| s.to_string())
+    }
+
+    fn direct_string(command: &NSScriptCommand) -> Option<String> {
+        command
+            .directParameter()?
+            .downcast::<NSString>()
+            .ok()
+            .map(|s| s.to_string())
+    }
+
+    /// Turn a result into the script's return value, reporting errors to the script
+    fn finish(
+        command: &NSScriptCommand,
+        result: Result<Option<String>, String>,
+    ) -> Option<Retained<NSString>> {
+        match result {
+            Ok(value) => value.map(|v| NSString::from_str(&v)),
+            Err(e) => {
+                command.setScriptErrorNumber(GENERAL_ERROR);
+                command.setScriptErrorString(Some(&NSString::from_str(&e)));
+                None
+            }
+        }
+    }
+
+    define_class!(
+        // SAFETY: NSScriptCommand has no subclassing requirements beyond overriding
+        // performDefaultImplementation, and the class does not implement Drop.
+        #[unsafe(super(NSScriptCommand, NSObject))]
+        #[name = "N67StartRecordingCommand"]
+        struct StartRecordingCommand;
+
+        impl StartRecordingCommand {
+            #[unsafe(method_id(performDefaultImplementation))]
+            fn perform(&self) -> Option<Retained<NSString>> {
+                let title = string_argument(self, ns_string!("title"));
+                finish(self, super::start_recording(title).map(Some))
+            }
+        }
+    );
+
+    define_class!(
+        // SAFETY: see StartRecordingCommand
+        #[unsafe(super(NSScriptCommand, NSObject))]
+        #[name = "N67StopRecordingCommand"]
+        struct StopRecordingCommand;
+
+        impl StopRecordingCommand {
+            #[unsafe(method_id(performDefaultImplementation))]
+            fn perform(&self) -> Option<Retained<NSString>> {
+                finish(self, super::stop_recording().map(|_| None))
+            }
+        }
+    );
+
+    define_class!(
+        // SAFETY: see StartRecordingCommand
+        #[unsafe(super(NSScriptCommand, NSObject))]
+        #[name = "N67CreateNoteCommand"]
+        struct CreateNoteCommand;
+
+        impl CreateNoteCommand {
+            #[unsafe(method_id(performDefaultImplementation))]
+            fn perform(&self) -> Option<Retained<NSString>> {
+                finish(self, super::create_note(direct_string(self)).map(Some))
+            }
+        }
+    );
+
+    define_class!(
+        // SAFETY: see StartRecordingCommand
+        #[unsafe(super(NSScriptCommand, NSObject))]
+        #[name = "N67LastSummaryCommand"]
+        struct LastSummaryCommand;
+
+        impl LastSummaryCommand {
+            #[unsafe(method_id(performDefaultImplementation))]
+            fn perform(&self) -> Option<Retained<NSString>> {
+                finish(self, super::last_summary())
+            }
+        }
+    );
+}
@@ -0,0 +1,444 @@
+//! Backups of the database and recordings to an S3-compatible bucket
+//! Layout under the configured prefix:
+//!   snapshots/<timestamp>/note67.db.gz   gzip'd consistent copy of the database
+//!   snapshots/<timestamp>/manifest.json  what the snapshot contains
+//!   audio/<note id>/<folder>/<file>.gz   each recording or upload, uploaded once
+//! Backups made before per-note folders keep recordings flat as audio/<file>.gz; restoring
+//! one puts them back in the old folder and lets the storage migration sort them out.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::Database;
+use crate::integrations::s3::{self, S3Client};
+use crate::jobs::{Job, JobKind, JobManager, Priority};
+use crate::power;
+use crate::secrets;
+use crate::storage::{self, NoteFolder};
+
+/// Hours between scheduled backups; unset or "0" disables scheduling
+pub const SETTING_INTERVAL_HOURS: &str = "s3_backup_interval_hours";
+/// RFC 3339 time of the last successful backup
+pub const SETTING_LAST_BACKUP: &str = "s3_last_backup_at";
+
+const DB_OBJECT: &str = "note67.db.gz";
+
+/// How often the scheduler checks whether a backup is due
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Recordings modified this recently may still be written to and are left for the next run
+const MIN_AUDIO_AGE: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupReport {
+    pub snapshot: String,
+    pub database_bytes: u64,
+    pub audio_uploaded: usize,
+    pub audio_skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSnapshot {
+    /// Snapshot id (UTC timestamp), passed to `restore_backup`
+    pub id: String,
+    pub created_at: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub snapshot: String,
+    pub audio_restored: usize,
+}
+
+/// Build a client from the saved S3 settings and the keychain secret
+pub fn client_from_settings(db: &Database) -> Result<(S3Client, String), String> {
+    let get = |key: &str| -> Result<String, String> {
+        Ok(db
+            .get_setting(key)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default())
+    };
+
+    let endpoint = get(s3::SETTING_ENDPOINT)?;
+    let endpoint = if endpoint.is_empty() {
+        let region = get(s3::SETTING_REGION)?;
+        format!(
+            "https://s3.{}.amazonaws.com",
+            if region.is_empty() { "us-east-1" } else { &region }
+        )
+    } else {
+        endpoint
+    };
+    let secret = secrets::get_secret(s3::SECRET_SECRET_KEY)?.unwrap_or_default();
+
+    let client = S3Client::new(
+        &endpoint,
+        &get(s3::SETTING_REGION)?,
+        &get(s3::SETTING_BUCKET)?,
+        &get(s3::SETTING_ACCESS_KEY_ID)?,
+        &secret,
+        get(s3::SETTING_PATH_STYLE)? == "true",
+    )
+    .map_err(|e| e.to_string())?;
+
+    let prefix = get(s3::SETTING_PREFIX)?;
+    let prefix = match prefix.trim_matches('/') {
+        "" => String::new(),
+        p => format!("{}/", p),
+    };
+    Ok((client, prefix))
+}
+
+/// Upload a database snapshot plus any recordings not yet in the bucket. Waits for a
+/// backup or restore already running.
+pub async fn run_backup(app: &AppHandle, priority: Priority) -> Result<BackupReport, String> {
+    let job = app
+        .state::<JobManager>()
+        .enqueue(JobKind::Backup, priority, "Back up to S3", None)
+        .await?;
+    let result = upload_backup(app, &job).await;
+    job.finish(&result);
+    result
+}
+
+async fn upload_backup(app: &AppHandle, job: &Job) -> Result<BackupReport, String> {
+    let db = app.state::<Database>();
+    let (client, prefix) = client_from_settings(&db)?;
+    let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+
+    let snapshot = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let snapshot_file = app_data.join("backup-snapshot.db");
+    let _ = fs::remove_file(&snapshot_file);
+    db.snapshot_to(&snapshot_file).map_err(|e| e.to_string())?;
+    let _cleanup = scopeguard::guard(snapshot_file.clone(), |path| {
+        let _ = fs::remove_file(path);
+    });
+
+    let compressed = gzip_file(snapshot_file).await?;
+    let database_bytes = compressed.len() as u64;
+    client
+        .put_object(
+            &format!("{}snapshots/{}/{}", prefix, snapshot, DB_OBJECT),
+            compressed,
+            "application/gzip",
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Recordings never change once finished, so anything already uploaded is skipped
+    let audio_prefix = format!("{}audio/", prefix);
+    let existing: HashSet<String> = client
+        .list_objects(&audio_prefix)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|o| o.key)
+        .collect();
+
+    let mut audio_uploaded = 0;
+    let mut audio_skipped = 0;
+    let mut audio_keys = Vec::new();
+    let files = recording_files(app);
+    let total = files.len();
+    for (i, (relative, path)) in files.into_iter().enumerate() {
+        job.check_cancelled()?;
+        job.progress(i as f32 / total as f32);
+        let key = format!("{}{}.gz", audio_prefix, relative);
+        audio_keys.push(key.clone());
+
+        let recently_modified = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age < MIN_AUDIO_AGE);
+        if existing.contains(&key) || recently_modified {
+            audio_skipped += 1;
+            continue;
+        }
+
+        let compressed = gzip_file(path).await?;
+        client
+            .put_object(&key, compressed, "application/gzip")
+            .await
+            .map_err(|e| e.to_string())?;
+        audio_uploaded += 1;
+    }
+
+    let manifest = serde_json::json!({
+        "created_at": Utc::now().to_rfc3339(),
+        "app_version": app.package_info().version.to_string(),
+        "database": DB_OBJECT,
+        "audio": audio_keys,
+    });
+    client
+        .put_object(
+            &format!("{}snapshots/{}/manifest.json", prefix, snapshot),
+            manifest.to_string().into_bytes(),
+            "application/json",
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    db.set_setting(SETTING_LAST_BACKUP, &Utc::now().to_rfc3339())
+        .map_err(|e| e.to_string())?;
+
+    let report = BackupReport {
+        snapshot,
+        database_bytes,
+        audio_uploaded,
+        audio_skipped,
+    };
+    let _ = app.emit("backup-completed", &report);
+    Ok(report)
+}
+
+/// Snapshots in the bucket, newest first
+pub async fn list_backups(app: &AppHandle) -> Result<Vec<BackupSnapshot>, String> {
+    let db = app.state::<Database>();
+    let (client, prefix) = client_from_settings(&db)?;
+    let snapshot_prefix = format!("{}snapshots/", prefix);
+
+    let mut snapshots: Vec<BackupSnapshot> = client
+        .list_objects(&snapshot_prefix)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|object| {
+            let id = object
+                .key
+                .strip_prefix(&snapshot_prefix)?
+                .strip_suffix(&format!("/{}", DB_OBJECT))?
+                .to_string();
+            Some(BackupSnapshot {
+                id,
+                created_at: object.last_modified,
+                size: object.size,
+            })
+        })
+        .collect();
+
+    // Ids are UTC timestamps, so they sort chronologically
+    snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(snapshots)
+}
+
+/// Replace the local database with a snapshot (the latest when `snapshot` is None) and
+/// download any recordings missing locally. Waits for a backup already running.
+pub async fn restore_backup(
+    app: &AppHandle,
+    snapshot: Option<String>,
+) -> Result<RestoreReport, String> {
+    let job = app
+        .state::<JobManager>()
+        .enqueue(JobKind::Backup, Priority::Normal, "Restore from S3", None)
+        .await?;
+    let result = download_backup(app, snapshot, &job).await;
+    job.finish(&result);
+    result
+}
+
+async fn download_backup(
+    app: &AppHandle,
+    snapshot: Option<String>,
+    job: &Job,
+) -> Result<RestoreReport, String> {
+    let snapshot = match snapshot {
+        Some(id) => id,
+        None => list_backups(app)
+            .await?
+            .into_iter()
+            .next()
+            .map(|s| s.id)
+            .ok_or_else(|| "No backups found in the bucket".to_string())?,
+    };
+
+    let db = app.state::<Database>();
+    let (client, prefix) = client_from_settings(&db)?;
+    let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+
+    let compressed = client
+        .get_object(&format!("{}snapshots/{}/{}", prefix, snapshot, DB_OBJECT))
+        .await
+        .map_err(|e| e.to_string())?;
+    let restore_file = app_data.join("backup-restore.db");
+    fs::write(&restore_file, gunzip(&compressed)?).map_err(|e| e.to_string())?;
+    let _cleanup = scopeguard::guard(restore_file.clone(), |path| {
+        let _ = fs::remove_file(path);
+    });
+
+    // Refuse to swap in anything that isn't an intact SQLite database
+    {
+        let conn = rusqlite::Connection::open(&restore_file).map_err(|e| e.to_string())?;
+        let check: String = conn
+            .query_row("PRAGMA quick_check", [], |row| row.get(0))
+            .map_err(|e| format!("Backup is not a valid database: {}", e))?;
+        if check != "ok" {
+            return Err(format!("Backup failed integrity check: {}", check));
+        }
+    }
+
+    db.replace_with(app, &restore_file)
+        .map_err(|e| format!("Failed to restore database: {}", e))?;
+
+    let data_root = storage::data_root(app)?;
+    let legacy_dir = app_data.join("recordings");
+    let audio_prefix = format!("{}audio/", prefix);
+    let mut audio_restored = 0;
+    let objects = client
+        .list_objects(&audio_prefix)
+        .await
+        .map_err(|e| e.to_string())?;
+    let total = objects.len();
+    for (i, object) in objects.into_iter().enumerate() {
+        // The database is already restored; recordings left behind are fetched by the next restore
+        job.check_cancelled()?;
+        job.progress(i as f32 / total as f32);
+        let Some(target) = object
+            .key
+            .strip_prefix(&audio_prefix)
+            .and_then(|k| k.strip_suffix(".gz"))
+            .and_then(|relative| restore_target(&data_root, &legacy_dir, relative))
+        else {
+            continue;
+        };
+        if target.exists() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let compressed = client
+            .get_object(&object.key)
+            .await
+            .map_err(|e| e.to_string())?;
+        fs::write(&target, gunzip(&compressed)?).map_err(|e| e.to_string())?;
+        audio_restored += 1;
+    }
+    storage::migrate_legacy_layout(app);
+
+    let report = RestoreReport {
+        snapshot,
+        audio_restored,
+    };
+    let _ = app.emit("backup-restored", &report);
+    Ok(report)
+}
+
+/// Start the background scheduler for periodic backups (call from setup)
+pub fn start_backup_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULER_INTERVAL).await;
+
+            let due = {
+                let Some(db) = app.try_state::<Database>() else {
+                    continue;
+                };
+                let interval_hours: i64 = db
+                    .get_setting(SETTING_INTERVAL_HOURS)
+                    .ok()
+                    .flatten()
+                    .and_then(|h| h.parse().ok())
+                    .unwrap_or(0);
+                let last = db
+                    .get_setting(SETTING_LAST_BACKUP)
+                    .ok()
+                    .flatten()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok());
+                interval_hours > 0
+                    && last.is_none_or(|t| {
+                        Utc::now().signed_duration_since(t) >= chrono::Duration::hours(interval_hours)
+                    })
+            };
+
+            // A backup started by hand in the meantime counts
+            if due
+                && !app.state::<JobManager>().is_busy(JobKind::Backup)
+                && !power::defer(&app, "backup")
+                && let Err(e) = run_backup(&app, Priority::Low).await
+            {
+                tracing::warn!("Scheduled backup failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Finished recording files (WAV, uploads) in every note's folder, with their path below
+/// the data root
+fn recording_files(app: &AppHandle) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    for kind in [NoteFolder::Recordings, NoteFolder::Uploads] {
+        for dir in storage::existing_folders(app, kind) {
+            let Some(note_id) = dir.parent().and_then(|d| d.file_name()).and_then(|n| n.to_str())
+            else {
+                continue;
+            };
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in entries.flatten().map(|e| e.path()) {
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if path.is_file() && path.extension().is_some_and(|e| e != "tmp") {
+                    let relative = format!("{}/{}/{}", note_id, kind.dir_name(), name);
+                    files.push((relative, path.clone()));
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Where a backed-up recording goes: `<note id>/<folder>/<file>` under the data root, or a
+/// bare file name (from an older backup) in the old recordings folder
+fn restore_target(data_root: &Path, legacy_dir: &Path, relative: &str) -> Option<PathBuf> {
+    let parts: Vec<&str> = relative.split('/').collect();
+    if parts.iter().any(|p| p.is_empty() || p.starts_with('.') || p.contains('\\')) {
+        return None;
+    }
+    match parts.as_slice() {
+        [name] => Some(legacy_dir.join(name)),
+        [note_id, folder, name]
+            if [NoteFolder::Recordings, NoteFolder::Uploads]
+                .iter()
+                .any(|kind| kind.dir_name() == *folder) =>
+        {
+            Some(data_root.join(note_id).join(folder).join(name))
+        }
+        _ => None,
+    }
+}
+
+async fn gzip_file(path: PathBuf) -> Result<Vec<u8>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&bytes).map_err(|e| e.to_string())?;
+        encoder.finish().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("Failed to decompress backup: {}", e))?;
+    Ok(out)
+}
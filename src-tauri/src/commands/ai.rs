@@ -1,16 +1,21 @@
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::ai::prompts::MAX_CONTENT_LENGTH;
 use crate::ai::{OllamaClient, OllamaModel, SummaryPrompts, WritingPrompts};
+use crate::commands::export::auto_export_note;
+use crate::commands::integrations::auto_post_summary;
 use crate::commands::links::update_incoming_links_internal;
 use crate::db::models::{ActionItem, ActionItemWithNote, Summary, SummaryType};
 use crate::db::Database;
+use crate::jobs::{JobKind, JobManager, Priority};
+use crate::note_windows;
+use crate::notifications;
+use crate::webhooks;
 
 /// Split text into chunks of approximately max_size characters
 /// Tries to split on sentence boundaries when possible
@@ -66,7 +71,6 @@ fn split_into_chunks(text: &str, max_size: usize) -> Vec<String> {
 pub struct AiState {
     pub client: Arc<OllamaClient>,
     pub selected_model: Mutex<Option<String>>,
-    pub is_generating: AtomicBool,
 }
 
 impl Default for AiState {
@@ -74,7 +78,6 @@ impl Default for AiState {
         Self {
             client: Arc::new(OllamaClient::new()),
             selected_model: Mutex::new(None),
-            is_generating: AtomicBool::new(false),
         }
     }
 }
@@ -156,31 +159,39 @@ pub async fn get_selected_model(state: State<'_, AiState>) -> Result<Option<Stri
     Ok(state.selected_model.lock().await.clone())
 }
 
-/// Check if AI is currently generating
+/// Check if an AI generation is queued or running
 #[tauri::command]
-pub fn is_ai_generating(state: State<'_, AiState>) -> bool {
-    state.is_generating.load(Ordering::SeqCst)
+pub fn is_ai_generating(jobs: State<'_, JobManager>) -> bool {
+    jobs.is_busy(JobKind::Ai)
 }
 
 /// Generate a summary for a note
 #[tauri::command]
 pub async fn generate_summary(
+    app: AppHandle,
     note_id: String,
     summary_type: String,
     custom_prompt: Option<String>,
     ai_state: State<'_, AiState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<Summary, String> {
-    // Check if already generating
-    if ai_state.is_generating.swap(true, Ordering::SeqCst) {
-        return Err("Already generating a summary".to_string());
-    }
-
-    // Ensure we reset the flag when done
-    let _guard = scopeguard::guard((), |_| {
-        ai_state.is_generating.store(false, Ordering::SeqCst);
-    });
+    let job = jobs
+        .enqueue(JobKind::Ai, Priority::Normal, "Generate summary", Some(&note_id))
+        .await?;
+    let result = summarize(app, note_id, summary_type, custom_prompt, &ai_state, &db).await;
+    job.finish(&result);
+    result
+}
 
+async fn summarize(
+    app: AppHandle,
+    note_id: String,
+    summary_type: String,
+    custom_prompt: Option<String>,
+    ai_state: &AiState,
+    db: &Database,
+) -> Result<Summary, String> {
     // Get selected model
     let model = ai_state
         .selected_model
@@ -327,6 +338,8 @@ pub async fn generate_summary(
         .map_err(|e| e.to_string())?
         .ok_or("Failed to retrieve saved summary")?;
 
+    notify_summary_generated(&app, &summary);
+
     Ok(summary)
 }
 
@@ -347,17 +360,24 @@ pub async fn generate_summary_stream(
     custom_prompt: Option<String>,
     ai_state: State<'_, AiState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<Summary, String> {
-    // Check if already generating
-    if ai_state.is_generating.swap(true, Ordering::SeqCst) {
-        return Err("Already generating a summary".to_string());
-    }
-
-    // Ensure we reset the flag when done
-    let _guard = scopeguard::guard((), |_| {
-        ai_state.is_generating.store(false, Ordering::SeqCst);
-    });
+    let job = jobs
+        .enqueue(JobKind::Ai, Priority::Normal, "Generate summary", Some(&note_id))
+        .await?;
+    let result = summarize_stream(app, note_id, summary_type, custom_prompt, &ai_state, &db).await;
+    job.finish(&result);
+    result
+}
 
+async fn summarize_stream(
+    app: AppHandle,
+    note_id: String,
+    summary_type: String,
+    custom_prompt: Option<String>,
+    ai_state: &AiState,
+    db: &Database,
+) -> Result<Summary, String> {
     // Get selected model
     let model = ai_state
         .selected_model
@@ -407,7 +427,7 @@ pub async fn generate_summary_stream(
             chunk: format!("Processing {} sections...\n\n", total_chunks),
             is_done: false,
         };
-        let _ = app.emit("summary-stream", status_event);
+        note_windows::emit_to_note(&app, &note_id, "summary-stream", status_event);
 
         // Summarize each chunk (non-streaming for intermediate steps)
         let mut chunk_summaries = Vec::new();
@@ -418,7 +438,7 @@ pub async fn generate_summary_stream(
                 chunk: format!("Analyzing section {} of {}...\n", i + 1, total_chunks),
                 is_done: false,
             };
-            let _ = app.emit("summary-stream", progress_event);
+            note_windows::emit_to_note(&app, &note_id, "summary-stream", progress_event);
 
             let chunk_prompt = match stype {
                 SummaryType::Overview => {
@@ -450,7 +470,7 @@ pub async fn generate_summary_stream(
             chunk: "\nCombining results...\n\n".to_string(),
             is_done: false,
         };
-        let _ = app.emit("summary-stream", merge_event);
+        note_windows::emit_to_note(&app, &note_id, "summary-stream", merge_event);
 
         // Merge chunk summaries with streaming
         let merge_prompt = match stype {
@@ -481,7 +501,7 @@ pub async fn generate_summary_stream(
                     chunk,
                     is_done: false,
                 };
-                let _ = app_clone.emit("summary-stream", event);
+                note_windows::emit_to_note(&app_clone, &note_id_clone, "summary-stream", event);
             }
         });
 
@@ -533,7 +553,7 @@ pub async fn generate_summary_stream(
                     chunk,
                     is_done: false,
                 };
-                let _ = app_clone.emit("summary-stream", event);
+                note_windows::emit_to_note(&app_clone, &note_id_clone, "summary-stream", event);
             }
         });
 
@@ -551,7 +571,7 @@ pub async fn generate_summary_stream(
         chunk: String::new(),
         is_done: true,
     };
-    let _ = app.emit("summary-stream", done_event);
+    note_windows::emit_to_note(&app, &note_id, "summary-stream", done_event);
 
     // Strip thinking tags from response
     let clean_response = strip_thinking_tags(&response);
@@ -567,9 +587,47 @@ pub async fn generate_summary_stream(
         .map_err(|e| e.to_string())?
         .ok_or("Failed to retrieve saved summary")?;
 
+    notify_summary_generated(&app, &summary);
+
     Ok(summary)
 }
 
+/// Fire integrations that react to a new summary (webhooks, Slack auto-post)
+fn notify_summary_generated(app: &AppHandle, summary: &Summary) {
+    auto_post_summary(app, summary);
+    auto_export_note(app, &summary.note_id);
+    webhooks::dispatch(
+        app,
+        webhooks::EVENT_SUMMARY_GENERATED,
+        serde_json::json!({
+            "note_id": summary.note_id,
+            "summary_id": summary.id,
+            "summary_type": summary.summary_type.as_str(),
+            "content": summary.content,
+        }),
+    );
+
+    let db = app.state::<Database>();
+    let title = db
+        .conn
+        .lock()
+        .ok()
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT title FROM notes WHERE id = ?1",
+                [&summary.note_id],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+        })
+        .unwrap_or_else(|| "your note".to_string());
+    notifications::notify_background(
+        app,
+        "Summary ready",
+        &format!("The summary of {} is ready", title),
+    );
+}
+
 /// Get all summaries for a note
 #[tauri::command]
 pub fn get_note_summaries(
@@ -830,8 +888,8 @@ pub async fn generate_title(
             .map_err(|e| e.to_string())?;
 
         // Debug: Log raw LLM output
-        eprintln!(
-            "[DEBUG] Attempt {}/{} - Raw LLM title response:\n{}",
+        tracing::debug!(
+            "Attempt {}/{} - Raw LLM title response:\n{}",
             attempt, max_retries, response
         );
 
@@ -839,8 +897,8 @@ pub async fn generate_title(
         title = clean_title_response(&response);
 
         // Debug: Log cleaned title
-        eprintln!(
-            "[DEBUG] Attempt {}/{} - Cleaned title: {}",
+        tracing::debug!(
+            "Attempt {}/{} - Cleaned title: {}",
             attempt, max_retries, title
         );
 
@@ -1166,8 +1224,8 @@ pub async fn generate_title_from_summary(
             .map_err(|e| e.to_string())?;
 
         // Debug: Log raw LLM output
-        eprintln!(
-            "[DEBUG] title_from_summary Attempt {}/{} - Raw response:\n{}",
+        tracing::debug!(
+            "title_from_summary Attempt {}/{} - Raw response:\n{}",
             attempt, max_retries, response
         );
 
@@ -1175,8 +1233,8 @@ pub async fn generate_title_from_summary(
         title = clean_title_response(&response);
 
         // Debug: Log cleaned title
-        eprintln!(
-            "[DEBUG] title_from_summary Attempt {}/{} - Cleaned: {}",
+        tracing::debug!(
+            "title_from_summary Attempt {}/{} - Cleaned: {}",
             attempt, max_retries, title
         );
 
@@ -1236,17 +1294,23 @@ pub async fn ai_write_stream(
     action: String,
     note_content: Option<String>,
     ai_state: State<'_, AiState>,
+    jobs: State<'_, JobManager>,
 ) -> Result<String, String> {
-    // Check if already generating
-    if ai_state.is_generating.swap(true, Ordering::SeqCst) {
-        return Err("Already generating".to_string());
-    }
-
-    // Ensure we reset the flag when done
-    let _guard = scopeguard::guard((), |_| {
-        ai_state.is_generating.store(false, Ordering::SeqCst);
-    });
+    let job = jobs
+        .enqueue(JobKind::Ai, Priority::Normal, "Writing assistant", None)
+        .await?;
+    let result = write_stream(app, content, action, note_content, &ai_state).await;
+    job.finish(&result);
+    result
+}
 
+async fn write_stream(
+    app: AppHandle,
+    content: String,
+    action: String,
+    note_content: Option<String>,
+    ai_state: &AiState,
+) -> Result<String, String> {
     // Get selected model
     let model = ai_state
         .selected_model
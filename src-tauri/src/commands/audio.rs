@@ -1,15 +1,34 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, Once};
 
 use serde::Serialize;
-use tauri::{AppHandle, Manager, State};
-
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio::calibration::{self, CalibrationReport};
+use crate::audio::capture_format;
+use crate::audio::clock;
+use crate::audio::compression::{self, CompressionProgress};
+use crate::audio::denoise;
+use crate::audio::devices::{self, InputDevice, RenderDevice};
+use crate::audio::encoder;
+use crate::audio::headphones;
+use crate::audio::mixer;
+use crate::audio::monitoring;
+use crate::audio::system_audio;
+use crate::audio::waveform::{self, WaveformPeaks};
 use crate::audio::{
-    self, aec, is_system_audio_available, mix_wav_files, RecordingPhase, RecordingState,
-    SystemAudioCapture,
+    self, aec, is_system_audio_available, mix_wav_files, CapturableApp, RecordingPhase,
+    RecordingState, SystemAudioCapture,
 };
+use crate::commands::transcription::queue_segment_transcription;
 use crate::db::Database;
+use crate::settings;
+use crate::storage::{self, NoteFolder};
+
+/// "true" makes recordings started from the tray, shortcuts and automations listen-only
+/// (system audio without the mic), for webinars one only watches
+pub const SETTING_LISTEN_ONLY: &str = "listen_only_recording";
 
 /// Result of dual recording containing paths to all recorded files
 #[derive(Debug, Clone, Serialize)]
@@ -25,51 +44,102 @@ pub struct DualRecordingResult {
 
 pub struct AudioState {
     pub recording: Arc<RecordingState>,
-    /// System audio capture instance (macOS only)
-    pub system_capture: Mutex<Option<Arc<dyn SystemAudioCapture>>>,
+    /// System audio capture instance (macOS only); read it through `system_capture()`
+    system_capture: Mutex<Option<Arc<dyn SystemAudioCapture>>>,
+    /// Probing for system audio capture is slow on some machines, so it happens on first use
+    system_capture_probed: Once,
     /// Path to the system audio recording file
     pub system_output_path: Mutex<Option<PathBuf>>,
+    /// How the active session was started, so it can be stopped the same way from the
+    /// backend (tray) regardless of who started it
+    pub mode: Mutex<Option<RecordingMode>>,
+}
+
+/// RMS levels from 0.0 to 1.0
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioLevels {
+    /// The latest mic audio; 0.0 when the mic isn't recording
+    pub mic: f32,
+    /// The latest system audio, 0.0 while capture delivers nothing; None when system audio
+    /// isn't being captured
+    pub system: Option<f32>,
+}
+
+/// Recording modes, named like the frontend's `RecordingMode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordingMode {
+    Dual,
+    MicOnly,
+    /// Listen-only: system audio without the microphone
+    SystemOnly,
+}
+
+fn set_mode(state: &AudioState, mode: Option<RecordingMode>) {
+    if let Ok(mut current) = state.mode.lock() {
+        *current = mode;
+    }
 }
 
 impl Default for AudioState {
     fn default() -> Self {
-        // Try to create system audio capture if supported
-        let system_capture = crate::audio::create_system_audio_capture().ok();
-
         Self {
             recording: Arc::new(RecordingState::new()),
-            system_capture: Mutex::new(system_capture),
+            system_capture: Mutex::new(None),
+            system_capture_probed: Once::new(),
             system_output_path: Mutex::new(None),
+            mode: Mutex::new(None),
         }
     }
 }
 
+impl AudioState {
+    /// The system audio capture, created on the first call if the platform supports it
+    pub fn system_capture(
+        &self,
+    ) -> Result<MutexGuard<'_, Option<Arc<dyn SystemAudioCapture>>>, String> {
+        self.system_capture_probed.call_once(|| {
+            let capture = crate::audio::create_system_audio_capture().ok();
+            *self
+                .system_capture
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = capture;
+        });
+        self.system_capture.lock().map_err(|e| e.to_string())
+    }
+}
+
 #[tauri::command]
 pub fn start_recording(
     app: AppHandle,
     state: State<AudioState>,
     note_id: String,
 ) -> Result<String, String> {
-    // Get app data directory for storing recordings
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let _span = tracing::info_span!("recording.start", note_id = %note_id).entered();
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
-    let filename = format!("{}.wav", note_id);
+    let filename = format!("{}.{}", note_id, encoder::recording_extension());
     let output_path = recordings_dir.join(&filename);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     audio::start_recording(state.recording.clone(), output_path.clone())
         .map_err(|e| e.to_string())?;
 
+    if let Ok(mut current_note) = state.recording.current_note_id.lock() {
+        *current_note = Some(note_id.clone());
+    }
+    set_mode(&state, Some(RecordingMode::MicOnly));
+
     Ok(output_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
 pub fn stop_recording(state: State<AudioState>) -> Result<Option<String>, String> {
+    let _span = tracing::info_span!("recording.stop").entered();
+    set_mode(&state, None);
+
     let path = audio::stop_recording(&state.recording).map_err(|e| e.to_string())?;
     Ok(path.map(|p| p.to_string_lossy().to_string()))
 }
@@ -84,6 +154,37 @@ pub fn get_audio_level(state: State<AudioState>) -> f32 {
     f32::from_bits(state.recording.audio_level.load(Ordering::SeqCst))
 }
 
+/// Mic and system audio levels, for a meter each during dual recording
+#[tauri::command]
+pub fn get_audio_levels(state: State<AudioState>) -> AudioLevels {
+    let capturing = state
+        .system_capture()
+        .ok()
+        .and_then(|cap| cap.as_ref().map(|c| c.is_capturing()))
+        .unwrap_or(false);
+    AudioLevels {
+        mic: f32::from_bits(state.recording.audio_level.load(Ordering::SeqCst)),
+        system: capturing.then(audio::system_audio_level),
+    }
+}
+
+/// Min/max peaks of an audio file in up to `buckets` slices, for the player's waveform
+#[tauri::command]
+pub async fn generate_waveform_peaks(
+    audio_path: String,
+    buckets: usize,
+) -> Result<WaveformPeaks, String> {
+    if buckets == 0 || buckets > waveform::MAX_BUCKETS {
+        return Err(format!("Buckets must be 1 to {}", waveform::MAX_BUCKETS));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        waveform::generate_peaks(Path::new(&audio_path), buckets)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 /// Check if system audio capture is available on this platform
 #[tauri::command]
 pub fn is_system_audio_supported() -> bool {
@@ -93,7 +194,7 @@ pub fn is_system_audio_supported() -> bool {
 /// Check if the app has permission to capture system audio
 #[tauri::command]
 pub fn has_system_audio_permission(state: State<AudioState>) -> Result<bool, String> {
-    let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+    let capture = state.system_capture()?;
 
     match capture.as_ref() {
         Some(cap) => cap.has_permission().map_err(|e| e.to_string()),
@@ -105,7 +206,7 @@ pub fn has_system_audio_permission(state: State<AudioState>) -> Result<bool, Str
 /// On macOS, this will trigger the system permission dialog if needed
 #[tauri::command]
 pub fn request_system_audio_permission(state: State<AudioState>) -> Result<bool, String> {
-    let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+    let capture = state.system_capture()?;
 
     match capture.as_ref() {
         Some(cap) => cap.request_permission().map_err(|e| e.to_string()),
@@ -113,6 +214,67 @@ pub fn request_system_audio_permission(state: State<AudioState>) -> Result<bool,
     }
 }
 
+// ========== Input Device Commands ==========
+
+/// Connected microphones, marking the default and the one recordings use
+#[tauri::command]
+pub fn list_input_devices() -> Result<Vec<InputDevice>, String> {
+    devices::list_input_devices().map_err(|e| e.to_string())
+}
+
+/// Record from the named microphone, or the system default with None. Takes effect at
+/// the next recording.
+#[tauri::command]
+pub fn set_input_device(app: AppHandle, name: Option<String>) -> Result<(), String> {
+    if let Some(name) = &name {
+        let connected = devices::list_input_devices().map_err(|e| e.to_string())?;
+        if !connected.iter().any(|d| &d.name == name) {
+            return Err(format!("Input device not found: {}", name));
+        }
+    }
+    settings::set(
+        &app,
+        devices::SETTING_INPUT_DEVICE,
+        name.as_deref().unwrap_or(""),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Output devices system audio can be captured from (Windows), or on macOS the installed
+/// virtual loopback devices it can fall back on
+#[tauri::command]
+pub fn list_render_devices() -> Result<Vec<RenderDevice>, String> {
+    devices::list_render_devices().map_err(|e| e.to_string())
+}
+
+/// Capture system audio from the output device with this endpoint id, or the system
+/// default with None. Takes effect at the next recording.
+#[tauri::command]
+pub fn set_render_device(app: AppHandle, id: Option<String>) -> Result<(), String> {
+    if let Some(id) = &id {
+        let connected = devices::list_render_devices().map_err(|e| e.to_string())?;
+        if !connected.iter().any(|d| &d.id == id) {
+            return Err(format!("Output device not found: {}", id));
+        }
+    }
+    settings::set(
+        &app,
+        devices::SETTING_LOOPBACK_DEVICE,
+        id.as_deref().unwrap_or(""),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Running apps that system audio can be limited to (the `system_audio_captured_app`
+/// setting) or leave out (`system_audio_excluded_apps`). macOS and Windows only.
+#[tauri::command]
+pub async fn list_capturable_apps() -> Result<Vec<CapturableApp>, String> {
+    tauri::async_runtime::spawn_blocking(audio::list_capturable_apps)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 // ========== Microphone Permission Commands ==========
 
 /// Check if a microphone is available on this device
@@ -225,30 +387,27 @@ pub fn start_dual_recording(
     state: State<AudioState>,
     note_id: String,
 ) -> Result<DualRecordingResult, String> {
-    // Get app data directory for storing recordings
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let _span = tracing::info_span!("recording.start", note_id = %note_id).entered();
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     // Mic recording path
-    let mic_filename = format!("{}_mic.wav", note_id);
+    let mic_filename = format!("{}_mic.{}", note_id, encoder::recording_extension());
     let mic_path = recordings_dir.join(&mic_filename);
 
     // System audio recording path
-    let system_filename = format!("{}_system.wav", note_id);
+    let system_filename = format!("{}_system.{}", note_id, encoder::recording_extension());
     let system_path = recordings_dir.join(&system_filename);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     // Start mic recording
     audio::start_recording(state.recording.clone(), mic_path.clone())
         .map_err(|e| e.to_string())?;
 
     // Try to start system audio recording if available
     let system_started = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             match cap.start(system_path.clone()) {
@@ -259,7 +418,7 @@ pub fn start_dual_recording(
                     true
                 }
                 Err(e) => {
-                    eprintln!("Failed to start system audio capture: {}", e);
+                    tracing::warn!("Failed to start system audio capture: {}", e);
                     false
                 }
             }
@@ -268,6 +427,8 @@ pub fn start_dual_recording(
         }
     };
 
+    set_mode(&state, Some(RecordingMode::Dual));
+
     Ok(DualRecordingResult {
         mic_path: Some(mic_path.to_string_lossy().to_string()),
         system_path: if system_started {
@@ -287,6 +448,9 @@ pub fn stop_dual_recording(
     state: State<AudioState>,
     note_id: String,
 ) -> Result<DualRecordingResult, String> {
+    let _span = tracing::info_span!("recording.stop", note_id = %note_id).entered();
+    set_mode(&state, None);
+
     // Stop mic recording
     let mic_path = audio::stop_recording(&state.recording)
         .map_err(|e| e.to_string())?
@@ -294,7 +458,7 @@ pub fn stop_dual_recording(
 
     // Stop system audio recording
     let system_path = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             cap.stop().map_err(|e| e.to_string())?
@@ -311,20 +475,32 @@ pub fn stop_dual_recording(
 
     // Merge files if we have both
     let playback_path = if let Some(ref sys_path) = system_path {
-        let app_data_dir = app
-            .path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-        let recordings_dir = app_data_dir.join("recordings");
-        let playback_filename = format!("{}.wav", note_id);
+        let recordings_dir = storage::folder(&app, &note_id, NoteFolder::Recordings)?;
+        let playback_filename = format!("{}.{}", note_id, encoder::recording_extension());
         let playback_file = recordings_dir.join(&playback_filename);
 
-        // Merge the two files
-        match mix_wav_files(&mic_path, sys_path, &playback_file) {
+        // Merge the two files, lined up by when each capture's audio arrived
+        let alignment = state
+            .recording
+            .mic_clock
+            .lock()
+            .ok()
+            .and_then(|mic| clock::alignment(&mic, &system_audio::system_capture_clock()));
+        let (mode, levels) = (mixer::mix_mode(&app), mixer::mix_levels(&app));
+        let mut progress = mixer::progress_events(app.clone(), note_id.clone());
+        let mixed = mix_wav_files(
+            &mic_path,
+            sys_path,
+            &playback_file,
+            mode,
+            levels,
+            alignment,
+            &mut progress,
+        );
+        match mixed {
             Ok(()) => Some(playback_file.to_string_lossy().to_string()),
             Err(e) => {
-                eprintln!("Failed to merge audio files: {}", e);
+                tracing::warn!("Failed to merge audio files: {}", e);
                 // Fall back to mic path as playback
                 None
             }
@@ -348,6 +524,9 @@ pub fn stop_dual_recording_with_segments(
     db: State<Database>,
     note_id: String,
 ) -> Result<DualRecordingResult, String> {
+    let _span = tracing::info_span!("recording.stop", note_id = %note_id).entered();
+    set_mode(&state, None);
+
     // Get the recording duration before stopping
     let duration_ms = state.recording.get_segment_elapsed_ms();
 
@@ -358,7 +537,7 @@ pub fn stop_dual_recording_with_segments(
 
     // Stop system audio recording
     let system_path = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             cap.stop().map_err(|e| e.to_string())?
@@ -381,20 +560,32 @@ pub fn stop_dual_recording_with_segments(
 
     // Merge files if we have both
     let playback_path = if let Some(ref sys_path) = system_path {
-        let app_data_dir = app
-            .path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-        let recordings_dir = app_data_dir.join("recordings");
-        let playback_filename = format!("{}.wav", note_id);
+        let recordings_dir = storage::folder(&app, &note_id, NoteFolder::Recordings)?;
+        let playback_filename = format!("{}.{}", note_id, encoder::recording_extension());
         let playback_file = recordings_dir.join(&playback_filename);
 
-        // Merge the two files
-        match mix_wav_files(&mic_path, sys_path, &playback_file) {
+        // Merge the two files, lined up by when each capture's audio arrived
+        let alignment = state
+            .recording
+            .mic_clock
+            .lock()
+            .ok()
+            .and_then(|mic| clock::alignment(&mic, &system_audio::system_capture_clock()));
+        let (mode, levels) = (mixer::mix_mode(&app), mixer::mix_levels(&app));
+        let mut progress = mixer::progress_events(app.clone(), note_id.clone());
+        let mixed = mix_wav_files(
+            &mic_path,
+            sys_path,
+            &playback_file,
+            mode,
+            levels,
+            alignment,
+            &mut progress,
+        );
+        match mixed {
             Ok(()) => Some(playback_file.to_string_lossy().to_string()),
             Err(e) => {
-                eprintln!("Failed to merge audio files: {}", e);
+                tracing::warn!("Failed to merge audio files: {}", e);
                 None
             }
         }
@@ -415,8 +606,7 @@ pub fn is_dual_recording(state: State<AudioState>) -> bool {
     let mic_recording = state.recording.is_recording.load(Ordering::SeqCst);
 
     let system_recording = state
-        .system_capture
-        .lock()
+        .system_capture()
         .ok()
         .and_then(|cap| cap.as_ref().map(|c| c.is_capturing()))
         .unwrap_or(false);
@@ -430,11 +620,80 @@ pub fn is_aec_enabled() -> bool {
     aec::is_aec_enabled()
 }
 
-/// Set AEC enabled state
-/// Disable AEC when using headphones for better performance
+/// Set AEC enabled state. Headphones turn it off automatically (see `audio::headphones`);
+/// a choice made here stands until the output device changes.
 #[tauri::command]
 pub fn set_aec_enabled(enabled: bool) {
+    // The echo path may have changed while it was off
+    if enabled && !aec::is_aec_enabled() {
+        aec::reset_aec();
+    }
     aec::set_aec_enabled(enabled);
+    headphones::forget_auto_toggle();
+}
+
+/// Play a test chirp and measure the echo it leaves in the mic, so the UI can advise on AEC.
+/// Takes about five seconds; refused while recording.
+#[tauri::command]
+pub async fn run_aec_calibration(app: AppHandle) -> Result<CalibrationReport, String> {
+    if app.state::<AudioState>().recording.get_phase() != RecordingPhase::Idle {
+        return Err("Can't test echo cancellation while recording".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(calibration::run)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Check if mic noise suppression is on for recordings without their own choice
+#[tauri::command]
+pub fn is_noise_suppression_enabled() -> bool {
+    denoise::is_enabled()
+}
+
+/// Turn mic noise suppression on or off, including for a recording in progress
+#[tauri::command]
+pub fn set_noise_suppression_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, denoise::SETTING_ENABLED, &enabled.to_string()).map_err(|e| e.to_string())
+}
+
+/// Check if the mic is played back to the output while recording
+#[tauri::command]
+pub fn is_input_monitoring_enabled() -> bool {
+    monitoring::is_enabled()
+}
+
+/// Turn input monitoring (hearing yourself on headphones) on or off, including for a
+/// recording in progress
+#[tauri::command]
+pub fn set_input_monitoring(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, monitoring::SETTING_ENABLED, &enabled.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// A note's own noise suppression choice; None when it follows the global setting
+#[tauri::command]
+pub fn get_note_noise_suppression(note_id: String) -> Option<bool> {
+    denoise::note_override(&note_id)
+}
+
+/// Turn noise suppression on or off for one note, or back to the global setting with None
+#[tauri::command]
+pub fn set_note_noise_suppression(
+    db: State<Database>,
+    note_id: String,
+    enabled: Option<bool>,
+) -> Result<(), String> {
+    db.set_note_noise_suppression(&note_id, enabled)
+        .map_err(|e| e.to_string())?;
+    denoise::set_override(&note_id, enabled);
+    Ok(())
+}
+
+/// Playback files queued or being compressed after transcription
+#[tauri::command]
+pub fn get_compression_progress() -> Vec<CompressionProgress> {
+    compression::progress()
 }
 
 // ========== Pause/Resume/Continue Recording Commands ==========
@@ -459,20 +718,22 @@ pub fn resume_recording_cmd(
     state: State<AudioState>,
     note_id: String,
 ) -> Result<String, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     // Get the next segment index
     let segment_index = state.recording.current_segment_index.load(Ordering::SeqCst);
 
-    let filename = format!("{}_seg{}.wav", note_id, segment_index);
+    let filename = format!(
+        "{}_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let output_path = recordings_dir.join(&filename);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     audio::resume_recording(state.recording.clone(), output_path.clone())
         .map_err(|e| e.to_string())?;
 
@@ -483,6 +744,7 @@ pub fn resume_recording_cmd(
 /// Returns the duration of the paused segment in milliseconds
 #[tauri::command]
 pub fn pause_dual_recording(
+    app: AppHandle,
     state: State<AudioState>,
     db: State<Database>,
 ) -> Result<i64, String> {
@@ -491,7 +753,7 @@ pub fn pause_dual_recording(
 
     // Stop system audio capture
     {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
         if let Some(cap) = capture.as_ref() {
             let _ = cap.stop();
         }
@@ -501,6 +763,7 @@ pub fn pause_dual_recording(
     let segment_id = state.recording.current_segment_db_id.load(Ordering::SeqCst);
     if segment_id > 0 {
         let _ = db.update_segment_duration(segment_id, duration_ms);
+        queue_segment_transcription(&app, segment_id);
     }
 
     Ok(duration_ms)
@@ -520,13 +783,7 @@ pub fn resume_dual_recording(
         return Err("Recording is not paused".to_string());
     }
 
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     // Get the next segment index from database
     let segment_index = db
@@ -549,11 +806,21 @@ pub fn resume_dual_recording(
         .store(start_offset_ms, Ordering::SeqCst);
 
     // Mic recording path with segment index
-    let mic_filename = format!("{}_mic_seg{}.wav", note_id, segment_index);
+    let mic_filename = format!(
+        "{}_mic_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let mic_path = recordings_dir.join(&mic_filename);
 
     // System audio recording path with segment index
-    let system_filename = format!("{}_system_seg{}.wav", note_id, segment_index);
+    let system_filename = format!(
+        "{}_system_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let system_path = recordings_dir.join(&system_filename);
 
     // Add segment to database
@@ -573,13 +840,16 @@ pub fn resume_dual_recording(
         .current_segment_db_id
         .store(segment_id, Ordering::SeqCst);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     // Start mic recording
     audio::resume_recording(state.recording.clone(), mic_path.clone())
         .map_err(|e| e.to_string())?;
 
     // Try to start system audio recording
     let system_started = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             match cap.start(system_path.clone()) {
@@ -589,7 +859,7 @@ pub fn resume_dual_recording(
                     true
                 }
                 Err(e) => {
-                    eprintln!("Failed to start system audio capture: {}", e);
+                    tracing::warn!("Failed to start system audio capture: {}", e);
                     false
                 }
             }
@@ -644,13 +914,7 @@ pub fn continue_note_recording(
         .map_err(|e| e.to_string())?;
     }
 
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     // Store note ID in state
     {
@@ -683,11 +947,21 @@ pub fn continue_note_recording(
         .store(start_offset_ms, Ordering::SeqCst);
 
     // Mic recording path with segment index
-    let mic_filename = format!("{}_mic_seg{}.wav", note_id, segment_index);
+    let mic_filename = format!(
+        "{}_mic_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let mic_path = recordings_dir.join(&mic_filename);
 
     // System audio recording path with segment index
-    let system_filename = format!("{}_system_seg{}.wav", note_id, segment_index);
+    let system_filename = format!(
+        "{}_system_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let system_path = recordings_dir.join(&system_filename);
 
     // Add segment to database
@@ -707,13 +981,16 @@ pub fn continue_note_recording(
         .current_segment_db_id
         .store(segment_id, Ordering::SeqCst);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     // Start mic recording
     audio::start_recording(state.recording.clone(), mic_path.clone())
         .map_err(|e| e.to_string())?;
 
     // Try to start system audio recording
     let system_started = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             match cap.start(system_path.clone()) {
@@ -723,7 +1000,7 @@ pub fn continue_note_recording(
                     true
                 }
                 Err(e) => {
-                    eprintln!("Failed to start system audio capture: {}", e);
+                    tracing::warn!("Failed to start system audio capture: {}", e);
                     false
                 }
             }
@@ -732,6 +1009,8 @@ pub fn continue_note_recording(
         }
     };
 
+    set_mode(&state, Some(RecordingMode::Dual));
+
     Ok(DualRecordingResult {
         mic_path: Some(mic_path.to_string_lossy().to_string()),
         system_path: if system_started {
@@ -752,13 +1031,8 @@ pub fn start_dual_recording_with_segments(
     db: State<Database>,
     note_id: String,
 ) -> Result<DualRecordingResult, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let _span = tracing::info_span!("recording.start", note_id = %note_id).entered();
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     // Reset state for new recording session
     state.recording.reset_for_new_session();
@@ -779,11 +1053,21 @@ pub fn start_dual_recording_with_segments(
         .map_err(|e| e.to_string())?;
 
     // Mic recording path with segment index
-    let mic_filename = format!("{}_mic_seg{}.wav", note_id, segment_index);
+    let mic_filename = format!(
+        "{}_mic_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let mic_path = recordings_dir.join(&mic_filename);
 
     // System audio recording path with segment index
-    let system_filename = format!("{}_system_seg{}.wav", note_id, segment_index);
+    let system_filename = format!(
+        "{}_system_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let system_path = recordings_dir.join(&system_filename);
 
     // Add segment to database (start_offset_ms is 0 for first segment)
@@ -803,13 +1087,16 @@ pub fn start_dual_recording_with_segments(
         .current_segment_db_id
         .store(segment_id, Ordering::SeqCst);
 
+    // The note's quality preset, which the captures follow
+    capture_format::begin(&app, &note_id);
+
     // Start mic recording
     audio::start_recording(state.recording.clone(), mic_path.clone())
         .map_err(|e| e.to_string())?;
 
     // Try to start system audio recording
     let system_started = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
 
         if let Some(cap) = capture.as_ref() {
             match cap.start(system_path.clone()) {
@@ -819,7 +1106,7 @@ pub fn start_dual_recording_with_segments(
                     true
                 }
                 Err(e) => {
-                    eprintln!("Failed to start system audio capture: {}", e);
+                    tracing::warn!("Failed to start system audio capture: {}", e);
                     false
                 }
             }
@@ -828,6 +1115,8 @@ pub fn start_dual_recording_with_segments(
         }
     };
 
+    set_mode(&state, Some(RecordingMode::Dual));
+
     Ok(DualRecordingResult {
         mic_path: Some(mic_path.to_string_lossy().to_string()),
         system_path: if system_started {
@@ -858,13 +1147,8 @@ pub fn start_system_only_recording_with_segments(
     db: State<Database>,
     note_id: String,
 ) -> Result<DualRecordingResult, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let _span = tracing::info_span!("recording.start", note_id = %note_id).entered();
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     state.recording.reset_for_new_session();
 
@@ -881,7 +1165,12 @@ pub fn start_system_only_recording_with_segments(
         .get_next_segment_index(&note_id)
         .map_err(|e| e.to_string())?;
 
-    let system_filename = format!("{}_system_seg{}.wav", note_id, segment_index);
+    let system_filename = format!(
+        "{}_system_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let system_path = recordings_dir.join(&system_filename);
 
     let segment_id = db
@@ -902,10 +1191,11 @@ pub fn start_system_only_recording_with_segments(
     // Start system audio capture. Errors here are fatal — without mic or system audio,
     // there's nothing to record.
     {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
         let cap = capture
             .as_ref()
             .ok_or_else(|| "System audio capture not available".to_string())?;
+        capture_format::begin(&app, &note_id);
         cap.start(system_path.clone()).map_err(|e| e.to_string())?;
     }
     {
@@ -915,6 +1205,8 @@ pub fn start_system_only_recording_with_segments(
 
     set_phase_for_system_only_session(&state.recording);
 
+    set_mode(&state, Some(RecordingMode::SystemOnly));
+
     Ok(DualRecordingResult {
         mic_path: None,
         system_path: Some(system_path.to_string_lossy().to_string()),
@@ -928,10 +1220,13 @@ pub fn stop_system_only_recording_with_segments(
     db: State<Database>,
     _note_id: String,
 ) -> Result<DualRecordingResult, String> {
+    let _span = tracing::info_span!("recording.stop").entered();
+    set_mode(&state, None);
+
     let duration_ms = state.recording.get_segment_elapsed_ms();
 
     let system_path = {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
         if let Some(cap) = capture.as_ref() {
             cap.stop().map_err(|e| e.to_string())?
         } else {
@@ -964,6 +1259,7 @@ pub fn stop_system_only_recording_with_segments(
 
 #[tauri::command]
 pub fn pause_system_only_recording(
+    app: AppHandle,
     state: State<AudioState>,
     db: State<Database>,
 ) -> Result<i64, String> {
@@ -973,7 +1269,7 @@ pub fn pause_system_only_recording(
     let duration_ms = state.recording.get_segment_elapsed_ms();
 
     {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
         if let Some(cap) = capture.as_ref() {
             let _ = cap.stop();
         }
@@ -984,6 +1280,7 @@ pub fn pause_system_only_recording(
     let segment_id = state.recording.current_segment_db_id.load(Ordering::SeqCst);
     if segment_id > 0 {
         let _ = db.update_segment_duration(segment_id, duration_ms);
+        queue_segment_transcription(&app, segment_id);
     }
 
     Ok(duration_ms)
@@ -1000,12 +1297,7 @@ pub fn resume_system_only_recording(
         return Err("Recording is not paused".to_string());
     }
 
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    let recordings_dir = app_data_dir.join("recordings");
-    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+    let recordings_dir = storage::create_folder(&app, &note_id, NoteFolder::Recordings)?;
 
     let segment_index = db
         .get_next_segment_index(&note_id)
@@ -1023,7 +1315,12 @@ pub fn resume_system_only_recording(
         .segment_start_offset_ms
         .store(start_offset_ms, Ordering::SeqCst);
 
-    let system_filename = format!("{}_system_seg{}.wav", note_id, segment_index);
+    let system_filename = format!(
+        "{}_system_seg{}.{}",
+        note_id,
+        segment_index,
+        encoder::recording_extension()
+    );
     let system_path = recordings_dir.join(&system_filename);
 
     let segment_id = db
@@ -1042,10 +1339,11 @@ pub fn resume_system_only_recording(
         .store(segment_id, Ordering::SeqCst);
 
     {
-        let capture = state.system_capture.lock().map_err(|e| e.to_string())?;
+        let capture = state.system_capture()?;
         let cap = capture
             .as_ref()
             .ok_or_else(|| "System audio capture not available".to_string())?;
+        capture_format::begin(&app, &note_id);
         cap.start(system_path.clone()).map_err(|e| e.to_string())?;
     }
     {
@@ -1061,3 +1359,296 @@ pub fn resume_system_only_recording(
         playback_path: None,
     })
 }
+
+// ========== Backend-driven recording ==========
+// Used by the tray, which records without going through the frontend. The frontend is
+// told through "tray-recording-started" / "tray-recording-stopped" so it can catch up.
+
+/// Payload of "tray-recording-started" and "tray-recording-stopped"
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendRecordingEvent {
+    pub note_id: String,
+    pub mode: RecordingMode,
+    /// The playback file, once stopped
+    pub audio_path: Option<String>,
+}
+
+/// Start recording into `note_id`, picking the mode the same way the frontend does:
+/// mic + system audio when both are usable, else whichever one is. With
+/// `SETTING_LISTEN_ONLY` on, only system audio is recorded, and never the mic instead.
+pub(crate) fn start_recording_for_note(
+    app: &AppHandle,
+    note_id: &str,
+) -> Result<RecordingMode, String> {
+    let state = app.state::<AudioState>();
+    let db = app.state::<Database>();
+
+    let system_ok =
+        is_system_audio_supported() && has_system_audio_permission(state.clone()).unwrap_or(false);
+    let listen_only = db
+        .get_setting(SETTING_LISTEN_ONLY)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if listen_only {
+        if !system_ok {
+            return Err(
+                "Listen-only recording needs system audio. Grant system audio permission to record."
+                    .to_string(),
+            );
+        }
+        start_system_only_recording_with_segments(app.clone(), state, db, note_id.to_string())?;
+        return Ok(RecordingMode::SystemOnly);
+    }
+    let mic_ok = has_microphone_available() && has_microphone_permission();
+
+    if mic_ok && system_ok {
+        start_dual_recording_with_segments(app.clone(), state, db, note_id.to_string())?;
+        Ok(RecordingMode::Dual)
+    } else if mic_ok {
+        start_recording(app.clone(), state, note_id.to_string())?;
+        Ok(RecordingMode::MicOnly)
+    } else if system_ok {
+        start_system_only_recording_with_segments(app.clone(), state, db, note_id.to_string())?;
+        Ok(RecordingMode::SystemOnly)
+    } else {
+        Err(
+            "No audio input available. Grant microphone or system audio permission to record."
+                .to_string(),
+        )
+    }
+}
+
+/// Start recording into a new timestamped note, telling the frontend through
+/// "tray-recording-started". Returns the note, or None when something is already recording.
+pub(crate) fn start_recording_in_new_note(app: &AppHandle) -> Result<Option<String>, String> {
+    if app.state::<AudioState>().recording.get_phase() != RecordingPhase::Idle {
+        return Ok(None);
+    }
+
+    let note_id = super::create_timestamped_note(app)?;
+    if let Err(e) = start_announced_recording(app, &note_id) {
+        // Don't leave an empty note behind
+        let _ = super::delete_note(app.clone(), app.state::<Database>(), note_id);
+        return Err(e);
+    }
+    Ok(Some(note_id))
+}
+
+/// `start_recording_for_note`, telling the frontend through "tray-recording-started"
+pub(crate) fn start_announced_recording(
+    app: &AppHandle,
+    note_id: &str,
+) -> Result<RecordingMode, String> {
+    let mode = start_recording_for_note(app, note_id)?;
+    let _ = app.emit(
+        "tray-recording-started",
+        BackendRecordingEvent {
+            note_id: note_id.to_string(),
+            mode,
+            audio_path: None,
+        },
+    );
+    Ok(mode)
+}
+
+/// Stop the active recording (recording or paused) and end its note.
+/// Returns None when nothing was recording.
+pub(crate) fn stop_active_recording(
+    app: &AppHandle,
+) -> Result<Option<BackendRecordingEvent>, String> {
+    let state = app.state::<AudioState>();
+    let db = app.state::<Database>();
+
+    if state.recording.get_phase() == RecordingPhase::Idle {
+        return Ok(None);
+    }
+    let (note_id, mode) = active_session(&state)?;
+
+    let audio_path = match mode {
+        RecordingMode::Dual => {
+            let result =
+                stop_dual_recording_with_segments(app.clone(), state, db.clone(), note_id.clone())?;
+            result
+                .playback_path
+                .or(result.system_path)
+                .or(result.mic_path)
+        }
+        RecordingMode::SystemOnly => {
+            let result =
+                stop_system_only_recording_with_segments(state, db.clone(), note_id.clone())?;
+            result.playback_path.or(result.system_path)
+        }
+        RecordingMode::MicOnly => stop_recording(state)?,
+    };
+
+    super::end_note(app.clone(), db, note_id.clone(), audio_path.clone())?;
+
+    Ok(Some(BackendRecordingEvent {
+        note_id,
+        mode,
+        audio_path,
+    }))
+}
+
+/// Pause the active recording, or resume it if paused.
+/// Returns whether it is now paused, or None when nothing was recording.
+pub(crate) fn toggle_pause_active_recording(app: &AppHandle) -> Result<Option<bool>, String> {
+    let state = app.state::<AudioState>();
+    let db = app.state::<Database>();
+
+    let phase = state.recording.get_phase();
+    if phase == RecordingPhase::Idle {
+        return Ok(None);
+    }
+    let (note_id, mode) = active_session(&state)?;
+
+    if phase == RecordingPhase::Paused {
+        match mode {
+            RecordingMode::Dual => {
+                resume_dual_recording(app.clone(), state, db, note_id)?;
+            }
+            RecordingMode::SystemOnly => {
+                resume_system_only_recording(app.clone(), state, db, note_id)?;
+            }
+            RecordingMode::MicOnly => {
+                resume_recording_cmd(app.clone(), state, note_id)?;
+            }
+        }
+        Ok(Some(false))
+    } else {
+        match mode {
+            RecordingMode::Dual => pause_dual_recording(app.clone(), state, db)?,
+            RecordingMode::SystemOnly => pause_system_only_recording(app.clone(), state, db)?,
+            RecordingMode::MicOnly => pause_recording_cmd(state)?,
+        };
+        Ok(Some(true))
+    }
+}
+
+/// Payload of "recording-segment-rotated"
+#[derive(Debug, Clone, Serialize)]
+pub struct SegmentRotatedEvent {
+    pub note_id: String,
+    /// The segment that was just finished
+    pub finished_segment_id: i64,
+    /// The segment now recording
+    pub segment_id: i64,
+}
+
+/// Finish the active recording's segment and carry on in a new one without stopping
+/// capture, so nothing is lost between them. Only recordings kept in segments (dual and
+/// listen-only) rotate. Returns None when there was nothing to rotate.
+pub(crate) fn rotate_active_segment(
+    app: &AppHandle,
+) -> Result<Option<SegmentRotatedEvent>, String> {
+    let state = app.state::<AudioState>();
+    let db = app.state::<Database>();
+
+    if state.recording.get_phase() != RecordingPhase::Recording {
+        return Ok(None);
+    }
+    let finished_segment_id = state.recording.current_segment_db_id.load(Ordering::SeqCst);
+    let (note_id, mode) = active_session(&state)?;
+    if mode == RecordingMode::MicOnly || finished_segment_id <= 0 {
+        return Ok(None);
+    }
+
+    let recordings_dir = storage::create_folder(app, &note_id, NoteFolder::Recordings)?;
+    let segment_index = db
+        .get_next_segment_index(&note_id)
+        .map_err(|e| e.to_string())?;
+    let segment_path = |track: &str| {
+        recordings_dir.join(format!(
+            "{}_{}_seg{}.{}",
+            note_id,
+            track,
+            segment_index,
+            encoder::recording_extension()
+        ))
+    };
+    let mic_path = (mode == RecordingMode::Dual).then(|| segment_path("mic"));
+    let system_path = state
+        .system_output_path
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_some()
+        .then(|| segment_path("system"));
+
+    // Switch files, taking the finished segment's length at the moment of the switch
+    let duration_ms = state.recording.get_segment_elapsed_ms();
+    match &mic_path {
+        Some(mic_path) => audio::rotate_recording(&state.recording, mic_path.clone())
+            .map_err(|e| e.to_string())?,
+        None => {
+            if let Ok(mut start_time) = state.recording.segment_start_time.lock() {
+                *start_time = Some(std::time::Instant::now());
+            }
+        }
+    }
+    if let Some(system_path) = &system_path {
+        let capture = state.system_capture()?;
+        if let Some(cap) = capture.as_ref() {
+            match cap.rotate(system_path.clone()) {
+                Ok(()) => {
+                    let mut sys_path = state.system_output_path.lock().map_err(|e| e.to_string())?;
+                    *sys_path = Some(system_path.clone());
+                }
+                Err(e) => tracing::warn!("Failed to rotate system audio capture: {}", e),
+            }
+        }
+    }
+
+    db.update_segment_duration(finished_segment_id, duration_ms)
+        .map_err(|e| e.to_string())?;
+    let start_offset_ms = db
+        .get_total_segment_duration(&note_id)
+        .map_err(|e| e.to_string())?;
+    let mic = mic_path.map(|p| p.to_string_lossy().to_string());
+    let system = system_path.map(|p| p.to_string_lossy().to_string());
+    let segment_id = db
+        .add_audio_segment(
+            &note_id,
+            segment_index,
+            mic.as_deref(),
+            system.as_deref(),
+            start_offset_ms,
+        )
+        .map_err(|e| e.to_string())?;
+
+    state
+        .recording
+        .current_segment_index
+        .store(segment_index as u32, Ordering::SeqCst);
+    state
+        .recording
+        .segment_start_offset_ms
+        .store(start_offset_ms, Ordering::SeqCst);
+    state
+        .recording
+        .current_segment_db_id
+        .store(segment_id, Ordering::SeqCst);
+
+    Ok(Some(SegmentRotatedEvent {
+        note_id,
+        finished_segment_id,
+        segment_id,
+    }))
+}
+
+/// The note and mode of the active recording
+fn active_session(state: &AudioState) -> Result<(String, RecordingMode), String> {
+    let note_id = state
+        .recording
+        .current_note_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or("The active recording has no note")?;
+    let mode = state
+        .mode
+        .lock()
+        .map_err(|e| e.to_string())?
+        .unwrap_or(RecordingMode::MicOnly);
+    Ok((note_id, mode))
+}
@@ -0,0 +1,284 @@
+//! Commands for cutting a note's recordings without external tools: `trim_audio` keeps a
+//! stretch of the note's timeline (dropping pre-meeting chatter, say) and
+//! `split_audio_segment` cuts one recorded segment in two. The files are rewritten, and
+//! the transcript and markers are cut and shifted to match, so nothing needs
+//! retranscribing. Uploads aren't on the timeline and are left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{compression, edit, encoder};
+use crate::commands::AudioState;
+use crate::db::{AudioSegment, Database};
+use crate::storage::{self, NoteFolder};
+
+/// Refuse to edit a note's files while they are being written
+fn check_editable(app: &AppHandle, note_id: &str) -> Result<(), String> {
+    let recording = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    if recording.as_deref() == Some(note_id) {
+        return Err("Can't edit the audio of a note being recorded".to_string());
+    }
+    if compression::progress().iter().any(|c| c.note_id == note_id) {
+        return Err("The note's recording is being compressed. Try again when it's done.".into());
+    }
+    Ok(())
+}
+
+/// A segment's files
+fn segment_files(segment: &AudioSegment) -> Vec<PathBuf> {
+    [&segment.mic_path, &segment.system_path]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// A segment's length, from the database or else its longest file
+fn segment_duration_ms(segment: &AudioSegment) -> i64 {
+    segment.duration_ms.unwrap_or_else(|| {
+        segment_files(segment)
+            .iter()
+            .filter_map(|f| crate::audio::converter::get_audio_duration_ms(f).ok())
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+/// Move finished cuts over their files; on failure, drop every cut not yet moved
+fn replace_with_cuts(files: &[PathBuf]) -> Result<(), String> {
+    for (i, file) in files.iter().enumerate() {
+        if let Err(e) = fs::rename(edit::temp_path(file), file) {
+            for rest in &files[i..] {
+                let _ = fs::remove_file(edit::temp_path(rest));
+            }
+            return Err(format!("Failed to replace {}: {}", file.display(), e));
+        }
+    }
+    Ok(())
+}
+
+/// The merged playback file no longer matches the segments once they are cut; the database
+/// forgets it in the same transaction as the cut
+fn remove_playback_file(app: &AppHandle, note_id: &str) {
+    let Ok(dir) = storage::folder(app, note_id, NoteFolder::Recordings) else {
+        return;
+    };
+    for format in [
+        encoder::RecordingFormat::Wav,
+        encoder::RecordingFormat::Flac,
+    ] {
+        let _ = fs::remove_file(dir.join(format!("{}.{}", note_id, format.extension())));
+    }
+}
+
+/// What trimming does to one segment
+enum SegmentTrim {
+    Remove,
+    /// Keep `from_ms..to_ms` of its files, starting at `offset_ms` on the new timeline
+    Cut {
+        from_ms: i64,
+        to_ms: i64,
+        offset_ms: i64,
+    },
+    /// Keep it whole, moved to `offset_ms`
+    Move {
+        offset_ms: i64,
+    },
+}
+
+fn trim_note(
+    app: &AppHandle,
+    note_id: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<AudioSegment>, String> {
+    check_editable(app, note_id)?;
+    if start_ms < 0 || end_ms <= start_ms {
+        return Err("The end must come after the start".to_string());
+    }
+    let db = app.state::<Database>();
+    let segments = db.get_audio_segments(note_id).map_err(|e| e.to_string())?;
+
+    let plan: Vec<(AudioSegment, SegmentTrim)> = segments
+        .into_iter()
+        .map(|segment| {
+            let (offset, duration) = (segment.start_offset_ms, segment_duration_ms(&segment));
+            let (from, to) = (start_ms.max(offset), end_ms.min(offset + duration));
+            let trim = if to <= from {
+                SegmentTrim::Remove
+            } else if from == offset && to == offset + duration {
+                SegmentTrim::Move {
+                    offset_ms: offset - start_ms,
+                }
+            } else {
+                SegmentTrim::Cut {
+                    from_ms: from - offset,
+                    to_ms: to - offset,
+                    offset_ms: from - start_ms,
+                }
+            };
+            (segment, trim)
+        })
+        .collect();
+    if plan
+        .iter()
+        .all(|(_, trim)| matches!(trim, SegmentTrim::Remove))
+    {
+        return Err("That would remove all of the note's recorded audio".to_string());
+    }
+
+    // Cut every file before replacing any
+    let mut cut_files = Vec::new();
+    for (segment, trim) in &plan {
+        let SegmentTrim::Cut { from_ms, to_ms, .. } = trim else {
+            continue;
+        };
+        for file in segment_files(segment) {
+            if let Err(e) = edit::cut(&file, *from_ms as u64, Some(*to_ms as u64)) {
+                for done in &cut_files {
+                    let _ = fs::remove_file(edit::temp_path(done));
+                }
+                return Err(format!("Failed to cut {}: {}", file.display(), e));
+            }
+            cut_files.push(file);
+        }
+    }
+    replace_with_cuts(&cut_files)?;
+
+    for (segment, trim) in &plan {
+        let result = match *trim {
+            SegmentTrim::Remove => db
+                .delete_transcript_segments_by_source("segment", segment.id)
+                .and_then(|_| db.delete_audio_segment(segment.id))
+                .map(|()| {
+                    for file in segment_files(segment) {
+                        let _ = fs::remove_file(file);
+                    }
+                }),
+            SegmentTrim::Cut {
+                from_ms,
+                to_ms,
+                offset_ms,
+            } => db
+                .cut_transcript_segments(
+                    note_id,
+                    Some(("segment", segment.id)),
+                    from_ms as f64 / 1000.0,
+                    to_ms as f64 / 1000.0,
+                )
+                .and_then(|_| db.update_segment_span(segment.id, offset_ms, to_ms - from_ms)),
+            SegmentTrim::Move { offset_ms } => {
+                db.update_segment_span(segment.id, offset_ms, segment_duration_ms(segment))
+            }
+        };
+        result.map_err(|e| e.to_string())?;
+    }
+    db.cut_transcript_segments(
+        note_id,
+        None,
+        start_ms as f64 / 1000.0,
+        end_ms as f64 / 1000.0,
+    )
+    .and_then(|_| db.cut_markers(note_id, start_ms, end_ms))
+    .map_err(|e| e.to_string())?;
+    remove_playback_file(app, note_id);
+
+    tracing::info!(
+        "Trimmed the audio of {} to {}..{} ms",
+        note_id,
+        start_ms,
+        end_ms
+    );
+    db.get_audio_segments(note_id).map_err(|e| e.to_string())
+}
+
+/// Keep only `start_ms..end_ms` of a note's recorded audio (on the note's timeline) and
+/// move it to the start. Segments outside are deleted, with their transcript. Returns the
+/// note's segments as they are now.
+#[tauri::command]
+pub async fn trim_audio(
+    app: AppHandle,
+    note_id: String,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<AudioSegment>, String> {
+    tauri::async_runtime::spawn_blocking(move || trim_note(&app, &note_id, start_ms, end_ms))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// The file for one track of a new segment, in the format of the file it comes from
+fn split_file_path(source: &Path, note_id: &str, track: &str, index: i32) -> PathBuf {
+    let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+    source.with_file_name(format!("{}_{}_seg{}.{}", note_id, track, index, ext))
+}
+
+fn split_segment(app: &AppHandle, segment_id: i64, at_ms: i64) -> Result<AudioSegment, String> {
+    let db = app.state::<Database>();
+    let segment = db
+        .get_audio_segment_by_id(segment_id)
+        .map_err(|e| e.to_string())?;
+    check_editable(app, &segment.note_id)?;
+    if at_ms <= 0 || at_ms >= segment_duration_ms(&segment) {
+        return Err("Split inside the recording".to_string());
+    }
+    let index = db
+        .get_next_segment_index(&segment.note_id)
+        .map_err(|e| e.to_string())?;
+
+    // The second half goes to new files, the first is cut in place
+    let mut created: Vec<PathBuf> = Vec::new();
+    let mut tracks: [Option<PathBuf>; 2] = [None, None];
+    let sources = [("mic", &segment.mic_path), ("system", &segment.system_path)];
+    for (slot, (track, source)) in tracks.iter_mut().zip(sources) {
+        let Some(source) = source.as_deref().map(Path::new) else {
+            continue;
+        };
+        let target = split_file_path(source, &segment.note_id, track, index);
+        let copied = edit::copy_from(source, &target, at_ms as u64)
+            .and_then(|_| edit::cut(source, 0, Some(at_ms as u64)));
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&target);
+            for file in &created {
+                let _ = fs::remove_file(file);
+            }
+            for file in segment_files(&segment) {
+                let _ = fs::remove_file(edit::temp_path(&file));
+            }
+            return Err(format!("Failed to split {}: {}", source.display(), e));
+        }
+        created.push(target.clone());
+        *slot = Some(target);
+    }
+    replace_with_cuts(&segment_files(&segment))?;
+
+    let [mic, system] = tracks.map(|t| t.map(|p| p.to_string_lossy().to_string()));
+    let new_id = db
+        .split_audio_segment(segment_id, at_ms, mic.as_deref(), system.as_deref())
+        .map_err(|e| e.to_string())?;
+    remove_playback_file(app, &segment.note_id);
+    tracing::info!("Split recording segment {} at {} ms", segment_id, at_ms);
+    db.get_audio_segment_by_id(new_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Cut a recorded segment in two `at_ms` into it. The second half becomes a new segment
+/// right after it, taking the transcript from that point on; returns the new segment.
+#[tauri::command]
+pub async fn split_audio_segment(
+    app: AppHandle,
+    segment_id: i64,
+    at_ms: i64,
+) -> Result<AudioSegment, String> {
+    tauri::async_runtime::spawn_blocking(move || split_segment(&app, segment_id, at_ms))
+        .await
+        .map_err(|e| e.to_string())?
+}
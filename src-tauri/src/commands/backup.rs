@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::backup::{self, BackupReport, BackupSnapshot, RestoreReport};
+use crate::db::Database;
+use crate::integrations::s3;
+use crate::jobs::Priority;
+use crate::secrets;
+use crate::settings;
+
+/// S3 backup configuration as shown in settings (the secret key is never returned)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct S3BackupSettings {
+    /// Empty for AWS; e.g. "https://<account>.r2.cloudflarestorage.com" or a MinIO URL
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Key prefix inside the bucket, e.g. "note67/laptop"
+    pub prefix: String,
+    pub access_key_id: String,
+    pub path_style: bool,
+    /// Hours between automatic backups; 0 disables scheduling
+    pub interval_hours: u32,
+    #[serde(default)]
+    pub has_secret_key: bool,
+    #[serde(default)]
+    pub last_backup_at: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_s3_backup_settings(db: State<'_, Database>) -> Result<S3BackupSettings, String> {
+    let get = |key: &str| -> Result<String, String> {
+        Ok(db
+            .get_setting(key)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default())
+    };
+
+    Ok(S3BackupSettings {
+        endpoint: get(s3::SETTING_ENDPOINT)?,
+        region: get(s3::SETTING_REGION)?,
+        bucket: get(s3::SETTING_BUCKET)?,
+        prefix: get(s3::SETTING_PREFIX)?,
+        access_key_id: get(s3::SETTING_ACCESS_KEY_ID)?,
+        path_style: get(s3::SETTING_PATH_STYLE)? == "true",
+        interval_hours: get(backup::SETTING_INTERVAL_HOURS)?.parse().unwrap_or(0),
+        has_secret_key: secrets::get_secret(s3::SECRET_SECRET_KEY)?.is_some(),
+        last_backup_at: db
+            .get_setting(backup::SETTING_LAST_BACKUP)
+            .map_err(|e| e.to_string())?,
+    })
+}
+
+/// Save S3 backup settings. The secret key is stored in the OS keychain; pass `None` to keep
+/// the current key and an empty string to remove it.
+#[tauri::command]
+pub fn set_s3_backup_settings(
+    settings: S3BackupSettings,
+    secret_access_key: Option<String>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let values = [
+        (s3::SETTING_ENDPOINT, settings.endpoint.trim().to_string()),
+        (s3::SETTING_REGION, settings.region.trim().to_string()),
+        (s3::SETTING_BUCKET, settings.bucket.trim().to_string()),
+        (s3::SETTING_PREFIX, settings.prefix.trim().to_string()),
+        (s3::SETTING_ACCESS_KEY_ID, settings.access_key_id.trim().to_string()),
+        (s3::SETTING_PATH_STYLE, settings.path_style.to_string()),
+        (backup::SETTING_INTERVAL_HOURS, settings.interval_hours.to_string()),
+    ];
+    for (key, value) in values {
+        settings::set(&app, key, &value).map_err(|e| e.to_string())?;
+    }
+
+    match secret_access_key.as_deref() {
+        Some("") => secrets::delete_secret(s3::SECRET_SECRET_KEY)?,
+        Some(secret) => secrets::set_secret(s3::SECRET_SECRET_KEY, secret)?,
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Back up the database and recordings to the configured bucket now
+#[tauri::command]
+pub async fn run_s3_backup(app: AppHandle) -> Result<BackupReport, String> {
+    backup::run_backup(&app, Priority::Normal).await
+}
+
+/// List the database snapshots in the bucket, newest first
+#[tauri::command]
+pub async fn list_s3_backups(app: AppHandle) -> Result<Vec<BackupSnapshot>, String> {
+    backup::list_backups(&app).await
+}
+
+/// Restore the database (and missing recordings) from a snapshot; the latest when `snapshot`
+/// is omitted. This replaces all local notes.
+#[tauri::command]
+pub async fn restore_s3_backup(
+    app: AppHandle,
+    snapshot: Option<String>,
+) -> Result<RestoreReport, String> {
+    backup::restore_backup(&app, snapshot).await
+}
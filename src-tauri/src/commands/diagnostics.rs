@@ -0,0 +1,373 @@
+//! Checks for support requests. `run_self_test` walks the path a note takes, one step at a
+//! time: a second of microphone audio recorded the way a note records, converted the way
+//! uploads are, transcribed by the loaded Whisper model, then the AI backend and a database
+//! round trip. Each step reports on its own, so one failure doesn't hide the rest.
+//! `get_health` is the cheap counterpart for the status bar and tray: the state of every
+//! part at once, without recording or running anything.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::audio::{self, converter, RecordingPhase, RecordingState};
+use crate::commands::ai::AiState;
+use crate::commands::audio::{has_microphone_available, AudioState, RecordingMode};
+use crate::commands::permissions::{get_permissions_status, PermissionsReport};
+use crate::commands::transcription::TranscriptionState;
+use crate::db::Database;
+use crate::jobs::{JobCounts, JobKind, JobManager};
+use crate::notifications::{available_space, LOW_DISK_SPACE_BYTES};
+use crate::storage;
+use crate::transcription::{ModelSize, Transcriber};
+
+/// Length of the test recording
+const CLIP: Duration = Duration::from_secs(1);
+/// How long the recording thread gets to finish the file after stopping
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Written and read back by the database step, then removed
+const PROBE_KEY: &str = "self_test_probe";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    /// Couldn't run, e.g. no model loaded; doesn't fail the test
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    pub name: &'static str,
+    pub status: StepStatus,
+    /// What was found, or why the step failed or was skipped
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// No step failed
+    pub passed: bool,
+    pub steps: Vec<SelfTestStep>,
+    pub app_version: String,
+    pub os: &'static str,
+}
+
+impl SelfTestReport {
+    fn new(app: &AppHandle, steps: Vec<SelfTestStep>) -> Self {
+        Self {
+            passed: steps.iter().all(|s| s.status != StepStatus::Failed),
+            steps,
+            app_version: app.package_info().version.to_string(),
+            os: std::env::consts::OS,
+        }
+    }
+}
+
+fn step(name: &'static str, started: Instant, outcome: Result<String, String>) -> SelfTestStep {
+    let (status, detail) = match outcome {
+        Ok(detail) => (StepStatus::Passed, detail),
+        Err(detail) => (StepStatus::Failed, detail),
+    };
+    SelfTestStep {
+        name,
+        status,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn skipped(name: &'static str, detail: &str) -> SelfTestStep {
+    SelfTestStep {
+        name,
+        status: StepStatus::Skipped,
+        detail: detail.to_string(),
+        duration_ms: 0,
+    }
+}
+
+/// Record `CLIP` from the default microphone into `path`, through the same recorder and
+/// writer as a note, but with its own state so an idle app's state is left alone
+fn record_clip(path: &Path) -> Result<String, String> {
+    if !has_microphone_available() {
+        return Err("No microphone found".to_string());
+    }
+    let state = Arc::new(RecordingState::new());
+    audio::start_recording(state.clone(), path.to_path_buf()).map_err(|e| e.to_string())?;
+    thread::sleep(CLIP);
+    let samples = state.take_audio_buffer();
+    audio::stop_recording(&state).map_err(|e| e.to_string())?;
+
+    // The recording thread finishes the file once it notices the stop
+    let deadline = Instant::now() + FINALIZE_TIMEOUT;
+    let duration_ms = loop {
+        match converter::get_audio_duration_ms(path) {
+            Ok(ms) if ms > 0 => break ms,
+            _ if Instant::now() >= deadline => {
+                return Err(if samples.is_empty() {
+                    "The microphone delivered no audio (see the log for the device error)"
+                        .to_string()
+                } else {
+                    "The recording was never written to disk".to_string()
+                });
+            }
+            _ => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+
+    // Without permission macOS delivers digital silence rather than an error
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak == 0.0 {
+        return Err(
+            "The microphone delivered only silence; check the microphone permission".to_string(),
+        );
+    }
+    Ok(format!("Recorded {} ms, peak level {:.3}", duration_ms, peak))
+}
+
+/// Convert the clip to 16 kHz mono, as uploads are before transcription
+fn convert_clip(clip: &Path, converted: &Path) -> Result<String, String> {
+    converter::convert_to_wav(clip, converted).map_err(|e| e.to_string())?;
+    let duration_ms = converter::get_audio_duration_ms(converted).map_err(|e| e.to_string())?;
+    if duration_ms == 0 {
+        return Err("The converted file is empty".to_string());
+    }
+    Ok(format!("Converted to {} ms of 16 kHz mono", duration_ms))
+}
+
+async fn check_ai(ai: &AiState) -> Result<String, String> {
+    if !ai.client.is_running().await {
+        return Err("Ollama isn't running".to_string());
+    }
+    let models = ai.client.list_models().await.map_err(|e| e.to_string())?;
+    let selected = ai.selected_model.lock().map_err(|e| e.to_string())?.clone();
+    match selected {
+        Some(name) if models.iter().any(|m| m.name == name) => {
+            Ok(format!("Ollama is running; {} is installed", name))
+        }
+        Some(name) => Err(format!(
+            "Ollama is running, but the selected model {} isn't installed",
+            name
+        )),
+        None => Ok(format!(
+            "Ollama is running with {} models; none is selected",
+            models.len()
+        )),
+    }
+}
+
+fn check_database(db: &Database) -> Result<String, String> {
+    let value = chrono::Utc::now().timestamp_millis().to_string();
+    db.set_setting(PROBE_KEY, &value).map_err(|e| e.to_string())?;
+    let read = db.get_setting(PROBE_KEY).map_err(|e| e.to_string());
+    db.delete_setting(PROBE_KEY).map_err(|e| e.to_string())?;
+    match read? {
+        Some(read) if read == value => Ok("Wrote and read back a row".to_string()),
+        other => Err(format!("Wrote {:?} but read back {:?}", value, other)),
+    }
+}
+
+/// Exercise recording, conversion, transcription, the AI backend and the database, and
+/// report each step. Takes a few seconds.
+#[tauri::command]
+pub async fn run_self_test(
+    app: AppHandle,
+    audio_state: State<'_, AudioState>,
+    transcription: State<'_, TranscriptionState>,
+    ai: State<'_, AiState>,
+    db: State<'_, Database>,
+) -> Result<SelfTestReport, String> {
+    let _span = tracing::info_span!("diagnostics.self_test");
+    let dir = std::env::temp_dir().join(format!("note67-self-test-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let clip = dir.join("clip.wav");
+    let converted = dir.join("converted.wav");
+    let mut steps = Vec::new();
+
+    // The device is busy with the note, and a failure here shouldn't touch it
+    let recording = audio_state.mode.lock().map_err(|e| e.to_string())?.is_some();
+    let recorded = if recording {
+        steps.push(skipped("microphone", "A recording is in progress"));
+        false
+    } else {
+        let started = Instant::now();
+        let path = clip.clone();
+        let outcome = tokio::task::spawn_blocking(move || record_clip(&path))
+            .await
+            .map_err(|e| e.to_string())?;
+        steps.push(step("microphone", started, outcome));
+        steps.last().is_some_and(|s| s.status == StepStatus::Passed)
+    };
+
+    let converted_ok = if recorded {
+        let started = Instant::now();
+        let (from, to) = (clip.clone(), converted.clone());
+        let outcome = tokio::task::spawn_blocking(move || convert_clip(&from, &to))
+            .await
+            .map_err(|e| e.to_string())?;
+        steps.push(step("conversion", started, outcome));
+        steps.last().is_some_and(|s| s.status == StepStatus::Passed)
+    } else {
+        steps.push(skipped("conversion", "Needs the microphone recording"));
+        false
+    };
+
+    match transcription.model.get().map(Transcriber::new) {
+        None => steps.push(skipped("transcription", "No Whisper model is loaded")),
+        Some(_) if !converted_ok => {
+            steps.push(skipped("transcription", "Needs the converted recording"))
+        }
+        Some(transcriber) => {
+            let started = Instant::now();
+            let path: PathBuf = converted.clone();
+            let outcome = tokio::task::spawn_blocking(move || transcriber.transcribe(&path))
+                .await
+                .map_err(|e| e.to_string())?
+                .map(|result| match result.full_text.trim() {
+                    "" => "Ran the model; no speech heard".to_string(),
+                    text => format!("Ran the model and heard \"{}\"", text),
+                })
+                .map_err(|e| e.to_string());
+            steps.push(step("transcription", started, outcome));
+        }
+    }
+
+    let started = Instant::now();
+    let outcome = check_ai(&ai).await;
+    steps.push(step("ai", started, outcome));
+
+    let started = Instant::now();
+    steps.push(step("database", started, check_database(&db)));
+
+    let _ = fs::remove_dir_all(&dir);
+    let report = SelfTestReport::new(&app, steps);
+    for s in &report.steps {
+        tracing::info!("Self-test {}: {:?}, {}", s.name, s.status, s.detail);
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiHealth {
+    pub reachable: bool,
+    pub selected_model: Option<String>,
+    /// None when Ollama is unreachable or no model is selected
+    pub model_installed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingHealth {
+    pub phase: RecordingPhase,
+    /// How the active session was started; None when idle
+    pub mode: Option<RecordingMode>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptionHealth {
+    pub model: Option<ModelSize>,
+    pub live: bool,
+    /// A file transcription is queued or running
+    pub busy: bool,
+    pub downloading_model: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskHealth {
+    /// Free space where notes are stored; None if it can't be read
+    pub free_bytes: Option<u64>,
+    /// Below the low disk space warning threshold
+    pub low: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+    pub ai: AiHealth,
+    pub generating: bool,
+    pub recording: RecordingHealth,
+    pub transcription: TranscriptionHealth,
+    pub jobs: HashMap<JobKind, JobCounts>,
+    pub disk: DiskHealth,
+    pub permissions: PermissionsReport,
+}
+
+async fn ai_health(ai: &AiState) -> AiHealth {
+    let selected_model = ai.selected_model.lock().ok().and_then(|m| m.clone());
+    let reachable = ai.client.is_running().await;
+    let model_installed = match &selected_model {
+        Some(name) if reachable => ai
+            .client
+            .list_models()
+            .await
+            .ok()
+            .map(|models| models.iter().any(|m| &m.name == name)),
+        _ => None,
+    };
+    AiHealth {
+        reachable,
+        selected_model,
+        model_installed,
+    }
+}
+
+/// The state of the AI backend, recording, transcription, jobs, disk space and
+/// permissions in one call
+#[tauri::command]
+pub async fn get_health(
+    app: AppHandle,
+    audio_state: State<'_, AudioState>,
+    transcription: State<'_, TranscriptionState>,
+    ai: State<'_, AiState>,
+    jobs: State<'_, JobManager>,
+) -> Result<HealthSnapshot, String> {
+    let free_bytes = storage::data_root(&app)
+        .ok()
+        .and_then(|dir| available_space(&dir));
+    let mode = *audio_state.mode.lock().map_err(|e| e.to_string())?;
+
+    Ok(HealthSnapshot {
+        ai: ai_health(&ai).await,
+        generating: jobs.is_busy(JobKind::Ai),
+        recording: RecordingHealth {
+            phase: audio_state.recording.get_phase(),
+            mode,
+        },
+        transcription: TranscriptionHealth {
+            model: transcription.model.current(),
+            live: transcription.live_state.is_running.load(Ordering::SeqCst),
+            busy: jobs.is_busy(JobKind::Transcription),
+            downloading_model: transcription.is_downloading.load(Ordering::SeqCst),
+        },
+        jobs: jobs.counts(),
+        disk: DiskHealth {
+            free_bytes,
+            low: free_bytes.is_some_and(|free| free < LOW_DISK_SPACE_BYTES),
+        },
+        permissions: get_permissions_status(app),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_status() {
+        let started = Instant::now();
+        assert_eq!(
+            step("a", started, Ok("fine".to_string())).status,
+            StepStatus::Passed
+        );
+        let failed = step("b", started, Err("broken".to_string()));
+        assert_eq!(failed.status, StepStatus::Failed);
+        assert_eq!(failed.detail, "broken");
+        assert_eq!(skipped("c", "later").status, StepStatus::Skipped);
+    }
+}
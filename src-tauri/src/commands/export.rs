@@ -1,28 +1,323 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use tauri::{AppHandle, Manager, State};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use zip::result::ZipResult;
+use zip::write::{SimpleFileOptions, ZipWriter};
+use zip::CompressionMethod;
 
-use crate::db::models::SummaryType;
+use crate::audio::{self, chapters};
+use crate::db::models::{ExportTemplate, SummaryType};
 use crate::db::Database;
+use crate::integrations::converter::{self, ConverterPreset};
+use crate::integrations::publish::{self, PublishDestination, PublishResult};
+use crate::jobs::{Job, JobKind, JobManager, Priority};
+use crate::settings;
+use crate::storage::{self, NoteFolder};
+use crate::templates;
 
-#[derive(serde::Serialize)]
+/// Setting keys for automatic export of finished notes
+pub const SETTING_AUTO_EXPORT_DIR: &str = "auto_export_dir";
+pub const SETTING_AUTO_EXPORT_FORMAT: &str = "auto_export_format";
+pub const SETTING_AUTO_EXPORT_TEMPLATE: &str = "auto_export_filename_template";
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{date}_{title}";
+
+/// Serializes auto-exports so concurrent triggers for a note don't race on its file
+static AUTO_EXPORT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize)]
 pub struct ExportData {
     pub markdown: String,
     pub filename: String,
 }
 
+/// Which notes to include in a bulk export. All fields are optional and combine with AND;
+/// an empty filter exports every note.
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportFilter {
+    /// Only notes started at or after this date (RFC 3339 or YYYY-MM-DD)
+    pub start_date: Option<String>,
+    /// Only notes started at or before this date (RFC 3339 or YYYY-MM-DD)
+    pub end_date: Option<String>,
+    /// Only notes carrying this tag (case-insensitive)
+    pub tag: Option<String>,
+    /// Only these notes
+    pub note_ids: Option<Vec<String>>,
+}
+
+/// Event payload emitted after each note of a bulk export
+#[derive(Clone, Serialize)]
+pub struct ExportProgressEvent {
+    pub note_id: String,
+    pub title: String,
+    pub current: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportFailure {
+    pub note_id: String,
+    pub error: String,
+}
+
+/// Automatic export of finished notes into a (typically cloud-synced) folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutoExportSettings {
+    /// Target folder; None disables auto-export
+    pub directory: Option<String>,
+    /// "markdown" or "pdf"
+    pub format: String,
+    /// Filename without extension; supports {title}, {date}, {time} and {id}
+    pub filename_template: String,
+}
+
+/// Event payload emitted after a note was auto-exported
+#[derive(Clone, Serialize)]
+pub struct AutoExportEvent {
+    pub note_id: String,
+    pub path: String,
+}
+
+/// Final report of a bulk export
+#[derive(Debug, Serialize)]
+pub struct ExportReport {
+    pub destination: String,
+    pub total: usize,
+    pub exported: Vec<String>,
+    pub failed: Vec<ExportFailure>,
+}
+
+/// A note handed to the share UI
+#[derive(Debug, Serialize)]
+pub struct SharedNote {
+    pub path: String,
+    /// The format shared, which is "markdown" when a PDF was asked for but the note has
+    /// text a PDF can't show
+    pub format: String,
+}
+
+/// Export a note as markdown, using the built-in layout or the export template `template_id`
 #[tauri::command]
 pub fn export_note_markdown(
     db: State<Database>,
     note_id: String,
+    template_id: Option<i64>,
 ) -> Result<ExportData, String> {
+    let template = template_id
+        .map(|id| db.get_export_template(id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    match template {
+        Some(template) => render_note_template(&conn, &note_id, &template.content),
+        None => build_note_markdown(&conn, &note_id),
+    }
+}
+
+#[tauri::command]
+pub fn list_export_templates(db: State<'_, Database>) -> Result<Vec<ExportTemplate>, String> {
+    db.list_export_templates().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_export_template(
+    name: String,
+    content: String,
+    db: State<'_, Database>,
+) -> Result<ExportTemplate, String> {
+    let name = validate_export_template(&name, &content)?;
+    db.create_export_template(&name, &content)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_export_template(
+    id: i64,
+    name: String,
+    content: String,
+    db: State<'_, Database>,
+) -> Result<ExportTemplate, String> {
+    let name = validate_export_template(&name, &content)?;
+    db.update_export_template(id, &name, &content)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_export_template(id: i64, db: State<'_, Database>) -> Result<(), String> {
+    db.delete_export_template(id).map_err(|e| e.to_string())
+}
+
+/// Render unsaved template content against a note, for the template editor's preview
+#[tauri::command]
+pub fn preview_export_template(
+    note_id: String,
+    content: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    Ok(render_note_template(&conn, &note_id, &content)?.markdown)
+}
+
+fn validate_export_template(name: &str, content: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    templates::validate(content).map_err(|e| e.to_string())?;
+    Ok(name.to_string())
+}
+
+/// Render a note through an export template
+fn render_note_template(
+    conn: &Connection,
+    note_id: &str,
+    template: &str,
+) -> Result<ExportData, String> {
+    let context = build_template_context(conn, note_id)?;
+    let markdown = templates::render(template, &context).map_err(|e| e.to_string())?;
+    let title = context["title"].as_str().unwrap_or_default();
+    Ok(ExportData {
+        markdown,
+        filename: format!("{}.md", safe_filename(title)),
+    })
+}
+
+/// The values export templates can use: note fields, `segments`, `summaries`, `tasks`
+/// and `tags`, plus preformatted dates and timestamps
+fn build_template_context(conn: &Connection, note_id: &str) -> Result<serde_json::Value, String> {
+    let (title, description, participants, started_at, ended_at): (
+        String,
+        Option<String>,
+        Option<String>,
+        String,
+        Option<String>,
+    ) = conn
+        .query_row(
+            "SELECT title, description, participants, started_at, ended_at FROM notes WHERE id = ?1",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT start_time, end_time, text, speaker FROM transcript_segments
+             WHERE note_id = ?1 ORDER BY start_time ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let segments: Vec<serde_json::Value> = stmt
+        .query_map([note_id], |row| {
+            let start: f64 = row.get(0)?;
+            let end: f64 = row.get(1)?;
+            Ok(serde_json::json!({
+                "start": start,
+                "end": end,
+                "timestamp": format_timestamp(start),
+                "text": row.get::<_, String>(2)?.trim(),
+                "speaker": row.get::<_, Option<String>>(3)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT summary_type, content, created_at FROM summaries
+             WHERE note_id = ?1 ORDER BY created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let summaries: Vec<serde_json::Value> = stmt
+        .query_map([note_id], |row| {
+            let summary_type: String = row.get(0)?;
+            let label = match SummaryType::from_str(&summary_type) {
+                SummaryType::Overview => "Overview",
+                SummaryType::ActionItems => "Action Items",
+                SummaryType::KeyDecisions => "Key Decisions",
+                SummaryType::Custom => "Custom Summary",
+            };
+            Ok(serde_json::json!({
+                "type": summary_type,
+                "label": label,
+                "content": row.get::<_, String>(1)?,
+                "created_at": row.get::<_, String>(2)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT text, assignee, due_date, done FROM action_items
+             WHERE note_id = ?1 ORDER BY sort_order ASC, id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let tasks: Vec<serde_json::Value> = stmt
+        .query_map([note_id], |row| {
+            Ok(serde_json::json!({
+                "text": row.get::<_, String>(0)?,
+                "assignee": row.get::<_, Option<String>>(1)?,
+                "due_date": row.get::<_, Option<String>>(2)?,
+                "done": row.get::<_, bool>(3)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name FROM tags t
+             INNER JOIN note_tags nt ON nt.tag_id = t.id
+             WHERE nt.note_id = ?1 ORDER BY t.name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tags: Vec<String> = stmt
+        .query_map([note_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let participants: Vec<String> = participants
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    Ok(serde_json::json!({
+        "id": note_id,
+        "title": title,
+        "description": description,
+        "participants": participants,
+        "started_at": started_at,
+        "ended_at": ended_at,
+        "date": format_datetime(&started_at),
+        "duration": ended_at.as_deref().map(|end| calculate_duration(&started_at, end)),
+        "segments": segments,
+        "summaries": summaries,
+        "tasks": tasks,
+        "tags": tags,
+    }))
+}
 
+/// Build the markdown export for a single note
+fn build_note_markdown(conn: &Connection, note_id: &str) -> Result<ExportData, String> {
     // Get note
     let note: (String, Option<String>, Option<String>, String, Option<String>) = conn
         .query_row(
             "SELECT title, description, participants, started_at, ended_at FROM notes WHERE id = ?1",
-            [&note_id],
+            [note_id],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
         )
         .map_err(|e| e.to_string())?;
@@ -38,7 +333,7 @@ pub fn export_note_markdown(
         .map_err(|e| e.to_string())?;
 
     let transcripts: Vec<(f64, f64, String)> = stmt
-        .query_map([&note_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .query_map([note_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
         .map_err(|e| e.to_string())?
         .filter_map(|r| r.ok())
         .collect();
@@ -52,7 +347,7 @@ pub fn export_note_markdown(
         .map_err(|e| e.to_string())?;
 
     let summaries: Vec<(String, String, String)> = stmt
-        .query_map([&note_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .query_map([note_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
         .map_err(|e| e.to_string())?
         .filter_map(|r| r.ok())
         .collect();
@@ -103,12 +398,7 @@ pub fn export_note_markdown(
     md.push_str("*Generated by Note67*\n");
 
     // Generate filename
-    let safe_title = title
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
-        .collect::<String>()
-        .replace(' ', "_");
-    let filename = format!("{}.md", safe_title);
+    let filename = format!("{}.md", safe_filename(&title));
 
     Ok(ExportData { markdown: md, filename })
 }
@@ -144,6 +434,1101 @@ pub fn get_export_directory(app: AppHandle) -> Result<String, String> {
     Ok(export_dir.to_string_lossy().to_string())
 }
 
+/// Export many notes at once. `format` is "markdown" or "json"; `destination` defaults to the
+/// export directory. Emits "export-progress" per note and returns a report of what was written.
+#[tauri::command]
+pub async fn export_notes(
+    app: AppHandle,
+    filter: Option<ExportFilter>,
+    format: String,
+    destination: Option<String>,
+    db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
+) -> Result<ExportReport, String> {
+    if format != "markdown" && format != "json" {
+        return Err(format!("Unsupported export format: {}", format));
+    }
+
+    let job = jobs
+        .enqueue(JobKind::Export, Priority::Normal, "Export notes", None)
+        .await?;
+    let result = write_notes(&app, filter, &format, destination, &db, &job);
+    job.finish(&result);
+    result
+}
+
+/// The work of `export_notes`; stops between notes if the job is cancelled
+fn write_notes(
+    app: &AppHandle,
+    filter: Option<ExportFilter>,
+    format: &str,
+    destination: Option<String>,
+    db: &Database,
+    job: &Job,
+) -> Result<ExportReport, String> {
+    let export_dir = match destination {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join("Note67"),
+    };
+    fs::create_dir_all(&export_dir).map_err(|e| e.to_string())?;
+
+    let filter = filter.unwrap_or_default();
+    let note_ids = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        select_export_notes(&conn, &filter)?
+    };
+
+    let total = note_ids.len();
+    let mut exported = Vec::new();
+    let mut failed = Vec::new();
+    let mut used_names = HashSet::new();
+
+    for (i, (note_id, title)) in note_ids.into_iter().enumerate() {
+        job.check_cancelled()?;
+        job.progress(i as f32 / total as f32);
+        let result = {
+            let conn = db.conn.lock().map_err(|e| e.to_string())?;
+            if format == "json" {
+                build_note_json(&conn, &note_id)
+            } else {
+                build_note_markdown(&conn, &note_id)
+            }
+        };
+
+        let written = result.and_then(|data| {
+            let path = unique_export_path(&export_dir, &data.filename, &mut used_names);
+            fs::write(&path, data.markdown).map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+
+        match written {
+            Ok(path) => exported.push(path.to_string_lossy().to_string()),
+            Err(error) => {
+                tracing::warn!("Failed to export note {}: {}", note_id, error);
+                failed.push(ExportFailure {
+                    note_id: note_id.clone(),
+                    error,
+                });
+            }
+        }
+
+        let _ = app.emit(
+            "export-progress",
+            ExportProgressEvent {
+                note_id,
+                title,
+                current: i + 1,
+                total,
+            },
+        );
+    }
+
+    Ok(ExportReport {
+        destination: export_dir.to_string_lossy().to_string(),
+        total,
+        exported,
+        failed,
+    })
+}
+
+/// Export a complete meeting record as a single zip: the markdown note and its PDF (when
+/// the note's text can go into one), the transcript (SRT and JSON), image attachments, and
+/// the recorded/uploaded audio.
+/// Returns the path of the written archive.
+#[tauri::command]
+pub async fn export_note_bundle(
+    app: AppHandle,
+    note_id: String,
+    destination: Option<String>,
+    db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
+) -> Result<String, String> {
+    let job = jobs
+        .enqueue(JobKind::Export, Priority::Normal, "Export meeting bundle", Some(&note_id))
+        .await?;
+    let result = write_bundle(&app, &note_id, destination, &db);
+    job.finish(&result);
+    result
+}
+
+fn write_bundle(
+    app: &AppHandle,
+    note_id: &str,
+    destination: Option<String>,
+    db: &Database,
+) -> Result<String, String> {
+    let export_dir = match destination {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join("Note67"),
+    };
+    fs::create_dir_all(&export_dir).map_err(|e| e.to_string())?;
+
+    let (markdown, json, srt, audio_path) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let audio_path: Option<String> = conn
+            .query_row("SELECT audio_path FROM notes WHERE id = ?1", [note_id], |row| {
+                row.get(0)
+            })
+            .map_err(|e| e.to_string())?;
+        (
+            build_note_markdown(&conn, note_id)?,
+            build_note_json(&conn, note_id)?,
+            build_transcript_srt(&conn, note_id)?,
+            audio_path,
+        )
+    };
+
+    // Collect audio files: the mixed playback file if present, else the recorded segments,
+    // plus any uploaded audio
+    let mut audio_files: Vec<PathBuf> = Vec::new();
+    if let Some(path) = audio_path.map(PathBuf::from).filter(|p| p.exists()) {
+        audio_files.push(path);
+    } else {
+        for segment in db.get_audio_segments(note_id).map_err(|e| e.to_string())? {
+            for path in [segment.mic_path, segment.system_path].into_iter().flatten() {
+                let path = PathBuf::from(path);
+                if path.exists() {
+                    audio_files.push(path);
+                }
+            }
+        }
+    }
+    for upload in db.get_uploaded_audio(note_id).map_err(|e| e.to_string())? {
+        let path = PathBuf::from(upload.file_path);
+        if path.exists() {
+            audio_files.push(path);
+        }
+    }
+
+    let attachments_dir = storage::folder(app, note_id, NoteFolder::Attachments)?;
+
+    let stem = Path::new(&markdown.filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Untitled")
+        .to_string();
+    let zip_path = unique_export_path(&export_dir, &format!("{}.zip", stem), &mut HashSet::new());
+
+    let pdf = pdf_renderable(note_id, &markdown.markdown)
+        .then(|| crate::pdf::markdown_to_pdf(&markdown.markdown));
+
+    let write_zip = || -> ZipResult<()> {
+        let file = fs::File::create(&zip_path)?;
+        let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+
+        zip_bytes(&mut zip, &markdown.filename, markdown.markdown.as_bytes())?;
+        if let Some(pdf) = &pdf {
+            zip_bytes(&mut zip, &format!("{}.pdf", stem), pdf)?;
+        }
+        zip_bytes(&mut zip, "transcript.json", json.markdown.as_bytes())?;
+        zip_bytes(&mut zip, "transcript.srt", srt.as_bytes())?;
+
+        if let Ok(entries) = fs::read_dir(&attachments_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() {
+                    let name = format!("attachments/{}", entry.file_name().to_string_lossy());
+                    zip_file(&mut zip, &name, &path)?;
+                }
+            }
+        }
+
+        for path in &audio_files {
+            if let Some(name) = path.file_name() {
+                let name = format!("audio/{}", name.to_string_lossy());
+                zip_file(&mut zip, &name, path)?;
+            }
+        }
+
+        zip.finish()?;
+        Ok(())
+    };
+
+    if let Err(e) = write_zip() {
+        // Don't leave a truncated archive behind
+        let _ = fs::remove_file(&zip_path);
+        return Err(format!("Failed to write bundle: {}", e));
+    }
+
+    Ok(zip_path.to_string_lossy().to_string())
+}
+
+/// Options for a bundle entry, stamped with the current local time
+fn zip_options(method: CompressionMethod) -> SimpleFileOptions {
+    let options = SimpleFileOptions::default().compression_method(method);
+    match zip::DateTime::try_from(chrono::Local::now().naive_local()) {
+        Ok(time) => options.last_modified_time(time),
+        Err(_) => options,
+    }
+}
+
+/// Add an in-memory file (deflated) to a bundle
+fn zip_bytes<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str, data: &[u8]) -> ZipResult<()> {
+    zip.start_file(name, zip_options(CompressionMethod::Deflated))?;
+    zip.write_all(data)?;
+    Ok(())
+}
+
+/// Add a file from disk to a bundle. Attachments and audio are already compressed, so
+/// they are stored; files of 4 GB or more get ZIP64 headers.
+fn zip_file<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &str, path: &Path) -> ZipResult<()> {
+    let mut file = fs::File::open(path)?;
+    let large = file.metadata()?.len() >= u32::MAX as u64;
+    zip.start_file(name, zip_options(CompressionMethod::Stored).large_file(large))?;
+    std::io::copy(&mut file, zip)?;
+    Ok(())
+}
+
+/// Export a note's recording as a single audio file with chapters players can jump between:
+/// one per marker, or where the transcript pauses when the note has no markers.
+/// Returns the path of the written file.
+#[tauri::command]
+pub async fn export_note_audio(
+    app: AppHandle,
+    note_id: String,
+    destination: Option<String>,
+    jobs: State<'_, JobManager>,
+) -> Result<String, String> {
+    let job = jobs
+        .enqueue(JobKind::Export, Priority::Normal, "Export audio", Some(&note_id))
+        .await?;
+    let id = note_id.clone();
+    let result =
+        tauri::async_runtime::spawn_blocking(move || write_audio(&app, &id, destination))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+    job.finish(&result);
+    result
+}
+
+fn write_audio(
+    app: &AppHandle,
+    note_id: &str,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let db = app.state::<Database>();
+    let (title, audio_path) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT title, audio_path FROM notes WHERE id = ?1",
+            [note_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .map_err(|e| e.to_string())?
+    };
+    let source = audio_path
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .ok_or("This note has no recording to export")?;
+    let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+    let duration_ms = audio::converter::get_audio_duration_ms(&source)
+        .map_err(|e| e.to_string())?
+        .max(0) as u64;
+
+    let markers: Vec<(u64, Option<String>)> = db
+        .get_markers(note_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|m| (m.position_ms.max(0) as u64, m.label))
+        .collect();
+    let chapters = if markers.is_empty() {
+        // Segment transcripts are timed from their own file; uploads aren't in the recording
+        let offsets: HashMap<i64, i64> = db
+            .get_audio_segments(note_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|s| (s.id, s.start_offset_ms))
+            .collect();
+        let mut spoken: Vec<(u64, u64, String)> = db
+            .get_transcript_segments(note_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter_map(|s| {
+                let offset = match (s.source_type.as_deref(), s.source_id) {
+                    (Some("upload"), _) => return None,
+                    (Some("segment"), Some(id)) => *offsets.get(&id)?,
+                    _ => 0,
+                };
+                let ms = |seconds: f64| (seconds * 1000.0).max(0.0) as u64 + offset as u64;
+                Some((ms(s.start_time), ms(s.end_time), s.text))
+            })
+            .collect();
+        spoken.sort_by_key(|(start, _, _)| *start);
+        chapters::from_transcript(&spoken, duration_ms)
+    } else {
+        chapters::from_markers(&markers, duration_ms)
+    };
+
+    let export_dir = match destination {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join("Note67"),
+    };
+    fs::create_dir_all(&export_dir).map_err(|e| e.to_string())?;
+    let filename = format!("{}.{}", safe_filename(&title), ext);
+    let path = unique_export_path(&export_dir, &filename, &mut HashSet::new());
+
+    let written = fs::copy(&source, &path)
+        .map_err(|e| e.to_string())
+        .and_then(|_| chapters::embed(&path, &chapters).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Failed to export audio: {}", e));
+    }
+
+    tracing::info!(
+        "Exported the audio of {} with {} chapters",
+        note_id,
+        chapters.len()
+    );
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Export a note to a temporary file and open the OS share sheet for it (see `share.rs`).
+/// `format` is "markdown", "pdf", "json" or "srt". Returns the shared file and its format,
+/// so the user can be told when a PDF fell back to markdown.
+#[tauri::command]
+pub fn share_note(
+    app: AppHandle,
+    note_id: String,
+    mut format: String,
+    db: State<'_, Database>,
+) -> Result<SharedNote, String> {
+    let (bytes, filename) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        match format.as_str() {
+            "markdown" => {
+                let data = build_note_markdown(&conn, &note_id)?;
+                (data.markdown.into_bytes(), data.filename)
+            }
+            "pdf" => {
+                let data = build_note_markdown(&conn, &note_id)?;
+                if pdf_renderable(&note_id, &data.markdown) {
+                    let stem = data.filename.trim_end_matches(".md").to_string();
+                    (crate::pdf::markdown_to_pdf(&data.markdown), format!("{}.pdf", stem))
+                } else {
+                    format = "markdown".to_string();
+                    (data.markdown.into_bytes(), data.filename)
+                }
+            }
+            "json" => {
+                let data = build_note_json(&conn, &note_id)?;
+                (data.markdown.into_bytes(), data.filename)
+            }
+            "srt" => {
+                let title: String = conn
+                    .query_row("SELECT title FROM notes WHERE id = ?1", [&note_id], |row| {
+                        row.get(0)
+                    })
+                    .map_err(|e| e.to_string())?;
+                (
+                    build_transcript_srt(&conn, &note_id)?.into_bytes(),
+                    format!("{}.srt", safe_filename(&title)),
+                )
+            }
+            _ => return Err(format!("Unsupported share format: {}", format)),
+        }
+    };
+
+    // One folder per note so shares of same-titled notes don't overwrite each other
+    let dir = crate::share::share_dir().join(&note_id);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(filename);
+    fs::write(&path, bytes).map_err(|e| e.to_string())?;
+
+    crate::share::share_file(&app, &path)?;
+    Ok(SharedNote {
+        path: path.to_string_lossy().to_string(),
+        format,
+    })
+}
+
+/// Publish a note as a standalone HTML page (summary, tasks, collapsible transcript and,
+/// with `include_audio`, the recording) to a folder, a GitHub Gist or an SFTP server.
+/// See `integrations/publish.rs`.
+#[tauri::command]
+pub async fn publish_note(
+    app: AppHandle,
+    note_id: String,
+    destination: PublishDestination,
+    include_audio: bool,
+    db: State<'_, Database>,
+) -> Result<PublishResult, String> {
+    let (context, filename, audio_path) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let audio_path: Option<String> = conn
+            .query_row("SELECT audio_path FROM notes WHERE id = ?1", [&note_id], |row| {
+                row.get(0)
+            })
+            .map_err(|e| e.to_string())?;
+        (
+            build_template_context(&conn, &note_id)?,
+            build_note_markdown(&conn, &note_id)?.filename,
+            audio_path,
+        )
+    };
+    let stem = filename.trim_end_matches(".md").to_string();
+
+    // The mixed playback file, else the first uploaded recording
+    let audio = if include_audio {
+        let uploaded = db.get_uploaded_audio(&note_id).map_err(|e| e.to_string())?;
+        let audio = audio_path
+            .into_iter()
+            .chain(uploaded.into_iter().map(|u| u.file_path))
+            .map(PathBuf::from)
+            .find(|p| p.exists());
+        Some(audio.ok_or("This note has no audio to publish")?)
+    } else {
+        None
+    };
+    let audio_name = |page: &Path| {
+        audio.as_ref().map(|audio| {
+            let ext = audio.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+            let page_stem = page.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+            format!("{}.{}", page_stem, ext)
+        })
+    };
+
+    match destination {
+        PublishDestination::Folder { path } => {
+            let dir = match path {
+                Some(dir) => PathBuf::from(dir),
+                None => app
+                    .path()
+                    .document_dir()
+                    .map_err(|e| e.to_string())?
+                    .join("Note67"),
+            };
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let page = unique_export_path(&dir, &format!("{}.html", stem), &mut HashSet::new());
+            let audio_name = audio_name(&page);
+
+            let mut files = Vec::new();
+            if let (Some(audio), Some(name)) = (&audio, &audio_name) {
+                let target = dir.join(name);
+                fs::copy(audio, &target).map_err(|e| e.to_string())?;
+                files.push(target.to_string_lossy().to_string());
+            }
+            write_atomic(
+                &page,
+                publish::render_page(&context, audio_name.as_deref()).as_bytes(),
+            )?;
+            let location = page.to_string_lossy().to_string();
+            files.insert(0, location.clone());
+            Ok(PublishResult { location, files })
+        }
+        PublishDestination::Gist { public } => {
+            if audio.is_some() {
+                return Err(publish::PublishError::AudioNotSupported.to_string());
+            }
+            let token = crate::secrets::get_secret(publish::SECRET_GIST_TOKEN)?
+                .filter(|t| !t.trim().is_empty())
+                .ok_or_else(|| publish::PublishError::NoGistToken.to_string())?;
+            let title = context["title"].as_str().unwrap_or_default().to_string();
+            let filename = format!("{}.html", stem);
+            let url = publish::create_gist(
+                &token,
+                &title,
+                &filename,
+                &publish::render_page(&context, None),
+                public,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            Ok(PublishResult {
+                location: url,
+                files: vec![filename],
+            })
+        }
+        PublishDestination::Sftp {
+            host,
+            port,
+            username,
+            remote_dir,
+            base_url,
+        } => {
+            // Stage the files locally, named as they will be on the server
+            let dir = std::env::temp_dir().join("note67-publish").join(&note_id);
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let page = dir.join(format!("{}.html", stem));
+            let audio_name = audio_name(&page);
+
+            let mut staged = vec![page.clone()];
+            if let (Some(audio), Some(name)) = (&audio, &audio_name) {
+                let target = dir.join(name);
+                fs::copy(audio, &target).map_err(|e| e.to_string())?;
+                staged.push(target);
+            }
+            fs::write(
+                &page,
+                publish::render_page(&context, audio_name.as_deref()),
+            )
+            .map_err(|e| e.to_string())?;
+
+            let upload = tauri::async_runtime::spawn_blocking({
+                let staged = staged.clone();
+                let remote_dir = remote_dir.clone();
+                move || {
+                    let files: Vec<&Path> = staged.iter().map(|p| p.as_path()).collect();
+                    publish::upload_sftp(&host, port, &username, &remote_dir, &files)
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            let _ = fs::remove_dir_all(&dir);
+            upload.map_err(|e| e.to_string())?;
+
+            let remote_dir = remote_dir.trim_end_matches('/');
+            let files: Vec<String> = staged
+                .iter()
+                .filter_map(|p| p.file_name())
+                .map(|name| format!("{}/{}", remote_dir, name.to_string_lossy()))
+                .collect();
+            let page_name = format!("{}.html", stem);
+            let location = match base_url.filter(|u| !u.trim().is_empty()) {
+                Some(base) => tauri::Url::parse(&format!("{}/", base.trim_end_matches('/')))
+                    .and_then(|base| base.join(&page_name))
+                    .map_err(|e| format!("Invalid base URL: {}", e))?
+                    .to_string(),
+                None => files[0].clone(),
+            };
+            Ok(PublishResult { location, files })
+        }
+    }
+}
+
+/// Converter presets (see `integrations/converter.rs`); the defaults until the user saves any
+#[tauri::command]
+pub fn get_converter_presets(db: State<'_, Database>) -> Result<Vec<ConverterPreset>, String> {
+    match db
+        .get_setting(converter::SETTING_PRESETS)
+        .map_err(|e| e.to_string())?
+    {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(converter::default_presets()),
+    }
+}
+
+#[tauri::command]
+pub fn set_converter_presets(app: AppHandle, presets: Vec<ConverterPreset>) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for preset in &presets {
+        if preset.name.trim().is_empty() || preset.command.trim().is_empty() {
+            return Err("Each preset needs a name and a command".to_string());
+        }
+        if preset.extension.is_empty() || !preset.extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid file extension for \"{}\"", preset.name));
+        }
+        if !names.insert(preset.name.trim().to_lowercase()) {
+            return Err(format!("Duplicate preset name: {}", preset.name));
+        }
+    }
+    let json = serde_json::to_string(&presets).map_err(|e| e.to_string())?;
+    settings::set(&app, converter::SETTING_PRESETS, &json).map_err(|e| e.to_string())
+}
+
+/// Ask the user before a converter command line first runs. Presets run any program, and
+/// one can be saved without anyone reading it; the defaults are trusted as they are.
+async fn confirm_converter(
+    app: &AppHandle,
+    db: &Database,
+    preset: &ConverterPreset,
+) -> Result<(), String> {
+    let command_line = preset.command_line();
+    if converter::default_presets()
+        .iter()
+        .any(|p| p.command_line() == command_line)
+    {
+        return Ok(());
+    }
+    let mut approved: Vec<String> = db
+        .get_setting(converter::SETTING_APPROVED)
+        .map_err(|e| e.to_string())?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    if approved.contains(&command_line) {
+        return Ok(());
+    }
+
+    let dialog = app
+        .dialog()
+        .message(format!(
+            "The \"{}\" converter runs this command:\n\n{}\n\nOnly run commands you trust.",
+            preset.name, command_line
+        ))
+        .title("Run converter?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Run".to_string(),
+            "Cancel".to_string(),
+        ));
+    let confirmed = tauri::async_runtime::spawn_blocking(move || dialog.blocking_show())
+        .await
+        .map_err(|e| e.to_string())?;
+    if !confirmed {
+        return Err(format!("Converter \"{}\" was not run", preset.name));
+    }
+
+    approved.push(command_line);
+    let json = serde_json::to_string(&approved).map_err(|e| e.to_string())?;
+    db.set_setting(converter::SETTING_APPROVED, &json)
+        .map_err(|e| e.to_string())
+}
+
+/// Export a note through the converter preset named `preset` (the markdown comes from the
+/// export template `template_id` when given). A command line the user hasn't run before
+/// is confirmed first. Writes to `destination` or the export directory and returns the
+/// path of the converted file.
+#[tauri::command]
+pub async fn export_note_converted(
+    app: AppHandle,
+    note_id: String,
+    preset: String,
+    template_id: Option<i64>,
+    destination: Option<String>,
+    db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
+) -> Result<String, String> {
+    let preset = get_converter_presets(db.clone())?
+        .into_iter()
+        .find(|p| p.name == preset)
+        .ok_or_else(|| format!("Converter preset not found: {}", preset))?;
+    confirm_converter(&app, &db, &preset).await?;
+    let data = export_note_markdown(db, note_id.clone(), template_id)?;
+
+    let dir = match destination {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .document_dir()
+            .map_err(|e| e.to_string())?
+            .join("Note67"),
+    };
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let stem = data.filename.trim_end_matches(".md");
+    let path = unique_export_path(
+        &dir,
+        &format!("{}.{}", stem, preset.extension),
+        &mut HashSet::new(),
+    );
+
+    let job = jobs
+        .enqueue(
+            JobKind::Export,
+            Priority::Normal,
+            &format!("Convert to {}", preset.name),
+            Some(&note_id),
+        )
+        .await?;
+    let output = path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        converter::convert(&preset, &data.markdown, &output)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r.map_err(|e| e.to_string()));
+    job.finish(&result);
+    result?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Build an SRT subtitle file from a note's transcript
+fn build_transcript_srt(conn: &Connection, note_id: &str) -> Result<String, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT start_time, end_time, text, speaker FROM transcript_segments
+             WHERE note_id = ?1 ORDER BY start_time ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let segments: Vec<(f64, f64, String, Option<String>)> = stmt
+        .query_map([note_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut srt = String::new();
+    for (i, (start, end, text, speaker)) in segments.iter().enumerate() {
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n",
+            i + 1,
+            format_srt_timestamp(*start),
+            format_srt_timestamp(*end)
+        ));
+        match speaker {
+            Some(speaker) => srt.push_str(&format!("{}: {}\n\n", speaker, text.trim())),
+            None => srt.push_str(&format!("{}\n\n", text.trim())),
+        }
+    }
+    Ok(srt)
+}
+
+/// Resolve a bulk export filter to (id, title) pairs, oldest first
+fn select_export_notes(
+    conn: &Connection,
+    filter: &ExportFilter,
+) -> Result<Vec<(String, String)>, String> {
+    // Dates are stored as RFC 3339 UTC strings, so plain string comparison orders correctly.
+    // A bare YYYY-MM-DD end date is widened to cover the whole day.
+    let end_date = filter.end_date.as_ref().map(|d| {
+        if d.len() == 10 {
+            format!("{}T23:59:59.999999999+00:00", d)
+        } else {
+            d.clone()
+        }
+    });
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.title FROM notes n
+             WHERE (?1 IS NULL OR n.started_at >= ?1)
+               AND (?2 IS NULL OR n.started_at <= ?2)
+               AND (?3 IS NULL OR n.id IN (
+                    SELECT nt.note_id FROM note_tags nt
+                    INNER JOIN tags t ON nt.tag_id = t.id
+                    WHERE LOWER(t.name) = LOWER(?3)))
+             ORDER BY n.started_at ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let notes: Vec<(String, String)> = stmt
+        .query_map(
+            rusqlite::params![filter.start_date, end_date, filter.tag],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(match &filter.note_ids {
+        Some(ids) => notes.into_iter().filter(|(id, _)| ids.contains(id)).collect(),
+        None => notes,
+    })
+}
+
+/// Build a JSON export (note metadata, summaries, and transcript) for a single note
+fn build_note_json(conn: &Connection, note_id: &str) -> Result<ExportData, String> {
+    let note = conn
+        .query_row(
+            "SELECT title, description, participants, started_at, ended_at FROM notes WHERE id = ?1",
+            [note_id],
+            |row| {
+                Ok(serde_json::json!({
+                    "id": note_id,
+                    "title": row.get::<_, String>(0)?,
+                    "description": row.get::<_, Option<String>>(1)?,
+                    "participants": row.get::<_, Option<String>>(2)?,
+                    "started_at": row.get::<_, String>(3)?,
+                    "ended_at": row.get::<_, Option<String>>(4)?,
+                }))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT start_time, end_time, text, speaker FROM transcript_segments
+             WHERE note_id = ?1 ORDER BY start_time ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let transcript: Vec<serde_json::Value> = stmt
+        .query_map([note_id], |row| {
+            Ok(serde_json::json!({
+                "start_time": row.get::<_, f64>(0)?,
+                "end_time": row.get::<_, f64>(1)?,
+                "text": row.get::<_, String>(2)?,
+                "speaker": row.get::<_, Option<String>>(3)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT summary_type, content, created_at FROM summaries
+             WHERE note_id = ?1 ORDER BY created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let summaries: Vec<serde_json::Value> = stmt
+        .query_map([note_id], |row| {
+            Ok(serde_json::json!({
+                "summary_type": row.get::<_, String>(0)?,
+                "content": row.get::<_, String>(1)?,
+                "created_at": row.get::<_, String>(2)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let title = note["title"].as_str().unwrap_or_default().to_string();
+    let export = serde_json::json!({
+        "note": note,
+        "summaries": summaries,
+        "transcript": transcript,
+    });
+
+    Ok(ExportData {
+        markdown: serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?,
+        filename: format!("{}.json", safe_filename(&title)),
+    })
+}
+
+#[tauri::command]
+pub fn get_auto_export_settings(db: State<'_, Database>) -> Result<AutoExportSettings, String> {
+    let get = |key: &str| db.get_setting(key).map_err(|e| e.to_string());
+    Ok(AutoExportSettings {
+        directory: get(SETTING_AUTO_EXPORT_DIR)?.filter(|d| !d.is_empty()),
+        format: get(SETTING_AUTO_EXPORT_FORMAT)?.unwrap_or_else(|| "markdown".to_string()),
+        filename_template: get(SETTING_AUTO_EXPORT_TEMPLATE)?
+            .unwrap_or_else(|| DEFAULT_FILENAME_TEMPLATE.to_string()),
+    })
+}
+
+#[tauri::command]
+pub fn set_auto_export_settings(
+    app: AppHandle,
+    settings: AutoExportSettings,
+) -> Result<(), String> {
+    if settings.format != "markdown" && settings.format != "pdf" {
+        return Err(format!("Unsupported auto-export format: {}", settings.format));
+    }
+    if settings.filename_template.trim().is_empty() {
+        return Err("Filename template cannot be empty".to_string());
+    }
+
+    let directory = settings.directory.unwrap_or_default();
+    if !directory.is_empty() {
+        fs::create_dir_all(&directory)
+            .map_err(|e| format!("Cannot use {} as export directory: {}", directory, e))?;
+    }
+
+    let values = [
+        (SETTING_AUTO_EXPORT_DIR, directory.as_str()),
+        (SETTING_AUTO_EXPORT_FORMAT, settings.format.as_str()),
+        (SETTING_AUTO_EXPORT_TEMPLATE, settings.filename_template.trim()),
+    ];
+    for (key, value) in values {
+        crate::settings::set(&app, key, value).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Re-export a finished note to the auto-export directory, if one is configured.
+/// Runs in the background; called when a note ends and when its transcript or summary changes.
+pub(crate) fn auto_export_note(app: &AppHandle, note_id: &str) {
+    let app = app.clone();
+    let note_id = note_id.to_string();
+    tauri::async_runtime::spawn_blocking(move || match run_auto_export(&app, &note_id) {
+        Ok(Some(path)) => {
+            let _ = app.emit(
+                "note-auto-exported",
+                AutoExportEvent {
+                    note_id,
+                    path: path.to_string_lossy().to_string(),
+                },
+            );
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Auto-export of note {} failed: {}", note_id, e),
+    });
+}
+
+/// Whether a note's markdown can go into a PDF; notes in scripts the PDF writer can't show
+/// (see `pdf::can_render`) are exported as markdown instead
+fn pdf_renderable(note_id: &str, markdown: &str) -> bool {
+    let renderable = crate::pdf::can_render(markdown);
+    if !renderable {
+        tracing::warn!("Note {} has text a PDF can't show; exporting it as markdown", note_id);
+    }
+    renderable
+}
+
+fn run_auto_export(app: &AppHandle, note_id: &str) -> Result<Option<PathBuf>, String> {
+    let db = app.state::<Database>();
+    let get = |key: &str| db.get_setting(key).map_err(|e| e.to_string());
+
+    let Some(dir) = get(SETTING_AUTO_EXPORT_DIR)?.filter(|d| !d.is_empty()) else {
+        return Ok(None);
+    };
+    let format = get(SETTING_AUTO_EXPORT_FORMAT)?.unwrap_or_else(|| "markdown".to_string());
+    let template =
+        get(SETTING_AUTO_EXPORT_TEMPLATE)?.unwrap_or_else(|| DEFAULT_FILENAME_TEMPLATE.to_string());
+
+    let _guard = AUTO_EXPORT_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let (data, title, started_at) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let (title, started_at, ended_at): (String, String, Option<String>) = conn
+            .query_row(
+                "SELECT title, started_at, ended_at FROM notes WHERE id = ?1",
+                [note_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        // Only finished notes are exported
+        if ended_at.is_none() {
+            return Ok(None);
+        }
+        (build_note_markdown(&conn, note_id)?, title, started_at)
+    };
+
+    let dir = PathBuf::from(dir);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let pdf = format == "pdf" && pdf_renderable(note_id, &data.markdown);
+    let ext = if pdf { "pdf" } else { "md" };
+    let stem = render_filename_template(&template, &title, &started_at, note_id);
+    let filename = format!("{}.{}", stem, ext);
+    let previous = db
+        .get_auto_export_path(note_id)
+        .map_err(|e| e.to_string())?
+        .map(PathBuf::from);
+
+    // Overwrite this note's own previous export; never clobber any other file
+    let desired = dir.join(&filename);
+    let path = match &previous {
+        Some(prev) if *prev == desired => desired,
+        Some(prev) if desired.exists() && is_suffixed_variant(prev, &dir, &stem, ext) => {
+            prev.clone()
+        }
+        _ if desired.exists() => unique_export_path(&dir, &filename, &mut HashSet::new()),
+        _ => desired,
+    };
+
+    let bytes = if pdf {
+        crate::pdf::markdown_to_pdf(&data.markdown)
+    } else {
+        data.markdown.into_bytes()
+    };
+    write_atomic(&path, &bytes)?;
+
+    // The note was renamed (or the template changed): drop the stale file
+    if let Some(prev) = previous
+        && prev != path
+        && prev.exists()
+    {
+        let _ = fs::remove_file(&prev);
+    }
+
+    db.set_auto_export_path(note_id, &path.to_string_lossy())
+        .map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+/// Whether `path` is `dir/<stem>_<n>.<ext>`, i.e. a collision-renamed copy of `stem`
+fn is_suffixed_variant(path: &Path, dir: &Path, stem: &str, ext: &str) -> bool {
+    path.parent() == Some(dir)
+        && path.extension().and_then(|e| e.to_str()) == Some(ext)
+        && path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix(stem))
+            .and_then(|s| s.strip_prefix('_'))
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Write through a temp file and rename, so sync clients never pick up a partial file
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| "Invalid export path".to_string())?;
+    let tmp = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
+
+/// Expand an auto-export filename template. Placeholders: {title}, {date} (YYYY-MM-DD),
+/// {time} (HH-MM), {id} (first 8 characters of the note id)
+fn render_filename_template(template: &str, title: &str, started_at: &str, note_id: &str) -> String {
+    let started = chrono::DateTime::parse_from_rfc3339(started_at)
+        .map(|dt| dt.with_timezone(&chrono::Local))
+        .ok();
+    let date = started
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let time = started
+        .map(|dt| dt.format("%H-%M").to_string())
+        .unwrap_or_default();
+    let short_id: String = note_id.chars().take(8).collect();
+
+    let rendered = template
+        .replace("{title}", &safe_filename(title))
+        .replace("{date}", &date)
+        .replace("{time}", &time)
+        .replace("{id}", &short_id);
+
+    // Keep the result a plain file name
+    let cleaned: String = rendered
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').to_string();
+    if cleaned.is_empty() {
+        safe_filename(title)
+    } else {
+        cleaned
+    }
+}
+
+/// Make a note title safe to use as a filename
+fn safe_filename(title: &str) -> String {
+    let safe = title
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .collect::<String>()
+        .replace(' ', "_");
+    if safe.is_empty() {
+        "Untitled".to_string()
+    } else {
+        safe
+    }
+}
+
+/// Pick a path in `dir` that neither exists on disk nor was already used by this export run,
+/// appending _2, _3, ... to the file stem on collision
+fn unique_export_path(dir: &Path, filename: &str, used: &mut HashSet<String>) -> PathBuf {
+    let path = Path::new(filename);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("export");
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("md");
+
+    let mut candidate = filename.to_string();
+    let mut n = 2;
+    while used.contains(&candidate) || dir.join(&candidate).exists() {
+        candidate = format!("{}_{}.{}", stem, n, ext);
+        n += 1;
+    }
+    used.insert(candidate.clone());
+    dir.join(candidate)
+}
+
 fn format_datetime(datetime_str: &str) -> String {
     chrono::DateTime::parse_from_rfc3339(datetime_str)
         .map(|dt| dt.format("%B %d, %Y at %H:%M").to_string())
@@ -163,6 +1548,15 @@ fn format_timestamp(seconds: f64) -> String {
     }
 }
 
+fn format_srt_timestamp(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let minutes = (total_ms % 3_600_000) / 60_000;
+    let secs = (total_ms % 60_000) / 1000;
+    let millis = total_ms % 1000;
+    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, millis)
+}
+
 fn calculate_duration(start: &str, end: &str) -> String {
     let start_dt = chrono::DateTime::parse_from_rfc3339(start);
     let end_dt = chrono::DateTime::parse_from_rfc3339(end);
@@ -187,3 +1581,49 @@ fn calculate_duration(start: &str, end: &str) -> String {
         _ => "Unknown".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_filename_template() {
+        let started = "2026-01-15T10:00:00+00:00";
+        assert_eq!(
+            render_filename_template("{title} - {id}", "Weekly Sync", started, "abcdef123456"),
+            "Weekly_Sync - abcdef12"
+        );
+        // Path separators from the template can't escape the export directory
+        assert_eq!(
+            render_filename_template("../{title}", "Sync", started, "id"),
+            "_Sync"
+        );
+        assert_eq!(render_filename_template("{id}", "Sync", started, ""), "Sync");
+
+        let dir = Path::new("/exports");
+        assert!(is_suffixed_variant(&dir.join("Sync_2.md"), dir, "Sync", "md"));
+        assert!(!is_suffixed_variant(&dir.join("Sync_Notes.md"), dir, "Sync", "md"));
+    }
+    #[test]
+    fn test_zip_entries() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip_bytes(&mut zip, "Notes – Sync.md", b"# Sync\n\nSync sync sync sync").unwrap();
+        zip_file(&mut zip, "audio/raw.bin", &manifest).unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut note = archive.by_name("Notes – Sync.md").unwrap();
+        assert_eq!(note.compression(), CompressionMethod::Deflated);
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut note, &mut text).unwrap();
+        assert_eq!(text, "# Sync\n\nSync sync sync sync");
+        drop(note);
+
+        let mut audio = archive.by_name("audio/raw.bin").unwrap();
+        assert_eq!(audio.compression(), CompressionMethod::Stored);
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut audio, &mut data).unwrap();
+        assert_eq!(data, fs::read(&manifest).unwrap());
+    }
+}
@@ -1,6 +1,7 @@
-use tauri::Manager;
 use uuid::Uuid;
 
+use crate::storage::{self, NoteFolder};
+
 /// Save an image to the attachments folder and return the asset URL
 #[tauri::command]
 pub async fn save_image(
@@ -9,16 +10,7 @@ pub async fn save_image(
     image_data: Vec<u8>,
     filename: String,
 ) -> Result<String, String> {
-    // Get app data directory
-    let app_data = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    // Create attachments/{note_id}/ folder
-    let attachments_dir = app_data.join("attachments").join(&note_id);
-    std::fs::create_dir_all(&attachments_dir)
-        .map_err(|e| format!("Failed to create attachments dir: {}", e))?;
+    let attachments_dir = storage::create_folder(&app_handle, &note_id, NoteFolder::Attachments)?;
 
     // Generate unique filename with original extension
     let extension = std::path::Path::new(&filename)
@@ -39,12 +31,7 @@ pub async fn save_image(
 /// Get the attachments directory path for a note
 #[tauri::command]
 pub fn get_attachments_dir(app_handle: tauri::AppHandle, note_id: String) -> Result<String, String> {
-    let app_data = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let attachments_dir = app_data.join("attachments").join(&note_id);
+    let attachments_dir = storage::folder(&app_handle, &note_id, NoteFolder::Attachments)?;
     Ok(attachments_dir.to_string_lossy().to_string())
 }
 
@@ -54,12 +41,7 @@ pub async fn delete_note_attachments(
     app_handle: tauri::AppHandle,
     note_id: String,
 ) -> Result<(), String> {
-    let app_data = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    let attachments_dir = app_data.join("attachments").join(&note_id);
+    let attachments_dir = storage::folder(&app_handle, &note_id, NoteFolder::Attachments)?;
 
     if attachments_dir.exists() {
         std::fs::remove_dir_all(&attachments_dir)
@@ -0,0 +1,354 @@
+//! Commands for importing history from other note and meeting apps.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::audio::converter::{convert_to_wav, get_audio_duration_ms};
+use crate::commands::links::sync_note_links_internal;
+use crate::commands::tags::sync_note_tags_internal;
+use crate::db::Database;
+use crate::importers::{self, ImportedNote};
+use crate::jobs::{Job, JobKind, JobManager, Priority};
+use crate::storage::{self, NoteFolder};
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Result of an import run
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    /// Ids of the notes created
+    pub imported: Vec<String>,
+    /// Sources imported by an earlier run
+    pub skipped: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
+
+/// Event payload emitted after each file of an import
+#[derive(Clone, Serialize)]
+pub struct ImportProgressEvent {
+    pub path: String,
+    pub current: usize,
+    pub total: usize,
+}
+
+/// Import Otter.ai transcripts (.txt exports) from a file or a folder of files.
+/// Each transcript becomes a finished note with speaker-labelled transcript segments.
+#[tauri::command]
+pub async fn import_otter_transcripts(
+    app: AppHandle,
+    path: String,
+    db: State<'_, Database>,
+) -> Result<ImportReport, String> {
+    let files = collect_files(Path::new(&path), &["txt"], false)?;
+    run_import(&app, &db, "otter", files, |file| {
+        let content = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        importers::parse_otter_transcript(&content, &file_stem(file), modified_at(file))
+            .ok_or_else(|| "Not an Otter transcript (no timestamped speaker blocks)".to_string())
+    })
+}
+
+/// Import a folder of markdown notes (searched recursively). The note text becomes the
+/// note body, so tags and [[links]] are picked up as usual.
+#[tauri::command]
+pub async fn import_markdown_folder(
+    app: AppHandle,
+    path: String,
+    db: State<'_, Database>,
+) -> Result<ImportReport, String> {
+    let files = collect_files(Path::new(&path), &["md", "markdown"], true)?;
+    run_import(&app, &db, "markdown", files, |file| {
+        let content = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        Ok(importers::parse_markdown_note(
+            &content,
+            &file_stem(file),
+            modified_at(file),
+        ))
+    })
+}
+
+/// Import Apple Voice Memos. Each recording becomes a note with the audio attached as an
+/// upload, ready to transcribe. `path` defaults to the Voice Memos library on macOS.
+#[tauri::command]
+pub async fn import_voice_memos(
+    app: AppHandle,
+    path: Option<String>,
+    db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
+) -> Result<ImportReport, String> {
+    let dir = match path {
+        Some(p) => PathBuf::from(p),
+        None => importers::default_voice_memos_dir()
+            .ok_or_else(|| "Voice Memos library not found. Choose its folder.".to_string())?,
+    };
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+
+    let job = jobs
+        .enqueue(JobKind::Conversion, Priority::Normal, "Import Voice Memos", None)
+        .await?;
+    let result = import_memos(&app, &db, &dir, &job);
+    job.finish(&result);
+    result
+}
+
+/// Create a note per memo, converting its audio. Stops between memos if the job is cancelled.
+fn import_memos(app: &AppHandle, db: &Database, dir: &Path, job: &Job) -> Result<ImportReport, String> {
+    let memos = importers::find_voice_memos(dir);
+    let total = memos.len();
+    let mut report = ImportReport::default();
+
+    for (i, memo) in memos.into_iter().enumerate() {
+        job.check_cancelled()?;
+        job.progress(i as f32 / total as f32);
+        let source = format!("voicememos:{}", memo.path.display());
+        let _ = app.emit(
+            "import-progress",
+            ImportProgressEvent {
+                path: memo.path.to_string_lossy().to_string(),
+                current: i + 1,
+                total,
+            },
+        );
+
+        if db.is_source_imported(&source).map_err(|e| e.to_string())? {
+            report.skipped.push(memo.path.to_string_lossy().to_string());
+            continue;
+        }
+
+        let note = ImportedNote {
+            title: memo.title.clone(),
+            description: None,
+            participants: None,
+            started_at: memo.recorded_at,
+            duration_secs: memo.duration_secs,
+            segments: Vec::new(),
+        };
+
+        let result = insert_note(db, &note).and_then(|note_id| {
+            // Convert to 16kHz WAV like regular uploads, via a temp file
+            let stem = &Uuid::new_v4().to_string()[..8];
+            let uploads_dir = match storage::create_folder(app, &note_id, NoteFolder::Uploads) {
+                Ok(dir) => dir,
+                Err(e) => {
+                    let _ = remove_note(db, &note_id);
+                    return Err(e);
+                }
+            };
+            let output_path = uploads_dir.join(format!("{}_upload_{}.wav", note_id, stem));
+            let temp_path = uploads_dir.join(format!("{}_upload_{}.wav.tmp", note_id, stem));
+            if let Err(e) = convert_to_wav(&memo.path, &temp_path)
+                .map_err(|e| e.to_string())
+                .and_then(|_| fs::rename(&temp_path, &output_path).map_err(|e| e.to_string()))
+            {
+                let _ = storage::delete_note_files(app, &note_id);
+                let _ = remove_note(db, &note_id);
+                return Err(e);
+            }
+
+            let original_filename = memo
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("memo.m4a")
+                .to_string();
+            db.add_uploaded_audio(
+                &note_id,
+                &output_path.to_string_lossy(),
+                &original_filename,
+                get_audio_duration_ms(&output_path).ok(),
+                "Voice Memo",
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(note_id)
+        });
+
+        finish_item(app, db, &mut report, &source, &memo.path, result);
+    }
+
+    Ok(report)
+}
+
+/// Parse and insert each file, skipping sources imported before
+fn run_import(
+    app: &AppHandle,
+    db: &Database,
+    kind: &str,
+    files: Vec<PathBuf>,
+    parse: impl Fn(&Path) -> Result<ImportedNote, String>,
+) -> Result<ImportReport, String> {
+    let total = files.len();
+    let mut report = ImportReport::default();
+
+    for (i, file) in files.into_iter().enumerate() {
+        let _ = app.emit(
+            "import-progress",
+            ImportProgressEvent {
+                path: file.to_string_lossy().to_string(),
+                current: i + 1,
+                total,
+            },
+        );
+
+        let source = format!("{}:{}", kind, file.display());
+        if db.is_source_imported(&source).map_err(|e| e.to_string())? {
+            report.skipped.push(file.to_string_lossy().to_string());
+            continue;
+        }
+
+        let result = parse(&file).and_then(|note| insert_note(db, &note));
+        finish_item(app, db, &mut report, &source, &file, result);
+    }
+
+    Ok(report)
+}
+
+fn finish_item(
+    app: &AppHandle,
+    db: &Database,
+    report: &mut ImportReport,
+    source: &str,
+    path: &Path,
+    result: Result<String, String>,
+) {
+    match result.and_then(|note_id| {
+        db.record_imported_source(source, &note_id)
+            .map_err(|e| e.to_string())?;
+        Ok(note_id)
+    }) {
+        Ok(note_id) => {
+            let _ = app.emit("note-created", &note_id);
+            report.imported.push(note_id);
+        }
+        Err(error) => {
+            tracing::warn!("Failed to import {}: {}", path.display(), error);
+            report.failed.push(ImportFailure {
+                path: path.to_string_lossy().to_string(),
+                error,
+            });
+        }
+    }
+}
+
+/// Create a finished note with its transcript segments in one transaction
+fn insert_note(db: &Database, note: &ImportedNote) -> Result<String, String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let ended_at = note.started_at
+        + chrono::Duration::milliseconds((note.duration_secs.unwrap_or(0.0) * 1000.0) as i64);
+
+    tx.execute(
+        "INSERT INTO notes (id, title, description, participants, started_at, ended_at, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+        (
+            &id,
+            &note.title,
+            &note.description,
+            &note.participants,
+            note.started_at.to_rfc3339(),
+            ended_at.to_rfc3339(),
+            &now,
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+
+    for segment in &note.segments {
+        tx.execute(
+            "INSERT INTO transcript_segments (note_id, start_time, end_time, text, speaker, source_type, source_id, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'import', NULL, ?6)",
+            (&id, segment.start, segment.end, &segment.text, &segment.speaker, &now),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    if let Some(description) = &note.description {
+        sync_tags_and_links(&tx, &id, description)?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// Undo `insert_note` when attaching the audio fails
+fn remove_note(db: &Database, note_id: &str) -> Result<(), String> {
+    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM notes WHERE id = ?1", [note_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn sync_tags_and_links(conn: &Connection, note_id: &str, description: &str) -> Result<(), String> {
+    sync_note_tags_internal(conn, note_id, description)?;
+    sync_note_links_internal(conn, note_id, description)
+}
+
+/// Files with one of `extensions` at `path` (a file, or a folder to list)
+fn collect_files(
+    path: &Path,
+    extensions: &[&str],
+    recursive: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let matches = |p: &Path| {
+        p.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+    };
+
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+            let p = entry.path();
+            let hidden = p
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if p.is_dir() {
+                if recursive {
+                    dirs.push(p);
+                }
+            } else if matches(&p) {
+                files.push(p);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn modified_at(path: &Path) -> DateTime<Utc> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now())
+}
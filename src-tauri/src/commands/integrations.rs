@@ -0,0 +1,451 @@
+//! Commands for third-party integrations (Slack, email).
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::models::{CalendarEvent, Summary};
+use crate::db::Database;
+use crate::integrations::calendar;
+use crate::integrations::eventkit::{self, CalendarMeeting};
+use crate::integrations::email::{self, SmtpConfig, SmtpSecurity};
+use crate::integrations::publish;
+use crate::integrations::slack::{self, SlackClient};
+use crate::mcp;
+use crate::secrets;
+use crate::settings;
+
+/// Post a note's latest summary and its action items to Slack.
+/// `channel` falls back to the configured default channel.
+#[tauri::command]
+pub async fn post_summary_to_slack(
+    note_id: String,
+    channel: Option<String>,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    post_note_to_slack(&db, &note_id, None, channel).await
+}
+
+/// Auto-post a freshly generated summary when the Slack auto-post setting is on
+pub(crate) fn auto_post_summary(app: &AppHandle, summary: &Summary) {
+    let db = app.state::<Database>();
+    let enabled = db
+        .get_setting(slack::SETTING_AUTO_POST)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        return;
+    }
+
+    let app = app.clone();
+    let summary = summary.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Database>();
+        if let Err(e) = post_note_to_slack(&db, &summary.note_id, Some(summary.clone()), None).await
+        {
+            tracing::warn!("Auto-post failed for note {}: {}", summary.note_id, e);
+        }
+    });
+}
+
+async fn post_note_to_slack(
+    db: &Database,
+    note_id: &str,
+    summary: Option<Summary>,
+    channel: Option<String>,
+) -> Result<(), String> {
+    let client = SlackClient::from_settings(
+        secrets::get_secret(slack::SECRET_WEBHOOK_URL)?,
+        secrets::get_secret(slack::SECRET_BOT_TOKEN)?,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let channel = match channel {
+        Some(channel) => Some(channel),
+        None => db
+            .get_setting(slack::SETTING_DEFAULT_CHANNEL)
+            .map_err(|e| e.to_string())?,
+    };
+
+    let title: String = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row("SELECT title FROM notes WHERE id = ?1", [note_id], |row| {
+            row.get(0)
+        })
+        .map_err(|e| e.to_string())?
+    };
+
+    // Latest summary unless a specific one was given
+    let summary = match summary {
+        Some(summary) => Some(summary),
+        None => db
+            .get_summaries(note_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next(),
+    };
+    let action_items = db.get_action_items(note_id).map_err(|e| e.to_string())?;
+
+    if summary.is_none() && action_items.is_empty() {
+        return Err("Nothing to post. Generate a summary first.".to_string());
+    }
+
+    let text = slack::format_summary_message(&title, summary.as_ref(), &action_items);
+    client
+        .post_message(channel.as_deref(), &text)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// SMTP settings as shown in the UI (the password itself is never returned)
+#[derive(Debug, Serialize)]
+pub struct SmtpSettings {
+    pub host: Option<String>,
+    pub port: u16,
+    pub username: Option<String>,
+    pub from: Option<String>,
+    pub security: String,
+    pub has_password: bool,
+}
+
+/// Get the SMTP settings
+#[tauri::command]
+pub fn get_smtp_settings(db: State<'_, Database>) -> Result<SmtpSettings, String> {
+    let security = db
+        .get_setting(email::SETTING_SECURITY)
+        .map_err(|e| e.to_string())?
+        .map(|s| SmtpSecurity::from_str(&s))
+        .unwrap_or(SmtpSecurity::StartTls);
+
+    Ok(SmtpSettings {
+        host: db.get_setting(email::SETTING_HOST).map_err(|e| e.to_string())?,
+        port: db
+            .get_setting(email::SETTING_PORT)
+            .map_err(|e| e.to_string())?
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(587),
+        username: db.get_setting(email::SETTING_USERNAME).map_err(|e| e.to_string())?,
+        from: db.get_setting(email::SETTING_FROM).map_err(|e| e.to_string())?,
+        security: security.as_str().to_string(),
+        has_password: secrets::get_secret(email::SECRET_PASSWORD)?.is_some(),
+    })
+}
+
+/// Save SMTP settings. The password is stored in the OS keychain; pass `None` to keep
+/// the current password and an empty string to remove it.
+#[tauri::command]
+pub fn set_smtp_settings(
+    host: String,
+    port: u16,
+    username: Option<String>,
+    password: Option<String>,
+    from: String,
+    security: String,
+    app: AppHandle,
+) -> Result<(), String> {
+    let values = [
+        (email::SETTING_SECURITY, security),
+        (email::SETTING_HOST, host),
+        (email::SETTING_PORT, port.to_string()),
+        (email::SETTING_USERNAME, username.unwrap_or_default()),
+        (email::SETTING_FROM, from),
+    ];
+    for (key, value) in values {
+        settings::set(&app, key, &value).map_err(|e| e.to_string())?;
+    }
+
+    match password.as_deref() {
+        Some("") => secrets::delete_secret(email::SECRET_PASSWORD)?,
+        Some(password) => secrets::set_secret(email::SECRET_PASSWORD, password)?,
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Email a note's latest summary and action items. When `recipients` is empty, the
+/// addresses found in the note's participants are used.
+/// Returns the list of recipients the email was sent to.
+#[tauri::command]
+pub async fn email_note_summary(
+    note_id: String,
+    recipients: Option<Vec<String>>,
+    db: State<'_, Database>,
+) -> Result<Vec<String>, String> {
+    let host = db
+        .get_setting(email::SETTING_HOST)
+        .map_err(|e| e.to_string())?
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| email::EmailError::NotConfigured.to_string())?;
+    let from = db
+        .get_setting(email::SETTING_FROM)
+        .map_err(|e| e.to_string())?
+        .filter(|f| !f.is_empty())
+        .ok_or_else(|| email::EmailError::NotConfigured.to_string())?;
+
+    let config = SmtpConfig {
+        host,
+        port: db
+            .get_setting(email::SETTING_PORT)
+            .map_err(|e| e.to_string())?
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(587),
+        username: db
+            .get_setting(email::SETTING_USERNAME)
+            .map_err(|e| e.to_string())?
+            .filter(|u| !u.is_empty()),
+        password: secrets::get_secret(email::SECRET_PASSWORD)?,
+        from,
+        security: db
+            .get_setting(email::SETTING_SECURITY)
+            .map_err(|e| e.to_string())?
+            .map(|s| SmtpSecurity::from_str(&s))
+            .unwrap_or(SmtpSecurity::StartTls),
+    };
+
+    let (title, participants): (String, Option<String>) = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT title, participants FROM notes WHERE id = ?1",
+            [&note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?
+    };
+
+    let recipients = match recipients.filter(|r| !r.is_empty()) {
+        Some(recipients) => recipients,
+        None => participants
+            .as_deref()
+            .map(email::extract_emails)
+            .unwrap_or_default(),
+    };
+    if recipients.is_empty() {
+        return Err("No recipients. Add email addresses to the note's participants.".to_string());
+    }
+
+    let summary = db
+        .get_summaries(&note_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next();
+    let action_items = db.get_action_items(&note_id).map_err(|e| e.to_string())?;
+    if summary.is_none() && action_items.is_empty() {
+        return Err("Nothing to send. Generate a summary first.".to_string());
+    }
+
+    let message =
+        email::build_summary_email(&title, summary.as_ref(), &action_items, recipients.clone());
+
+    tokio::task::spawn_blocking(move || email::send_email(&config, &message))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    Ok(recipients)
+}
+
+/// Result of an `import_calendar` run
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarImportReport {
+    pub created: usize,
+    pub updated: usize,
+    /// Past or cancelled events that were not imported
+    pub skipped: usize,
+    /// Events starting after the import window, left for a later import
+    pub later: usize,
+}
+
+/// Import the events of the next `calendar::IMPORT_WINDOW_DAYS` days from an ICS file path
+/// or feed URL (http, https or webcal), one per occurrence for recurring ones.
+/// Each event gets a note stub with its title, start time and participants; re-importing
+/// the same feed updates the existing stubs. With `auto_record`, new events are scheduled
+/// to record when they start.
+#[tauri::command]
+pub async fn import_calendar(
+    app: AppHandle,
+    source: String,
+    auto_record: Option<bool>,
+    db: State<'_, Database>,
+) -> Result<CalendarImportReport, String> {
+    let source = source.trim();
+    let content = if let Some(rest) = source.strip_prefix("webcal://") {
+        fetch_calendar(&format!("https://{}", rest)).await?
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        fetch_calendar(source).await?
+    } else {
+        std::fs::read_to_string(source).map_err(|e| format!("Failed to read {}: {}", source, e))?
+    };
+
+    let now = chrono::Utc::now();
+    let until = now + chrono::Duration::days(calendar::IMPORT_WINDOW_DAYS);
+    let events = calendar::parse_ics(&content, now, until);
+    if events.is_empty() && !content.contains("BEGIN:VCALENDAR") {
+        return Err("Not a valid ICS calendar".to_string());
+    }
+
+    let mut report = CalendarImportReport {
+        created: 0,
+        updated: 0,
+        skipped: 0,
+        later: 0,
+    };
+
+    for event in events {
+        if event.cancelled || event.end.unwrap_or(event.start) < now {
+            report.skipped += 1;
+            continue;
+        }
+        if event.start > until {
+            report.later += 1;
+            continue;
+        }
+
+        let participants = (!event.attendees.is_empty()).then(|| event.attendees.join(", "));
+        let created = db
+            .upsert_calendar_event(
+                &event.uid,
+                &event.summary,
+                event.start,
+                event.end,
+                event.location.as_deref(),
+                participants.as_deref(),
+                event.description.as_deref(),
+                "ics",
+                auto_record.unwrap_or(false),
+            )
+            .map_err(|e| e.to_string())?;
+
+        if created {
+            report.created += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+
+    if report.created > 0 {
+        let _ = app.emit("note-created", ());
+    }
+
+    Ok(report)
+}
+
+async fn fetch_calendar(url: &str) -> Result<String, String> {
+    let response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Failed to fetch calendar: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch calendar: HTTP {}", response.status()));
+    }
+    response.text().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_upcoming_calendar_events(db: State<'_, Database>) -> Result<Vec<CalendarEvent>, String> {
+    db.list_upcoming_calendar_events().map_err(|e| e.to_string())
+}
+
+/// Enable or disable scheduled recording for an imported calendar event
+#[tauri::command]
+pub fn set_calendar_event_auto_record(
+    id: i64,
+    auto_record: bool,
+    db: State<'_, Database>,
+) -> Result<(), String> {
+    db.set_calendar_event_auto_record(id, auto_record)
+        .map_err(|e| e.to_string())
+}
+
+/// System calendar access status (see `eventkit::authorization_status`)
+#[tauri::command]
+pub fn get_calendar_access_status() -> String {
+    eventkit::authorization_status().to_string()
+}
+
+/// Prompt for system calendar access. Returns whether access was granted.
+#[tauri::command]
+pub async fn request_calendar_access() -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(eventkit::request_access)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Today's meetings from the system calendar
+#[tauri::command]
+pub fn get_todays_meetings() -> Result<Vec<CalendarMeeting>, String> {
+    eventkit::todays_meetings()
+}
+
+/// Create a note prefilled from a system calendar meeting (title, time, participants), such
+/// as one offered by "upcoming-meeting". Returns the note id; calling again for the same
+/// meeting returns the same note.
+#[tauri::command]
+pub fn create_note_from_meeting(
+    app: AppHandle,
+    meeting_id: String,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    let meeting = eventkit::find_meeting(&meeting_id)?
+        .ok_or_else(|| "Meeting not found in the calendar".to_string())?;
+
+    let note_id = eventkit::prefill_note(&db, &meeting).map_err(|e| e.to_string())?;
+    let _ = app.emit("note-created", &note_id);
+    Ok(note_id)
+}
+
+/// MCP server settings, with the config snippet for agent hosts such as Claude Desktop
+#[derive(Debug, Serialize)]
+pub struct McpSettings {
+    pub enabled: bool,
+    /// Command the host should launch
+    pub command: String,
+    pub args: Vec<String>,
+    /// Entry for the host's `mcpServers` config
+    pub config_json: String,
+}
+
+#[tauri::command]
+pub fn get_mcp_settings(db: State<'_, Database>) -> Result<McpSettings, String> {
+    let enabled = db
+        .get_setting(mcp::SETTING_ENABLED)
+        .map_err(|e| e.to_string())?
+        .is_some_and(|v| v == "true");
+    let command = std::env::current_exe()
+        .map_err(|e| e.to_string())?
+        .to_string_lossy()
+        .to_string();
+    let args = vec![mcp::FLAG.to_string()];
+    let config_json = serde_json::to_string_pretty(&serde_json::json!({
+        "mcpServers": { "note67": { "command": command, "args": args } }
+    }))
+    .map_err(|e| e.to_string())?;
+
+    Ok(McpSettings {
+        enabled,
+        command,
+        args,
+        config_json,
+    })
+}
+
+/// Allow or refuse tool calls from the MCP server
+#[tauri::command]
+pub fn set_mcp_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, mcp::SETTING_ENABLED, &enabled.to_string()).map_err(|e| e.to_string())
+}
+
+/// Whether a GitHub token for publishing to gists is stored
+#[tauri::command]
+pub fn has_gist_token() -> Result<bool, String> {
+    Ok(secrets::get_secret(publish::SECRET_GIST_TOKEN)?.is_some())
+}
+
+/// Store the GitHub token used by `publish_note` for gists in the OS keychain; an empty
+/// string removes it
+#[tauri::command]
+pub fn set_gist_token(token: String) -> Result<(), String> {
+    match token.trim() {
+        "" => secrets::delete_secret(publish::SECRET_GIST_TOKEN),
+        token => secrets::set_secret(publish::SECRET_GIST_TOKEN, token),
+    }
+}
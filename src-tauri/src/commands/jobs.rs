@@ -0,0 +1,23 @@
+//! Commands for the background job queue (see `jobs.rs`). Each change to a job is also
+//! emitted as "job-updated".
+
+use tauri::State;
+
+use crate::db::models::Job;
+use crate::jobs::JobManager;
+
+/// Queued and running jobs, then the most recently finished ones
+#[tauri::command]
+pub fn list_jobs(jobs: State<JobManager>) -> Result<Vec<Job>, String> {
+    jobs.list()
+}
+
+/// Cancel a queued or running job. Running jobs stop at their next checkpoint.
+#[tauri::command]
+pub fn cancel_job(id: i64, jobs: State<JobManager>) -> Result<(), String> {
+    if jobs.cancel(id) {
+        Ok(())
+    } else {
+        Err("Job is not queued or running".to_string())
+    }
+}
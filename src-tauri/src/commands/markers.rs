@@ -0,0 +1,77 @@
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio::RecordingPhase;
+use crate::commands::AudioState;
+use crate::db::models::RecordingMarker;
+use crate::db::Database;
+
+/// Labels are cut to this many characters
+const MAX_LABEL_CHARS: usize = 200;
+
+/// Bookmark the current moment of the recording of `note_id`, optionally labelled.
+/// Emits "recording-marker-added".
+#[tauri::command]
+pub fn add_recording_marker(
+    app: AppHandle,
+    note_id: String,
+    label: Option<String>,
+) -> Result<RecordingMarker, String> {
+    add_marker(&app, Some(&note_id), label)
+}
+
+/// Bookmark the current moment of the active recording, which must be of `note_id` when
+/// given. Also used by the "add marker" hotkey.
+pub(crate) fn add_marker(
+    app: &AppHandle,
+    note_id: Option<&str>,
+    label: Option<String>,
+) -> Result<RecordingMarker, String> {
+    let state = app.state::<AudioState>();
+    let recording = &state.recording;
+    if recording.get_phase() != RecordingPhase::Recording {
+        return Err("Not recording".to_string());
+    }
+    let current = recording
+        .current_note_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or_else(|| "Not recording".to_string())?;
+    if note_id.is_some_and(|id| id != current) {
+        return Err("That note is not being recorded".to_string());
+    }
+
+    let label = label
+        .map(|label| {
+            label
+                .trim()
+                .chars()
+                .take(MAX_LABEL_CHARS)
+                .collect::<String>()
+        })
+        .filter(|label| !label.is_empty());
+    let marker = app
+        .state::<Database>()
+        .add_marker(&current, recording.note_position_ms(), label.as_deref())
+        .map_err(|e| e.to_string())?;
+    let _ = app.emit("recording-marker-added", &marker);
+    Ok(marker)
+}
+
+/// A note's markers in audio order
+#[tauri::command]
+pub fn get_recording_markers(
+    db: State<Database>,
+    note_id: String,
+) -> Result<Vec<RecordingMarker>, String> {
+    db.get_markers(&note_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_recording_marker(db: State<Database>, marker_id: i64) -> Result<(), String> {
+    if db.delete_marker(marker_id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err("Marker not found".to_string())
+    }
+}
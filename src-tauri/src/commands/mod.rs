@@ -1,23 +1,51 @@
 pub mod ai;
 pub mod audio;
+pub mod audio_edit;
+pub mod backup;
+pub mod diagnostics;
 pub mod export;
 pub mod graph;
 pub mod images;
+pub mod import;
+pub mod integrations;
+pub mod jobs;
 pub mod links;
+pub mod markers;
 pub mod notes;
+pub mod onboarding;
+pub mod permissions;
+pub mod playback;
+pub mod resources;
 pub mod settings;
+pub mod speakers;
+pub mod storage;
 pub mod tags;
 pub mod transcription;
 pub mod upload;
+pub mod webhooks;
 
 pub use ai::*;
 pub use audio::*;
+pub use audio_edit::*;
+pub use backup::*;
+pub use diagnostics::*;
 pub use export::*;
 pub use graph::*;
 pub use images::*;
+pub use import::*;
+pub use integrations::*;
+pub use jobs::*;
 pub use links::*;
+pub use markers::*;
 pub use notes::*;
+pub use onboarding::*;
+pub use permissions::*;
+pub use playback::*;
+pub use resources::*;
 pub use settings::*;
+pub use speakers::*;
+pub use storage::*;
 pub use tags::*;
 pub use transcription::*;
 pub use upload::*;
+pub use webhooks::*;
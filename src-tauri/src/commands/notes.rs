@@ -1,14 +1,19 @@
 use std::path::PathBuf;
 
 use chrono::Utc;
-use tauri::{AppHandle, Emitter, State};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
 use uuid::Uuid;
 
 use crate::audio::converter::get_audio_duration_ms;
+use crate::commands::export::auto_export_note;
 use crate::commands::links::{sync_note_links_internal, update_incoming_links_internal};
 use crate::commands::tags::sync_note_tags_internal;
 use crate::db::models::{AudioSegment, NewNote, Note, UpdateNote};
 use crate::db::Database;
+use crate::jobs::{Job, JobKind, JobManager, Priority};
+use crate::storage;
+use crate::webhooks;
 
 #[tauri::command]
 pub fn create_note(
@@ -57,6 +62,21 @@ pub fn create_note(
     })
 }
 
+/// Create an empty note titled with the current time, for recordings started outside the
+/// main window (tray, global hotkeys). Returns its id.
+pub(crate) fn create_timestamped_note(app_handle: &AppHandle) -> Result<String, String> {
+    let note = create_note(
+        app_handle.clone(),
+        app_handle.state::<Database>(),
+        NewNote {
+            title: format!("Note {}", chrono::Local::now().format("%Y-%m-%d %H:%M")),
+            description: None,
+            participants: None,
+        },
+    )?;
+    Ok(note.id)
+}
+
 #[tauri::command]
 pub fn get_note(db: State<Database>, id: String) -> Result<Option<Note>, String> {
     let conn = db.conn.lock().map_err(|e| e.to_string())?;
@@ -237,8 +257,8 @@ pub fn search_notes(db: State<Database>, query: String) -> Result<Vec<Note>, Str
             "SELECT m.id, m.title, m.description, m.participants, m.started_at, m.ended_at,
                     m.audio_path, m.created_at, m.updated_at
              FROM notes m
-             JOIN notes_fts fts ON m.rowid = fts.rowid
-             WHERE notes_fts MATCH ?1
+             WHERE m.rowid IN (SELECT rowid FROM notes_fts WHERE notes_fts MATCH ?1)
+                OR m.id IN (SELECT note_id FROM transcript_fts WHERE transcript_fts MATCH ?1)
              ORDER BY m.started_at DESC
              LIMIT 50",
         )
@@ -265,20 +285,100 @@ pub fn search_notes(db: State<Database>, query: String) -> Result<Vec<Note>, Str
     Ok(notes)
 }
 
+/// Segments indexed per transaction by `backfill_transcript_index`
+const TRANSCRIPT_INDEX_BATCH: usize = 500;
+
+/// How much of the transcript text is searchable
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptIndexStatus {
+    pub indexed: u64,
+    pub total: u64,
+}
+
+/// Report how many transcript segments are in the search index
+#[tauri::command]
+pub fn get_transcript_index_status(db: State<Database>) -> Result<TranscriptIndexStatus, String> {
+    let (indexed, total) = db.transcript_index_status().map_err(|e| e.to_string())?;
+    Ok(TranscriptIndexStatus { indexed, total })
+}
+
+/// Add transcript segments written before the search index existed. New segments are
+/// indexed as they're saved; this only needs to run once per database. Runs as an
+/// indexing job in small batches so recording and editing carry on meanwhile, emitting
+/// `transcript-index-progress` after each one; a second call waits for the first and then
+/// finds little left to do.
+#[tauri::command]
+pub async fn backfill_transcript_index(
+    app: AppHandle,
+    jobs: State<'_, JobManager>,
+) -> Result<TranscriptIndexStatus, String> {
+    let job = jobs
+        .enqueue(JobKind::Indexing, Priority::Low, "Index transcripts", None)
+        .await?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = index_transcripts(&app, &job);
+        job.finish(&result);
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+}
+
+fn index_transcripts(app: &AppHandle, job: &Job) -> Result<TranscriptIndexStatus, String> {
+    let db = app.state::<Database>();
+    let (mut indexed, total) = db.transcript_index_status().map_err(|e| e.to_string())?;
+    let mut after_id = 0;
+    while let Some((last_id, added)) = db
+        .index_transcript_segments(after_id, TRANSCRIPT_INDEX_BATCH)
+        .map_err(|e| e.to_string())?
+    {
+        after_id = last_id;
+        indexed = (indexed + added as u64).min(total);
+        if total > 0 {
+            job.progress(indexed as f32 / total as f32);
+        }
+        let _ = app.emit(
+            "transcript-index-progress",
+            TranscriptIndexStatus { indexed, total },
+        );
+        job.check_cancelled()?;
+    }
+    let (indexed, total) = db.transcript_index_status().map_err(|e| e.to_string())?;
+    Ok(TranscriptIndexStatus { indexed, total })
+}
+
 #[tauri::command]
 pub fn end_note(
+    app_handle: AppHandle,
     db: State<Database>,
     id: String,
     audio_path: Option<String>,
 ) -> Result<(), String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
     let now = Utc::now();
+    let title: String = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
 
-    conn.execute(
-        "UPDATE notes SET ended_at = ?1, updated_at = ?2, audio_path = ?3 WHERE id = ?4",
-        (now.to_rfc3339(), now.to_rfc3339(), &audio_path, &id),
-    )
-    .map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE notes SET ended_at = ?1, updated_at = ?2, audio_path = ?3 WHERE id = ?4",
+            (now.to_rfc3339(), now.to_rfc3339(), &audio_path, &id),
+        )
+        .map_err(|e| e.to_string())?;
+
+        conn.query_row("SELECT title FROM notes WHERE id = ?1", [&id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+    };
+
+    auto_export_note(&app_handle, &id);
+    webhooks::dispatch(
+        &app_handle,
+        webhooks::EVENT_NOTE_ENDED,
+        serde_json::json!({
+            "note_id": id,
+            "title": title,
+            "ended_at": now.to_rfc3339(),
+        }),
+    );
 
     Ok(())
 }
@@ -308,10 +408,16 @@ pub fn delete_note(
     if let Some(path) = audio_path {
         if !path.is_empty() {
             if let Err(e) = std::fs::remove_file(&path) {
-                eprintln!("Failed to delete audio file {}: {}", path, e);
+                tracing::warn!("Failed to delete audio file {}: {}", path, e);
             }
         }
     }
+    drop(conn);
+
+    // Recordings, uploads and attachments all live in the note's folder
+    if let Err(e) = storage::delete_note_files(&app_handle, &id) {
+        tracing::warn!("Failed to delete files of note {}: {}", id, e);
+    }
 
     // Emit event for real-time updates
     let _ = app_handle.emit("note-deleted", &id);
@@ -384,14 +490,14 @@ pub fn delete_note_audio_segments(db: State<Database>, note_id: String) -> Resul
         // Delete mic file if present
         if let Some(ref mic_path) = segment.mic_path {
             if let Err(e) = std::fs::remove_file(mic_path) {
-                eprintln!("Failed to delete mic segment file {}: {}", mic_path, e);
+                tracing::warn!("Failed to delete mic segment file {}: {}", mic_path, e);
             }
         }
 
         // Delete system audio file if present
         if let Some(ref sys_path) = segment.system_path {
             if let Err(e) = std::fs::remove_file(sys_path) {
-                eprintln!("Failed to delete system segment file {}: {}", sys_path, e);
+                tracing::warn!("Failed to delete system segment file {}: {}", sys_path, e);
             }
         }
     }
@@ -0,0 +1,260 @@
+//! First-run setup wizard. The current step is persisted so the wizard picks up where it
+//! left off after a restart (granting screen recording on macOS requires one). Steps that
+//! are already satisfied, or don't apply on this platform, are passed over when advancing.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::audio::is_system_audio_available;
+use crate::commands::ai::AiState;
+use crate::commands::audio::{AudioState, has_microphone_permission};
+use crate::commands::transcription::TranscriptionState;
+use crate::db::Database;
+use crate::settings::{self, SETTING_ONBOARDING_DISMISSED};
+use OnboardingStep::*;
+
+pub const SETTING_STEP: &str = "onboarding_step";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    Welcome,
+    Microphone,
+    ScreenRecording,
+    Model,
+    Ollama,
+    Done,
+}
+
+const ORDER: [OnboardingStep; 6] = [Welcome, Microphone, ScreenRecording, Model, Ollama, Done];
+
+/// Setting values, in `ORDER`
+pub const STEP_NAMES: &[&str] = &[
+    "welcome",
+    "microphone",
+    "screen_recording",
+    "model",
+    "ollama",
+    "done",
+];
+
+impl OnboardingStep {
+    fn index(self) -> usize {
+        ORDER.iter().position(|s| *s == self).unwrap_or(0)
+    }
+
+    fn as_str(self) -> &'static str {
+        STEP_NAMES[self.index()]
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        STEP_NAMES.iter().position(|n| *n == s).map(|i| ORDER[i])
+    }
+
+    /// Steps the user may skip: system audio and AI summaries are extras
+    fn optional(self) -> bool {
+        matches!(self, ScreenRecording | Ollama)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OnboardingChecks {
+    pub microphone: bool,
+    /// None when system audio capture isn't available on this platform
+    pub screen_recording: Option<bool>,
+    pub model_downloaded: bool,
+    pub ollama_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingStepStatus {
+    pub step: OnboardingStep,
+    pub done: bool,
+    pub optional: bool,
+    /// False when the step doesn't apply here (e.g. screen recording off macOS)
+    pub applicable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingStatus {
+    pub step: OnboardingStep,
+    pub completed: bool,
+    pub steps: Vec<OnboardingStepStatus>,
+    pub checks: OnboardingChecks,
+}
+
+fn applicable(step: OnboardingStep, checks: &OnboardingChecks) -> bool {
+    step != ScreenRecording || checks.screen_recording.is_some()
+}
+
+/// Whether a step's requirement is met; Welcome and Done have none
+fn satisfied(step: OnboardingStep, checks: &OnboardingChecks) -> bool {
+    match step {
+        Welcome | Done => true,
+        Microphone => checks.microphone,
+        ScreenRecording => checks.screen_recording.unwrap_or(true),
+        Model => checks.model_downloaded,
+        Ollama => checks.ollama_running,
+    }
+}
+
+/// The step after `current`, passing over steps that are satisfied or don't apply.
+/// Fails when `current` isn't satisfied and can't be skipped.
+fn next_step(
+    current: OnboardingStep,
+    checks: &OnboardingChecks,
+    skip: bool,
+) -> Result<OnboardingStep, String> {
+    let skippable = skip && current.optional();
+    if !satisfied(current, checks) && !skippable {
+        return Err(format!("The {} step isn't done yet", current.as_str()));
+    }
+    Ok(ORDER[current.index() + 1..]
+        .iter()
+        .copied()
+        .find(|s| *s == Done || (applicable(*s, checks) && !satisfied(*s, checks)))
+        .unwrap_or(Done))
+}
+
+async fn checks(app: &AppHandle) -> OnboardingChecks {
+    let screen_recording = is_system_audio_available().then(|| {
+        let state = app.state::<AudioState>();
+        let capture = state.system_capture();
+        capture
+            .ok()
+            .and_then(|c| c.as_ref().and_then(|c| c.has_permission().ok()))
+            .unwrap_or(false)
+    });
+    let model_downloaded = app
+        .state::<TranscriptionState>()
+        .model_manager
+        .lock()
+        .ok()
+        .and_then(|m| {
+            m.as_ref()
+                .map(|m| m.list_models().iter().any(|i| i.downloaded))
+        })
+        .unwrap_or(false);
+    let ollama_running = app.state::<AiState>().client.is_running().await;
+
+    OnboardingChecks {
+        microphone: has_microphone_permission(),
+        screen_recording,
+        model_downloaded,
+        ollama_running,
+    }
+}
+
+fn current_step(db: &Database) -> OnboardingStep {
+    let completed = db
+        .get_setting(SETTING_ONBOARDING_DISMISSED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if completed {
+        return Done;
+    }
+    db.get_setting(SETTING_STEP)
+        .ok()
+        .flatten()
+        .and_then(|s| OnboardingStep::from_str(&s))
+        .unwrap_or(Welcome)
+}
+
+fn status(step: OnboardingStep, checks: OnboardingChecks) -> OnboardingStatus {
+    OnboardingStatus {
+        step,
+        completed: step == Done,
+        steps: ORDER
+            .iter()
+            .filter(|s| !matches!(s, Welcome | Done))
+            .map(|&s| OnboardingStepStatus {
+                step: s,
+                done: satisfied(s, &checks),
+                optional: s.optional(),
+                applicable: applicable(s, &checks),
+            })
+            .collect(),
+        checks,
+    }
+}
+
+fn save_step(app: &AppHandle, step: OnboardingStep) -> Result<(), String> {
+    settings::set(app, SETTING_STEP, step.as_str()).map_err(|e| e.to_string())?;
+    settings::set(
+        app,
+        SETTING_ONBOARDING_DISMISSED,
+        if step == Done { "true" } else { "false" },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Where the wizard is and what's already in place
+#[tauri::command]
+pub async fn get_onboarding_status(app: AppHandle) -> Result<OnboardingStatus, String> {
+    let step = current_step(&app.state::<Database>());
+    Ok(status(step, checks(&app).await))
+}
+
+/// Move past the current step. `skip` passes an optional step that isn't done.
+#[tauri::command]
+pub async fn advance_onboarding(
+    app: AppHandle,
+    skip: Option<bool>,
+) -> Result<OnboardingStatus, String> {
+    let checks = checks(&app).await;
+    let current = current_step(&app.state::<Database>());
+    let next = next_step(current, &checks, skip.unwrap_or(false))?;
+    save_step(&app, next)?;
+    Ok(status(next, checks))
+}
+
+/// Finish (or dismiss) the wizard from any step
+#[tauri::command]
+pub fn complete_onboarding(app: AppHandle) -> Result<(), String> {
+    save_step(&app, Done)
+}
+
+/// Start the wizard over, e.g. from settings
+#[tauri::command]
+pub fn reset_onboarding(app: AppHandle) -> Result<(), String> {
+    save_step(&app, Welcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(microphone: bool, screen: Option<bool>, model: bool) -> OnboardingChecks {
+        OnboardingChecks {
+            microphone,
+            screen_recording: screen,
+            model_downloaded: model,
+            ollama_running: false,
+        }
+    }
+
+    #[test]
+    fn test_next_step() {
+        let fresh = checks(false, Some(false), false);
+        assert_eq!(next_step(Welcome, &fresh, false), Ok(Microphone));
+        assert!(next_step(Microphone, &fresh, false).is_err());
+        // Required steps can't be skipped, optional ones can
+        assert!(next_step(Microphone, &fresh, true).is_err());
+        assert_eq!(next_step(ScreenRecording, &fresh, true), Ok(Model));
+
+        // Granted permissions and non-applicable steps are passed over
+        let ready = checks(true, None, false);
+        assert_eq!(next_step(Welcome, &ready, false), Ok(Model));
+        let all = checks(true, Some(true), true);
+        assert_eq!(next_step(Welcome, &all, false), Ok(Ollama));
+        assert_eq!(next_step(Ollama, &all, true), Ok(Done));
+    }
+
+    #[test]
+    fn test_step_names() {
+        for step in ORDER {
+            assert_eq!(OnboardingStep::from_str(step.as_str()), Some(step));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Everything the app needs from the OS in one report, for the permissions screen:
+//! microphone, system audio (screen recording on macOS), notifications and launch at login,
+//! each with the OS settings pane that changes it. Where the OS has no way to ask (macOS
+//! notifications sent through osascript), the state is "unknown" rather than a guess.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+use crate::audio::is_system_audio_available;
+use crate::commands::audio::{AudioState, has_microphone_available};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Microphone,
+    SystemAudio,
+    Notifications,
+    Autostart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    /// For autostart: the app launches at login
+    Granted,
+    Denied,
+    /// The user hasn't been asked yet
+    NotDetermined,
+    /// Blocked by policy (parental controls, MDM)
+    Restricted,
+    /// Doesn't apply on this platform
+    Unsupported,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionStatus {
+    pub state: PermissionState,
+    /// Opened by `open_permission_settings`; None when the OS has no such pane
+    pub settings_url: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionsReport {
+    pub microphone: PermissionStatus,
+    pub system_audio: PermissionStatus,
+    pub notifications: PermissionStatus,
+    pub autostart: PermissionStatus,
+}
+
+/// Map AVAuthorizationStatus (0 not determined, 1 restricted, 2 denied, 3 authorized)
+#[allow(dead_code)] // Used on macOS
+fn from_av_status(status: i64) -> PermissionState {
+    match status {
+        0 => PermissionState::NotDetermined,
+        1 => PermissionState::Restricted,
+        2 => PermissionState::Denied,
+        3 => PermissionState::Granted,
+        _ => PermissionState::Unknown,
+    }
+}
+
+fn microphone() -> PermissionState {
+    if !has_microphone_available() {
+        return PermissionState::Unsupported;
+    }
+    platform::microphone()
+}
+
+fn system_audio(app: &AppHandle) -> PermissionState {
+    if !is_system_audio_available() {
+        return PermissionState::Unsupported;
+    }
+    let state = app.state::<AudioState>();
+    let capture = state.system_capture();
+    match capture
+        .ok()
+        .and_then(|c| c.as_ref().map(|c| c.has_permission()))
+    {
+        Some(Ok(true)) => PermissionState::Granted,
+        Some(Ok(false)) => PermissionState::Denied,
+        _ => PermissionState::Unknown,
+    }
+}
+
+fn autostart(app: &AppHandle) -> PermissionState {
+    match app.autolaunch().is_enabled() {
+        Ok(true) => PermissionState::Granted,
+        Ok(false) => PermissionState::Denied,
+        Err(_) => PermissionState::Unknown,
+    }
+}
+
+fn status(state: PermissionState, permission: Permission) -> PermissionStatus {
+    PermissionStatus {
+        state,
+        settings_url: platform::settings_url(permission),
+    }
+}
+
+/// The state of every permission the app uses
+#[tauri::command]
+pub fn get_permissions_status(app: AppHandle) -> PermissionsReport {
+    PermissionsReport {
+        microphone: status(microphone(), Permission::Microphone),
+        system_audio: status(system_audio(&app), Permission::SystemAudio),
+        notifications: status(platform::notifications(), Permission::Notifications),
+        autostart: status(autostart(&app), Permission::Autostart),
+    }
+}
+
+/// Open the OS settings pane for one permission
+#[tauri::command]
+pub fn open_permission_settings(permission: Permission) -> Result<(), String> {
+    let url = platform::settings_url(permission)
+        .ok_or("There is no settings pane for this on this platform")?;
+    platform::open_url(url).map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Permission, PermissionState, from_av_status};
+    use crate::commands::audio::get_microphone_auth_status;
+
+    pub fn microphone() -> PermissionState {
+        from_av_status(get_microphone_auth_status())
+    }
+
+    /// Notifications go through osascript, which has no way to read its authorization
+    pub fn notifications() -> PermissionState {
+        PermissionState::Unknown
+    }
+
+    pub fn settings_url(permission: Permission) -> Option<&'static str> {
+        Some(match permission {
+            Permission::Microphone => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone"
+            }
+            Permission::SystemAudio => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture"
+            }
+            Permission::Notifications => {
+                "x-apple.systempreferences:com.apple.preference.notifications"
+            }
+            Permission::Autostart => {
+                "x-apple.systempreferences:com.apple.LoginItems-Settings.extension"
+            }
+        })
+    }
+
+    pub fn open_url(url: &str) -> std::io::Result<()> {
+        std::process::Command::new("open")
+            .arg(url)
+            .spawn()
+            .map(|_| ())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::process::Command;
+
+    use super::{Permission, PermissionState};
+    use crate::process::no_window;
+
+    /// A value under HKCU, as printed by `reg query` ("    Name    REG_SZ    Value")
+    fn registry_value(key: &str, name: &str) -> Option<String> {
+        let output = no_window(&mut Command::new("reg"))
+            .args(["query", &format!("HKCU\\{}", key), "/v", name])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find(|line| line.trim_start().starts_with(name))
+            .and_then(|line| line.split_whitespace().last())
+            .map(str::to_string)
+    }
+
+    /// The microphone privacy switch ("Let apps access your microphone")
+    pub fn microphone() -> PermissionState {
+        let consent = registry_value(
+            concat!(
+                "Software\\Microsoft\\Windows\\CurrentVersion\\",
+                "CapabilityAccessManager\\ConsentStore\\microphone"
+            ),
+            "Value",
+        );
+        match consent.as_deref() {
+            Some("Deny") => PermissionState::Denied,
+            _ => PermissionState::Granted,
+        }
+    }
+
+    /// Toasts are on unless turned off system-wide
+    pub fn notifications() -> PermissionState {
+        let enabled = registry_value(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\PushNotifications",
+            "ToastEnabled",
+        );
+        match enabled.as_deref() {
+            Some("0x0") => PermissionState::Denied,
+            _ => PermissionState::Granted,
+        }
+    }
+
+    pub fn settings_url(permission: Permission) -> Option<&'static str> {
+        match permission {
+            Permission::Microphone => Some("ms-settings:privacy-microphone"),
+            // Loopback capture needs no permission
+            Permission::SystemAudio => None,
+            Permission::Notifications => Some("ms-settings:notifications"),
+            Permission::Autostart => Some("ms-settings:startupapps"),
+        }
+    }
+
+    pub fn open_url(url: &str) -> std::io::Result<()> {
+        no_window(&mut Command::new("cmd"))
+            .args(["/C", "start", url])
+            .spawn()
+            .map(|_| ())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::{Permission, PermissionState};
+
+    /// No permission prompts for audio input on Linux desktops
+    pub fn microphone() -> PermissionState {
+        PermissionState::Granted
+    }
+
+    /// Notifications need notify-send
+    pub fn notifications() -> PermissionState {
+        let available = std::process::Command::new("notify-send")
+            .arg("--version")
+            .output()
+            .is_ok_and(|out| out.status.success());
+        if available {
+            PermissionState::Granted
+        } else {
+            PermissionState::Unsupported
+        }
+    }
+
+    pub fn settings_url(_permission: Permission) -> Option<&'static str> {
+        None
+    }
+
+    pub fn open_url(_url: &str) -> std::io::Result<()> {
+        Err(std::io::Error::other("Not available on this platform"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_av_status() {
+        assert_eq!(from_av_status(0), PermissionState::NotDetermined);
+        assert_eq!(from_av_status(2), PermissionState::Denied);
+        assert_eq!(from_av_status(3), PermissionState::Granted);
+        assert_eq!(from_av_status(7), PermissionState::Unknown);
+    }
+
+    #[test]
+    fn test_permission_names() {
+        let permission: Permission = serde_json::from_str("\"system_audio\"").unwrap();
+        assert_eq!(permission, Permission::SystemAudio);
+        assert_eq!(
+            serde_json::to_string(&PermissionState::NotDetermined).unwrap(),
+            "\"not_determined\""
+        );
+    }
+}
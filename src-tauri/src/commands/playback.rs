@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, State};
+
+use crate::audio::playback::{self, PlaybackStatus};
+use crate::audio::silence::{self, SilenceMap, SilenceSource};
+use crate::db::Database;
+
+/// Play an audio file natively, from `start_ms` if given. With `end_ms`, playback stops
+/// there, for playing one transcript segment. Progress arrives as "playback-position".
+#[tauri::command]
+pub async fn play_audio(
+    app: AppHandle,
+    audio_path: String,
+    start_ms: Option<u64>,
+    end_ms: Option<u64>,
+) -> Result<(), String> {
+    let path = PathBuf::from(&audio_path);
+    if !path.exists() {
+        return Err(format!("Audio file not found: {}", audio_path));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        playback::play(&app, &path, start_ms.unwrap_or(0), end_ms)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn pause_audio() {
+    playback::pause();
+}
+
+/// Carry on from where playback was paused, or from the top once it has played out
+#[tauri::command]
+pub fn resume_audio() {
+    playback::resume();
+}
+
+#[tauri::command]
+pub fn stop_audio() {
+    playback::stop();
+}
+
+#[tauri::command]
+pub fn seek_audio(position_ms: u64) {
+    playback::seek(position_ms);
+}
+
+/// Set the playback speed, 0.5 to 3.0; the pitch is kept
+#[tauri::command]
+pub fn set_playback_speed(speed: f32) -> Result<(), String> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err("Speed must be a positive number".to_string());
+    }
+    playback::set_speed(speed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_playback_status() -> PlaybackStatus {
+    playback::status()
+}
+
+/// Quiet stretches of a note's audio, in note time, for playback to skip. Uses the mixed
+/// playback file when there is one, else the recorded segments' mic and system files.
+#[tauri::command]
+pub async fn get_silence_map(
+    note_id: String,
+    db: State<'_, Database>,
+) -> Result<SilenceMap, String> {
+    let audio_path: Option<String> = {
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT audio_path FROM notes WHERE id = ?1",
+            [&note_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?
+    };
+
+    let sources = match audio_path.map(PathBuf::from).filter(|p| p.exists()) {
+        Some(path) => vec![SilenceSource {
+            offset_ms: 0,
+            paths: vec![path],
+        }],
+        None => db
+            .get_audio_segments(&note_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|segment| SilenceSource {
+                offset_ms: segment.start_offset_ms.max(0) as u64,
+                paths: [segment.mic_path, segment.system_path]
+                    .into_iter()
+                    .flatten()
+                    .map(PathBuf::from)
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    tauri::async_runtime::spawn_blocking(move || silence::silence_map(&sources))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,174 @@
+//! Where the memory and disk space go, for the storage screen and support requests. Each
+//! figure points at the command that gives it back:
+//!   the loaded model: `unload_model`
+//!   downloaded models: `delete_model`
+//!   recordings: `delete_note_audio_segments`, per note (`delete_note` removes all of a
+//!     note's files)
+//!   free pages in the database: `compact_database`
+//! Audio buffers are drained on every live transcription pass and need no cleanup.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio::system_audio_buffer_bytes;
+use crate::commands::audio::AudioState;
+use crate::commands::transcription::TranscriptionState;
+use crate::db::Database;
+use crate::storage;
+use crate::transcription::ModelSize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedModelUsage {
+    pub size: ModelSize,
+    pub bytes: u64,
+    /// Transcriptions holding the model right now
+    pub consumers: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FolderUsage {
+    pub bytes: u64,
+    pub files: u64,
+    /// Left behind by interrupted uploads; removed at the next launch
+    pub temp_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsage {
+    pub model: Option<LoadedModelUsage>,
+    pub microphone_buffer_bytes: u64,
+    pub system_audio_buffer_bytes: u64,
+    /// Every note's folder: recordings, uploads and attachments
+    pub recordings: FolderUsage,
+    pub downloaded_models_bytes: u64,
+    pub database_bytes: u64,
+    /// Free pages that `compact_database` would give back
+    pub database_reclaimable_bytes: u64,
+}
+
+/// Add up files by (path, length), counting .tmp leftovers separately
+fn tally<'a>(files: impl IntoIterator<Item = (&'a Path, u64)>) -> FolderUsage {
+    let mut usage = FolderUsage::default();
+    for (path, len) in files {
+        if path.extension().is_some_and(|e| e == "tmp") {
+            usage.temp_bytes += len;
+        } else {
+            usage.bytes += len;
+            usage.files += 1;
+        }
+    }
+    usage
+}
+
+fn folder_usage(dir: &Path) -> FolderUsage {
+    let files: Vec<(std::path::PathBuf, u64)> = storage::walk_files(dir)
+        .into_iter()
+        .filter_map(|path| {
+            let len = fs::metadata(&path).ok()?.len();
+            Some((path, len))
+        })
+        .collect();
+    tally(files.iter().map(|(path, len)| (path.as_path(), *len)))
+}
+
+fn model_usage(state: &TranscriptionState) -> Option<LoadedModelUsage> {
+    let size = state.model.current()?;
+    let bytes = state
+        .model_manager
+        .lock()
+        .ok()?
+        .as_ref()
+        .and_then(|manager| fs::metadata(manager.model_path(size)).ok())
+        .map(|m| m.len())
+        .unwrap_or(size.size_mb() * 1024 * 1024);
+    Some(LoadedModelUsage {
+        size,
+        bytes,
+        consumers: state.model.consumers(),
+    })
+}
+
+fn downloaded_models_bytes(state: &TranscriptionState) -> u64 {
+    let Ok(manager) = state.model_manager.lock() else {
+        return 0;
+    };
+    manager.as_ref().map_or(0, |manager| {
+        manager
+            .list_models()
+            .iter()
+            .filter(|m| m.downloaded)
+            .filter_map(|m| fs::metadata(manager.model_path(m.size)).ok())
+            .map(|m| m.len())
+            .sum()
+    })
+}
+
+/// Memory and disk used by the model, audio buffers, recordings and database
+#[tauri::command]
+pub fn get_resource_usage(
+    app: AppHandle,
+    db: State<'_, Database>,
+    audio: State<'_, AudioState>,
+    transcription: State<'_, TranscriptionState>,
+) -> Result<ResourceUsage, String> {
+    let data_root = storage::data_root(&app)?;
+    let (database_bytes, database_reclaimable_bytes) =
+        db.storage_stats().map_err(|e| e.to_string())?;
+    let microphone_buffer_bytes = audio.recording.audio_buffer.bytes();
+
+    Ok(ResourceUsage {
+        model: model_usage(&transcription),
+        microphone_buffer_bytes: microphone_buffer_bytes as u64,
+        system_audio_buffer_bytes: system_audio_buffer_bytes() as u64,
+        recordings: folder_usage(&data_root),
+        downloaded_models_bytes: downloaded_models_bytes(&transcription),
+        database_bytes,
+        database_reclaimable_bytes,
+    })
+}
+
+/// Give the database's free pages back to the disk. Refused while recording, since it
+/// holds the database for a while.
+#[tauri::command]
+pub async fn compact_database(app: AppHandle) -> Result<(), String> {
+    if app
+        .state::<AudioState>()
+        .recording
+        .is_recording
+        .load(Ordering::SeqCst)
+    {
+        return Err("Can't compact the database while recording".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<Database>().compact().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally() {
+        let usage = tally([
+            (Path::new("a_mic.wav"), 1000),
+            (Path::new("a_system.wav"), 500),
+            (Path::new("upload.wav.tmp"), 300),
+        ]);
+        assert_eq!(
+            usage,
+            FolderUsage {
+                bytes: 1500,
+                files: 2,
+                temp_bytes: 300,
+            }
+        );
+        assert_eq!(tally([]), FolderUsage::default());
+    }
+}
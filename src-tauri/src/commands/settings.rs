@@ -1,7 +1,14 @@
-use tauri::{AppHandle, State};
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Emitter, Manager, State, Theme, WindowEvent};
 use tauri_plugin_autostart::ManagerExt;
 
 use crate::db::Database;
+use crate::meeting_detection::{self, MeetingDetectionState};
+use crate::shortcuts::{self, ShortcutScope};
+use crate::settings::{
+    self, ConflictPolicy, SettingDef, SettingsConflict, SettingsImportReport, SETTING_THEME,
+};
 
 /// Open the macOS Screen Recording privacy settings
 #[cfg(target_os = "macos")]
@@ -51,19 +58,36 @@ pub fn open_microphone_settings() -> Result<(), String> {
 /// Get the theme preference from settings
 #[tauri::command]
 pub fn get_theme_preference(db: State<'_, Database>) -> Result<String, String> {
-    db.get_setting("theme")
+    db.get_setting(SETTING_THEME)
         .map_err(|e| e.to_string())
         .map(|opt| opt.unwrap_or_else(|| "system".to_string()))
 }
 
 /// Set the theme preference in settings
 #[tauri::command]
-pub fn set_theme_preference(theme: String, db: State<'_, Database>) -> Result<(), String> {
-    // Validate theme value
-    if !["light", "dark", "system"].contains(&theme.as_str()) {
-        return Err(format!("Invalid theme value: {}", theme));
-    }
-    db.set_setting("theme", &theme).map_err(|e| e.to_string())
+pub fn set_theme_preference(app: AppHandle, theme: String) -> Result<(), String> {
+    settings::set(&app, SETTING_THEME, &theme).map_err(|e| e.to_string())
+}
+
+/// The OS appearance ("light" or "dark"), which the "system" theme preference follows
+#[tauri::command]
+pub fn get_system_theme(app: AppHandle) -> Result<Theme, String> {
+    let window = app.get_webview_window("main").ok_or("Main window not found")?;
+    window.theme().map_err(|e| e.to_string())
+}
+
+/// Tell every window when the OS appearance changes ("system-theme-changed" with "light" or
+/// "dark"). The OS reports it per window, so only the main window's reports are forwarded.
+pub fn watch_system_theme(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::ThemeChanged(theme) = event {
+            let _ = handle.emit("system-theme-changed", theme);
+        }
+    });
 }
 
 /// Get a setting value by key
@@ -72,10 +96,11 @@ pub fn get_setting(key: String, db: State<'_, Database>) -> Result<Option<String
     db.get_setting(&key).map_err(|e| e.to_string())
 }
 
-/// Set a setting value by key
+/// Set a setting value by key. Only keys in the settings registry are accepted, and the
+/// value must match the key's type.
 #[tauri::command]
-pub fn set_setting(key: String, value: String, db: State<'_, Database>) -> Result<(), String> {
-    db.set_setting(&key, &value).map_err(|e| e.to_string())
+pub fn set_setting(app: AppHandle, key: String, value: String) -> Result<(), String> {
+    settings::set(&app, &key, &value).map_err(|e| e.to_string())
 }
 
 /// Get multiple settings at once
@@ -89,6 +114,80 @@ pub fn get_settings(keys: Vec<String>, db: State<'_, Database>) -> Result<std::c
     Ok(result)
 }
 
+/// Every known (non-sensitive) setting with its typed value or default
+#[tauri::command]
+pub fn get_all_settings(
+    db: State<'_, Database>,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    settings::snapshot(&db).map_err(|e| e.to_string())
+}
+
+/// The settings registry: each key with its type and default
+#[tauri::command]
+pub fn get_settings_schema() -> Vec<SettingDef> {
+    settings::REGISTRY.to_vec()
+}
+
+/// Write the portable settings, shortcuts and export templates to a JSON file at `path`.
+/// Credentials, commands and destinations are left out (see `settings::IMPORTABLE`).
+#[tauri::command]
+pub fn export_settings(path: String, db: State<'_, Database>) -> Result<(), String> {
+    let bundle = settings::export_bundle(&db).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// What importing the settings file at `path` would change, so the UI can ask before
+/// overwriting anything
+#[tauri::command]
+pub fn preview_settings_import(
+    path: String,
+    db: State<'_, Database>,
+) -> Result<Vec<SettingsConflict>, String> {
+    let bundle = read_bundle(&path)?;
+    settings::find_conflicts(&db, &bundle).map_err(|e| e.to_string())
+}
+
+/// Import a file written by `export_settings`. Values that differ from this machine's are
+/// overwritten or kept according to `on_conflict`.
+#[tauri::command]
+pub fn import_settings(
+    app: AppHandle,
+    path: String,
+    on_conflict: ConflictPolicy,
+) -> Result<SettingsImportReport, String> {
+    let bundle = read_bundle(&path)?;
+    let report = settings::import_bundle(&app, &bundle, on_conflict).map_err(|e| e.to_string())?;
+
+    // Settings that are cached at runtime
+    let mut scopes: Vec<ShortcutScope> = report
+        .applied
+        .iter()
+        .filter_map(|id| shortcuts::find(id).map(|def| def.scope))
+        .collect();
+    scopes.dedup();
+    for scope in scopes {
+        shortcuts::reload(&app, scope);
+    }
+    if report
+        .applied
+        .iter()
+        .any(|key| key == meeting_detection::SETTING_AUTO_START)
+    {
+        let enabled = bundle.settings.get(meeting_detection::SETTING_AUTO_START);
+        app.state::<std::sync::Arc<MeetingDetectionState>>()
+            .set_auto_start(enabled.is_some_and(|v| v == "true"));
+    }
+
+    Ok(report)
+}
+
+fn read_bundle(path: &str) -> Result<settings::SettingsBundle, String> {
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    settings::parse_bundle(&json).map_err(|e| e.to_string())
+}
+
 /// Get the autostart status
 #[tauri::command]
 pub fn get_autostart_enabled(app: AppHandle) -> Result<bool, String> {
@@ -0,0 +1,51 @@
+//! Commands for naming the voices diarization tells apart (see `transcription::voices`).
+
+use tauri::State;
+
+use crate::db::models::{Speaker, SpeakerEmbedding};
+use crate::db::Database;
+
+/// Names are cut to this many characters
+const MAX_NAME_CHARS: usize = 100;
+
+/// The voices heard in a note's transcripts, for naming
+#[tauri::command]
+pub fn get_note_speakers(
+    db: State<Database>,
+    note_id: String,
+) -> Result<Vec<SpeakerEmbedding>, String> {
+    db.get_speaker_embeddings(&note_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Name a voice of a note's transcript. Its segments take the name, and later transcripts
+/// give it to the voices that sound like it.
+#[tauri::command]
+pub fn label_speaker(
+    db: State<Database>,
+    embedding_id: i64,
+    name: String,
+) -> Result<Speaker, String> {
+    let name: String = name.trim().chars().take(MAX_NAME_CHARS).collect();
+    if name.is_empty() {
+        return Err("Speaker name is empty".to_string());
+    }
+    db.label_speaker(embedding_id, &name)
+        .map_err(|e| e.to_string())
+}
+
+/// All named voices
+#[tauri::command]
+pub fn list_speakers(db: State<Database>) -> Result<Vec<Speaker>, String> {
+    db.list_speakers().map_err(|e| e.to_string())
+}
+
+/// Forget a named voice; transcripts keep the name where it was given
+#[tauri::command]
+pub fn delete_speaker(db: State<Database>, speaker_id: i64) -> Result<(), String> {
+    if db.delete_speaker(speaker_id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err("Speaker not found".to_string())
+    }
+}
@@ -0,0 +1,85 @@
+//! Commands for choosing where notes' recordings, uploads and attachments are kept.
+//! Changing the location is a two-step flow: `check_storage_location` sizes up the move
+//! for the confirmation screen, then `set_storage_location` performs it, emitting
+//! "storage-move-progress" as files are copied and "storage-moved" when done.
+//! `get_storage_usage` and `purge_note_audio` show and free the space notes take.
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::notifications::available_space;
+use crate::storage::{self, NoteStorageUsage, StorageMovePlan, StorageMoveReport};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageLocation {
+    pub path: String,
+    /// Still the folder inside the app data directory
+    pub is_default: bool,
+    pub free_bytes: Option<u64>,
+}
+
+/// Where note files are kept now
+#[tauri::command]
+pub fn get_storage_location(app: AppHandle) -> Result<StorageLocation, String> {
+    let root = storage::data_root(&app)?;
+    Ok(StorageLocation {
+        path: root.to_string_lossy().to_string(),
+        is_default: !storage::is_custom_location(&app),
+        free_bytes: available_space(&root),
+    })
+}
+
+/// What moving note files to `path` (the default location when omitted) would take,
+/// or why it can't be done. Changes nothing.
+#[tauri::command]
+pub fn check_storage_location(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<StorageMovePlan, String> {
+    storage::plan_move(&app, path.as_deref())
+}
+
+/// Move note files to `path` (the default location when omitted) and keep them there
+#[tauri::command]
+pub async fn set_storage_location(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<StorageMoveReport, String> {
+    tauri::async_runtime::spawn_blocking(move || storage::move_storage(&app, path.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageUsage {
+    pub location: String,
+    pub total_bytes: u64,
+    pub free_bytes: Option<u64>,
+    /// Notes with files, largest first
+    pub notes: Vec<NoteStorageUsage>,
+}
+
+/// Disk space taken by note files, in total and per note
+#[tauri::command]
+pub async fn get_storage_usage(app: AppHandle) -> Result<StorageUsage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let root = storage::data_root(&app)?;
+        let notes = storage::note_usage(&app)?;
+        Ok(StorageUsage {
+            location: root.to_string_lossy().to_string(),
+            total_bytes: notes.iter().map(|n| n.total_bytes).sum(),
+            free_bytes: available_space(&root),
+            notes,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Delete a note's recordings and uploads, keeping its transcript. Returns the bytes freed.
+#[tauri::command]
+pub async fn purge_note_audio(app: AppHandle, note_id: String) -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(move || storage::purge_note_audio(&app, &note_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
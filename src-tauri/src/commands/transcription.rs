@@ -1,14 +1,21 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, Manager, State};
-use whisper_rs::{WhisperContext, WhisperContextParameters};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
 
+use crate::audio::compression;
 use crate::commands::audio::AudioState;
+use crate::commands::export::auto_export_note;
+use crate::db::models::AudioSegment;
 use crate::db::Database;
+use crate::jobs::{Job, JobKind, JobManager, Priority};
+use crate::note_windows;
+use crate::webhooks;
 use crate::transcription::{
-    is_echo_of_system, live, should_skip_segment, LiveTranscriptionState, ModelInfo, ModelManager,
-    ModelSize, TranscriptionResult, Transcriber,
+    is_echo_of_system, language, live, should_skip_segment, voices, LiveTranscriptionState,
+    ModelHost, ModelInfo, ModelManager, ModelSize, TranscriptionResult,
 };
 
 /// Clamp a segment's (start, end) so `start` never goes backwards relative to
@@ -27,10 +34,8 @@ fn clamp_monotonic(start: f64, end: f64, last_start: &mut f64) -> (f64, f64) {
 /// State for transcription operations
 pub struct TranscriptionState {
     pub model_manager: Mutex<Option<ModelManager>>,
-    pub transcriber: Mutex<Option<Arc<Transcriber>>>,
-    pub whisper_ctx: Mutex<Option<Arc<WhisperContext>>>,
-    pub current_model: Mutex<Option<ModelSize>>,
-    pub is_transcribing: AtomicBool,
+    /// The loaded model, shared by file and live transcription
+    pub model: ModelHost,
     pub download_progress: Arc<AtomicU8>,
     pub is_downloading: AtomicBool,
     pub live_state: Arc<LiveTranscriptionState>,
@@ -40,10 +45,7 @@ impl Default for TranscriptionState {
     fn default() -> Self {
         Self {
             model_manager: Mutex::new(None),
-            transcriber: Mutex::new(None),
-            whisper_ctx: Mutex::new(None),
-            current_model: Mutex::new(None),
-            is_transcribing: AtomicBool::new(false),
+            model: ModelHost::default(),
             download_progress: Arc::new(AtomicU8::new(0)),
             is_downloading: AtomicBool::new(false),
             live_state: Arc::new(LiveTranscriptionState::new()),
@@ -58,10 +60,7 @@ pub fn init_transcription_state(app: &AppHandle) -> TranscriptionState {
 
     TranscriptionState {
         model_manager: Mutex::new(Some(model_manager)),
-        transcriber: Mutex::new(None),
-        whisper_ctx: Mutex::new(None),
-        current_model: Mutex::new(None),
-        is_transcribing: AtomicBool::new(false),
+        model: ModelHost::default(),
         download_progress: Arc::new(AtomicU8::new(0)),
         is_downloading: AtomicBool::new(false),
         live_state: Arc::new(LiveTranscriptionState::new()),
@@ -139,19 +138,8 @@ pub async fn delete_model(
 ) -> Result<(), String> {
     let model_size = parse_model_size(&size)?;
 
-    // Check if this model is currently loaded
-    {
-        let current = state.current_model.lock().map_err(|e| e.to_string())?;
-        if current.as_ref() == Some(&model_size) {
-            // Unload the transcriber
-            let mut transcriber = state.transcriber.lock().map_err(|e| e.to_string())?;
-            *transcriber = None;
-            drop(transcriber);
-
-            let mut current = state.current_model.lock().map_err(|e| e.to_string())?;
-            *current = None;
-        }
-    }
+    // Unload it if it is the loaded model
+    state.model.unload_if(model_size);
 
     let manager = {
         let guard = state.model_manager.lock().map_err(|e| e.to_string())?;
@@ -167,11 +155,8 @@ pub fn load_model(size: String, state: State<TranscriptionState>) -> Result<(),
     let model_size = parse_model_size(&size)?;
 
     // Check if already loaded
-    {
-        let current = state.current_model.lock().map_err(|e| e.to_string())?;
-        if current.as_ref() == Some(&model_size) {
-            return Ok(()); // Already loaded
-        }
+    if state.model.current() == Some(model_size) {
+        return Ok(());
     }
 
     // Get model path
@@ -185,99 +170,123 @@ pub fn load_model(size: String, state: State<TranscriptionState>) -> Result<(),
         return Err(format!("Model {} is not downloaded", size));
     }
 
-    // Load the model
-    let transcriber = Transcriber::new(&model_path).map_err(|e| e.to_string())?;
-
-    // Also load WhisperContext for live transcription
-    let whisper_ctx = WhisperContext::new_with_params(
-        model_path.to_str().unwrap(),
-        WhisperContextParameters::default(),
-    )
-    .map_err(|e| format!("Failed to load whisper context: {}", e))?;
-
-    // Store the transcriber
-    {
-        let mut t = state.transcriber.lock().map_err(|e| e.to_string())?;
-        *t = Some(Arc::new(transcriber));
-    }
-
-    // Store the whisper context
-    {
-        let mut ctx = state.whisper_ctx.lock().map_err(|e| e.to_string())?;
-        *ctx = Some(Arc::new(whisper_ctx));
-    }
-
-    // Update current model
-    {
-        let mut current = state.current_model.lock().map_err(|e| e.to_string())?;
-        *current = Some(model_size);
-    }
-
-    Ok(())
+    // Load the model; runs still using the previous one keep it until they finish
+    state
+        .model
+        .load(model_size, &model_path)
+        .map_err(|e| e.to_string())
 }
 
 /// Get the currently loaded model
 #[tauri::command]
 pub fn get_loaded_model(state: State<TranscriptionState>) -> Option<String> {
-    let current = state.current_model.lock().ok()?;
-    current.as_ref().map(|m| m.as_str().to_string())
+    state.model.current().map(|m| m.as_str().to_string())
+}
+
+/// Free the memory held by the loaded model. Transcriptions in progress finish first; the
+/// memory is released when the last of them does.
+#[tauri::command]
+pub fn unload_model(state: State<TranscriptionState>) {
+    state.model.unload();
 }
 
 /// Transcribe an audio file
 #[tauri::command]
 pub async fn transcribe_audio(
+    app: AppHandle,
     audio_path: String,
     note_id: String,
     speaker: Option<String>,
     state: State<'_, TranscriptionState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<TranscriptionResult, String> {
-    // Check if already transcribing
-    if state.is_transcribing.swap(true, Ordering::SeqCst) {
-        return Err("Already transcribing".to_string());
-    }
+    let job = jobs
+        .enqueue(JobKind::Transcription, Priority::Normal, "Transcribe audio", Some(&note_id))
+        .await?;
+    let result = transcribe_file(&app, &audio_path, &note_id, speaker.as_deref(), &state, &db).await;
+    job.finish(&result);
+    result
+}
 
+async fn transcribe_file(
+    app: &AppHandle,
+    audio_path: &str,
+    note_id: &str,
+    speaker: Option<&str>,
+    state: &TranscriptionState,
+    db: &Database,
+) -> Result<TranscriptionResult, String> {
     // Get the transcriber
-    let transcriber = {
-        let guard = state.transcriber.lock().map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
-        guard.clone().ok_or_else(|| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            "No model loaded. Please load a model first.".to_string()
-        })?
-    };
-
-    // Run transcription in a blocking task (since whisper-rs is synchronous)
-    let path = PathBuf::from(&audio_path);
-    let result = tokio::task::spawn_blocking(move || transcriber.transcribe(&path))
-        .await
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
+    let transcriber = state
+        .model
+        .get()
+        .map(|model| language::transcriber(db, note_id, model))
+        .ok_or("No model loaded. Please load a model first.")?;
+
+    // Run transcription in a blocking task (since whisper-rs is synchronous). Without a
+    // speaker given, the voices in the file are told apart.
+    let path = PathBuf::from(audio_path);
+    let diarize = speaker.is_none();
+    let mut result = tokio::task::spawn_blocking(move || {
+        if diarize {
+            transcriber.transcribe_speakers(&path)
+        } else {
+            transcriber.transcribe(&path)
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    language::record(db, note_id, &result);
+    if diarize {
+        voices::identify(db, note_id, None, &mut result);
+    }
 
     // Save segments to database (skip blank/noise segments)
+    let mut saved_count = 0;
     for segment in &result.segments {
         if !should_skip_segment(&segment.text, segment.start_time, segment.end_time) {
-            db.add_transcript_segment(&note_id, segment.start_time, segment.end_time, &segment.text, speaker.as_deref(), None, None)
-                .map_err(|e| e.to_string())?;
+            let speaker = segment.speaker.as_deref().or(speaker);
+            db.add_transcript_segment(
+                note_id,
+                segment.start_time,
+                segment.end_time,
+                &segment.text,
+                speaker,
+                None,
+                None,
+                result.original_language(),
+                &segment.words,
+            )
+            .map_err(|e| e.to_string())?;
+            saved_count += 1;
         }
     }
 
-    state.is_transcribing.store(false, Ordering::SeqCst);
+    notify_transcription_completed(app, note_id, saved_count);
     Ok(result)
 }
 
-/// Check if currently transcribing
+/// Fire the "transcription.completed" webhook for a note, and start what follows a
+/// finished transcript (auto-export, compressing the recording)
+pub(crate) fn notify_transcription_completed(app: &AppHandle, note_id: &str, segment_count: usize) {
+    auto_export_note(app, note_id);
+    compression::schedule(app, note_id);
+    webhooks::dispatch(
+        app,
+        webhooks::EVENT_TRANSCRIPTION_COMPLETED,
+        serde_json::json!({
+            "note_id": note_id,
+            "segment_count": segment_count,
+        }),
+    );
+}
+
+/// Check if a file transcription is queued or running
 #[tauri::command]
-pub fn is_transcribing(state: State<TranscriptionState>) -> bool {
-    state.is_transcribing.load(Ordering::SeqCst)
+pub fn is_transcribing(jobs: State<JobManager>) -> bool {
+    jobs.is_busy(JobKind::Transcription)
 }
 
 /// Result of dual transcription
@@ -299,56 +308,61 @@ pub struct DualTranscriptionResult {
 /// - note_id: The note ID to associate segments with
 #[tauri::command]
 pub async fn transcribe_dual_audio(
+    app: AppHandle,
     mic_path: String,
     system_path: Option<String>,
     note_id: String,
     state: State<'_, TranscriptionState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<DualTranscriptionResult, String> {
-    // Check if already transcribing
-    if state.is_transcribing.swap(true, Ordering::SeqCst) {
-        return Err("Already transcribing".to_string());
-    }
+    let job = jobs
+        .enqueue(JobKind::Transcription, Priority::Normal, "Transcribe recording", Some(&note_id))
+        .await?;
+    let result = transcribe_dual_files(&app, &mic_path, system_path, &note_id, &state, &db).await;
+    job.finish(&result);
+    result
+}
 
+async fn transcribe_dual_files(
+    app: &AppHandle,
+    mic_path: &str,
+    system_path: Option<String>,
+    note_id: &str,
+    state: &TranscriptionState,
+    db: &Database,
+) -> Result<DualTranscriptionResult, String> {
     // Get the transcriber
-    let transcriber = {
-        let guard = state.transcriber.lock().map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
-        guard.clone().ok_or_else(|| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            "No model loaded. Please load a model first.".to_string()
-        })?
-    };
+    let transcriber = state
+        .model
+        .get()
+        .map(|model| language::transcriber(db, note_id, model))
+        .ok_or("No model loaded. Please load a model first.")?;
 
     let mut total_segments = 0;
 
     // Transcribe mic audio (labeled as "You")
-    let mic_path_buf = PathBuf::from(&mic_path);
+    let mic_path_buf = PathBuf::from(mic_path);
     let transcriber_clone = transcriber.clone();
     let mic_result = tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&mic_path_buf))
         .await
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    language::record(db, note_id, &mic_result);
 
     // Save mic segments to database with "You" speaker label (skip blank/noise)
     for segment in &mic_result.segments {
         if !should_skip_segment(&segment.text, segment.start_time, segment.end_time) {
             db.add_transcript_segment(
-                &note_id,
+                note_id,
                 segment.start_time,
                 segment.end_time,
                 &segment.text,
                 Some("You"),
                 None,
                 None,
+                mic_result.original_language(),
+                &segment.words,
             )
             .map_err(|e| e.to_string())?;
             total_segments += 1;
@@ -366,13 +380,15 @@ pub async fn transcribe_dual_audio(
                 for segment in &result.segments {
                     if !should_skip_segment(&segment.text, segment.start_time, segment.end_time) {
                         db.add_transcript_segment(
-                            &note_id,
+                            note_id,
                             segment.start_time,
                             segment.end_time,
                             &segment.text,
                             Some("Others"),
                             None,
                             None,
+                            result.original_language(),
+                            &segment.words,
                         )
                         .map_err(|e| e.to_string())?;
                         total_segments += 1;
@@ -381,11 +397,11 @@ pub async fn transcribe_dual_audio(
                 Some(result)
             }
             Ok(Err(e)) => {
-                eprintln!("Failed to transcribe system audio: {}", e);
+                tracing::warn!("Failed to transcribe system audio: {}", e);
                 None
             }
             Err(e) => {
-                eprintln!("Failed to spawn system audio transcription task: {}", e);
+                tracing::warn!("Failed to spawn system audio transcription task: {}", e);
                 None
             }
         }
@@ -393,7 +409,7 @@ pub async fn transcribe_dual_audio(
         None
     };
 
-    state.is_transcribing.store(false, Ordering::SeqCst);
+    notify_transcription_completed(app, note_id, total_segments);
 
     Ok(DualTranscriptionResult {
         mic_result,
@@ -402,7 +418,7 @@ pub async fn transcribe_dual_audio(
     })
 }
 
-/// Get transcript segments for a note
+/// Get transcript segments for a note, with the timings of their words
 #[tauri::command]
 pub fn get_transcript(
     note_id: String,
@@ -423,11 +439,80 @@ pub fn add_transcript_segment(
     source_id: Option<i64>,
     db: State<Database>,
 ) -> Result<i64, String> {
-    db.add_transcript_segment(&note_id, start_time, end_time, &text, speaker.as_deref(), source_type.as_deref(), source_id)
+    db.add_transcript_segment(
+        &note_id,
+        start_time,
+        end_time,
+        &text,
+        speaker.as_deref(),
+        source_type.as_deref(),
+        source_id,
+        None,
+        &[],
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// A note's transcription language and the one its transcripts came out in
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteLanguage {
+    /// Its own language, "auto" to detect it; None when it follows the setting
+    pub language: Option<String>,
+    pub detected_language: Option<String>,
+    /// Whether it is translated into English as it is transcribed
+    pub translate: bool,
+}
+
+#[tauri::command]
+pub fn get_note_language(db: State<Database>, note_id: String) -> Result<NoteLanguage, String> {
+    let (language, detected_language) =
+        db.get_note_languages(&note_id).map_err(|e| e.to_string())?;
+    let translate = db.get_note_translate(&note_id).map_err(|e| e.to_string())?;
+    Ok(NoteLanguage {
+        language,
+        detected_language,
+        translate,
+    })
+}
+
+/// Give a note its own transcription language (a code from `list_transcription_languages`
+/// or "auto"), or None to follow the setting. Applies to transcriptions started after.
+#[tauri::command]
+pub fn set_note_language(
+    db: State<Database>,
+    note_id: String,
+    language: Option<String>,
+) -> Result<(), String> {
+    if let Some(code) = &language
+        && !language::is_valid(code)
+    {
+        return Err(format!("Unknown language: {}", code));
+    }
+    db.set_note_language(&note_id, language.as_deref())
         .map_err(|e| e.to_string())
 }
 
-/// Start live transcription during recording
+/// Have a note translated into English as it is transcribed, or transcribed as spoken.
+/// Applies to transcriptions started after.
+#[tauri::command]
+pub fn set_note_translate(
+    db: State<Database>,
+    note_id: String,
+    translate: bool,
+) -> Result<(), String> {
+    db.set_note_translate(&note_id, translate)
+        .map_err(|e| e.to_string())
+}
+
+/// The languages Whisper transcribes
+#[tauri::command]
+pub fn list_transcription_languages() -> Vec<language::Language> {
+    language::all()
+}
+
+/// Start live transcription during recording, in `language` ("auto" to detect it) or else
+/// the note's
 #[tauri::command]
 pub async fn start_live_transcription(
     app: AppHandle,
@@ -435,19 +520,33 @@ pub async fn start_live_transcription(
     language: Option<String>,
     state: State<'_, TranscriptionState>,
     audio_state: State<'_, AudioState>,
+    db: State<'_, Database>,
 ) -> Result<(), String> {
-    // Get the whisper context
-    let whisper_ctx = {
-        let guard = state.whisper_ctx.lock().map_err(|e| e.to_string())?;
-        guard.clone().ok_or("No model loaded. Please load a model first.")?
-    };
+    // The live session holds the model until it stops
+    let model = state
+        .model
+        .get()
+        .ok_or("No model loaded. Please load a model first.")?;
 
     let recording_state = audio_state.recording.clone();
     let live_state = state.live_state.clone();
+    let language = match language {
+        Some(code) => Some(code).filter(|code| code != language::AUTO),
+        None => language::for_note(&db, &note_id),
+    };
+    let translate = language::translates(&db, &note_id);
 
-    live::start_live_transcription(app, note_id, language, recording_state, live_state, whisper_ctx)
-        .await
-        .map_err(|e| e.to_string())
+    live::start_live_transcription(
+        app,
+        note_id,
+        language,
+        translate,
+        recording_state,
+        live_state,
+        model,
+    )
+    .await
+    .map_err(|e| e.to_string())
 }
 
 /// Stop live transcription and get final result
@@ -458,18 +557,25 @@ pub async fn stop_live_transcription(
     state: State<'_, TranscriptionState>,
 ) -> Result<TranscriptionResult, String> {
     let live_state = state.live_state.clone();
-    let result = live::stop_live_transcription(live_state).await;
+    let mut result = live::stop_live_transcription(live_state).await;
+    // The language last heard, as stored on the note
+    let db = app.state::<Database>();
+    result.language = db
+        .get_note_languages(&note_id)
+        .ok()
+        .and_then(|(_, detected)| detected);
+    result.translated = language::translates(&db, &note_id);
 
     // Segments are already saved to database during live transcription with speaker labels
 
     // Emit final event (with empty segments - they were already sent in periodic updates)
     let event = crate::transcription::TranscriptionUpdateEvent {
-        note_id,
+        note_id: note_id.clone(),
         segments: vec![],
         is_final: true,
         audio_source: crate::transcription::AudioSource::Mic, // Default for final event
     };
-    let _ = app.emit("transcription-update", event);
+    note_windows::emit_to_note(&app, &note_id, "transcription-update", event);
 
     Ok(result)
 }
@@ -496,35 +602,157 @@ pub async fn retranscribe_audio_segment(
     segment_id: i64,
     state: State<'_, TranscriptionState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<usize, String> {
     // Get the segment info
     let segment = db
         .get_audio_segment_by_id(segment_id)
         .map_err(|e| e.to_string())?;
 
-    // Check if already transcribing
-    if state.is_transcribing.swap(true, Ordering::SeqCst) {
-        return Err("Already transcribing. Please wait for the current transcription to finish.".to_string());
+    let label = format!("Re-transcribe recording {}", segment.segment_index + 1);
+    let job = jobs
+        .enqueue(JobKind::Transcription, Priority::Normal, &label, Some(&segment.note_id))
+        .await?;
+    background_segments().remove(&segment_id);
+    let result = retranscribe_segment(&segment, &state, &db).await;
+    job.finish(&result);
+    result
+}
+
+/// "true" transcribes each recording segment finished by pause or segment rotation in the
+/// background, while the recording goes on
+pub const SETTING_TRANSCRIBE_WHILE_RECORDING: &str = "transcribe_segments_while_recording";
+
+/// Time for the recording threads to finish writing a segment's files before they are read
+const SEGMENT_SETTLE: Duration = Duration::from_secs(2);
+
+/// Segments sent for transcription while recording: None while waiting, then the model that
+/// transcribed it and the transcript lines saved. `retranscribe_all` keeps what the loaded
+/// model already did and takes over the rest.
+static BACKGROUND_SEGMENTS: Mutex<BTreeMap<i64, Option<(ModelSize, usize)>>> =
+    Mutex::new(BTreeMap::new());
+
+fn background_segments() -> MutexGuard<'static, BTreeMap<i64, Option<(ModelSize, usize)>>> {
+    BACKGROUND_SEGMENTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Payload of "segment-transcription-progress"
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentTranscriptionEvent {
+    pub note_id: String,
+    pub segment_id: i64,
+    /// "queued", "transcribing", "completed", "failed", or "skipped" when a transcription
+    /// of the whole note took it over
+    pub status: &'static str,
+    /// Transcript lines saved, once completed
+    pub segment_count: usize,
+    pub error: Option<String>,
+}
+
+/// Transcribe a recording segment just finished by pause or segment rotation as a
+/// low-priority job, while the recording goes on, so most of the transcript is ready by
+/// the time it ends. Does nothing when turned off or no model is loaded. Emits
+/// "segment-transcription-progress".
+pub(crate) fn queue_segment_transcription(app: &AppHandle, segment_id: i64) {
+    let enabled = app
+        .try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_TRANSCRIBE_WHILE_RECORDING).ok().flatten())
+        .is_none_or(|v| v == "true");
+    if !enabled || segment_id <= 0 || app.state::<TranscriptionState>().model.get().is_none() {
+        return;
     }
+    background_segments().insert(segment_id, None);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Database>();
+        let segment = match db.get_audio_segment_by_id(segment_id) {
+            Ok(segment) => segment,
+            Err(e) => {
+                background_segments().remove(&segment_id);
+                tracing::warn!("Recording segment {} to transcribe not found: {}", segment_id, e);
+                return;
+            }
+        };
+        let emit = |status, segment_count, error| {
+            note_windows::emit_to_note(
+                &app,
+                &segment.note_id,
+                "segment-transcription-progress",
+                SegmentTranscriptionEvent {
+                    note_id: segment.note_id.clone(),
+                    segment_id,
+                    status,
+                    segment_count,
+                    error,
+                },
+            );
+        };
+        emit("queued", 0, None);
+        tokio::time::sleep(SEGMENT_SETTLE).await;
+
+        let label = format!("Transcribe recording {}", segment.segment_index + 1);
+        let job = match app
+            .state::<JobManager>()
+            .enqueue(JobKind::Transcription, Priority::Low, &label, Some(&segment.note_id))
+            .await
+        {
+            Ok(job) => job,
+            Err(e) => {
+                background_segments().remove(&segment_id);
+                emit("failed", 0, Some(e));
+                return;
+            }
+        };
+        if !background_segments().contains_key(&segment_id) {
+            job.finish(&Ok::<_, String>(0));
+            emit("skipped", 0, None);
+            return;
+        }
+
+        emit("transcribing", 0, None);
+        let state = app.state::<TranscriptionState>();
+        let model = state.model.get().map(|model| model.size);
+        let result = retranscribe_segment(&segment, &state, &db).await;
+        job.finish(&result);
+        match result {
+            Ok(count) => {
+                if let (Some(entry), Some(model)) =
+                    (background_segments().get_mut(&segment_id), model)
+                {
+                    *entry = Some((model, count));
+                }
+                emit("completed", count, None);
+            }
+            Err(e) => {
+                background_segments().remove(&segment_id);
+                tracing::warn!("Failed to transcribe recording segment {}: {}", segment_id, e);
+                emit("failed", 0, Some(e));
+            }
+        }
+    });
+}
+
+async fn retranscribe_segment(
+    segment: &AudioSegment,
+    state: &TranscriptionState,
+    db: &Database,
+) -> Result<usize, String> {
+    let segment_id = segment.id;
 
     // Delete existing transcript segments for this segment
     db.delete_transcript_segments_by_source("segment", segment_id)
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
+        .map_err(|e| e.to_string())?;
 
     // Get the transcriber
-    let transcriber = {
-        let guard = state.transcriber.lock().map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
-        guard.clone().ok_or_else(|| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            "No model loaded. Please load a Whisper model first.".to_string()
-        })?
-    };
+    let transcriber = state
+        .model
+        .get()
+        .map(|model| language::transcriber(db, &segment.note_id, model))
+        .ok_or("No model loaded. Please load a Whisper model first.")?;
 
     let mut total_segments = 0;
     let mut system_segments_for_echo: Vec<(f64, f64, String)> = Vec::new();
@@ -536,6 +764,7 @@ pub async fn retranscribe_audio_segment(
 
         match tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&sys_path_buf)).await {
             Ok(Ok(result)) => {
+                language::record(db, &segment.note_id, &result);
                 for seg in &result.segments {
                     if !should_skip_segment(&seg.text, seg.start_time, seg.end_time) {
                         // Store for echo detection
@@ -549,6 +778,8 @@ pub async fn retranscribe_audio_segment(
                             Some("Others"),
                             Some("segment"),
                             Some(segment_id),
+                            result.original_language(),
+                            &seg.words,
                         )
                         .map_err(|e| e.to_string())?;
                         total_segments += 1;
@@ -556,10 +787,10 @@ pub async fn retranscribe_audio_segment(
                 }
             }
             Ok(Err(e)) => {
-                eprintln!("Failed to transcribe system audio: {}", e);
+                tracing::warn!("Failed to transcribe system audio: {}", e);
             }
             Err(e) => {
-                eprintln!("Failed to spawn system audio transcription task: {}", e);
+                tracing::warn!("Failed to spawn system audio transcription task: {}", e);
             }
         }
     }
@@ -570,14 +801,9 @@ pub async fn retranscribe_audio_segment(
         let transcriber_clone = transcriber.clone();
         let mic_result = tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&mic_path_buf))
             .await
-            .map_err(|e| {
-                state.is_transcribing.store(false, Ordering::SeqCst);
-                e.to_string()
-            })?
-            .map_err(|e| {
-                state.is_transcribing.store(false, Ordering::SeqCst);
-                e.to_string()
-            })?;
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+        language::record(db, &segment.note_id, &mic_result);
 
         // Save mic segments to database with "You" speaker label, filtering out echoes
         for seg in &mic_result.segments {
@@ -598,14 +824,14 @@ pub async fn retranscribe_audio_segment(
                 Some("You"),
                 Some("segment"),
                 Some(segment_id),
+                mic_result.original_language(),
+                &seg.words,
             )
             .map_err(|e| e.to_string())?;
             total_segments += 1;
         }
     }
 
-    state.is_transcribing.store(false, Ordering::SeqCst);
-
     Ok(total_segments)
 }
 
@@ -616,56 +842,73 @@ pub async fn retranscribe_note(
     app: AppHandle,
     state: State<'_, TranscriptionState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<RetranscribeResult, String> {
-    // Check if already transcribing
-    if state.is_transcribing.swap(true, Ordering::SeqCst) {
-        return Err("Already transcribing. Please wait for the current transcription to finish.".to_string());
-    }
+    let job = jobs
+        .enqueue(JobKind::Transcription, Priority::Normal, "Re-transcribe note", Some(&note_id))
+        .await?;
+    let result = retranscribe_all(&app, &note_id, &state, &db, &job).await;
+    job.finish(&result);
+    result
+}
 
+async fn retranscribe_all(
+    app: &AppHandle,
+    note_id: &str,
+    state: &TranscriptionState,
+    db: &Database,
+    job: &Job,
+) -> Result<RetranscribeResult, String> {
     // Get the transcriber
-    let transcriber = {
-        let guard = state.transcriber.lock().map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
-        guard.clone().ok_or_else(|| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            "No model loaded. Please load a Whisper model first.".to_string()
-        })?
-    };
+    let model = state
+        .model
+        .get()
+        .ok_or("No model loaded. Please load a Whisper model first.")?;
+    let model_size = model.size;
+    let transcriber = language::transcriber(db, note_id, model);
 
     // Get all audio segments and uploads for this note
-    let segments = db.get_audio_segments(&note_id).map_err(|e| {
-        state.is_transcribing.store(false, Ordering::SeqCst);
-        e.to_string()
-    })?;
-
-    let uploads = db.get_uploaded_audio(&note_id).map_err(|e| {
-        state.is_transcribing.store(false, Ordering::SeqCst);
-        e.to_string()
-    })?;
-
-    println!("[retranscribe_note] note_id: {}", note_id);
-    println!("[retranscribe_note] Found {} audio segments", segments.len());
+    let segments = db.get_audio_segments(note_id).map_err(|e| e.to_string())?;
+    let uploads = db.get_uploaded_audio(note_id).map_err(|e| e.to_string())?;
+
+    tracing::debug!("Re-transcribing note {}", note_id);
+    tracing::debug!("Found {} audio segments", segments.len());
     for seg in &segments {
-        println!("[retranscribe_note]   Segment {}: mic_path={:?}", seg.id, seg.mic_path);
+        tracing::debug!("  Segment {}: mic_path={:?}", seg.id, seg.mic_path);
     }
-    println!("[retranscribe_note] Found {} uploads", uploads.len());
+    tracing::debug!("Found {} uploads", uploads.len());
 
     let total_items = segments.len() + uploads.len();
     let mut completed_items = 0;
     let mut failed_items: Vec<String> = Vec::new();
     let mut total_segments_created = 0;
 
-    // Delete ALL existing transcripts for this note first
+    // Segments the loaded model already transcribed while recording are kept; any still
+    // waiting for it are done here instead
+    let kept: BTreeMap<i64, usize> = {
+        let mut background = background_segments();
+        segments
+            .iter()
+            .filter_map(|segment| match background.remove(&segment.id) {
+                Some(Some((size, count))) if size == model_size => Some((segment.id, count)),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // Delete ALL other existing transcripts for this note first
     // This handles both new format (with source_type) and legacy format (source_type=null)
-    if let Err(e) = db.delete_transcript_segments(&note_id) {
-        state.is_transcribing.store(false, Ordering::SeqCst);
+    let deleted = if kept.is_empty() {
+        db.delete_transcript_segments(note_id)
+    } else {
+        db.delete_transcript_segments_except(note_id, &kept.keys().copied().collect::<Vec<_>>())
+    };
+    if let Err(e) = deleted {
         return Err(format!("Failed to delete existing transcripts: {}", e));
     }
 
     // Emit initial progress
-    let _ = app.emit("retranscribe-progress", serde_json::json!({
+    note_windows::emit_to_note(app, note_id, "retranscribe-progress", serde_json::json!({
         "noteId": note_id,
         "totalItems": total_items,
         "completedItems": completed_items,
@@ -674,16 +917,24 @@ pub async fn retranscribe_note(
 
     // Process audio segments
     for segment in &segments {
+        job.check_cancelled()?;
+        job.progress(completed_items as f32 / total_items as f32);
         let item_name = format!("Recording {}", segment.segment_index + 1);
 
         // Emit progress
-        let _ = app.emit("retranscribe-progress", serde_json::json!({
+        note_windows::emit_to_note(app, note_id, "retranscribe-progress", serde_json::json!({
             "noteId": note_id,
             "totalItems": total_items,
             "completedItems": completed_items,
             "currentItem": item_name,
         }));
 
+        if let Some(count) = kept.get(&segment.id) {
+            total_segments_created += count;
+            completed_items += 1;
+            continue;
+        }
+
         // Detect if this is a legacy merged audio file (only applies when mic_path is set)
         // Legacy files are like "{noteId}.wav" (merged playback)
         // New format files have "_mic_seg" in the name
@@ -703,8 +954,8 @@ pub async fn retranscribe_note(
                         let mic_file = parent.join(format!("{}_mic.wav", stem_str));
                         let system_file = parent.join(format!("{}_system.wav", stem_str));
 
-                        println!("[retranscribe_note] Legacy merged file detected: {:?}", stored_mic_path);
-                        println!("[retranscribe_note] Looking for separate files: mic={:?}, system={:?}", mic_file, system_file);
+                        tracing::debug!("Legacy merged file detected: {:?}", stored_mic_path);
+                        tracing::debug!("Looking for separate files: mic={:?}, system={:?}", mic_file, system_file);
 
                         let mic = if mic_file.exists() { mic_file } else { stored_mic_path.clone() };
                         let system = if system_file.exists() { Some(system_file) } else { None };
@@ -724,13 +975,14 @@ pub async fn retranscribe_note(
         let mut system_segments_for_echo: Vec<(f64, f64, String)> = Vec::new();
 
         if let Some(sys_path) = &actual_system_path {
-            println!("[retranscribe_note] Transcribing system FIRST: {:?}", sys_path);
+            tracing::debug!("Transcribing system FIRST: {:?}", sys_path);
             let sys_path_clone = sys_path.clone();
             let transcriber_clone = transcriber.clone();
 
             match tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&sys_path_clone)).await {
                 Ok(Ok(result)) => {
-                    println!("[retranscribe_note] System transcription succeeded, {} segments", result.segments.len());
+                    tracing::debug!("System transcription succeeded, {} segments", result.segments.len());
+                    language::record(db, note_id, &result);
                     let mut last_start = 0.0_f64;
                     for seg in &result.segments {
                         if !should_skip_segment(&seg.text, seg.start_time, seg.end_time) {
@@ -740,13 +992,15 @@ pub async fn retranscribe_note(
                             let (start_time, end_time) =
                                 clamp_monotonic(seg.start_time, seg.end_time, &mut last_start);
                             if let Ok(_) = db.add_transcript_segment(
-                                &note_id,
+                                note_id,
                                 start_time,
                                 end_time,
                                 &seg.text,
                                 Some("Others"),
                                 Some("segment"),
                                 Some(segment.id),
+                                result.original_language(),
+                                &seg.words,
                             ) {
                                 total_segments_created += 1;
                             }
@@ -754,23 +1008,24 @@ pub async fn retranscribe_note(
                     }
                 }
                 Ok(Err(e)) => {
-                    eprintln!("Failed to transcribe system audio for segment {}: {}", segment.id, e);
+                    tracing::warn!("Failed to transcribe system audio for segment {}: {}", segment.id, e);
                 }
                 Err(e) => {
-                    eprintln!("Failed to spawn system audio transcription for segment {}: {}", segment.id, e);
+                    tracing::warn!("Failed to spawn system audio transcription for segment {}: {}", segment.id, e);
                 }
             }
         }
 
         // Now transcribe mic audio and filter out echoes (if mic recording exists)
         if let Some(mic_path) = actual_mic_path {
-            println!("[retranscribe_note] Transcribing mic: {:?}", mic_path);
+            tracing::debug!("Transcribing mic: {:?}", mic_path);
             let mic_path_for_task = mic_path.clone();
             let transcriber_clone = transcriber.clone();
 
             match tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&mic_path_for_task)).await {
                 Ok(Ok(result)) => {
-                    println!("[retranscribe_note] Mic transcription succeeded, {} segments", result.segments.len());
+                    tracing::debug!("Mic transcription succeeded, {} segments", result.segments.len());
+                    language::record(db, note_id, &result);
                     let mut echo_filtered = 0;
                     let mut last_start = 0.0_f64;
                     for seg in &result.segments {
@@ -781,7 +1036,7 @@ pub async fn retranscribe_note(
                         // Filter out segments that are echoes of system audio
                         // (using raw Whisper times for overlap matching)
                         if is_echo_of_system(&seg.text, seg.start_time, seg.end_time, &system_segments_for_echo) {
-                            println!("[retranscribe_note] Filtered echo: \"{}\"", seg.text);
+                            tracing::debug!("Filtered echo: \"{}\"", seg.text);
                             echo_filtered += 1;
                             continue;
                         }
@@ -789,32 +1044,34 @@ pub async fn retranscribe_note(
                         let (start_time, end_time) =
                             clamp_monotonic(seg.start_time, seg.end_time, &mut last_start);
                         if let Ok(_) = db.add_transcript_segment(
-                            &note_id,
+                            note_id,
                             start_time,
                             end_time,
                             &seg.text,
                             Some("You"),
                             Some("segment"),
                             Some(segment.id),
+                            result.original_language(),
+                            &seg.words,
                         ) {
                             total_segments_created += 1;
                         }
                     }
                     if echo_filtered > 0 {
-                        println!("[retranscribe_note] Filtered {} echo segments from mic", echo_filtered);
+                        tracing::debug!("Filtered {} echo segments from mic", echo_filtered);
                     }
                 }
                 Ok(Err(e)) => {
-                    println!("[retranscribe_note] Mic transcription error: {}", e);
+                    tracing::warn!("Mic transcription error: {}", e);
                     failed_items.push(format!("{} (mic): {}", item_name, e));
                 }
                 Err(e) => {
-                    println!("[retranscribe_note] Mic task error: {}", e);
+                    tracing::warn!("Mic task error: {}", e);
                     failed_items.push(format!("{} (mic): {}", item_name, e));
                 }
             }
         } else {
-            println!("[retranscribe_note] Listen-only segment (no mic recording)");
+            tracing::debug!("Listen-only segment (no mic recording)");
         }
 
         completed_items += 1;
@@ -822,10 +1079,12 @@ pub async fn retranscribe_note(
 
     // Process uploaded audio files
     for upload in &uploads {
+        job.check_cancelled()?;
+        job.progress(completed_items as f32 / total_items as f32);
         let item_name = upload.original_filename.clone();
 
         // Emit progress
-        let _ = app.emit("retranscribe-progress", serde_json::json!({
+        note_windows::emit_to_note(app, note_id, "retranscribe-progress", serde_json::json!({
             "noteId": note_id,
             "totalItems": total_items,
             "completedItems": completed_items,
@@ -846,21 +1105,27 @@ pub async fn retranscribe_note(
         let file_path = PathBuf::from(&upload.file_path);
         let transcriber_clone = transcriber.clone();
 
-        match tokio::task::spawn_blocking(move || transcriber_clone.transcribe(&file_path)).await {
-            Ok(Ok(result)) => {
+        let transcribed =
+            tokio::task::spawn_blocking(move || transcriber_clone.transcribe_speakers(&file_path));
+        match transcribed.await {
+            Ok(Ok(mut result)) => {
+                language::record(db, note_id, &result);
+                voices::identify(db, note_id, Some(("upload", upload.id)), &mut result);
                 let mut last_start = 0.0_f64;
                 for seg in &result.segments {
                     if !should_skip_segment(&seg.text, seg.start_time, seg.end_time) {
                         let (start_time, end_time) =
                             clamp_monotonic(seg.start_time, seg.end_time, &mut last_start);
                         if let Ok(_) = db.add_transcript_segment(
-                            &note_id,
+                            note_id,
                             start_time,
                             end_time,
                             &seg.text,
-                            Some(&upload.speaker_label),
+                            Some(seg.speaker.as_deref().unwrap_or(&upload.speaker_label)),
                             Some("upload"),
                             Some(upload.id),
+                            result.original_language(),
+                            &seg.words,
                         ) {
                             total_segments_created += 1;
                         }
@@ -881,16 +1146,15 @@ pub async fn retranscribe_note(
         completed_items += 1;
     }
 
-    state.is_transcribing.store(false, Ordering::SeqCst);
-
     // Emit final progress
-    let _ = app.emit("retranscribe-progress", serde_json::json!({
+    note_windows::emit_to_note(app, note_id, "retranscribe-progress", serde_json::json!({
         "noteId": note_id,
         "totalItems": total_items,
         "completedItems": completed_items,
         "currentItem": "",
         "isComplete": true,
     }));
+    notify_transcription_completed(app, note_id, total_segments_created);
 
     Ok(RetranscribeResult {
         total_items,
@@ -1,15 +1,18 @@
 //! Commands for uploading and managing external audio files.
 
 use std::path::PathBuf;
-use std::sync::atomic::Ordering;
 
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, State};
 use uuid::Uuid;
 
 use crate::audio::converter::{convert_to_wav, get_audio_duration_ms, is_supported_format};
-use crate::commands::transcription::TranscriptionState;
+use crate::commands::transcription::{notify_transcription_completed, TranscriptionState};
 use crate::db::models::UploadedAudio;
 use crate::db::Database;
+use crate::jobs::{JobKind, JobManager, Priority};
+use crate::notifications;
+use crate::storage::{self, NoteFolder};
+use crate::transcription::{language, voices};
 
 /// Upload and convert an audio file for a note
 ///
@@ -21,6 +24,7 @@ pub async fn upload_audio(
     source_path: String,
     speaker_label: Option<String>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<UploadedAudio, String> {
     let source = PathBuf::from(&source_path);
 
@@ -49,26 +53,33 @@ pub async fn upload_audio(
     let output_filename = format!("{}_upload_{}.wav", note_id, upload_id);
     let temp_filename = format!("{}_upload_{}.wav.tmp", note_id, upload_id);
 
-    // Get recordings directory
-    let app_data = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    let recordings_dir = app_data.join("recordings");
-    std::fs::create_dir_all(&recordings_dir)
-        .map_err(|e| format!("Failed to create recordings directory: {}", e))?;
-
-    let temp_path = recordings_dir.join(&temp_filename);
-    let output_path = recordings_dir.join(&output_filename);
+    let uploads_dir = storage::create_folder(&app, &note_id, NoteFolder::Uploads)?;
+    let temp_path = uploads_dir.join(&temp_filename);
+    let output_path = uploads_dir.join(&output_filename);
 
     // Convert to WAV using temp file first
     // If app closes mid-conversion, only .tmp file remains (cleaned up on next startup)
-    let convert_result = convert_to_wav(&source, &temp_path);
+    let job = jobs
+        .enqueue(
+            JobKind::Conversion,
+            Priority::Normal,
+            &format!("Convert {}", original_filename),
+            Some(&note_id),
+        )
+        .await?;
+    let convert_result = {
+        let temp_path = temp_path.clone();
+        tokio::task::spawn_blocking(move || convert_to_wav(&source, &temp_path).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r)
+    };
+    job.finish(&convert_result);
 
     if let Err(e) = convert_result {
         // Clean up temp file on failure
         let _ = std::fs::remove_file(&temp_path);
-        return Err(e.to_string());
+        return Err(e);
     }
 
     // Rename temp to final (atomic on most filesystems)
@@ -118,6 +129,8 @@ pub fn delete_uploaded_audio(upload_id: i64, db: State<Database>) -> Result<(),
     // Delete associated transcript segments
     db.delete_transcript_segments_by_source("upload", upload_id)
         .map_err(|e| e.to_string())?;
+    db.delete_speaker_embeddings_by_source(&info.note_id, Some("upload"), Some(upload_id))
+        .map_err(|e| e.to_string())?;
 
     // Delete database record
     db.delete_uploaded_audio(upload_id)
@@ -127,62 +140,67 @@ pub fn delete_uploaded_audio(upload_id: i64, db: State<Database>) -> Result<(),
 /// Transcribe an uploaded audio file (also used for retranscription)
 #[tauri::command]
 pub async fn transcribe_uploaded_audio(
+    app: AppHandle,
     upload_id: i64,
     state: State<'_, TranscriptionState>,
     db: State<'_, Database>,
+    jobs: State<'_, JobManager>,
 ) -> Result<usize, String> {
     // Get the upload info
     let info = db
         .get_uploaded_audio_by_id(upload_id)
         .map_err(|e| e.to_string())?;
 
-    // Check if already transcribing
-    if state.is_transcribing.swap(true, Ordering::SeqCst) {
-        return Err("Already transcribing. Please wait for the current transcription to finish.".to_string());
-    }
+    let job = jobs
+        .enqueue(
+            JobKind::Transcription,
+            Priority::Normal,
+            &format!("Transcribe {}", info.original_filename),
+            Some(&info.note_id),
+        )
+        .await?;
+    let result = transcribe_upload(&app, &info, &state, &db).await;
+    job.finish(&result);
+    result
+}
+
+async fn transcribe_upload(
+    app: &AppHandle,
+    info: &UploadedAudio,
+    state: &TranscriptionState,
+    db: &Database,
+) -> Result<usize, String> {
+    let upload_id = info.id;
 
     // Delete existing transcript segments for this upload (for retranscription)
     db.delete_transcript_segments_by_source("upload", upload_id)
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
+        .map_err(|e| e.to_string())?;
 
     // Update status to processing
     db.update_uploaded_audio_status(upload_id, "processing")
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
+        .map_err(|e| e.to_string())?;
 
     // Get the transcriber
-    let transcriber = {
-        let guard = state.transcriber.lock().map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            e.to_string()
-        })?;
-        guard.clone().ok_or_else(|| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            "No model loaded. Please load a Whisper model first.".to_string()
-        })?
-    };
+    let transcriber = state
+        .model
+        .get()
+        .map(|model| language::transcriber(db, &info.note_id, model))
+        .ok_or("No model loaded. Please load a Whisper model first.")?;
 
-    // Run transcription
+    // Run transcription, telling apart the voices in the file
     let path = PathBuf::from(&info.file_path);
-    let result = tokio::task::spawn_blocking(move || transcriber.transcribe(&path))
+    let mut result = tokio::task::spawn_blocking(move || transcriber.transcribe_speakers(&path))
         .await
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
-            let _ = db.update_uploaded_audio_status(upload_id, "failed");
-            e.to_string()
-        })?
-        .map_err(|e| {
-            state.is_transcribing.store(false, Ordering::SeqCst);
+        .map_err(|e| e.to_string())
+        .and_then(|r| r.map_err(|e| e.to_string()))
+        .inspect_err(|_| {
             let _ = db.update_uploaded_audio_status(upload_id, "failed");
-            e.to_string()
         })?;
 
-    // Save transcript segments with the speaker label
+    language::record(db, &info.note_id, &result);
+    voices::identify(db, &info.note_id, Some(("upload", upload_id)), &mut result);
+
+    // Save transcript segments with the speaker heard, else the upload's label
     let mut saved_count = 0;
     for segment in &result.segments {
         // Skip blank/noise segments
@@ -201,9 +219,11 @@ pub async fn transcribe_uploaded_audio(
             segment.start_time,
             segment.end_time,
             &segment.text,
-            Some(&info.speaker_label),
+            Some(segment.speaker.as_deref().unwrap_or(&info.speaker_label)),
             Some("upload"),
             Some(upload_id),
+            result.original_language(),
+            &segment.words,
         )
         .map_err(|e| e.to_string())?;
         saved_count += 1;
@@ -213,7 +233,15 @@ pub async fn transcribe_uploaded_audio(
     db.update_uploaded_audio_status(upload_id, "completed")
         .map_err(|e| e.to_string())?;
 
-    state.is_transcribing.store(false, Ordering::SeqCst);
+    notify_transcription_completed(app, &info.note_id, saved_count);
+    notifications::notify_background(
+        app,
+        "Transcription finished",
+        &format!(
+            "{} is ready ({} segments)",
+            info.original_filename, saved_count
+        ),
+    );
 
     Ok(saved_count)
 }
@@ -0,0 +1,152 @@
+//! Commands for configuring outgoing webhooks and inspecting their delivery log.
+
+use tauri::{AppHandle, State};
+
+use crate::db::models::{Webhook, WebhookDelivery};
+use crate::db::Database;
+use crate::webhooks::{self, EVENTS};
+
+fn validate_webhook(
+    url: &str,
+    events: &[String],
+    payload_template: Option<&str>,
+) -> Result<(), String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err("Webhook URL must start with http:// or https://".to_string());
+    }
+    if events.is_empty() {
+        return Err("Select at least one event".to_string());
+    }
+    if let Some(unknown) = events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+        return Err(format!("Unknown webhook event: {}", unknown));
+    }
+    // The template must produce valid JSON for every subscribed event
+    if let Some(template) = payload_template.filter(|t| !t.trim().is_empty()) {
+        for event in events {
+            webhooks::preview_payload(Some(template), event)
+                .map_err(|e| format!("{} ({})", e, event))?;
+        }
+    }
+    Ok(())
+}
+
+/// List the events a webhook can subscribe to
+#[tauri::command]
+pub fn list_webhook_events() -> Vec<String> {
+    EVENTS.iter().map(|e| e.to_string()).collect()
+}
+
+/// Fill in whether a webhook signs its requests
+fn with_secret_flag(mut hook: Webhook) -> Result<Webhook, String> {
+    hook.has_secret = webhooks::get_secret(hook.id)?.is_some();
+    Ok(hook)
+}
+
+/// List all configured webhooks
+#[tauri::command]
+pub fn list_webhooks(db: State<Database>) -> Result<Vec<Webhook>, String> {
+    db.list_webhooks()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(with_secret_flag)
+        .collect()
+}
+
+/// Create a webhook. If `secret` is set, it is stored in the OS keychain and requests
+/// carry an `X-Note67-Signature` header. `payload_template` reshapes the request body
+/// (see `webhooks.rs` for the context it gets).
+#[tauri::command]
+pub fn create_webhook(
+    url: String,
+    events: Vec<String>,
+    secret: Option<String>,
+    payload_template: Option<String>,
+    db: State<Database>,
+) -> Result<Webhook, String> {
+    let payload_template = payload_template.filter(|t| !t.trim().is_empty());
+    validate_webhook(&url, &events, payload_template.as_deref())?;
+    let hook = db
+        .create_webhook(&url, &events, payload_template.as_deref())
+        .map_err(|e| e.to_string())?;
+    if let Some(secret) = secret.as_deref().filter(|s| !s.is_empty())
+        && let Err(e) = webhooks::set_secret(hook.id, secret)
+    {
+        // Not without its secret: the receiver would reject unsigned requests
+        let _ = db.delete_webhook(hook.id);
+        return Err(e);
+    }
+    with_secret_flag(hook)
+}
+
+/// Update a webhook's URL, events, secret, enabled flag, or payload template. Pass
+/// `secret: None` to keep the current secret and an empty string to remove it.
+#[tauri::command]
+pub fn update_webhook(
+    id: i64,
+    url: String,
+    events: Vec<String>,
+    secret: Option<String>,
+    enabled: bool,
+    payload_template: Option<String>,
+    db: State<Database>,
+) -> Result<Webhook, String> {
+    let payload_template = payload_template.filter(|t| !t.trim().is_empty());
+    validate_webhook(&url, &events, payload_template.as_deref())?;
+    let hook = db
+        .update_webhook(id, &url, &events, enabled, payload_template.as_deref())
+        .map_err(|e| e.to_string())?;
+    if let Some(secret) = secret {
+        webhooks::set_secret(id, &secret)?;
+    }
+    with_secret_flag(hook)
+}
+
+/// Send a sample event to a webhook right away (one attempt, marked with an
+/// `X-Note67-Test` header) and return the logged delivery. `event` defaults to the
+/// first event the webhook subscribes to.
+#[tauri::command]
+pub async fn test_webhook(
+    app: AppHandle,
+    id: i64,
+    event: Option<String>,
+    db: State<'_, Database>,
+) -> Result<WebhookDelivery, String> {
+    let hook = db.get_webhook(id).map_err(|e| e.to_string())?;
+    let event = event
+        .or_else(|| hook.events.first().cloned())
+        .ok_or_else(|| "Webhook has no events".to_string())?;
+    if !EVENTS.contains(&event.as_str()) {
+        return Err(format!("Unknown webhook event: {}", event));
+    }
+
+    let delivery_id = webhooks::test_fire(&app, &hook, &event).await?;
+    db.get_webhook_delivery(delivery_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Render the body a webhook would send for a sample `event`
+#[tauri::command]
+pub fn preview_webhook_payload(
+    event: String,
+    payload_template: Option<String>,
+) -> Result<String, String> {
+    webhooks::preview_payload(payload_template.as_deref(), &event)
+}
+
+/// Delete a webhook, its signing secret, and its delivery log
+#[tauri::command]
+pub fn delete_webhook(id: i64, db: State<Database>) -> Result<(), String> {
+    db.delete_webhook(id).map_err(|e| e.to_string())?;
+    webhooks::set_secret(id, "")
+}
+
+/// Recent deliveries for a webhook (newest first, default 50)
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    webhook_id: i64,
+    limit: Option<i64>,
+    db: State<Database>,
+) -> Result<Vec<WebhookDelivery>, String> {
+    db.get_webhook_deliveries(webhook_id, limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,310 @@
+//! Crash reports. Every panic is logged; with crash reports turned on (opt-in) it also
+//! leaves a JSON report in `<app data>/crash-reports` with the backtrace, what the app was
+//! doing (recording? transcribing?) and the last log lines. Crashes that never reach the
+//! panic hook (aborts, signals, the process being killed) are caught on the next launch:
+//! a session marker is written at startup and removed on a clean exit, so a marker left
+//! behind becomes an "unclean_shutdown" report built from the previous session's log.
+
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{AudioState, TranscriptionState};
+use crate::db::Database;
+use crate::jobs::{JobKind, JobManager};
+use crate::logging;
+
+/// Write crash reports (off by default)
+pub const SETTING_ENABLED: &str = "crash_reports_enabled";
+
+const DIR_NAME: &str = "crash-reports";
+const SESSION_MARKER: &str = "session.lock";
+/// Older reports are deleted beyond this many
+const MAX_REPORTS: usize = 20;
+const LOG_LINES: usize = 200;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// File stem, passed to `get_crash_report` / `delete_crash_report`
+    pub id: String,
+    /// "panic" or "unclean_shutdown"
+    pub kind: String,
+    pub created_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub message: Option<String>,
+    /// file:line:column of the panic
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    /// Not known for unclean shutdowns
+    pub state: Option<AppStateSnapshot>,
+    pub recent_logs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppStateSnapshot {
+    /// "idle", "recording" or "paused"
+    pub recording: String,
+    pub recording_note_id: Option<String>,
+    pub transcribing: bool,
+    pub live_transcription: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub kind: String,
+    pub created_at: String,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionMarker {
+    started_at: String,
+    pid: u32,
+}
+
+fn reports_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(DIR_NAME))
+}
+
+/// Install the panic hook and check how the previous session ended. Call once the
+/// database is managed and before `logging::init`, so the previous session's log is still
+/// the newest.
+pub fn init(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+    let enabled = app
+        .state::<Database>()
+        .get_setting(SETTING_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    if let Some(dir) = reports_dir(app) {
+        let marker = dir.join(SESSION_MARKER);
+        if marker.exists() && enabled {
+            write_unclean_shutdown_report(app, &dir, &marker);
+        }
+        let _ = fs::create_dir_all(&dir);
+        let session = SessionMarker {
+            started_at: Utc::now().to_rfc3339(),
+            pid: std::process::id(),
+        };
+        if let Ok(json) = serde_json::to_string(&session) {
+            let _ = fs::write(&marker, json);
+        }
+    }
+
+    // Follow the setting
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<serde_json::Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed {
+            key,
+            value: Some(serde_json::Value::Bool(enabled)),
+        }) = serde_json::from_str(event.payload())
+            && key == SETTING_ENABLED
+        {
+            ENABLED.store(enabled, Ordering::Relaxed);
+        }
+    });
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = panic_message(info);
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        tracing::error!(
+            "Panic at {}: {}",
+            location.as_deref().unwrap_or("unknown location"),
+            message
+        );
+        if ENABLED.load(Ordering::Relaxed) {
+            write_panic_report(message, location);
+        }
+        default_hook(info);
+    }));
+}
+
+/// Remove the session marker; call right before the app exits on purpose
+pub fn mark_clean_exit() {
+    if let Some(dir) = APP.get().and_then(reports_dir) {
+        let _ = fs::remove_file(dir.join(SESSION_MARKER));
+    }
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+/// What the app was doing. Only atomics and `try_lock`: the panicking thread may hold any
+/// of these locks.
+fn snapshot(app: &AppHandle) -> Option<AppStateSnapshot> {
+    let audio = app.try_state::<AudioState>()?;
+    let recording = match audio.recording.get_phase() {
+        RecordingPhase::Idle => "idle",
+        RecordingPhase::Recording => "recording",
+        RecordingPhase::Paused => "paused",
+    };
+    let transcription = app.try_state::<TranscriptionState>();
+    Some(AppStateSnapshot {
+        recording: recording.to_string(),
+        recording_note_id: audio
+            .recording
+            .current_note_id
+            .try_lock()
+            .ok()
+            .and_then(|id| id.clone()),
+        transcribing: app
+            .try_state::<JobManager>()
+            .and_then(|jobs| jobs.try_is_busy(JobKind::Transcription))
+            .unwrap_or(false),
+        live_transcription: transcription
+            .as_ref()
+            .is_some_and(|t| t.live_state.is_running.load(Ordering::SeqCst)),
+    })
+}
+
+fn new_report(app: &AppHandle, kind: &str) -> CrashReport {
+    let now = Utc::now();
+    CrashReport {
+        id: format!(
+            "{}-{}",
+            kind.replace('_', "-"),
+            now.format("%Y%m%dT%H%M%S%3fZ")
+        ),
+        kind: kind.to_string(),
+        created_at: now.to_rfc3339(),
+        app_version: app.package_info().version.to_string(),
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        message: None,
+        location: None,
+        thread: None,
+        backtrace: None,
+        state: None,
+        recent_logs: logging::log_dir(app)
+            .map(|dir| logging::recent_lines(&dir, LOG_LINES))
+            .unwrap_or_default(),
+    }
+}
+
+fn write_panic_report(message: String, location: Option<String>) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let Some(dir) = reports_dir(app) else {
+        return;
+    };
+    let report = CrashReport {
+        message: Some(message),
+        location,
+        thread: std::thread::current().name().map(String::from),
+        backtrace: Some(Backtrace::force_capture().to_string()),
+        state: snapshot(app),
+        ..new_report(app, "panic")
+    };
+    save(&dir, &report);
+}
+
+fn write_unclean_shutdown_report(app: &AppHandle, dir: &Path, marker: &Path) {
+    let session: Option<SessionMarker> = fs::read_to_string(marker)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+    let started_at = session.as_ref().map(|s| s.started_at.as_str());
+
+    // A panic report from that session already explains it
+    let explained = started_at.is_some_and(|started| {
+        list(dir)
+            .iter()
+            .any(|r| r.kind == "panic" && r.created_at.as_str() >= started)
+    });
+    if explained {
+        return;
+    }
+    let report = CrashReport {
+        message: Some(match started_at {
+            Some(started) => format!("The session started at {} did not exit cleanly", started),
+            None => "The previous session did not exit cleanly".to_string(),
+        }),
+        ..new_report(app, "unclean_shutdown")
+    };
+    save(dir, &report);
+}
+
+fn save(dir: &Path, report: &CrashReport) {
+    let _ = fs::create_dir_all(dir);
+    if let Ok(json) = serde_json::to_string_pretty(report) {
+        let _ = fs::write(dir.join(format!("{}.json", report.id)), json);
+    }
+    for old in list(dir).into_iter().skip(MAX_REPORTS) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", old.id)));
+    }
+}
+
+/// Reports in `dir`, newest first
+fn list(dir: &Path) -> Vec<CrashReportSummary> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<CrashReportSummary> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|json| serde_json::from_str::<CrashReport>(&json).ok())
+        .map(|r| CrashReportSummary {
+            id: r.id,
+            kind: r.kind,
+            created_at: r.created_at,
+            message: r.message,
+        })
+        .collect();
+    reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    reports
+}
+
+fn report_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    let dir = reports_dir(app).ok_or("App data directory is unavailable")?;
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+#[tauri::command]
+pub fn list_crash_reports(app: AppHandle) -> Vec<CrashReportSummary> {
+    reports_dir(&app).map(|dir| list(&dir)).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_crash_report(app: AppHandle, id: String) -> Result<CrashReport, String> {
+    let json = fs::read_to_string(report_path(&app, &id)?)
+        .map_err(|_| format!("Crash report not found: {}", id))?;
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_crash_report(app: AppHandle, id: String) -> Result<(), String> {
+    fs::remove_file(report_path(&app, &id)?).map_err(|e| e.to_string())
+}
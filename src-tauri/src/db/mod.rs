@@ -1,19 +1,77 @@
 pub mod models;
 pub mod schema;
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection};
 use tauri::{AppHandle, Manager};
 
 use crate::db::models::{
-    ActionItem, ActionItemWithNote, AudioSegment, Summary, SummaryType, TranscriptSegment,
-    UploadedAudio,
+    ActionItem, ActionItemWithNote, AudioSegment, CalendarEvent, ExportTemplate, Job,
+    RecordingMarker, Speaker, SpeakerEmbedding, Summary, SummaryType, TranscriptSegment,
+    UploadedAudio, Webhook, WebhookDelivery, Word,
 };
 use crate::db::schema::run_migrations;
 
+/// A transcript segment for `add_transcript_segments_batch`: (note_id, start, end, text,
+/// speaker, source_type, source_id, original_language, words)
+pub type NewSegmentRow = (
+    String,
+    f64,
+    f64,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<String>,
+    Vec<Word>,
+);
+
+/// A segment's words as stored: a JSON array, or NULL when there are none
+fn words_json(words: &[Word]) -> serde_json::Result<Option<String>> {
+    if words.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(words).map(Some)
+}
+
+/// Forget a note's merged playback file once its segments are cut; playback and export
+/// fall back to the segments
+fn clear_playback_path(conn: &Connection, note_id: &str) -> rusqlite::Result<usize> {
+    conn.execute("UPDATE notes SET audio_path = NULL WHERE id = ?1", [note_id])
+}
+
+/// Column order for reading a `Webhook` row (see `map_webhook`).
+const WEBHOOK_COLS: &str = "id, url, events, enabled, created_at, payload_template";
+
+/// Deliveries kept per webhook; older ones are dropped as new ones are logged
+const MAX_WEBHOOK_DELIVERIES: i64 = 200;
+
+/// Column order for reading a `WebhookDelivery` row.
+const WEBHOOK_DELIVERY_COLS: &str =
+    "id, webhook_id, event, payload, status_code, success, attempts, error, created_at";
+
+/// Column order for reading a `CalendarEvent` row (see `map_calendar_event`).
+const CALENDAR_EVENT_COLS: &str =
+    "id, uid, note_id, title, starts_at, ends_at, location, source, auto_record, record_triggered";
+
+/// Column order for reading an `ExportTemplate` row.
+const EXPORT_TEMPLATE_COLS: &str = "id, name, content, created_at, updated_at";
+
+/// Column order for reading a `Speaker` row (see `map_speaker`).
+const SPEAKER_COLS: &str = "id, name, embedding, sample_count, created_at, updated_at";
+
+/// Column order for reading a `SpeakerEmbedding` row (see `map_speaker_embedding`).
+const SPEAKER_EMBEDDING_COLS: &str =
+    "id, note_id, label, embedding, speaker_id, source_type, source_id, created_at";
+
+/// Column order for reading a `Job` row (see `map_job`).
+const JOB_COLS: &str =
+    "id, kind, label, note_id, priority, status, error, created_at, started_at, finished_at";
+
 /// Column order for reading an `ActionItem` row (see `map_action_item`).
 const ACTION_ITEM_COLS: &str =
     "id, note_id, stable_id, text, description, parent_id, assignee, due_date, done, sort_order, created_at, updated_at";
@@ -44,9 +102,73 @@ impl Database {
         })
     }
 
+    /// Write a consistent copy of the database to `path` (which must not exist)
+    pub fn snapshot_to(&self, path: &Path) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+        Ok(())
+    }
+
+    /// Size of the database and how much of it is free pages that `compact` would give
+    /// back, both in bytes
+    pub fn storage_stats(&self) -> anyhow::Result<(u64, u64)> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let pragma = |name: &str| -> rusqlite::Result<u64> {
+            conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0))
+                .map(|n| n.max(0) as u64)
+        };
+        let page_size = pragma("page_size")?;
+        Ok((
+            pragma("page_count")? * page_size,
+            pragma("freelist_count")? * page_size,
+        ))
+    }
+
+    /// Rewrite the database file without its free pages
+    pub fn compact(&self) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute_batch("VACUUM;")?;
+        Ok(())
+    }
+
+    /// Replace the database file with `source` (e.g. a restored backup) and reopen it.
+    /// The current connection is closed first; migrations run on the restored copy.
+    pub fn replace_with(&self, app_handle: &AppHandle, source: &Path) -> anyhow::Result<()> {
+        let db_path = get_db_path(app_handle)?;
+        // Stage next to the live file so the final swap is a rename
+        let staged = db_path.with_extension("db.restore");
+        std::fs::copy(source, &staged)?;
+
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        // Close the live connection before swapping the file underneath it
+        let old = std::mem::replace(&mut *conn, Connection::open_in_memory()?);
+        if let Err((old, e)) = old.close() {
+            *conn = old;
+            let _ = std::fs::remove_file(&staged);
+            return Err(e.into());
+        }
+
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.to_string_lossy(), suffix));
+        }
+        let swapped = std::fs::rename(&staged, &db_path);
+
+        // Reopen whichever file is now in place, even if the swap failed
+        let restored = Connection::open(&db_path)?;
+        restored.execute_batch("PRAGMA foreign_keys = ON;")?;
+        *conn = restored;
+        swapped?;
+        run_migrations(&conn)?;
+        Ok(())
+    }
+
     /// Add a transcript segment to the database
     /// source_type: 'upload' (from uploaded_audio), 'segment' (from audio_segments), 'live' (from live transcription)
     /// source_id: the id of the source record (uploaded_audio.id or audio_segments.id)
+    /// original_language: the language spoken, when the text is its translation into English
+    /// words: its words, timed
+    #[allow(clippy::too_many_arguments)]
     pub fn add_transcript_segment(
         &self,
         note_id: &str,
@@ -56,25 +178,38 @@ impl Database {
         speaker: Option<&str>,
         source_type: Option<&str>,
         source_id: Option<i64>,
+        original_language: Option<&str>,
+        words: &[Word],
     ) -> anyhow::Result<i64> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
         let now = Utc::now();
 
         conn.execute(
-            "INSERT INTO transcript_segments (note_id, start_time, end_time, text, speaker, source_type, source_id, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![note_id, start_time, end_time, text, speaker, source_type, source_id, now.to_rfc3339()],
+            "INSERT INTO transcript_segments (note_id, start_time, end_time, text, speaker, source_type, source_id, original_language, words, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                note_id,
+                start_time,
+                end_time,
+                text,
+                speaker,
+                source_type,
+                source_id,
+                original_language,
+                words_json(words)?,
+                now.to_rfc3339()
+            ],
         )?;
 
         Ok(conn.last_insert_rowid())
     }
 
     /// Add multiple transcript segments in a single transaction (batch insert)
-    /// Tuple: (note_id, start, end, text, speaker, source_type, source_id)
     pub fn add_transcript_segments_batch(
         &self,
-        segments: &[(String, f64, f64, String, Option<String>, Option<String>, Option<i64>)],
+        segments: &[NewSegmentRow],
     ) -> anyhow::Result<usize> {
+        let _span = tracing::info_span!("db.transaction", op = "add_transcript_segments_batch").entered();
         let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
         let now = Utc::now().to_rfc3339();
 
@@ -83,12 +218,34 @@ impl Database {
 
         {
             let mut stmt = tx.prepare_cached(
-                "INSERT INTO transcript_segments (note_id, start_time, end_time, text, speaker, source_type, source_id, created_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                "INSERT INTO transcript_segments (note_id, start_time, end_time, text, speaker, source_type, source_id, original_language, words, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             )?;
 
-            for (note_id, start_time, end_time, text, speaker, source_type, source_id) in segments {
-                stmt.execute(params![note_id, start_time, end_time, text, speaker.as_deref(), source_type.as_deref(), source_id, &now])?;
+            for (
+                note_id,
+                start_time,
+                end_time,
+                text,
+                speaker,
+                source_type,
+                source_id,
+                original_language,
+                words,
+            ) in segments
+            {
+                stmt.execute(params![
+                    note_id,
+                    start_time,
+                    end_time,
+                    text,
+                    speaker.as_deref(),
+                    source_type.as_deref(),
+                    source_id,
+                    original_language.as_deref(),
+                    words_json(words)?,
+                    &now
+                ])?;
                 count += 1;
             }
         }
@@ -106,7 +263,8 @@ impl Database {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
 
         let mut stmt = conn.prepare(
-            "SELECT t.id, t.note_id, t.start_time, t.end_time, t.text, t.speaker, t.source_type, t.source_id, t.created_at
+            "SELECT t.id, t.note_id, t.start_time, t.end_time, t.text, t.speaker, t.source_type, t.source_id, t.created_at,
+                 t.original_language, t.words
              FROM transcript_segments t
              LEFT JOIN audio_segments a ON t.source_type = 'segment' AND t.source_id = a.id
              LEFT JOIN uploaded_audio u ON t.source_type = 'upload' AND t.source_id = u.id
@@ -126,6 +284,11 @@ impl Database {
                     source_type: row.get(6)?,
                     source_id: row.get(7)?,
                     created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+                    original_language: row.get(9)?,
+                    words: row
+                        .get::<_, Option<String>>(10)?
+                        .and_then(|words| serde_json::from_str(&words).ok())
+                        .unwrap_or_default(),
                 })
             })?
             .filter_map(|r| r.ok())
@@ -144,6 +307,26 @@ impl Database {
         Ok(())
     }
 
+    /// Delete all of a note's transcript segments except those of the given recorded
+    /// segments
+    pub fn delete_transcript_segments_except(
+        &self,
+        note_id: &str,
+        keep_segment_ids: &[i64],
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let placeholders = vec!["?"; keep_segment_ids.len()].join(", ");
+        let sql = format!(
+            "DELETE FROM transcript_segments WHERE note_id = ?
+             AND NOT (source_type IS 'segment' AND COALESCE(source_id, 0) IN ({}))",
+            placeholders
+        );
+        let values = std::iter::once(rusqlite::types::Value::from(note_id.to_string()))
+            .chain(keep_segment_ids.iter().map(|&id| id.into()));
+        conn.execute(&sql, rusqlite::params_from_iter(values))?;
+        Ok(())
+    }
+
     /// Delete transcript segments by source (e.g., when deleting an uploaded audio)
     pub fn delete_transcript_segments_by_source(
         &self,
@@ -158,6 +341,55 @@ impl Database {
         Ok(deleted)
     }
 
+    /// Transcript segments in the search index and in total
+    pub fn transcript_index_status(&self) -> anyhow::Result<(u64, u64)> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let total: i64 =
+            conn.query_row("SELECT COUNT(*) FROM transcript_segments", [], |row| row.get(0))?;
+        let missing: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM transcript_segments
+             WHERE id NOT IN (SELECT rowid FROM transcript_fts)",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(((total - missing).max(0) as u64, total.max(0) as u64))
+    }
+
+    /// Index the next `limit` transcript segments after segment `after_id` that are not in
+    /// the search index yet. Returns the last segment id looked at and how many were added,
+    /// or None once there are no segments past `after_id`.
+    pub fn index_transcript_segments(
+        &self,
+        after_id: i64,
+        limit: usize,
+    ) -> anyhow::Result<Option<(i64, usize)>> {
+        let _span = tracing::info_span!("db.transaction", op = "index_transcript_segments").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+
+        let last_id: Option<i64> = tx.query_row(
+            "SELECT MAX(id) FROM (
+                 SELECT id FROM transcript_segments WHERE id > ?1 ORDER BY id LIMIT ?2
+             )",
+            params![after_id, limit as i64],
+            |row| row.get(0),
+        )?;
+        let Some(last_id) = last_id else {
+            return Ok(None);
+        };
+
+        let added = tx.execute(
+            "INSERT INTO transcript_fts (rowid, text, note_id)
+             SELECT id, text, note_id FROM transcript_segments
+             WHERE id > ?1 AND id <= ?2
+               AND id NOT IN (SELECT rowid FROM transcript_fts WHERE rowid > ?1 AND rowid <= ?2)",
+            params![after_id, last_id],
+        )?;
+
+        tx.commit()?;
+        Ok(Some((last_id, added)))
+    }
+
     /// Add a summary to the database
     pub fn add_summary(
         &self,
@@ -463,6 +695,13 @@ impl Database {
         Ok(())
     }
 
+    /// Remove a setting
+    pub fn delete_setting(&self, key: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
     // ========== Audio Segments (for pause/resume/continue) ==========
 
     /// Add a new audio segment for a note
@@ -566,239 +805,1535 @@ impl Database {
         Ok(total.unwrap_or(0))
     }
 
-    /// Delete all audio segments for a note
-    pub fn delete_audio_segments(&self, note_id: &str) -> anyhow::Result<()> {
+    /// Notes created before `before` that have recorded audio but were never ended, with
+    /// their start time and audio_path. These are left behind when the app quits mid-recording.
+    pub fn get_unended_recorded_notes(
+        &self,
+        before: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(String, DateTime<Utc>, Option<String>)>> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-        conn.execute(
-            "DELETE FROM audio_segments WHERE note_id = ?1",
-            [note_id],
+        let mut stmt = conn.prepare(
+            "SELECT id, started_at, audio_path FROM notes
+             WHERE ended_at IS NULL AND created_at < ?1
+               AND (audio_path IS NOT NULL
+                    OR EXISTS (SELECT 1 FROM audio_segments s WHERE s.note_id = notes.id))",
         )?;
-        Ok(())
+        let notes = stmt
+            .query_map([before.to_rfc3339()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?.parse().unwrap_or_else(|_| Utc::now()),
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(notes)
     }
 
-    /// Get the latest (most recent) segment for a note
-    #[allow(dead_code)]
-    pub fn get_latest_segment(&self, note_id: &str) -> anyhow::Result<Option<AudioSegment>> {
+    /// Set a note's end time, and its audio_path if it has none yet
+    pub fn mark_note_ended(
+        &self,
+        note_id: &str,
+        ended_at: DateTime<Utc>,
+        audio_path: Option<&str>,
+    ) -> anyhow::Result<()> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-
-        let segment = conn
-            .query_row(
-                "SELECT id, note_id, segment_index, mic_path, system_path, start_offset_ms, duration_ms, display_order, created_at
-                 FROM audio_segments
-                 WHERE note_id = ?1
-                 ORDER BY segment_index DESC
-                 LIMIT 1",
-                [note_id],
-                |row| {
-                    Ok(AudioSegment {
-                        id: row.get(0)?,
-                        note_id: row.get(1)?,
-                        segment_index: row.get(2)?,
-                        mic_path: row.get(3)?,
-                        system_path: row.get(4)?,
-                        start_offset_ms: row.get(5)?,
-                        duration_ms: row.get(6)?,
-                        display_order: row.get(7)?,
-                        created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
-                    })
-                },
-            )
-            .ok();
-
-        Ok(segment)
+        conn.execute(
+            "UPDATE notes SET ended_at = ?1, updated_at = ?2, audio_path = COALESCE(audio_path, ?3)
+             WHERE id = ?4",
+            params![ended_at.to_rfc3339(), Utc::now().to_rfc3339(), audio_path, note_id],
+        )?;
+        Ok(())
     }
 
-    /// Get an audio segment by ID
-    pub fn get_audio_segment_by_id(&self, id: i64) -> anyhow::Result<AudioSegment> {
+    /// Notes whose noise suppression differs from the global setting, with their choice
+    pub fn get_noise_suppression_overrides(&self) -> anyhow::Result<Vec<(String, bool)>> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-
-        conn.query_row(
-            "SELECT id, note_id, segment_index, mic_path, system_path, start_offset_ms, duration_ms, display_order, created_at
-             FROM audio_segments WHERE id = ?1",
-            [id],
-            |row| {
-                Ok(AudioSegment {
-                    id: row.get(0)?,
-                    note_id: row.get(1)?,
-                    segment_index: row.get(2)?,
-                    mic_path: row.get(3)?,
-                    system_path: row.get(4)?,
-                    start_offset_ms: row.get(5)?,
-                    duration_ms: row.get(6)?,
-                    display_order: row.get(7)?,
-                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
-                })
-            },
-        )
-        .map_err(|e| anyhow::anyhow!("Audio segment not found: {}", e))
+        let mut stmt = conn.prepare(
+            "SELECT id, noise_suppression FROM notes WHERE noise_suppression IS NOT NULL",
+        )?;
+        let overrides = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(overrides)
     }
 
-    // ========== Uploaded Audio ==========
-
-    /// Add an uploaded audio file record
-    pub fn add_uploaded_audio(
+    /// Turn noise suppression on or off for one note; None follows the global setting
+    pub fn set_note_noise_suppression(
         &self,
         note_id: &str,
-        file_path: &str,
-        original_filename: &str,
-        duration_ms: Option<i64>,
-        speaker_label: &str,
-    ) -> anyhow::Result<i64> {
+        enabled: Option<bool>,
+    ) -> anyhow::Result<()> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-        let now = Utc::now();
+        conn.execute(
+            "UPDATE notes SET noise_suppression = ?1 WHERE id = ?2",
+            params![enabled, note_id],
+        )?;
+        Ok(())
+    }
 
-        // Get the next display_order for this note (across both segments and uploads)
-        let max_segment_order: Option<i32> = conn
-            .query_row(
-                "SELECT MAX(display_order) FROM audio_segments WHERE note_id = ?1",
-                [note_id],
-                |row| row.get(0),
-            )
-            .ok()
-            .flatten();
-        let max_upload_order: Option<i32> = conn
+    /// The recording quality preset a note was first recorded with, if it has been
+    pub fn get_note_recording_quality(&self, note_id: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let quality: Option<String> = conn
             .query_row(
-                "SELECT MAX(display_order) FROM uploaded_audio WHERE note_id = ?1",
+                "SELECT recording_quality FROM notes WHERE id = ?1",
                 [note_id],
                 |row| row.get(0),
             )
             .ok()
             .flatten();
-        let display_order = std::cmp::max(
-            max_segment_order.unwrap_or(-1),
-            max_upload_order.unwrap_or(-1),
-        ) + 1;
+        Ok(quality)
+    }
 
+    /// Store the preset a note is recorded with and the rates it gives; `mic_sample_rate`
+    /// is None when the mic is recorded at the device's own rate
+    pub fn set_note_recording_quality(
+        &self,
+        note_id: &str,
+        preset: &str,
+        mic_sample_rate: Option<u32>,
+        system_sample_rate: u32,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
         conn.execute(
-            "INSERT INTO uploaded_audio (note_id, file_path, original_filename, duration_ms, speaker_label, display_order, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![note_id, file_path, original_filename, duration_ms, speaker_label, display_order, now.to_rfc3339()],
+            "UPDATE notes SET recording_quality = ?1, mic_sample_rate = ?2,
+                 system_sample_rate = ?3
+             WHERE id = ?4",
+            params![preset, mic_sample_rate, system_sample_rate, note_id],
         )?;
-
-        Ok(conn.last_insert_rowid())
+        Ok(())
     }
 
-    /// Get all uploaded audio for a note, ordered by display_order
-    pub fn get_uploaded_audio(&self, note_id: &str) -> anyhow::Result<Vec<UploadedAudio>> {
+    /// A note's transcription language and the one its transcripts came out in
+    pub fn get_note_languages(
+        &self,
+        note_id: &str,
+    ) -> anyhow::Result<(Option<String>, Option<String>)> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-
-        let mut stmt = conn.prepare(
-            "SELECT id, note_id, file_path, original_filename, duration_ms, speaker_label, transcription_status, display_order, created_at
-             FROM uploaded_audio
-             WHERE note_id = ?1
-             ORDER BY display_order ASC",
+        let languages = conn.query_row(
+            "SELECT language, detected_language FROM notes WHERE id = ?1",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
-
-        let uploads = stmt
-            .query_map([note_id], |row| {
-                Ok(UploadedAudio {
-                    id: row.get(0)?,
-                    note_id: row.get(1)?,
-                    file_path: row.get(2)?,
-                    original_filename: row.get(3)?,
-                    duration_ms: row.get(4)?,
-                    speaker_label: row.get(5)?,
-                    transcription_status: row.get(6)?,
-                    display_order: row.get(7)?,
-                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
-                })
-            })?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        Ok(uploads)
+        Ok(languages)
     }
 
-    /// Get uploaded audio by ID
-    pub fn get_uploaded_audio_by_id(&self, id: i64) -> anyhow::Result<UploadedAudio> {
-        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-
-        conn.query_row(
-            "SELECT id, note_id, file_path, original_filename, duration_ms, speaker_label, transcription_status, display_order, created_at
-             FROM uploaded_audio WHERE id = ?1",
-            [id],
-            |row| {
-                Ok(UploadedAudio {
-                    id: row.get(0)?,
-                    note_id: row.get(1)?,
-                    file_path: row.get(2)?,
-                    original_filename: row.get(3)?,
-                    duration_ms: row.get(4)?,
-                    speaker_label: row.get(5)?,
-                    transcription_status: row.get(6)?,
-                    display_order: row.get(7)?,
-                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
-                })
-            },
-        )
-        .map_err(|e| anyhow::anyhow!("Uploaded audio not found: {}", e))
+    /// The language a note is transcribed in, if it has its own
+    pub fn get_note_language(&self, note_id: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.get_note_languages(note_id)?.0)
     }
 
-    /// Update transcription status for uploaded audio
-    pub fn update_uploaded_audio_status(&self, id: i64, status: &str) -> anyhow::Result<()> {
+    /// Give a note its own transcription language, or None to follow the setting
+    pub fn set_note_language(&self, note_id: &str, language: Option<&str>) -> anyhow::Result<()> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
         conn.execute(
-            "UPDATE uploaded_audio SET transcription_status = ?1 WHERE id = ?2",
-            params![status, id],
+            "UPDATE notes SET language = ?1 WHERE id = ?2",
+            params![language, note_id],
         )?;
         Ok(())
     }
 
-    /// Update speaker label for uploaded audio
-    pub fn update_uploaded_audio_speaker(&self, id: i64, speaker_label: &str) -> anyhow::Result<()> {
+    /// Whether a note is transcribed straight into English
+    pub fn get_note_translate(&self, note_id: &str) -> anyhow::Result<bool> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-        conn.execute(
-            "UPDATE uploaded_audio SET speaker_label = ?1 WHERE id = ?2",
-            params![speaker_label, id],
+        let translate = conn.query_row(
+            "SELECT translate FROM notes WHERE id = ?1",
+            [note_id],
+            |row| row.get(0),
         )?;
-        Ok(())
+        Ok(translate)
     }
 
-    /// Delete uploaded audio by ID
-    pub fn delete_uploaded_audio(&self, id: i64) -> anyhow::Result<()> {
+    /// Have a note transcribed straight into English, or in the language spoken
+    pub fn set_note_translate(&self, note_id: &str, translate: bool) -> anyhow::Result<()> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-        conn.execute("DELETE FROM uploaded_audio WHERE id = ?1", [id])?;
+        conn.execute(
+            "UPDATE notes SET translate = ?1 WHERE id = ?2",
+            params![translate, note_id],
+        )?;
         Ok(())
     }
 
-    /// Delete all uploaded audio for a note
-    #[allow(dead_code)]
-    pub fn delete_note_uploaded_audio(&self, note_id: &str) -> anyhow::Result<()> {
+    /// Store the language a note's transcript came out in
+    pub fn set_note_detected_language(&self, note_id: &str, language: &str) -> anyhow::Result<()> {
         let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
-        conn.execute("DELETE FROM uploaded_audio WHERE note_id = ?1", [note_id])?;
+        conn.execute(
+            "UPDATE notes SET detected_language = ?1 WHERE id = ?2",
+            params![language, note_id],
+        )?;
         Ok(())
     }
 
-    // ========== Audio Ordering ==========
-
+    /// Ended notes with audio and a transcript, ended before `before`. Notes whose audio
+    /// was purged since they were last recorded, or that were never transcribed, are left
+    /// out.
+    pub fn get_notes_with_audio_ended_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<String>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM notes
+             WHERE ended_at IS NOT NULL AND ended_at < ?1
+               AND (audio_purged_at IS NULL OR audio_purged_at < ended_at)
+               AND EXISTS (SELECT 1 FROM transcript_segments t WHERE t.note_id = notes.id)
+               AND (audio_path IS NOT NULL
+                    OR EXISTS (SELECT 1 FROM audio_segments s WHERE s.note_id = notes.id)
+                    OR EXISTS (SELECT 1 FROM uploaded_audio u WHERE u.note_id = notes.id))
+             ORDER BY ended_at ASC",
+        )?;
+        let notes = stmt
+            .query_map([before.to_rfc3339()], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(notes)
+    }
+
+    /// Record that a note's audio files are gone: its recording paths are cleared and the
+    /// time noted. Segments and uploads stay listed, so the transcript keeps its timeline.
+    pub fn mark_note_audio_purged(&self, note_id: &str) -> anyhow::Result<()> {
+        let _span = tracing::info_span!("db.transaction", op = "mark_note_audio_purged").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE notes SET audio_path = NULL, audio_purged_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), note_id],
+        )?;
+        tx.execute(
+            "UPDATE audio_segments SET mic_path = NULL, system_path = NULL WHERE note_id = ?1",
+            [note_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// When each note's audio was purged, for notes whose audio was
+    pub fn get_audio_purged_notes(&self) -> anyhow::Result<HashMap<String, DateTime<Utc>>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT id, audio_purged_at FROM notes WHERE audio_purged_at IS NOT NULL",
+        )?;
+        let purged = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?.parse().unwrap_or_else(|_| Utc::now()),
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(purged)
+    }
+
+    /// Point every stored reference to the file at `old_path` (recordings, segments and
+    /// uploads) at `new_path`. Returns the number of rows changed.
+    pub fn relocate_file(&self, old_path: &str, new_path: &str) -> anyhow::Result<usize> {
+        let _span = tracing::info_span!("db.transaction", op = "relocate_file").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        let mut changed = tx.execute(
+            "UPDATE notes SET audio_path = ?2 WHERE audio_path = ?1",
+            params![old_path, new_path],
+        )?;
+        changed += tx.execute(
+            "UPDATE audio_segments SET mic_path = ?2 WHERE mic_path = ?1",
+            params![old_path, new_path],
+        )?;
+        changed += tx.execute(
+            "UPDATE audio_segments SET system_path = ?2 WHERE system_path = ?1",
+            params![old_path, new_path],
+        )?;
+        changed += tx.execute(
+            "UPDATE uploaded_audio SET file_path = ?2 WHERE file_path = ?1",
+            params![old_path, new_path],
+        )?;
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Rewrite every stored file path that starts with `old_prefix` to start with
+    /// `new_prefix` instead. Returns the number of rows changed.
+    pub fn relocate_path_prefix(
+        &self,
+        old_prefix: &str,
+        new_prefix: &str,
+    ) -> anyhow::Result<usize> {
+        let _span = tracing::info_span!("db.transaction", op = "relocate_path_prefix").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        let mut changed = 0;
+        for (table, column) in [
+            ("notes", "audio_path"),
+            ("audio_segments", "mic_path"),
+            ("audio_segments", "system_path"),
+            ("uploaded_audio", "file_path"),
+        ] {
+            changed += tx.execute(
+                &format!(
+                    "UPDATE {table} SET {column} = ?2 || substr({column}, length(?1) + 1)
+                     WHERE substr({column}, 1, length(?1)) = ?1"
+                ),
+                params![old_prefix, new_prefix],
+            )?;
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Replace `from` with `to` in every note body that contains it, without touching
+    /// updated_at (used when attachments move). Returns the number of notes changed.
+    pub fn replace_in_descriptions(&self, from: &str, to: &str) -> anyhow::Result<usize> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let changed = conn.execute(
+            "UPDATE notes SET description = replace(description, ?1, ?2)
+             WHERE instr(description, ?1) > 0",
+            params![from, to],
+        )?;
+        Ok(changed)
+    }
+
+    /// Delete all audio segments for a note
+    pub fn delete_audio_segments(&self, note_id: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "DELETE FROM audio_segments WHERE note_id = ?1",
+            [note_id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete one audio segment
+    pub fn delete_audio_segment(&self, id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM audio_segments WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Move a segment on the note's timeline and set its length, after its files were cut
+    pub fn update_segment_span(
+        &self,
+        segment_id: i64,
+        start_offset_ms: i64,
+        duration_ms: i64,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE audio_segments SET start_offset_ms = ?1, duration_ms = ?2 WHERE id = ?3",
+            params![start_offset_ms, duration_ms, segment_id],
+        )?;
+        Ok(())
+    }
+
+    /// Split a segment `at_ms` into it, after its files were: the segment keeps the audio
+    /// before, and a new one right after it in the list gets the rest at `mic_path` and
+    /// `system_path`, along with the transcript from that point on. Returns the new
+    /// segment's id.
+    pub fn split_audio_segment(
+        &self,
+        segment_id: i64,
+        at_ms: i64,
+        mic_path: Option<&str>,
+        system_path: Option<&str>,
+    ) -> anyhow::Result<i64> {
+        let _span = tracing::info_span!("db.transaction", op = "split_audio_segment").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        let (note_id, start_offset_ms, duration_ms, display_order) = tx.query_row(
+            "SELECT note_id, start_offset_ms, duration_ms, display_order
+             FROM audio_segments WHERE id = ?1",
+            [segment_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, i32>(3)?,
+                ))
+            },
+        )?;
+        let segment_index: i32 = tx.query_row(
+            "SELECT COALESCE(MAX(segment_index), -1) + 1 FROM audio_segments WHERE note_id = ?1",
+            [&note_id],
+            |row| row.get(0),
+        )?;
+
+        // Make room in the list, which segments and uploads share
+        for table in ["audio_segments", "uploaded_audio"] {
+            tx.execute(
+                &format!(
+                    "UPDATE {table} SET display_order = display_order + 1
+                     WHERE note_id = ?1 AND display_order > ?2"
+                ),
+                params![note_id, display_order],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO audio_segments (note_id, segment_index, mic_path, system_path,
+                 start_offset_ms, duration_ms, display_order, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                note_id,
+                segment_index,
+                mic_path,
+                system_path,
+                start_offset_ms + at_ms,
+                duration_ms.map(|d| (d - at_ms).max(0)),
+                display_order + 1,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        let new_id = tx.last_insert_rowid();
+        tx.execute(
+            "UPDATE audio_segments SET duration_ms = ?1 WHERE id = ?2",
+            params![at_ms, segment_id],
+        )?;
+
+        // Transcript times are from the start of the segment's files
+        let at = at_ms as f64 / 1000.0;
+        tx.execute(
+            "UPDATE transcript_segments
+             SET source_id = ?1, start_time = start_time - ?3, end_time = end_time - ?3
+             WHERE source_type = 'segment' AND source_id = ?2 AND start_time >= ?3",
+            params![new_id, segment_id, at],
+        )?;
+        tx.execute(
+            "UPDATE transcript_segments SET end_time = MIN(end_time, ?2)
+             WHERE source_type = 'segment' AND source_id = ?1",
+            params![segment_id, at],
+        )?;
+        clear_playback_path(&tx, &note_id)?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+
+    /// Cut transcript segments to `from..to` seconds and shift what's left to start at
+    /// `from`. `source` picks the segments of one recording or upload (`("segment", id)`);
+    /// None picks those timed on the note's timeline (live and older transcripts).
+    /// Returns the number of segments deleted.
+    pub fn cut_transcript_segments(
+        &self,
+        note_id: &str,
+        source: Option<(&str, i64)>,
+        from: f64,
+        to: f64,
+    ) -> anyhow::Result<usize> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let (source_type, source_id) = source.unzip();
+        let scope = "note_id = ?1 AND (CASE WHEN ?2 IS NULL
+                         THEN source_type IS NULL OR source_type = 'live'
+                         ELSE source_type = ?2 AND source_id = ?3 END)";
+        let deleted = conn.execute(
+            &format!(
+                "DELETE FROM transcript_segments
+                 WHERE {scope} AND (end_time <= ?4 OR start_time >= ?5)"
+            ),
+            params![note_id, source_type, source_id, from, to],
+        )?;
+        conn.execute(
+            &format!(
+                "UPDATE transcript_segments
+                 SET start_time = MAX(start_time, ?4) - ?4, end_time = MIN(end_time, ?5) - ?4
+                 WHERE {scope}"
+            ),
+            params![note_id, source_type, source_id, from, to],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Drop a note's markers outside `from_ms..to_ms` and shift the rest to start at
+    /// `from_ms`. The note's merged playback file no longer matches its segments, so its
+    /// `audio_path` is cleared too.
+    pub fn cut_markers(&self, note_id: &str, from_ms: i64, to_ms: i64) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM markers WHERE note_id = ?1 AND (position_ms < ?2 OR position_ms > ?3)",
+            params![note_id, from_ms, to_ms],
+        )?;
+        tx.execute(
+            "UPDATE markers SET position_ms = position_ms - ?2 WHERE note_id = ?1",
+            params![note_id, from_ms],
+        )?;
+        clear_playback_path(&tx, note_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the latest (most recent) segment for a note
+    #[allow(dead_code)]
+    pub fn get_latest_segment(&self, note_id: &str) -> anyhow::Result<Option<AudioSegment>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let segment = conn
+            .query_row(
+                "SELECT id, note_id, segment_index, mic_path, system_path, start_offset_ms, duration_ms, display_order, created_at
+                 FROM audio_segments
+                 WHERE note_id = ?1
+                 ORDER BY segment_index DESC
+                 LIMIT 1",
+                [note_id],
+                |row| {
+                    Ok(AudioSegment {
+                        id: row.get(0)?,
+                        note_id: row.get(1)?,
+                        segment_index: row.get(2)?,
+                        mic_path: row.get(3)?,
+                        system_path: row.get(4)?,
+                        start_offset_ms: row.get(5)?,
+                        duration_ms: row.get(6)?,
+                        display_order: row.get(7)?,
+                        created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+                    })
+                },
+            )
+            .ok();
+
+        Ok(segment)
+    }
+
+    /// Get an audio segment by ID
+    pub fn get_audio_segment_by_id(&self, id: i64) -> anyhow::Result<AudioSegment> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        conn.query_row(
+            "SELECT id, note_id, segment_index, mic_path, system_path, start_offset_ms, duration_ms, display_order, created_at
+             FROM audio_segments WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(AudioSegment {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    segment_index: row.get(2)?,
+                    mic_path: row.get(3)?,
+                    system_path: row.get(4)?,
+                    start_offset_ms: row.get(5)?,
+                    duration_ms: row.get(6)?,
+                    display_order: row.get(7)?,
+                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Audio segment not found: {}", e))
+    }
+
+    // ========== Recording Markers ==========
+
+    /// Add a marker `position_ms` into the note's audio
+    pub fn add_marker(
+        &self,
+        note_id: &str,
+        position_ms: i64,
+        label: Option<&str>,
+    ) -> anyhow::Result<RecordingMarker> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let now = Utc::now();
+        conn.execute(
+            "INSERT INTO markers (note_id, position_ms, label, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![note_id, position_ms, label, now.to_rfc3339()],
+        )?;
+        Ok(RecordingMarker {
+            id: conn.last_insert_rowid(),
+            note_id: note_id.to_string(),
+            position_ms,
+            label: label.map(str::to_string),
+            created_at: now,
+        })
+    }
+
+    /// A note's markers in audio order
+    pub fn get_markers(&self, note_id: &str) -> anyhow::Result<Vec<RecordingMarker>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, position_ms, label, created_at FROM markers
+             WHERE note_id = ?1 ORDER BY position_ms ASC, id ASC",
+        )?;
+        let markers = stmt
+            .query_map([note_id], |row| {
+                Ok(RecordingMarker {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    position_ms: row.get(2)?,
+                    label: row.get(3)?,
+                    created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(markers)
+    }
+
+    /// Delete a marker; false if there was none
+    pub fn delete_marker(&self, id: i64) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(conn.execute("DELETE FROM markers WHERE id = ?1", [id])? > 0)
+    }
+
+    // ========== Uploaded Audio ==========
+
+    /// Add an uploaded audio file record
+    pub fn add_uploaded_audio(
+        &self,
+        note_id: &str,
+        file_path: &str,
+        original_filename: &str,
+        duration_ms: Option<i64>,
+        speaker_label: &str,
+    ) -> anyhow::Result<i64> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let now = Utc::now();
+
+        // Get the next display_order for this note (across both segments and uploads)
+        let max_segment_order: Option<i32> = conn
+            .query_row(
+                "SELECT MAX(display_order) FROM audio_segments WHERE note_id = ?1",
+                [note_id],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+        let max_upload_order: Option<i32> = conn
+            .query_row(
+                "SELECT MAX(display_order) FROM uploaded_audio WHERE note_id = ?1",
+                [note_id],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+        let display_order = std::cmp::max(
+            max_segment_order.unwrap_or(-1),
+            max_upload_order.unwrap_or(-1),
+        ) + 1;
+
+        conn.execute(
+            "INSERT INTO uploaded_audio (note_id, file_path, original_filename, duration_ms, speaker_label, display_order, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![note_id, file_path, original_filename, duration_ms, speaker_label, display_order, now.to_rfc3339()],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Get all uploaded audio for a note, ordered by display_order
+    pub fn get_uploaded_audio(&self, note_id: &str) -> anyhow::Result<Vec<UploadedAudio>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, file_path, original_filename, duration_ms, speaker_label, transcription_status, display_order, created_at
+             FROM uploaded_audio
+             WHERE note_id = ?1
+             ORDER BY display_order ASC",
+        )?;
+
+        let uploads = stmt
+            .query_map([note_id], |row| {
+                Ok(UploadedAudio {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    file_path: row.get(2)?,
+                    original_filename: row.get(3)?,
+                    duration_ms: row.get(4)?,
+                    speaker_label: row.get(5)?,
+                    transcription_status: row.get(6)?,
+                    display_order: row.get(7)?,
+                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(uploads)
+    }
+
+    /// Get uploaded audio by ID
+    pub fn get_uploaded_audio_by_id(&self, id: i64) -> anyhow::Result<UploadedAudio> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        conn.query_row(
+            "SELECT id, note_id, file_path, original_filename, duration_ms, speaker_label, transcription_status, display_order, created_at
+             FROM uploaded_audio WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(UploadedAudio {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    file_path: row.get(2)?,
+                    original_filename: row.get(3)?,
+                    duration_ms: row.get(4)?,
+                    speaker_label: row.get(5)?,
+                    transcription_status: row.get(6)?,
+                    display_order: row.get(7)?,
+                    created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Uploaded audio not found: {}", e))
+    }
+
+    /// Update transcription status for uploaded audio
+    pub fn update_uploaded_audio_status(&self, id: i64, status: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE uploaded_audio SET transcription_status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+
+    /// Update speaker label for uploaded audio
+    pub fn update_uploaded_audio_speaker(&self, id: i64, speaker_label: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE uploaded_audio SET speaker_label = ?1 WHERE id = ?2",
+            params![speaker_label, id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete uploaded audio by ID
+    pub fn delete_uploaded_audio(&self, id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM uploaded_audio WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Delete all uploaded audio for a note
+    #[allow(dead_code)]
+    pub fn delete_note_uploaded_audio(&self, note_id: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM uploaded_audio WHERE note_id = ?1", [note_id])?;
+        Ok(())
+    }
+
+    // ========== Audio Ordering ==========
+
     /// Reorder audio items for a note. Takes a list of (type, id, new_order) tuples
     /// where type is "segment" or "upload"
     pub fn reorder_audio_items(
         &self,
-        items: &[(String, i64, i32)], // (item_type, id, new_order)
+        items: &[(String, i64, i32)], // (item_type, id, new_order)
+    ) -> anyhow::Result<()> {
+        let _span = tracing::info_span!("db.transaction", op = "reorder_audio_items").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+
+        for (item_type, id, new_order) in items {
+            match item_type.as_str() {
+                "segment" => {
+                    tx.execute(
+                        "UPDATE audio_segments SET display_order = ?1 WHERE id = ?2",
+                        params![new_order, id],
+                    )?;
+                }
+                "upload" => {
+                    tx.execute(
+                        "UPDATE uploaded_audio SET display_order = ?1 WHERE id = ?2",
+                        params![new_order, id],
+                    )?;
+                }
+                _ => {}
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // ========== Speakers ==========
+
+    /// Keep a voice diarization heard in a note's transcript, whose segments carry `label`
+    pub fn add_speaker_embedding(
+        &self,
+        note_id: &str,
+        label: &str,
+        embedding: &[f32],
+        speaker_id: Option<i64>,
+        source_type: Option<&str>,
+        source_id: Option<i64>,
+    ) -> anyhow::Result<i64> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT INTO speaker_embeddings
+                 (note_id, label, embedding, speaker_id, source_type, source_id, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                note_id,
+                label,
+                serde_json::to_string(embedding)?,
+                speaker_id,
+                source_type,
+                source_id,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The voices heard in a note's transcripts
+    pub fn get_speaker_embeddings(&self, note_id: &str) -> anyhow::Result<Vec<SpeakerEmbedding>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SPEAKER_EMBEDDING_COLS} FROM speaker_embeddings
+             WHERE note_id = ?1 ORDER BY id ASC"
+        ))?;
+        let embeddings = stmt
+            .query_map([note_id], Self::map_speaker_embedding)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(embeddings)
+    }
+
+    /// Forget the voices of one transcript of a note, before it is transcribed again
+    pub fn delete_speaker_embeddings_by_source(
+        &self,
+        note_id: &str,
+        source_type: Option<&str>,
+        source_id: Option<i64>,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "DELETE FROM speaker_embeddings
+             WHERE note_id = ?1 AND source_type IS ?2 AND source_id IS ?3",
+            params![note_id, source_type, source_id],
+        )?;
+        Ok(())
+    }
+
+    /// All voice profiles, by name
+    pub fn list_speakers(&self) -> anyhow::Result<Vec<Speaker>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SPEAKER_COLS} FROM speakers ORDER BY name COLLATE NOCASE"
+        ))?;
+        let speakers = stmt
+            .query_map([], Self::map_speaker)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(speakers)
+    }
+
+    /// Name the voice `embedding_id` after the profile `name`, created if there is none: its
+    /// transcript segments take the name, and its print joins the profile's mean unless the
+    /// voice was already named after it
+    pub fn label_speaker(&self, embedding_id: i64, name: &str) -> anyhow::Result<Speaker> {
+        let _span = tracing::info_span!("db.transaction", op = "label_speaker").entered();
+        let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let tx = conn.transaction()?;
+
+        let voice = tx
+            .query_row(
+                &format!("SELECT {SPEAKER_EMBEDDING_COLS} FROM speaker_embeddings WHERE id = ?1"),
+                [embedding_id],
+                Self::map_speaker_embedding,
+            )
+            .map_err(|e| anyhow::anyhow!("Voice not found: {}", e))?;
+        let now = Utc::now().to_rfc3339();
+        let existing = tx
+            .query_row(
+                &format!("SELECT {SPEAKER_COLS} FROM speakers WHERE name = ?1"),
+                [name],
+                Self::map_speaker,
+            )
+            .ok();
+        let speaker_id = match existing {
+            Some(speaker) if voice.speaker_id == Some(speaker.id) => speaker.id,
+            Some(speaker) => {
+                let count = speaker.sample_count as f32;
+                let mean: Vec<f32> = speaker
+                    .embedding
+                    .iter()
+                    .zip(&voice.embedding)
+                    .map(|(m, v)| (m * count + v) / (count + 1.0))
+                    .collect();
+                tx.execute(
+                    "UPDATE speakers
+                     SET embedding = ?1, sample_count = sample_count + 1, updated_at = ?2
+                     WHERE id = ?3",
+                    params![serde_json::to_string(&mean)?, now, speaker.id],
+                )?;
+                speaker.id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO speakers (name, embedding, sample_count, created_at, updated_at)
+                     VALUES (?1, ?2, 1, ?3, ?3)",
+                    params![name, serde_json::to_string(&voice.embedding)?, now],
+                )?;
+                tx.last_insert_rowid()
+            }
+        };
+
+        tx.execute(
+            "UPDATE transcript_segments SET speaker = ?1
+             WHERE note_id = ?2 AND speaker = ?3 AND source_type IS ?4 AND source_id IS ?5",
+            params![name, voice.note_id, voice.label, voice.source_type, voice.source_id],
+        )?;
+        tx.execute(
+            "UPDATE speaker_embeddings SET label = ?1, speaker_id = ?2 WHERE id = ?3",
+            params![name, speaker_id, embedding_id],
+        )?;
+        let speaker = tx.query_row(
+            &format!("SELECT {SPEAKER_COLS} FROM speakers WHERE id = ?1"),
+            [speaker_id],
+            Self::map_speaker,
+        )?;
+        tx.commit()?;
+        Ok(speaker)
+    }
+
+    /// Delete a voice profile; the voices named after it keep their names. False if there
+    /// was none.
+    pub fn delete_speaker(&self, id: i64) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(conn.execute("DELETE FROM speakers WHERE id = ?1", [id])? > 0)
+    }
+
+    fn map_speaker(row: &rusqlite::Row) -> rusqlite::Result<Speaker> {
+        Ok(Speaker {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            embedding: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+            sample_count: row.get(3)?,
+            created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    fn map_speaker_embedding(row: &rusqlite::Row) -> rusqlite::Result<SpeakerEmbedding> {
+        Ok(SpeakerEmbedding {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            label: row.get(2)?,
+            embedding: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+            speaker_id: row.get(4)?,
+            source_type: row.get(5)?,
+            source_id: row.get(6)?,
+            created_at: row.get::<_, String>(7)?.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    // ========== Webhooks ==========
+
+    /// Create a webhook subscribed to `events`. Its signing secret goes in the keychain
+    /// (see `webhooks::store_secret`).
+    pub fn create_webhook(
+        &self,
+        url: &str,
+        events: &[String],
+        payload_template: Option<&str>,
+    ) -> anyhow::Result<Webhook> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT INTO webhooks (url, events, enabled, created_at, payload_template)
+             VALUES (?1, ?2, 1, ?3, ?4)",
+            params![url, events.join(","), Utc::now().to_rfc3339(), payload_template],
+        )?;
+        let id = conn.last_insert_rowid();
+        Ok(conn.query_row(
+            &format!("SELECT {WEBHOOK_COLS} FROM webhooks WHERE id = ?1"),
+            [id],
+            Self::map_webhook,
+        )?)
+    }
+
+    /// List all configured webhooks
+    pub fn list_webhooks(&self) -> anyhow::Result<Vec<Webhook>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt =
+            conn.prepare(&format!("SELECT {WEBHOOK_COLS} FROM webhooks ORDER BY created_at ASC"))?;
+        let hooks = stmt
+            .query_map([], Self::map_webhook)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(hooks)
+    }
+
+    pub fn get_webhook(&self, id: i64) -> anyhow::Result<Webhook> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.query_row(
+            &format!("SELECT {WEBHOOK_COLS} FROM webhooks WHERE id = ?1"),
+            [id],
+            Self::map_webhook,
+        )
+        .map_err(|e| anyhow::anyhow!("Webhook not found: {}", e))
+    }
+
+    /// Enabled webhooks subscribed to `event`
+    pub fn get_webhooks_for_event(&self, event: &str) -> anyhow::Result<Vec<Webhook>> {
+        Ok(self
+            .list_webhooks()?
+            .into_iter()
+            .filter(|hook| hook.enabled && hook.events.iter().any(|e| e == event))
+            .collect())
+    }
+
+    /// Update a webhook's configuration
+    pub fn update_webhook(
+        &self,
+        id: i64,
+        url: &str,
+        events: &[String],
+        enabled: bool,
+        payload_template: Option<&str>,
+    ) -> anyhow::Result<Webhook> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE webhooks SET url = ?1, events = ?2, enabled = ?3, payload_template = ?4
+             WHERE id = ?5",
+            params![url, events.join(","), enabled, payload_template, id],
+        )?;
+        conn.query_row(
+            &format!("SELECT {WEBHOOK_COLS} FROM webhooks WHERE id = ?1"),
+            [id],
+            Self::map_webhook,
+        )
+        .map_err(|e| anyhow::anyhow!("Webhook not found: {}", e))
+    }
+
+    /// Delete a webhook (its delivery log is removed by CASCADE)
+    pub fn delete_webhook(&self, id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM webhooks WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Signing secrets earlier versions kept in the webhooks table, by webhook id
+    pub fn get_stored_webhook_secrets(&self) -> anyhow::Result<Vec<(i64, String)>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(
+            "SELECT id, secret FROM webhooks WHERE secret IS NOT NULL AND secret != ''",
+        )?;
+        let secrets = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(secrets)
+    }
+
+    /// Drop a webhook's signing secret from the table once it is in the keychain
+    pub fn clear_stored_webhook_secret(&self, id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("UPDATE webhooks SET secret = NULL WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn map_webhook(row: &rusqlite::Row) -> rusqlite::Result<Webhook> {
+        let events: String = row.get(2)?;
+        Ok(Webhook {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            events: events
+                .split(',')
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect(),
+            has_secret: false,
+            enabled: row.get(3)?,
+            created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+            payload_template: row.get(5)?,
+        })
+    }
+
+    /// Log a new (not yet attempted) delivery and return its id. Only the webhook's last
+    /// `MAX_WEBHOOK_DELIVERIES` deliveries are kept.
+    pub fn add_webhook_delivery(
+        &self,
+        webhook_id: i64,
+        event: &str,
+        payload: &str,
+    ) -> anyhow::Result<i64> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT INTO webhook_deliveries (webhook_id, event, payload, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![webhook_id, event, payload, Utc::now().to_rfc3339()],
+        )?;
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE webhook_id = ?1 AND id NOT IN (
+                 SELECT id FROM webhook_deliveries WHERE webhook_id = ?1
+                 ORDER BY created_at DESC, id DESC LIMIT ?2
+             )",
+            params![webhook_id, MAX_WEBHOOK_DELIVERIES],
+        )?;
+        Ok(id)
+    }
+
+    /// Record the outcome of the latest delivery attempt
+    pub fn update_webhook_delivery(
+        &self,
+        id: i64,
+        status_code: Option<i64>,
+        success: bool,
+        attempts: i64,
+        error: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE webhook_deliveries SET status_code = ?1, success = ?2, attempts = ?3, error = ?4 WHERE id = ?5",
+            params![status_code, success, attempts, error, id],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent deliveries for a webhook, newest first
+    pub fn get_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        limit: i64,
+    ) -> anyhow::Result<Vec<WebhookDelivery>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {WEBHOOK_DELIVERY_COLS} FROM webhook_deliveries
+             WHERE webhook_id = ?1
+             ORDER BY created_at DESC, id DESC LIMIT ?2"
+        ))?;
+        let deliveries = stmt
+            .query_map(params![webhook_id, limit], Self::map_webhook_delivery)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(deliveries)
+    }
+
+    pub fn get_webhook_delivery(&self, id: i64) -> anyhow::Result<WebhookDelivery> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.query_row(
+            &format!("SELECT {WEBHOOK_DELIVERY_COLS} FROM webhook_deliveries WHERE id = ?1"),
+            [id],
+            Self::map_webhook_delivery,
+        )
+        .map_err(|e| anyhow::anyhow!("Webhook delivery not found: {}", e))
+    }
+
+    fn map_webhook_delivery(row: &rusqlite::Row) -> rusqlite::Result<WebhookDelivery> {
+        Ok(WebhookDelivery {
+            id: row.get(0)?,
+            webhook_id: row.get(1)?,
+            event: row.get(2)?,
+            payload: row.get(3)?,
+            status_code: row.get(4)?,
+            success: row.get(5)?,
+            attempts: row.get(6)?,
+            error: row.get(7)?,
+            created_at: row.get::<_, String>(8)?.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    // ========== Jobs ==========
+
+    /// Record a newly queued job
+    pub fn insert_job(
+        &self,
+        kind: &str,
+        label: &str,
+        note_id: Option<&str>,
+        priority: i64,
+    ) -> anyhow::Result<Job> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let now = Utc::now();
+        conn.execute(
+            "INSERT INTO jobs (kind, label, note_id, priority, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, 'queued', ?5)",
+            params![kind, label, note_id, priority, now.to_rfc3339()],
+        )?;
+        Ok(Job {
+            id: conn.last_insert_rowid(),
+            kind: kind.to_string(),
+            label: label.to_string(),
+            note_id: note_id.map(str::to_string),
+            priority,
+            status: "queued".to_string(),
+            progress: None,
+            error: None,
+            created_at: now,
+            started_at: None,
+            finished_at: None,
+        })
+    }
+
+    /// Mark a job running, or finished with `status` (and `error` for failures)
+    pub fn update_job_status(
+        &self,
+        id: i64,
+        status: &str,
+        error: Option<&str>,
     ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let now = Utc::now().to_rfc3339();
+        if status == "running" {
+            conn.execute(
+                "UPDATE jobs SET status = ?1, started_at = ?2 WHERE id = ?3",
+                params![status, now, id],
+            )?;
+        } else {
+            conn.execute(
+                "UPDATE jobs SET status = ?1, error = ?2, finished_at = ?3 WHERE id = ?4",
+                params![status, error, now, id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The most recently finished jobs, newest first
+    pub fn get_finished_jobs(&self, limit: i64) -> anyhow::Result<Vec<Job>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {JOB_COLS} FROM jobs
+             WHERE status NOT IN ('queued', 'running')
+             ORDER BY finished_at DESC, id DESC LIMIT ?1"
+        ))?;
+        let jobs = stmt
+            .query_map([limit], Self::map_job)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(jobs)
+    }
+
+    /// At startup: mark jobs the last session never finished as interrupted, and forget
+    /// finished jobs older than `keep_since`. Returns the number interrupted.
+    pub fn close_stale_jobs(&self, keep_since: DateTime<Utc>) -> anyhow::Result<usize> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let interrupted = conn.execute(
+            "UPDATE jobs SET status = 'interrupted', finished_at = ?1
+             WHERE status IN ('queued', 'running')",
+            [Utc::now().to_rfc3339()],
+        )?;
+        conn.execute(
+            "DELETE FROM jobs WHERE finished_at < ?1",
+            [keep_since.to_rfc3339()],
+        )?;
+        Ok(interrupted)
+    }
+
+    fn map_job(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+        let parse = |s: Option<String>| s.and_then(|s| s.parse().ok());
+        Ok(Job {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            label: row.get(2)?,
+            note_id: row.get(3)?,
+            priority: row.get(4)?,
+            status: row.get(5)?,
+            progress: None,
+            error: row.get(6)?,
+            created_at: row.get::<_, String>(7)?.parse().unwrap_or_else(|_| Utc::now()),
+            started_at: parse(row.get(8)?),
+            finished_at: parse(row.get(9)?),
+        })
+    }
+
+    // ========== Calendar Events ==========
+
+    /// Insert or update a calendar event by `uid`, creating its note stub on first import.
+    /// The stub's title/time/participants follow the event until the note is ended.
+    /// Returns true if the event was newly created.
+    #[allow(clippy::too_many_arguments)]
+    pub fn upsert_calendar_event(
+        &self,
+        uid: &str,
+        title: &str,
+        starts_at: DateTime<Utc>,
+        ends_at: Option<DateTime<Utc>>,
+        location: Option<&str>,
+        participants: Option<&str>,
+        description: Option<&str>,
+        source: &str,
+        auto_record: bool,
+    ) -> anyhow::Result<bool> {
+        let _span = tracing::info_span!("db.transaction", op = "upsert_calendar_event").entered();
         let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
         let tx = conn.transaction()?;
+        let now = Utc::now().to_rfc3339();
 
-        for (item_type, id, new_order) in items {
-            match item_type.as_str() {
-                "segment" => {
-                    tx.execute(
-                        "UPDATE audio_segments SET display_order = ?1 WHERE id = ?2",
-                        params![new_order, id],
-                    )?;
-                }
-                "upload" => {
+        let existing: Option<(i64, Option<String>)> = tx
+            .query_row(
+                "SELECT id, note_id FROM calendar_events WHERE uid = ?1",
+                [uid],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .ok();
+
+        let created = match existing {
+            Some((id, note_id)) => {
+                tx.execute(
+                    "UPDATE calendar_events SET title = ?1, starts_at = ?2, ends_at = ?3, location = ?4, updated_at = ?5
+                     WHERE id = ?6",
+                    params![title, starts_at.to_rfc3339(), ends_at.map(|e| e.to_rfc3339()), location, now, id],
+                )?;
+                if let Some(note_id) = note_id {
                     tx.execute(
-                        "UPDATE uploaded_audio SET display_order = ?1 WHERE id = ?2",
-                        params![new_order, id],
+                        "UPDATE notes SET title = ?1, started_at = ?2, participants = ?3, updated_at = ?4
+                         WHERE id = ?5 AND ended_at IS NULL",
+                        params![title, starts_at.to_rfc3339(), participants, now, note_id],
                     )?;
                 }
-                _ => {}
+                false
             }
-        }
+            None => {
+                let note_id = uuid::Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO notes (id, title, description, participants, started_at, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                    params![note_id, title, description, participants, starts_at.to_rfc3339(), now],
+                )?;
+                tx.execute(
+                    "INSERT INTO calendar_events (uid, note_id, title, starts_at, ends_at, location, source, auto_record, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+                    params![
+                        uid,
+                        note_id,
+                        title,
+                        starts_at.to_rfc3339(),
+                        ends_at.map(|e| e.to_rfc3339()),
+                        location,
+                        source,
+                        auto_record,
+                        now
+                    ],
+                )?;
+                true
+            }
+        };
 
         tx.commit()?;
+        Ok(created)
+    }
+
+    /// Note stub linked to a calendar event, by event `uid`
+    pub fn get_calendar_event_note_id(&self, uid: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let note_id = conn
+            .query_row(
+                "SELECT note_id FROM calendar_events WHERE uid = ?1",
+                [uid],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+        Ok(note_id)
+    }
+
+    /// Upcoming (not yet ended) calendar events, soonest first
+    pub fn list_upcoming_calendar_events(&self) -> anyhow::Result<Vec<CalendarEvent>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {CALENDAR_EVENT_COLS} FROM calendar_events
+             WHERE COALESCE(ends_at, starts_at) >= ?1
+             ORDER BY starts_at ASC"
+        ))?;
+        let events = stmt
+            .query_map([Utc::now().to_rfc3339()], Self::map_calendar_event)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(events)
+    }
+
+    /// Turn auto-record on or off for an event (re-arms the trigger when enabled)
+    pub fn set_calendar_event_auto_record(&self, id: i64, auto_record: bool) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE calendar_events SET auto_record = ?1, record_triggered = 0, updated_at = ?2 WHERE id = ?3",
+            params![auto_record, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Claim auto-record events starting within `lead_secs` of `now` (and not yet over).
+    /// Each event is returned once as (event id, note id, title).
+    pub fn take_due_scheduled_recordings(
+        &self,
+        now: DateTime<Utc>,
+        lead_secs: i64,
+    ) -> anyhow::Result<Vec<(i64, String, String)>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let horizon = (now + chrono::Duration::seconds(lead_secs)).to_rfc3339();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, title FROM calendar_events
+             WHERE auto_record = 1 AND record_triggered = 0 AND note_id IS NOT NULL
+               AND starts_at <= ?1 AND COALESCE(ends_at, starts_at) >= ?2",
+        )?;
+        let due: Vec<(i64, String, String)> = stmt
+            .query_map(params![horizon, now.to_rfc3339()], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for (id, _, _) in &due {
+            conn.execute(
+                "UPDATE calendar_events SET record_triggered = 1 WHERE id = ?1",
+                [id],
+            )?;
+        }
+        Ok(due)
+    }
+
+    fn map_calendar_event(row: &rusqlite::Row) -> rusqlite::Result<CalendarEvent> {
+        Ok(CalendarEvent {
+            id: row.get(0)?,
+            uid: row.get(1)?,
+            note_id: row.get(2)?,
+            title: row.get(3)?,
+            starts_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+            ends_at: row
+                .get::<_, Option<String>>(5)?
+                .and_then(|s| s.parse().ok()),
+            location: row.get(6)?,
+            source: row.get(7)?,
+            auto_record: row.get(8)?,
+            record_triggered: row.get(9)?,
+        })
+    }
+
+    // ========== Auto Export ==========
+
+    /// File a note was last auto-exported to
+    pub fn get_auto_export_path(&self, note_id: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let path = conn
+            .query_row(
+                "SELECT path FROM auto_exports WHERE note_id = ?1",
+                [note_id],
+                |row| row.get(0),
+            )
+            .ok();
+        Ok(path)
+    }
+
+    pub fn set_auto_export_path(&self, note_id: &str, path: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT INTO auto_exports (note_id, path, exported_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(note_id) DO UPDATE SET path = excluded.path, exported_at = excluded.exported_at",
+            params![note_id, path, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    // ========== Imports ==========
+
+    /// Whether an import source (e.g. "otter:/path/file.txt") already produced a note
+    pub fn is_source_imported(&self, source: &str) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM imported_sources WHERE source = ?1",
+            [source],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn record_imported_source(&self, source: &str, note_id: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO imported_sources (source, note_id, imported_at) VALUES (?1, ?2, ?3)",
+            params![source, note_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    // ========== Export Templates ==========
+
+    pub fn list_export_templates(&self) -> anyhow::Result<Vec<ExportTemplate>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {EXPORT_TEMPLATE_COLS} FROM export_templates ORDER BY name COLLATE NOCASE"
+        ))?;
+        let templates = stmt
+            .query_map([], Self::map_export_template)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(templates)
+    }
+
+    pub fn get_export_template(&self, id: i64) -> anyhow::Result<ExportTemplate> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.query_row(
+            &format!("SELECT {EXPORT_TEMPLATE_COLS} FROM export_templates WHERE id = ?1"),
+            [id],
+            Self::map_export_template,
+        )
+        .map_err(|e| anyhow::anyhow!("Export template not found: {}", e))
+    }
+
+    pub fn create_export_template(&self, name: &str, content: &str) -> anyhow::Result<ExportTemplate> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO export_templates (name, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![name, content, now],
+        )?;
+        let id = conn.last_insert_rowid();
+        Ok(conn.query_row(
+            &format!("SELECT {EXPORT_TEMPLATE_COLS} FROM export_templates WHERE id = ?1"),
+            [id],
+            Self::map_export_template,
+        )?)
+    }
+
+    pub fn update_export_template(
+        &self,
+        id: i64,
+        name: &str,
+        content: &str,
+    ) -> anyhow::Result<ExportTemplate> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "UPDATE export_templates SET name = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
+            params![name, content, Utc::now().to_rfc3339(), id],
+        )?;
+        conn.query_row(
+            &format!("SELECT {EXPORT_TEMPLATE_COLS} FROM export_templates WHERE id = ?1"),
+            [id],
+            Self::map_export_template,
+        )
+        .map_err(|e| anyhow::anyhow!("Export template not found: {}", e))
+    }
+
+    pub fn delete_export_template(&self, id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM export_templates WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn map_export_template(row: &rusqlite::Row) -> rusqlite::Result<ExportTemplate> {
+        Ok(ExportTemplate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            content: row.get(2)?,
+            created_at: row.get::<_, String>(3)?.parse().unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    // ========== Shortcut Bindings ==========
+
+    /// Stored shortcut overrides, id -> accelerator (empty when disabled)
+    pub fn get_shortcut_bindings(&self) -> anyhow::Result<HashMap<String, String>> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut stmt = conn.prepare("SELECT id, accelerator FROM shortcut_bindings")?;
+        let bindings = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(bindings)
+    }
+
+    pub fn set_shortcut_binding(&self, id: &str, accelerator: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO shortcut_bindings (id, accelerator) VALUES (?1, ?2)",
+            params![id, accelerator],
+        )?;
+        Ok(())
+    }
+
+    /// Go back to the default binding
+    pub fn delete_shortcut_binding(&self, id: &str) -> anyhow::Result<()> {
+        let conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
+        conn.execute("DELETE FROM shortcut_bindings WHERE id = ?1", [id])?;
         Ok(())
     }
 
@@ -811,6 +2346,7 @@ impl Database {
         note_id: &str,
         duration_ms: Option<i64>,
     ) -> anyhow::Result<Option<AudioSegment>> {
+        let _span = tracing::info_span!("db.transaction", op = "migrate_legacy_audio").entered();
         let mut conn = self.conn.lock().map_err(|e| anyhow::anyhow!("{}", e))?;
 
         // Check if note has audio_path
@@ -891,3 +2427,99 @@ fn get_db_path(app_handle: &AppHandle) -> anyhow::Result<PathBuf> {
 
     Ok(app_data_dir.join("note67.db"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_test_db() -> Database {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        run_migrations(&conn).unwrap();
+        Database {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    #[test]
+    fn test_audio_edits_clear_playback_path() {
+        let db = open_test_db();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO notes (id, title, started_at, created_at, updated_at)
+                 VALUES ('n1', 'Standup', ?1, ?1, ?1)",
+                ["2026-01-05T10:00:00+00:00"],
+            )
+            .unwrap();
+        let set_playback_path = |db: &Database| {
+            db.conn
+                .lock()
+                .unwrap()
+                .execute(
+                    "UPDATE notes SET audio_path = '/recordings/n1.wav' WHERE id = 'n1'",
+                    [],
+                )
+                .unwrap();
+        };
+        let playback_path = |db: &Database| -> Option<String> {
+            db.conn
+                .lock()
+                .unwrap()
+                .query_row("SELECT audio_path FROM notes WHERE id = 'n1'", [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        set_playback_path(&db);
+        let segment = db
+            .add_audio_segment("n1", 0, Some("/recordings/n1_mic_seg0.wav"), None, 0)
+            .unwrap();
+        db.update_segment_span(segment, 0, 60_000).unwrap();
+        db.split_audio_segment(segment, 30_000, Some("/recordings/n1_mic_seg1.wav"), None)
+            .unwrap();
+        assert_eq!(playback_path(&db), None);
+
+        set_playback_path(&db);
+        db.cut_markers("n1", 1_000, 20_000).unwrap();
+        assert_eq!(playback_path(&db), None);
+        assert_eq!(db.get_audio_segments("n1").unwrap().len(), 2);
+    }
+    #[test]
+    fn test_webhook_deliveries_are_capped() {
+        let db = open_test_db();
+        let events = ["note.ended".to_string()];
+        let hook = db.create_webhook("https://example.com/hook", &events, None).unwrap();
+        let other = db.create_webhook("https://example.com/other", &events, None).unwrap();
+        db.add_webhook_delivery(other.id, "note.ended", "{}").unwrap();
+
+        let first = db.add_webhook_delivery(hook.id, "note.ended", "{}").unwrap();
+        for _ in 0..MAX_WEBHOOK_DELIVERIES {
+            db.add_webhook_delivery(hook.id, "note.ended", "{}").unwrap();
+        }
+        let kept = db.get_webhook_deliveries(hook.id, 1000).unwrap();
+        assert_eq!(kept.len() as i64, MAX_WEBHOOK_DELIVERIES);
+        assert!(kept.iter().all(|d| d.id != first));
+        // Other webhooks keep their own log
+        assert_eq!(db.get_webhook_deliveries(other.id, 1000).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_stored_webhook_secrets() {
+        let db = open_test_db();
+        let events = ["note.ended".to_string()];
+        let hook = db.create_webhook("https://example.com/hook", &events, None).unwrap();
+        db.create_webhook("https://example.com/other", &events, None).unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE webhooks SET secret = 'shh' WHERE id = ?1", [hook.id])
+            .unwrap();
+
+        assert_eq!(db.get_stored_webhook_secrets().unwrap(), vec![(hook.id, "shh".to_string())]);
+        db.clear_stored_webhook_secret(hook.id).unwrap();
+        assert!(db.get_stored_webhook_secrets().unwrap().is_empty());
+    }
+}
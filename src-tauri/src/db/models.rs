@@ -25,6 +25,18 @@ pub struct TranscriptSegment {
     pub source_type: Option<String>, // 'upload', 'segment', 'live', or null for legacy
     pub source_id: Option<i64>,      // ID of the source audio
     pub created_at: DateTime<Utc>,
+    /// The language spoken, when `text` is its translation into English
+    pub original_language: Option<String>,
+    /// Its words, timed; empty for segments transcribed before words were timed
+    pub words: Vec<Word>,
+}
+
+/// A word of a transcript segment and when it was spoken (seconds, as the segment's)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub start_time: f64,
+    pub end_time: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +146,47 @@ pub struct AudioSegment {
     pub created_at: DateTime<Utc>,
 }
 
+/// A bookmark dropped while recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingMarker {
+    pub id: i64,
+    pub note_id: String,
+    /// Milliseconds into the note's audio
+    pub position_ms: i64,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A named voice, recognised in transcripts by its print
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Speaker {
+    pub id: i64,
+    pub name: String,
+    /// Mean print of the voices named after it
+    #[serde(skip)]
+    pub embedding: Vec<f32>,
+    /// Voices named after it so far
+    pub sample_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A voice diarization heard in a note's transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerEmbedding {
+    pub id: i64,
+    pub note_id: String,
+    /// The speaker its transcript segments carry: `Speaker N`, or a name once known
+    pub label: String,
+    #[serde(skip)]
+    pub embedding: Vec<f32>,
+    /// The profile it was named after or matched to
+    pub speaker_id: Option<i64>,
+    pub source_type: Option<String>,
+    pub source_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct NewAudioSegment {
@@ -157,3 +210,75 @@ pub struct UploadedAudio {
     pub display_order: i32,
     pub created_at: DateTime<Utc>,
 }
+
+/// A background job (see `jobs.rs`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    /// "transcription", "conversion", "ai", "export" or "backup"
+    pub kind: String,
+    pub label: String,
+    pub note_id: Option<String>,
+    /// Higher runs first among queued jobs of the same kind
+    pub priority: i64,
+    pub status: String, // "queued", "running", "completed", "failed", "cancelled", "interrupted"
+    /// 0.0-1.0 while running, when the job reports it (not stored)
+    pub progress: Option<f32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// An outgoing webhook subscribed to one or more app events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    /// Whether requests are signed; the secret itself stays in the keychain
+    pub has_secret: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    /// Shapes the request body (see `webhooks::render_payload`); None sends the standard envelope
+    pub payload_template: Option<String>,
+}
+
+/// One logged webhook delivery (updated in place as retries happen)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub payload: String,
+    pub status_code: Option<i64>,
+    pub success: bool,
+    pub attempts: i64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A calendar event imported from ICS, linked to its note stub
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub id: i64,
+    pub uid: String,
+    pub note_id: Option<String>,
+    pub title: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub location: Option<String>,
+    pub source: String,
+    pub auto_record: bool,
+    pub record_triggered: bool,
+}
+
+/// A user-defined export template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
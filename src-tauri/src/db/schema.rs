@@ -4,6 +4,10 @@ use rusqlite::Connection;
 pub const SCHEMA_VERSION: i32 = 10;
 
 pub fn run_migrations(conn: &Connection) -> rusqlite::Result<()> {
+    // One transaction for all pending steps: a single sync instead of one per statement,
+    // which is what made upgrades of large databases slow to start
+    let tx = conn.unchecked_transaction()?;
+    let conn = &*tx;
     let version = get_schema_version(conn)?;
 
     if version < 1 {
@@ -45,8 +49,59 @@ pub fn run_migrations(conn: &Connection) -> rusqlite::Result<()> {
     if version < 13 {
         migrate_v13(conn)?;
     }
+    if version < 14 {
+        migrate_v14(conn)?;
+    }
+    if version < 15 {
+        migrate_v15(conn)?;
+    }
+    if version < 16 {
+        migrate_v16(conn)?;
+    }
+    if version < 17 {
+        migrate_v17(conn)?;
+    }
+    if version < 18 {
+        migrate_v18(conn)?;
+    }
+    if version < 19 {
+        migrate_v19(conn)?;
+    }
+    if version < 20 {
+        migrate_v20(conn)?;
+    }
+    if version < 21 {
+        migrate_v21(conn)?;
+    }
+    if version < 22 {
+        migrate_v22(conn)?;
+    }
+    if version < 23 {
+        migrate_v23(conn)?;
+    }
+    if version < 24 {
+        migrate_v24(conn)?;
+    }
+    if version < 25 {
+        migrate_v25(conn)?;
+    }
+    if version < 26 {
+        migrate_v26(conn)?;
+    }
+    if version < 27 {
+        migrate_v27(conn)?;
+    }
+    if version < 28 {
+        migrate_v28(conn)?;
+    }
+    if version < 29 {
+        migrate_v29(conn)?;
+    }
+    if version < 30 {
+        migrate_v30(conn)?;
+    }
 
-    Ok(())
+    tx.commit()
 }
 
 fn get_schema_version(conn: &Connection) -> rusqlite::Result<i32> {
@@ -398,8 +453,7 @@ fn migrate_v10(conn: &Connection) -> rusqlite::Result<()> {
     // Make audio_segments.mic_path nullable to support listen-only (system-audio-only) recordings.
     // SQLite cannot drop NOT NULL in place, so recreate the table.
     conn.execute_batch(
-        "BEGIN;
-         CREATE TABLE audio_segments_new (
+        "CREATE TABLE audio_segments_new (
              id INTEGER PRIMARY KEY AUTOINCREMENT,
              note_id TEXT NOT NULL,
              segment_index INTEGER NOT NULL,
@@ -417,8 +471,7 @@ fn migrate_v10(conn: &Connection) -> rusqlite::Result<()> {
          FROM audio_segments;
          DROP TABLE audio_segments;
          ALTER TABLE audio_segments_new RENAME TO audio_segments;
-         CREATE INDEX IF NOT EXISTS idx_audio_segments_note ON audio_segments(note_id);
-         COMMIT;",
+         CREATE INDEX IF NOT EXISTS idx_audio_segments_note ON audio_segments(note_id);",
     )?;
 
     set_schema_version(conn, 10)?;
@@ -473,8 +526,7 @@ fn migrate_v13(conn: &Connection) -> rusqlite::Result<()> {
     // Allow standalone tasks (note_id NULL) so the central Tasks page can add
     // tasks not tied to any note. SQLite can't drop NOT NULL in place; recreate.
     conn.execute_batch(
-        "BEGIN;
-         CREATE TABLE action_items_new (
+        "CREATE TABLE action_items_new (
              id INTEGER PRIMARY KEY AUTOINCREMENT,
              note_id TEXT,
              stable_id TEXT NOT NULL,
@@ -497,11 +549,351 @@ fn migrate_v13(conn: &Connection) -> rusqlite::Result<()> {
          ALTER TABLE action_items_new RENAME TO action_items;
          CREATE INDEX IF NOT EXISTS idx_action_items_note ON action_items(note_id);
          CREATE INDEX IF NOT EXISTS idx_action_items_open ON action_items(done, due_date);
-         CREATE INDEX IF NOT EXISTS idx_action_items_parent ON action_items(parent_id);
-         COMMIT;",
+         CREATE INDEX IF NOT EXISTS idx_action_items_parent ON action_items(parent_id);",
     )?;
 
     set_schema_version(conn, 13)?;
 
     Ok(())
 }
+
+fn migrate_v14(conn: &Connection) -> rusqlite::Result<()> {
+    // Outgoing webhooks. `events` is a comma-separated list of event names
+    // ("note.ended", ...). Every delivery (including retries) is logged so
+    // users can see why an integration didn't fire.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS webhooks (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             url TEXT NOT NULL,
+             events TEXT NOT NULL,
+             secret TEXT,
+             enabled INTEGER NOT NULL DEFAULT 1,
+             created_at TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS webhook_deliveries (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             webhook_id INTEGER NOT NULL,
+             event TEXT NOT NULL,
+             payload TEXT NOT NULL,
+             status_code INTEGER,
+             success INTEGER NOT NULL DEFAULT 0,
+             attempts INTEGER NOT NULL DEFAULT 0,
+             error TEXT,
+             created_at TEXT NOT NULL,
+             FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE
+         );
+         CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook
+             ON webhook_deliveries(webhook_id, created_at);",
+    )?;
+
+    set_schema_version(conn, 14)?;
+
+    Ok(())
+}
+
+fn migrate_v15(conn: &Connection) -> rusqlite::Result<()> {
+    // Calendar events imported from ICS files/feeds. Each event gets a note stub;
+    // `uid` keeps re-imports idempotent. `auto_record` events are picked up by the
+    // recording scheduler, which sets `record_triggered` so each fires once.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS calendar_events (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             uid TEXT NOT NULL UNIQUE,
+             note_id TEXT,
+             title TEXT NOT NULL,
+             starts_at TEXT NOT NULL,
+             ends_at TEXT,
+             location TEXT,
+             source TEXT NOT NULL,
+             auto_record INTEGER NOT NULL DEFAULT 0,
+             record_triggered INTEGER NOT NULL DEFAULT 0,
+             created_at TEXT NOT NULL,
+             updated_at TEXT NOT NULL,
+             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE SET NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_calendar_events_starts ON calendar_events(starts_at);",
+    )?;
+
+    set_schema_version(conn, 15)?;
+
+    Ok(())
+}
+
+fn migrate_v16(conn: &Connection) -> rusqlite::Result<()> {
+    // Remember which file each note was auto-exported to, so re-exports overwrite the
+    // note's own file (or clean it up after a rename) and never another note's.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS auto_exports (
+             note_id TEXT PRIMARY KEY,
+             path TEXT NOT NULL,
+             exported_at TEXT NOT NULL,
+             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+         );",
+    )?;
+
+    set_schema_version(conn, 16)?;
+
+    Ok(())
+}
+
+fn migrate_v17(conn: &Connection) -> rusqlite::Result<()> {
+    // Track files brought in by the importers so running an import again skips them.
+    // Deleting the note frees the source to be imported again.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS imported_sources (
+             source TEXT PRIMARY KEY,
+             note_id TEXT NOT NULL,
+             imported_at TEXT NOT NULL,
+             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+         );",
+    )?;
+
+    set_schema_version(conn, 17)?;
+
+    Ok(())
+}
+
+fn migrate_v18(conn: &Connection) -> rusqlite::Result<()> {
+    // User-defined export templates (see `templates.rs` for the syntax)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS export_templates (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             name TEXT NOT NULL UNIQUE,
+             content TEXT NOT NULL,
+             created_at TEXT NOT NULL,
+             updated_at TEXT NOT NULL
+         );",
+    )?;
+
+    set_schema_version(conn, 18)?;
+
+    Ok(())
+}
+
+fn migrate_v19(conn: &Connection) -> rusqlite::Result<()> {
+    // Optional per-webhook payload template; NULL sends the standard
+    // {event, timestamp, data} envelope
+    conn.execute(
+        "ALTER TABLE webhooks ADD COLUMN payload_template TEXT",
+        [],
+    )?;
+
+    set_schema_version(conn, 19)?;
+
+    Ok(())
+}
+
+fn migrate_v20(conn: &Connection) -> rusqlite::Result<()> {
+    // Keyboard shortcut overrides (see `shortcuts.rs`); an empty accelerator disables the
+    // shortcut, a missing row means the default
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS shortcut_bindings (
+             id TEXT PRIMARY KEY,
+             accelerator TEXT NOT NULL
+         );",
+    )?;
+
+    // Global hotkeys used to be a JSON object of action -> accelerator in settings
+    let legacy: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'global_shortcuts'",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(legacy) = legacy {
+        let bindings: std::collections::HashMap<String, String> =
+            serde_json::from_str(&legacy).unwrap_or_default();
+        for (action, accelerator) in bindings {
+            conn.execute(
+                "INSERT OR REPLACE INTO shortcut_bindings (id, accelerator) VALUES (?1, ?2)",
+                [format!("global.{}", action), accelerator],
+            )?;
+        }
+        conn.execute("DELETE FROM settings WHERE key = 'global_shortcuts'", [])?;
+    }
+
+    set_schema_version(conn, 20)?;
+
+    Ok(())
+}
+
+fn migrate_v21(conn: &Connection) -> rusqlite::Result<()> {
+    // Full-text index over transcript text, keyed by segment id. Triggers keep it current
+    // as segments change; segments written before this version are indexed afterwards by
+    // the `backfill_transcript_index` command (in batches, through
+    // `Database::index_transcript_segments`), so the upgrade itself stays fast. The index
+    // keeps its own copy of the text so removing a not-yet-indexed segment is harmless.
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS transcript_fts USING fts5(
+             text,
+             note_id UNINDEXED
+         );
+         CREATE TRIGGER IF NOT EXISTS transcript_segments_ai AFTER INSERT ON transcript_segments BEGIN
+             INSERT INTO transcript_fts(rowid, text, note_id) VALUES (NEW.id, NEW.text, NEW.note_id);
+         END;
+         CREATE TRIGGER IF NOT EXISTS transcript_segments_ad AFTER DELETE ON transcript_segments BEGIN
+             DELETE FROM transcript_fts WHERE rowid = OLD.id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS transcript_segments_au
+         AFTER UPDATE OF text, note_id ON transcript_segments BEGIN
+             DELETE FROM transcript_fts WHERE rowid = OLD.id;
+             INSERT INTO transcript_fts(rowid, text, note_id) VALUES (NEW.id, NEW.text, NEW.note_id);
+         END;",
+    )?;
+
+    set_schema_version(conn, 21)?;
+
+    Ok(())
+}
+
+fn migrate_v22(conn: &Connection) -> rusqlite::Result<()> {
+    // Background jobs (see `jobs.rs`): queued and running jobs, then their history. No
+    // foreign key on note_id, so a job outlives the note it worked on.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS jobs (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             kind TEXT NOT NULL,
+             label TEXT NOT NULL,
+             note_id TEXT,
+             priority INTEGER NOT NULL DEFAULT 1,
+             status TEXT NOT NULL,
+             error TEXT,
+             created_at TEXT NOT NULL,
+             started_at TEXT,
+             finished_at TEXT
+         );
+         CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);",
+    )?;
+
+    set_schema_version(conn, 22)?;
+
+    Ok(())
+}
+
+fn migrate_v23(conn: &Connection) -> rusqlite::Result<()> {
+    // Per-note noise suppression: 1 on, 0 off, NULL follows the global setting
+    conn.execute(
+        "ALTER TABLE notes ADD COLUMN noise_suppression INTEGER",
+        [],
+    )?;
+
+    set_schema_version(conn, 23)?;
+
+    Ok(())
+}
+
+fn migrate_v24(conn: &Connection) -> rusqlite::Result<()> {
+    // Bookmarks dropped while recording, at a position in note time (the same timeline as
+    // the audio segments' start_offset_ms)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS markers (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             note_id TEXT NOT NULL,
+             position_ms INTEGER NOT NULL,
+             label TEXT,
+             created_at TEXT NOT NULL,
+             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+         );
+         CREATE INDEX IF NOT EXISTS idx_markers_note ON markers(note_id);",
+    )?;
+
+    set_schema_version(conn, 24)?;
+
+    Ok(())
+}
+
+fn migrate_v25(conn: &Connection) -> rusqlite::Result<()> {
+    // When a note's audio files were deleted to free space (its transcript is kept)
+    conn.execute("ALTER TABLE notes ADD COLUMN audio_purged_at TEXT", [])?;
+
+    set_schema_version(conn, 25)?;
+
+    Ok(())
+}
+
+fn migrate_v26(conn: &Connection) -> rusqlite::Result<()> {
+    // The recording quality preset a note was first recorded with, and the rates it gives
+    // (mic_sample_rate NULL when the mic is recorded at the device's own rate)
+    conn.execute_batch(
+        "ALTER TABLE notes ADD COLUMN recording_quality TEXT;
+         ALTER TABLE notes ADD COLUMN mic_sample_rate INTEGER;
+         ALTER TABLE notes ADD COLUMN system_sample_rate INTEGER;",
+    )?;
+
+    set_schema_version(conn, 26)?;
+
+    Ok(())
+}
+
+fn migrate_v27(conn: &Connection) -> rusqlite::Result<()> {
+    // Named voice profiles, and the voices diarization heard in each transcript. A voice's
+    // embedding is its print as a JSON array; `label` is the speaker its segments carry,
+    // and the source columns match those of the transcript segments it came from.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS speakers (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             name TEXT NOT NULL UNIQUE,
+             embedding TEXT NOT NULL,
+             sample_count INTEGER NOT NULL DEFAULT 1,
+             created_at TEXT NOT NULL,
+             updated_at TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS speaker_embeddings (
+             id INTEGER PRIMARY KEY AUTOINCREMENT,
+             note_id TEXT NOT NULL,
+             label TEXT NOT NULL,
+             embedding TEXT NOT NULL,
+             speaker_id INTEGER,
+             source_type TEXT,
+             source_id INTEGER,
+             created_at TEXT NOT NULL,
+             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+             FOREIGN KEY (speaker_id) REFERENCES speakers(id) ON DELETE SET NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_speaker_embeddings_note ON speaker_embeddings(note_id);",
+    )?;
+
+    set_schema_version(conn, 27)?;
+
+    Ok(())
+}
+
+fn migrate_v28(conn: &Connection) -> rusqlite::Result<()> {
+    // A note's own transcription language ("auto" to detect; NULL follows the setting), and
+    // the language its transcripts came out in
+    conn.execute_batch(
+        "ALTER TABLE notes ADD COLUMN language TEXT;
+         ALTER TABLE notes ADD COLUMN detected_language TEXT;",
+    )?;
+
+    set_schema_version(conn, 28)?;
+
+    Ok(())
+}
+
+fn migrate_v29(conn: &Connection) -> rusqlite::Result<()> {
+    // Notes transcribed straight into English, and the language their segments were spoken
+    // in (NULL when the text is in the language spoken)
+    conn.execute_batch(
+        "ALTER TABLE notes ADD COLUMN translate INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE transcript_segments ADD COLUMN original_language TEXT;",
+    )?;
+
+    set_schema_version(conn, 29)?;
+
+    Ok(())
+}
+
+fn migrate_v30(conn: &Connection) -> rusqlite::Result<()> {
+    // The words of each segment with their timings, as a JSON array (NULL for segments
+    // transcribed before)
+    conn.execute(
+        "ALTER TABLE transcript_segments ADD COLUMN words TEXT",
+        [],
+    )?;
+
+    set_schema_version(conn, 30)?;
+
+    Ok(())
+}
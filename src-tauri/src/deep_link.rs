@@ -0,0 +1,305 @@
+//! `note67://` URL scheme handling
+//!
+//! Supported links:
+//! - `note67://note/{id}` opens a note
+//! - `note67://record?title=...` starts a new recording
+//! - `note67://search?q=...` runs a search
+//!
+//! macOS delivers links to the running app as `RunEvent::Opened`. On Windows and Linux
+//! the OS launches a new process with the link as an argument; that process forwards it
+//! over a loopback socket to the instance already running and exits.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Url};
+
+pub const SCHEME: &str = "note67";
+
+/// File in the app data dir holding the port of the running instance's listener
+const PORT_FILE: &str = "deep-link.port";
+
+/// A parsed `note67://` link, emitted to the frontend as "deep-link"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLink {
+    OpenNote { note_id: String },
+    /// Any web page can open links, so the frontend confirms before recording
+    Record { title: Option<String> },
+    Search { query: String },
+}
+
+/// The link the app was launched with, received before the frontend was listening;
+/// handed over by `take_pending_deep_link`
+#[derive(Default)]
+pub struct PendingDeepLink(pub Mutex<Option<DeepLink>>);
+
+/// Parse a `note67://` URL. Returns None for other schemes and unknown actions.
+pub fn parse(url: &str) -> Option<DeepLink> {
+    let url = Url::parse(url.trim()).ok()?;
+    if url.scheme() != SCHEME {
+        return None;
+    }
+
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    // In `note67://note/abc` the action is the host and the rest is the path
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    match url.host_str()? {
+        "note" => {
+            let note_id = segments
+                .first()
+                .map(|s| s.to_string())
+                .or_else(|| query("id"))?;
+            Some(DeepLink::OpenNote { note_id })
+        }
+        "record" => Some(DeepLink::Record {
+            title: query("title"),
+        }),
+        "search" => Some(DeepLink::Search {
+            query: query("q").or_else(|| query("query"))?,
+        }),
+        _ => None,
+    }
+}
+
+/// First `note67://` URL among the process arguments
+pub fn url_from_args(args: &[String]) -> Option<String> {
+    args.iter()
+        .find(|arg| arg.starts_with(&format!("{}://", SCHEME)))
+        .cloned()
+}
+
+/// Show the main window and hand the link to the frontend
+pub fn handle_url(app: &AppHandle, url: &str) {
+    let Some(link) = parse_and_show(app, url) else {
+        return;
+    };
+    let _ = app.emit("deep-link", &link);
+}
+
+/// Hold the link the app was launched with until the frontend asks for it
+pub fn set_launch_url(app: &AppHandle, url: &str) {
+    let Some(link) = parse_and_show(app, url) else {
+        return;
+    };
+    if let Some(pending) = app.try_state::<PendingDeepLink>()
+        && let Ok(mut pending) = pending.0.lock()
+    {
+        *pending = Some(link);
+    }
+}
+
+fn parse_and_show(app: &AppHandle, url: &str) -> Option<DeepLink> {
+    let Some(link) = parse(url) else {
+        tracing::warn!("Ignoring unsupported link: {}", url);
+        return None;
+    };
+    tracing::info!("Received {:?}", link);
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    Some(link)
+}
+
+/// Forward a link to an instance that's already running. Returns true if it was delivered,
+/// in which case this process should exit.
+pub fn forward_to_running_instance(app: &AppHandle, url: &str) -> bool {
+    let Some(port) = port_file(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|port| port.trim().parse::<u16>().ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut stream) = TcpStream::connect_timeout(
+        &(Ipv4Addr::LOCALHOST, port).into(),
+        Duration::from_millis(500),
+    ) else {
+        return false;
+    };
+    writeln!(stream, "{}", url).is_ok()
+}
+
+/// Accept links forwarded by later launches of the app
+pub fn start_listener(app: &AppHandle) {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!("Failed to start listener: {}", e);
+            return;
+        }
+    };
+    let port = match listener.local_addr() {
+        Ok(addr) => addr.port(),
+        Err(_) => return,
+    };
+    if let Some(path) = port_file(app)
+        && let Err(e) = fs::write(&path, port.to_string())
+    {
+        tracing::warn!("Failed to write {}: {}", path.display(), e);
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+            let mut line = String::new();
+            if BufReader::new(stream).read_line(&mut line).is_ok() {
+                let url = line.trim();
+                // Only links are accepted, nothing else is read from the socket
+                if url.starts_with(&format!("{}://", SCHEME)) {
+                    handle_url(&app, url);
+                }
+            }
+        }
+    });
+}
+
+/// Take the link the app was launched with, if any. Later links arrive as "deep-link" events.
+#[tauri::command]
+pub fn take_pending_deep_link(
+    pending: tauri::State<'_, PendingDeepLink>,
+) -> Result<Option<DeepLink>, String> {
+    let mut pending = pending.0.lock().map_err(|e| e.to_string())?;
+    Ok(pending.take())
+}
+
+fn port_file(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(PORT_FILE))
+}
+
+/// Register the app as the handler for `note67://` for the current user.
+/// macOS registers the scheme from Info.plist when the app is installed.
+#[cfg(target_os = "windows")]
+pub fn register_scheme() {
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    let key = format!(r"HKCU\Software\Classes\{}", SCHEME);
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    let entries = [
+        (key.clone(), None, "URL:Note67".to_string()),
+        (key.clone(), Some("URL Protocol"), String::new()),
+        (format!(r"{}\shell\open\command", key), None, command),
+    ];
+
+    for (key, name, value) in entries {
+        let mut cmd = std::process::Command::new("reg");
+        cmd.args(["add", &key, "/f"]);
+        match name {
+            Some(name) => cmd.args(["/v", name]),
+            None => cmd.arg("/ve"),
+        };
+        cmd.args(["/d", &value]);
+        if let Err(e) = crate::process::no_window(&mut cmd).output() {
+            tracing::warn!("Failed to register URL scheme: {}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn register_scheme() {
+    let (Ok(exe), Some(home)) = (std::env::current_exe(), std::env::var_os("HOME")) else {
+        return;
+    };
+    let desktop_name = "note67-url-handler.desktop";
+    let applications = PathBuf::from(home).join(".local/share/applications");
+    let desktop_path = applications.join(desktop_name);
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Note67\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe.display(),
+        SCHEME
+    );
+
+    // Skip the xdg-mime call when nothing changed since the last launch
+    if fs::read_to_string(&desktop_path).is_ok_and(|existing| existing == entry) {
+        return;
+    }
+    if let Err(e) = fs::create_dir_all(&applications).and_then(|_| fs::write(&desktop_path, entry))
+    {
+        tracing::warn!(
+            "Failed to write {}: {}",
+            desktop_path.display(),
+            e
+        );
+        return;
+    }
+    let _ = std::process::Command::new("xdg-mime")
+        .args([
+            "default",
+            desktop_name,
+            &format!("x-scheme-handler/{}", SCHEME),
+        ])
+        .output();
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn register_scheme() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("note67://note/3f1c-42"),
+            Some(DeepLink::OpenNote {
+                note_id: "3f1c-42".to_string()
+            })
+        );
+        assert_eq!(
+            parse("note67://record?title=Weekly%20sync"),
+            Some(DeepLink::Record {
+                title: Some("Weekly sync".to_string())
+            })
+        );
+        assert_eq!(
+            parse("note67://record"),
+            Some(DeepLink::Record { title: None })
+        );
+        assert_eq!(
+            parse("note67://search?q=budget+review"),
+            Some(DeepLink::Search {
+                query: "budget review".to_string()
+            })
+        );
+        assert_eq!(parse("note67://search"), None);
+        assert_eq!(parse("note67://note/"), None);
+        assert_eq!(parse("note67://delete/abc"), None);
+        assert_eq!(parse("https://note/abc"), None);
+    }
+
+    #[test]
+    fn test_url_from_args() {
+        let args = vec![
+            "note67".to_string(),
+            "--minimized".to_string(),
+            "note67://note/abc".to_string(),
+        ];
+        assert_eq!(url_from_args(&args).as_deref(), Some("note67://note/abc"));
+        assert_eq!(url_from_args(&args[..2]), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Do Not Disturb while recording, so notification sounds stay out of the system-audio
+//! capture. A monitor thread turns the OS mode on when a recording starts and restores it
+//! when the recording ends:
+//!   macOS: runs two user-made Shortcuts (Focus has no public API; the Shortcuts app's
+//!          "Set Focus" action is the supported way), named by settings
+//!   Windows: not supported; Focus Assist has no documented API
+//!   Linux: GNOME's notification banners setting, remembering the previous value
+//! What to restore is saved in the database before changing anything, so a crash mid
+//! recording is undone on the next launch.
+
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::audio::RecordingPhase;
+use crate::commands::AudioState;
+use crate::db::Database;
+
+/// "true" turns on Do Not Disturb while recording
+pub const SETTING_ENABLED: &str = "focus_mode_while_recording";
+/// macOS: Shortcuts run to turn Focus on and off
+pub const SETTING_SHORTCUT_ON: &str = "focus_mode_shortcut_on";
+pub const SETTING_SHORTCUT_OFF: &str = "focus_mode_shortcut_off";
+/// Internal: the state to restore, present while this module has DND turned on
+const SETTING_RESTORE: &str = "focus_mode_restore";
+
+pub const DEFAULT_SHORTCUT_ON: &str = "Note67 Focus On";
+pub const DEFAULT_SHORTCUT_OFF: &str = "Note67 Focus Off";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn setting(db: &Database, key: &str) -> Option<String> {
+    db.get_setting(key).ok().flatten()
+}
+
+/// Follow the recording phase; also undoes a change left behind by a previous session
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        restore(&app);
+
+        let mut active = false;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let Some(state) = app.try_state::<AudioState>() else {
+                continue;
+            };
+            let recording = state.recording.get_phase() != RecordingPhase::Idle;
+            if recording == active {
+                continue;
+            }
+            active = recording;
+            if recording {
+                enable(&app);
+            } else {
+                restore(&app);
+            }
+        }
+    });
+}
+
+fn enable(app: &AppHandle) {
+    let db = app.state::<Database>();
+    if setting(&db, SETTING_ENABLED).as_deref() != Some("true")
+        || setting(&db, SETTING_RESTORE).is_some()
+    {
+        return;
+    }
+    let previous = match platform::current(&db) {
+        Ok(previous) => previous,
+        Err(e) => {
+            tracing::warn!("Failed to read the Do Not Disturb state: {}", e);
+            return;
+        }
+    };
+    // Saved first: if we crash after turning DND on, the next launch turns it back off
+    if db.set_setting(SETTING_RESTORE, &previous).is_err() {
+        return;
+    }
+    match platform::enable(&db) {
+        Ok(()) => tracing::info!("Turned on Do Not Disturb for recording"),
+        Err(e) => {
+            tracing::warn!("Failed to turn on Do Not Disturb: {}", e);
+            let _ = db.delete_setting(SETTING_RESTORE);
+        }
+    }
+}
+
+/// Put back the state saved by `enable`, if any
+pub fn restore(app: &AppHandle) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+    let Some(previous) = setting(&db, SETTING_RESTORE) else {
+        return;
+    };
+    match platform::restore(&db, &previous) {
+        Ok(()) => tracing::info!("Restored Do Not Disturb after recording"),
+        Err(e) => tracing::warn!("Failed to restore Do Not Disturb: {}", e),
+    }
+    let _ = db.delete_setting(SETTING_RESTORE);
+}
+
+/// Whether Do Not Disturb can be controlled on this system
+#[tauri::command]
+pub fn is_focus_mode_supported() -> bool {
+    platform::supported()
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    use super::{
+        DEFAULT_SHORTCUT_OFF, DEFAULT_SHORTCUT_ON, SETTING_SHORTCUT_OFF, SETTING_SHORTCUT_ON,
+        setting,
+    };
+    use crate::db::Database;
+
+    pub fn supported() -> bool {
+        true
+    }
+
+    /// Focus can't be read, so there's nothing to remember
+    pub fn current(_db: &Database) -> Result<String, String> {
+        Ok(String::new())
+    }
+
+    pub fn enable(db: &Database) -> Result<(), String> {
+        let name = setting(db, SETTING_SHORTCUT_ON).unwrap_or(DEFAULT_SHORTCUT_ON.to_string());
+        run_shortcut(&name)
+    }
+
+    pub fn restore(db: &Database, _previous: &str) -> Result<(), String> {
+        let name = setting(db, SETTING_SHORTCUT_OFF).unwrap_or(DEFAULT_SHORTCUT_OFF.to_string());
+        run_shortcut(&name)
+    }
+
+    fn run_shortcut(name: &str) -> Result<(), String> {
+        let output = Command::new("shortcuts")
+            .args(["run", name])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Shortcut \"{}\" failed: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::db::Database;
+
+    const UNSUPPORTED: &str = "Focus Assist can't be controlled on Windows";
+
+    pub fn supported() -> bool {
+        false
+    }
+
+    pub fn current(_db: &Database) -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn enable(_db: &Database) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn restore(_db: &Database, _previous: &str) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use std::process::Command;
+
+    use crate::db::Database;
+
+    const SCHEMA: &str = "org.gnome.desktop.notifications";
+    const KEY: &str = "show-banners";
+
+    fn gsettings(args: &[&str]) -> Result<String, String> {
+        let output = Command::new("gsettings")
+            .args(args)
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn supported() -> bool {
+        gsettings(&["get", SCHEMA, KEY]).is_ok()
+    }
+
+    pub fn current(_db: &Database) -> Result<String, String> {
+        gsettings(&["get", SCHEMA, KEY])
+    }
+
+    pub fn enable(_db: &Database) -> Result<(), String> {
+        gsettings(&["set", SCHEMA, KEY, "false"]).map(|_| ())
+    }
+
+    pub fn restore(_db: &Database, previous: &str) -> Result<(), String> {
+        let value = if previous == "false" { "false" } else { "true" };
+        gsettings(&["set", SCHEMA, KEY, value]).map(|_| ())
+    }
+}
@@ -0,0 +1,751 @@
+//! System-wide hotkeys for recording control, registered with the OS so they fire while
+//! the window is hidden in the tray
+//! macOS: Carbon `RegisterEventHotKey`, Windows: `RegisterHotKey`, Linux: X11 key grabs
+//! (Wayland compositors don't allow global grabs, so the hotkeys are unavailable there)
+//!
+//! Recording is driven by the frontend, so a pressed hotkey is forwarded as a
+//! "global-shortcut" event; "new note and record" creates the note here first, and "add
+//! marker" adds the marker here so it lands on the moment the key was pressed.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands;
+use crate::db::Database;
+use crate::shortcuts;
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Actions whose hotkey the OS accepted
+static REGISTERED: Mutex<Vec<HotkeyAction>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    StartRecording,
+    StopRecording,
+    TogglePause,
+    NewNoteAndRecord,
+    AddMarker,
+}
+
+impl HotkeyAction {
+    /// The index of an action is its registration id
+    pub const ALL: [HotkeyAction; 5] = [
+        HotkeyAction::StartRecording,
+        HotkeyAction::StopRecording,
+        HotkeyAction::TogglePause,
+        HotkeyAction::NewNoteAndRecord,
+        HotkeyAction::AddMarker,
+    ];
+
+    /// The action's entry in `shortcuts::SHORTCUTS`
+    fn shortcut_id(self) -> &'static str {
+        match self {
+            HotkeyAction::StartRecording => "global.start_recording",
+            HotkeyAction::StopRecording => "global.stop_recording",
+            HotkeyAction::TogglePause => "global.toggle_pause",
+            HotkeyAction::NewNoteAndRecord => "global.new_note_and_record",
+            HotkeyAction::AddMarker => "global.add_marker",
+        }
+    }
+
+    fn id(self) -> u32 {
+        Self::ALL
+            .iter()
+            .position(|a| *a == self)
+            .unwrap_or_default() as u32
+    }
+}
+
+/// Event payload emitted as "global-shortcut"
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutEvent {
+    pub action: HotkeyAction,
+    /// The note created for `new_note_and_record`, or marked for `add_marker`
+    pub note_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// An ASCII letter (uppercase) or digit
+    Char(char),
+    /// F1-F12
+    F(u8),
+    Space,
+}
+
+/// A parsed accelerator such as "CmdOrCtrl+Shift+R"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accelerator {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Cmd on macOS, the Windows/Super key elsewhere
+    pub meta: bool,
+    pub key: Key,
+}
+
+impl Accelerator {
+    pub fn parse(accelerator: &str) -> Result<Self, String> {
+        let mut parsed = Accelerator {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: Key::Space,
+        };
+        let mut key = None;
+
+        for part in accelerator.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "alt" | "option" => parsed.alt = true,
+                "shift" => parsed.shift = true,
+                "cmd" | "command" | "super" | "meta" | "win" => parsed.meta = true,
+                "cmdorctrl" | "commandorcontrol" => {
+                    if cfg!(target_os = "macos") {
+                        parsed.meta = true;
+                    } else {
+                        parsed.ctrl = true;
+                    }
+                }
+                name if key.is_none() => key = Some(parse_key(name)?),
+                _ => {
+                    return Err(format!(
+                        "Invalid shortcut \"{}\": more than one key",
+                        accelerator
+                    ));
+                }
+            }
+        }
+
+        parsed.key = key.ok_or_else(|| format!("Invalid shortcut \"{}\": no key", accelerator))?;
+        // A global grab of a plain or shift-only key would swallow normal typing
+        if !(parsed.ctrl || parsed.alt || parsed.meta) {
+            return Err(format!(
+                "Invalid shortcut \"{}\": include Ctrl, Alt or Cmd",
+                accelerator
+            ));
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_key(name: &str) -> Result<Key, String> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => {
+            return Ok(Key::Char(c.to_ascii_uppercase()));
+        }
+        _ => {}
+    }
+    if name == "space" {
+        return Ok(Key::Space);
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok())
+        && (1..=12).contains(&n)
+    {
+        return Ok(Key::F(n));
+    }
+    Err(format!("Unsupported key: {}", name))
+}
+
+/// The bound accelerator of every action
+fn load_bindings(db: &Database) -> HashMap<HotkeyAction, Option<String>> {
+    let mut bindings = shortcuts::bindings(db);
+    HotkeyAction::ALL
+        .iter()
+        .map(|&action| (action, bindings.remove(action.shortcut_id()).flatten()))
+        .collect()
+}
+
+/// Whether the OS accepted the hotkey of a "global.*" shortcut
+pub fn is_registered(shortcut_id: &str) -> bool {
+    REGISTERED
+        .lock()
+        .map(|r| r.iter().any(|action| action.shortcut_id() == shortcut_id))
+        .unwrap_or(false)
+}
+
+/// Replace the OS registrations with `bindings`
+fn apply(app: &AppHandle, bindings: &HashMap<HotkeyAction, Option<String>>) {
+    let parsed: Vec<(u32, Accelerator)> = bindings
+        .iter()
+        .filter_map(|(action, accelerator)| {
+            let accelerator = accelerator.as_deref()?;
+            match Accelerator::parse(accelerator) {
+                Ok(parsed) => Some((action.id(), parsed)),
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let registered = platform::register(app, parsed);
+    if let Ok(mut current) = REGISTERED.lock() {
+        *current = registered
+            .into_iter()
+            .filter_map(|id| HotkeyAction::ALL.get(id as usize).copied())
+            .collect();
+    }
+}
+
+/// Register the stored hotkeys. Called during setup, and again after settings are imported.
+pub fn install(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+    let bindings = load_bindings(&app.state::<Database>());
+    apply(app, &bindings);
+}
+
+/// Called by the platform backends when a hotkey is pressed
+fn trigger(id: u32) {
+    let (Some(app), Some(&action)) = (APP.get(), HotkeyAction::ALL.get(id as usize)) else {
+        return;
+    };
+    let app = app.clone();
+    // Off the OS callback (the main thread on macOS): creating a note touches the DB
+    tauri::async_runtime::spawn(async move {
+        let note_id = match action {
+            HotkeyAction::NewNoteAndRecord => match commands::create_timestamped_note(&app) {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    tracing::warn!("Failed to create note: {}", e);
+                    return;
+                }
+            },
+            HotkeyAction::AddMarker => match commands::add_marker(&app, None, None) {
+                Ok(marker) => Some(marker.note_id),
+                Err(e) => {
+                    tracing::debug!("No marker added: {}", e);
+                    return;
+                }
+            },
+            _ => None,
+        };
+        let _ = app.emit("global-shortcut", ShortcutEvent { action, note_id });
+    });
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use tauri::AppHandle;
+
+    use super::{Accelerator, Key};
+
+    type OSStatus = i32;
+    type EventRef = *mut c_void;
+    type EventTargetRef = *mut c_void;
+    type EventHotKeyRef = *mut c_void;
+    type EventHandlerUPP = extern "C" fn(*mut c_void, EventRef, *mut c_void) -> OSStatus;
+
+    #[repr(C)]
+    struct EventTypeSpec {
+        event_class: u32,
+        event_kind: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct EventHotKeyID {
+        signature: u32,
+        id: u32,
+    }
+
+    #[link(name = "Carbon", kind = "framework")]
+    unsafe extern "C" {
+        fn GetApplicationEventTarget() -> EventTargetRef;
+        fn InstallEventHandler(
+            target: EventTargetRef,
+            handler: EventHandlerUPP,
+            num_types: u32,
+            list: *const EventTypeSpec,
+            user_data: *mut c_void,
+            out_ref: *mut *mut c_void,
+        ) -> OSStatus;
+        fn RegisterEventHotKey(
+            key_code: u32,
+            modifiers: u32,
+            id: EventHotKeyID,
+            target: EventTargetRef,
+            options: u32,
+            out_ref: *mut EventHotKeyRef,
+        ) -> OSStatus;
+        fn UnregisterEventHotKey(hot_key: EventHotKeyRef) -> OSStatus;
+        fn GetEventParameter(
+            event: EventRef,
+            name: u32,
+            desired_type: u32,
+            actual_type: *mut u32,
+            size: usize,
+            actual_size: *mut usize,
+            data: *mut c_void,
+        ) -> OSStatus;
+    }
+
+    const fn four_cc(code: &[u8; 4]) -> u32 {
+        u32::from_be_bytes(*code)
+    }
+
+    const SIGNATURE: u32 = four_cc(b"N67K");
+    const EVENT_CLASS_KEYBOARD: u32 = four_cc(b"keyb");
+    const EVENT_HOT_KEY_PRESSED: u32 = 5;
+    const PARAM_DIRECT_OBJECT: u32 = four_cc(b"----");
+    const TYPE_EVENT_HOT_KEY_ID: u32 = four_cc(b"hkid");
+
+    const CMD_KEY: u32 = 1 << 8;
+    const SHIFT_KEY: u32 = 1 << 9;
+    const OPTION_KEY: u32 = 1 << 11;
+    const CONTROL_KEY: u32 = 1 << 12;
+
+    /// Registered hot key refs (as usize so the static is Send) and whether the event
+    /// handler is installed. Only touched on the main thread.
+    static STATE: Mutex<(Vec<usize>, bool)> = Mutex::new((Vec::new(), false));
+
+    extern "C" fn on_hot_key(_: *mut c_void, event: EventRef, _: *mut c_void) -> OSStatus {
+        let mut id = EventHotKeyID::default();
+        let status = unsafe {
+            GetEventParameter(
+                event,
+                PARAM_DIRECT_OBJECT,
+                TYPE_EVENT_HOT_KEY_ID,
+                std::ptr::null_mut(),
+                std::mem::size_of::<EventHotKeyID>(),
+                std::ptr::null_mut(),
+                &mut id as *mut EventHotKeyID as *mut c_void,
+            )
+        };
+        if status == 0 && id.signature == SIGNATURE {
+            super::trigger(id.id);
+        }
+        0
+    }
+
+    /// Virtual key codes (Events.h, kVK_*)
+    fn key_code(key: Key) -> Option<u32> {
+        let code = match key {
+            Key::Space => 0x31,
+            Key::F(n) => [
+                0x7A, 0x78, 0x63, 0x76, 0x60, 0x61, 0x62, 0x64, 0x65, 0x6D, 0x67, 0x6F,
+            ][usize::from(n - 1)],
+            Key::Char(c) => match c {
+                'A' => 0x00,
+                'S' => 0x01,
+                'D' => 0x02,
+                'F' => 0x03,
+                'H' => 0x04,
+                'G' => 0x05,
+                'Z' => 0x06,
+                'X' => 0x07,
+                'C' => 0x08,
+                'V' => 0x09,
+                'B' => 0x0B,
+                'Q' => 0x0C,
+                'W' => 0x0D,
+                'E' => 0x0E,
+                'R' => 0x0F,
+                'Y' => 0x10,
+                'T' => 0x11,
+                '1' => 0x12,
+                '2' => 0x13,
+                '3' => 0x14,
+                '4' => 0x15,
+                '6' => 0x16,
+                '5' => 0x17,
+                '9' => 0x19,
+                '7' => 0x1A,
+                '8' => 0x1C,
+                '0' => 0x1D,
+                'O' => 0x1F,
+                'U' => 0x20,
+                'I' => 0x22,
+                'P' => 0x23,
+                'L' => 0x25,
+                'J' => 0x26,
+                'K' => 0x28,
+                'N' => 0x2D,
+                'M' => 0x2E,
+                _ => return None,
+            },
+        };
+        Some(code)
+    }
+
+    fn modifiers(accelerator: &Accelerator) -> u32 {
+        let mut modifiers = 0;
+        if accelerator.meta {
+            modifiers |= CMD_KEY;
+        }
+        if accelerator.shift {
+            modifiers |= SHIFT_KEY;
+        }
+        if accelerator.alt {
+            modifiers |= OPTION_KEY;
+        }
+        if accelerator.ctrl {
+            modifiers |= CONTROL_KEY;
+        }
+        modifiers
+    }
+
+    /// Carbon hot keys must be registered on the main thread. Returns the ids that were
+    /// registered.
+    pub fn register(app: &AppHandle, bindings: Vec<(u32, Accelerator)>) -> Vec<u32> {
+        let (tx, rx) = mpsc::channel();
+        // Runs inline when already on the main thread (during setup)
+        let scheduled = app.run_on_main_thread(move || {
+            let Ok(mut state) = STATE.lock() else {
+                return;
+            };
+            unsafe {
+                for hot_key in state.0.drain(..) {
+                    UnregisterEventHotKey(hot_key as EventHotKeyRef);
+                }
+                if !state.1 {
+                    let spec = EventTypeSpec {
+                        event_class: EVENT_CLASS_KEYBOARD,
+                        event_kind: EVENT_HOT_KEY_PRESSED,
+                    };
+                    state.1 = InstallEventHandler(
+                        GetApplicationEventTarget(),
+                        on_hot_key,
+                        1,
+                        &spec,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    ) == 0;
+                }
+
+                let mut registered = Vec::new();
+                for (id, accelerator) in bindings {
+                    let Some(code) = key_code(accelerator.key) else {
+                        continue;
+                    };
+                    let mut hot_key: EventHotKeyRef = std::ptr::null_mut();
+                    let status = RegisterEventHotKey(
+                        code,
+                        modifiers(&accelerator),
+                        EventHotKeyID {
+                            signature: SIGNATURE,
+                            id,
+                        },
+                        GetApplicationEventTarget(),
+                        0,
+                        &mut hot_key,
+                    );
+                    if status == 0 {
+                        state.0.push(hot_key as usize);
+                        registered.push(id);
+                    }
+                }
+                let _ = tx.send(registered);
+            }
+        });
+        if scheduled.is_err() {
+            return Vec::new();
+        }
+        rx.recv_timeout(Duration::from_secs(2)).unwrap_or_default()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+    use std::thread::JoinHandle;
+
+    use tauri::AppHandle;
+    use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN, RegisterHotKey, UnregisterHotKey,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetMessageW, MSG, PM_NOREMOVE, PeekMessageW, PostThreadMessageW, WM_HOTKEY, WM_QUIT,
+        WM_USER,
+    };
+
+    use super::{Accelerator, Key};
+
+    /// The thread that owns the registrations (hotkeys are delivered to the registering
+    /// thread's message queue)
+    static THREAD: Mutex<Option<(u32, JoinHandle<()>)>> = Mutex::new(None);
+
+    fn virtual_key(key: Key) -> u32 {
+        match key {
+            Key::Char(c) => c as u32,
+            Key::F(n) => 0x70 + u32::from(n - 1),
+            Key::Space => 0x20,
+        }
+    }
+
+    fn modifiers(accelerator: &Accelerator) -> u32 {
+        let mut modifiers = MOD_NOREPEAT;
+        if accelerator.ctrl {
+            modifiers |= MOD_CONTROL;
+        }
+        if accelerator.alt {
+            modifiers |= MOD_ALT;
+        }
+        if accelerator.shift {
+            modifiers |= MOD_SHIFT;
+        }
+        if accelerator.meta {
+            modifiers |= MOD_WIN;
+        }
+        modifiers
+    }
+
+    pub fn register(_app: &AppHandle, bindings: Vec<(u32, Accelerator)>) -> Vec<u32> {
+        let Ok(mut thread) = THREAD.lock() else {
+            return Vec::new();
+        };
+        if let Some((thread_id, handle)) = thread.take() {
+            unsafe { PostThreadMessageW(thread_id, WM_QUIT, 0, 0) };
+            let _ = handle.join();
+        }
+        if bindings.is_empty() {
+            return Vec::new();
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::spawn(move || unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            // Create the message queue before anyone can post WM_QUIT to it
+            PeekMessageW(
+                &mut msg,
+                std::ptr::null_mut(),
+                WM_USER,
+                WM_USER,
+                PM_NOREMOVE,
+            );
+
+            let registered: Vec<u32> = bindings
+                .iter()
+                .filter(|(id, accelerator)| {
+                    RegisterHotKey(
+                        std::ptr::null_mut(),
+                        *id as i32,
+                        modifiers(accelerator),
+                        virtual_key(accelerator.key),
+                    ) != 0
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            let _ = tx.send((GetCurrentThreadId(), registered.clone()));
+
+            while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+                if msg.message == WM_HOTKEY {
+                    super::trigger(msg.wParam as u32);
+                }
+            }
+            for id in registered {
+                UnregisterHotKey(std::ptr::null_mut(), id as i32);
+            }
+        });
+
+        match rx.recv() {
+            Ok((thread_id, registered)) => {
+                *thread = Some((thread_id, handle));
+                registered
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use tauri::AppHandle;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xproto::{ConnectionExt, GrabMode, ModMask};
+
+    use super::{Accelerator, Key};
+
+    /// The event thread and its stop flag
+    static THREAD: Mutex<Option<(Arc<AtomicBool>, JoinHandle<()>)>> = Mutex::new(None);
+
+    /// Caps Lock and Num Lock change the modifier state, so each hotkey is grabbed with
+    /// every combination of them
+    const LOCKS: [u16; 4] = [0, 1 << 1, 1 << 4, (1 << 1) | (1 << 4)];
+
+    fn keysym(key: Key) -> u32 {
+        match key {
+            Key::Char(c) => c.to_ascii_lowercase() as u32,
+            Key::F(n) => 0xFFBE + u32::from(n - 1),
+            Key::Space => 0x20,
+        }
+    }
+
+    fn modifiers(accelerator: &Accelerator) -> u16 {
+        let mut modifiers = 0;
+        if accelerator.shift {
+            modifiers |= u16::from(ModMask::SHIFT);
+        }
+        if accelerator.ctrl {
+            modifiers |= u16::from(ModMask::CONTROL);
+        }
+        if accelerator.alt {
+            modifiers |= u16::from(ModMask::M1);
+        }
+        if accelerator.meta {
+            modifiers |= u16::from(ModMask::M4);
+        }
+        modifiers
+    }
+
+    pub fn register(_app: &AppHandle, bindings: Vec<(u32, Accelerator)>) -> Vec<u32> {
+        let Ok(mut thread) = THREAD.lock() else {
+            return Vec::new();
+        };
+        if let Some((stop, handle)) = thread.take() {
+            stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+        if bindings.is_empty() {
+            return Vec::new();
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                if let Err(e) = run(&bindings, &tx, &stop) {
+                    tracing::warn!("X11 hotkeys unavailable: {}", e);
+                    let _ = tx.send(Vec::new());
+                }
+            }
+        });
+
+        let registered = rx.recv().unwrap_or_default();
+        *thread = Some((stop, handle));
+        registered
+    }
+
+    fn run(
+        bindings: &[(u32, Accelerator)],
+        tx: &mpsc::Sender<Vec<u32>>,
+        stop: &AtomicBool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let min_keycode = conn.setup().min_keycode;
+        let max_keycode = conn.setup().max_keycode;
+        let mapping = conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?
+            .reply()?;
+        let per_keycode = usize::from(mapping.keysyms_per_keycode.max(1));
+        let keycode = |keysym: u32| {
+            mapping
+                .keysyms
+                .chunks(per_keycode)
+                .position(|syms| syms.contains(&keysym))
+                .map(|i| min_keycode + i as u8)
+        };
+
+        let mut grabs: HashMap<(u8, u16), u32> = HashMap::new();
+        let mut registered = Vec::new();
+        for (id, accelerator) in bindings {
+            let Some(code) = keycode(keysym(accelerator.key)) else {
+                continue;
+            };
+            let modifiers = modifiers(accelerator);
+            let grabbed = LOCKS.iter().all(|locks| {
+                conn.grab_key(
+                    false,
+                    root,
+                    ModMask::from(modifiers | locks),
+                    code,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                )
+                .map(|cookie| cookie.check().is_ok())
+                .unwrap_or(false)
+            });
+            if grabbed {
+                grabs.insert((code, modifiers), *id);
+                registered.push(*id);
+            } else {
+                // Another client holds it; release any combinations we did get
+                for locks in LOCKS {
+                    let _ = conn.ungrab_key(code, root, ModMask::from(modifiers | locks));
+                }
+            }
+        }
+        conn.flush()?;
+        let _ = tx.send(registered);
+
+        let ignored = LOCKS[3];
+        while !stop.load(Ordering::SeqCst) {
+            match conn.poll_for_event()? {
+                Some(Event::KeyPress(event)) => {
+                    let state = u16::from(event.state) & !ignored & 0xFF;
+                    if let Some(id) = grabs.get(&(event.detail, state)) {
+                        super::trigger(*id);
+                    }
+                }
+                Some(_) => {}
+                None => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+
+        for (code, modifiers) in grabs.keys() {
+            for locks in LOCKS {
+                let _ = conn.ungrab_key(*code, root, ModMask::from(modifiers | locks));
+            }
+        }
+        conn.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+mod platform {
+    use tauri::AppHandle;
+
+    use super::Accelerator;
+
+    pub fn register(_app: &AppHandle, _bindings: Vec<(u32, Accelerator)>) -> Vec<u32> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accelerator() {
+        let parsed = Accelerator::parse("Ctrl+Shift+r").unwrap();
+        assert!(parsed.ctrl && parsed.shift && !parsed.alt && !parsed.meta);
+        assert_eq!(parsed.key, Key::Char('R'));
+
+        assert_eq!(Accelerator::parse("Alt + F5").unwrap().key, Key::F(5));
+        assert_eq!(Accelerator::parse("Super+Space").unwrap().key, Key::Space);
+        let cmd_or_ctrl = Accelerator::parse("CmdOrCtrl+1").unwrap();
+        assert_eq!(cmd_or_ctrl.meta, cfg!(target_os = "macos"));
+
+        assert!(Accelerator::parse("Shift+R").is_err());
+        assert!(Accelerator::parse("Ctrl+R+S").is_err());
+        assert!(Accelerator::parse("Ctrl+F13").is_err());
+        assert!(Accelerator::parse("Ctrl+").is_err());
+    }
+}
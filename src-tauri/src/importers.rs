@@ -0,0 +1,373 @@
+//! Parsers for history exported from other apps: Otter transcripts, folders of markdown
+//! notes, and Apple Voice Memos recordings
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+
+/// Core Data timestamps count from 2001-01-01
+const CORE_DATA_EPOCH_OFFSET: i64 = 978_307_200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedSegment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub speaker: Option<String>,
+}
+
+/// A note parsed from another app's export
+#[derive(Debug, Clone)]
+pub struct ImportedNote {
+    pub title: String,
+    pub description: Option<String>,
+    pub participants: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: Option<f64>,
+    pub segments: Vec<ImportedSegment>,
+}
+
+/// A Voice Memos recording found on disk
+#[derive(Debug, Clone)]
+pub struct VoiceMemo {
+    pub path: PathBuf,
+    pub title: String,
+    pub recorded_at: DateTime<Utc>,
+    pub duration_secs: Option<f64>,
+}
+
+/// Parse an Otter.ai text export: blocks of "Speaker Name  1:23" followed by the text.
+/// Returns None if the file has no transcript blocks.
+pub fn parse_otter_transcript(
+    content: &str,
+    fallback_title: &str,
+    fallback_date: DateTime<Utc>,
+) -> Option<ImportedNote> {
+    // Otter separates the speaker from the timestamp with at least two spaces, which keeps
+    // transcript lines like "see you at 10:30" from being read as headers
+    let header = Regex::new(r"^(?:(.+?)(?:\s{2,}|\t))?(\d{1,2}:\d{2}(?::\d{2})?)$").expect("valid regex");
+
+    let mut blocks: Vec<(f64, Option<String>, Vec<String>)> = Vec::new();
+    let mut preamble = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("Transcribed by") {
+            continue;
+        }
+        if let Some(captures) = header.captures(line) {
+            let speaker = captures.get(1).map(|m| m.as_str().trim().to_string());
+            blocks.push((parse_clock(&captures[2]), speaker, Vec::new()));
+        } else if let Some((_, _, text)) = blocks.last_mut() {
+            if !line.is_empty() {
+                text.push(line.to_string());
+            }
+        } else if !line.is_empty() {
+            preamble.push(line.to_string());
+        }
+    }
+    if blocks.is_empty() {
+        return None;
+    }
+
+    // Each block runs until the next one starts; estimate the last from its length
+    let mut segments = Vec::with_capacity(blocks.len());
+    for i in 0..blocks.len() {
+        let (start, speaker, lines) = &blocks[i];
+        let text = lines.join(" ");
+        let end = blocks
+            .get(i + 1)
+            .map(|(next, _, _)| *next)
+            .unwrap_or_else(|| start + (text.split_whitespace().count() as f64 / 2.5).max(1.0));
+        if text.is_empty() {
+            continue;
+        }
+        segments.push(ImportedSegment {
+            start: *start,
+            end,
+            text,
+            speaker: speaker.clone(),
+        });
+    }
+
+    // Otter's generic "Speaker 1" labels aren't participants
+    let generic = Regex::new(r"^Speaker \d+$").expect("valid regex");
+    let speakers: BTreeSet<&str> = segments
+        .iter()
+        .filter_map(|s| s.speaker.as_deref())
+        .filter(|s| !generic.is_match(s))
+        .collect();
+
+    Some(ImportedNote {
+        title: preamble
+            .first()
+            .cloned()
+            .unwrap_or_else(|| title_from_stem(fallback_title)),
+        description: None,
+        participants: (!speakers.is_empty())
+            .then(|| speakers.into_iter().collect::<Vec<_>>().join(", ")),
+        started_at: preamble
+            .iter()
+            .find_map(|l| parse_loose_date(l))
+            .unwrap_or(fallback_date),
+        duration_secs: segments.last().map(|s| s.end),
+        segments,
+    })
+}
+
+/// Parse a markdown note. Title, date and participants come from YAML frontmatter when
+/// present, otherwise from the first heading and the file name.
+pub fn parse_markdown_note(content: &str, file_stem: &str, fallback_date: DateTime<Utc>) -> ImportedNote {
+    let mut front: Vec<(String, String)> = Vec::new();
+    let mut body = content.trim_start_matches('\u{feff}');
+
+    if let Some(rest) = body.strip_prefix("---\n").or_else(|| body.strip_prefix("---\r\n"))
+        && let Some(end) = rest.find("\n---")
+    {
+        for line in rest[..end].lines() {
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+                front.push((key.trim().to_lowercase(), value.to_string()));
+            }
+        }
+        body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    }
+    let front_value = |keys: &[&str]| {
+        front
+            .iter()
+            .find(|(k, v)| keys.contains(&k.as_str()) && !v.is_empty())
+            .map(|(_, v)| v.clone())
+    };
+
+    let mut title = front_value(&["title"]);
+    let mut lines: Vec<&str> = body.lines().collect();
+    if title.is_none()
+        && let Some(pos) = lines.iter().position(|l| !l.trim().is_empty())
+        && let Some(heading) = lines[pos].trim().strip_prefix("# ")
+    {
+        title = Some(heading.trim().to_string());
+        lines.remove(pos);
+    }
+    let description = lines.join("\n").trim().to_string();
+
+    let participants = front_value(&["participants", "attendees"]).map(|p| {
+        p.trim_matches(['[', ']'])
+            .split(',')
+            .map(|s| s.trim().trim_matches('"'))
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+
+    ImportedNote {
+        title: title.unwrap_or_else(|| title_from_stem(file_stem)),
+        description: (!description.is_empty()).then_some(description),
+        participants: participants.filter(|p| !p.is_empty()),
+        started_at: front_value(&["date", "created", "created_at"])
+            .and_then(|d| parse_loose_date(&d))
+            .or_else(|| parse_loose_date(file_stem))
+            .unwrap_or(fallback_date),
+        duration_secs: None,
+        segments: Vec::new(),
+    }
+}
+
+/// The Voice Memos library of the current user (macOS)
+#[cfg(target_os = "macos")]
+pub fn default_voice_memos_dir() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    [
+        // macOS 14+
+        "Library/Group Containers/group.com.apple.VoiceMemos.shared/Recordings",
+        "Library/Application Support/com.apple.voicememos/Recordings",
+    ]
+    .iter()
+    .map(|p| home.join(p))
+    .find(|p| p.is_dir())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn default_voice_memos_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Recordings in a Voice Memos folder. Titles and dates come from the app's
+/// CloudRecordings.db when readable, otherwise from the file names.
+pub fn find_voice_memos(dir: &Path) -> Vec<VoiceMemo> {
+    if let Some(memos) = read_voice_memos_db(dir) {
+        return memos;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut memos: Vec<VoiceMemo> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("m4a")))
+        .map(|path| {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+            // Files are named like "20240315 101530-ABCD1234.m4a"
+            let recorded_at = stem
+                .get(..15)
+                .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y%m%d %H%M%S").ok())
+                .and_then(|n| Local.from_local_datetime(&n).earliest())
+                .map(|d| d.with_timezone(&Utc))
+                .or_else(|| {
+                    fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .map(DateTime::<Utc>::from)
+                })
+                .unwrap_or_else(Utc::now);
+            VoiceMemo {
+                title: format!("Voice Memo {}", recorded_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+                path,
+                recorded_at,
+                duration_secs: None,
+            }
+        })
+        .collect();
+    memos.sort_by_key(|m| m.recorded_at);
+    memos
+}
+
+fn read_voice_memos_db(dir: &Path) -> Option<Vec<VoiceMemo>> {
+    let db_path = dir.join("CloudRecordings.db");
+    if !db_path.exists() {
+        return None;
+    }
+    let conn =
+        rusqlite::Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .ok()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT ZPATH, COALESCE(NULLIF(ZENCRYPTEDTITLE, ''), ZCUSTOMLABEL), ZDATE, ZDURATION
+             FROM ZCLOUDRECORDING WHERE ZPATH IS NOT NULL ORDER BY ZDATE ASC",
+        )
+        .ok()?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+            ))
+        })
+        .ok()?;
+
+    let memos = rows
+        .filter_map(|r| r.ok())
+        .filter_map(|(path, title, date, duration)| {
+            let path = dir.join(Path::new(&path).file_name()?);
+            if !path.exists() {
+                return None;
+            }
+            let recorded_at = date
+                .and_then(|d| Utc.timestamp_opt(d as i64 + CORE_DATA_EPOCH_OFFSET, 0).single())
+                .unwrap_or_else(Utc::now);
+            Some(VoiceMemo {
+                title: title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| {
+                    format!("Voice Memo {}", recorded_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))
+                }),
+                path,
+                recorded_at,
+                duration_secs: duration,
+            })
+        })
+        .collect();
+    Some(memos)
+}
+
+/// "1:23" or "1:02:03" to seconds
+fn parse_clock(value: &str) -> f64 {
+    value
+        .split(':')
+        .fold(0.0, |acc, part| acc * 60.0 + part.parse::<f64>().unwrap_or(0.0))
+}
+
+/// Find a date in free text: RFC 3339, "YYYY-MM-DD HH:MM" or "YYYY-MM-DD" (local time)
+fn parse_loose_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value.trim()) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let re = Regex::new(r"(\d{4}-\d{2}-\d{2})(?:[ T](\d{2}:\d{2}))?").expect("valid regex");
+    let captures = re.captures(value)?;
+    let date = NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").ok()?;
+    let naive = match captures.get(2) {
+        Some(time) => NaiveDateTime::parse_from_str(
+            &format!("{} {}", &captures[1], time.as_str()),
+            "%Y-%m-%d %H:%M",
+        )
+        .ok()?,
+        None => date.and_hms_opt(0, 0, 0)?,
+    };
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+fn title_from_stem(stem: &str) -> String {
+    let title = stem.replace(['_', '-'], " ").trim().to_string();
+    if title.is_empty() {
+        "Imported Note".to_string()
+    } else {
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_otter_transcript() {
+        let content = "Speaker 1  0:00\n\
+Good morning everyone.\n\
+\n\
+Alice Smith  0:04\n\
+Thanks. Let's start with\n\
+the roadmap.\n\
+\n\
+Speaker 1  1:02:03\n\
+Sounds good.\n\
+\n\
+Transcribed by https://otter.ai\n";
+
+        let note = parse_otter_transcript(content, "weekly_sync", Utc::now()).unwrap();
+        assert_eq!(note.title, "weekly sync");
+        assert_eq!(note.participants.as_deref(), Some("Alice Smith"));
+        assert_eq!(note.segments.len(), 3);
+        assert_eq!(
+            note.segments[1],
+            ImportedSegment {
+                start: 4.0,
+                end: 3723.0,
+                text: "Thanks. Let's start with the roadmap.".to_string(),
+                speaker: Some("Alice Smith".to_string()),
+            }
+        );
+        assert!(parse_otter_transcript("just some text", "x", Utc::now()).is_none());
+    }
+
+    #[test]
+    fn test_parse_markdown_note() {
+        let content = "---\ntitle: \"Planning\"\nattendees: [Alice, Bob]\n---\n\n# Heading stays\n\nBody #tag";
+        let note = parse_markdown_note(content, "2026-01-15_notes", Utc::now());
+        assert_eq!(note.title, "Planning");
+        assert_eq!(note.participants.as_deref(), Some("Alice, Bob"));
+        assert_eq!(note.description.as_deref(), Some("# Heading stays\n\nBody #tag"));
+        assert_eq!(
+            note.started_at.with_timezone(&Local).date_naive().to_string(),
+            "2026-01-15"
+        );
+
+        let note = parse_markdown_note("# Retro\n\nWhat went well", "file", Utc::now());
+        assert_eq!(note.title, "Retro");
+        assert_eq!(note.description.as_deref(), Some("What went well"));
+    }
+}
@@ -0,0 +1,875 @@
+//! ICS (iCalendar) parsing for calendar import, plus the scheduler that fires
+//! recordings for imported events with auto-record enabled.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{self, AudioState};
+use crate::db::Database;
+use crate::notifications;
+
+/// How often the scheduler checks for due recordings
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fire a scheduled recording this long before the event starts
+const SCHEDULE_LEAD_SECS: i64 = 60;
+
+/// How far ahead events are imported, so a daily series adds a couple of weeks of note
+/// stubs rather than months; importing again later picks up the next ones
+pub const IMPORT_WINDOW_DAYS: i64 = 14;
+
+/// A content line split into (name, parameters, value)
+type Property = (String, Vec<(String, String)>, String);
+
+/// A VEVENT parsed from an ICS file
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    /// "Name <email>" (or just the email when no CN is given)
+    pub attendees: Vec<String>,
+    pub cancelled: bool,
+}
+
+/// Parse every VEVENT in an ICS document. Recurring events (RRULE) become one event per
+/// occurrence overlapping `from..=until`, less their EXDATEs and with their moved or
+/// cancelled occurrences (RECURRENCE-ID) applied; each occurrence's uid is the series' uid
+/// and its start ("uid/20260115T100000Z"). TZID-qualified times are converted with the
+/// file's VTIMEZONE of that name, or read as local time when it has none.
+pub fn parse_ics(content: &str, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<IcsEvent> {
+    let mut vevents = Vec::new();
+    let mut timezones = HashMap::new();
+    // Observances of the VTIMEZONE being read
+    let mut observances = Vec::new();
+    // Open components, innermost last, with the properties read in each
+    let mut open: Vec<(String, Vec<Property>)> = Vec::new();
+
+    for line in unfold_lines(content) {
+        let Some((name, params, value)) = parse_property(&line) else {
+            continue;
+        };
+
+        match name.as_str() {
+            "BEGIN" => open.push((value.trim().to_uppercase(), Vec::new())),
+            "END" => {
+                let Some((component, props)) = open.pop() else {
+                    continue;
+                };
+                match component.as_str() {
+                    "VEVENT" => vevents.push(props),
+                    "STANDARD" | "DAYLIGHT" => observances.extend(parse_observance(&props)),
+                    "VTIMEZONE" => {
+                        let observances = std::mem::take(&mut observances);
+                        if let Some((_, _, tzid)) = get(&props, "TZID") {
+                            timezones.insert(tzid.trim().to_string(), Timezone { observances });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {
+                if let Some((_, props)) = open.last_mut() {
+                    props.push((name, params, value));
+                }
+            }
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut series = Vec::new();
+    // Occurrences of a series given their own VEVENT, by (uid, original start)
+    let mut overrides = HashMap::new();
+    for props in &vevents {
+        let Some(event) = build_event(props, &timezones) else {
+            continue;
+        };
+        let recurrence_id = get(props, "RECURRENCE-ID")
+            .and_then(|(_, params, value)| parse_ics_datetime(value, params, &timezones));
+        if let Some(recurrence_id) = recurrence_id {
+            overrides.insert((event.uid.clone(), recurrence_id), event);
+        } else if get(props, "RRULE").is_some() {
+            series.push((event, props));
+        } else {
+            events.push(event);
+        }
+    }
+
+    for (event, props) in series {
+        for start in occurrences(&event, props, &timezones, from, until) {
+            let occurrence = overrides
+                .remove(&(event.uid.clone(), start))
+                .unwrap_or_else(|| IcsEvent {
+                    start,
+                    end: event.end.map(|end| start + (end - event.start)),
+                    ..event.clone()
+                });
+            events.push(IcsEvent {
+                uid: occurrence_uid(&event.uid, start),
+                ..occurrence
+            });
+        }
+    }
+
+    // Occurrences moved into the window from outside it
+    let mut moved: Vec<_> = overrides
+        .into_iter()
+        .filter(|(_, event)| event.end.unwrap_or(event.start) >= from && event.start <= until)
+        .collect();
+    moved.sort_by_key(|((_, recurrence_id), _)| *recurrence_id);
+    for ((uid, recurrence_id), event) in moved {
+        events.push(IcsEvent {
+            uid: occurrence_uid(&uid, recurrence_id),
+            ..event
+        });
+    }
+
+    events
+}
+
+fn occurrence_uid(uid: &str, start: DateTime<Utc>) -> String {
+    format!("{}/{}", uid, start.format("%Y%m%dT%H%M%SZ"))
+}
+
+fn get<'a>(props: &'a [Property], key: &str) -> Option<&'a Property> {
+    props.iter().find(|(name, _, _)| name == key)
+}
+
+fn build_event(props: &[Property], timezones: &HashMap<String, Timezone>) -> Option<IcsEvent> {
+    let (_, start_params, start_value) = get(props, "DTSTART")?;
+    let start = parse_ics_datetime(start_value, start_params, timezones)?;
+    let end = get(props, "DTEND")
+        .and_then(|(_, params, value)| parse_ics_datetime(value, params, timezones));
+
+    let attendees = props
+        .iter()
+        .filter(|(name, _, _)| name == "ATTENDEE")
+        .filter_map(|(_, params, value)| {
+            let email = value
+                .strip_prefix("mailto:")
+                .or_else(|| value.strip_prefix("MAILTO:"))
+                .unwrap_or(value)
+                .trim()
+                .to_string();
+            let cn = params
+                .iter()
+                .find(|(k, _)| k == "CN")
+                .map(|(_, v)| v.trim_matches('"').to_string());
+            match (cn, email.contains('@')) {
+                (Some(cn), true) if cn != email => Some(format!("{} <{}>", cn, email)),
+                (_, true) => Some(email),
+                (Some(cn), false) => Some(cn),
+                (None, false) => None,
+            }
+        })
+        .collect();
+
+    Some(IcsEvent {
+        uid: get(props, "UID")
+            .map(|(_, _, v)| v.clone())
+            .unwrap_or_else(|| format!("{}-{}", start.timestamp(), props.len())),
+        summary: get(props, "SUMMARY")
+            .map(|(_, _, v)| unescape(v))
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "Untitled Meeting".to_string()),
+        description: get(props, "DESCRIPTION")
+            .map(|(_, _, v)| unescape(v))
+            .filter(|s| !s.trim().is_empty()),
+        location: get(props, "LOCATION")
+            .map(|(_, _, v)| unescape(v))
+            .filter(|s| !s.trim().is_empty()),
+        start,
+        end,
+        attendees,
+        cancelled: get(props, "STATUS")
+            .is_some_and(|(_, _, v)| v.eq_ignore_ascii_case("CANCELLED")),
+    })
+}
+
+/// Starts of the occurrences of a recurring event that overlap `from..=until`, less its
+/// EXDATEs. Occurrences keep the wall-clock time of the first across DST changes.
+fn occurrences(
+    event: &IcsEvent,
+    props: &[Property],
+    timezones: &HashMap<String, Timezone>,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let first = get(props, "DTSTART").and_then(|(_, params, value)| parse_wall_time(value, params));
+    let rule = get(props, "RRULE").and_then(|(_, _, value)| Rule::parse(value));
+    let (Some((first, zone)), Some(rule)) = (first, rule) else {
+        return vec![event.start];
+    };
+    let duration = event
+        .end
+        .map_or(chrono::Duration::zero(), |end| end - event.start);
+    // An UNTIL without "Z" is in the zone of DTSTART
+    let rule_until = rule.until.as_deref().and_then(|value| {
+        let (naive, until_zone) = parse_wall_time(value, &[])?;
+        let until_zone = if until_zone == Zone::Local {
+            zone.clone()
+        } else {
+            until_zone
+        };
+        until_zone.to_utc(naive, timezones)
+    });
+    let dates = |key: &str| -> Vec<DateTime<Utc>> {
+        props
+            .iter()
+            .filter(|(name, _, _)| name == key)
+            .flat_map(|(_, params, value)| {
+                value
+                    .split(',')
+                    .filter_map(|value| parse_ics_datetime(value, params, timezones))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    };
+    let exdates = dates("EXDATE");
+
+    // A day past `until` in wall-clock time covers any UTC offset
+    let horizon = (until + chrono::Duration::days(1)).naive_utc();
+    let mut starts: Vec<DateTime<Utc>> = rule
+        .expand(first, horizon)
+        .into_iter()
+        .filter_map(|naive| zone.to_utc(naive, timezones))
+        .filter(|start| rule_until.is_none_or(|rule_until| *start <= rule_until))
+        .chain(dates("RDATE"))
+        .filter(|start| !exdates.contains(start))
+        .filter(|start| *start + duration >= from && *start <= until)
+        .collect();
+    starts.sort();
+    starts.dedup();
+    starts
+}
+
+fn parse_ics_datetime(
+    value: &str,
+    params: &[(String, String)],
+    timezones: &HashMap<String, Timezone>,
+) -> Option<DateTime<Utc>> {
+    let (naive, zone) = parse_wall_time(value, params)?;
+    zone.to_utc(naive, timezones)
+}
+
+/// The zone a date-time is written in
+#[derive(Debug, Clone, PartialEq)]
+enum Zone {
+    Utc,
+    /// Floating times, all-day dates, and TZIDs the file has no VTIMEZONE for
+    Local,
+    Tzid(String),
+}
+
+impl Zone {
+    fn to_utc(
+        &self,
+        naive: NaiveDateTime,
+        timezones: &HashMap<String, Timezone>,
+    ) -> Option<DateTime<Utc>> {
+        let timezone = match self {
+            Zone::Utc => return Some(Utc.from_utc_datetime(&naive)),
+            Zone::Tzid(tzid) => timezones.get(tzid),
+            Zone::Local => None,
+        };
+        match timezone {
+            Some(timezone) => {
+                let offset = chrono::Duration::seconds(timezone.offset_at(naive) as i64);
+                Some(Utc.from_utc_datetime(&(naive - offset)))
+            }
+            None => Some(
+                Local
+                    .from_local_datetime(&naive)
+                    .earliest()?
+                    .with_timezone(&Utc),
+            ),
+        }
+    }
+}
+
+/// A date-time value as written: its wall-clock time and zone
+fn parse_wall_time(value: &str, params: &[(String, String)]) -> Option<(NaiveDateTime, Zone)> {
+    let value = value.trim();
+    let is_date = params.iter().any(|(k, v)| k == "VALUE" && v == "DATE") || value.len() == 8;
+
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms_opt(0, 0, 0)?, Zone::Local));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((naive, Zone::Utc));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = params
+        .iter()
+        .find(|(k, _)| k == "TZID")
+        .map_or(Zone::Local, |(_, tzid)| {
+            Zone::Tzid(tzid.trim_matches('"').trim().to_string())
+        });
+    Some((naive, zone))
+}
+
+/// A VTIMEZONE: the STANDARD and DAYLIGHT observances it switches between
+struct Timezone {
+    observances: Vec<Observance>,
+}
+
+/// A STANDARD or DAYLIGHT observance: the UTC offset it brings, from when
+struct Observance {
+    /// First onset, in the wall-clock time before it
+    start: NaiveDateTime,
+    rule: Option<Rule>,
+    rdates: Vec<NaiveDateTime>,
+    /// Seconds east of UTC before and after each onset
+    offset_from: i32,
+    offset_to: i32,
+}
+
+impl Timezone {
+    /// UTC offset in seconds at wall-clock time `naive`: that of the observance with the
+    /// latest onset before it
+    fn offset_at(&self, naive: NaiveDateTime) -> i32 {
+        self.observances
+            .iter()
+            .filter_map(|observance| Some((observance.last_onset(naive)?, observance.offset_to)))
+            .max_by_key(|(onset, _)| *onset)
+            .map(|(_, offset)| offset)
+            .or_else(|| {
+                let first = self
+                    .observances
+                    .iter()
+                    .min_by_key(|observance| observance.start);
+                first.map(|observance| observance.offset_from)
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl Observance {
+    fn last_onset(&self, naive: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut onsets = match &self.rule {
+            Some(rule) => {
+                let until = rule
+                    .until
+                    .as_deref()
+                    .and_then(|value| parse_wall_time(value, &[]))
+                    .map(|(until, _)| until);
+                let mut onsets = rule.expand(self.start, naive);
+                onsets.retain(|onset| until.is_none_or(|until| *onset <= until));
+                onsets
+            }
+            None => vec![self.start],
+        };
+        onsets.extend(self.rdates.iter().copied());
+        onsets.into_iter().filter(|onset| *onset <= naive).max()
+    }
+}
+
+fn parse_observance(props: &[Property]) -> Option<Observance> {
+    let parse = |value: &str| NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%S").ok();
+    let (_, _, start) = get(props, "DTSTART")?;
+    let (_, _, offset_from) = get(props, "TZOFFSETFROM")?;
+    let (_, _, offset_to) = get(props, "TZOFFSETTO")?;
+    Some(Observance {
+        start: parse(start)?,
+        rule: get(props, "RRULE").and_then(|(_, _, value)| Rule::parse(value)),
+        rdates: props
+            .iter()
+            .filter(|(name, _, _)| name == "RDATE")
+            .flat_map(|(_, _, value)| value.split(',').filter_map(parse).collect::<Vec<_>>())
+            .collect(),
+        offset_from: parse_utc_offset(offset_from)?,
+        offset_to: parse_utc_offset(offset_to)?,
+    })
+}
+
+/// "+0530", "-0400" or "+053000" in seconds
+fn parse_utc_offset(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let digits = &value[1..];
+    let part = |range: std::ops::Range<usize>| digits.get(range)?.parse::<i32>().ok();
+    let seconds = if digits.len() == 6 { part(4..6)? } else { 0 };
+    Some(sign * (part(0..2)? * 3600 + part(2..4)? * 60 + seconds))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// An RRULE. Rules by the hour or finer, and BYSETPOS, aren't supported.
+#[derive(Debug, Clone)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    /// As written; see `occurrences` for its zone
+    until: Option<String>,
+    /// (nth in the month or year, negative from the end; weekday)
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Option<Rule> {
+        let parts: HashMap<String, &str> = value
+            .split(';')
+            .filter_map(|part| part.split_once('='))
+            .map(|(key, value)| (key.trim().to_uppercase(), value.trim()))
+            .collect();
+        let list = |key: &str| -> Vec<&str> {
+            parts
+                .get(key)
+                .map_or(Vec::new(), |value| value.split(',').collect())
+        };
+
+        let frequency = match parts.get("FREQ")?.to_uppercase().as_str() {
+            "DAILY" => Frequency::Daily,
+            "WEEKLY" => Frequency::Weekly,
+            "MONTHLY" => Frequency::Monthly,
+            "YEARLY" => Frequency::Yearly,
+            _ => return None,
+        };
+        let by_day = list("BYDAY")
+            .into_iter()
+            .filter_map(|day| {
+                let day = day.trim();
+                let (nth, weekday) = day.split_at(day.len().checked_sub(2)?);
+                let weekday = match weekday.to_uppercase().as_str() {
+                    "MO" => Weekday::Mon,
+                    "TU" => Weekday::Tue,
+                    "WE" => Weekday::Wed,
+                    "TH" => Weekday::Thu,
+                    "FR" => Weekday::Fri,
+                    "SA" => Weekday::Sat,
+                    "SU" => Weekday::Sun,
+                    _ => return None,
+                };
+                let nth = if nth.is_empty() {
+                    None
+                } else {
+                    Some(nth.parse().ok()?)
+                };
+                Some((nth, weekday))
+            })
+            .collect();
+
+        Some(Rule {
+            frequency,
+            interval: parts
+                .get("INTERVAL")
+                .and_then(|value| value.parse().ok())
+                .filter(|interval| *interval > 0)
+                .unwrap_or(1),
+            count: parts.get("COUNT").and_then(|value| value.parse().ok()),
+            until: parts.get("UNTIL").map(|value| value.to_string()),
+            by_day,
+            by_month_day: list("BYMONTHDAY")
+                .iter()
+                .filter_map(|d| d.parse().ok())
+                .collect(),
+            by_month: list("BYMONTH")
+                .iter()
+                .filter_map(|m| m.parse().ok())
+                .collect(),
+        })
+    }
+
+    /// Occurrences in wall-clock time from `start` up to `horizon`, within COUNT. UNTIL is
+    /// left to the caller, as its zone depends on what the rule belongs to.
+    fn expand(&self, start: NaiveDateTime, horizon: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut occurrences = Vec::new();
+        let mut counted = 0;
+        let date = start.date();
+        for period in 0u32.. {
+            let step = period.saturating_mul(self.interval);
+            let anchor = match self.frequency {
+                Frequency::Daily => date.checked_add_days(Days::new(step as u64)),
+                Frequency::Weekly => date
+                    .week(Weekday::Mon)
+                    .first_day()
+                    .checked_add_days(Days::new(step as u64 * 7)),
+                Frequency::Monthly => date
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(step))),
+                Frequency::Yearly => NaiveDate::from_ymd_opt(date.year(), 1, 1).and_then(|first| {
+                    first.checked_add_months(Months::new(step.saturating_mul(12)))
+                }),
+            };
+            let Some(anchor) = anchor.filter(|anchor| *anchor <= horizon.date()) else {
+                break;
+            };
+
+            let mut days = self.days_of_period(anchor, date);
+            days.retain(|day| self.by_month.is_empty() || self.by_month.contains(&day.month()));
+            days.sort();
+            days.dedup();
+            for day in days {
+                let at = day.and_time(start.time());
+                if at < start {
+                    continue;
+                }
+                if at > horizon || self.count.is_some_and(|count| counted >= count) {
+                    return occurrences;
+                }
+                counted += 1;
+                occurrences.push(at);
+            }
+        }
+        occurrences
+    }
+
+    /// The days of the period starting `anchor` that the rule picks, for a series that
+    /// started on `start`
+    fn days_of_period(&self, anchor: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        match self.frequency {
+            Frequency::Daily => {
+                let picked = self.by_day.is_empty()
+                    || self
+                        .by_day
+                        .iter()
+                        .any(|(_, weekday)| *weekday == anchor.weekday());
+                if picked {
+                    vec![anchor]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        anchor.checked_add_days(Days::new(weekday.num_days_from_monday() as u64))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => self.days_of_month(anchor, start),
+            Frequency::Yearly => {
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                months
+                    .into_iter()
+                    .filter_map(|month| NaiveDate::from_ymd_opt(anchor.year(), month, 1))
+                    .flat_map(|first| {
+                        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                            first.with_day(start.day()).into_iter().collect()
+                        } else {
+                            self.days_of_month(first, start)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The days of the month starting `first` that the rule picks
+    fn days_of_month(&self, first: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let Some(length) = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map(|last| last.day() as i32)
+        else {
+            return Vec::new();
+        };
+        let day = |day: i32| {
+            let day = if day < 0 { length + day + 1 } else { day };
+            (1..=length)
+                .contains(&day)
+                .then(|| first.with_day(day as u32))
+                .flatten()
+        };
+
+        if !self.by_month_day.is_empty() {
+            return self.by_month_day.iter().filter_map(|d| day(*d)).collect();
+        }
+        if self.by_day.is_empty() {
+            return day(start.day() as i32).into_iter().collect();
+        }
+        self.by_day
+            .iter()
+            .flat_map(|(nth, weekday)| {
+                let matching: Vec<NaiveDate> = (1..=length)
+                    .filter_map(day)
+                    .filter(|date| date.weekday() == *weekday)
+                    .collect();
+                match nth {
+                    None => matching,
+                    Some(nth) => {
+                        let index = if *nth > 0 {
+                            *nth - 1
+                        } else {
+                            matching.len() as i32 + *nth
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|index| matching.get(index).copied())
+                            .into_iter()
+                            .collect()
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Join folded lines (continuations start with a space or tab)
+fn unfold_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.lines() {
+        let raw = raw.trim_end_matches('\r');
+        if let Some(rest) = raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t'))
+            && let Some(last) = lines.last_mut()
+        {
+            last.push_str(rest);
+            continue;
+        }
+        lines.push(raw.to_string());
+    }
+    lines
+}
+
+/// Split "NAME;PARAM=VALUE:value" into (NAME, params, value). Colons inside quoted
+/// parameter values don't end the name part.
+fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    })?;
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_uppercase();
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.trim().to_uppercase(), v.to_string()))
+        .collect();
+
+    Some((name, params, value.to_string()))
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// Start the background scheduler for auto-record calendar events (call from setup)
+pub fn start_recording_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(SCHEDULER_INTERVAL);
+
+            let Some(db) = app.try_state::<Database>() else {
+                continue;
+            };
+
+            let due = match db.take_due_scheduled_recordings(Utc::now(), SCHEDULE_LEAD_SECS) {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::warn!("Failed to check scheduled recordings: {}", e);
+                    continue;
+                }
+            };
+
+            for (_, note_id, title) in due {
+                tracing::info!("Scheduled recording due: {}", title);
+                let state = app.state::<AudioState>();
+                let body = if state.recording.get_phase() != RecordingPhase::Idle {
+                    format!("Already recording, so {} wasn't recorded", title)
+                } else {
+                    match commands::start_announced_recording(&app, &note_id) {
+                        Ok(_) => format!("Recording {}", title),
+                        Err(e) => {
+                            tracing::warn!("Failed to record {}: {}", title, e);
+                            format!("Couldn't record {}: {}", title, e)
+                        }
+                    }
+                };
+                notifications::notify_background(&app, "Scheduled recording", &body);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_ics_datetime(value, &[], &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_ics() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+UID:abc-123\r\n\
+SUMMARY:Weekly sync\\, team\r\n\
+DTSTART:20260115T100000Z\r\n\
+DTEND:20260115T103000Z\r\n\
+ATTENDEE;CN=\"Alice Smith\";ROLE=REQ-PARTICIPANT:mailto:alice@example.com\r\n\
+ATTENDEE:mailto:bob@example.com\r\n\
+DESCRIPTION:Agenda: long line that gets \r\n  folded\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:cancelled\r\n\
+DTSTART:20260116T100000Z\r\n\
+STATUS:CANCELLED\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+        let events = parse_ics(ics, at("20260101T000000Z"), at("20261231T000000Z"));
+        assert_eq!(events.len(), 2);
+
+        let event = &events[0];
+        assert_eq!(event.uid, "abc-123");
+        assert_eq!(event.summary, "Weekly sync, team");
+        assert_eq!(event.start.to_rfc3339(), "2026-01-15T10:00:00+00:00");
+        assert_eq!(event.end.unwrap().to_rfc3339(), "2026-01-15T10:30:00+00:00");
+        assert_eq!(
+            event.attendees,
+            vec!["Alice Smith <alice@example.com>", "bob@example.com"]
+        );
+        assert_eq!(
+            event.description.as_deref(),
+            Some("Agenda: long line that gets  folded")
+        );
+        assert!(!event.cancelled);
+        assert!(events[1].cancelled);
+    }
+
+    #[test]
+    fn test_recurring_event() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup\r\n\
+SUMMARY:Standup\r\n\
+DTSTART:20251201T100000Z\r\n\
+DTEND:20251201T101500Z\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\n\
+EXDATE:20260107T100000Z\r\n\
+BEGIN:VALARM\r\n\
+DESCRIPTION:Reminder\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup\r\n\
+RECURRENCE-ID:20260112T100000Z\r\n\
+SUMMARY:Standup (moved)\r\n\
+DTSTART:20260112T140000Z\r\n\
+DTEND:20260112T141500Z\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+        let events = parse_ics(ics, at("20260105T000000Z"), at("20260115T000000Z"));
+        let summary: Vec<(&str, String, &str)> = events
+            .iter()
+            .map(|e| (e.uid.as_str(), e.start.to_rfc3339(), e.summary.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "standup/20260105T100000Z",
+                    "2026-01-05T10:00:00+00:00".to_string(),
+                    "Standup"
+                ),
+                (
+                    "standup/20260112T100000Z",
+                    "2026-01-12T14:00:00+00:00".to_string(),
+                    "Standup (moved)"
+                ),
+                (
+                    "standup/20260114T100000Z",
+                    "2026-01-14T10:00:00+00:00".to_string(),
+                    "Standup"
+                ),
+            ]
+        );
+        assert_eq!(
+            events[0].end.unwrap().to_rfc3339(),
+            "2026-01-05T10:15:00+00:00"
+        );
+        assert_eq!(events[0].description, None);
+    }
+
+    #[test]
+    fn test_vtimezone() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VTIMEZONE\r\n\
+TZID:America/New_York\r\n\
+BEGIN:DAYLIGHT\r\n\
+DTSTART:19700308T020000\r\n\
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\n\
+TZOFFSETFROM:-0500\r\n\
+TZOFFSETTO:-0400\r\n\
+END:DAYLIGHT\r\n\
+BEGIN:STANDARD\r\n\
+DTSTART:19701101T020000\r\n\
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r\n\
+TZOFFSETFROM:-0400\r\n\
+TZOFFSETTO:-0500\r\n\
+END:STANDARD\r\n\
+END:VTIMEZONE\r\n\
+BEGIN:VEVENT\r\n\
+UID:summer\r\n\
+DTSTART;TZID=America/New_York:20260715T090000\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:review\r\n\
+DTSTART;TZID=America/New_York:20260130T090000\r\n\
+RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=3\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+        let events = parse_ics(ics, at("20260101T000000Z"), at("20261231T000000Z"));
+        let starts: Vec<String> = events.iter().map(|e| e.start.to_rfc3339()).collect();
+        assert_eq!(
+            starts,
+            vec![
+                "2026-07-15T13:00:00+00:00",
+                // The last Friday of each month at 9am New York time, across the DST change
+                "2026-01-30T14:00:00+00:00",
+                "2026-02-27T14:00:00+00:00",
+                "2026-03-27T13:00:00+00:00",
+            ]
+        );
+    }
+}
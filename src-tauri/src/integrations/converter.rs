@@ -0,0 +1,206 @@
+//! External document converters (pandoc or any other command) run on the markdown export.
+//! Each preset names an output format and the command that produces it; presets are stored
+//! as JSON in the `converter_presets` setting. A command line other than the defaults' is
+//! confirmed by the user before it first runs.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::process::no_window;
+
+pub const SETTING_PRESETS: &str = "converter_presets";
+
+/// Command lines (see `ConverterPreset::command_line`) the user agreed to run, as JSON
+pub const SETTING_APPROVED: &str = "converter_approved_commands";
+
+/// Replaced in a preset's arguments. Without `{input}` the markdown is written to stdin;
+/// without `{output}` the command's stdout becomes the output file.
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+pub const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+#[derive(Error, Debug)]
+pub enum ConverterError {
+    #[error("Converter \"{0}\" was not found. Install it or fix the preset's command.")]
+    NotFound(String),
+    #[error("Failed to run converter: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Converter exited with {status}: {stderr}")]
+    Failed { status: String, stderr: String },
+}
+
+/// One output format, e.g. EPUB via `pandoc {input} -o {output}`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConverterPreset {
+    pub name: String,
+    /// File extension of the output, without the dot
+    pub extension: String,
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl ConverterPreset {
+    /// The command and its arguments as one line, for asking before it runs
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.command)
+            .chain(&self.args)
+            .map(|a| {
+                if a.contains(char::is_whitespace) {
+                    format!("\"{}\"", a)
+                } else {
+                    a.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Presets offered until the user saves their own
+pub fn default_presets() -> Vec<ConverterPreset> {
+    let pandoc = |name: &str, extension: &str, args: &[&str]| ConverterPreset {
+        name: name.to_string(),
+        extension: extension.to_string(),
+        command: "pandoc".to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+    };
+    vec![
+        pandoc(
+            "Word",
+            "docx",
+            &["-f", "markdown", "{input}", "-o", "{output}"],
+        ),
+        pandoc(
+            "LaTeX",
+            "tex",
+            &["-f", "markdown", "-s", "{input}", "-o", "{output}"],
+        ),
+        pandoc(
+            "EPUB",
+            "epub",
+            &["-f", "markdown", "{input}", "-o", "{output}"],
+        ),
+        pandoc(
+            "reveal.js slides",
+            "html",
+            &[
+                "-f", "markdown", "-t", "revealjs", "-s", "{input}", "-o", "{output}",
+            ],
+        ),
+    ]
+}
+
+/// Run `preset` on `markdown`, writing the result to `output`
+pub fn convert(
+    preset: &ConverterPreset,
+    markdown: &str,
+    output: &Path,
+) -> Result<(), ConverterError> {
+    let uses_input = preset.args.iter().any(|a| a.contains(INPUT_PLACEHOLDER));
+    let uses_output = preset.args.iter().any(|a| a.contains(OUTPUT_PLACEHOLDER));
+
+    // Some converters can't read stdin, so the markdown goes through a temp file when asked
+    let input = std::env::temp_dir().join(format!("note67-convert-{}.md", uuid::Uuid::new_v4()));
+    if uses_input {
+        std::fs::write(&input, markdown)?;
+    }
+    let _cleanup = scopeguard::guard((), |_| {
+        if uses_input {
+            let _ = std::fs::remove_file(&input);
+        }
+    });
+
+    let args: Vec<String> = preset
+        .args
+        .iter()
+        .map(|a| {
+            a.replace(INPUT_PLACEHOLDER, &input.to_string_lossy())
+                .replace(OUTPUT_PLACEHOLDER, &output.to_string_lossy())
+        })
+        .collect();
+
+    let mut child = no_window(&mut Command::new(&preset.command))
+        .args(&args)
+        .stdin(if uses_input {
+            Stdio::null()
+        } else {
+            Stdio::piped()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ConverterError::NotFound(preset.command.clone()),
+            _ => ConverterError::Io(e),
+        })?;
+
+    // Write stdin on another thread so a converter that streams output before reading all
+    // of its input can't deadlock against us
+    let writer = child.stdin.take().map(|mut stdin| {
+        let markdown = markdown.to_string();
+        std::thread::spawn(move || stdin.write_all(markdown.as_bytes()))
+    });
+    let result = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if !result.status.success() {
+        return Err(ConverterError::Failed {
+            status: result.status.to_string(),
+            stderr: String::from_utf8_lossy(&result.stderr).trim().to_string(),
+        });
+    }
+
+    if !uses_output {
+        std::fs::write(output, &result.stdout)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_convert() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // stdin to stdout
+        let upper = ConverterPreset {
+            name: "Upper".to_string(),
+            extension: "txt".to_string(),
+            command: "tr".to_string(),
+            args: vec!["a-z".to_string(), "A-Z".to_string()],
+        };
+        let output = dir.path().join("upper.txt");
+        convert(&upper, "# hello", &output).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "# HELLO");
+
+        // {input} and {output} files
+        let copy = ConverterPreset {
+            name: "Copy".to_string(),
+            extension: "md".to_string(),
+            command: "cp".to_string(),
+            args: vec![
+                INPUT_PLACEHOLDER.to_string(),
+                OUTPUT_PLACEHOLDER.to_string(),
+            ],
+        };
+        let output = dir.path().join("copy.md");
+        convert(&copy, "# hello", &output).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "# hello");
+
+        let missing = ConverterPreset {
+            command: "note67-no-such-converter".to_string(),
+            ..copy
+        };
+        assert!(matches!(
+            convert(&missing, "x", &output),
+            Err(ConverterError::NotFound(_))
+        ));
+    }
+}
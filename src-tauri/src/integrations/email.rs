@@ -0,0 +1,407 @@
+//! Minimal SMTP client for emailing note summaries.
+//! Supports implicit TLS (port 465), STARTTLS (port 587), and plain connections, with AUTH PLAIN.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use base64::Engine;
+use native_tls::{TlsConnector, TlsStream};
+use thiserror::Error;
+
+use crate::db::models::{ActionItem, Summary};
+
+/// Settings keys used by the SMTP integration (the password lives in the OS keychain)
+pub const SETTING_HOST: &str = "smtp_host";
+pub const SETTING_PORT: &str = "smtp_port";
+pub const SETTING_USERNAME: &str = "smtp_username";
+pub const SETTING_FROM: &str = "smtp_from";
+pub const SETTING_SECURITY: &str = "smtp_security";
+pub const SECRET_PASSWORD: &str = "smtp_password";
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum EmailError {
+    #[error("Email is not configured. Add SMTP settings first.")]
+    NotConfigured,
+    #[error("Invalid email address: {0:?}")]
+    InvalidAddress(String),
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(String),
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmtpSecurity {
+    /// TLS from the first byte (usually port 465)
+    Tls,
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    StartTls,
+    /// Unencrypted (local relays only)
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmtpSecurity::Tls => "tls",
+            SmtpSecurity::StartTls => "starttls",
+            SmtpSecurity::None => "none",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "tls" => SmtpSecurity::Tls,
+            "none" => SmtpSecurity::None,
+            _ => SmtpSecurity::StartTls,
+        }
+    }
+}
+
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub security: SmtpSecurity,
+}
+
+pub struct EmailMessage {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(s) => s.read(buf),
+            Stream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(s) => s.write(buf),
+            Stream::Tls(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Plain(s) => s.flush(),
+            Stream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+struct SmtpConnection {
+    stream: Stream,
+    buf: Vec<u8>,
+}
+
+impl SmtpConnection {
+    /// Read one (possibly multi-line) reply and return (code, text)
+    fn read_reply(&mut self) -> Result<(u16, String), EmailError> {
+        let mut text = String::new();
+        loop {
+            let line = self.read_line()?;
+            if line.len() < 3 {
+                return Err(EmailError::Smtp(format!("Malformed reply: {}", line)));
+            }
+            let code: u16 = line[..3]
+                .parse()
+                .map_err(|_| EmailError::Smtp(format!("Malformed reply: {}", line)))?;
+            text.push_str(line.get(4..).unwrap_or(""));
+            text.push('\n');
+            // "250-..." continues, "250 ..." ends the reply
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text.trim_end().to_string()));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, EmailError> {
+        loop {
+            if let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                let line = String::from_utf8_lossy(&self.buf[..pos]).to_string();
+                self.buf.drain(..pos + 2);
+                return Ok(line);
+            }
+            let mut chunk = [0u8; 1024];
+            let n = self.stream.read(&mut chunk)?;
+            if n == 0 {
+                return Err(EmailError::Smtp("Connection closed by server".to_string()));
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Send a command and require a reply code in the 2xx/3xx class given by `expected`
+    fn command(&mut self, line: &str, expected: u16) -> Result<String, EmailError> {
+        self.stream.write_all(line.as_bytes())?;
+        self.stream.write_all(b"\r\n")?;
+        self.stream.flush()?;
+        self.expect(expected)
+    }
+
+    fn expect(&mut self, expected: u16) -> Result<String, EmailError> {
+        let (code, text) = self.read_reply()?;
+        if code / 100 != expected / 100 {
+            return Err(EmailError::Smtp(format!("{} {}", code, text)));
+        }
+        Ok(text)
+    }
+}
+
+/// Send an email through the configured SMTP server. Blocking; call from `spawn_blocking`.
+pub fn send_email(config: &SmtpConfig, message: &EmailMessage) -> Result<(), EmailError> {
+    // The addresses go into SMTP commands and headers as they are
+    check_address(&config.from)?;
+    for recipient in &message.to {
+        check_address(recipient)?;
+    }
+
+    let tcp = TcpStream::connect((config.host.as_str(), config.port))
+        .map_err(|e| EmailError::ConnectionFailed(e.to_string()))?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+
+    let connector = TlsConnector::new().map_err(|e| EmailError::Tls(e.to_string()))?;
+
+    let stream = if config.security == SmtpSecurity::Tls {
+        let tls = connector
+            .connect(&config.host, tcp)
+            .map_err(|e| EmailError::Tls(e.to_string()))?;
+        Stream::Tls(Box::new(tls))
+    } else {
+        Stream::Plain(tcp)
+    };
+
+    let mut conn = SmtpConnection {
+        stream,
+        buf: Vec::new(),
+    };
+    conn.expect(220)?;
+    conn.command("EHLO note67.local", 250)?;
+
+    if config.security == SmtpSecurity::StartTls {
+        conn.command("STARTTLS", 220)?;
+        let Stream::Plain(tcp) = conn.stream else {
+            return Err(EmailError::Tls("Unexpected stream state".to_string()));
+        };
+        let tls = connector
+            .connect(&config.host, tcp)
+            .map_err(|e| EmailError::Tls(e.to_string()))?;
+        conn = SmtpConnection {
+            stream: Stream::Tls(Box::new(tls)),
+            buf: Vec::new(),
+        };
+        conn.command("EHLO note67.local", 250)?;
+    }
+
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        let credentials = format!("\0{}\0{}", username, password);
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        conn.command(&format!("AUTH PLAIN {}", encoded), 235)?;
+    }
+
+    conn.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    for recipient in &message.to {
+        conn.command(&format!("RCPT TO:<{}>", recipient), 250)?;
+    }
+    conn.command("DATA", 354)?;
+
+    let body = dot_stuff(&build_mime_message(&config.from, message));
+    conn.stream.write_all(body.as_bytes())?;
+    conn.command(".", 250)?;
+
+    let _ = conn.command("QUIT", 221);
+    Ok(())
+}
+
+/// Reject addresses that could break out of `<...>` in a command or header
+fn check_address(address: &str) -> Result<(), EmailError> {
+    let unsafe_char = |c: char| c.is_control() || c.is_whitespace() || c == '<' || c == '>';
+    if !address.contains('@') || address.chars().any(unsafe_char) {
+        return Err(EmailError::InvalidAddress(address.to_string()));
+    }
+    Ok(())
+}
+
+/// Build a multipart/alternative message with text and HTML parts
+fn build_mime_message(from: &str, message: &EmailMessage) -> String {
+    let boundary = format!("note67-{}", uuid::Uuid::new_v4());
+    let encoder = base64::engine::general_purpose::STANDARD;
+
+    let mut mime = String::new();
+    mime.push_str(&format!("From: <{}>\r\n", from));
+    let to: Vec<String> = message.to.iter().map(|t| format!("<{}>", t)).collect();
+    mime.push_str(&format!("To: {}\r\n", to.join(", ")));
+    mime.push_str(&format!(
+        "Subject: =?UTF-8?B?{}?=\r\n",
+        encoder.encode(&message.subject)
+    ));
+    mime.push_str(&format!("Date: {}\r\n", chrono::Utc::now().to_rfc2822()));
+    mime.push_str(&format!("Message-ID: <{}@note67>\r\n", uuid::Uuid::new_v4()));
+    mime.push_str("MIME-Version: 1.0\r\n");
+    mime.push_str(&format!(
+        "Content-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n",
+        boundary
+    ));
+
+    for (content_type, body) in [
+        ("text/plain", &message.text_body),
+        ("text/html", &message.html_body),
+    ] {
+        mime.push_str(&format!("--{}\r\n", boundary));
+        mime.push_str(&format!("Content-Type: {}; charset=utf-8\r\n", content_type));
+        mime.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        let encoded = encoder.encode(body);
+        for chunk in encoded.as_bytes().chunks(76) {
+            mime.push_str(&String::from_utf8_lossy(chunk));
+            mime.push_str("\r\n");
+        }
+    }
+    mime.push_str(&format!("--{}--\r\n", boundary));
+    mime
+}
+
+/// Escape lines starting with "." so they aren't read as the end of DATA
+fn dot_stuff(body: &str) -> String {
+    body.split("\r\n")
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Pull email addresses out of a note's free-form participants field
+pub fn extract_emails(participants: &str) -> Vec<String> {
+    participants
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace() || c == '<' || c == '>')
+        .map(|s| s.trim_matches(|c: char| c == '"' || c == '(' || c == ')'))
+        .filter(|s| {
+            s.split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'))
+        })
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Build the subject, text, and HTML bodies for a note summary email
+pub fn build_summary_email(
+    title: &str,
+    summary: Option<&Summary>,
+    action_items: &[ActionItem],
+    to: Vec<String>,
+) -> EmailMessage {
+    let top_level: Vec<&ActionItem> = action_items
+        .iter()
+        .filter(|item| item.parent_id.is_none())
+        .collect();
+
+    let mut text = format!("{}\n\n", title);
+    let mut html = format!(
+        "<html><body style=\"font-family: -apple-system, Segoe UI, sans-serif;\"><h2>{}</h2>",
+        escape_html(title)
+    );
+
+    if let Some(summary) = summary {
+        text.push_str(&summary.content);
+        text.push_str("\n\n");
+        html.push_str(&format!(
+            "<div style=\"white-space: pre-wrap;\">{}</div>",
+            escape_html(&summary.content)
+        ));
+    }
+
+    if !top_level.is_empty() {
+        text.push_str("Action items\n");
+        html.push_str("<h3>Action items</h3><ul style=\"list-style: none; padding-left: 0;\">");
+        for item in top_level {
+            let check = if item.done { "☑" } else { "☐" };
+            let due = item
+                .due_date
+                .as_ref()
+                .map(|d| format!(" (due {})", d))
+                .unwrap_or_default();
+            text.push_str(&format!("{} {}{}\n", check, item.text, due));
+            html.push_str(&format!(
+                "<li>{} {}{}</li>",
+                check,
+                escape_html(&item.text),
+                escape_html(&due)
+            ));
+        }
+        html.push_str("</ul>");
+    }
+
+    text.push_str("\n--\nSent from Note67\n");
+    html.push_str("<p style=\"color: #888;\">Sent from Note67</p></body></html>");
+
+    EmailMessage {
+        to,
+        subject: format!("Meeting summary: {}", title),
+        text_body: text,
+        html_body: html,
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_emails() {
+        let emails = extract_emails("Alice <alice@example.com>, Bob, carol@corp.io; dave@localhost");
+        assert_eq!(emails, vec!["alice@example.com", "carol@corp.io"]);
+    }
+
+    #[test]
+    fn test_check_address() {
+        assert!(check_address("alice@example.com").is_ok());
+        for address in [
+            "",
+            "alice",
+            "alice@example.com>\r\nRCPT TO:<eve@example.com",
+            "alice@example.com\nBcc: eve@example.com",
+            "alice@example.com> <eve@example.com",
+        ] {
+            assert!(check_address(address).is_err(), "{:?}", address);
+        }
+    }
+
+    #[test]
+    fn test_dot_stuff() {
+        assert_eq!(dot_stuff("a\r\n.b\r\nc"), "a\r\n..b\r\nc");
+    }
+}
@@ -0,0 +1,396 @@
+//! Native calendar access on macOS via EventKit.
+//! Reads today's meetings (after the user grants calendar access) and watches for
+//! meetings about to start so the UI can offer to record them.
+//! Other platforms report the integration as unsupported.
+
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::Database;
+
+/// Setting key: "false" turns off upcoming-meeting prompts
+pub const SETTING_REMINDERS_ENABLED: &str = "calendar_reminders_enabled";
+
+/// Prompt this many minutes before a meeting starts
+const REMINDER_LEAD_MINUTES: i64 = 5;
+
+/// How often the upcoming-meeting monitor polls the calendar
+const MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A meeting read from the system calendar
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarMeeting {
+    /// EventKit event identifier and start (see `occurrence_id`): the identifier alone is
+    /// shared by every occurrence of a recurring meeting
+    pub id: String,
+    pub title: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    /// "Name <email>" (or whichever of the two is known)
+    pub participants: Vec<String>,
+    pub all_day: bool,
+}
+
+/// Payload of the "upcoming-meeting" event
+#[derive(Debug, Clone, Serialize)]
+pub struct UpcomingMeeting {
+    /// For `create_note_from_meeting`, once the user chooses to record it
+    pub meeting_id: String,
+    pub title: String,
+    pub starts_at: DateTime<Utc>,
+    pub minutes_until: i64,
+}
+
+/// Calendar access status: "authorized", "denied", "restricted", "not_determined"
+/// or "unsupported" when EventKit isn't available
+pub fn authorization_status() -> &'static str {
+    #[cfg(target_os = "macos")]
+    {
+        macos::authorization_status()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        "unsupported"
+    }
+}
+
+/// Ask the user for calendar access. Returns whether access was granted.
+pub fn request_access() -> Result<bool, String> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::request_access()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err("Calendar integration is only available on macOS".to_string())
+    }
+}
+
+/// Timed (non all-day) meetings between `start` and `end`, soonest first
+pub fn meetings_between(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<CalendarMeeting>, String> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::meetings_between(start, end)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (start, end);
+        Err("Calendar integration is only available on macOS".to_string())
+    }
+}
+
+/// Today's meetings in the local timezone
+pub fn todays_meetings() -> Result<Vec<CalendarMeeting>, String> {
+    let today = Local::now().date_naive();
+    let start = Local
+        .from_local_datetime(&today.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    meetings_between(start, start + chrono::Duration::days(1))
+}
+
+/// Id of one occurrence of an EventKit event, the form ICS occurrences are keyed by
+fn occurrence_id(identifier: &str, starts_at: DateTime<Utc>) -> String {
+    format!("{}/{}", identifier, starts_at.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// The meeting with `id` (see `CalendarMeeting::id`) starting within a day of now
+pub fn find_meeting(id: &str) -> Result<Option<CalendarMeeting>, String> {
+    let now = Utc::now();
+    let day = chrono::Duration::days(1);
+    Ok(meetings_between(now - day, now + day)?
+        .into_iter()
+        .find(|meeting| meeting.id == id))
+}
+
+/// Create (or refresh) the note stub for a calendar meeting and return its note id
+pub fn prefill_note(db: &Database, meeting: &CalendarMeeting) -> anyhow::Result<String> {
+    let participants = (!meeting.participants.is_empty()).then(|| meeting.participants.join(", "));
+    db.upsert_calendar_event(
+        &meeting.id,
+        &meeting.title,
+        meeting.starts_at,
+        meeting.ends_at,
+        meeting.location.as_deref(),
+        participants.as_deref(),
+        meeting.notes.as_deref(),
+        "eventkit",
+        false,
+    )?;
+    db.get_calendar_event_note_id(&meeting.id)?
+        .ok_or_else(|| anyhow::anyhow!("Calendar event has no note"))
+}
+
+/// Start polling the calendar for meetings about to begin (call from setup).
+/// Emits "upcoming-meeting" once per meeting; its note is created only if the user takes
+/// up the offer to record it.
+pub fn start_upcoming_meeting_monitor(app: &AppHandle) {
+    if authorization_status() == "unsupported" {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut prompted: HashSet<String> = HashSet::new();
+
+        loop {
+            thread::sleep(MONITOR_INTERVAL);
+
+            if authorization_status() != "authorized" {
+                continue;
+            }
+            let Some(db) = app.try_state::<Database>() else {
+                continue;
+            };
+            if db
+                .get_setting(SETTING_REMINDERS_ENABLED)
+                .ok()
+                .flatten()
+                .as_deref()
+                == Some("false")
+            {
+                continue;
+            }
+
+            let now = Utc::now();
+            let meetings =
+                match meetings_between(now, now + chrono::Duration::minutes(REMINDER_LEAD_MINUTES))
+                {
+                    Ok(meetings) => meetings,
+                    Err(e) => {
+                        tracing::warn!("Failed to read calendar: {}", e);
+                        continue;
+                    }
+                };
+
+            for meeting in meetings {
+                // Only meetings that haven't started yet, once each
+                if meeting.starts_at < now || !prompted.insert(meeting.id.clone()) {
+                    continue;
+                }
+
+                tracing::info!("Upcoming meeting: {}", meeting.title);
+                let _ = app.emit(
+                    "upcoming-meeting",
+                    UpcomingMeeting {
+                        meeting_id: meeting.id.clone(),
+                        title: meeting.title.clone(),
+                        starts_at: meeting.starts_at,
+                        minutes_until: (meeting.starts_at - now).num_minutes().max(0),
+                    },
+                );
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::{CStr, c_char};
+    use std::sync::mpsc;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use objc2::runtime::{AnyClass, AnyObject, Bool};
+    use objc2::{msg_send, sel};
+    use objc2_foundation::NSError;
+
+    use super::CalendarMeeting;
+
+    /// EKEntityTypeEvent
+    const ENTITY_TYPE_EVENT: isize = 0;
+
+    fn event_store_class() -> Option<&'static AnyClass> {
+        AnyClass::get(c"EKEventStore")
+    }
+
+    pub fn authorization_status() -> &'static str {
+        let Some(class) = event_store_class() else {
+            return "unsupported";
+        };
+        // EKAuthorizationStatus: 0 not determined, 1 restricted, 2 denied,
+        // 3 authorized / full access (macOS 14+), 4 write-only
+        let status: isize =
+            unsafe { msg_send![class, authorizationStatusForEntityType: ENTITY_TYPE_EVENT] };
+        match status {
+            0 => "not_determined",
+            1 => "restricted",
+            3 => "authorized",
+            _ => "denied",
+        }
+    }
+
+    pub fn request_access() -> Result<bool, String> {
+        let class = event_store_class().ok_or("EventKit is not available")?;
+        let (tx, rx) = mpsc::channel();
+
+        unsafe {
+            let store: *mut AnyObject = msg_send![class, new];
+            if store.is_null() {
+                return Err("Failed to create event store".to_string());
+            }
+
+            let block = block2::RcBlock::new(move |granted: Bool, _error: *mut NSError| {
+                let _ = tx.send(granted.as_bool());
+            });
+
+            // macOS 14 split calendar access into full and write-only
+            let responds: Bool = msg_send![store, respondsToSelector: sel!(requestFullAccessToEventsWithCompletion:)];
+            if responds.as_bool() {
+                let _: () = msg_send![store, requestFullAccessToEventsWithCompletion: &*block];
+            } else {
+                let _: () = msg_send![
+                    store,
+                    requestAccessToEntityType: ENTITY_TYPE_EVENT,
+                    completion: &*block
+                ];
+            }
+
+            // Wait for the user to answer the permission prompt
+            let granted = rx
+                .recv_timeout(std::time::Duration::from_secs(120))
+                .unwrap_or(false);
+            let _: () = msg_send![store, release];
+            Ok(granted)
+        }
+    }
+
+    pub fn meetings_between(
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarMeeting>, String> {
+        let class = event_store_class().ok_or("EventKit is not available")?;
+        if authorization_status() != "authorized" {
+            return Err("Calendar access has not been granted".to_string());
+        }
+
+        let mut meetings = Vec::new();
+        unsafe {
+            let store: *mut AnyObject = msg_send![class, new];
+            if store.is_null() {
+                return Err("Failed to create event store".to_string());
+            }
+
+            let date_class = AnyClass::get(c"NSDate").ok_or("NSDate is not available")?;
+            let start_date: *mut AnyObject =
+                msg_send![date_class, dateWithTimeIntervalSince1970: start.timestamp() as f64];
+            let end_date: *mut AnyObject =
+                msg_send![date_class, dateWithTimeIntervalSince1970: end.timestamp() as f64];
+            let no_calendars: *mut AnyObject = std::ptr::null_mut();
+
+            let predicate: *mut AnyObject = msg_send![
+                store,
+                predicateForEventsWithStartDate: start_date,
+                endDate: end_date,
+                calendars: no_calendars
+            ];
+            let events: *mut AnyObject = msg_send![store, eventsMatchingPredicate: predicate];
+
+            let count: usize = if events.is_null() {
+                0
+            } else {
+                msg_send![events, count]
+            };
+            for i in 0..count {
+                let event: *mut AnyObject = msg_send![events, objectAtIndex: i];
+                if let Some(meeting) = read_event(event) {
+                    meetings.push(meeting);
+                }
+            }
+
+            let _: () = msg_send![store, release];
+        }
+
+        meetings.retain(|m| !m.all_day);
+        meetings.sort_by_key(|m| m.starts_at);
+        Ok(meetings)
+    }
+
+    unsafe fn read_event(event: *mut AnyObject) -> Option<CalendarMeeting> {
+        if event.is_null() {
+            return None;
+        }
+        unsafe {
+            let id = string_prop(msg_send![event, eventIdentifier])?;
+            let start_date: *mut AnyObject = msg_send![event, startDate];
+            let end_date: *mut AnyObject = msg_send![event, endDate];
+            let all_day: Bool = msg_send![event, isAllDay];
+
+            let mut participants = Vec::new();
+            let attendees: *mut AnyObject = msg_send![event, attendees];
+            let count: usize = if attendees.is_null() {
+                0
+            } else {
+                msg_send![attendees, count]
+            };
+            for i in 0..count {
+                let attendee: *mut AnyObject = msg_send![attendees, objectAtIndex: i];
+                let name = string_prop(msg_send![attendee, name]);
+                let url: *mut AnyObject = msg_send![attendee, URL];
+                let email = if url.is_null() {
+                    None
+                } else {
+                    string_prop(msg_send![url, resourceSpecifier]).filter(|s| s.contains('@'))
+                };
+                match (name, email) {
+                    (Some(name), Some(email)) if name != email => {
+                        participants.push(format!("{} <{}>", name, email))
+                    }
+                    (_, Some(email)) => participants.push(email),
+                    (Some(name), None) => participants.push(name),
+                    (None, None) => {}
+                }
+            }
+
+            let starts_at = date_to_utc(start_date)?;
+            Some(CalendarMeeting {
+                id: super::occurrence_id(&id, starts_at),
+                title: string_prop(msg_send![event, title])
+                    .filter(|t| !t.trim().is_empty())
+                    .unwrap_or_else(|| "Untitled Meeting".to_string()),
+                starts_at,
+                ends_at: date_to_utc(end_date),
+                location: string_prop(msg_send![event, location]).filter(|l| !l.is_empty()),
+                notes: string_prop(msg_send![event, notes]).filter(|n| !n.is_empty()),
+                participants,
+                all_day: all_day.as_bool(),
+            })
+        }
+    }
+
+    unsafe fn string_prop(value: *mut AnyObject) -> Option<String> {
+        if value.is_null() {
+            return None;
+        }
+        let utf8: *const c_char = unsafe { msg_send![value, UTF8String] };
+        if utf8.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr(utf8) }
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+
+    unsafe fn date_to_utc(date: *mut AnyObject) -> Option<DateTime<Utc>> {
+        if date.is_null() {
+            return None;
+        }
+        let secs: f64 = unsafe { msg_send![date, timeIntervalSince1970] };
+        Utc.timestamp_opt(secs as i64, 0).single()
+    }
+}
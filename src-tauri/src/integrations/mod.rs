@@ -0,0 +1,7 @@
+pub mod calendar;
+pub mod converter;
+pub mod email;
+pub mod eventkit;
+pub mod publish;
+pub mod s3;
+pub mod slack;
@@ -0,0 +1,483 @@
+//! Publishing a note as a standalone HTML page: a local folder, a GitHub Gist, or an SFTP
+//! server. The page is self-contained (inline CSS, no scripts) apart from the optional
+//! audio file, which is published next to it.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::process::no_window;
+
+const GIST_API_URL: &str = "https://api.github.com/gists";
+
+/// Keychain key of the GitHub token used to create gists (needs the `gist` scope)
+pub const SECRET_GIST_TOKEN: &str = "github_gist_token";
+
+#[derive(Error, Debug)]
+pub enum PublishError {
+    #[error("No GitHub token is configured. Add one with the gist scope in Settings.")]
+    NoGistToken,
+    #[error("Audio can't be attached to a gist")]
+    AudioNotSupported,
+    #[error("Request failed: {0}")]
+    RequestFailed(String),
+    #[error("GitHub API error: {0}")]
+    Api(String),
+    #[error("sftp was not found. Install OpenSSH to publish over SFTP.")]
+    SftpNotFound,
+    #[error("SFTP upload failed: {0}")]
+    Sftp(String),
+    #[error("Invalid SFTP {0}")]
+    InvalidSftpTarget(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Where `publish_note` sends the page
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PublishDestination {
+    /// A local directory; the export directory when `path` is not given
+    Folder { path: Option<String> },
+    Gist {
+        #[serde(default)]
+        public: bool,
+    },
+    /// Uploaded with the system `sftp` client, so authentication uses the user's SSH
+    /// keys/agent (there is no password prompt). `base_url` is the web address that
+    /// serves `remote_dir`, used to build the returned URL.
+    Sftp {
+        host: String,
+        port: Option<u16>,
+        username: String,
+        remote_dir: String,
+        base_url: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublishResult {
+    /// Path of the page for folders, its URL otherwise (the remote path for SFTP without
+    /// a base URL)
+    pub location: String,
+    /// Files that were written/uploaded
+    pub files: Vec<String>,
+}
+
+/// Render the page from the export template context (see `build_template_context`).
+/// `audio_src` is the relative URL of the audio file, if one is published with it.
+pub fn render_page(context: &Value, audio_src: Option<&str>) -> String {
+    let text = |key: &str| context[key].as_str().unwrap_or_default();
+    let title = if text("title").is_empty() {
+        "Untitled"
+    } else {
+        text("title")
+    };
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<meta name=\"generator\" content=\"Note67\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(&format!("<style>{}</style>\n", PAGE_CSS));
+    html.push_str("</head>\n<body>\n<main>\n<header>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+
+    let mut meta = vec![escape_html(text("date"))];
+    if let Some(duration) = context["duration"].as_str() {
+        meta.push(escape_html(duration));
+    }
+    html.push_str(&format!("<p class=\"meta\">{}</p>\n", meta.join(" · ")));
+    if let Some(participants) = context["participants"].as_array().filter(|p| !p.is_empty()) {
+        let names: Vec<String> = participants
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(escape_html)
+            .collect();
+        html.push_str(&format!(
+            "<p class=\"meta\">With {}</p>\n",
+            names.join(", ")
+        ));
+    }
+    if let Some(tags) = context["tags"].as_array().filter(|t| !t.is_empty()) {
+        html.push_str("<p class=\"tags\">");
+        for tag in tags.iter().filter_map(|t| t.as_str()) {
+            html.push_str(&format!("<span>#{}</span> ", escape_html(tag)));
+        }
+        html.push_str("</p>\n");
+    }
+    html.push_str("</header>\n");
+
+    if let Some(description) = context["description"].as_str().filter(|d| !d.is_empty()) {
+        html.push_str(&format!(
+            "<p class=\"description\">{}</p>\n",
+            escape_html(description)
+        ));
+    }
+
+    if let Some(src) = audio_src {
+        html.push_str(&format!(
+            "<audio controls preload=\"metadata\" src=\"{}\"></audio>\n",
+            escape_html(src)
+        ));
+    }
+
+    // Only the latest summary of each type (the context lists newest first)
+    let mut seen = Vec::new();
+    for summary in context["summaries"].as_array().into_iter().flatten() {
+        let summary_type = summary["type"].as_str().unwrap_or_default();
+        if seen.contains(&summary_type) {
+            continue;
+        }
+        seen.push(summary_type);
+        html.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n{}</section>\n",
+            escape_html(summary["label"].as_str().unwrap_or("Summary")),
+            markdown_to_html(summary["content"].as_str().unwrap_or_default())
+        ));
+    }
+
+    if let Some(tasks) = context["tasks"].as_array().filter(|t| !t.is_empty()) {
+        html.push_str("<section>\n<h2>Tasks</h2>\n<ul class=\"tasks\">\n");
+        for task in tasks {
+            let mut item = escape_html(task["text"].as_str().unwrap_or_default());
+            if let Some(assignee) = task["assignee"].as_str() {
+                item.push_str(&format!(" <em>({})</em>", escape_html(assignee)));
+            }
+            if let Some(due) = task["due_date"].as_str() {
+                item.push_str(&format!(" <em>due {}</em>", escape_html(due)));
+            }
+            let check = if task["done"].as_bool().unwrap_or(false) {
+                "☑"
+            } else {
+                "☐"
+            };
+            html.push_str(&format!("<li>{} {}</li>\n", check, item));
+        }
+        html.push_str("</ul>\n</section>\n");
+    }
+
+    if let Some(segments) = context["segments"].as_array().filter(|s| !s.is_empty()) {
+        html.push_str(&format!(
+            "<details>\n<summary>Transcript ({} segments)</summary>\n",
+            segments.len()
+        ));
+        for segment in segments {
+            html.push_str(&format!(
+                "<p><span class=\"ts\">{}</span>",
+                escape_html(segment["timestamp"].as_str().unwrap_or_default())
+            ));
+            if let Some(speaker) = segment["speaker"].as_str() {
+                html.push_str(&format!(" <strong>{}:</strong>", escape_html(speaker)));
+            }
+            html.push_str(&format!(
+                " {}</p>\n",
+                escape_html(segment["text"].as_str().unwrap_or_default())
+            ));
+        }
+        html.push_str("</details>\n");
+    }
+
+    html.push_str("<footer>Published with Note67</footer>\n</main>\n</body>\n</html>\n");
+    html
+}
+
+const PAGE_CSS: &str = "\
+body{margin:0;background:#fafafa;color:#1f2328;font:16px/1.6 -apple-system,'Segoe UI',Roboto,sans-serif}\
+main{max-width:760px;margin:0 auto;padding:48px 24px}\
+h1{margin:0 0 4px;font-size:2em;line-height:1.2}\
+h2{margin-top:2em;font-size:1.25em;border-bottom:1px solid #e5e7eb;padding-bottom:4px}\
+.meta{margin:0;color:#6b7280}\
+.tags span{display:inline-block;margin-right:4px;padding:0 8px;border-radius:10px;background:#eef2ff;color:#4338ca;font-size:.85em}\
+.description{font-style:italic}\
+audio{width:100%;margin:16px 0}\
+ul.tasks{list-style:none;padding-left:0}\
+details{margin-top:2em}\
+summary{cursor:pointer;font-weight:600;font-size:1.1em}\
+details p{margin:.4em 0}\
+.ts{color:#9ca3af;font-family:ui-monospace,monospace;font-size:.85em}\
+code{background:#f3f4f6;padding:0 4px;border-radius:4px}\
+footer{margin-top:48px;color:#9ca3af;font-size:.85em}\
+@media(prefers-color-scheme:dark){body{background:#111827;color:#e5e7eb}h2{border-color:#374151}\
+.tags span{background:#312e81;color:#c7d2fe}code{background:#1f2937}}";
+
+/// Convert the subset of markdown the AI summaries use (headings, bullet, numbered and
+/// task lists, bold, inline code) into HTML
+fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut list: Option<&str> = None;
+    let mut paragraph: Vec<String> = Vec::new();
+
+    fn flush(html: &mut String, paragraph: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", paragraph.join(" ")));
+            paragraph.clear();
+        }
+    }
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        let (tag, item) = if let Some(rest) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            ("ul", Some(rest))
+        } else if let Some((number, rest)) = trimmed.split_once(". ")
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+        {
+            ("ol", Some(rest))
+        } else {
+            ("", None)
+        };
+
+        if list.is_some_and(|open| item.is_none() || open != tag) {
+            html.push_str(&format!("</{}>\n", list.take().unwrap_or_default()));
+        }
+
+        if let Some(item) = item {
+            flush(&mut html, &mut paragraph);
+            if list.is_none() {
+                html.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            let item = if let Some(task) = item.strip_prefix("[ ] ") {
+                format!("☐ {}", inline_html(task))
+            } else if let Some(task) = item
+                .strip_prefix("[x] ")
+                .or_else(|| item.strip_prefix("[X] "))
+            {
+                format!("☑ {}", inline_html(task))
+            } else {
+                inline_html(item)
+            };
+            html.push_str(&format!("<li>{}</li>\n", item));
+        } else if trimmed.starts_with('#') {
+            flush(&mut html, &mut paragraph);
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            // The page uses h1/h2 itself, so summary headings start at h3
+            let level = (level + 2).min(6);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                inline_html(trimmed.trim_start_matches('#').trim())
+            ));
+        } else if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph);
+        } else {
+            paragraph.push(inline_html(trimmed));
+        }
+    }
+
+    flush(&mut html, &mut paragraph);
+    if let Some(tag) = list {
+        html.push_str(&format!("</{}>\n", tag));
+    }
+    html
+}
+
+/// Escape a line and apply `**bold**` and `` `code` `` (only when the markers are paired)
+fn inline_html(text: &str) -> String {
+    let mut html = escape_html(text);
+    for (marker, tag) in [("**", "strong"), ("`", "code")] {
+        let parts: Vec<&str> = html.split(marker).collect();
+        if parts.len().is_multiple_of(2) {
+            continue;
+        }
+        html = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                if i % 2 == 1 {
+                    format!("<{tag}>{part}</{tag}>")
+                } else {
+                    part.to_string()
+                }
+            })
+            .collect();
+    }
+    html
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[derive(Deserialize)]
+struct GistResponse {
+    html_url: String,
+}
+
+/// Create a gist containing the page and return its URL
+pub async fn create_gist(
+    token: &str,
+    description: &str,
+    filename: &str,
+    html: &str,
+    public: bool,
+) -> Result<String, PublishError> {
+    let mut files = serde_json::Map::new();
+    files.insert(filename.to_string(), serde_json::json!({ "content": html }));
+
+    let response = reqwest::Client::new()
+        .post(GIST_API_URL)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "Note67")
+        .json(&serde_json::json!({
+            "description": description,
+            "public": public,
+            "files": files,
+        }))
+        .send()
+        .await
+        .map_err(|e| PublishError::RequestFailed(e.to_string()))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(PublishError::Api(format!("{} {}", status, body)));
+    }
+
+    let gist: GistResponse = response
+        .json()
+        .await
+        .map_err(|e| PublishError::RequestFailed(e.to_string()))?;
+    Ok(gist.html_url)
+}
+
+/// Upload `files` into `remote_dir` with the system `sftp` client in batch mode
+pub fn upload_sftp(
+    host: &str,
+    port: Option<u16>,
+    username: &str,
+    remote_dir: &str,
+    files: &[&Path],
+) -> Result<(), PublishError> {
+    check_sftp_target("host", host)?;
+    check_sftp_target("username", username)?;
+
+    let remote_dir = remote_dir.trim_end_matches('/');
+    // A leading `-` lets the batch continue when the directory already exists
+    let mut batch = format!("-mkdir {}\n", quote_sftp(remote_dir)?);
+    for file in files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        batch.push_str(&format!(
+            "put {} {}\n",
+            quote_sftp(&file.to_string_lossy())?,
+            quote_sftp(&format!("{}/{}", remote_dir, name))?
+        ));
+    }
+
+    let mut child = no_window(&mut Command::new("sftp"))
+        .args(["-b", "-", "-o", "BatchMode=yes"])
+        .args(["-P", &port.unwrap_or(22).to_string()])
+        .arg("--")
+        .arg(format!("{}@{}", username, host))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => PublishError::SftpNotFound,
+            _ => PublishError::Io(e),
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(batch.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(PublishError::Sftp(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reject a host or username that sftp would read as an option or that isn't one word
+fn check_sftp_target(what: &str, value: &str) -> Result<(), PublishError> {
+    if value.is_empty()
+        || value.starts_with('-')
+        || value.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(PublishError::InvalidSftpTarget(format!("{}: {:?}", what, value)));
+    }
+    Ok(())
+}
+
+/// Quote a path for an sftp batch file. Control characters are refused: a line break would
+/// end the command and start another one.
+fn quote_sftp(path: &str) -> Result<String, PublishError> {
+    if path.chars().any(char::is_control) {
+        return Err(PublishError::InvalidSftpTarget(format!("path: {:?}", path)));
+    }
+    Ok(format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\"")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_html() {
+        let md = "## Key Points\n- **Budget** approved\n- [ ] Send `deck`\n\n1. First\n2. Second\nDone <now>";
+        assert_eq!(
+            markdown_to_html(md),
+            "<h4>Key Points</h4>\n<ul>\n<li><strong>Budget</strong> approved</li>\n\
+             <li>☐ Send <code>deck</code></li>\n</ul>\n<ol>\n<li>First</li>\n<li>Second</li>\n\
+             </ol>\n<p>Done &lt;now&gt;</p>\n"
+        );
+        assert_eq!(inline_html("a ** b"), "a ** b");
+    }
+
+    #[test]
+    fn test_render_page() {
+        let context = serde_json::json!({
+            "title": "Standup <1>",
+            "date": "Jan 1, 2025",
+            "duration": "15m",
+            "participants": ["Ana"],
+            "tags": ["team"],
+            "summaries": [
+                {"type": "overview", "label": "Overview", "content": "New"},
+                {"type": "overview", "label": "Overview", "content": "Old"}
+            ],
+            "tasks": [],
+            "segments": [{"timestamp": "00:01", "text": "Hi", "speaker": "Ana"}]
+        });
+        let html = render_page(&context, Some("audio.wav"));
+        assert!(html.contains("<title>Standup &lt;1&gt;</title>"));
+        assert!(html.contains("<p>New</p>"));
+        assert!(!html.contains("Old"));
+        assert!(!html.contains("Tasks"));
+        assert!(html.contains("<summary>Transcript (1 segments)</summary>"));
+        assert!(html.contains("src=\"audio.wav\""));
+    }
+
+    #[test]
+    fn test_check_sftp_target() {
+        assert!(check_sftp_target("host", "example.com").is_ok());
+        assert!(check_sftp_target("username", "ana.b").is_ok());
+        assert!(check_sftp_target("host", "-oProxyCommand=touch x").is_err());
+        assert!(check_sftp_target("host", "example.com\nput x").is_err());
+        assert!(check_sftp_target("username", "ana b").is_err());
+        assert!(check_sftp_target("username", "").is_err());
+    }
+
+    #[test]
+    fn test_sftp_rejects_line_breaks_in_paths() {
+        assert_eq!(quote_sftp("notes/a \"b\"").unwrap(), "\"notes/a \\\"b\\\"\"");
+        let result = upload_sftp("example.com", None, "ana", "notes\nrm /home/ana/x", &[]);
+        assert!(matches!(result, Err(PublishError::InvalidSftpTarget(_))));
+        let file = Path::new("/tmp/Standup\r-rm x.html");
+        let result = upload_sftp("example.com", None, "ana", "notes", &[file]);
+        assert!(matches!(result, Err(PublishError::InvalidSftpTarget(_))));
+    }
+}
@@ -0,0 +1,335 @@
+//! Minimal client for S3-compatible object storage (AWS S3, MinIO, Cloudflare R2, B2, ...)
+//! Requests are signed with AWS Signature Version 4
+
+use chrono::Utc;
+use regex::Regex;
+use ring::{digest, hmac};
+use serde::Serialize;
+
+pub const SETTING_ENDPOINT: &str = "s3_endpoint";
+pub const SETTING_REGION: &str = "s3_region";
+pub const SETTING_BUCKET: &str = "s3_bucket";
+pub const SETTING_PREFIX: &str = "s3_prefix";
+pub const SETTING_ACCESS_KEY_ID: &str = "s3_access_key_id";
+/// "true" to address the bucket as `<endpoint>/<bucket>` instead of `<bucket>.<host>`
+pub const SETTING_PATH_STYLE: &str = "s3_path_style";
+
+/// Keychain key for the secret access key
+pub const SECRET_SECRET_KEY: &str = "s3_secret_access_key";
+
+#[derive(Debug, thiserror::Error)]
+pub enum S3Error {
+    #[error("S3 backup is not configured")]
+    NotConfigured,
+    #[error("Invalid S3 endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("S3 request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("S3 returned HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+}
+
+/// An object returned by `list_objects`
+#[derive(Debug, Clone, Serialize)]
+pub struct S3Object {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: String,
+}
+
+pub struct S3Client {
+    http: reqwest::Client,
+    scheme: String,
+    /// Host (with port if non-default) requests are sent to
+    host: String,
+    /// Leading path for path-style addressing ("/<bucket>"), empty otherwise
+    base_path: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Client {
+    pub fn new(
+        endpoint: &str,
+        region: &str,
+        bucket: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+        path_style: bool,
+    ) -> Result<Self, S3Error> {
+        if bucket.is_empty() || access_key_id.is_empty() || secret_access_key.is_empty() {
+            return Err(S3Error::NotConfigured);
+        }
+
+        let url = reqwest::Url::parse(endpoint.trim_end_matches('/'))
+            .map_err(|e| S3Error::InvalidEndpoint(e.to_string()))?;
+        let endpoint_host = url
+            .host_str()
+            .ok_or_else(|| S3Error::InvalidEndpoint(endpoint.to_string()))?;
+        let endpoint_host = match url.port() {
+            Some(port) => format!("{}:{}", endpoint_host, port),
+            None => endpoint_host.to_string(),
+        };
+
+        let (host, base_path) = if path_style {
+            (endpoint_host, format!("/{}", uri_encode(bucket, false)))
+        } else {
+            (format!("{}.{}", bucket, endpoint_host), String::new())
+        };
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            scheme: url.scheme().to_string(),
+            host,
+            base_path,
+            region: if region.is_empty() { "us-east-1" } else { region }.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+        })
+    }
+
+    pub async fn put_object(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), S3Error> {
+        let response = self
+            .request(reqwest::Method::PUT, key, &[], body, Some(content_type))
+            .await?;
+        check_status(response).await?;
+        Ok(())
+    }
+
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>, S3Error> {
+        let response = self
+            .request(reqwest::Method::GET, key, &[], Vec::new(), None)
+            .await?;
+        let response = check_status(response).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// All objects whose key starts with `prefix` (follows pagination)
+    pub async fn list_objects(&self, prefix: &str) -> Result<Vec<S3Object>, S3Error> {
+        let contents_re = Regex::new(r"(?s)<Contents>(.*?)</Contents>").expect("valid regex");
+        let field = |xml: &str, name: &str| -> Option<String> {
+            let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+            let end = xml[start..].find(&format!("</{}>", name))? + start;
+            Some(xml_unescape(&xml[start..end]))
+        };
+
+        let mut objects = Vec::new();
+        let mut continuation: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+            if let Some(token) = &continuation {
+                query.push(("continuation-token".to_string(), token.clone()));
+            }
+
+            let response = self
+                .request(reqwest::Method::GET, "", &query, Vec::new(), None)
+                .await?;
+            let xml = check_status(response).await?.text().await?;
+
+            for captures in contents_re.captures_iter(&xml) {
+                let entry = &captures[1];
+                if let Some(key) = field(entry, "Key") {
+                    objects.push(S3Object {
+                        key,
+                        size: field(entry, "Size").and_then(|s| s.parse().ok()).unwrap_or(0),
+                        last_modified: field(entry, "LastModified").unwrap_or_default(),
+                    });
+                }
+            }
+
+            continuation = field(&xml, "NextContinuationToken");
+            if field(&xml, "IsTruncated").as_deref() != Some("true") || continuation.is_none() {
+                break;
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(String, String)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response, S3Error> {
+        let uri = format!("{}/{}", self.base_path, uri_encode(key, false));
+        let mut pairs: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        pairs.sort();
+        let canonical_query = pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex(digest::digest(&digest::SHA256, &body).as_ref());
+        let headers = [
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        let authorization = authorization_header(
+            method.as_str(),
+            &uri,
+            &canonical_query,
+            &headers,
+            &payload_hash,
+            &amz_date,
+            &self.region,
+            "s3",
+            &self.access_key_id,
+            &self.secret_access_key,
+        );
+
+        let mut url = format!("{}://{}{}", self.scheme, self.host, uri);
+        if !canonical_query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query);
+        }
+
+        let mut request = self
+            .http
+            .request(method, url)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header("Authorization", authorization)
+            .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
+        }
+        Ok(request.send().await?)
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, S3Error> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    Err(S3Error::Status { status, body })
+}
+
+/// Build the SigV4 `Authorization` header. `headers` must be lowercase and sorted by name.
+#[allow(clippy::too_many_arguments)]
+fn authorization_header(
+    method: &str,
+    canonical_uri: &str,
+    canonical_query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    amz_date: &str,
+    region: &str,
+    service: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, canonical_uri, canonical_query, canonical_headers, signed_headers, payload_hash
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+
+    let sign = |key: &[u8], data: &str| {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+    };
+    let k_date = sign(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let k_region = sign(k_date.as_ref(), region);
+    let k_service = sign(k_region.as_ref(), service);
+    let k_signing = sign(k_service.as_ref(), "aws4_request");
+    let signature = hex(sign(k_signing.as_ref(), &string_to_sign).as_ref());
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key_id, scope, signed_headers, signature
+    )
+}
+
+/// SigV4 URI encoding: everything but unreserved characters, and '/' unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization_header() {
+        // "get-vanilla" from the AWS SigV4 test suite
+        let empty_hash = hex(digest::digest(&digest::SHA256, b"").as_ref());
+        let header = authorization_header(
+            "GET",
+            "/",
+            "",
+            &[("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")],
+            &empty_hash,
+            "20150830T123600Z",
+            "us-east-1",
+            "service",
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+        );
+        assert_eq!(
+            header,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        assert_eq!(uri_encode("notes/a b+c.gz", false), "notes/a%20b%2Bc.gz");
+    }
+}
@@ -0,0 +1,217 @@
+//! Slack client for posting note summaries.
+//! Supports an incoming webhook URL (fixed channel) or a bot token (chat.postMessage to any channel).
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::db::models::{ActionItem, Summary};
+
+const SLACK_POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+/// Keychain keys of the Slack credentials (see `secrets.rs`)
+pub const SECRET_WEBHOOK_URL: &str = "slack_webhook_url";
+pub const SECRET_BOT_TOKEN: &str = "slack_bot_token";
+
+/// Settings keys used by the Slack integration
+pub const SETTING_DEFAULT_CHANNEL: &str = "slack_default_channel";
+pub const SETTING_AUTO_POST: &str = "slack_auto_post_summary";
+
+#[derive(Error, Debug)]
+pub enum SlackError {
+    #[error("Slack is not configured. Add a webhook URL or bot token in Settings.")]
+    NotConfigured,
+    #[error("A channel is required when posting with a bot token")]
+    MissingChannel,
+    #[error("Request failed: {0}")]
+    RequestFailed(String),
+    #[error("Slack API error: {0}")]
+    Api(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct PostMessageResponse {
+    ok: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+pub enum SlackAuth {
+    Webhook(String),
+    BotToken(String),
+}
+
+pub struct SlackClient {
+    client: reqwest::Client,
+    auth: SlackAuth,
+}
+
+impl SlackClient {
+    /// Build a client from stored settings. A bot token takes precedence over a webhook URL.
+    pub fn from_settings(
+        webhook_url: Option<String>,
+        bot_token: Option<String>,
+    ) -> Result<Self, SlackError> {
+        let auth = match (
+            bot_token.filter(|t| !t.trim().is_empty()),
+            webhook_url.filter(|u| !u.trim().is_empty()),
+        ) {
+            (Some(token), _) => SlackAuth::BotToken(token),
+            (None, Some(url)) => SlackAuth::Webhook(url),
+            (None, None) => return Err(SlackError::NotConfigured),
+        };
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            auth,
+        })
+    }
+
+    /// Post a mrkdwn message. `channel` is ignored for webhooks (the channel is fixed
+    /// when the webhook is created) and required for bot tokens.
+    pub async fn post_message(&self, channel: Option<&str>, text: &str) -> Result<(), SlackError> {
+        match &self.auth {
+            SlackAuth::Webhook(url) => {
+                let response = self
+                    .client
+                    .post(url)
+                    .json(&serde_json::json!({ "text": text, "mrkdwn": true }))
+                    .send()
+                    .await
+                    .map_err(|e| SlackError::RequestFailed(e.to_string()))?;
+
+                if !response.status().is_success() {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(SlackError::Api(body));
+                }
+                Ok(())
+            }
+            SlackAuth::BotToken(token) => {
+                let channel = channel
+                    .filter(|c| !c.trim().is_empty())
+                    .ok_or(SlackError::MissingChannel)?;
+
+                let response = self
+                    .client
+                    .post(SLACK_POST_MESSAGE_URL)
+                    .bearer_auth(token)
+                    .json(&serde_json::json!({ "channel": channel, "text": text, "mrkdwn": true }))
+                    .send()
+                    .await
+                    .map_err(|e| SlackError::RequestFailed(e.to_string()))?;
+
+                let result: PostMessageResponse = response
+                    .json()
+                    .await
+                    .map_err(|e| SlackError::RequestFailed(e.to_string()))?;
+
+                if !result.ok {
+                    return Err(SlackError::Api(
+                        result.error.unwrap_or_else(|| "unknown_error".to_string()),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Format a note summary and its action items as a Slack mrkdwn message.
+/// Action items are rendered as a checklist. All note text is escaped, so it can't
+/// mention `<!channel>` or post a disguised `<url|link>`.
+pub fn format_summary_message(
+    title: &str,
+    summary: Option<&Summary>,
+    action_items: &[ActionItem],
+) -> String {
+    let mut text = format!("*{}*\n", escape(title));
+
+    if let Some(summary) = summary {
+        text.push('\n');
+        text.push_str(&markdown_to_mrkdwn(&summary.content));
+        text.push('\n');
+    }
+
+    let top_level: Vec<&ActionItem> = action_items
+        .iter()
+        .filter(|item| item.parent_id.is_none())
+        .collect();
+    if !top_level.is_empty() {
+        text.push_str("\n*Action items*\n");
+        for item in top_level {
+            let check = if item.done { "☑" } else { "☐" };
+            text.push_str(&format!("{} {}", check, escape(&item.text)));
+            if let Some(due) = &item.due_date {
+                text.push_str(&format!(" _(due {})_", escape(due)));
+            }
+            text.push('\n');
+        }
+    }
+
+    text.trim_end().to_string()
+}
+
+/// Convert the subset of markdown the AI summaries use into Slack mrkdwn
+fn markdown_to_mrkdwn(markdown: &str) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            let line = escape(line);
+            let trimmed = line.trim_start();
+            let line = if let Some(heading) = trimmed.strip_prefix('#') {
+                format!("*{}*", heading.trim_start_matches('#').trim())
+            } else if let Some(task) = trimmed
+                .strip_prefix("- [ ] ")
+                .or_else(|| trimmed.strip_prefix("* [ ] "))
+            {
+                format!("☐ {}", task)
+            } else if let Some(task) = trimmed
+                .strip_prefix("- [x] ")
+                .or_else(|| trimmed.strip_prefix("- [X] "))
+            {
+                format!("☑ {}", task)
+            } else if let Some(item) = trimmed
+                .strip_prefix("- ")
+                .or_else(|| trimmed.strip_prefix("* "))
+            {
+                format!("• {}", item)
+            } else {
+                line.clone()
+            };
+            line.replace("**", "*")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape the characters Slack reads as control sequences in message text
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_mrkdwn() {
+        let md = "## Key Points\n- **Budget** approved\n- [ ] Send deck\n- [x] Book room";
+        assert_eq!(
+            markdown_to_mrkdwn(md),
+            "*Key Points*\n• *Budget* approved\n☐ Send deck\n☑ Book room"
+        );
+    }
+
+    #[test]
+    fn test_escapes_note_text() {
+        assert_eq!(
+            markdown_to_mrkdwn("- <!channel> see <https://evil.example|docs> & more"),
+            "• &lt;!channel&gt; see &lt;https://evil.example|docs&gt; &amp; more"
+        );
+        assert_eq!(
+            format_summary_message("Q&A <!here>", None, &[]),
+            "*Q&amp;A &lt;!here&gt;*"
+        );
+    }
+}
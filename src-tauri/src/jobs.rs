@@ -0,0 +1,429 @@
+//! One queue for the app's long-running work: file transcription, audio conversion, AI
+//! generation, exports, backups and search indexing. Each kind of job has its own workers
+//! (one for transcription, AI, backups and indexing, which would only compete for the same
+//! model, server, bucket or index; two for the rest), so jobs of different kinds never
+//! wait on each other, and a job arriving while its kind is busy waits its turn, highest
+//! priority first, instead of failing with "already running". Jobs are recorded in the
+//! `jobs` table, reported as "job-updated" events, and can be cancelled: a queued job is
+//! dropped at once, a running one stops at its next `check_cancelled`.
+//!
+//! A command takes a worker with `JobManager::enqueue`, does its work and reports the
+//! outcome with `Job::finish`. A `Job` dropped without finishing counts as failed.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::db::models::Job as JobRecord;
+use crate::db::Database;
+
+/// Finished jobs are kept this long
+const HISTORY_DAYS: i64 = 30;
+/// Finished jobs included in `list`
+const HISTORY_LIMIT: i64 = 50;
+
+pub const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Transcription,
+    Conversion,
+    Ai,
+    Export,
+    Backup,
+    Indexing,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 6] = [
+        JobKind::Transcription,
+        JobKind::Conversion,
+        JobKind::Ai,
+        JobKind::Export,
+        JobKind::Backup,
+        JobKind::Indexing,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobKind::Transcription => "transcription",
+            JobKind::Conversion => "conversion",
+            JobKind::Ai => "ai",
+            JobKind::Export => "export",
+            JobKind::Backup => "backup",
+            JobKind::Indexing => "indexing",
+        }
+    }
+
+    /// Jobs of this kind that may run at once
+    fn workers(self) -> usize {
+        match self {
+            JobKind::Transcription | JobKind::Ai | JobKind::Backup | JobKind::Indexing => 1,
+            JobKind::Conversion | JobKind::Export => 2,
+        }
+    }
+}
+
+/// Order among queued jobs of one kind; ties go to the oldest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Scheduled or automatic work
+    Low = 0,
+    /// Started by the user
+    Normal = 1,
+}
+
+/// Jobs of one kind waiting for or holding a worker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct JobCounts {
+    pub queued: usize,
+    pub running: usize,
+}
+
+/// Queued jobs of one kind and how many are running
+#[derive(Debug, Default)]
+struct Lane {
+    running: usize,
+    waiting: Vec<(Priority, i64)>,
+}
+
+impl Lane {
+    /// Take a worker for `id` if one is free and no other waiting job goes first
+    fn try_start(&mut self, id: i64, workers: usize) -> bool {
+        if self.running >= workers {
+            return false;
+        }
+        let next = self
+            .waiting
+            .iter()
+            .max_by_key(|(priority, id)| (*priority, Reverse(*id)))
+            .map(|(_, id)| *id);
+        if next != Some(id) {
+            return false;
+        }
+        self.remove(id);
+        self.running += 1;
+        true
+    }
+
+    fn remove(&mut self, id: i64) {
+        self.waiting.retain(|(_, waiting)| *waiting != id);
+    }
+}
+
+struct ActiveJob {
+    record: JobRecord,
+    cancel: Arc<AtomicBool>,
+}
+
+struct Inner {
+    app: AppHandle,
+    lanes: HashMap<JobKind, (Mutex<Lane>, Notify)>,
+    /// Queued and running jobs
+    active: Mutex<BTreeMap<i64, ActiveJob>>,
+}
+
+/// Managed state; cheap to clone
+#[derive(Clone)]
+pub struct JobManager(Arc<Inner>);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl JobManager {
+    /// Create the manager, marking jobs the last session never finished as interrupted.
+    /// Call from setup once the database is managed.
+    pub fn new(app: &AppHandle) -> Self {
+        if let Some(db) = app.try_state::<Database>() {
+            match db.close_stale_jobs(Utc::now() - Duration::days(HISTORY_DAYS)) {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Jobs: {} were interrupted by the last quit", n),
+                Err(e) => tracing::warn!("Jobs: failed to close stale jobs: {}", e),
+            }
+        }
+        let lanes = JobKind::ALL
+            .into_iter()
+            .map(|kind| (kind, (Mutex::new(Lane::default()), Notify::new())))
+            .collect();
+        Self(Arc::new(Inner {
+            app: app.clone(),
+            lanes,
+            active: Mutex::new(BTreeMap::new()),
+        }))
+    }
+
+    /// Queue a job and wait for a worker of its kind. Fails if the job can't be recorded,
+    /// or with `CANCELLED` if it is cancelled while queued.
+    pub async fn enqueue(
+        &self,
+        kind: JobKind,
+        priority: Priority,
+        label: &str,
+        note_id: Option<&str>,
+    ) -> Result<Job, String> {
+        let record = self
+            .0
+            .app
+            .state::<Database>()
+            .insert_job(kind.as_str(), label, note_id, priority as i64)
+            .map_err(|e| e.to_string())?;
+        let id = record.id;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _ = self.0.app.emit("job-updated", &record);
+        lock(&self.0.active).insert(
+            id,
+            ActiveJob {
+                record,
+                cancel: cancel.clone(),
+            },
+        );
+
+        let (lane, notify) = &self.0.lanes[&kind];
+        lock(lane).waiting.push((priority, id));
+        // Leaves the queue if this future is dropped before a worker is free
+        let mut queued = scopeguard::guard(true, |queued| {
+            if queued {
+                lock(lane).remove(id);
+                notify.notify_waiters();
+                self.close(id, "cancelled", None);
+            }
+        });
+        loop {
+            // Created before checking, so a release in between still wakes this waiter
+            let released = notify.notified();
+            {
+                let mut lane = lock(lane);
+                if cancel.load(Ordering::SeqCst) {
+                    return Err(CANCELLED.to_string());
+                }
+                if lane.try_start(id, kind.workers()) {
+                    break;
+                }
+            }
+            released.await;
+        }
+        *queued = false;
+
+        self.update(id, |record| {
+            record.status = "running".to_string();
+            record.started_at = Some(Utc::now());
+        });
+        let _ = self
+            .0
+            .app
+            .state::<Database>()
+            .update_job_status(id, "running", None);
+        Ok(Job {
+            manager: self.clone(),
+            id,
+            kind,
+            cancel,
+            finished: false,
+        })
+    }
+
+    /// Ask a job to stop. Returns false if it is not queued or running.
+    pub fn cancel(&self, id: i64) -> bool {
+        let kind = {
+            let active = lock(&self.0.active);
+            let Some(job) = active.get(&id) else {
+                return false;
+            };
+            job.cancel.store(true, Ordering::SeqCst);
+            job.record.kind.clone()
+        };
+        // Wake the queue so a waiting job notices
+        if let Some(kind) = JobKind::ALL.into_iter().find(|k| k.as_str() == kind) {
+            self.0.lanes[&kind].1.notify_waiters();
+        }
+        true
+    }
+
+    /// Queued and running jobs, oldest first, then recently finished ones, newest first
+    pub fn list(&self) -> Result<Vec<JobRecord>, String> {
+        let mut jobs: Vec<JobRecord> = lock(&self.0.active)
+            .values()
+            .map(|job| job.record.clone())
+            .collect();
+        jobs.extend(
+            self.0
+                .app
+                .state::<Database>()
+                .get_finished_jobs(HISTORY_LIMIT)
+                .map_err(|e| e.to_string())?,
+        );
+        Ok(jobs)
+    }
+
+    /// Whether any job of this kind is queued or running
+    pub fn is_busy(&self, kind: JobKind) -> bool {
+        lock(&self.0.active)
+            .values()
+            .any(|job| job.record.kind == kind.as_str())
+    }
+
+    /// Queued and running jobs of every kind
+    pub fn counts(&self) -> HashMap<JobKind, JobCounts> {
+        let active = lock(&self.0.active);
+        JobKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let mut counts = JobCounts::default();
+                for job in active.values().filter(|job| job.record.kind == kind.as_str()) {
+                    if job.record.status == "running" {
+                        counts.running += 1;
+                    } else {
+                        counts.queued += 1;
+                    }
+                }
+                (kind, counts)
+            })
+            .collect()
+    }
+
+    /// `is_busy` for the panic hook: None instead of blocking if the queue is locked
+    pub fn try_is_busy(&self, kind: JobKind) -> Option<bool> {
+        let active = self.0.active.try_lock().ok()?;
+        Some(active.values().any(|job| job.record.kind == kind.as_str()))
+    }
+
+    fn update(&self, id: i64, change: impl FnOnce(&mut JobRecord)) {
+        let record = {
+            let mut active = lock(&self.0.active);
+            let Some(job) = active.get_mut(&id) else {
+                return;
+            };
+            change(&mut job.record);
+            job.record.clone()
+        };
+        let _ = self.0.app.emit("job-updated", &record);
+    }
+
+    /// Record how a job ended and forget it
+    fn close(&self, id: i64, status: &str, error: Option<&str>) {
+        let Some(ActiveJob { mut record, .. }) = lock(&self.0.active).remove(&id) else {
+            return;
+        };
+        if let Err(e) = self
+            .0
+            .app
+            .state::<Database>()
+            .update_job_status(id, status, error)
+        {
+            tracing::warn!("Jobs: failed to record the end of job {}: {}", id, e);
+        }
+        record.status = status.to_string();
+        record.error = error.map(str::to_string);
+        record.finished_at = Some(Utc::now());
+        let _ = self.0.app.emit("job-updated", &record);
+    }
+}
+
+/// A running job holding one of its kind's workers
+pub struct Job {
+    manager: JobManager,
+    id: i64,
+    kind: JobKind,
+    cancel: Arc<AtomicBool>,
+    finished: bool,
+}
+
+impl Job {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Report how far along the job is, from 0.0 to 1.0
+    pub fn progress(&self, fraction: f32) {
+        self.manager.update(self.id, |record| {
+            record.progress = Some(fraction.clamp(0.0, 1.0));
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Fails with `CANCELLED` once the job was cancelled; call between steps
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record the outcome and free the worker
+    pub fn finish<T>(mut self, result: &Result<T, String>) {
+        self.finished = true;
+        match result {
+            Ok(_) => self.release("completed", None),
+            Err(_) if self.is_cancelled() => self.release("cancelled", None),
+            Err(e) => self.release("failed", Some(e.as_str())),
+        }
+    }
+
+    fn release(&self, status: &str, error: Option<&str>) {
+        self.manager.close(self.id, status, error);
+        let (lane, notify) = &self.manager.0.lanes[&self.kind];
+        lock(lane).running -= 1;
+        notify.notify_waiters();
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if !self.finished {
+            let status = if self.is_cancelled() {
+                "cancelled"
+            } else {
+                "failed"
+            };
+            self.release(status, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lane_order() {
+        let mut lane = Lane::default();
+        lane.waiting = vec![
+            (Priority::Low, 1),
+            (Priority::Normal, 2),
+            (Priority::Normal, 3),
+        ];
+
+        // Normal before low, oldest first
+        assert!(!lane.try_start(1, 1));
+        assert!(!lane.try_start(3, 1));
+        assert!(lane.try_start(2, 1));
+        assert_eq!(lane.running, 1);
+
+        // No free worker
+        assert!(!lane.try_start(3, 1));
+        lane.running -= 1;
+        assert!(lane.try_start(3, 1));
+        assert!(lane.try_start(1, 2));
+        assert!(lane.waiting.is_empty());
+    }
+
+    #[test]
+    fn test_lane_remove() {
+        let mut lane = Lane::default();
+        lane.waiting = vec![(Priority::Normal, 1), (Priority::Normal, 2)];
+        lane.remove(1);
+        assert!(lane.try_start(2, 1));
+    }
+}
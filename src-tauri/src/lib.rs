@@ -1,124 +1,112 @@
 mod ai;
+mod app_menu;
 mod audio;
+mod auto_pause;
+#[cfg(target_os = "macos")]
+mod automation;
+mod backup;
 mod commands;
+mod crash;
 mod db;
+mod deep_link;
+mod focus_mode;
+mod hotkeys;
+mod importers;
+mod integrations;
+mod jobs;
+mod logging;
+mod mcp;
 mod meeting_detection;
+mod note_windows;
+mod notifications;
+mod pdf;
+mod power;
+mod process;
+mod profiling;
+mod quick_note;
+mod recorder_widget;
+mod recovery;
+mod retention;
+mod secrets;
+mod segment_rotation;
+mod settings;
+mod share;
+mod shortcuts;
+mod shutdown;
+mod storage;
+mod templates;
 mod transcription;
+mod tray;
+mod updates;
+mod webhooks;
+mod window_state;
 
+use chrono::{DateTime, Utc};
 use commands::{init_transcription_state, AiState, AudioState};
 use db::Database;
 use meeting_detection::MeetingDetectionState;
 use serde::Deserialize;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Tracks whether the app was launched with --minimized flag (e.g., via autostart)
 static STARTED_MINIMIZED: AtomicBool = AtomicBool::new(false);
 use tauri::{
-    image::Image,
-    menu::{Menu, MenuBuilder, MenuItem, SubmenuBuilder},
     tray::TrayIconBuilder,
     Emitter, Listener, Manager, RunEvent, WindowEvent,
 };
 use tauri_plugin_autostart::MacosLauncher;
 
-#[derive(Debug, Deserialize)]
-struct UpdateStatus {
-    available: bool,
-    version: Option<String>,
+/// Flags understood at launch
+#[derive(Debug, Default, Clone, Copy)]
+struct LaunchArgs {
+    /// Start hidden in the tray (autostart passes this)
+    minimized: bool,
+    /// Create a note and start recording right away
+    record: bool,
 }
 
-/// Updates the system tray icon and menu based on update availability
-fn update_tray_for_update(app: &tauri::AppHandle, available: bool, version: Option<String>) {
-    if let Some(tray) = app.tray_by_id("main-tray") {
-        // Rebuild menu with or without update item
-        let menu_result: Result<Menu<tauri::Wry>, tauri::Error> = (|| {
-            if available {
-                let version_str = version.unwrap_or_else(|| "new".to_string());
-                let install_update = MenuItem::with_id(
-                    app,
-                    "install_update",
-                    format!("Install Update (v{})", version_str),
-                    true,
-                    None::<&str>,
-                )?;
-                let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
-                let open = MenuItem::with_id(app, "open", "Open", true, Some("CmdOrCtrl+O"))?;
-                let new_note =
-                    MenuItem::with_id(app, "new_note", "New Note", true, Some("CmdOrCtrl+N"))?;
-                let settings =
-                    MenuItem::with_id(app, "settings", "Settings", true, Some("CmdOrCtrl+,"))?;
-                let exit = MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)?;
-
-                Menu::with_items(
-                    app,
-                    &[
-                        &install_update,
-                        &separator,
-                        &open,
-                        &new_note,
-                        &settings,
-                        &exit,
-                    ],
-                )
-            } else {
-                let open = MenuItem::with_id(app, "open", "Open", true, Some("CmdOrCtrl+O"))?;
-                let new_note =
-                    MenuItem::with_id(app, "new_note", "New Note", true, Some("CmdOrCtrl+N"))?;
-                let settings =
-                    MenuItem::with_id(app, "settings", "Settings", true, Some("CmdOrCtrl+,"))?;
-                let exit = MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)?;
-
-                Menu::with_items(app, &[&open, &new_note, &settings, &exit])
-            }
-        })();
-
-        if let Ok(menu) = menu_result {
-            let _ = tray.set_menu(Some(menu));
-        }
-
-        // Swap icon based on update availability
-        // Windows: use colored icon.png (visible on both dark/light taskbars)
-        // macOS: use template icons for automatic dark/light adaptation
-        #[cfg(target_os = "windows")]
-        let icon_result = Image::from_bytes(include_bytes!("../icons/icon.png"));
-
-        #[cfg(not(target_os = "windows"))]
-        let icon_result = if available {
-            Image::from_bytes(include_bytes!("../icons/icon_tray_update.png"))
-        } else {
-            Image::from_bytes(include_bytes!("../icons/icon_tray.png"))
-        };
-
-        if let Ok(icon) = icon_result {
-            let _ = tray.set_icon(Some(icon));
-            // Re-apply template mode for proper dark/light mode support on macOS
-            #[cfg(target_os = "macos")]
-            let _ = tray.set_icon_as_template(true);
+impl LaunchArgs {
+    fn parse(args: &[String]) -> Self {
+        let has = |flag: &str| args.iter().any(|arg| arg == flag);
+        Self {
+            minimized: has("--minimized"),
+            record: has("--record"),
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct UpdateStatus {
+    available: bool,
+    version: Option<String>,
+}
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! Welcome to Note67.", name)
 }
 
-/// Clean up orphaned .tmp files from interrupted upload conversions
-fn cleanup_temp_files(app: &tauri::AppHandle) {
-    if let Ok(app_data) = app.path().app_data_dir() {
-        let recordings_dir = app_data.join("recordings");
-        if recordings_dir.exists() {
-            if let Ok(entries) = std::fs::read_dir(&recordings_dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.extension().map(|e| e == "tmp").unwrap_or(false) {
-                        let _ = std::fs::remove_file(&path);
-                    }
-                }
-            }
-        }
-    }
+/// Startup work the window doesn't wait for, run on a background thread once setup is done
+fn spawn_deferred_startup(app: &tauri::AppHandle, launched_at: DateTime<Utc>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let started = Instant::now();
+
+        // Move files from the old flat folders into per-note folders, then repair what a
+        // crash or forced quit left in them
+        storage::migrate_legacy_layout(&app);
+        recovery::run(&app, launched_at);
+
+        // Register the note67:// handler (spawns `reg` / `xdg-mime`)
+        deep_link::register_scheme();
+
+        // Probe for system audio capture now rather than when the first recording starts
+        let _ = app.state::<AudioState>().system_capture();
+
+        tracing::info!("Deferred startup took {:?}", started.elapsed());
+    });
 }
 
 /// Show the main window when frontend is ready.
@@ -138,6 +126,11 @@ fn show_main_window(app: tauri::AppHandle) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // `note67 --mcp` serves MCP over stdio for agent hosts instead of opening the app
+    if std::env::args().any(|arg| arg == mcp::FLAG) {
+        std::process::exit(mcp::run_stdio());
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
@@ -145,12 +138,24 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .setup(|app| {
-            // Check if app was launched with --minimized flag (from autostart)
+            let started = Instant::now();
+            let launched_at = Utc::now();
+
+            // --minimized (from autostart) keeps the window hidden in the tray
             let args: Vec<String> = std::env::args().collect();
-            if args.iter().any(|arg| arg == "--minimized") {
+            let launch = LaunchArgs::parse(&args);
+            if launch.minimized {
                 STARTED_MINIMIZED.store(true, Ordering::Relaxed);
             }
 
+            // A link that launched a second instance goes to the running one instead
+            let launch_url = deep_link::url_from_args(&args);
+            if let Some(url) = &launch_url
+                && deep_link::forward_to_running_instance(app.handle(), url)
+            {
+                std::process::exit(0);
+            }
+
             // Initialize autostart plugin (desktop only)
             #[cfg(desktop)]
             app.handle().plugin(tauri_plugin_autostart::init(
@@ -161,10 +166,72 @@ pub fn run() {
             let db = Database::new(app.handle())?;
             app.manage(db);
 
-            // Clean up orphaned temp files from interrupted uploads
-            cleanup_temp_files(app.handle());
+            // Queue for transcription, conversion, AI, export and backup jobs
+            app.manage(jobs::JobManager::new(app.handle()));
+
+            // Serve attachments and recordings from a storage location outside app data
+            storage::allow_asset_access(app.handle());
+
+            // Panic hook and crash reports; before the log rolls over to this session
+            crash::init(app.handle());
+
+            // Diagnostics log in <app data>/logs
+            logging::init(app.handle());
+
+            // Opt-in performance trace next to the log
+            profiling::init(app.handle());
+
+            // Exports shared in earlier sessions have long been picked up
+            share::clear_shared_files();
+
+            // Credentials earlier versions left in the settings table go to the keychain
+            if let Err(e) = settings::migrate_credentials(&app.state::<Database>()) {
+                tracing::warn!("Failed to move credentials to the keychain: {}", e);
+            }
+            if let Err(e) = webhooks::migrate_secrets(&app.state::<Database>()) {
+                tracing::warn!("Failed to move webhook secrets to the keychain: {}", e);
+            }
+
+            // Reopen the main window where it was left; it is still hidden here
+            if let Some(window) = app.get_webview_window("main") {
+                window_state::track(&window);
+            }
+
+            // Let the "system" theme preference follow OS appearance changes
+            commands::watch_system_theme(app.handle());
+
+            // Apps left out of system audio capture
+            audio::exclusions::init(app.handle());
+
+            // The microphone recordings use
+            audio::devices::init(app.handle());
+
+            // Mic clipping and silence are reported as events
+            audio::quality::init(app.handle());
+
+            // WAV or FLAC for new recordings
+            audio::encoder::init(app.handle());
+
+            // Rate and channels of new recordings
+            audio::capture_format::init(app.handle());
+
+            // Mic noise suppression, globally and per note
+            audio::denoise::init(app.handle());
+
+            // Hearing the mic on headphones while recording
+            audio::monitoring::init(app.handle());
+
+            // Recording write errors are reported as events
+            audio::writer::init(app.handle());
+
+            // System audio capture stopping and resuming is reported as events
+            audio::system_audio::init(app.handle());
+
+            // Whisper thread count and priority
+            transcription::threads::init(app.handle());
 
             app.manage(AudioState::default());
+            app.manage(recovery::PendingRecoveryReport::default());
             app.manage(AiState::default());
             let transcription_state = init_transcription_state(app.handle());
             app.manage(transcription_state);
@@ -175,80 +242,50 @@ pub fn run() {
             // Start meeting detection
             meeting_detection::start_meeting_detection(app.handle());
 
-            // Create custom application menu (macOS) with Hide instead of Quit on Cmd+Q
+            // Fire recordings for calendar events scheduled to auto-record
+            integrations::calendar::start_recording_scheduler(app.handle());
+
+            // Prompt before system calendar meetings start (macOS)
+            integrations::eventkit::start_upcoming_meeting_monitor(app.handle());
+
+            // Run scheduled S3 backups
+            backup::start_backup_scheduler(app.handle());
+
+            // Warn when the disk holding recordings runs low
+            notifications::start_disk_space_monitor(app.handle());
+
+            // Battery saver: follow whether the machine runs on battery
+            power::start_monitor(app.handle());
+
+            // Background update checks on the selected channel
+            updates::start_update_scheduler(app.handle());
+
+            // AppleScript commands (see Note67.sdef)
             #[cfg(target_os = "macos")]
-            {
-                use tauri::menu::PredefinedMenuItem;
-
-                let hide_window = MenuItem::with_id(app, "hide_window", "Hide Window", true, Some("CmdOrCtrl+Q"))?;
-                let quit = MenuItem::with_id(app, "quit_app", "Quit Note67", true, Some("CmdOrCtrl+Shift+Q"))?;
-
-                let app_submenu = SubmenuBuilder::new(app, "Note67")
-                    .item(&PredefinedMenuItem::about(app, Some("About Note67"), None)?)
-                    .separator()
-                    .item(&hide_window)
-                    .item(&quit)
-                    .build()?;
-
-                let edit_submenu = SubmenuBuilder::new(app, "Edit")
-                    .item(&PredefinedMenuItem::undo(app, None)?)
-                    .item(&PredefinedMenuItem::redo(app, None)?)
-                    .separator()
-                    .item(&PredefinedMenuItem::cut(app, None)?)
-                    .item(&PredefinedMenuItem::copy(app, None)?)
-                    .item(&PredefinedMenuItem::paste(app, None)?)
-                    .item(&PredefinedMenuItem::select_all(app, None)?)
-                    .build()?;
-
-                let window_submenu = SubmenuBuilder::new(app, "Window")
-                    .item(&PredefinedMenuItem::minimize(app, None)?)
-                    .item(&PredefinedMenuItem::maximize(app, None)?)
-                    .separator()
-                    .item(&PredefinedMenuItem::close_window(app, None)?)
-                    .build()?;
-
-                let menu = MenuBuilder::new(app)
-                    .item(&app_submenu)
-                    .item(&edit_submenu)
-                    .item(&window_submenu)
-                    .build()?;
-
-                app.set_menu(menu)?;
-
-                // Handle custom menu events
-                app.on_menu_event(move |app_handle, event| {
-                    match event.id().as_ref() {
-                        "hide_window" => {
-                            if let Some(window) = app_handle.get_webview_window("main") {
-                                let _ = window.hide();
-                            }
-                        }
-                        "quit_app" => {
-                            std::process::exit(0);
-                        }
-                        _ => {}
-                    }
-                });
+            automation::install(app.handle());
+
+            // Handle note67:// links
+            app.manage(deep_link::PendingDeepLink::default());
+            deep_link::start_listener(app.handle());
+            if let Some(url) = &launch_url {
+                deep_link::set_launch_url(app.handle(), url);
             }
 
-            // Setup system tray menu
-            let open = MenuItem::with_id(app, "open", "Open", true, Some("CmdOrCtrl+O"))?;
-            let new_note = MenuItem::with_id(app, "new_note", "New Note", true, Some("CmdOrCtrl+N"))?;
-            let settings = MenuItem::with_id(app, "settings", "Settings", true, Some("CmdOrCtrl+,"))?;
-            let exit = MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)?;
+            // System-wide recording hotkeys
+            hotkeys::install(app.handle());
 
-            let menu = Menu::with_items(app, &[&open, &new_note, &settings, &exit])?;
+            // Application menu (macOS) with Hide instead of Quit on Cmd+Q
+            app_menu::install(app.handle())?;
 
-            // Use colored icon on Windows (visible on both dark/light), template icon on macOS
-            #[cfg(target_os = "windows")]
-            let icon = Image::from_bytes(include_bytes!("../icons/icon.png"))?;
+            // Setup system tray menu
+            let menu = tray::build_menu(app.handle())?;
 
-            #[cfg(not(target_os = "windows"))]
-            let icon = Image::from_bytes(include_bytes!("../icons/icon_tray.png"))?;
+            // Use colored icon on Windows (visible on both dark/light), template icon on macOS
+            let icon = tray::icon()?;
 
             // Windows: no template mode, use white icon directly
             #[cfg(target_os = "windows")]
-            let _tray = TrayIconBuilder::with_id("main-tray")
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(icon)
                 .menu(&menu)
                 .show_menu_on_left_click(true)
@@ -266,6 +303,10 @@ pub fn run() {
                             let _ = window.emit("tray-new-note", ());
                         }
                     }
+                    "quick_note" => quick_note::open_from_tray(app),
+                    "quick_note_clipboard" => quick_note::save_clipboard_from_tray(app),
+                    "start_recording" => tray::start_recording(app),
+                    "stop_recording" => tray::stop_recording(app),
                     "settings" => {
                         if let Some(window) = app.get_webview_window("main") {
                             let _ = window.show();
@@ -278,16 +319,18 @@ pub fn run() {
                             let _ = window.emit("tray-install-update", ());
                         }
                     }
-                    "exit" => {
-                        std::process::exit(0);
+                    "exit" => shutdown::exit(app),
+                    id => {
+                        if let Some(note_id) = id.strip_prefix(tray::RECENT_NOTE_PREFIX) {
+                            tray::open_recent_note(app, note_id);
+                        }
                     }
-                    _ => {}
                 })
                 .build(app)?;
 
             // macOS/Linux: use template mode for automatic dark/light adaptation
             #[cfg(not(target_os = "windows"))]
-            let _tray = TrayIconBuilder::with_id("main-tray")
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(icon)
                 .icon_as_template(true)
                 .menu(&menu)
@@ -306,6 +349,10 @@ pub fn run() {
                             let _ = window.emit("tray-new-note", ());
                         }
                     }
+                    "quick_note" => quick_note::open_from_tray(app),
+                    "quick_note_clipboard" => quick_note::save_clipboard_from_tray(app),
+                    "start_recording" => tray::start_recording(app),
+                    "stop_recording" => tray::stop_recording(app),
                     "settings" => {
                         if let Some(window) = app.get_webview_window("main") {
                             let _ = window.show();
@@ -318,10 +365,12 @@ pub fn run() {
                             let _ = window.emit("tray-install-update", ());
                         }
                     }
-                    "exit" => {
-                        std::process::exit(0);
+                    "exit" => shutdown::exit(app),
+                    id => {
+                        if let Some(note_id) = id.strip_prefix(tray::RECENT_NOTE_PREFIX) {
+                            tray::open_recent_note(app, note_id);
+                        }
                     }
-                    _ => {}
                 })
                 .build(app)?;
 
@@ -330,15 +379,55 @@ pub fn run() {
             app.listen("update-status-changed", move |event| {
                 let payload = event.payload();
                 if let Ok(status) = serde_json::from_str::<UpdateStatus>(payload) {
-                    update_tray_for_update(&app_handle, status.available, status.version);
+                    tray::set_update_available(&app_handle, status.available, status.version);
                 }
             });
 
+            // Show recording state and recent notes in the tray
+            tray::start_recording_monitor(app.handle());
+
+            // Pause recording while the screen is locked or the machine sleeps
+            auto_pause::start_monitor(app.handle());
+
+            // Roll long recordings over into new segments
+            segment_rotation::start_monitor(app.handle());
+
+            // Delete old notes' audio when a retention period is set
+            retention::start_monitor(app.handle());
+
+            // No echo cancellation needed on headphones
+            audio::headphones::start_monitor(app.handle());
+
+            // Live waveform while recording
+            audio::waveform::start_monitor(app.handle());
+
+            // Do Not Disturb while recording
+            focus_mode::start_monitor(app.handle());
+            tray::watch_notes(app.handle());
+            note_windows::watch_notes(app.handle());
+
+            // --record, or a login launch with "record at login" on, starts recording now
+            let record_at_login = launch.minimized
+                && app
+                    .state::<Database>()
+                    .get_setting(settings::SETTING_RECORD_AT_LOGIN)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|v| v == "true");
+            if launch.record || record_at_login {
+                tray::start_recording(app.handle());
+            }
+
+            spawn_deferred_startup(app.handle(), launched_at);
+            tracing::info!("Setup took {:?}", started.elapsed());
+
             Ok(())
         })
         .on_window_event(|window, event| {
-            // Hide window instead of closing when user clicks the close button
-            if let WindowEvent::CloseRequested { api, .. } = event {
+            // Hide the main window instead of closing when user clicks the close button
+            if let WindowEvent::CloseRequested { api, .. } = event
+                && window.label() == "main"
+            {
                 let _ = window.hide();
                 api.prevent_close();
             }
@@ -353,14 +442,34 @@ pub fn run() {
             commands::delete_note,
             commands::update_note,
             commands::search_notes,
+            commands::get_transcript_index_status,
+            commands::backfill_transcript_index,
             commands::start_recording,
             commands::stop_recording,
             commands::get_recording_status,
             commands::get_audio_level,
+            commands::get_audio_levels,
+            commands::generate_waveform_peaks,
+            commands::play_audio,
+            commands::pause_audio,
+            commands::resume_audio,
+            commands::stop_audio,
+            commands::seek_audio,
+            commands::set_playback_speed,
+            commands::get_playback_status,
+            commands::get_silence_map,
+            commands::add_recording_marker,
+            commands::get_recording_markers,
+            commands::delete_recording_marker,
             commands::is_system_audio_supported,
             commands::has_system_audio_permission,
             commands::request_system_audio_permission,
             commands::has_microphone_available,
+            commands::list_input_devices,
+            commands::set_input_device,
+            commands::list_render_devices,
+            commands::set_render_device,
+            commands::list_capturable_apps,
             commands::has_microphone_permission,
             commands::get_microphone_auth_status,
             commands::request_microphone_permission,
@@ -370,6 +479,14 @@ pub fn run() {
             commands::is_dual_recording,
             commands::is_aec_enabled,
             commands::set_aec_enabled,
+            commands::run_aec_calibration,
+            commands::is_noise_suppression_enabled,
+            commands::set_noise_suppression_enabled,
+            commands::is_input_monitoring_enabled,
+            commands::set_input_monitoring,
+            commands::get_note_noise_suppression,
+            commands::set_note_noise_suppression,
+            commands::get_compression_progress,
             // Pause/Resume/Continue recording commands
             commands::get_recording_phase,
             commands::pause_recording_cmd,
@@ -387,6 +504,8 @@ pub fn run() {
             commands::get_note_audio_segments,
             commands::get_note_total_duration,
             commands::delete_note_audio_segments,
+            commands::trim_audio,
+            commands::split_audio_segment,
             commands::migrate_legacy_audio,
             commands::list_models,
             commands::download_model,
@@ -395,11 +514,16 @@ pub fn run() {
             commands::delete_model,
             commands::load_model,
             commands::get_loaded_model,
+            commands::unload_model,
             commands::transcribe_audio,
             commands::transcribe_dual_audio,
             commands::is_transcribing,
             commands::get_transcript,
             commands::add_transcript_segment,
+            commands::get_note_language,
+            commands::set_note_language,
+            commands::set_note_translate,
+            commands::list_transcription_languages,
             commands::start_live_transcription,
             commands::stop_live_transcription,
             commands::is_live_transcribing,
@@ -431,27 +555,145 @@ pub fn run() {
             commands::export_note_markdown,
             commands::save_export_to_file,
             commands::get_export_directory,
+            commands::export_notes,
+            commands::export_note_bundle,
+            commands::export_note_audio,
+            commands::share_note,
+            commands::get_converter_presets,
+            commands::set_converter_presets,
+            commands::export_note_converted,
+            commands::publish_note,
+            commands::get_auto_export_settings,
+            commands::set_auto_export_settings,
+            commands::list_export_templates,
+            commands::create_export_template,
+            commands::update_export_template,
+            commands::delete_export_template,
+            commands::preview_export_template,
+            // Import commands
+            commands::import_otter_transcripts,
+            commands::import_markdown_folder,
+            commands::import_voice_memos,
             // Upload commands
             commands::upload_audio,
             commands::get_uploaded_audio,
             commands::delete_uploaded_audio,
             commands::transcribe_uploaded_audio,
             commands::update_uploaded_audio_speaker,
+            commands::get_note_speakers,
+            commands::label_speaker,
+            commands::list_speakers,
+            commands::delete_speaker,
             commands::reorder_audio_items,
             // Settings commands
             commands::get_theme_preference,
             commands::set_theme_preference,
+            commands::get_system_theme,
             commands::get_setting,
             commands::set_setting,
             commands::get_settings,
+            commands::get_all_settings,
+            commands::get_settings_schema,
+            commands::export_settings,
+            commands::preview_settings_import,
+            commands::import_settings,
+            commands::get_permissions_status,
+            commands::open_permission_settings,
+            commands::get_resource_usage,
+            commands::get_storage_location,
+            commands::check_storage_location,
+            commands::set_storage_location,
+            commands::get_storage_usage,
+            commands::purge_note_audio,
+            commands::list_jobs,
+            commands::cancel_job,
+            commands::run_self_test,
+            commands::get_health,
+            commands::compact_database,
             commands::get_autostart_enabled,
             commands::set_autostart_enabled,
             commands::open_screen_recording_settings,
             commands::open_microphone_settings,
+            // Integration commands
+            commands::post_summary_to_slack,
+            commands::get_smtp_settings,
+            commands::set_smtp_settings,
+            commands::email_note_summary,
+            commands::import_calendar,
+            commands::list_upcoming_calendar_events,
+            commands::set_calendar_event_auto_record,
+            commands::get_calendar_access_status,
+            commands::request_calendar_access,
+            commands::get_todays_meetings,
+            commands::create_note_from_meeting,
+            commands::get_mcp_settings,
+            commands::set_mcp_enabled,
+            commands::has_gist_token,
+            commands::set_gist_token,
+            // Backup commands
+            commands::get_s3_backup_settings,
+            commands::set_s3_backup_settings,
+            commands::run_s3_backup,
+            commands::list_s3_backups,
+            commands::restore_s3_backup,
+            // Webhook commands
+            commands::list_webhook_events,
+            commands::list_webhooks,
+            commands::create_webhook,
+            commands::update_webhook,
+            commands::test_webhook,
+            commands::preview_webhook_payload,
+            commands::delete_webhook,
+            commands::get_webhook_deliveries,
             // Meeting detection commands
             meeting_detection::set_meeting_detection_enabled,
             meeting_detection::is_meeting_detection_enabled,
             meeting_detection::clear_detected_meetings,
+            meeting_detection::set_meeting_auto_start,
+            meeting_detection::is_meeting_auto_start_enabled,
+            // Deep link commands
+            deep_link::take_pending_deep_link,
+            // Shortcut commands
+            shortcuts::list_shortcuts,
+            shortcuts::set_shortcut,
+            shortcuts::reset_shortcut,
+            // Update commands
+            updates::get_update_policy,
+            updates::set_update_policy,
+            updates::check_for_updates_now,
+            updates::skip_update_version,
+            updates::install_update,
+            // Diagnostics commands
+            logging::get_recent_logs,
+            logging::open_log_folder,
+            crash::list_crash_reports,
+            crash::get_crash_report,
+            crash::delete_crash_report,
+            recovery::take_recovery_report,
+            // Note window commands
+            note_windows::open_note_window,
+            note_windows::close_note_window,
+            note_windows::list_note_windows,
+            // Onboarding commands
+            commands::get_onboarding_status,
+            commands::advance_onboarding,
+            commands::complete_onboarding,
+            commands::reset_onboarding,
+            // Auto-pause commands
+            auto_pause::resume_auto_paused_recording,
+            focus_mode::is_focus_mode_supported,
+            // Power commands
+            power::get_power_status,
+            // Quick note commands
+            quick_note::open_quick_note_window,
+            quick_note::create_quick_note,
+            quick_note::create_quick_note_from_clipboard,
+            // Recorder widget commands
+            recorder_widget::open_recorder_widget,
+            recorder_widget::close_recorder_widget,
+            recorder_widget::is_recorder_widget_open,
+            recorder_widget::recorder_widget_toggle_pause,
+            recorder_widget::recorder_widget_stop,
             // Image commands
             commands::save_image,
             commands::get_attachments_dir,
@@ -476,8 +718,22 @@ pub fn run() {
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app_handle, event| {
-            // Prevent app from exiting when Cmd+Q is pressed (hide window instead)
-            if let RunEvent::ExitRequested { api, .. } = event {
+            // macOS delivers note67:// links to the running app
+            #[cfg(target_os = "macos")]
+            if let RunEvent::Opened { urls } = &event {
+                for url in urls {
+                    deep_link::handle_url(app_handle, url.as_str());
+                }
+            }
+
+            // Also covers the OS ending the session, which skips `shutdown::exit`
+            if let RunEvent::Exit = event {
+                shutdown::prepare(app_handle);
+            }
+
+            // Prevent app from exiting when Cmd+Q is pressed (hide window instead).
+            // `shutdown::exit` asks with an exit code and is let through.
+            if let RunEvent::ExitRequested { api, code: None, .. } = event {
                 api.prevent_exit();
                 // Hide all windows
                 if let Some(window) = app_handle.get_webview_window("main") {
@@ -0,0 +1,310 @@
+//! Diagnostics log. A small `tracing` subscriber writes every event at or above the level
+//! from settings to `<app data>/logs/note67.log` (and to stderr). The file is rotated at
+//! `MAX_FILE_BYTES`, keeping `KEEP_FILES` old ones as `note67.log.1` (newest) and up.
+//! Spans are only tracked for performance traces (see `profiling.rs`); the module path of
+//! each event is enough context for the log.
+
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use chrono::Utc;
+use serde::Deserialize;
+use tauri::{AppHandle, Listener, Manager};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::db::Database;
+use crate::profiling;
+
+/// "error", "warn", "info", "debug" or "trace"
+pub const SETTING_LEVEL: &str = "log_level";
+
+pub const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+const FILE_NAME: &str = "note67.log";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const KEEP_FILES: usize = 4;
+/// Lines returned by `get_recent_logs` by default
+const DEFAULT_RECENT_LINES: usize = 500;
+
+/// Index into `LEVELS` of the most verbose level written
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(2);
+static LOG_FILE: Mutex<Option<LogFile>> = Mutex::new(None);
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size + line.len() as u64 > MAX_FILE_BYTES
+            && let Ok(rotated) = self.rotate()
+        {
+            *self = rotated;
+        }
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+
+    /// note67.log.N -> .N+1 (dropping the oldest), note67.log -> .1, then start a new file
+    fn rotate(&self) -> std::io::Result<Self> {
+        let path = |n: usize| self.dir.join(format!("{}.{}", FILE_NAME, n));
+        let _ = fs::remove_file(path(KEEP_FILES));
+        for n in (1..KEEP_FILES).rev() {
+            let _ = fs::rename(path(n), path(n + 1));
+        }
+        fs::rename(self.dir.join(FILE_NAME), path(1))?;
+        Self::open(&self.dir)
+    }
+}
+
+struct FileSubscriber {
+    next_span: AtomicU64,
+}
+
+impl Subscriber for FileSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        if metadata.is_span() {
+            return profiling::is_enabled();
+        }
+        level_index(metadata.level()) <= MAX_LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_span.fetch_add(1, Ordering::Relaxed);
+        profiling::open_span(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        profiling::record_span(span.into_u64(), values);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let line = format!(
+            "{} {:>5} {}: {}{}\n",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            metadata.level(),
+            metadata.target(),
+            fields.message,
+            fields.rest
+        );
+
+        eprint!("{}", line);
+        if let Some(file) = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            file.write_line(&line);
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+
+    fn clone_span(&self, span: &Id) -> Id {
+        profiling::clone_span(span.into_u64());
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        profiling::close_span(span.into_u64())
+    }
+}
+
+/// The event's message followed by its other fields as ` name=value`
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.rest, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+fn level_index(level: &Level) -> usize {
+    match *level {
+        Level::ERROR => 0,
+        Level::WARN => 1,
+        Level::INFO => 2,
+        Level::DEBUG => 3,
+        Level::TRACE => 4,
+    }
+}
+
+fn set_level(level: &str) {
+    if let Some(index) = LEVELS.iter().position(|l| *l == level) {
+        MAX_LEVEL.store(index, Ordering::Relaxed);
+        // Callsites cache whether they are enabled; make them ask again
+        tracing::callsite::rebuild_interest_cache();
+    }
+}
+
+pub fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("logs"))
+        .map_err(|e| e.to_string())
+}
+
+/// Start writing the log file, at the level saved in settings. Call once the database is
+/// managed.
+pub fn init(app: &AppHandle) {
+    if let Some(level) = app
+        .state::<Database>()
+        .get_setting(SETTING_LEVEL)
+        .ok()
+        .flatten()
+    {
+        set_level(&level);
+    }
+
+    match log_dir(app).and_then(|dir| LogFile::open(&dir).map_err(|e| e.to_string())) {
+        Ok(file) => *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file),
+        Err(e) => eprintln!("[logging] Failed to open the log file: {}", e),
+    }
+    let subscriber = FileSubscriber {
+        next_span: AtomicU64::new(1),
+    };
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        return;
+    }
+
+    // Follow level changes from settings
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<serde_json::Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed {
+            key,
+            value: Some(serde_json::Value::String(level)),
+        }) = serde_json::from_str(event.payload())
+            && key == SETTING_LEVEL
+        {
+            set_level(&level);
+        }
+    });
+
+    tracing::info!(
+        "Note67 {} starting ({} {})",
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+}
+
+/// The last `count` lines of `text`
+fn tail_lines(text: &str, count: usize) -> Vec<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+/// The last `count` log lines (oldest first), reaching into the previous file when the
+/// current one is short
+pub fn recent_lines(dir: &Path, count: usize) -> Vec<String> {
+    let read = |name: String| fs::read_to_string(dir.join(name)).unwrap_or_default();
+
+    let current = read(FILE_NAME.to_string());
+    let mut recent = tail_lines(&current, count);
+    let previous;
+    if recent.len() < count {
+        previous = read(format!("{}.1", FILE_NAME));
+        let mut older = tail_lines(&previous, count - recent.len());
+        older.append(&mut recent);
+        recent = older;
+    }
+    recent.into_iter().map(String::from).collect()
+}
+
+#[tauri::command]
+pub fn get_recent_logs(app: AppHandle, lines: Option<usize>) -> Result<String, String> {
+    let dir = log_dir(&app)?;
+    Ok(recent_lines(&dir, lines.unwrap_or(DEFAULT_RECENT_LINES)).join("\n"))
+}
+
+/// Show the log folder in the system file manager
+#[tauri::command]
+pub fn open_log_folder(app: AppHandle) -> Result<(), String> {
+    let dir = log_dir(&app)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    #[cfg(target_os = "macos")]
+    let opener = "open";
+    #[cfg(target_os = "windows")]
+    let opener = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let opener = "xdg-open";
+
+    std::process::Command::new(opener)
+        .arg(&dir)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open the log folder: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), vec!["b", "c"]);
+        assert_eq!(tail_lines("a\nb", 5), vec!["a", "b"]);
+        assert!(tail_lines("", 3).is_empty());
+    }
+
+    #[test]
+    fn test_level_index_matches_levels() {
+        for level in [
+            Level::ERROR,
+            Level::WARN,
+            Level::INFO,
+            Level::DEBUG,
+            Level::TRACE,
+        ] {
+            assert_eq!(LEVELS[level_index(&level)], level.as_str().to_lowercase());
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Model Context Protocol server over stdio
+//!
+//! Agent hosts such as Claude Desktop launch `note67 --mcp` and exchange newline-delimited
+//! JSON-RPC messages on stdin/stdout. The server opens the database read-only and exposes
+//! tools for searching notes and reading transcripts and summaries. It is opt-in: tool
+//! calls are refused unless `mcp_enabled` is set in Note67's settings.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{Value, json};
+
+pub const SETTING_ENABLED: &str = "mcp_enabled";
+
+/// Command-line flag that starts the server instead of the app
+pub const FLAG: &str = "--mcp";
+
+/// Must match `identifier` in tauri.conf.json, which names the app data directory
+const APP_IDENTIFIER: &str = "com.note67.app";
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Maximum number of results for list-style tools
+const MAX_LIMIT: i64 = 50;
+
+/// Path of the database the app uses
+pub fn database_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER).join("note67.db"))
+}
+
+/// Serve requests from stdin until it closes. Returns the process exit code.
+pub fn run_stdio() -> i32 {
+    let Some(path) = database_path().filter(|p| p.exists()) else {
+        eprintln!("[mcp] Note67 database not found. Open Note67 once to create it.");
+        return 1;
+    };
+    let conn = match Connection::open_with_flags(
+        &path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    ) {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[mcp] Failed to open {}: {}", path.display(), e);
+            return 1;
+        }
+    };
+    // The app may be writing at the same time
+    let _ = conn.busy_timeout(std::time::Duration::from_secs(5));
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&conn, &message),
+            Err(e) => Some(error_response(
+                Value::Null,
+                -32700,
+                &format!("Parse error: {}", e),
+            )),
+        };
+        if let Some(response) = response {
+            let _ = writeln!(stdout, "{}", response);
+            let _ = stdout.flush();
+        }
+    }
+    0
+}
+
+/// Handle one JSON-RPC message. Notifications (no `id`) get no response.
+fn handle_message(conn: &Connection, message: &Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let method = message
+        .get("method")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "note67", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => Ok(call_tool(conn, &params)),
+        _ if id.is_none() => return None,
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    };
+
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_notes",
+            "description": "Search meeting notes by title, body, participants and transcript text. Returns matching notes, newest first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to search for" },
+                    "limit": { "type": "integer", "description": "Maximum results (default 10)" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "list_recent_notes",
+            "description": "List the most recent meeting notes.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "limit": { "type": "integer", "description": "Maximum results (default 10)" }
+                }
+            }
+        },
+        {
+            "name": "get_transcript",
+            "description": "Get the timestamped transcript of a note.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "note_id": { "type": "string" } },
+                "required": ["note_id"]
+            }
+        },
+        {
+            "name": "get_summary",
+            "description": "Get the AI summaries and open action items of a note.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "note_id": { "type": "string" } },
+                "required": ["note_id"]
+            }
+        }
+    ])
+}
+
+/// Run a tool. Failures are reported as tool results with `isError` so the agent sees them.
+fn call_tool(conn: &Connection, params: &Value) -> Value {
+    let name = params
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default();
+    let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+
+    let result = if is_enabled(conn) {
+        match name {
+            "search_notes" => search_notes(conn, &args),
+            "list_recent_notes" => list_recent_notes(conn, &args),
+            "get_transcript" => get_transcript(conn, &args),
+            "get_summary" => get_summary(conn, &args),
+            _ => Err(format!("Unknown tool: {}", name)),
+        }
+    } else {
+        Err("The Note67 MCP server is disabled. Enable it in Note67's settings.".to_string())
+    };
+
+    match result {
+        Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+        Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+    }
+}
+
+fn is_enabled(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        [SETTING_ENABLED],
+        |row| row.get::<_, String>(0),
+    )
+    .is_ok_and(|v| v == "true")
+}
+
+fn string_arg(args: &Value, name: &str) -> Result<String, String> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+fn limit_arg(args: &Value) -> i64 {
+    args.get("limit")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(10)
+        .clamp(1, MAX_LIMIT)
+}
+
+fn search_notes(conn: &Connection, args: &Value) -> Result<String, String> {
+    let query = string_arg(args, "query")?;
+    let pattern = format!("%{}%", query.to_lowercase());
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.title, n.started_at, n.participants FROM notes n
+             WHERE LOWER(n.title) LIKE ?1
+                OR LOWER(COALESCE(n.description, '')) LIKE ?1
+                OR LOWER(COALESCE(n.participants, '')) LIKE ?1
+                OR n.id IN (SELECT note_id FROM transcript_segments WHERE LOWER(text) LIKE ?1)
+             ORDER BY n.started_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let notes = note_rows(&mut stmt, rusqlite::params![pattern, limit_arg(args)])?;
+    if notes.is_empty() {
+        return Ok(format!("No notes match \"{}\".", query));
+    }
+    Ok(notes)
+}
+
+fn list_recent_notes(conn: &Connection, args: &Value) -> Result<String, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, started_at, participants FROM notes
+             ORDER BY started_at DESC LIMIT ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let notes = note_rows(&mut stmt, rusqlite::params![limit_arg(args)])?;
+    if notes.is_empty() {
+        return Ok("There are no notes yet.".to_string());
+    }
+    Ok(notes)
+}
+
+/// One line per note: id, date, title and participants
+fn note_rows(
+    stmt: &mut rusqlite::Statement,
+    params: impl rusqlite::Params,
+) -> Result<String, String> {
+    let rows: Vec<String> = stmt
+        .query_map(params, |row| {
+            let id: String = row.get(0)?;
+            let title: String = row.get(1)?;
+            let started_at: String = row.get(2)?;
+            let participants: Option<String> = row.get(3)?;
+            let date = started_at
+                .get(..16)
+                .unwrap_or(&started_at)
+                .replace('T', " ");
+            Ok(match participants.filter(|p| !p.trim().is_empty()) {
+                Some(p) => format!("- {} | {} | {} (with {})", id, date, title, p),
+                None => format!("- {} | {} | {}", id, date, title),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows.join("\n"))
+}
+
+fn note_title(conn: &Connection, note_id: &str) -> Result<String, String> {
+    conn.query_row("SELECT title FROM notes WHERE id = ?1", [note_id], |row| {
+        row.get(0)
+    })
+    .map_err(|_| format!("Note not found: {}", note_id))
+}
+
+fn get_transcript(conn: &Connection, args: &Value) -> Result<String, String> {
+    let note_id = string_arg(args, "note_id")?;
+    let title = note_title(conn, &note_id)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT start_time, text, speaker FROM transcript_segments
+             WHERE note_id = ?1 ORDER BY start_time ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let lines: Vec<String> = stmt
+        .query_map([&note_id], |row| {
+            let start: f64 = row.get(0)?;
+            let text: String = row.get(1)?;
+            let speaker: Option<String> = row.get(2)?;
+            let secs = start.max(0.0) as u64;
+            let timestamp = format!(
+                "{:02}:{:02}:{:02}",
+                secs / 3600,
+                (secs % 3600) / 60,
+                secs % 60
+            );
+            Ok(match speaker {
+                Some(speaker) => format!("[{}] {}: {}", timestamp, speaker, text.trim()),
+                None => format!("[{}] {}", timestamp, text.trim()),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    if lines.is_empty() {
+        return Ok(format!("\"{}\" has no transcript.", title));
+    }
+    Ok(format!("# {}\n\n{}", title, lines.join("\n")))
+}
+
+fn get_summary(conn: &Connection, args: &Value) -> Result<String, String> {
+    let note_id = string_arg(args, "note_id")?;
+    let title = note_title(conn, &note_id)?;
+    let mut out = format!("# {}\n", title);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT summary_type, content FROM summaries
+             WHERE note_id = ?1 ORDER BY created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let summaries: Vec<(String, String)> = stmt
+        .query_map([&note_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (summary_type, content) in &summaries {
+        out.push_str(&format!("\n## {}\n\n{}\n", summary_type, content.trim()));
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT text, assignee FROM action_items
+             WHERE note_id = ?1 AND done = 0 ORDER BY sort_order ASC, id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let tasks: Vec<String> = stmt
+        .query_map([&note_id], |row| {
+            let text: String = row.get(0)?;
+            let assignee: Option<String> = row.get(1)?;
+            Ok(match assignee {
+                Some(a) => format!("- [ ] {} (@{})", text, a),
+                None => format!("- [ ] {}", text),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .filter_map(|r| r.ok())
+        .collect();
+    if !tasks.is_empty() {
+        out.push_str(&format!("\n## Open action items\n\n{}\n", tasks.join("\n")));
+    }
+
+    if summaries.is_empty() && tasks.is_empty() {
+        return Ok(format!("\"{}\" has no summary yet.", title));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_message() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE notes (id TEXT PRIMARY KEY, title TEXT NOT NULL, description TEXT,
+                 participants TEXT, started_at TEXT NOT NULL);
+             CREATE TABLE transcript_segments (note_id TEXT, start_time REAL, text TEXT, speaker TEXT);
+             INSERT INTO notes VALUES ('n1', 'Budget review', NULL, 'Ana', '2026-01-15T10:00:00+00:00');
+             INSERT INTO transcript_segments VALUES ('n1', 65.0, 'Q3 numbers look good', 'Ana');",
+        )
+        .unwrap();
+
+        let init = handle_message(
+            &conn,
+            &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        )
+        .unwrap();
+        assert_eq!(init["result"]["serverInfo"]["name"], "note67");
+        assert!(
+            handle_message(
+                &conn,
+                &json!({"jsonrpc": "2.0", "method": "notifications/initialized"})
+            )
+            .is_none()
+        );
+        let unknown =
+            handle_message(&conn, &json!({"jsonrpc": "2.0", "id": 2, "method": "nope"})).unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+
+        let search = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": {"name": "search_notes", "arguments": {"query": "q3 NUMBERS"}}});
+        // Disabled until the user opts in
+        let refused = handle_message(&conn, &search).unwrap();
+        assert_eq!(refused["result"]["isError"], true);
+
+        conn.execute("INSERT INTO settings VALUES ('mcp_enabled', 'true')", [])
+            .unwrap();
+        let found = handle_message(&conn, &search).unwrap();
+        assert_eq!(
+            found["result"]["content"][0]["text"],
+            "- n1 | 2026-01-15 10:00 | Budget review (with Ana)"
+        );
+
+        let transcript = handle_message(
+            &conn,
+            &json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call",
+            "params": {"name": "get_transcript", "arguments": {"note_id": "n1"}}}),
+        )
+        .unwrap();
+        assert_eq!(
+            transcript["result"]["content"][0]["text"],
+            "# Budget review\n\n[00:01:05] Ana: Q3 numbers look good"
+        );
+    }
+}
@@ -1,13 +1,56 @@
 //! Meeting detection module for detecting when meeting apps start
 //! Supports browser-based meetings (Google Meet, etc.) via window title monitoring
 
+//! Native meeting apps (Zoom, Teams, Webex) are detected on every platform from their
+//! running processes plus microphone usage where the OS exposes it
+
 use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager};
 
+use crate::audio::RecordingPhase;
+use crate::commands::AudioState;
+use crate::db::Database;
+use crate::settings;
+
+/// Setting key: "true" starts a dual recording as soon as a meeting is detected
+pub const SETTING_AUTO_START: &str = "meeting_auto_start";
+
+/// Don't announce the same app again within this window (both detectors may see one call)
+const ANNOUNCE_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// A native meeting app and the processes that reveal an active call
+struct MeetingApp {
+    name: &'static str,
+    /// Helper processes that only run while a call is in progress
+    call_processes: &'static [&'static str],
+    /// Main app processes; counted as in a call when they're also capturing the microphone
+    app_processes: &'static [&'static str],
+}
+
+/// Process names are compared lowercased, without the ".exe" suffix
+const MEETING_APPS: &[MeetingApp] = &[
+    MeetingApp {
+        name: "Zoom",
+        call_processes: &["cpthost", "aomhost"],
+        app_processes: &["zoom.us", "zoom"],
+    },
+    MeetingApp {
+        name: "Microsoft Teams",
+        call_processes: &[],
+        app_processes: &["msteams", "ms-teams", "teams", "microsoft teams", "teams-for-linux"],
+    },
+    MeetingApp {
+        name: "Webex",
+        call_processes: &["atmgr", "webexmta", "meeting center"],
+        app_processes: &["webex", "ciscocollabhost", "cisco webex meetings"],
+    },
+];
+
 /// Patterns to detect active meetings (not just app/page open)
 const MEETING_PATTERNS: &[(&str, &str)] = &[
     // Google Meet - only when actually in a meeting (has meeting code)
@@ -55,13 +98,20 @@ pub struct MeetingDetected {
     pub app_name: String,
     pub bundle_id: Option<String>,
     pub is_browser: bool,
+    /// A recording was started for it without asking, as the user opted in to (see
+    /// "tray-recording-started")
+    pub auto_start: bool,
 }
 
 /// State for meeting detection
 pub struct MeetingDetectionState {
     enabled: AtomicBool,
     running: AtomicBool,
+    auto_start: AtomicBool,
     detected_meetings: std::sync::Mutex<std::collections::HashSet<String>>,
+    /// Native apps currently in a call (from process detection)
+    detected_calls: std::sync::Mutex<std::collections::HashSet<String>>,
+    last_announced: std::sync::Mutex<HashMap<String, Instant>>,
 }
 
 impl Default for MeetingDetectionState {
@@ -69,7 +119,10 @@ impl Default for MeetingDetectionState {
         Self {
             enabled: AtomicBool::new(true),
             running: AtomicBool::new(false),
+            auto_start: AtomicBool::new(false),
             detected_meetings: std::sync::Mutex::new(std::collections::HashSet::new()),
+            detected_calls: std::sync::Mutex::new(std::collections::HashSet::new()),
+            last_announced: std::sync::Mutex::new(HashMap::new()),
         }
     }
 }
@@ -83,14 +136,127 @@ impl MeetingDetectionState {
         self.enabled.load(Ordering::SeqCst)
     }
 
+    pub fn set_auto_start(&self, enabled: bool) {
+        self.auto_start.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_auto_start(&self) -> bool {
+        self.auto_start.load(Ordering::SeqCst)
+    }
+
     /// Clear all detected meetings
     pub fn clear_all_detected(&self) {
         if let Ok(mut detected) = self.detected_meetings.lock() {
             detected.clear();
         }
+        if let Ok(mut calls) = self.detected_calls.lock() {
+            calls.clear();
+        }
+        if let Ok(mut announced) = self.last_announced.lock() {
+            announced.clear();
+        }
+    }
+
+    /// Whether `app_name` was announced within the cooldown window
+    fn recently_announced(&self, app_name: &str) -> bool {
+        self.last_announced
+            .lock()
+            .map(|announced| {
+                announced
+                    .get(app_name)
+                    .is_some_and(|at| at.elapsed() < ANNOUNCE_COOLDOWN)
+            })
+            .unwrap_or(false)
     }
 }
 
+/// Match a window title against the meeting patterns, returning the meeting app name
+fn match_meeting_title(title: &str) -> Option<&'static str> {
+    // Skip if this matches a "not in meeting" pattern
+    if NOT_IN_MEETING_PATTERNS.iter().any(|p| title.contains(p)) {
+        return None;
+    }
+
+    // First check explicit meeting patterns
+    if let Some((_, meeting_name)) = MEETING_PATTERNS.iter().find(|(p, _)| title.contains(p)) {
+        return Some(meeting_name);
+    }
+
+    // If no explicit pattern, check for audio indicator (🔊)
+    if title.contains(AUDIO_ACTIVE_INDICATOR) {
+        return AUDIO_APPS
+            .iter()
+            .find(|(p, _)| title.contains(p))
+            .map(|(_, meeting_name)| *meeting_name);
+    }
+
+    None
+}
+
+/// Emit "meeting-detected" and show a native notification offering to record, or start
+/// recording when the user opted in to that. Nothing is offered while a recording is
+/// already running.
+fn announce_meeting(
+    app: &AppHandle,
+    state: &MeetingDetectionState,
+    meeting_name: &str,
+    is_browser: bool,
+) {
+    if let Ok(mut announced) = state.last_announced.lock() {
+        announced.insert(meeting_name.to_string(), Instant::now());
+    }
+    if app.state::<AudioState>().recording.get_phase() != RecordingPhase::Idle {
+        return;
+    }
+
+    let started = if state.is_auto_start() {
+        crate::commands::start_recording_in_new_note(app)
+    } else {
+        Ok(None)
+    };
+    let meeting = MeetingDetected {
+        app_name: meeting_name.to_string(),
+        bundle_id: None,
+        is_browser,
+        auto_start: matches!(started, Ok(Some(_))),
+    };
+    let _ = app.emit("meeting-detected", &meeting);
+
+    let body = match started {
+        Ok(Some(_)) => "Recording started automatically.".to_string(),
+        Ok(None) => "Open Note67 to start recording this meeting.".to_string(),
+        Err(e) => {
+            tracing::warn!("Failed to start recording {} meeting: {}", meeting_name, e);
+            format!("Couldn't start recording: {}", e)
+        }
+    };
+    crate::notifications::notify(&format!("{} meeting detected", meeting_name), &body);
+}
+
+/// Native meeting apps that look like they're in a call. `mic_apps` lists processes
+/// currently capturing the microphone, or None when the platform can't tell.
+fn detect_calls(processes: &[String], mic_apps: Option<&[String]>) -> Vec<&'static str> {
+    let running = |names: &[&str]| names.iter().any(|n| processes.iter().any(|p| p == n));
+    let using_mic = |names: &[&str]| {
+        mic_apps.is_some_and(|mic| names.iter().any(|n| mic.iter().any(|m| m == n)))
+    };
+
+    MEETING_APPS
+        .iter()
+        .filter(|app| {
+            running(app.call_processes) || (running(app.app_processes) && using_mic(app.app_processes))
+        })
+        .map(|app| app.name)
+        .collect()
+}
+
+/// Normalize a process name or path: file name, lowercased, without ".exe"
+fn normalize_process_name(name: &str) -> String {
+    let name = name.trim().trim_matches('"');
+    let base = name.rsplit(['/', '\\', '#']).next().unwrap_or(name).to_lowercase();
+    base.strip_suffix(".exe").map(str::to_string).unwrap_or(base)
+}
+
 /// Start meeting detection (call from setup)
 pub fn start_meeting_detection(app: &AppHandle) {
     let state = app.state::<Arc<MeetingDetectionState>>();
@@ -100,9 +266,172 @@ pub fn start_meeting_detection(app: &AppHandle) {
         return;
     }
 
+    if let Some(db) = app.try_state::<Database>() {
+        let auto_start = db.get_setting(SETTING_AUTO_START).ok().flatten();
+        state.set_auto_start(auto_start.as_deref() == Some("true"));
+    }
+
     // Start window title monitoring for meetings
     #[cfg(target_os = "macos")]
     start_window_title_detection(app.clone());
+
+    // Start process + microphone monitoring for native meeting apps
+    start_process_detection(app.clone());
+}
+
+fn start_process_detection(app: AppHandle) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_secs(5));
+
+            let Some(state) = app.try_state::<Arc<MeetingDetectionState>>() else {
+                continue;
+            };
+            if !state.is_enabled() {
+                continue;
+            }
+
+            let (processes, titles) = process_snapshot();
+            let mic_apps = microphone_apps();
+            let mut active: std::collections::HashSet<String> =
+                detect_calls(&processes, mic_apps.as_deref())
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect();
+
+            // Browser meetings on platforms without the macOS window title monitor
+            for title in &titles {
+                if let Some(meeting_name) = match_meeting_title(title) {
+                    active.insert(meeting_name.to_string());
+                }
+            }
+
+            let newly_active: Vec<String> = {
+                let mut calls = state.detected_calls.lock().unwrap();
+                let new = active.iter().filter(|a| !calls.contains(*a)).cloned().collect();
+                *calls = active;
+                new
+            };
+
+            for meeting_name in newly_active {
+                if state.recently_announced(&meeting_name) {
+                    continue;
+                }
+                tracing::info!("Detected {} call", meeting_name);
+                let is_browser = !MEETING_APPS.iter().any(|a| a.name == meeting_name);
+                announce_meeting(&app, &state, &meeting_name, is_browser);
+            }
+        }
+    });
+}
+
+/// Running process names (normalized) and, where cheap to get, visible window titles
+#[cfg(not(target_os = "windows"))]
+fn process_snapshot() -> (Vec<String>, Vec<String>) {
+    let processes = std::process::Command::new("ps")
+        .args(["-axo", "comm="])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .map(normalize_process_name)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // macOS titles come from the dedicated window title monitor
+    #[cfg(target_os = "macos")]
+    let titles = Vec::new();
+
+    // X11 window titles via wmctrl when installed: "<id> <desktop> <host> <title>"
+    #[cfg(not(target_os = "macos"))]
+    let titles = std::process::Command::new("wmctrl")
+        .arg("-l")
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .filter_map(|line| line.splitn(4, char::is_whitespace).nth(3))
+                .map(|t| t.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (processes, titles)
+}
+
+#[cfg(target_os = "windows")]
+fn process_snapshot() -> (Vec<String>, Vec<String>) {
+    // Verbose CSV: "Image Name","PID",...,"Window Title"
+    let output = crate::process::no_window(&mut std::process::Command::new("tasklist"))
+        .args(["/v", "/fo", "csv", "/nh"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+
+    let mut processes = Vec::new();
+    let mut titles = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split("\",\"").collect();
+        if let Some(name) = fields.first() {
+            processes.push(normalize_process_name(name));
+        }
+        if let Some(title) = fields.last().map(|t| t.trim().trim_matches('"'))
+            && fields.len() > 1
+            && title != "N/A"
+        {
+            titles.push(title.to_string());
+        }
+    }
+    (processes, titles)
+}
+
+/// Processes currently capturing the microphone (PulseAudio/PipeWire source outputs)
+#[cfg(target_os = "linux")]
+fn microphone_apps() -> Option<Vec<String>> {
+    let output = std::process::Command::new("pactl")
+        .args(["list", "source-outputs"])
+        .output()
+        .ok()?;
+    let apps = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("application.process.binary = "))
+        .map(normalize_process_name)
+        .collect();
+    Some(apps)
+}
+
+/// Processes currently capturing the microphone, from the privacy consent store:
+/// apps using the mic right now have `LastUsedTimeStop` = 0
+#[cfg(target_os = "windows")]
+fn microphone_apps() -> Option<Vec<String>> {
+    const CONSENT_KEY: &str = "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\microphone";
+
+    let output = crate::process::no_window(&mut std::process::Command::new("reg"))
+        .args(["query", CONSENT_KEY, "/s", "/v", "LastUsedTimeStop"])
+        .output()
+        .ok()?;
+
+    let mut apps = Vec::new();
+    let mut current_key = String::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if line.starts_with("HKEY_") {
+            current_key = line.trim().to_string();
+        } else if line.contains("LastUsedTimeStop") && line.trim_end().ends_with("0x0") {
+            // Desktop apps: "...\NonPackaged\C:#Path#To#App.exe"
+            // Store apps: "...\MSTeams_8wekyb3d8bbwe"
+            let name = normalize_process_name(&current_key);
+            let name = name.split('_').next().unwrap_or(&name).to_string();
+            apps.push(name);
+        }
+    }
+    Some(apps)
+}
+
+/// macOS doesn't expose which app holds the microphone; rely on in-call helper processes
+#[cfg(target_os = "macos")]
+fn microphone_apps() -> Option<Vec<String>> {
+    None
 }
 
 #[cfg(target_os = "macos")]
@@ -174,38 +503,10 @@ fn start_window_title_detection(app: AppHandle) {
                                     || title_str.to_lowercase().contains("slack")
                                     || title_str.to_lowercase().contains("huddle"))
                             {
-                                println!("[meeting-detection] Found window: '{}'", title_str);
-                            }
-
-                            // Skip if this matches a "not in meeting" pattern
-                            let is_not_meeting = NOT_IN_MEETING_PATTERNS
-                                .iter()
-                                .any(|p| title_str.contains(p));
-                            if is_not_meeting {
-                                continue;
+                                tracing::debug!("Found window: '{}'", title_str);
                             }
 
-                            // Check for meeting patterns in window title
-                            let mut detected_app: Option<&str> = None;
-
-                            // First check explicit meeting patterns
-                            for (pattern, meeting_name) in MEETING_PATTERNS {
-                                if title_str.contains(pattern) {
-                                    detected_app = Some(*meeting_name);
-                                    break;
-                                }
-                            }
-
-                            // If no explicit pattern, check for audio indicator (🔊)
-                            if detected_app.is_none() && title_str.contains(AUDIO_ACTIVE_INDICATOR)
-                            {
-                                for (app_pattern, meeting_name) in AUDIO_APPS {
-                                    if title_str.contains(app_pattern) {
-                                        detected_app = Some(*meeting_name);
-                                        break;
-                                    }
-                                }
-                            }
+                            let detected_app = match_meeting_title(&title_str);
 
                             if let Some(meeting_name) = detected_app {
                                 // Use title without emoji as key (emoji changes during call)
@@ -227,18 +528,12 @@ fn start_window_title_detection(app: AppHandle) {
                                 };
 
                                 if should_emit {
-                                    println!(
-                                        "[meeting-detection] Detected {} meeting: '{}'",
+                                    tracing::info!(
+                                        "Detected {} meeting: '{}'",
                                         meeting_name, title_str
                                     );
 
-                                    let meeting = MeetingDetected {
-                                        app_name: meeting_name.to_string(),
-                                        bundle_id: None,
-                                        is_browser: true,
-                                    };
-
-                                    let _ = app.emit("meeting-detected", &meeting);
+                                    announce_meeting(&app, &state, meeting_name, true);
                                 }
                             }
                         }
@@ -272,9 +567,68 @@ pub fn is_meeting_detection_enabled(state: tauri::State<Arc<MeetingDetectionStat
     state.is_enabled()
 }
 
+/// Tauri command to enable/disable starting a recording automatically when a meeting is detected
+#[tauri::command]
+pub fn set_meeting_auto_start(
+    app: AppHandle,
+    state: tauri::State<Arc<MeetingDetectionState>>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, SETTING_AUTO_START, if enabled { "true" } else { "false" })
+        .map_err(|e| e.to_string())?;
+    state.set_auto_start(enabled);
+    Ok(())
+}
+
+/// Tauri command to check if recordings auto-start on detected meetings
+#[tauri::command]
+pub fn is_meeting_auto_start_enabled(state: tauri::State<Arc<MeetingDetectionState>>) -> bool {
+    state.is_auto_start()
+}
+
 /// Tauri command to clear all detected meetings (allows re-detection)
 #[tauri::command]
 pub fn clear_detected_meetings(state: tauri::State<Arc<MeetingDetectionState>>) {
     state.clear_all_detected();
-    println!("[meeting-detection] Cleared all detected meetings");
+    tracing::info!("Cleared all detected meetings");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_calls() {
+        let processes: Vec<String> = ["finder", "zoom.us", "msteams", "cpthost"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        // Zoom's in-call helper is running; Teams is open but not using the mic
+        let mic: Vec<String> = vec!["zoom.us".to_string()];
+        assert_eq!(detect_calls(&processes, Some(&mic)), vec!["Zoom"]);
+
+        let mic: Vec<String> = vec!["msteams".to_string()];
+        assert_eq!(
+            detect_calls(&processes, Some(&mic)),
+            vec!["Zoom", "Microsoft Teams"]
+        );
+
+        // Without mic info only helper processes count
+        let processes = vec!["zoom.us".to_string(), "msteams".to_string()];
+        assert!(detect_calls(&processes, None).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_process_name() {
+        assert_eq!(
+            normalize_process_name("/Applications/zoom.us.app/Contents/MacOS/zoom.us"),
+            "zoom.us"
+        );
+        assert_eq!(normalize_process_name("\"Zoom.exe"), "zoom");
+        assert_eq!(
+            normalize_process_name("C:#Users#me#AppData#Roaming#Zoom#bin#Zoom.exe"),
+            "zoom"
+        );
+    }
 }
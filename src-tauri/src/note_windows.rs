@@ -0,0 +1,170 @@
+//! Notes opened in their own windows. Each window is labeled `note-<note id>` and loads the
+//! frontend with `?window=note&noteId=<id>`. Events about a single note (live transcript,
+//! summary stream, re-transcription progress) go out through `emit_to_note`, which delivers
+//! them to the window showing that note but not to the other note windows.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{
+    AppHandle, Emitter, EventTarget, Listener, Manager, WebviewUrl, WebviewWindowBuilder,
+    WindowEvent,
+};
+
+use crate::db::Database;
+use crate::window_state;
+
+pub const LABEL_PREFIX: &str = "note-";
+
+const WIDTH: f64 = 760.0;
+const HEIGHT: f64 = 720.0;
+
+/// Open note windows, note id -> window label
+static OPEN: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+fn label_for(note_id: &str) -> String {
+    format!("{}{}", LABEL_PREFIX, note_id)
+}
+
+/// Note ids end up in a window label and a URL, which both want `[A-Za-z0-9_-]`;
+/// UUIDs always qualify
+fn is_valid_note_id(note_id: &str) -> bool {
+    !note_id.is_empty()
+        && note_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Emit an event that concerns one note: every window except the other notes' windows
+/// receives it
+pub fn emit_to_note<S: Serialize + Clone>(app: &AppHandle, note_id: &str, event: &str, payload: S) {
+    let own = label_for(note_id);
+    let _ = app.emit_filter(event, payload, |target| match target {
+        EventTarget::Window { label }
+        | EventTarget::Webview { label }
+        | EventTarget::WebviewWindow { label }
+        | EventTarget::AnyLabel { label } => !label.starts_with(LABEL_PREFIX) || *label == own,
+        _ => true,
+    });
+}
+
+/// Open a note in its own window, or focus the window already showing it
+// async: creating a window from a sync command deadlocks on Windows
+#[tauri::command]
+pub async fn open_note_window(app: AppHandle, note_id: String) -> Result<(), String> {
+    if !is_valid_note_id(&note_id) {
+        return Err(format!("Invalid note id: {}", note_id));
+    }
+    let label = label_for(&note_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    let title: String = {
+        let db = app.state::<Database>();
+        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        conn.query_row("SELECT title FROM notes WHERE id = ?1", [&note_id], |row| {
+            row.get(0)
+        })
+        .map_err(|_| format!("Note not found: {}", note_id))?
+    };
+
+    let url = format!("index.html?window=note&noteId={}", note_id);
+    let window = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(url.into()))
+        .title(if title.is_empty() { "Untitled" } else { &title })
+        .inner_size(WIDTH, HEIGHT)
+        .min_inner_size(420.0, 360.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+    window_state::track(&window);
+
+    OPEN.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(note_id.clone(), label);
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            OPEN.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&note_id);
+        }
+    });
+    Ok(())
+}
+
+/// Close the window showing a note, if any
+#[tauri::command]
+pub fn close_note_window(app: AppHandle, note_id: String) -> Result<(), String> {
+    match app.get_webview_window(&label_for(&note_id)) {
+        Some(window) => window.destroy().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Ids of the notes currently open in their own windows
+#[tauri::command]
+pub fn list_note_windows() -> Vec<String> {
+    OPEN.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Keep window titles in step with their notes and close the windows of deleted notes
+pub fn watch_notes(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen("note-updated", move |event| {
+        let Ok(note_id) = serde_json::from_str::<String>(event.payload()) else {
+            return;
+        };
+        let Some(window) = handle.get_webview_window(&label_for(&note_id)) else {
+            return;
+        };
+        // Listeners run on the emitting thread, which may hold the database
+        refresh_title(&handle, window, note_id);
+    });
+
+    let handle = app.clone();
+    app.listen("note-deleted", move |event| {
+        if let Ok(note_id) = serde_json::from_str::<String>(event.payload())
+            && let Some(window) = handle.get_webview_window(&label_for(&note_id))
+        {
+            let _ = window.destroy();
+        }
+    });
+}
+
+fn refresh_title(app: &AppHandle, window: tauri::WebviewWindow, note_id: String) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let db = app.state::<Database>();
+        let Ok(conn) = db.conn.lock() else {
+            return;
+        };
+        let title: Option<String> = conn
+            .query_row("SELECT title FROM notes WHERE id = ?1", [&note_id], |row| {
+                row.get(0)
+            })
+            .ok();
+        drop(conn);
+        if let Some(title) = title {
+            let _ = window.set_title(if title.is_empty() { "Untitled" } else { &title });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_note_id() {
+        assert!(is_valid_note_id("3f2b9c1e-0000-4a5b-8c7d-123456789abc"));
+        assert!(!is_valid_note_id(""));
+        assert!(!is_valid_note_id("a&window=main"));
+        assert!(!is_valid_note_id("../x"));
+    }
+}
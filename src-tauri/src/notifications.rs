@@ -0,0 +1,184 @@
+//! Native desktop notifications, shown through the platform's notification tool
+//! macOS: `osascript`, Linux: `notify-send`, Windows: a PowerShell toast
+
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::db::Database;
+use crate::storage;
+
+/// "false" turns off notifications for background events
+pub const SETTING_ENABLED: &str = "background_notifications_enabled";
+
+/// Warn when the storage location's volume has less free space than this
+pub(crate) const LOW_DISK_SPACE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Warn again only after free space has recovered past this
+const LOW_DISK_SPACE_RESET_BYTES: u64 = 2 * LOW_DISK_SPACE_BYTES;
+const DISK_SPACE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Show a notification without blocking the caller. Failures are logged and ignored.
+pub fn notify(title: &str, body: &str) {
+    let mut command = build_command(title, body);
+    thread::spawn(move || {
+        if let Err(e) = command.status() {
+            tracing::warn!("Failed to show notification: {}", e);
+        }
+    });
+}
+
+/// Notify about something that finished in the background. Skipped when the user turned
+/// these off, or when the main window is in front and shows it anyway.
+pub fn notify_background(app: &AppHandle, title: &str, body: &str) {
+    let enabled = app
+        .try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_ENABLED).ok().flatten())
+        .map(|value| value != "false")
+        .unwrap_or(true);
+    if !enabled {
+        return;
+    }
+
+    let in_front = app.get_webview_window("main").is_some_and(|window| {
+        window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false)
+    });
+    if !in_front {
+        notify(title, body);
+    }
+}
+
+/// Watch the free space where recordings are written and warn once when it runs low
+/// (call from setup)
+pub fn start_disk_space_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut warned = false;
+        loop {
+            // Looked up each time, since the storage location can move
+            let free = storage::data_root(&app)
+                .ok()
+                .and_then(|dir| available_space(&dir));
+            match free {
+                Some(free) if free < LOW_DISK_SPACE_BYTES && !warned => {
+                    warned = true;
+                    notify_background(
+                        &app,
+                        "Low disk space",
+                        &format!(
+                            "Only {} left for recordings. Free up space to keep recording.",
+                            format_bytes(free)
+                        ),
+                    );
+                }
+                Some(free) if free > LOW_DISK_SPACE_RESET_BYTES => warned = false,
+                _ => {}
+            }
+            thread::sleep(DISK_SPACE_INTERVAL);
+        }
+    });
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MB: u64 = 1024 * 1024;
+    if bytes >= 1024 * MB {
+        format!("{:.1} GB", bytes as f64 / (1024 * MB) as f64)
+    } else {
+        format!("{} MB", bytes / MB)
+    }
+}
+
+/// Bytes available to this user on the volume holding `path`, which need not exist yet
+pub(crate) fn available_space(path: &Path) -> Option<u64> {
+    volume_space(path.ancestors().find(|p| p.exists())?)
+}
+
+#[cfg(unix)]
+fn volume_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = stat.f_bavail as u64 * stat.f_frsize as u64;
+    Some(available)
+}
+
+#[cfg(target_os = "windows")]
+fn volume_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(target_os = "macos")]
+fn build_command(title: &str, body: &str) -> Command {
+    let script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        applescript_escape(body),
+        applescript_escape(title)
+    );
+    let mut command = Command::new("osascript");
+    command.args(["-e", &script]);
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(target_os = "windows")]
+fn build_command(title: &str, body: &str) -> Command {
+    // The text is passed in the environment, never spliced into the script: PowerShell
+    // quoting can't be trusted with meeting titles from outside invites
+    let script = "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $xml.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($xml.CreateTextNode($env:NOTE67_TOAST_TITLE)) > $null; \
+         $text.Item(1).AppendChild($xml.CreateTextNode($env:NOTE67_TOAST_BODY)) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('Note67').Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("NOTE67_TOAST_TITLE", title)
+        .env("NOTE67_TOAST_BODY", body);
+    crate::process::no_window(&mut command);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn build_command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name", "Note67", title, body]);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512 * 1024 * 1024), "512 MB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.5 GB");
+    }
+}
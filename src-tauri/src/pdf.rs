@@ -0,0 +1,266 @@
+//! Minimal PDF writer for note exports
+//! Lays out markdown as wrapped text with the built-in Helvetica fonts (no embedding),
+//! so only Windows-1252 text can be shown: check `can_render` first, as the rest would be
+//! replaced with '?'
+
+/// A4 page size in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const BODY_SIZE: f32 = 10.5;
+
+struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+}
+
+/// Whether every character of `markdown` can be shown in the PDF
+pub fn can_render(markdown: &str) -> bool {
+    markdown
+        .chars()
+        .all(|c| matches!(c, '☐' | '☑' | '\t' | '\r' | '\n') || win_ansi(c).is_some())
+}
+
+/// Render a markdown document to PDF bytes. Headings become bold, other markup is stripped.
+pub fn markdown_to_pdf(markdown: &str) -> Vec<u8> {
+    let lines = layout(markdown);
+    let pages = paginate(&lines);
+    write_document(&pages)
+}
+
+fn layout(markdown: &str) -> Vec<Line> {
+    let max_width = PAGE_WIDTH - 2.0 * MARGIN;
+    let mut lines = Vec::new();
+
+    for raw in markdown.lines() {
+        let trimmed = raw.trim_end();
+        let (text, size, bold) = if let Some(h) = trimmed.strip_prefix("# ") {
+            (h, 18.0, true)
+        } else if let Some(h) = trimmed.strip_prefix("## ") {
+            (h, 14.0, true)
+        } else if let Some(h) = trimmed.strip_prefix("### ") {
+            (h, 12.0, true)
+        } else if trimmed == "---" {
+            ("", BODY_SIZE, false)
+        } else {
+            (trimmed, BODY_SIZE, false)
+        };
+
+        let text = text.replace("**", "").replace('☐', "[ ]").replace('☑', "[x]");
+        if text.is_empty() {
+            lines.push(Line {
+                text: String::new(),
+                size,
+                bold,
+            });
+            continue;
+        }
+
+        for wrapped in wrap(&text, size, max_width) {
+            lines.push(Line {
+                text: wrapped,
+                size,
+                bold,
+            });
+        }
+    }
+
+    lines
+}
+
+/// Greedy word wrap using approximate Helvetica glyph widths
+fn wrap(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let width = |s: &str| s.chars().map(char_width).sum::<f32>() * size;
+    let mut out = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split(' ') {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if width(&candidate) <= max_width || current.is_empty() {
+            current = candidate;
+        } else {
+            out.push(std::mem::take(&mut current));
+            current = word.to_string();
+        }
+    }
+    out.push(current);
+    out
+}
+
+/// Approximate advance width of a glyph, in ems
+fn char_width(c: char) -> f32 {
+    match c {
+        'i' | 'j' | 'l' | '.' | ',' | '\'' | '|' | '!' | ':' | ';' => 0.25,
+        'f' | 't' | 'r' | 'I' | ' ' | '(' | ')' | '[' | ']' => 0.32,
+        'm' | 'w' | 'M' | 'W' => 0.85,
+        'A'..='Z' => 0.68,
+        _ => 0.56,
+    }
+}
+
+fn paginate(lines: &[Line]) -> Vec<Vec<u8>> {
+    let mut pages = Vec::new();
+    let mut content = Vec::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    for line in lines {
+        let leading = line.size * 1.4;
+        if y - leading < MARGIN {
+            pages.push(std::mem::take(&mut content));
+            y = PAGE_HEIGHT - MARGIN;
+        }
+        y -= leading;
+
+        if line.text.is_empty() {
+            continue;
+        }
+        let font = if line.bold { "F2" } else { "F1" };
+        content.extend_from_slice(
+            format!("BT /{} {} Tf {} {} Td (", font, line.size, MARGIN, y).as_bytes(),
+        );
+        content.extend(encode_text(&line.text));
+        content.extend_from_slice(b") Tj ET\n");
+    }
+
+    pages.push(content);
+    pages
+}
+
+/// Encode text as a WinAnsi PDF string body, escaping delimiters
+fn encode_text(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                c as u8
+            }
+            '\t' => b' ',
+            c => win_ansi(c).unwrap_or(b'?'),
+        };
+        out.push(byte);
+    }
+    out
+}
+
+/// The Windows-1252 byte for `c`, if it has one
+fn win_ansi(c: char) -> Option<u8> {
+    let byte = match c {
+        c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u32 as u8,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '€' => 0x80,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+/// Serialize catalog, page tree, fonts and pages with a cross-reference table
+fn write_document(pages: &[Vec<u8>]) -> Vec<u8> {
+    // Objects: 1 catalog, 2 pages, 3-4 fonts, then a (page, content) pair per page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + i * 2).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+
+    for (page_id, content) in page_ids.iter().zip(pages) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(object);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref_offset = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_pdf() {
+        let long_line = "word ".repeat(400);
+        let markdown = format!("# Weekly Sync\n\n**Date:** today (café)\n\n{}", long_line);
+        let pdf = markdown_to_pdf(&markdown);
+
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("(Weekly Sync) Tj"));
+        assert!(text.contains("today \\(caf"));
+
+        // The startxref offset points at the xref table
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|s| s.lines().next())
+            .and_then(|s| s.parse().ok())
+            .unwrap();
+        assert!(pdf[startxref..].starts_with(b"xref"));
+    }
+
+    #[test]
+    fn test_can_render() {
+        assert!(can_render("# Café — “notes”\n\n☐ Follow up €"));
+        assert!(!can_render("# 会议记录"));
+        assert!(!can_render("Встреча"));
+    }
+}
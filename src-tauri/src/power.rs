@@ -0,0 +1,208 @@
+//! Battery saver. A monitor thread polls whether the machine runs on battery; with the
+//! battery saver setting on, being on battery puts the app in a throttled state that other
+//! modules check with `throttled()`:
+//!   live transcription runs half as often (the audio waits in the buffers, so nothing is lost)
+//!   a smaller transcription model is suggested when the loaded one is large
+//!   scheduled backups and update downloads wait until the machine is back on AC
+//! Summaries only run when asked for, so there is no summary work to defer.
+//! "power-state-changed" tells the UI when the state changes, and "background-job-deferred"
+//! when a job is held back, so it can say why things slowed down.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::TranscriptionState;
+use crate::db::Database;
+use crate::transcription::ModelSize;
+
+/// "true" backs off while on battery
+pub const SETTING_ENABLED: &str = "battery_saver_enabled";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Models larger than this are worth swapping out on battery
+const BATTERY_MODEL_MAX_MB: u64 = 300;
+
+static ON_BATTERY: AtomicBool = AtomicBool::new(false);
+static THROTTLED: AtomicBool = AtomicBool::new(false);
+
+/// Payload of "power-state-changed" and the result of `get_power_status`
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub throttled: bool,
+    /// A lighter model to switch to, when throttled and the current one is large
+    pub suggested_model: Option<ModelSize>,
+}
+
+/// Payload of "background-job-deferred"
+#[derive(Debug, Clone, Serialize)]
+pub struct DeferredJob {
+    /// "backup" or "update_download"
+    pub job: &'static str,
+    pub reason: &'static str,
+}
+
+/// Whether work should back off (battery saver on and running on battery)
+pub fn throttled() -> bool {
+    THROTTLED.load(Ordering::Relaxed)
+}
+
+/// Tell the UI a background job was held back; returns whether it should wait
+pub fn defer(app: &AppHandle, job: &'static str) -> bool {
+    if !throttled() {
+        return false;
+    }
+    tracing::info!("Deferring {} until on AC power", job);
+    let _ = app.emit(
+        "background-job-deferred",
+        DeferredJob {
+            job,
+            reason: "battery",
+        },
+    );
+    true
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_ENABLED).ok().flatten())
+        .is_some_and(|v| v == "true")
+}
+
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        loop {
+            let on_battery = platform::on_battery();
+            let throttled = on_battery && enabled(&app);
+            let battery_changed = ON_BATTERY.swap(on_battery, Ordering::Relaxed) != on_battery;
+            let throttle_changed = THROTTLED.swap(throttled, Ordering::Relaxed) != throttled;
+            if battery_changed || throttle_changed {
+                tracing::info!(
+                    "Power: {} (battery saver {})",
+                    if on_battery { "battery" } else { "AC" },
+                    if throttled { "active" } else { "inactive" }
+                );
+                let _ = app.emit("power-state-changed", status(&app));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// The largest downloaded model within the battery budget, or one worth downloading.
+/// None when `current` is already light enough.
+fn suggested_model(current: ModelSize, downloaded: &[ModelSize]) -> Option<ModelSize> {
+    if current.size_mb() <= BATTERY_MODEL_MAX_MB {
+        return None;
+    }
+    downloaded
+        .iter()
+        .copied()
+        .filter(|m| m.size_mb() <= BATTERY_MODEL_MAX_MB)
+        .max_by_key(|m| m.size_mb())
+        .or(Some(ModelSize::SmallQ8))
+}
+
+fn status(app: &AppHandle) -> PowerStatus {
+    let throttled = throttled();
+    let suggested_model = throttled
+        .then(|| app.try_state::<TranscriptionState>())
+        .flatten()
+        .and_then(|state| {
+            let current = state.model.current()?;
+            let downloaded: Vec<ModelSize> = state
+                .model_manager
+                .lock()
+                .ok()?
+                .as_ref()?
+                .list_models()
+                .into_iter()
+                .filter(|m| m.downloaded)
+                .map(|m| m.size)
+                .collect();
+            suggested_model(current, &downloaded)
+        });
+    PowerStatus {
+        on_battery: ON_BATTERY.load(Ordering::Relaxed),
+        throttled,
+        suggested_model,
+    }
+}
+
+#[tauri::command]
+pub fn get_power_status(app: AppHandle) -> PowerStatus {
+    status(&app)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    /// `pmset -g batt` starts with "Now drawing from 'Battery Power'" or "'AC Power'"
+    pub fn on_battery() -> bool {
+        std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).contains("'Battery Power'"))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    /// ACLineStatus: 0 offline, 1 online, 255 unknown
+    pub fn on_battery() -> bool {
+        let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+        unsafe { GetSystemPowerStatus(&mut status) != 0 && status.ACLineStatus == 0 }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use std::fs;
+
+    /// On battery when a battery is present and no mains adapter is online
+    pub fn on_battery() -> bool {
+        let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+            return false;
+        };
+        let mut battery = false;
+        for entry in entries.flatten() {
+            let read = |name: &str| {
+                fs::read_to_string(entry.path().join(name))
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_default()
+            };
+            match read("type").as_str() {
+                "Mains" | "USB" if read("online") == "1" => return false,
+                "Battery" => battery = true,
+                _ => {}
+            }
+        }
+        battery
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggested_model() {
+        use ModelSize::*;
+        // Light enough already
+        assert_eq!(suggested_model(Base, &[Base, Medium]), None);
+        // The largest downloaded model within budget
+        assert_eq!(suggested_model(Medium, &[Tiny, Base, Medium]), Some(Base));
+        assert_eq!(
+            suggested_model(Large, &[Base, SmallQ8, Large]),
+            Some(SmallQ8)
+        );
+        // Nothing suitable downloaded
+        assert_eq!(suggested_model(Large, &[Large]), Some(SmallQ8));
+    }
+}
@@ -0,0 +1,13 @@
+//! Running external programs (tasklist, reg, sftp, converters, ...) without a console window
+
+use std::process::Command;
+
+/// Keep `command` from flashing a console window on Windows; does nothing elsewhere
+pub fn no_window(command: &mut Command) -> &mut Command {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(windows_sys::Win32::System::Threading::CREATE_NO_WINDOW);
+    }
+    command
+}
@@ -0,0 +1,336 @@
+//! Performance traces for diagnosing slow recordings, transcriptions and AI requests. With
+//! the opt-in setting on, every `tracing` span (recording start/stop, live transcription
+//! ticks, Whisper runs, database transactions, Ollama requests) is written to
+//! `<app data>/logs/trace-<time>.json` in the Chrome trace format, which chrome://tracing,
+//! Perfetto and speedscope open as a timeline or flame graph. With it off, spans are
+//! disabled at their callsites and cost nothing.
+//!
+//! Each span becomes one complete ("X") event from its creation to its close, on the
+//! thread that created it, so a span held across `.await`s covers the whole request.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Listener, Manager};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Record};
+
+use crate::db::Database;
+use crate::logging;
+
+/// Write a trace file (off by default)
+pub const SETTING_ENABLED: &str = "profiling_enabled";
+
+const FILE_PREFIX: &str = "trace-";
+/// Older trace files are deleted beyond this many
+const KEEP_FILES: usize = 5;
+/// A trace stops growing at this size; turn the setting off and on for a new one
+const MAX_FILE_BYTES: u64 = 100 * 1024 * 1024;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACE: Mutex<Option<TraceFile>> = Mutex::new(None);
+static SPANS: Mutex<Option<HashMap<u64, OpenSpan>>> = Mutex::new(None);
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Small stable id for this thread in traces
+    static THREAD_ID: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+}
+
+struct TraceFile {
+    file: File,
+    size: u64,
+    started: Instant,
+    /// Threads whose name was written
+    named: HashSet<u64>,
+}
+
+impl TraceFile {
+    fn create(path: &Path) -> std::io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        // The closing bracket is optional in the trace format, so a trace cut short by
+        // a crash still opens
+        file.write_all(b"[\n")?;
+        Ok(Self {
+            file,
+            size: 2,
+            started: Instant::now(),
+            named: HashSet::new(),
+        })
+    }
+
+    fn write_event(&mut self, event: &Value) {
+        if self.size >= MAX_FILE_BYTES {
+            return;
+        }
+        let line = format!("{},\n", event);
+        if self.file.write_all(line.as_bytes()).is_ok() {
+            self.size += line.len() as u64;
+        }
+    }
+}
+
+struct OpenSpan {
+    name: &'static str,
+    category: &'static str,
+    args: Map<String, Value>,
+    start: Instant,
+    thread: u64,
+    thread_name: Option<String>,
+    /// Handles to the span still alive
+    refs: usize,
+}
+
+/// Span fields as trace event arguments
+struct Args<'a>(&'a mut Map<String, Value>);
+
+impl Visit for Args<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A span was created (called by the log subscriber)
+pub(crate) fn open_span(id: u64, attrs: &Attributes<'_>) {
+    if !is_enabled() {
+        return;
+    }
+    let metadata = attrs.metadata();
+    let mut args = Map::new();
+    attrs.record(&mut Args(&mut args));
+    let span = OpenSpan {
+        name: metadata.name(),
+        category: metadata.target(),
+        args,
+        start: Instant::now(),
+        thread: THREAD_ID.with(|id| *id),
+        thread_name: std::thread::current().name().map(str::to_string),
+        refs: 1,
+    };
+    lock(&SPANS)
+        .get_or_insert_with(HashMap::new)
+        .insert(id, span);
+}
+
+/// Fields were recorded on a span after it was created
+pub(crate) fn record_span(id: u64, values: &Record<'_>) {
+    if let Some(span) = lock(&SPANS).as_mut().and_then(|spans| spans.get_mut(&id)) {
+        values.record(&mut Args(&mut span.args));
+    }
+}
+
+pub(crate) fn clone_span(id: u64) {
+    if let Some(span) = lock(&SPANS).as_mut().and_then(|spans| spans.get_mut(&id)) {
+        span.refs += 1;
+    }
+}
+
+/// A handle to the span was dropped; the last one writes its event. Returns whether the
+/// span is closed.
+pub(crate) fn close_span(id: u64) -> bool {
+    let span = {
+        let mut spans = lock(&SPANS);
+        let Some(spans) = spans.as_mut() else {
+            return false;
+        };
+        let Some(span) = spans.get_mut(&id) else {
+            return false;
+        };
+        span.refs -= 1;
+        if span.refs > 0 {
+            return false;
+        }
+        spans.remove(&id)
+    };
+    if let Some(span) = span {
+        write_span(span);
+    }
+    true
+}
+
+fn write_span(span: OpenSpan) {
+    let mut trace = lock(&TRACE);
+    let Some(trace) = trace.as_mut() else {
+        return;
+    };
+    // Spans opened before this trace started are cut to its start
+    let start = span.start.max(trace.started);
+    if trace.named.insert(span.thread) {
+        let name = span
+            .thread_name
+            .unwrap_or_else(|| format!("thread {}", span.thread));
+        trace.write_event(&json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": 1,
+            "tid": span.thread,
+            "args": { "name": name },
+        }));
+    }
+    trace.write_event(&json!({
+        "name": span.name,
+        "cat": span.category,
+        "ph": "X",
+        "ts": start.duration_since(trace.started).as_micros() as u64,
+        "dur": start.elapsed().as_micros() as u64,
+        "pid": 1,
+        "tid": span.thread,
+        "args": span.args,
+    }));
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn start(dir: &Path) {
+    if is_enabled() {
+        return;
+    }
+    remove_old_traces(dir);
+    let path = dir.join(format!(
+        "{}{}.json",
+        FILE_PREFIX,
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    match fs::create_dir_all(dir).and_then(|_| TraceFile::create(&path)) {
+        Ok(trace) => {
+            *lock(&TRACE) = Some(trace);
+            ENABLED.store(true, Ordering::Relaxed);
+            // Callsites cache whether their spans are enabled; make them ask again
+            tracing::callsite::rebuild_interest_cache();
+            tracing::info!("Profiling to {}", path.display());
+        }
+        Err(e) => tracing::warn!("Failed to start profiling: {}", e),
+    }
+}
+
+fn stop() {
+    if !ENABLED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    tracing::callsite::rebuild_interest_cache();
+    *lock(&SPANS) = None;
+    if let Some(mut trace) = lock(&TRACE).take() {
+        let _ = trace.file.write_all(b"{}]\n");
+    }
+    tracing::info!("Profiling stopped");
+}
+
+/// Keep the newest `KEEP_FILES - 1` traces, making room for a new one
+fn remove_old_traces(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut traces: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(FILE_PREFIX) && n.ends_with(".json"))
+        })
+        .collect();
+    // Names are timestamps, so they sort chronologically
+    traces.sort();
+    let excess = (traces.len() + 1).saturating_sub(KEEP_FILES);
+    for path in traces.into_iter().take(excess) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Start a trace if the setting is on, and follow changes to it. Call after `logging::init`.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = logging::log_dir(app) else {
+        return;
+    };
+    let enabled = app
+        .state::<Database>()
+        .get_setting(SETTING_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    if enabled {
+        start(&dir);
+    }
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", move |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload())
+            && key == SETTING_ENABLED
+        {
+            match value {
+                Some(Value::Bool(true)) => start(&dir),
+                _ => stop(),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_old_traces() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..KEEP_FILES + 2 {
+            fs::write(
+                dir.path().join(format!("{}2026010{}T000000Z.json", FILE_PREFIX, i)),
+                "",
+            )
+            .unwrap();
+        }
+        fs::write(dir.path().join("note67.log"), "").unwrap();
+
+        remove_old_traces(dir.path());
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+
+        assert_eq!(left.len(), KEEP_FILES);
+        assert_eq!(left[0], "note67.log");
+        assert_eq!(left[1], format!("{}20260103T000000Z.json", FILE_PREFIX));
+    }
+}
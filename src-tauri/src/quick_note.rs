@@ -0,0 +1,123 @@
+//! Quick notes: text-only notes jotted from the tray between meetings, without starting a
+//! recording. "Quick Note…" opens a small input window (the frontend with
+//! `?window=quick-note`); "Quick Note from Clipboard" saves the clipboard text right away.
+//! Either way the text becomes the note's description, so it is indexed for search like
+//! any other note, and the title carries the time it was taken.
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::commands::create_note;
+use crate::db::Database;
+use crate::db::models::{NewNote, Note};
+use crate::notifications;
+
+pub const LABEL: &str = "quick-note";
+
+const WIDTH: f64 = 420.0;
+const HEIGHT: f64 = 220.0;
+
+fn title() -> String {
+    format!(
+        "Quick Note {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M")
+    )
+}
+
+/// Trimmed text, or None when there is nothing worth saving
+fn note_text(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn save(app: &AppHandle, text: &str) -> Result<Note, String> {
+    let text = note_text(text).ok_or("The quick note is empty")?;
+    create_note(
+        app.clone(),
+        app.state::<Database>(),
+        NewNote {
+            title: title(),
+            description: Some(text),
+            participants: None,
+        },
+    )
+}
+
+/// Open the quick note window, or focus it if it is already open
+// async: creating a window from a sync command deadlocks on Windows
+#[tauri::command]
+pub async fn open_quick_note_window(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.show();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    WebviewWindowBuilder::new(
+        &app,
+        LABEL,
+        WebviewUrl::App("index.html?window=quick-note".into()),
+    )
+    .title("Quick Note")
+    .inner_size(WIDTH, HEIGHT)
+    .resizable(false)
+    .maximizable(false)
+    .minimizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .center()
+    .focused(true)
+    .build()
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Save the text from the quick note window and close it
+#[tauri::command]
+pub fn create_quick_note(app: AppHandle, text: String) -> Result<Note, String> {
+    let note = save(&app, &text)?;
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.destroy();
+    }
+    Ok(note)
+}
+
+/// Save the clipboard text as a quick note
+#[tauri::command]
+pub fn create_quick_note_from_clipboard(app: AppHandle) -> Result<Note, String> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|_| "The clipboard has no text".to_string())?;
+    save(&app, &text)
+}
+
+/// Tray: "Quick Note…"
+pub fn open_from_tray(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = open_quick_note_window(app).await {
+            tracing::warn!("Failed to open the quick note window: {}", e);
+        }
+    });
+}
+
+/// Tray: "Quick Note from Clipboard". There's no window to report to, so the outcome is a
+/// notification.
+pub fn save_clipboard_from_tray(app: &AppHandle) {
+    match create_quick_note_from_clipboard(app.clone()) {
+        Ok(note) => notifications::notify("Quick note saved", &note.title),
+        Err(e) => notifications::notify("Quick note not saved", &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_text() {
+        assert_eq!(note_text("  idea \n"), Some("idea".to_string()));
+        assert_eq!(note_text("a\nb"), Some("a\nb".to_string()));
+        assert_eq!(note_text(" \n\t"), None);
+    }
+}
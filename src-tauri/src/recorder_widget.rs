@@ -0,0 +1,162 @@
+//! Floating mini recorder: a small always-on-top window with the level meter, elapsed
+//! time, pause/stop buttons and live transcript snippets, for when the full app would be
+//! in the way (e.g. during a screen share). The window loads the same frontend with
+//! `?window=recorder-widget`; while it is open a ticker pushes "recorder-widget-state" to it,
+//! and transcript snippets come from the global "transcription-update" event.
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{self, AudioState};
+use crate::tray;
+use crate::window_state;
+
+pub const LABEL: &str = "recorder-widget";
+
+const WIDTH: f64 = 320.0;
+const HEIGHT: f64 = 140.0;
+/// Gap between the widget and the screen edges
+const MARGIN: f64 = 16.0;
+/// Often enough for a smooth level meter
+const TICK: Duration = Duration::from_millis(100);
+
+/// Payload of "recorder-widget-state"
+#[derive(Debug, Clone, Serialize)]
+pub struct RecorderWidgetState {
+    /// Same values as `get_recording_phase`
+    pub phase: u8,
+    pub level: f32,
+    pub elapsed_ms: u64,
+    pub note_id: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Payload of "recording-pause-toggled"
+#[derive(Debug, Clone, Serialize)]
+pub struct PauseToggledEvent {
+    pub note_id: Option<String>,
+    pub paused: bool,
+}
+
+/// Open the recorder widget, or focus it if it is already open
+// async: creating a window from a sync command deadlocks on Windows
+#[tauri::command]
+pub async fn open_recorder_widget(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.show();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    let mut builder = WebviewWindowBuilder::new(
+        &app,
+        LABEL,
+        WebviewUrl::App("index.html?window=recorder-widget".into()),
+    )
+    .title("Note67 Recorder")
+    .inner_size(WIDTH, HEIGHT)
+    .resizable(false)
+    .maximizable(false)
+    .minimizable(false)
+    .decorations(false)
+    .always_on_top(true)
+    .visible_on_all_workspaces(true)
+    .skip_taskbar(true)
+    // Keep the widget (and its transcript) out of screen shares and recordings
+    .content_protected(true);
+
+    // Top-right corner of the primary monitor
+    if let Ok(Some(monitor)) = app.primary_monitor() {
+        let scale = monitor.scale_factor();
+        let size = monitor.size().to_logical::<f64>(scale);
+        let origin = monitor.position().to_logical::<f64>(scale);
+        builder = builder.position(
+            origin.x + size.width - WIDTH - MARGIN,
+            origin.y + MARGIN * 2.0,
+        );
+    }
+
+    let window = builder.build().map_err(|e| e.to_string())?;
+    window_state::track(&window);
+    start_ticker(&app);
+    Ok(())
+}
+
+/// Close the recorder widget if it is open
+#[tauri::command]
+pub fn close_recorder_widget(app: AppHandle) -> Result<(), String> {
+    match app.get_webview_window(LABEL) {
+        Some(window) => window.destroy().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn is_recorder_widget_open(app: AppHandle) -> bool {
+    app.get_webview_window(LABEL).is_some()
+}
+
+/// The widget's pause button: pause or resume whatever is recording
+#[tauri::command]
+pub fn recorder_widget_toggle_pause(app: AppHandle) -> Result<(), String> {
+    let note_id = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    if let Some(paused) = commands::toggle_pause_active_recording(&app)? {
+        let _ = app.emit(
+            "recording-pause-toggled",
+            PauseToggledEvent { note_id, paused },
+        );
+    }
+    Ok(())
+}
+
+/// The widget's stop button: stop the recording the same way the tray does
+#[tauri::command]
+pub fn recorder_widget_stop(app: AppHandle) {
+    tray::stop_recording(&app);
+}
+
+/// Push the recording state to the widget until it is closed
+fn start_ticker(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        while app.get_webview_window(LABEL).is_some() {
+            let _ = app.emit_to(LABEL, "recorder-widget-state", current_state(&app));
+            thread::sleep(TICK);
+        }
+    });
+}
+
+fn current_state(app: &AppHandle) -> RecorderWidgetState {
+    let recording = &app.state::<AudioState>().recording;
+    let phase = recording.get_phase();
+    let indicator = tray::recording_indicator();
+    RecorderWidgetState {
+        phase: phase as u8,
+        level: match phase {
+            RecordingPhase::Recording => {
+                f32::from_bits(recording.audio_level.load(Ordering::SeqCst))
+            }
+            _ => 0.0,
+        },
+        elapsed_ms: indicator
+            .as_ref()
+            .map(|i| i.elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+        note_id: recording
+            .current_note_id
+            .lock()
+            .ok()
+            .and_then(|id| id.clone()),
+        title: indicator.map(|i| i.title),
+    }
+}
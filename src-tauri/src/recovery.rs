@@ -0,0 +1,436 @@
+//! Recovery after a crash or forced quit. On launch, each note's recordings and uploads
+//! folders are checked for what an interrupted session leaves behind: half-written upload
+//! conversions (`.tmp`), WAV files whose writer never finalized the header (so they read
+//! only up to its last update), mic/system pairs that were never merged for playback, and
+//! notes that have audio but never got an end time. FLAC recordings need no repair (they
+//! read up to the last whole frame), but an unfinished pair is merged all the same. What
+//! can be repaired is, and the outcome is reported to the UI as a `recovery-report` event
+//! and kept for `take_recovery_report`, since the event can fire before the frontend
+//! listens.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::encoder::RecordingFormat;
+use crate::audio::mixer::{self, MixLevels, MixMode};
+use crate::audio::{converter, flac, mix_wav_files};
+use crate::commands::AudioState;
+use crate::db::Database;
+use crate::storage::{self, NoteFolder};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// What startup recovery found and did
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RecoveryReport {
+    /// Partial upload conversions that were deleted
+    pub removed_temp_files: Vec<String>,
+    /// Recordings whose WAV header was rewritten to cover the audio on disk
+    pub repaired_recordings: Vec<String>,
+    /// Playback files created from a recovered mic/system pair
+    pub merged_recordings: Vec<String>,
+    /// Ids of notes that were given an end time
+    pub ended_notes: Vec<String>,
+    pub failed: Vec<RecoveryFailure>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.removed_temp_files.is_empty()
+            && self.repaired_recordings.is_empty()
+            && self.merged_recordings.is_empty()
+            && self.ended_notes.is_empty()
+            && self.failed.is_empty()
+    }
+
+    fn fail(&mut self, path: &Path, error: impl ToString) {
+        self.failed.push(RecoveryFailure {
+            path: path.to_string_lossy().to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+/// The last report with anything in it, until the frontend takes it
+#[derive(Default)]
+pub struct PendingRecoveryReport(pub Mutex<Option<RecoveryReport>>);
+
+/// Repair what the previous session left behind. Only notes created before `launched_at`
+/// are touched, so a recording started by this launch is never ended.
+pub fn run(app: &AppHandle, launched_at: DateTime<Utc>) {
+    let mut report = RecoveryReport::default();
+    let mut merged: HashMap<String, PathBuf> = HashMap::new();
+    let mix_mode = mixer::mix_mode(app);
+    let mix_levels = mixer::mix_levels(app);
+
+    for dir in storage::existing_folders(app, NoteFolder::Recordings)
+        .into_iter()
+        .chain(storage::existing_folders(app, NoteFolder::Uploads))
+    {
+        merged.extend(recover_files(
+            &dir,
+            launched_at,
+            mix_mode,
+            mix_levels,
+            &mut report,
+        ));
+    }
+
+    let db = app.state::<Database>();
+    let recording_note = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    match db.get_unended_recorded_notes(launched_at) {
+        Ok(notes) => {
+            for (note_id, started_at, audio_path) in notes {
+                if recording_note.as_deref() == Some(note_id.as_str()) {
+                    continue;
+                }
+                let merged_path = merged.get(&note_id).map(|p| p.to_string_lossy().to_string());
+                let ended_at = started_at + recorded_duration(&db, &note_id, audio_path.as_deref());
+                match db.mark_note_ended(&note_id, ended_at, merged_path.as_deref()) {
+                    Ok(()) => {
+                        let _ = app.emit("note-updated", &note_id);
+                        report.ended_notes.push(note_id);
+                    }
+                    Err(e) => tracing::warn!("Recovery: failed to end note {}: {}", note_id, e),
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Recovery: failed to list unfinished notes: {}", e),
+    }
+
+    if report.is_empty() {
+        return;
+    }
+    tracing::info!(
+        "Recovery: removed {} temp files, repaired {} recordings, merged {}, ended {} notes, {} failures",
+        report.removed_temp_files.len(),
+        report.repaired_recordings.len(),
+        report.merged_recordings.len(),
+        report.ended_notes.len(),
+        report.failed.len()
+    );
+    if let Some(pending) = app.try_state::<PendingRecoveryReport>()
+        && let Ok(mut pending) = pending.0.lock()
+    {
+        *pending = Some(report.clone());
+    }
+    let _ = app.emit("recovery-report", report);
+}
+
+/// The recovery report from this launch, if there was anything to recover. Cleared once taken.
+#[tauri::command]
+pub fn take_recovery_report(
+    pending: tauri::State<'_, PendingRecoveryReport>,
+) -> Result<Option<RecoveryReport>, String> {
+    let mut pending = pending.0.lock().map_err(|e| e.to_string())?;
+    Ok(pending.take())
+}
+
+/// Clean up temp files, repair WAV headers and merge interrupted pairs that have no
+/// playback file. Files written since `launched_at` belong to this session and are left alone.
+/// Returns the playback files created, by note id.
+fn recover_files(
+    dir: &Path,
+    launched_at: DateTime<Utc>,
+    mix_mode: MixMode,
+    mix_levels: MixLevels,
+    report: &mut RecoveryReport,
+) -> HashMap<String, PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+
+    // note id -> segment index (None for unsegmented) -> what was found of the pair
+    let mut pairs: HashMap<String, BTreeMap<Option<u32>, Pair>> = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+            continue;
+        };
+        let modified = entry.metadata().and_then(|m| m.modified());
+        if modified.is_ok_and(|t| DateTime::<Utc>::from(t) >= launched_at) {
+            continue;
+        }
+
+        if name.ends_with(".tmp") {
+            match fs::remove_file(&path) {
+                Ok(()) => report.removed_temp_files.push(path.to_string_lossy().to_string()),
+                Err(e) => report.fail(&path, e),
+            }
+            continue;
+        }
+        let Some(format) = RecordingFormat::of_path(&path) else {
+            continue;
+        };
+
+        let interrupted = match format {
+            RecordingFormat::Wav => match repair_wav_header(&path) {
+                Ok(repaired) => {
+                    if repaired {
+                        report.repaired_recordings.push(path.to_string_lossy().to_string());
+                    }
+                    repaired
+                }
+                Err(e) => {
+                    report.fail(&path, e);
+                    continue;
+                }
+            },
+            RecordingFormat::Flac => match flac::is_finalized(&path) {
+                Ok(finalized) => !finalized,
+                Err(e) => {
+                    report.fail(&path, e);
+                    continue;
+                }
+            },
+        };
+        if let Some((note_id, segment, track)) = parse_recording_name(&name) {
+            let entry = pairs
+                .entry(note_id)
+                .or_default()
+                .entry(segment)
+                .or_insert(Pair {
+                    mic: false,
+                    system: false,
+                    interrupted: false,
+                    format,
+                });
+            match track {
+                Track::Mic => entry.mic = true,
+                Track::System => entry.system = true,
+            }
+            entry.interrupted |= interrupted;
+        }
+    }
+
+    // Stopping a recording mixes its (latest) pair into <note id>.wav; do the same here
+    let mut merged = HashMap::new();
+    for (note_id, segments) in pairs {
+        let has_playback = [RecordingFormat::Wav, RecordingFormat::Flac]
+            .iter()
+            .any(|f| dir.join(format!("{}.{}", note_id, f.extension())).exists());
+        if has_playback {
+            continue;
+        }
+        let Some((&segment, pair)) = segments
+            .iter()
+            .rev()
+            .find(|(_, pair)| pair.mic && pair.system && pair.interrupted)
+        else {
+            continue;
+        };
+        let ext = pair.format.extension();
+        let playback = dir.join(format!("{}.{}", note_id, ext));
+        let suffix = segment.map(|n| format!("_seg{}", n)).unwrap_or_default();
+        let mic = dir.join(format!("{}_mic{}.{}", note_id, suffix, ext));
+        let system = dir.join(format!("{}_system{}.{}", note_id, suffix, ext));
+        let mixed =
+            mix_wav_files(&mic, &system, &playback, mix_mode, mix_levels, None, &mut |_| {});
+        match mixed {
+            Ok(()) => {
+                report.merged_recordings.push(playback.to_string_lossy().to_string());
+                merged.insert(note_id, playback);
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&playback);
+                report.fail(&playback, e);
+            }
+        }
+    }
+    merged
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Mic,
+    System,
+}
+
+/// The files of one recorded segment
+struct Pair {
+    mic: bool,
+    system: bool,
+    /// Either file was cut short
+    interrupted: bool,
+    format: RecordingFormat,
+}
+
+/// Split `<note id>_mic.wav` / `<note id>_system_seg3.flac` into (note id, segment, track)
+fn parse_recording_name(name: &str) -> Option<(String, Option<u32>, Track)> {
+    let stem = name
+        .strip_suffix(".wav")
+        .or_else(|| name.strip_suffix(".flac"))?;
+    let (stem, segment) = match stem.rsplit_once("_seg") {
+        Some((rest, n)) => (rest, Some(n.parse().ok()?)),
+        None => (stem, None),
+    };
+    if let Some(note_id) = stem.strip_suffix("_mic") {
+        Some((note_id.to_string(), segment, Track::Mic))
+    } else {
+        stem.strip_suffix("_system")
+            .map(|note_id| (note_id.to_string(), segment, Track::System))
+    }
+}
+
+/// How long a note recorded for. Segments the crash left without a duration get one from
+/// their (repaired) file first.
+fn recorded_duration(db: &Database, note_id: &str, audio_path: Option<&str>) -> Duration {
+    let segments = db.get_audio_segments(note_id).unwrap_or_default();
+    if segments.is_empty() {
+        return audio_path
+            .and_then(|p| converter::get_audio_duration_ms(Path::new(p)).ok())
+            .map(Duration::milliseconds)
+            .unwrap_or_else(Duration::zero);
+    }
+
+    let mut total_ms = 0;
+    for segment in segments {
+        let duration_ms = match segment.duration_ms {
+            Some(ms) => ms,
+            None => {
+                let ms = [segment.mic_path.as_deref(), segment.system_path.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|p| converter::get_audio_duration_ms(Path::new(p)).ok())
+                    .max()
+                    .unwrap_or(0);
+                let _ = db.update_segment_duration(segment.id, ms);
+                ms
+            }
+        };
+        total_ms += duration_ms;
+    }
+    Duration::milliseconds(total_ms)
+}
+
+/// Point the RIFF and data chunk sizes of a WAV at the audio actually on disk. Until a
+/// writer is finalized the sizes are only those of its last update, a few seconds back
+/// (or 0 in files from older versions), so a recording cut short by a crash looks short.
+/// Assumes the data chunk is the last one, as in every WAV the app writes.
+/// Returns false if the header was already complete.
+pub fn repair_wav_header(path: &Path) -> io::Result<bool> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.metadata()?.len();
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff)?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(invalid("not a WAV file"));
+    }
+
+    let mut block_align = 1u64;
+    let mut pos = 12u64;
+    loop {
+        if pos + 8 > len {
+            return Err(invalid("no data chunk"));
+        }
+        let mut chunk = [0u8; 8];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut chunk)?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+
+        if &chunk[0..4] == b"fmt " && size >= 16 {
+            let mut fmt = [0u8; 16];
+            file.read_exact(&mut fmt)?;
+            block_align = u16::from_le_bytes([fmt[12], fmt[13]]).max(1) as u64;
+        }
+
+        if &chunk[0..4] == b"data" {
+            // Whole frames only, and no more than a WAV can describe
+            let start = pos + 8;
+            let data_len = ((len - start) / block_align * block_align)
+                .min((u32::MAX as u64 - start) / block_align * block_align);
+            if size == data_len {
+                return Ok(false);
+            }
+            file.set_len(start + data_len)?;
+            file.seek(SeekFrom::Start(4))?;
+            file.write_all(&((start + data_len - 8) as u32).to_le_bytes())?;
+            file.seek(SeekFrom::Start(pos + 4))?;
+            file.write_all(&(data_len as u32).to_le_bytes())?;
+            file.sync_all()?;
+            return Ok(true);
+        }
+
+        pos += 8 + size + (size & 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recording_name() {
+        assert_eq!(
+            parse_recording_name("abc-1_mic.wav"),
+            Some(("abc-1".to_string(), None, Track::Mic))
+        );
+        assert_eq!(
+            parse_recording_name("abc-1_system_seg3.wav"),
+            Some(("abc-1".to_string(), Some(3), Track::System))
+        );
+        assert_eq!(
+            parse_recording_name("abc-1_mic_seg0.wav"),
+            Some(("abc-1".to_string(), Some(0), Track::Mic))
+        );
+        assert_eq!(
+            parse_recording_name("abc-1_system.flac"),
+            Some(("abc-1".to_string(), None, Track::System))
+        );
+        assert_eq!(parse_recording_name("abc-1.wav"), None);
+        assert_eq!(parse_recording_name("abc-1_seg2.wav"), None);
+        assert_eq!(parse_recording_name("abc-1_upload_1f2e.wav"), None);
+    }
+
+    #[test]
+    fn test_repair_wav_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("n_mic.wav");
+
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..3200 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        assert!(!repair_wav_header(&path).unwrap());
+
+        // What a crash leaves: placeholder sizes and a half-written last frame
+        let mut bytes = fs::read(&path).unwrap();
+        let data = bytes.windows(4).position(|w| w == b"data").unwrap();
+        bytes[4..8].copy_from_slice(&0u32.to_le_bytes());
+        bytes[data + 4..data + 8].copy_from_slice(&0u32.to_le_bytes());
+        bytes.push(7);
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(repair_wav_header(&path).unwrap());
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 1600);
+        assert_eq!(converter::get_audio_duration_ms(&path).ok(), Some(100));
+        assert!(!repair_wav_header(&path).unwrap());
+    }
+}
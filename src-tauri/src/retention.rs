@@ -0,0 +1,90 @@
+//! Deleting old audio to free space. With `SETTING_AUDIO_DAYS` set, a sweep every few hours
+//! purges the recordings and uploads of notes that ended more than that many days ago,
+//! keeping their transcripts, summaries and attachments. Notes without a transcript are
+//! left alone, so nothing is lost that wasn't written down. Emits "audio-retention-swept"
+//! when a sweep freed anything.
+
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::Database;
+use crate::storage;
+
+/// Days a note's audio is kept after it ends; 0 (the default) keeps it forever
+pub const SETTING_AUDIO_DAYS: &str = "audio_retention_days";
+
+/// Wait after startup before the first sweep
+const STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Payload of "audio-retention-swept"
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionSweep {
+    pub note_ids: Vec<String>,
+    pub bytes_freed: u64,
+}
+
+fn retention_days(app: &AppHandle) -> Option<i64> {
+    let days = app
+        .try_state::<Database>()?
+        .get_setting(SETTING_AUDIO_DAYS)
+        .ok()
+        .flatten()?
+        .trim()
+        .parse::<i64>()
+        .ok()?;
+    (days > 0).then_some(days)
+}
+
+/// Purge the audio of every note past the retention period
+pub fn sweep(app: &AppHandle) -> Result<RetentionSweep, String> {
+    let mut swept = RetentionSweep {
+        note_ids: Vec::new(),
+        bytes_freed: 0,
+    };
+    let Some(days) = retention_days(app) else {
+        return Ok(swept);
+    };
+    let before = Utc::now() - chrono::Duration::days(days);
+    let notes = app
+        .state::<Database>()
+        .get_notes_with_audio_ended_before(before)
+        .map_err(|e| e.to_string())?;
+    for note_id in notes {
+        match storage::purge_note_audio(app, &note_id) {
+            Ok(freed) => {
+                swept.bytes_freed += freed;
+                swept.note_ids.push(note_id);
+            }
+            Err(e) => tracing::warn!("Retention: kept audio of {}: {}", note_id, e),
+        }
+    }
+    Ok(swept)
+}
+
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(STARTUP_DELAY);
+        loop {
+            match sweep(&app) {
+                Ok(swept) if !swept.note_ids.is_empty() => {
+                    tracing::info!(
+                        "Retention: purged audio of {} notes, {} bytes freed",
+                        swept.note_ids.len(),
+                        swept.bytes_freed
+                    );
+                    let _ = app.emit("audio-retention-swept", &swept);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Retention sweep failed: {}", e),
+            }
+            thread::sleep(SWEEP_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,168 @@
+//! Secure storage for credentials (SMTP passwords, API tokens) in the OS keychain
+//! macOS: Keychain, Windows: Credential Manager, Linux: Secret Service via `secret-tool`
+
+/// Keychain service name all Note67 secrets are stored under
+const SERVICE: &str = "com.note67.app";
+
+#[cfg(target_os = "macos")]
+pub fn set_secret(key: &str, value: &str) -> Result<(), String> {
+    security_framework::passwords::set_generic_password(SERVICE, key, value.as_bytes())
+        .map_err(|e| format!("Failed to store secret in Keychain: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+pub fn get_secret(key: &str) -> Result<Option<String>, String> {
+    // errSecItemNotFound
+    const NOT_FOUND: i32 = -25300;
+
+    match security_framework::passwords::get_generic_password(SERVICE, key) {
+        Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).to_string())),
+        Err(e) if e.code() == NOT_FOUND => Ok(None),
+        Err(e) => Err(format!("Failed to read secret from Keychain: {}", e)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn delete_secret(key: &str) -> Result<(), String> {
+    const NOT_FOUND: i32 = -25300;
+
+    match security_framework::passwords::delete_generic_password(SERVICE, key) {
+        Ok(()) => Ok(()),
+        Err(e) if e.code() == NOT_FOUND => Ok(()),
+        Err(e) => Err(format!("Failed to delete secret from Keychain: {}", e)),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn target_name(key: &str) -> Vec<u16> {
+    format!("{}/{}", SERVICE, key)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+pub fn set_secret(key: &str, value: &str) -> Result<(), String> {
+    use windows_sys::Win32::Security::Credentials::{
+        CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    let mut target = target_name(key);
+    let mut blob = value.as_bytes().to_vec();
+    let credential = CREDENTIALW {
+        Flags: 0,
+        Type: CRED_TYPE_GENERIC,
+        TargetName: target.as_mut_ptr(),
+        Comment: std::ptr::null_mut(),
+        LastWritten: unsafe { std::mem::zeroed() },
+        CredentialBlobSize: blob.len() as u32,
+        CredentialBlob: blob.as_mut_ptr(),
+        Persist: CRED_PERSIST_LOCAL_MACHINE,
+        AttributeCount: 0,
+        Attributes: std::ptr::null_mut(),
+        TargetAlias: std::ptr::null_mut(),
+        UserName: std::ptr::null_mut(),
+    };
+
+    if unsafe { CredWriteW(&credential, 0) } == 0 {
+        return Err(format!(
+            "Failed to store secret in Credential Manager: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn get_secret(key: &str) -> Result<Option<String>, String> {
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND};
+    use windows_sys::Win32::Security::Credentials::{
+        CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC,
+    };
+
+    let target = target_name(key);
+    let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+    if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+        if unsafe { GetLastError() } == ERROR_NOT_FOUND {
+            return Ok(None);
+        }
+        return Err(format!(
+            "Failed to read secret from Credential Manager: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let value = unsafe {
+        let cred = &*credential;
+        let bytes =
+            std::slice::from_raw_parts(cred.CredentialBlob, cred.CredentialBlobSize as usize);
+        String::from_utf8_lossy(bytes).to_string()
+    };
+    unsafe { CredFree(credential as *const _) };
+    Ok(Some(value))
+}
+
+#[cfg(target_os = "windows")]
+pub fn delete_secret(key: &str) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND};
+    use windows_sys::Win32::Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC};
+
+    let target = target_name(key);
+    if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0
+        && unsafe { GetLastError() } != ERROR_NOT_FOUND
+    {
+        return Err(format!(
+            "Failed to delete secret from Credential Manager: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn set_secret(key: &str, value: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("secret-tool")
+        .args(["store", "--label", &format!("Note67 {}", key)])
+        .args(["service", SERVICE, "account", key])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run secret-tool (is libsecret installed?): {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(value.as_bytes())
+            .map_err(|e| format!("Failed to write secret: {}", e))?;
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err("Failed to store secret in the system keyring".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn get_secret(key: &str) -> Result<Option<String>, String> {
+    let output = std::process::Command::new("secret-tool")
+        .args(["lookup", "service", SERVICE, "account", key])
+        .output()
+        .map_err(|e| format!("Failed to run secret-tool (is libsecret installed?): {}", e))?;
+
+    // secret-tool exits non-zero when nothing matches
+    if !output.status.success() || output.stdout.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn delete_secret(key: &str) -> Result<(), String> {
+    std::process::Command::new("secret-tool")
+        .args(["clear", "service", SERVICE, "account", key])
+        .status()
+        .map_err(|e| format!("Failed to run secret-tool (is libsecret installed?): {}", e))?;
+    Ok(())
+}
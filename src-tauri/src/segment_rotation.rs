@@ -0,0 +1,77 @@
+//! Rolling long recordings over into a new segment every `SETTING_MINUTES` minutes, so a
+//! crash late in a long meeting only costs the segment being recorded, and finished
+//! segments are transcribed while the recording goes on. Capture never stops: the mic
+//! and system audio writers switch files in place, and each new segment is stored in
+//! `audio_segments` like one started by pause and resume. Emits "recording-segment-rotated".
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{self, AudioState};
+use crate::db::Database;
+
+/// Minutes per segment; 0 (the default) never rotates
+pub const SETTING_MINUTES: &str = "recording_segment_minutes";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wait after a failed rotation before trying again
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+fn segment_length(app: &AppHandle) -> Option<Duration> {
+    let minutes = app
+        .try_state::<Database>()?
+        .get_setting(SETTING_MINUTES)
+        .ok()
+        .flatten()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+}
+
+pub fn start_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut failed_at: Option<Instant> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let Some(state) = app.try_state::<AudioState>() else {
+                continue;
+            };
+            if state.recording.get_phase() != RecordingPhase::Recording
+                || failed_at.is_some_and(|at| at.elapsed() < RETRY_INTERVAL)
+            {
+                continue;
+            }
+            let Some(length) = segment_length(&app) else {
+                continue;
+            };
+            if state.recording.get_segment_elapsed_ms() < length.as_millis() as i64 {
+                continue;
+            }
+
+            match commands::rotate_active_segment(&app) {
+                Ok(Some(event)) => {
+                    failed_at = None;
+                    tracing::info!(
+                        "Recording of {} moved on to segment {}",
+                        event.note_id,
+                        event.segment_id
+                    );
+                    let _ = app.emit("recording-segment-rotated", &event);
+                    commands::queue_segment_transcription(&app, event.finished_segment_id);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    failed_at = Some(Instant::now());
+                    tracing::warn!("Failed to start a new recording segment: {}", e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,787 @@
+//! Typed registry of the settings stored in the `settings` table. Every known key is listed
+//! in `REGISTRY` with its type and default; writes go through `set`, which validates the
+//! value and emits "settings-changed" so every window can pick up the new value.
+//! Settings, shortcuts and export templates can be bundled into a JSON file to carry them to
+//! another machine. Only the preferences in `IMPORTABLE` travel: credentials, commands and
+//! destinations stay behind, so a shared file can't make the app run or send anything.
+//! Credentials are registered as sensitive settings: `set` stores them in the OS keychain
+//! (`secrets.rs`) rather than the table.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+
+use crate::audio::{
+    capture_format, compression, denoise, devices, encoder, exclusions, headphones, mixer,
+    monitoring,
+};
+use crate::auto_pause;
+use crate::backup;
+use crate::commands::{self, export, onboarding, transcription};
+use crate::crash;
+use crate::db::Database;
+use crate::focus_mode;
+use crate::integrations::{converter, email, eventkit, s3, slack};
+use crate::logging;
+use crate::mcp;
+use crate::meeting_detection;
+use crate::notifications;
+use crate::power;
+use crate::profiling;
+use crate::retention;
+use crate::secrets;
+use crate::segment_rotation;
+use crate::shortcuts;
+use crate::shutdown;
+use crate::transcription::threads;
+use crate::updates;
+
+pub const SETTING_THEME: &str = "theme";
+pub const SETTING_OLLAMA_MODEL: &str = "ollama_model";
+pub const SETTING_WHISPER_MODEL: &str = "whisper_model";
+pub const SETTING_WHISPER_LANGUAGE: &str = "whisper_language";
+pub const SETTING_USER_PROFILE: &str = "user_profile";
+pub const SETTING_ONBOARDING_DISMISSED: &str = "onboarding_dismissed";
+/// Start recording a new note when the app is launched at login
+pub const SETTING_RECORD_AT_LOGIN: &str = "record_at_login";
+
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("Unknown setting: {0}")]
+    UnknownKey(String),
+    #[error("Invalid value for {key}: {reason}")]
+    Invalid { key: String, reason: String },
+    #[error("Failed to save setting: {0}")]
+    Db(#[from] anyhow::Error),
+    #[error("{0}")]
+    Secret(String),
+    #[error("Not a Note67 settings file: {0}")]
+    InvalidBundle(String),
+    #[error("This settings file was made by a newer version of Note67")]
+    UnsupportedVersion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SettingKind {
+    Bool,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    String,
+    /// One of a fixed set of strings
+    Enum {
+        values: &'static [&'static str],
+    },
+    /// A JSON document (object or array)
+    Json,
+}
+
+/// One known setting
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SettingDef {
+    pub key: &'static str,
+    #[serde(flatten)]
+    pub kind: SettingKind,
+    /// Stored form of the default; None when unset means "not configured"
+    pub default: Option<&'static str>,
+    /// Credentials: writable, but kept in the OS keychain instead of the table and never
+    /// included in snapshots or change events
+    pub sensitive: bool,
+}
+
+const fn def(key: &'static str, kind: SettingKind, default: Option<&'static str>) -> SettingDef {
+    SettingDef {
+        key,
+        kind,
+        default,
+        sensitive: false,
+    }
+}
+
+const fn sensitive(key: &'static str) -> SettingDef {
+    SettingDef {
+        key,
+        kind: SettingKind::String,
+        default: None,
+        sensitive: true,
+    }
+}
+
+const BOOL: SettingKind = SettingKind::Bool;
+const STRING: SettingKind = SettingKind::String;
+const JSON: SettingKind = SettingKind::Json;
+
+pub static REGISTRY: &[SettingDef] = &[
+    // General
+    def(
+        SETTING_THEME,
+        SettingKind::Enum {
+            values: &["light", "dark", "system"],
+        },
+        Some("system"),
+    ),
+    def(SETTING_USER_PROFILE, JSON, None),
+    def(SETTING_ONBOARDING_DISMISSED, BOOL, Some("false")),
+    def(
+        onboarding::SETTING_STEP,
+        SettingKind::Enum {
+            values: onboarding::STEP_NAMES,
+        },
+        Some("welcome"),
+    ),
+    def(SETTING_RECORD_AT_LOGIN, BOOL, Some("false")),
+    def(notifications::SETTING_ENABLED, BOOL, Some("true")),
+    def(crash::SETTING_ENABLED, BOOL, Some("false")),
+    def(
+        logging::SETTING_LEVEL,
+        SettingKind::Enum {
+            values: logging::LEVELS,
+        },
+        Some("info"),
+    ),
+    def(profiling::SETTING_ENABLED, BOOL, Some("false")),
+    // Recording
+    def(devices::SETTING_INPUT_DEVICE, STRING, None),
+    def(devices::SETTING_LOOPBACK_DEVICE, STRING, None),
+    def(
+        encoder::SETTING_FORMAT,
+        SettingKind::Enum {
+            values: encoder::FORMATS,
+        },
+        Some("wav"),
+    ),
+    def(
+        capture_format::SETTING_PRESET,
+        SettingKind::Enum {
+            values: capture_format::PRESETS,
+        },
+        Some("device"),
+    ),
+    def(mixer::SETTING_STEREO_SPLIT, BOOL, Some("false")),
+    def(mixer::SETTING_NORMALIZE, BOOL, Some("true")),
+    def(
+        mixer::SETTING_MIC_GAIN,
+        SettingKind::Integer { min: -24, max: 24 },
+        Some("0"),
+    ),
+    def(
+        mixer::SETTING_SYSTEM_GAIN,
+        SettingKind::Integer { min: -24, max: 24 },
+        Some("0"),
+    ),
+    def(compression::SETTING_ENABLED, BOOL, Some("false")),
+    def(denoise::SETTING_ENABLED, BOOL, Some("false")),
+    def(monitoring::SETTING_ENABLED, BOOL, Some("false")),
+    def(commands::audio::SETTING_LISTEN_ONLY, BOOL, Some("false")),
+    def(headphones::SETTING_ENABLED, BOOL, Some("true")),
+    def(auto_pause::SETTING_ENABLED, BOOL, Some("true")),
+    def(
+        auto_pause::SETTING_ON_RETURN,
+        SettingKind::Enum {
+            values: auto_pause::ON_RETURN,
+        },
+        Some("prompt"),
+    ),
+    def(
+        segment_rotation::SETTING_MINUTES,
+        SettingKind::Integer { min: 0, max: 1440 },
+        Some("0"),
+    ),
+    def(
+        retention::SETTING_AUDIO_DAYS,
+        SettingKind::Integer { min: 0, max: 3650 },
+        Some("0"),
+    ),
+    def(exclusions::SETTING_EXCLUDED_APPS, JSON, Some("[]")),
+    def(exclusions::SETTING_CAPTURED_APP, STRING, None),
+    def(power::SETTING_ENABLED, BOOL, Some("false")),
+    def(shutdown::SETTING_WAIT_FOR_TRANSCRIPTION, BOOL, Some("true")),
+    def(focus_mode::SETTING_ENABLED, BOOL, Some("false")),
+    def(
+        focus_mode::SETTING_SHORTCUT_ON,
+        STRING,
+        Some(focus_mode::DEFAULT_SHORTCUT_ON),
+    ),
+    def(
+        focus_mode::SETTING_SHORTCUT_OFF,
+        STRING,
+        Some(focus_mode::DEFAULT_SHORTCUT_OFF),
+    ),
+    // Models
+    def(SETTING_OLLAMA_MODEL, STRING, None),
+    def(SETTING_WHISPER_MODEL, STRING, None),
+    def(SETTING_WHISPER_LANGUAGE, STRING, Some("auto")),
+    def(
+        threads::SETTING_MAX_THREADS,
+        SettingKind::Integer { min: 0, max: 256 },
+        Some("0"),
+    ),
+    def(
+        threads::SETTING_PRIORITY,
+        SettingKind::Enum {
+            values: threads::PRIORITIES,
+        },
+        Some("normal"),
+    ),
+    def(transcription::SETTING_TRANSCRIBE_WHILE_RECORDING, BOOL, Some("true")),
+    // Meetings and calendar
+    def(meeting_detection::SETTING_AUTO_START, BOOL, Some("false")),
+    def(eventkit::SETTING_REMINDERS_ENABLED, BOOL, Some("true")),
+    // Export
+    def(export::SETTING_AUTO_EXPORT_DIR, STRING, None),
+    def(
+        export::SETTING_AUTO_EXPORT_FORMAT,
+        SettingKind::Enum {
+            values: &["markdown", "pdf"],
+        },
+        Some("markdown"),
+    ),
+    def(
+        export::SETTING_AUTO_EXPORT_TEMPLATE,
+        STRING,
+        Some(export::DEFAULT_FILENAME_TEMPLATE),
+    ),
+    def(converter::SETTING_PRESETS, JSON, None),
+    // Slack
+    sensitive(slack::SECRET_WEBHOOK_URL),
+    sensitive(slack::SECRET_BOT_TOKEN),
+    def(slack::SETTING_DEFAULT_CHANNEL, STRING, None),
+    def(slack::SETTING_AUTO_POST, BOOL, Some("false")),
+    // Email
+    def(email::SETTING_HOST, STRING, None),
+    def(
+        email::SETTING_PORT,
+        SettingKind::Integer { min: 1, max: 65535 },
+        Some("587"),
+    ),
+    def(email::SETTING_USERNAME, STRING, None),
+    def(email::SETTING_FROM, STRING, None),
+    def(
+        email::SETTING_SECURITY,
+        SettingKind::Enum {
+            values: &["tls", "starttls", "none"],
+        },
+        Some("starttls"),
+    ),
+    // S3 backup
+    def(s3::SETTING_ENDPOINT, STRING, None),
+    def(s3::SETTING_REGION, STRING, None),
+    def(s3::SETTING_BUCKET, STRING, None),
+    def(s3::SETTING_PREFIX, STRING, None),
+    def(s3::SETTING_ACCESS_KEY_ID, STRING, None),
+    def(s3::SETTING_PATH_STYLE, BOOL, Some("false")),
+    def(
+        backup::SETTING_INTERVAL_HOURS,
+        SettingKind::Integer {
+            min: 0,
+            max: 24 * 365,
+        },
+        Some("0"),
+    ),
+    // Updates
+    def(
+        updates::SETTING_CHANNEL,
+        SettingKind::Enum {
+            values: updates::CHANNELS,
+        },
+        Some("stable"),
+    ),
+    def(updates::SETTING_AUTO_DOWNLOAD, BOOL, Some("false")),
+    def(updates::SETTING_SKIPPED_VERSION, STRING, None),
+    def(
+        updates::SETTING_CHECK_INTERVAL_HOURS,
+        SettingKind::Integer {
+            min: 0,
+            max: 24 * 30,
+        },
+        Some("24"),
+    ),
+    // Integrations
+    def(mcp::SETTING_ENABLED, BOOL, Some("false")),
+];
+
+/// Settings a bundle carries. Anything that names a command (converter presets, Focus
+/// shortcuts), a destination (export directory, hosts, buckets, channels) or turns on
+/// something that sends, records or deletes data is left out and has to be set here.
+const IMPORTABLE: &[&str] = &[
+    SETTING_THEME,
+    notifications::SETTING_ENABLED,
+    logging::SETTING_LEVEL,
+    encoder::SETTING_FORMAT,
+    capture_format::SETTING_PRESET,
+    mixer::SETTING_STEREO_SPLIT,
+    mixer::SETTING_NORMALIZE,
+    mixer::SETTING_MIC_GAIN,
+    mixer::SETTING_SYSTEM_GAIN,
+    compression::SETTING_ENABLED,
+    denoise::SETTING_ENABLED,
+    monitoring::SETTING_ENABLED,
+    headphones::SETTING_ENABLED,
+    auto_pause::SETTING_ENABLED,
+    auto_pause::SETTING_ON_RETURN,
+    segment_rotation::SETTING_MINUTES,
+    SETTING_WHISPER_LANGUAGE,
+    threads::SETTING_MAX_THREADS,
+    threads::SETTING_PRIORITY,
+    transcription::SETTING_TRANSCRIBE_WHILE_RECORDING,
+    eventkit::SETTING_REMINDERS_ENABLED,
+    export::SETTING_AUTO_EXPORT_FORMAT,
+];
+
+/// Payload of "settings-changed"
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChanged {
+    pub key: String,
+    /// The typed value (None for sensitive settings)
+    pub value: Option<Value>,
+}
+
+pub fn find(key: &str) -> Option<&'static SettingDef> {
+    REGISTRY.iter().find(|def| def.key == key)
+}
+
+/// Check `value` against the setting's type, returning the form to store
+pub fn validate(key: &str, value: &str) -> Result<String, SettingsError> {
+    let def = find(key).ok_or_else(|| SettingsError::UnknownKey(key.to_string()))?;
+    let invalid = |reason: String| SettingsError::Invalid {
+        key: key.to_string(),
+        reason,
+    };
+
+    match def.kind {
+        SettingKind::Bool => match value.trim() {
+            "true" | "false" => Ok(value.trim().to_string()),
+            other => Err(invalid(format!(
+                "expected true or false, got \"{}\"",
+                other
+            ))),
+        },
+        SettingKind::Integer { min, max } => {
+            let number: i64 = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("expected a whole number, got \"{}\"", value)))?;
+            if !(min..=max).contains(&number) {
+                return Err(invalid(format!("must be between {} and {}", min, max)));
+            }
+            Ok(number.to_string())
+        }
+        SettingKind::String => Ok(value.to_string()),
+        SettingKind::Enum { values } => {
+            if values.contains(&value) {
+                Ok(value.to_string())
+            } else {
+                Err(invalid(format!("expected one of {}", values.join(", "))))
+            }
+        }
+        SettingKind::Json => {
+            serde_json::from_str::<Value>(value)
+                .map_err(|e| invalid(format!("not valid JSON: {}", e)))?;
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// The typed form of a stored value. Values that no longer parse are returned as strings.
+pub fn typed_value(def: &SettingDef, stored: &str) -> Value {
+    match def.kind {
+        SettingKind::Bool => Value::Bool(stored == "true"),
+        SettingKind::Integer { .. } => stored
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(stored.to_string())),
+        SettingKind::Json => {
+            serde_json::from_str(stored).unwrap_or_else(|_| Value::String(stored.to_string()))
+        }
+        SettingKind::String | SettingKind::Enum { .. } => Value::String(stored.to_string()),
+    }
+}
+
+/// Validate and save a setting, then tell every window about it
+pub fn set(app: &AppHandle, key: &str, value: &str) -> Result<(), SettingsError> {
+    let value = validate(key, value)?;
+    let def = find(key).expect("validated above");
+    if def.sensitive {
+        store_secret(key, &value)?;
+    } else {
+        app.state::<Database>().set_setting(key, &value)?;
+    }
+
+    let _ = app.emit(
+        "settings-changed",
+        SettingsChanged {
+            key: key.to_string(),
+            value: (!def.sensitive).then(|| typed_value(def, &value)),
+        },
+    );
+    Ok(())
+}
+
+/// Store a credential in the keychain; an empty value removes it
+fn store_secret(key: &str, value: &str) -> Result<(), SettingsError> {
+    match value.trim() {
+        "" => secrets::delete_secret(key),
+        value => secrets::set_secret(key, value),
+    }
+    .map_err(SettingsError::Secret)
+}
+
+/// Move credentials saved in the table by earlier versions into the keychain
+pub fn migrate_credentials(db: &Database) -> Result<(), SettingsError> {
+    for def in REGISTRY.iter().filter(|def| def.sensitive) {
+        if let Some(value) = db.get_setting(def.key)? {
+            store_secret(def.key, &value)?;
+            db.delete_setting(def.key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Every non-sensitive setting with its typed value, falling back to the default
+/// (null when there is none)
+pub fn snapshot(db: &Database) -> Result<HashMap<String, Value>, SettingsError> {
+    let mut settings = HashMap::new();
+    for def in REGISTRY.iter().filter(|def| !def.sensitive) {
+        let value = db
+            .get_setting(def.key)?
+            .or(def.default.map(str::to_string))
+            .map(|stored| typed_value(def, &stored))
+            .unwrap_or(Value::Null);
+        settings.insert(def.key.to_string(), value);
+    }
+    Ok(settings)
+}
+
+// ========== Export / import ==========
+
+/// Bumped when the bundle layout changes
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Everything `export_settings` writes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Stored values of the `IMPORTABLE` settings that are set
+    pub settings: BTreeMap<String, String>,
+    #[serde(default)]
+    pub export_templates: Vec<BundledTemplate>,
+    /// Shortcut overrides, id -> accelerator (empty when disabled)
+    #[serde(default)]
+    pub shortcuts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledTemplate {
+    pub name: String,
+    pub content: String,
+}
+
+/// What to do when an imported value differs from the one on this machine
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Overwrite,
+    KeepExisting,
+}
+
+/// A value that exists on both sides with different contents
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingsConflict {
+    /// "setting", "shortcut" or "export_template"
+    pub kind: &'static str,
+    /// Setting key, shortcut id or template name
+    pub name: String,
+    pub current: String,
+    pub incoming: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SettingsImportReport {
+    /// Settings keys, shortcut ids and template names written
+    pub applied: Vec<String>,
+    /// Conflicts left at their current value
+    pub kept: Vec<String>,
+    /// Entries that were ignored, with the reason (unknown key, invalid value, ...)
+    pub skipped: Vec<String>,
+}
+
+pub fn export_bundle(db: &Database) -> Result<SettingsBundle, SettingsError> {
+    let mut settings = BTreeMap::new();
+    for key in IMPORTABLE {
+        if let Some(value) = db.get_setting(key)? {
+            settings.insert(key.to_string(), value);
+        }
+    }
+    let export_templates = db
+        .list_export_templates()?
+        .into_iter()
+        .map(|t| BundledTemplate {
+            name: t.name,
+            content: t.content,
+        })
+        .collect();
+    let shortcuts = db.get_shortcut_bindings()?.into_iter().collect();
+
+    Ok(SettingsBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        settings,
+        export_templates,
+        shortcuts,
+    })
+}
+
+pub fn parse_bundle(json: &str) -> Result<SettingsBundle, SettingsError> {
+    let bundle: SettingsBundle =
+        serde_json::from_str(json).map_err(|e| SettingsError::InvalidBundle(e.to_string()))?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(SettingsError::UnsupportedVersion);
+    }
+    Ok(bundle)
+}
+
+/// Entries of `bundle` that would change a value already set on this machine
+pub fn find_conflicts(
+    db: &Database,
+    bundle: &SettingsBundle,
+) -> Result<Vec<SettingsConflict>, SettingsError> {
+    let mut current = HashMap::new();
+    for key in bundle.settings.keys().filter(|key| IMPORTABLE.contains(&key.as_str())) {
+        if let Some(value) = db.get_setting(key)? {
+            current.insert(key.clone(), value);
+        }
+    }
+    let templates: HashMap<String, String> = db
+        .list_export_templates()?
+        .into_iter()
+        .map(|t| (t.name, t.content))
+        .collect();
+    let shortcuts = db.get_shortcut_bindings()?;
+    Ok(conflicts(&current, &shortcuts, &templates, bundle))
+}
+
+fn conflicts(
+    settings: &HashMap<String, String>,
+    shortcuts: &HashMap<String, String>,
+    templates: &HashMap<String, String>,
+    bundle: &SettingsBundle,
+) -> Vec<SettingsConflict> {
+    let changed = |kind: &'static str,
+                   current: &HashMap<String, String>,
+                   entries: &BTreeMap<String, String>| {
+        entries
+            .iter()
+            .filter_map(|(name, incoming)| {
+                let current = current.get(name)?;
+                (current != incoming).then(|| SettingsConflict {
+                    kind,
+                    name: name.clone(),
+                    current: current.clone(),
+                    incoming: incoming.clone(),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let setting_conflicts = changed("setting", settings, &bundle.settings);
+    let shortcut_conflicts = changed("shortcut", shortcuts, &bundle.shortcuts);
+    let template_conflicts = bundle.export_templates.iter().filter_map(|template| {
+        let current = templates.get(&template.name)?;
+        (*current != template.content).then(|| SettingsConflict {
+            kind: "export_template",
+            name: template.name.clone(),
+            current: current.clone(),
+            incoming: template.content.clone(),
+        })
+    });
+    setting_conflicts
+        .into_iter()
+        .chain(shortcut_conflicts)
+        .chain(template_conflicts)
+        .collect()
+}
+
+/// Apply `bundle`; values already set here and different are resolved by `policy`
+pub fn import_bundle(
+    app: &AppHandle,
+    bundle: &SettingsBundle,
+    policy: ConflictPolicy,
+) -> Result<SettingsImportReport, SettingsError> {
+    let db = app.state::<Database>();
+    let mut report = SettingsImportReport::default();
+
+    for (key, incoming) in &bundle.settings {
+        match find(key) {
+            None => {
+                report.skipped.push(format!("{}: unknown setting", key));
+                continue;
+            }
+            Some(def) if def.sensitive => {
+                report
+                    .skipped
+                    .push(format!("{}: credentials are not imported", key));
+                continue;
+            }
+            Some(_) if !IMPORTABLE.contains(&key.as_str()) => {
+                report
+                    .skipped
+                    .push(format!("{}: not imported, set it on this machine", key));
+                continue;
+            }
+            Some(_) => {}
+        }
+        match db.get_setting(key)? {
+            Some(current) if current == *incoming => continue,
+            Some(_) if policy == ConflictPolicy::KeepExisting => {
+                report.kept.push(key.clone());
+                continue;
+            }
+            _ => {}
+        }
+        match set(app, key, incoming) {
+            Ok(()) => report.applied.push(key.clone()),
+            Err(SettingsError::Invalid { reason, .. }) => {
+                report.skipped.push(format!("{}: {}", key, reason))
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let existing = db.get_shortcut_bindings()?;
+    for (id, incoming) in &bundle.shortcuts {
+        let Some(def) = shortcuts::find(id) else {
+            report.skipped.push(format!("{}: unknown shortcut", id));
+            continue;
+        };
+        if !incoming.is_empty()
+            && let Err(e) = shortcuts::normalize(incoming, def.scope)
+        {
+            report.skipped.push(format!("{}: {}", id, e));
+            continue;
+        }
+        match existing.get(id) {
+            Some(current) if current == incoming => continue,
+            Some(_) if policy == ConflictPolicy::KeepExisting => {
+                report.kept.push(id.clone());
+                continue;
+            }
+            _ => {}
+        }
+        db.set_shortcut_binding(id, incoming)?;
+        report.applied.push(id.clone());
+    }
+
+    let existing = db.list_export_templates()?;
+    for template in &bundle.export_templates {
+        if template.name.trim().is_empty() {
+            report
+                .skipped
+                .push("Export template without a name".to_string());
+            continue;
+        }
+        if let Err(e) = crate::templates::validate(&template.content) {
+            report
+                .skipped
+                .push(format!("Export template \"{}\": {}", template.name, e));
+            continue;
+        }
+        match existing.iter().find(|t| t.name == template.name) {
+            Some(current) if current.content == template.content => {}
+            Some(_) if policy == ConflictPolicy::KeepExisting => {
+                report.kept.push(template.name.clone());
+            }
+            Some(current) => {
+                db.update_export_template(current.id, &template.name, &template.content)?;
+                report.applied.push(template.name.clone());
+            }
+            None => {
+                db.create_export_template(&template.name, &template.content)?;
+                report.applied.push(template.name.clone());
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(SETTING_THEME, "dark").unwrap(), "dark");
+        assert!(validate(SETTING_THEME, "blue").is_err());
+        assert_eq!(validate(email::SETTING_PORT, " 465 ").unwrap(), "465");
+        assert!(validate(email::SETTING_PORT, "0").is_err());
+        assert!(validate(mcp::SETTING_ENABLED, "yes").is_err());
+        assert!(validate(SETTING_USER_PROFILE, "{\"name\":").is_err());
+        assert!(matches!(
+            validate("no_such_setting", "x"),
+            Err(SettingsError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_registry_keys_are_unique() {
+        let mut keys: Vec<_> = REGISTRY.iter().map(|def| def.key).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), REGISTRY.len());
+    }
+
+    #[test]
+    fn test_importable() {
+        for key in IMPORTABLE {
+            assert!(find(key).is_some_and(|def| !def.sensitive), "{}", key);
+        }
+        for key in [
+            converter::SETTING_PRESETS,
+            export::SETTING_AUTO_EXPORT_DIR,
+            s3::SETTING_ENDPOINT,
+            email::SETTING_HOST,
+            updates::SETTING_CHANNEL,
+            mcp::SETTING_ENABLED,
+            focus_mode::SETTING_SHORTCUT_ON,
+        ] {
+            assert!(!IMPORTABLE.contains(&key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let bundle = parse_bundle(
+            r#"{
+                "version": 1,
+                "exported_at": "2026-01-05T10:00:00Z",
+                "settings": {"theme": "dark", "mcp_enabled": "true", "ollama_model": "llama3"},
+                "export_templates": [{"name": "Brief", "content": "{{title}}"}]
+            }"#,
+        )
+        .unwrap();
+
+        let settings = HashMap::from([
+            ("theme".to_string(), "light".to_string()),
+            ("mcp_enabled".to_string(), "true".to_string()),
+        ]);
+        let templates = HashMap::from([("Brief".to_string(), "# {{title}}".to_string())]);
+        let found = conflicts(&settings, &HashMap::new(), &templates, &bundle);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].name, "theme");
+        assert_eq!(found[0].incoming, "dark");
+        assert_eq!(found[1].kind, "export_template");
+
+        assert!(matches!(
+            parse_bundle(
+                r#"{"version": 99, "exported_at": "2026-01-05T10:00:00Z", "settings": {}}"#
+            ),
+            Err(SettingsError::UnsupportedVersion)
+        ));
+    }
+}
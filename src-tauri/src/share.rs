@@ -0,0 +1,173 @@
+//! Hand a file to the OS share UI
+//! macOS: the NSSharingServicePicker share sheet (Messages, Mail, AirDrop, ...)
+//! Windows: the Share dialog, through DataTransferManager
+//! Linux: desktops offer no share sheet, so the file's folder is opened instead
+//!
+//! Shared files are written under `share_dir()` and stay there while the receiving app may
+//! still read them; `clear_shared_files` removes them on the next start.
+
+use std::path::{Path, PathBuf};
+
+use tauri::AppHandle;
+
+/// Where exports are written to be shared
+pub fn share_dir() -> PathBuf {
+    std::env::temp_dir().join("note67-share")
+}
+
+/// Remove the files shared in earlier sessions
+pub fn clear_shared_files() {
+    let dir = share_dir();
+    if dir.exists()
+        && let Err(e) = std::fs::remove_dir_all(&dir)
+    {
+        tracing::warn!("Failed to remove shared files in {}: {}", dir.display(), e);
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn share_file(app: &AppHandle, path: &Path) -> Result<(), String> {
+    use tauri::Manager;
+
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    // Raw pointers aren't Send; AppKit objects are only touched on the main thread
+    let ns_window = window.ns_window().map_err(|e| e.to_string())? as usize;
+    let path = path.to_string_lossy().to_string();
+
+    app.run_on_main_thread(move || unsafe { macos::show_picker(ns_window, &path) })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyClass, AnyObject};
+    use objc2_foundation::{NSRect, NSString};
+
+    /// NSRectEdgeMinY
+    const EDGE_MIN_Y: usize = 1;
+
+    /// # Safety
+    /// Must run on the main thread with `ns_window` pointing at a live NSWindow.
+    pub unsafe fn show_picker(ns_window: usize, path: &str) {
+        let (Some(url_class), Some(array_class), Some(picker_class)) = (
+            AnyClass::get(c"NSURL"),
+            AnyClass::get(c"NSArray"),
+            AnyClass::get(c"NSSharingServicePicker"),
+        ) else {
+            tracing::warn!("NSSharingServicePicker is not available");
+            return;
+        };
+
+        unsafe {
+            let ns_window = ns_window as *mut AnyObject;
+            let view: *mut AnyObject = msg_send![ns_window, contentView];
+            if view.is_null() {
+                return;
+            }
+
+            let path = NSString::from_str(path);
+            let url: *mut AnyObject = msg_send![url_class, fileURLWithPath: &*path];
+            let items: *mut AnyObject = msg_send![array_class, arrayWithObject: url];
+
+            // Deliberately not released: the picker has to outlive this call while it is
+            // on screen, and it is small
+            let picker: *mut AnyObject = msg_send![picker_class, alloc];
+            let picker: *mut AnyObject = msg_send![picker, initWithItems: items];
+            if picker.is_null() {
+                return;
+            }
+
+            let bounds: NSRect = msg_send![view, bounds];
+            let _: () = msg_send![
+                picker,
+                showRelativeToRect: bounds,
+                ofView: view,
+                preferredEdge: EDGE_MIN_Y
+            ];
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn share_file(app: &AppHandle, path: &Path) -> Result<(), String> {
+    use tauri::Manager;
+
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    // Raw pointers aren't Send; the Share dialog belongs to the window's own thread
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?.0 as usize;
+    let path = path.to_path_buf();
+
+    app.run_on_main_thread(move || {
+        if let Err(e) = unsafe { winrt::show_share_ui(hwnd, &path) } {
+            tracing::warn!("Failed to open the Share dialog: {}", e);
+        }
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "windows")]
+mod winrt {
+    use std::path::Path;
+    use std::sync::{Mutex, PoisonError};
+
+    use windows::core::{factory, Interface, HSTRING};
+    use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+    use windows::Foundation::Collections::IIterable;
+    use windows::Foundation::{EventRegistrationToken, TypedEventHandler};
+    use windows::Storage::{IStorageItem, StorageFile};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+
+    /// The window's handler for the last share, replaced so only the newest file is offered
+    static HANDLER: Mutex<Option<EventRegistrationToken>> = Mutex::new(None);
+
+    /// # Safety
+    /// Must run on the thread that owns `hwnd`, a live top-level window.
+    pub unsafe fn show_share_ui(hwnd: usize, path: &Path) -> windows::core::Result<()> {
+        let hwnd = HWND(hwnd as *mut _);
+        let interop = factory::<DataTransferManager, IDataTransferManagerInterop>()?;
+        let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd)? };
+
+        let title = HSTRING::from(
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        );
+        let path = HSTRING::from(path.as_os_str());
+        let handler = TypedEventHandler::new(
+            move |_: &Option<DataTransferManager>, args: &Option<DataRequestedEventArgs>| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let data = args.Request()?.Data()?;
+                data.Properties()?.SetTitle(&title)?;
+                // A local file opens at once, so this holds the window's thread only briefly
+                let file: IStorageItem = StorageFile::GetFileFromPathAsync(&path)?.get()?.cast()?;
+                let items = IIterable::<IStorageItem>::try_from(vec![Some(file)])?;
+                data.SetStorageItemsReadOnly(&items)
+            },
+        );
+
+        let mut token = HANDLER.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(previous) = token.take() {
+            let _ = manager.RemoveDataRequested(previous);
+        }
+        *token = Some(manager.DataRequested(&handler)?);
+        unsafe { interop.ShowShareUIForWindow(hwnd) }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn share_file(_app: &AppHandle, path: &Path) -> Result<(), String> {
+    let dir = path.parent().unwrap_or(path);
+    std::process::Command::new("xdg-open")
+        .arg(dir)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open the file manager: {}", e))
+}
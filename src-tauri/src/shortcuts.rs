@@ -0,0 +1,411 @@
+//! Customizable keyboard shortcuts for the app menu (macOS), the tray menu and the global
+//! hotkeys. Defaults live in `SHORTCUTS`; overrides are stored in the `shortcut_bindings`
+//! table, and changing one rebuilds the affected menu or re-registers the hotkeys.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+
+use crate::app_menu;
+use crate::db::Database;
+use crate::hotkeys::{self, Accelerator};
+use crate::tray;
+
+use ShortcutScope::{Global, Menu, Tray};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutScope {
+    /// The application menu (macOS only)
+    Menu,
+    /// The tray menu
+    Tray,
+    /// System-wide hotkeys, active while the app is in the background
+    Global,
+}
+
+pub struct ShortcutDef {
+    pub id: &'static str,
+    pub scope: ShortcutScope,
+    pub label: &'static str,
+    pub default: Option<&'static str>,
+}
+
+const fn shortcut(
+    id: &'static str,
+    scope: ShortcutScope,
+    label: &'static str,
+    default: Option<&'static str>,
+) -> ShortcutDef {
+    ShortcutDef {
+        id,
+        scope,
+        label,
+        default,
+    }
+}
+
+pub static SHORTCUTS: &[ShortcutDef] = &[
+    shortcut("menu.hide_window", Menu, "Hide Window", Some("CmdOrCtrl+Q")),
+    shortcut(
+        "menu.quit_app",
+        Menu,
+        "Quit Note67",
+        Some("CmdOrCtrl+Shift+Q"),
+    ),
+    shortcut("tray.open", Tray, "Open", Some("CmdOrCtrl+O")),
+    shortcut("tray.new_note", Tray, "New Note", Some("CmdOrCtrl+N")),
+    shortcut("tray.quick_note", Tray, "Quick Note", None),
+    shortcut("tray.settings", Tray, "Settings", Some("CmdOrCtrl+,")),
+    shortcut("tray.toggle_recording", Tray, "Start/Stop Recording", None),
+    shortcut(
+        "global.start_recording",
+        Global,
+        "Start Recording",
+        Some("CmdOrCtrl+Alt+R"),
+    ),
+    shortcut(
+        "global.stop_recording",
+        Global,
+        "Stop Recording",
+        Some("CmdOrCtrl+Alt+S"),
+    ),
+    shortcut(
+        "global.toggle_pause",
+        Global,
+        "Pause/Resume Recording",
+        Some("CmdOrCtrl+Alt+P"),
+    ),
+    shortcut(
+        "global.new_note_and_record",
+        Global,
+        "New Note and Record",
+        Some("CmdOrCtrl+Alt+N"),
+    ),
+    shortcut(
+        "global.add_marker",
+        Global,
+        "Add Marker",
+        Some("CmdOrCtrl+Alt+M"),
+    ),
+];
+
+/// A shortcut as shown in settings
+#[derive(Debug, Clone, Serialize)]
+pub struct Shortcut {
+    pub id: &'static str,
+    pub scope: ShortcutScope,
+    pub label: &'static str,
+    /// None when disabled
+    pub accelerator: Option<String>,
+    pub default_accelerator: Option<&'static str>,
+    /// Global hotkeys only: false when the OS refused it, usually because another app
+    /// holds it
+    pub registered: Option<bool>,
+}
+
+pub fn find(id: &str) -> Option<&'static ShortcutDef> {
+    SHORTCUTS.iter().find(|def| def.id == id)
+}
+
+/// The shortcuts that exist on this platform
+fn available() -> impl Iterator<Item = &'static ShortcutDef> {
+    SHORTCUTS
+        .iter()
+        .filter(|def| def.scope != Menu || cfg!(target_os = "macos"))
+}
+
+/// Effective accelerator of every shortcut: the stored override, else the default
+pub fn bindings(db: &Database) -> HashMap<&'static str, Option<String>> {
+    let stored = db.get_shortcut_bindings().unwrap_or_default();
+    SHORTCUTS
+        .iter()
+        .map(|def| {
+            let accelerator = match stored.get(def.id) {
+                Some(accelerator) => Some(accelerator.trim().to_string()).filter(|a| !a.is_empty()),
+                None => def.default.map(str::to_string),
+            };
+            (def.id, accelerator)
+        })
+        .collect()
+}
+
+/// Effective accelerator of one shortcut
+pub fn accelerator(app: &AppHandle, id: &str) -> Option<String> {
+    let db = app.try_state::<Database>()?;
+    bindings(&db).remove(id).flatten()
+}
+
+/// A menu item carrying the accelerator bound to `shortcut_id`. An accelerator the menu
+/// can't use is dropped rather than failing the whole menu.
+pub fn menu_item(
+    app: &AppHandle,
+    id: &str,
+    text: &str,
+    enabled: bool,
+    shortcut_id: &str,
+) -> tauri::Result<MenuItem<Wry>> {
+    if let Some(accelerator) = accelerator(app, shortcut_id) {
+        match MenuItem::with_id(app, id, text, enabled, Some(accelerator.as_str())) {
+            Ok(item) => return Ok(item),
+            Err(e) => tracing::warn!(
+                "Ignoring {} for {}: {}",
+                accelerator, shortcut_id, e
+            ),
+        }
+    }
+    MenuItem::with_id(app, id, text, enabled, None::<&str>)
+}
+
+/// Keys accepted in menu accelerators besides letters, digits and F-keys
+const MENU_KEYS: &[&str] = &[
+    "space",
+    "tab",
+    "enter",
+    "backspace",
+    "delete",
+    "escape",
+    "up",
+    "down",
+    "left",
+    "right",
+    "home",
+    "end",
+    "pageup",
+    "pagedown",
+    ",",
+    ".",
+    "/",
+    ";",
+    "'",
+    "[",
+    "]",
+    "\\",
+    "-",
+    "=",
+    "`",
+];
+
+/// A comparable form of `accelerator` ("CmdOrCtrl" resolved for this platform, modifiers
+/// in a fixed order), or an error if it isn't usable in `scope`
+pub fn normalize(accelerator: &str, scope: ShortcutScope) -> Result<String, String> {
+    if scope == Global {
+        // Global hotkeys support fewer keys and need a modifier
+        Accelerator::parse(accelerator)?;
+    }
+
+    let (mut ctrl, mut alt, mut shift, mut meta) = (false, false, false, false);
+    let mut key = None;
+    for part in accelerator.split('+').map(str::trim) {
+        match part.to_lowercase().as_str() {
+            "ctrl" | "control" => ctrl = true,
+            "alt" | "option" => alt = true,
+            "shift" => shift = true,
+            "cmd" | "command" | "super" | "meta" | "win" => meta = true,
+            "cmdorctrl" | "commandorcontrol" => {
+                if cfg!(target_os = "macos") {
+                    meta = true;
+                } else {
+                    ctrl = true;
+                }
+            }
+            name if key.is_none() && is_menu_key(name) => key = Some(name.to_uppercase()),
+            _ => return Err(format!("Invalid shortcut \"{}\"", accelerator)),
+        }
+    }
+    let key = key.ok_or_else(|| format!("Invalid shortcut \"{}\": no key", accelerator))?;
+
+    let mut normalized = String::new();
+    for (held, name) in [
+        (ctrl, "Ctrl"),
+        (alt, "Alt"),
+        (shift, "Shift"),
+        (meta, "Cmd"),
+    ] {
+        if held {
+            normalized.push_str(name);
+            normalized.push('+');
+        }
+    }
+    normalized.push_str(&key);
+    Ok(normalized)
+}
+
+fn is_menu_key(name: &str) -> bool {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && c.is_ascii_alphanumeric()
+    {
+        return true;
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&n);
+    }
+    MENU_KEYS.contains(&name)
+}
+
+/// Global hotkeys take the key system-wide, so they clash with everything; menu
+/// accelerators only clash within their own menu
+fn clashes(a: ShortcutScope, b: ShortcutScope) -> bool {
+    a == b || a == Global || b == Global
+}
+
+/// The shortcut `accelerator` would clash with, if any
+fn find_conflict(
+    bindings: &HashMap<&'static str, Option<String>>,
+    def: &ShortcutDef,
+    normalized: &str,
+) -> Option<&'static ShortcutDef> {
+    available().find(|other| {
+        other.id != def.id
+            && clashes(def.scope, other.scope)
+            && bindings
+                .get(other.id)
+                .cloned()
+                .flatten()
+                .and_then(|a| normalize(&a, other.scope).ok())
+                .is_some_and(|a| a == normalized)
+    })
+}
+
+/// Rebuild whatever shows the shortcuts of `scope`
+pub fn reload(app: &AppHandle, scope: ShortcutScope) {
+    match scope {
+        Menu => app_menu::rebuild(app),
+        Tray => tray::refresh(app),
+        Global => hotkeys::install(app),
+    }
+}
+
+fn describe(def: &'static ShortcutDef, accelerator: Option<String>) -> Shortcut {
+    Shortcut {
+        id: def.id,
+        scope: def.scope,
+        label: def.label,
+        accelerator,
+        default_accelerator: def.default,
+        registered: (def.scope == Global).then(|| hotkeys::is_registered(def.id)),
+    }
+}
+
+#[tauri::command]
+pub fn list_shortcuts(db: State<'_, Database>) -> Vec<Shortcut> {
+    let mut bindings = bindings(&db);
+    available()
+        .map(|def| describe(def, bindings.remove(def.id).flatten()))
+        .collect()
+}
+
+/// Bind a shortcut (`None` or empty disables it). Fails on an invalid accelerator or one
+/// that clashes with another shortcut.
+#[tauri::command]
+pub fn set_shortcut(
+    app: AppHandle,
+    id: String,
+    accelerator: Option<String>,
+    db: State<'_, Database>,
+) -> Result<Shortcut, String> {
+    let def = find(&id).ok_or_else(|| format!("Unknown shortcut: {}", id))?;
+    let accelerator = accelerator
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty());
+
+    if let Some(accelerator) = &accelerator {
+        let normalized = normalize(accelerator, def.scope)?;
+        if let Some(other) = find_conflict(&bindings(&db), def, &normalized) {
+            return Err(format!(
+                "{} is already used by \"{}\"",
+                accelerator, other.label
+            ));
+        }
+    }
+    db.set_shortcut_binding(def.id, accelerator.as_deref().unwrap_or(""))
+        .map_err(|e| e.to_string())?;
+
+    Ok(changed(&app, def, accelerator))
+}
+
+/// Restore a shortcut's default binding
+#[tauri::command]
+pub fn reset_shortcut(
+    app: AppHandle,
+    id: String,
+    db: State<'_, Database>,
+) -> Result<Shortcut, String> {
+    let def = find(&id).ok_or_else(|| format!("Unknown shortcut: {}", id))?;
+    if let Some(default) = def.default {
+        let normalized = normalize(default, def.scope)?;
+        if let Some(other) = find_conflict(&bindings(&db), def, &normalized) {
+            return Err(format!(
+                "The default {} is now used by \"{}\"",
+                default, other.label
+            ));
+        }
+    }
+    db.delete_shortcut_binding(def.id)
+        .map_err(|e| e.to_string())?;
+
+    Ok(changed(&app, def, def.default.map(str::to_string)))
+}
+
+fn changed(app: &AppHandle, def: &'static ShortcutDef, accelerator: Option<String>) -> Shortcut {
+    reload(app, def.scope);
+    let shortcut = describe(def, accelerator);
+    let _ = app.emit("shortcuts-changed", &shortcut);
+    shortcut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("shift + ctrl + r", Tray).unwrap(),
+            normalize("Ctrl+Shift+R", Tray).unwrap()
+        );
+        assert_eq!(
+            normalize("CmdOrCtrl+,", Tray).unwrap(),
+            if cfg!(target_os = "macos") {
+                "Cmd+,"
+            } else {
+                "Ctrl+,"
+            }
+        );
+        assert!(normalize("Ctrl+F24", Menu).is_ok());
+        // Global hotkeys are stricter
+        assert!(normalize("Ctrl+,", Global).is_err());
+        assert!(normalize("Shift+R", Global).is_err());
+        assert!(normalize("Ctrl+R+S", Tray).is_err());
+        assert!(normalize("Ctrl+", Tray).is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let tray = find("tray.new_note").unwrap();
+        let global = find("global.start_recording").unwrap();
+
+        let mut bindings: HashMap<&'static str, Option<String>> =
+            SHORTCUTS.iter().map(|def| (def.id, None)).collect();
+        bindings.insert("tray.open", Some("Ctrl+K".to_string()));
+        bindings.insert("global.stop_recording", Some("Ctrl+Alt+K".to_string()));
+        bindings.insert("menu.hide_window", Some("Ctrl+J".to_string()));
+
+        let key = |a: &str| normalize(a, Tray).unwrap();
+        assert_eq!(
+            find_conflict(&bindings, tray, &key("Ctrl+K")).map(|d| d.id),
+            Some("tray.open")
+        );
+        // Any menu accelerator clashes with a global hotkey
+        assert_eq!(
+            find_conflict(&bindings, tray, &key("Ctrl+Alt+K")).map(|d| d.id),
+            Some("global.stop_recording")
+        );
+        // The tray and app menus are separate
+        assert!(find_conflict(&bindings, tray, &key("Ctrl+J")).is_none());
+        assert!(find_conflict(&bindings, global, &key("Ctrl+Alt+K")).is_some());
+    }
+}
@@ -0,0 +1,100 @@
+//! Quitting without damaging the current recording. Every exit path (tray "Exit", the app
+//! menu's Quit, installing an update, the OS ending the session) goes through `prepare`:
+//! the live transcription loop is told to stop, the recording is stopped the same way the
+//! tray's "Stop Recording" does (which finalizes the WAV files and ends the note), and a
+//! live transcription pass that already took its audio can finish saving its segments.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio;
+use crate::commands::{self, AudioState, TranscriptionState};
+use crate::crash;
+use crate::db::Database;
+use crate::focus_mode;
+
+/// Let a live transcription pass finish before quitting (on by default)
+pub const SETTING_WAIT_FOR_TRANSCRIPTION: &str = "shutdown_wait_for_transcription";
+
+/// The longest quitting waits for a live transcription pass
+const TRANSCRIPTION_WAIT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static PREPARED: AtomicBool = AtomicBool::new(false);
+
+/// Quit the app once `prepare` is done. Runs on its own thread: stopping capture can take a
+/// moment and menu handlers are called on the main thread.
+pub fn exit(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        prepare(&app);
+        app.exit(0);
+    });
+}
+
+/// Stop recording and transcription cleanly and mark the exit as clean. Only the first
+/// call does anything, so it is safe on every exit path.
+pub fn prepare(app: &AppHandle) {
+    if PREPARED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let started = Instant::now();
+
+    let live = app
+        .try_state::<TranscriptionState>()
+        .map(|state| state.live_state.clone());
+    if let Some(live) = &live {
+        live.is_running.store(false, Ordering::SeqCst);
+    }
+
+    if app.try_state::<AudioState>().is_some() {
+        stop_recording(app);
+    }
+
+    if let Some(live) = &live
+        && wait_for_transcription(app)
+    {
+        let deadline = Instant::now() + TRANSCRIPTION_WAIT;
+        while live.pass_in_flight.load(Ordering::SeqCst) && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        if live.pass_in_flight.load(Ordering::SeqCst) {
+            tracing::warn!("Quitting with a live transcription pass still running");
+        }
+    }
+
+    focus_mode::restore(app);
+    crash::mark_clean_exit();
+    tracing::info!("Shutdown took {:?}", started.elapsed());
+}
+
+/// Stop the active recording and end its note. If that fails, at least stop the capture
+/// so the WAV headers get written.
+fn stop_recording(app: &AppHandle) {
+    match commands::stop_active_recording(app) {
+        Ok(Some(stopped)) => {
+            tracing::info!("Stopped recording {} to quit", stopped.note_id);
+            let _ = app.emit("tray-recording-stopped", stopped);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!("Failed to stop recording on quit: {}", e);
+            let state = app.state::<AudioState>();
+            let _ = audio::stop_recording(&state.recording);
+            if let Ok(capture) = state.system_capture()
+                && let Some(capture) = capture.as_ref()
+            {
+                let _ = capture.stop();
+            }
+        }
+    }
+}
+
+fn wait_for_transcription(app: &AppHandle) -> bool {
+    app.try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_WAIT_FOR_TRANSCRIPTION).ok().flatten())
+        .is_none_or(|v| v == "true")
+}
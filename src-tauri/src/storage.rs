@@ -0,0 +1,693 @@
+//! Where a note's files live. Everything a note owns sits in one folder under the data root:
+//!   data/<note id>/recordings/   mic, system, segment and playback WAVs
+//!   data/<note id>/uploads/      converted uploads and imported voice memos
+//!   data/<note id>/attachments/  images pasted into the note
+//! so deleting, exporting or checking a note's files is a single directory operation.
+//! The data root is `data/` in the app data folder unless the user moved it elsewhere
+//! (another drive, say) with `move_storage`, which copies the files over, rewrites the
+//! paths stored in the database and only then removes the old folder.
+//! Older versions kept recordings and uploads flat in `recordings/` and attachments in
+//! `attachments/<note id>/`; `migrate_legacy_layout` moves those into place at startup.
+//! `purge_note_audio` frees a note's audio (recordings and uploads) while keeping its
+//! transcript; `retention` does it for old notes.
+
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::compression;
+use crate::commands::AudioState;
+use crate::db::Database;
+use crate::notifications::{available_space, LOW_DISK_SPACE_BYTES};
+
+/// Folder holding every note's files, when not the default. Changed only by `move_storage`.
+pub const SETTING_LOCATION: &str = "storage_location";
+
+const DATA_DIR: &str = "data";
+const LEGACY_RECORDINGS_DIR: &str = "recordings";
+const LEGACY_ATTACHMENTS_DIR: &str = "attachments";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteFolder {
+    Recordings,
+    Uploads,
+    Attachments,
+}
+
+impl NoteFolder {
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            NoteFolder::Recordings => "recordings",
+            NoteFolder::Uploads => "uploads",
+            NoteFolder::Attachments => "attachments",
+        }
+    }
+}
+
+/// Set while `move_storage` runs; no new files are written until it finishes
+static MOVING: AtomicBool = AtomicBool::new(false);
+
+/// The folder holding every note's files
+pub fn data_root(app: &AppHandle) -> Result<PathBuf, String> {
+    let custom = app
+        .try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_LOCATION).ok().flatten())
+        .filter(|location| !location.is_empty());
+    match custom {
+        Some(location) => Ok(PathBuf::from(location)),
+        None => default_root(app),
+    }
+}
+
+/// Whether the data root was moved away from the default
+pub fn is_custom_location(app: &AppHandle) -> bool {
+    app.try_state::<Database>()
+        .and_then(|db| db.get_setting(SETTING_LOCATION).ok().flatten())
+        .is_some_and(|location| !location.is_empty())
+}
+
+fn default_root(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(DATA_DIR))
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Note ids become folder names, so anything that could leave the data root is refused
+fn check_note_id(note_id: &str) -> Result<(), String> {
+    let valid = !note_id.is_empty()
+        && note_id != "."
+        && note_id != ".."
+        && !note_id.contains(['/', '\\', ':']);
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid note id: {:?}", note_id))
+    }
+}
+
+/// The folder holding everything a note owns (which may not exist yet)
+pub fn note_dir(app: &AppHandle, note_id: &str) -> Result<PathBuf, String> {
+    check_note_id(note_id)?;
+    Ok(data_root(app)?.join(note_id))
+}
+
+/// One of a note's folders (which may not exist yet)
+pub fn folder(app: &AppHandle, note_id: &str, folder: NoteFolder) -> Result<PathBuf, String> {
+    Ok(note_dir(app, note_id)?.join(folder.dir_name()))
+}
+
+/// One of a note's folders, created if missing, for writing a new file into
+pub fn create_folder(app: &AppHandle, note_id: &str, kind: NoteFolder) -> Result<PathBuf, String> {
+    if MOVING.load(Ordering::SeqCst) {
+        return Err(
+            "Storage is being moved to a new location. Try again when it's done.".to_string(),
+        );
+    }
+    let dir = folder(app, note_id, kind)?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {} folder: {}", kind.dir_name(), e))?;
+    Ok(dir)
+}
+
+/// Remove every file a note owns
+pub fn delete_note_files(app: &AppHandle, note_id: &str) -> Result<(), String> {
+    let dir = note_dir(app, note_id)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete note files: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Total size of the files below `dir`
+fn dir_size(dir: &Path) -> u64 {
+    walk_files(dir)
+        .iter()
+        .filter_map(|f| fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Disk space taken by one note's files, by folder
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteStorageUsage {
+    pub note_id: String,
+    pub recordings_bytes: u64,
+    pub uploads_bytes: u64,
+    pub attachments_bytes: u64,
+    pub total_bytes: u64,
+    /// When the note's audio was purged, if it was
+    pub audio_purged_at: Option<DateTime<Utc>>,
+}
+
+/// Disk usage of every note with files, largest first
+pub fn note_usage(app: &AppHandle) -> Result<Vec<NoteStorageUsage>, String> {
+    let root = data_root(app)?;
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Ok(Vec::new());
+    };
+    let mut purged = app
+        .state::<Database>()
+        .get_audio_purged_notes()
+        .map_err(|e| e.to_string())?;
+    let mut usage: Vec<NoteStorageUsage> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| {
+            let note_id = e.file_name().to_string_lossy().to_string();
+            let dir = e.path();
+            let recordings_bytes = dir_size(&dir.join(NoteFolder::Recordings.dir_name()));
+            let uploads_bytes = dir_size(&dir.join(NoteFolder::Uploads.dir_name()));
+            let attachments_bytes = dir_size(&dir.join(NoteFolder::Attachments.dir_name()));
+            NoteStorageUsage {
+                audio_purged_at: purged.remove(&note_id),
+                note_id,
+                recordings_bytes,
+                uploads_bytes,
+                attachments_bytes,
+                total_bytes: dir_size(&dir),
+            }
+        })
+        .collect();
+    usage.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes));
+    Ok(usage)
+}
+
+/// Delete a note's recordings and uploads, keeping its transcript, summaries and
+/// attachments, and record that its audio is gone. Returns the bytes freed.
+pub fn purge_note_audio(app: &AppHandle, note_id: &str) -> Result<u64, String> {
+    if MOVING.load(Ordering::SeqCst) {
+        return Err("Storage is being moved to a new location".to_string());
+    }
+    let recording = app
+        .state::<AudioState>()
+        .recording
+        .current_note_id
+        .lock()
+        .ok()
+        .and_then(|id| id.clone());
+    if recording.as_deref() == Some(note_id) {
+        return Err("The note is being recorded".to_string());
+    }
+    if compression::progress().iter().any(|c| c.note_id == note_id) {
+        return Err("The note's recording is being compressed".to_string());
+    }
+
+    let mut freed = 0;
+    for kind in [NoteFolder::Recordings, NoteFolder::Uploads] {
+        let dir = folder(app, note_id, kind)?;
+        if !dir.exists() {
+            continue;
+        }
+        let size = dir_size(&dir);
+        fs::remove_dir_all(&dir)
+            .map_err(|e| format!("Failed to delete {}: {}", kind.dir_name(), e))?;
+        freed += size;
+    }
+    app.state::<Database>()
+        .mark_note_audio_purged(note_id)
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Storage: purged audio of {} ({} bytes)", note_id, freed);
+    Ok(freed)
+}
+
+/// The `kind` folder of every note that has one
+pub fn existing_folders(app: &AppHandle, kind: NoteFolder) -> Vec<PathBuf> {
+    let Ok(entries) = data_root(app).and_then(|root| fs::read_dir(root).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path().join(kind.dir_name()))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Every file below `dir`, sorted
+pub fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => pending.push(entry.path()),
+                Ok(t) if t.is_file() => files.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Where note files are kept and what moving them to `target` would take
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageMovePlan {
+    pub from: String,
+    pub to: String,
+    pub files: u64,
+    pub bytes: u64,
+    /// Free space at the target, when it can be read
+    pub free_bytes: Option<u64>,
+    /// The target is on the same volume, so files are renamed rather than copied
+    pub same_volume: bool,
+    /// There is room for the files with space to spare (assumed when free space is unknown)
+    pub fits: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageMoveReport {
+    pub location: String,
+    pub files_moved: u64,
+    pub bytes_moved: u64,
+    /// Set when the old folder could not be removed afterwards (the move itself succeeded)
+    pub cleanup_error: Option<String>,
+}
+
+/// Payload of "storage-move-progress"
+#[derive(Debug, Clone, Serialize)]
+struct StorageMoveProgress {
+    files_done: u64,
+    files_total: u64,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+/// Check a new location (None for the default) and size up the move, without changing
+/// anything. The target must be an absolute path to an empty or missing folder outside
+/// the current one.
+pub fn plan_move(app: &AppHandle, target: Option<&str>) -> Result<StorageMovePlan, String> {
+    let from = data_root(app)?;
+    let to = match target.map(str::trim).filter(|t| !t.is_empty()) {
+        // Rebuilt from components to drop trailing separators
+        Some(target) => Path::new(target).components().collect(),
+        None => default_root(app)?,
+    };
+    if !to.is_absolute() {
+        return Err("Choose a full folder path".to_string());
+    }
+    if to == from {
+        return Err("Notes are already stored there".to_string());
+    }
+    if to.starts_with(&from) || from.starts_with(&to) {
+        return Err("The new location can't be inside the current one, or contain it".to_string());
+    }
+    if to.exists() {
+        let empty = fs::read_dir(&to)
+            .map_err(|e| format!("Can't open {}: {}", to.display(), e))?
+            .next()
+            .is_none();
+        if !empty {
+            return Err("Choose an empty folder".to_string());
+        }
+    }
+
+    let files = walk_files(&from);
+    let bytes = files
+        .iter()
+        .filter_map(|f| fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum();
+    let free_bytes = available_space(&to);
+    let same_volume = same_volume(&from, &to);
+    let fits = same_volume || free_bytes.is_none_or(|free| free >= bytes + LOW_DISK_SPACE_BYTES);
+    Ok(StorageMovePlan {
+        from: from.to_string_lossy().to_string(),
+        to: to.to_string_lossy().to_string(),
+        files: files.len() as u64,
+        bytes,
+        free_bytes,
+        same_volume,
+        fits,
+    })
+}
+
+/// Move every note's files to `target` (None for the default location) and point the
+/// database at them. On the same volume the folder is renamed; otherwise files are copied
+/// first, the paths in the database and the setting change only once every copy is
+/// complete, and the old folder is removed last. A failure at any step leaves the notes
+/// working from wherever they were.
+pub fn move_storage(app: &AppHandle, target: Option<&str>) -> Result<StorageMoveReport, String> {
+    let recording = app
+        .state::<AudioState>()
+        .mode
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if recording {
+        return Err("Can't move storage while recording".to_string());
+    }
+    if MOVING.swap(true, Ordering::SeqCst) {
+        return Err("Storage is already being moved".to_string());
+    }
+    let _moving = scopeguard::guard((), |_| MOVING.store(false, Ordering::SeqCst));
+
+    let plan = plan_move(app, target)?;
+    if !plan.fits {
+        return Err(format!(
+            "Not enough free space at {}: {} MB needed",
+            plan.to,
+            (plan.bytes + LOW_DISK_SPACE_BYTES) / (1024 * 1024)
+        ));
+    }
+    let (from, to) = (PathBuf::from(&plan.from), PathBuf::from(&plan.to));
+
+    let renamed = plan.same_volume && from.exists() && {
+        let _ = fs::remove_dir(&to);
+        fs::create_dir_all(to.parent().unwrap_or(&to)).is_ok() && fs::rename(&from, &to).is_ok()
+    };
+    if !renamed && let Err(e) = copy_tree(app, &from, &to, &plan) {
+        // Only what this move created is removed; the target was empty or missing
+        let _ = fs::remove_dir_all(&to);
+        return Err(format!("Failed to copy notes' files: {}", e));
+    }
+
+    let db = app.state::<Database>();
+    let (old, new) = (folder_prefix(&from), folder_prefix(&to));
+    let repoint = || -> anyhow::Result<()> {
+        db.relocate_path_prefix(&old, &new)?;
+        db.replace_in_descriptions(&old, &new)?;
+        db.replace_in_descriptions(&encode_uri_component(&old), &encode_uri_component(&new))?;
+        if to == default_root(app).map_err(anyhow::Error::msg)? {
+            db.delete_setting(SETTING_LOCATION)
+        } else {
+            db.set_setting(SETTING_LOCATION, &plan.to)
+        }
+    };
+    if let Err(e) = repoint() {
+        // Put back whatever was rewritten, then the files
+        let _ = db.relocate_path_prefix(&new, &old);
+        let _ = db.replace_in_descriptions(&new, &old);
+        let _ =
+            db.replace_in_descriptions(&encode_uri_component(&new), &encode_uri_component(&old));
+        if renamed {
+            let _ = fs::rename(&to, &from);
+        } else {
+            let _ = fs::remove_dir_all(&to);
+        }
+        return Err(format!("Failed to update stored paths: {}", e));
+    }
+    allow_asset_access(app);
+
+    let cleanup_error = match fs::remove_dir_all(&from) {
+        Ok(()) => None,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::warn!("Storage: failed to remove {}: {}", from.display(), e);
+            Some(e.to_string())
+        }
+    };
+    tracing::info!("Storage: moved {} files to {}", plan.files, plan.to);
+    let report = StorageMoveReport {
+        location: plan.to,
+        files_moved: plan.files,
+        bytes_moved: plan.bytes,
+        cleanup_error,
+    };
+    let _ = app.emit("storage-moved", &report);
+    Ok(report)
+}
+
+/// Copy every file under `from` to the same place under `to`, reporting progress
+fn copy_tree(
+    app: &AppHandle,
+    from: &Path,
+    to: &Path,
+    plan: &StorageMovePlan,
+) -> std::io::Result<()> {
+    let mut progress = StorageMoveProgress {
+        files_done: 0,
+        files_total: plan.files,
+        bytes_done: 0,
+        bytes_total: plan.bytes,
+    };
+    fs::create_dir_all(to)?;
+    for file in walk_files(from) {
+        let Ok(relative) = file.strip_prefix(from) else {
+            continue;
+        };
+        let target = to.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let copied = fs::copy(&file, &target)?;
+        if fs::metadata(&target)?.len() != copied {
+            return Err(std::io::Error::other(format!(
+                "{} was not copied completely",
+                file.display()
+            )));
+        }
+        progress.files_done += 1;
+        progress.bytes_done += copied;
+        let _ = app.emit("storage-move-progress", &progress);
+    }
+    Ok(())
+}
+
+/// Let the webview load attachments and recordings from a data root outside the app data
+/// folder (which the asset protocol scope in tauri.conf.json already covers)
+pub fn allow_asset_access(app: &AppHandle) {
+    if let Ok(root) = data_root(app)
+        && let Err(e) = app.asset_protocol_scope().allow_directory(&root, true)
+    {
+        tracing::warn!(
+            "Storage: failed to allow asset access to {}: {}",
+            root.display(),
+            e
+        );
+    }
+}
+
+/// Whether a rename from `a` to `b` (which need not exist yet) stays on one volume
+#[cfg(unix)]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| {
+        path.ancestors()
+            .find_map(|p| fs::metadata(p).ok())
+            .map(|m| m.dev())
+    };
+    device(a).is_some_and(|dev| device(b) == Some(dev))
+}
+
+#[cfg(target_os = "windows")]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    use std::path::Component;
+
+    let prefix = |path: &Path| match path.components().next() {
+        Some(Component::Prefix(prefix)) => {
+            Some(prefix.as_os_str().to_string_lossy().to_lowercase())
+        }
+        _ => None,
+    };
+    prefix(a).is_some_and(|p| prefix(b) == Some(p))
+}
+
+/// Move files from the flat layout of older versions into their notes' folders, updating
+/// the database as they go. Each path is rewritten before its file moves (and restored if
+/// the move fails), so an interruption leaves the database pointing at the new location
+/// and the next launch finishes the move.
+pub fn migrate_legacy_layout(app: &AppHandle) {
+    let (Ok(app_data), Ok(root)) = (app.path().app_data_dir(), data_root(app)) else {
+        return;
+    };
+    let db = app.state::<Database>();
+    let mut moved = 0;
+
+    let legacy_recordings = app_data.join(LEGACY_RECORDINGS_DIR);
+    if let Ok(entries) = fs::read_dir(&legacy_recordings) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let Some((note_id, kind)) = legacy_owner(name) else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            let target = root.join(note_id).join(kind.dir_name()).join(name);
+            let (old, new) = (path.to_string_lossy(), target.to_string_lossy());
+            if let Err(e) = db.relocate_file(&old, &new) {
+                tracing::warn!("Storage: failed to update paths for {}: {}", old, e);
+                continue;
+            }
+            match move_into_place(&path, &target) {
+                Ok(()) => moved += 1,
+                Err(e) => {
+                    tracing::warn!("Storage: failed to move {}: {}", old, e);
+                    let _ = db.relocate_file(&new, &old);
+                }
+            }
+        }
+        // Only succeeds once everything has moved
+        let _ = fs::remove_dir(&legacy_recordings);
+    }
+
+    let legacy_attachments = app_data.join(LEGACY_ATTACHMENTS_DIR);
+    if let Ok(entries) = fs::read_dir(&legacy_attachments) {
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let Some(note_id) = dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !dir.is_dir() || check_note_id(note_id).is_err() {
+                continue;
+            }
+            let target = root.join(note_id).join(NoteFolder::Attachments.dir_name());
+            // Notes embed attachments by path and by asset URL. A folder that only partly
+            // moves is finished by the next launch, so the links aren't rewritten back.
+            let (old, new) = (folder_prefix(&dir), folder_prefix(&target));
+            for (from, to) in [
+                (old.clone(), new.clone()),
+                (encode_uri_component(&old), encode_uri_component(&new)),
+            ] {
+                if let Err(e) = db.replace_in_descriptions(&from, &to) {
+                    tracing::warn!("Storage: failed to update attachment links: {}", e);
+                }
+            }
+            let result = fs::read_dir(&dir).and_then(|files| {
+                for file in files.flatten() {
+                    move_into_place(&file.path(), &target.join(file.file_name()))?;
+                    moved += 1;
+                }
+                fs::remove_dir(&dir)
+            });
+            if let Err(e) = result {
+                tracing::warn!("Storage: failed to move attachments of {}: {}", note_id, e);
+            }
+        }
+        let _ = fs::remove_dir(&legacy_attachments);
+    }
+
+    if moved > 0 {
+        tracing::info!("Storage: moved {} files into per-note folders", moved);
+    }
+}
+
+/// Move `from` to `to`, creating `to`'s folder. Never replaces an existing file.
+fn move_into_place(from: &Path, to: &Path) -> std::io::Result<()> {
+    if to.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        // The storage location is on another volume
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Which note and folder a file from the flat `recordings/` folder belongs to. Every name
+/// starts with the note id (`<id>.wav`, `<id>_mic_seg2.wav`, `<id>_upload_1f2e3d4c.wav`).
+fn legacy_owner(name: &str) -> Option<(&str, NoteFolder)> {
+    let note_id = name.split(['_', '.']).next()?;
+    check_note_id(note_id).ok()?;
+    let kind = if name[note_id.len()..].starts_with("_upload_") {
+        NoteFolder::Uploads
+    } else {
+        NoteFolder::Recordings
+    };
+    Some((note_id, kind))
+}
+
+/// A folder path with a trailing separator, so it only matches whole path components
+fn folder_prefix(dir: &Path) -> String {
+    format!("{}{}", dir.to_string_lossy(), MAIN_SEPARATOR)
+}
+
+/// JavaScript's encodeURIComponent, which `convertFileSrc` applies to asset paths
+fn encode_uri_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_owner() {
+        let id = "0f8e2c1a-3b4d-4e5f-8a9b-0c1d2e3f4a5b";
+        assert_eq!(
+            legacy_owner(&format!("{}.wav", id)),
+            Some((id, NoteFolder::Recordings))
+        );
+        assert_eq!(
+            legacy_owner(&format!("{}_system_seg3.wav", id)),
+            Some((id, NoteFolder::Recordings))
+        );
+        assert_eq!(
+            legacy_owner(&format!("{}_upload_1f2e3d4c.wav.tmp", id)),
+            Some((id, NoteFolder::Uploads))
+        );
+        assert_eq!(legacy_owner(".DS_Store"), None);
+        assert_eq!(legacy_owner("_mic.wav"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_same_volume() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        assert!(same_volume(dir, &dir.join("missing").join("data")));
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("recordings")).unwrap();
+        fs::write(dir.join("a.txt"), [0u8; 10]).unwrap();
+        fs::write(dir.join("recordings").join("b.wav"), [0u8; 32]).unwrap();
+        assert_eq!(dir_size(dir), 42);
+        assert_eq!(dir_size(&dir.join("missing")), 0);
+    }
+
+    #[test]
+    fn test_check_note_id() {
+        assert!(check_note_id("0f8e2c1a-3b4d-4e5f-8a9b-0c1d2e3f4a5b").is_ok());
+        assert!(check_note_id("").is_err());
+        assert!(check_note_id("..").is_err());
+        assert!(check_note_id("../secrets").is_err());
+        assert!(check_note_id("a\\b").is_err());
+    }
+
+    #[test]
+    fn test_encode_uri_component() {
+        assert_eq!(
+            encode_uri_component("/Users/me/Application Support/attachments/"),
+            "%2FUsers%2Fme%2FApplication%20Support%2Fattachments%2F"
+        );
+        assert_eq!(
+            encode_uri_component("C:\\a-b_c.d!~*'()"),
+            "C%3A%5Ca-b_c.d!~*'()"
+        );
+        assert_eq!(encode_uri_component("é"), "%C3%A9");
+    }
+}
@@ -0,0 +1,347 @@
+//! A small Handlebars-style template renderer over JSON values
+//!
+//! Supported syntax:
+//! - `{{path.to.field}}` inserts a value (not escaped; output is markdown or plain text)
+//! - `{{json path}}` inserts a value as JSON (quoted and escaped strings, objects, lists)
+//! - `{{#each list}}...{{else}}...{{/each}}` repeats for each item; inside, fields resolve
+//!   against the item first, `{{this}}` is the item and `{{@index}}` its 0-based position
+//! - `{{#if value}}...{{else}}...{{/if}}` and `{{#unless value}}...{{/unless}}`;
+//!   null, false, 0, "" and empty lists are false
+//! - `{{! comment}}`
+
+use serde_json::Value;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Template error: {0}")]
+pub struct TemplateError(String);
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Value(String),
+    Json(String),
+    Each {
+        path: String,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    If {
+        path: String,
+        negate: bool,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Value(String),
+    Json(String),
+    Open(String, String),
+    Else,
+    Close(String),
+}
+
+/// Render `template` with `data` as the root context
+pub fn render(template: &str, data: &Value) -> Result<String, TemplateError> {
+    let nodes = parse(template)?;
+    let mut out = String::new();
+    let mut scopes = vec![Scope {
+        value: data,
+        index: None,
+    }];
+    render_nodes(&nodes, &mut scopes, &mut out);
+    Ok(out)
+}
+
+/// Check a template for syntax errors without rendering it
+pub fn validate(template: &str) -> Result<(), TemplateError> {
+    parse(template).map(|_| ())
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            tokens.push(Token::Text(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+        // `{{{x}}}` is accepted as an alias of `{{x}}` since nothing is escaped anyway
+        let (inner, consumed) = if let Some(triple) = after.strip_prefix('{') {
+            let end = triple
+                .find("}}}")
+                .ok_or_else(|| TemplateError("Unclosed {{{ tag".to_string()))?;
+            (&triple[..end], 1 + end + 3)
+        } else {
+            let end = after
+                .find("}}")
+                .ok_or_else(|| TemplateError("Unclosed {{ tag".to_string()))?;
+            (&after[..end], end + 2)
+        };
+        rest = &after[consumed..];
+
+        let inner = inner.trim();
+        if inner.starts_with('!') {
+            continue;
+        }
+        if let Some(block) = inner.strip_prefix('#') {
+            let mut parts = block.splitn(2, char::is_whitespace);
+            let kind = parts.next().unwrap_or_default().to_string();
+            let path = parts.next().unwrap_or_default().trim().to_string();
+            if path.is_empty() {
+                return Err(TemplateError(format!("{{{{#{}}}}} needs a value", kind)));
+            }
+            tokens.push(Token::Open(kind, path));
+        } else if let Some(kind) = inner.strip_prefix('/') {
+            tokens.push(Token::Close(kind.trim().to_string()));
+        } else if inner == "else" {
+            tokens.push(Token::Else);
+        } else if inner.is_empty() {
+            return Err(TemplateError("Empty {{}} tag".to_string()));
+        } else if let Some(path) = inner.strip_prefix("json ") {
+            tokens.push(Token::Json(path.trim().to_string()));
+        } else {
+            tokens.push(Token::Value(inner.to_string()));
+        }
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+    Ok(tokens)
+}
+
+fn parse(template: &str) -> Result<Vec<Node>, TemplateError> {
+    let tokens = tokenize(template)?;
+    let mut iter = tokens.into_iter();
+    let (nodes, end) = parse_block(&mut iter, None)?;
+    debug_assert!(!end);
+    Ok(nodes)
+}
+
+/// Parse nodes until the closing tag of `open` (or the end for the top level).
+/// Returns the nodes and whether parsing stopped at an `{{else}}`.
+fn parse_block(
+    tokens: &mut impl Iterator<Item = Token>,
+    open: Option<&str>,
+) -> Result<(Vec<Node>, bool), TemplateError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Value(path) => nodes.push(Node::Value(path)),
+            Token::Json(path) => nodes.push(Node::Json(path)),
+            Token::Else => {
+                if open.is_none() {
+                    return Err(TemplateError("{{else}} outside a block".to_string()));
+                }
+                return Ok((nodes, true));
+            }
+            Token::Close(kind) => {
+                return match open {
+                    Some(expected) if expected == kind => Ok((nodes, false)),
+                    Some(expected) => Err(TemplateError(format!(
+                        "Expected {{{{/{}}}}} but found {{{{/{}}}}}",
+                        expected, kind
+                    ))),
+                    None => Err(TemplateError(format!("Unexpected {{{{/{}}}}}", kind))),
+                };
+            }
+            Token::Open(kind, path) => {
+                if !matches!(kind.as_str(), "each" | "if" | "unless") {
+                    return Err(TemplateError(format!("Unknown block {{{{#{}}}}}", kind)));
+                }
+                let (body, hit_else) = parse_block(tokens, Some(&kind))?;
+                let otherwise = if hit_else {
+                    let (otherwise, again) = parse_block(tokens, Some(&kind))?;
+                    if again {
+                        return Err(TemplateError(format!(
+                            "Second {{{{else}}}} in {{{{#{}}}}}",
+                            kind
+                        )));
+                    }
+                    otherwise
+                } else {
+                    Vec::new()
+                };
+                nodes.push(match kind.as_str() {
+                    "each" => Node::Each {
+                        path,
+                        body,
+                        otherwise,
+                    },
+                    _ => Node::If {
+                        path,
+                        negate: kind == "unless",
+                        body,
+                        otherwise,
+                    },
+                });
+            }
+        }
+    }
+    match open {
+        Some(kind) => Err(TemplateError(format!("Missing {{{{/{}}}}}", kind))),
+        None => Ok((nodes, false)),
+    }
+}
+
+struct Scope<'a> {
+    value: &'a Value,
+    index: Option<usize>,
+}
+
+fn render_nodes<'d>(nodes: &[Node], scopes: &mut Vec<Scope<'d>>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => {
+                if let Some(value) = lookup(scopes, path) {
+                    out.push_str(&value_to_string(&value));
+                }
+            }
+            Node::Json(path) => {
+                let value = lookup(scopes, path).unwrap_or(Value::Null);
+                out.push_str(&value.to_string());
+            }
+            Node::If {
+                path,
+                negate,
+                body,
+                otherwise,
+            } => {
+                let truthy = lookup(scopes, path).is_some_and(|v| is_truthy(&v));
+                let branch = if truthy != *negate { body } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::Each {
+                path,
+                body,
+                otherwise,
+            } => {
+                let items = lookup_ref(scopes, path).and_then(|v| v.as_array());
+                match items {
+                    Some(items) if !items.is_empty() => {
+                        for (index, item) in items.iter().enumerate() {
+                            scopes.push(Scope {
+                                value: item,
+                                index: Some(index),
+                            });
+                            render_nodes(body, scopes, out);
+                            scopes.pop();
+                        }
+                    }
+                    _ => render_nodes(otherwise, scopes, out),
+                }
+            }
+        }
+    }
+}
+
+/// Resolve a path, also answering `@index`
+fn lookup(scopes: &[Scope], path: &str) -> Option<Value> {
+    if path == "@index" {
+        return scopes
+            .iter()
+            .rev()
+            .find_map(|s| s.index)
+            .map(|i| Value::from(i as u64));
+    }
+    lookup_ref(scopes, path).cloned()
+}
+
+/// Resolve a dotted path against the innermost scope that has its first segment
+fn lookup_ref<'a>(scopes: &[Scope<'a>], path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix("this.").unwrap_or(path);
+    let current = scopes.last()?.value;
+    if path == "this" || path == "." {
+        return Some(current);
+    }
+
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let mut value = scopes
+        .iter()
+        .rev()
+        .find_map(|scope| scope.value.get(first))?;
+    for segment in segments {
+        value = match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => value.get(segment)?,
+        };
+    }
+    Some(value)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(value_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let data = json!({
+            "title": "Weekly Sync",
+            "participants": ["Ana", "Ben"],
+            "segments": [
+                {"speaker": "Ana", "text": "Hello"},
+                {"speaker": null, "text": "Hi"}
+            ],
+            "tasks": [],
+            "summary": {"content": "All good"}
+        });
+
+        let template = "# {{title}}\n{{! a comment }}\
+            With {{participants}}\n\
+            {{#each segments}}{{@index}}. {{#if speaker}}{{speaker}}: {{/if}}{{text}} ({{title}})\n{{/each}}\
+            {{#each tasks}}- {{text}}{{else}}No tasks{{/each}}\n\
+            {{#unless missing}}{{summary.content}}{{/unless}} {{participants.1}}";
+        assert_eq!(
+            render(template, &data).unwrap(),
+            "# Weekly Sync\nWith Ana, Ben\n\
+             0. Ana: Hello (Weekly Sync)\n1. Hi (Weekly Sync)\n\
+             No tasks\nAll good Ben"
+        );
+
+        assert_eq!(
+            render(
+                r#"{"t": {{json title}}, "p": {{json participants}}, "m": {{json missing}}}"#,
+                &json!({"title": "A \"B\"", "participants": ["Ana"]})
+            )
+            .unwrap(),
+            r#"{"t": "A \"B\"", "p": ["Ana"], "m": null}"#
+        );
+
+        assert!(validate("{{#if title}}open").is_err());
+        assert!(validate("{{#each a}}{{/if}}").is_err());
+        assert!(validate("{{#with a}}{{/with}}").is_err());
+        assert!(validate("{{title").is_err());
+        assert!(validate("{{else}}").is_err());
+    }
+}
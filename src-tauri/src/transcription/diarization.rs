@@ -0,0 +1,457 @@
+//! Speaker diarization for transcripts of a single file (uploads, the merged playback
+//! file), where no separate mic and system recordings tell the voices apart.
+//!
+//! Each segment long enough to judge gets a voice print: the mean and spread of its
+//! mel-cepstrum over the frames that carry sound. The prints are clustered bottom-up with
+//! Ward's method, and another speaker is counted only while splitting one more cluster
+//! off explains a large share of how the prints differ and sets the two apart by more
+//! than a voice varies on its own. Shorter segments take the speaker of the nearest one
+//! that was judged. Speakers are numbered in the order they first speak.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use realfft::num_complex::Complex32;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use super::TranscriptionSegment;
+
+/// Rate of the audio Whisper is given, which is what gets diarized
+const SAMPLE_RATE: f64 = 16000.0;
+
+/// 25 ms frames every 10 ms
+const FRAME: usize = 400;
+const HOP: usize = 160;
+const FFT_SIZE: usize = 512;
+
+const MEL_BANDS: usize = 26;
+const LOWEST_HZ: f32 = 100.0;
+const HIGHEST_HZ: f32 = 7600.0;
+
+/// Cepstral coefficients kept, after the first (which is loudness rather than voice)
+const CEPSTRA: usize = 12;
+
+/// Voiced frames a segment needs for a print of its own: half a second
+const MIN_FRAMES: usize = 50;
+
+/// Frames this far below the loudest of their segment (power, 30 dB) are pauses
+const PAUSE_RATIO: f32 = 1e-3;
+
+/// Mean square below which a frame is silence, however quiet its segment
+const SILENCE_POWER: f32 = 1e-7;
+
+/// Most speakers told apart
+const MAX_SPEAKERS: usize = 8;
+
+/// Share of the prints' total spread a further speaker has to explain
+const MIN_SPLIT_GAIN: f64 = 0.3;
+
+/// Least distance between the mean prints of two speakers
+const MIN_SEPARATION: f64 = 1.0;
+
+/// A voice told apart in a file
+#[derive(Debug, Clone)]
+pub struct Voice {
+    /// `Speaker N`, the label its segments are given
+    pub label: String,
+    /// Mean voice print of its segments, for recognising it again (see `voices`)
+    pub print: Vec<f32>,
+}
+
+/// The voices heard in a file, when there is more than one
+#[derive(Debug, Clone)]
+pub struct Diarization {
+    /// Per segment, the label of its speaker
+    pub labels: Vec<String>,
+    /// In the order they first speak
+    pub voices: Vec<Voice>,
+}
+
+/// Tell apart the speakers of `segments` of 16kHz mono `samples`; None when one voice is
+/// heard
+pub fn diarize(samples: &[f32], segments: &[TranscriptionSegment]) -> Option<Diarization> {
+    let mut cepstrum = Cepstrum::new();
+    let prints: Vec<Option<Vec<f64>>> = segments
+        .iter()
+        .map(|segment| cepstrum.print(samples, segment))
+        .collect();
+    let judged: Vec<usize> = (0..segments.len())
+        .filter(|&i| prints[i].is_some())
+        .collect();
+    let points: Vec<Vec<f64>> = prints.into_iter().flatten().collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let groups = cluster(&points);
+    if groups.iter().all(|&group| group == groups[0]) {
+        return None;
+    }
+
+    let middle = |segment: &TranscriptionSegment| (segment.start_time + segment.end_time) / 2.0;
+    let mut order = Vec::new();
+    let labels = segments
+        .iter()
+        .map(|segment| {
+            let nearest = (0..judged.len())
+                .min_by(|&a, &b| {
+                    let distance =
+                        |i: usize| (middle(&segments[judged[i]]) - middle(segment)).abs();
+                    distance(a).total_cmp(&distance(b))
+                })
+                .unwrap_or(0);
+            let group = groups[nearest];
+            let number = order.iter().position(|&g| g == group).unwrap_or_else(|| {
+                order.push(group);
+                order.len() - 1
+            });
+            format!("Speaker {}", number + 1)
+        })
+        .collect();
+
+    let voices = order
+        .iter()
+        .enumerate()
+        .map(|(number, &group)| {
+            let members: Vec<&Vec<f64>> = points
+                .iter()
+                .zip(&groups)
+                .filter(|(_, g)| **g == group)
+                .map(|(point, _)| point)
+                .collect();
+            let print = (0..CEPSTRA * 2)
+                .map(|k| (members.iter().map(|m| m[k]).sum::<f64>() / members.len() as f64) as f32)
+                .collect();
+            Voice {
+                label: format!("Speaker {}", number + 1),
+                print,
+            }
+        })
+        .collect();
+    Some(Diarization { labels, voices })
+}
+
+/// Mel-frequency cepstral coefficients of 16kHz frames
+struct Cepstrum {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Per band, its first FFT bin and the weights from there
+    bands: Vec<(usize, Vec<f32>)>,
+    /// Per kept coefficient, the DCT row over the bands
+    dct: Vec<Vec<f32>>,
+    samples: Vec<f32>,
+    spectrum: Vec<Complex32>,
+}
+
+impl Cepstrum {
+    fn new() -> Self {
+        let window = (0..FRAME)
+            .map(|i| 0.54 - 0.46 * (2.0 * PI * i as f32 / (FRAME - 1) as f32).cos())
+            .collect();
+
+        let mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+        let hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+        let (low, high) = (mel(LOWEST_HZ), mel(HIGHEST_HZ));
+        let edges: Vec<f32> = (0..MEL_BANDS + 2)
+            .map(|i| hz(low + (high - low) * i as f32 / (MEL_BANDS + 1) as f32))
+            .map(|f| f * FFT_SIZE as f32 / SAMPLE_RATE as f32)
+            .collect();
+        let bands = edges
+            .windows(3)
+            .map(|edge| {
+                let (left, centre, right) = (edge[0], edge[1], edge[2]);
+                let first = left.ceil() as usize;
+                let weights = (first..=right.floor() as usize)
+                    .map(|bin| {
+                        let bin = bin as f32;
+                        if bin <= centre {
+                            (bin - left) / (centre - left)
+                        } else {
+                            (right - bin) / (right - centre)
+                        }
+                    })
+                    .collect();
+                (first, weights)
+            })
+            .collect();
+
+        let scale = (2.0 / MEL_BANDS as f32).sqrt();
+        let dct = (1..=CEPSTRA)
+            .map(|k| {
+                (0..MEL_BANDS)
+                    .map(|m| scale * (PI * k as f32 * (m as f32 + 0.5) / MEL_BANDS as f32).cos())
+                    .collect()
+            })
+            .collect();
+
+        let fft = RealFftPlanner::new().plan_fft_forward(FFT_SIZE);
+        Self {
+            window,
+            bands,
+            dct,
+            samples: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            fft,
+        }
+    }
+
+    /// The mean square of a frame and its coefficients
+    fn frame(&mut self, frame: &[f32]) -> (f32, [f32; CEPSTRA]) {
+        let power = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+
+        self.samples.fill(0.0);
+        let mut previous = frame[0];
+        for (i, &sample) in frame.iter().enumerate() {
+            // Pre-emphasis lifts the higher formants, where voices differ most
+            self.samples[i] = (sample - 0.97 * previous) * self.window[i];
+            previous = sample;
+        }
+        // The buffers come from the plan, which is all the forward transform checks
+        let _ = self.fft.process(&mut self.samples, &mut self.spectrum);
+
+        let energies: Vec<f32> = self
+            .bands
+            .iter()
+            .map(|(first, weights)| {
+                let energy: f32 = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| w * self.spectrum[first + i].norm_sqr())
+                    .sum();
+                (energy + 1e-10).ln()
+            })
+            .collect();
+        let mut coefficients = [0.0; CEPSTRA];
+        for (coefficient, row) in coefficients.iter_mut().zip(&self.dct) {
+            *coefficient = row.iter().zip(&energies).map(|(a, b)| a * b).sum();
+        }
+        (power, coefficients)
+    }
+
+    /// Mean and spread of the coefficients over the voiced frames of a segment, if it has
+    /// enough of them
+    fn print(&mut self, samples: &[f32], segment: &TranscriptionSegment) -> Option<Vec<f64>> {
+        let at = |seconds: f64| ((seconds.max(0.0) * SAMPLE_RATE) as usize).min(samples.len());
+        let (start, end) = (at(segment.start_time), at(segment.end_time));
+        let frames: Vec<(f32, [f32; CEPSTRA])> = (start..end.saturating_sub(FRAME))
+            .step_by(HOP)
+            .map(|pos| self.frame(&samples[pos..pos + FRAME]))
+            .collect();
+
+        let loudest = frames.iter().map(|(power, _)| *power).fold(0.0, f32::max);
+        let floor = (loudest * PAUSE_RATIO).max(SILENCE_POWER);
+        let voiced: Vec<&[f32; CEPSTRA]> = frames
+            .iter()
+            .filter(|(power, _)| *power >= floor)
+            .map(|(_, coefficients)| coefficients)
+            .collect();
+        if voiced.len() < MIN_FRAMES {
+            return None;
+        }
+
+        let count = voiced.len() as f64;
+        let mut print = vec![0.0; CEPSTRA * 2];
+        for k in 0..CEPSTRA {
+            let mean = voiced.iter().map(|c| c[k] as f64).sum::<f64>() / count;
+            let variance = voiced
+                .iter()
+                .map(|c| (c[k] as f64 - mean).powi(2))
+                .sum::<f64>()
+                / count;
+            print[k] = mean;
+            print[CEPSTRA + k] = variance.sqrt();
+        }
+        Some(print)
+    }
+}
+
+/// A cluster of voice prints
+struct Group {
+    centroid: Vec<f64>,
+    size: f64,
+}
+
+impl Group {
+    /// Increase in the within-cluster sum of squares from merging two clusters
+    fn merge_cost(&self, other: &Group) -> f64 {
+        self.size * other.size / (self.size + other.size)
+            * distance_sqr(&self.centroid, &other.centroid)
+    }
+}
+
+fn distance_sqr(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+/// Cluster of each point, merging the closest clusters (Ward's method) down to the number
+/// of speakers told apart
+fn cluster(points: &[Vec<f64>]) -> Vec<usize> {
+    let n = points.len();
+    let mut groups: Vec<Option<Group>> = points
+        .iter()
+        .map(|point| {
+            Some(Group {
+                centroid: point.clone(),
+                size: 1.0,
+            })
+        })
+        .collect();
+    let mut owner: Vec<usize> = (0..n).collect();
+
+    let mut costs = vec![f64::INFINITY; n * n];
+    for i in 0..n {
+        for j in i + 1..n {
+            costs[i * n + j] = groups[i]
+                .as_ref()
+                .unwrap()
+                .merge_cost(groups[j].as_ref().unwrap());
+        }
+    }
+
+    // Per number of clusters up to MAX_SPEAKERS: the clustering, its within-cluster sum of
+    // squares, and the distance between the two clusters merged to reach it from one more
+    let mut partitions: Vec<Vec<usize>> = vec![Vec::new(); MAX_SPEAKERS + 1];
+    let mut within = vec![0.0; MAX_SPEAKERS + 1];
+    let mut separation = vec![0.0; MAX_SPEAKERS + 2];
+    let mut total = 0.0;
+    let mut live = n;
+    if live <= MAX_SPEAKERS {
+        partitions[live] = owner.clone();
+    }
+
+    while live > 1 {
+        let (mut a, mut b) = (0, 0);
+        let mut best = f64::INFINITY;
+        for i in 0..n {
+            for j in i + 1..n {
+                if costs[i * n + j] < best {
+                    (a, b, best) = (i, j, costs[i * n + j]);
+                }
+            }
+        }
+
+        let merged = groups[b].take().unwrap();
+        let group = groups[a].as_mut().unwrap();
+        let apart = distance_sqr(&group.centroid, &merged.centroid).sqrt();
+        let size = group.size + merged.size;
+        for (c, m) in group.centroid.iter_mut().zip(&merged.centroid) {
+            *c = (*c * group.size + m * merged.size) / size;
+        }
+        group.size = size;
+        for o in owner.iter_mut().filter(|o| **o == b) {
+            *o = a;
+        }
+        total += best;
+        live -= 1;
+
+        for j in 0..n {
+            costs[j.min(b) * n + j.max(b)] = f64::INFINITY;
+            if j != a {
+                let cost = match (&groups[a], &groups[j]) {
+                    (Some(group), Some(other)) => group.merge_cost(other),
+                    _ => f64::INFINITY,
+                };
+                costs[j.min(a) * n + j.max(a)] = cost;
+            }
+        }
+
+        if live <= MAX_SPEAKERS {
+            partitions[live] = owner.clone();
+            within[live] = total;
+            separation[live + 1] = apart;
+        }
+    }
+
+    let most = n.min(MAX_SPEAKERS);
+    let mut count = 1;
+    while count < most
+        && total > 0.0
+        && (within[count] - within[count + 1]) / total >= MIN_SPLIT_GAIN
+        && separation[count + 1] >= MIN_SEPARATION
+    {
+        count += 1;
+    }
+    partitions[count].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A voiced sound: harmonics of `pitch` shaped by formants, over a little noise
+    fn voice(pitch: f32, formants: &[f32], seconds: f32, seed: u32) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(2654435761).max(1);
+        let harmonics: Vec<(f32, f32)> = (1..)
+            .map(|h| h as f32 * pitch)
+            .take_while(|f| *f < 7000.0)
+            .map(|f| {
+                let gain: f32 = formants
+                    .iter()
+                    .map(|formant| 1.0 / (1.0 + ((f - formant) / 150.0).powi(2)))
+                    .sum();
+                (f, gain)
+            })
+            .collect();
+        (0..(seconds * 16000.0) as usize)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let noise = (state as f32 / u32::MAX as f32 - 0.5) * 0.002;
+                let tone: f32 = harmonics
+                    .iter()
+                    .map(|(f, gain)| gain * (2.0 * PI * f * t).sin())
+                    .sum();
+                0.05 * tone + noise
+            })
+            .collect()
+    }
+
+    fn transcript(voices: Vec<Vec<f32>>) -> (Vec<f32>, Vec<TranscriptionSegment>) {
+        let mut samples = Vec::new();
+        let mut segments = Vec::new();
+        for voice in voices {
+            let start_time = samples.len() as f64 / 16000.0;
+            samples.extend(voice);
+            segments.push(TranscriptionSegment {
+                start_time,
+                end_time: samples.len() as f64 / 16000.0,
+                text: String::new(),
+                speaker: None,
+                words: Vec::new(),
+            });
+        }
+        (samples, segments)
+    }
+
+    #[test]
+    fn test_two_voices() {
+        let low = [500.0, 1500.0, 2500.0];
+        let high = [800.0, 2200.0, 3300.0];
+        let voices = (0..8)
+            .map(|i| match i % 2 {
+                0 => voice(120.0 + i as f32, &low, 1.5, i),
+                _ => voice(210.0 + i as f32, &high, 1.5, i),
+            })
+            .collect();
+        let (samples, segments) = transcript(voices);
+        let diarization = diarize(&samples, &segments).unwrap();
+        for (i, label) in diarization.labels.iter().enumerate() {
+            assert_eq!(label, &format!("Speaker {}", i % 2 + 1));
+        }
+        assert_eq!(diarization.voices.len(), 2);
+        assert_eq!(diarization.voices[1].label, "Speaker 2");
+        assert_eq!(diarization.voices[1].print.len(), CEPSTRA * 2);
+    }
+
+    #[test]
+    fn test_one_voice() {
+        let formants = [500.0, 1500.0, 2500.0];
+        let voices = (0..8)
+            .map(|i| voice(120.0 + (i % 3) as f32, &formants, 1.5, i))
+            .collect();
+        let (samples, segments) = transcript(voices);
+        assert!(diarize(&samples, &segments).is_none());
+    }
+}
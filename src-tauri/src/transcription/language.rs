@@ -0,0 +1,76 @@
+//! The language a note is transcribed in: its own (`notes.language`), else the
+//! "whisper_language" setting's. "auto" leaves Whisper to detect it, from the start of
+//! each file and afresh on every live pass, so a meeting that changes language is
+//! followed as it goes. The language a transcript came out in is stored on the note
+//! (`notes.detected_language`).
+//!
+//! A note may instead be translated as it is transcribed (`notes.translate`): its
+//! segments are then in English, each keeping the language spoken
+//! (`transcript_segments.original_language`).
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::{SharedModel, TranscriptionResult, Transcriber};
+use crate::db::Database;
+use crate::settings::SETTING_WHISPER_LANGUAGE;
+
+/// Leave Whisper to detect the language
+pub const AUTO: &str = "auto";
+
+/// A language Whisper transcribes
+#[derive(Debug, Clone, Serialize)]
+pub struct Language {
+    /// ISO 639-1 code, as stored
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+/// Every language Whisper knows
+pub fn all() -> Vec<Language> {
+    (0..=whisper_rs::get_lang_max_id())
+        .filter_map(|id| {
+            Some(Language {
+                code: whisper_rs::get_lang_str(id)?,
+                name: whisper_rs::get_lang_str_full(id)?,
+            })
+        })
+        .collect()
+}
+
+/// Whether `code` is "auto" or the code of a language Whisper knows
+pub fn is_valid(code: &str) -> bool {
+    code == AUTO || (!code.contains('\0') && whisper_rs::get_lang_id(code).is_some())
+}
+
+/// The language to transcribe `note_id` in; None to detect it
+pub fn for_note(db: &Database, note_id: &str) -> Option<String> {
+    db.get_note_language(note_id)
+        .ok()
+        .flatten()
+        .or_else(|| db.get_setting(SETTING_WHISPER_LANGUAGE).ok().flatten())
+        .filter(|code| code != AUTO && is_valid(code))
+}
+
+/// Whether `note_id` is translated into English as it is transcribed
+pub fn translates(db: &Database, note_id: &str) -> bool {
+    db.get_note_translate(note_id).unwrap_or(false)
+}
+
+/// A transcriber for `note_id`, in its language and translating if it asks to be
+pub fn transcriber(db: &Database, note_id: &str, model: Arc<SharedModel>) -> Transcriber {
+    Transcriber::new(model)
+        .with_language(for_note(db, note_id))
+        .with_translate(translates(db, note_id))
+}
+
+/// Store the language a transcript of `note_id` came out in
+pub fn record(db: &Database, note_id: &str, result: &TranscriptionResult) {
+    let Some(language) = result.language.as_deref() else {
+        return;
+    };
+    if let Err(e) = db.set_note_detected_language(note_id, language) {
+        tracing::warn!("Failed to store the language of {}: {}", note_id, e);
+    }
+}
@@ -1,19 +1,25 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use serde::Serialize;
 use tauri::{AppHandle, Emitter};
 use tokio::sync::Mutex;
 use tokio::time::interval;
 
-use crate::audio::{take_system_audio_samples, RecordingPhase, RecordingState};
-use crate::db::Database;
+use crate::audio::{
+    aec, take_system_audio_dropped, take_system_audio_samples, RecordingPhase, RecordingState,
+};
+use crate::db::{Database, NewSegmentRow};
+use crate::note_windows;
+use crate::power;
 use crate::transcription::{
-    is_echo_of_system, should_skip_segment, TranscriptionError, TranscriptionResult,
+    is_echo_of_system, should_skip_segment, SharedModel, TranscriptionError, TranscriptionResult,
     TranscriptionSegment,
 };
+use crate::transcription::{language, transcriber::detected_language, words};
 use tauri::Manager;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
+use whisper_rs::{FullParams, SamplingStrategy};
 
 /// Simple voice activity detection based on RMS energy
 /// Returns true if audio has enough energy to likely contain speech
@@ -27,9 +33,123 @@ fn has_voice_activity(samples: &[f32], threshold: f32) -> bool {
     rms > threshold
 }
 
+/// Seconds between live transcription passes at normal load
+const TICK_SECS: u64 = 3;
+
+/// Transcription slower than this fraction of realtime makes the load go up a level
+const OVERLOADED_RTF: f64 = 1.0;
+/// ...and this much headroom, for `RELAX_AFTER_PASSES` passes in a row, brings it down one
+const RELAXED_RTF: f64 = 0.5;
+const RELAX_AFTER_PASSES: u32 = 3;
+
+/// How live transcription copes when it can't keep up. Each level trades latency or
+/// coverage for staying current; audio that goes untranscribed is still in the recording
+/// and is picked up when the note is retranscribed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveLoadLevel {
+    /// A pass every tick
+    Normal,
+    /// Every other tick, so each pass transcribes twice the audio in one call
+    Batched,
+    /// Every third tick
+    HeavilyBatched,
+    /// Every third tick, and system audio is dropped so the mic (the user) keeps up
+    MicOnly,
+}
+
+impl LiveLoadLevel {
+    /// Ticks that make up one pass
+    pub fn ticks_per_pass(self) -> u32 {
+        match self {
+            Self::Normal => 1,
+            Self::Batched => 2,
+            Self::HeavilyBatched | Self::MicOnly => 3,
+        }
+    }
+
+    pub fn skips_system_audio(self) -> bool {
+        self == Self::MicOnly
+    }
+
+    fn up(self) -> Self {
+        match self {
+            Self::Normal => Self::Batched,
+            Self::Batched => Self::HeavilyBatched,
+            Self::HeavilyBatched | Self::MicOnly => Self::MicOnly,
+        }
+    }
+
+    fn down(self) -> Self {
+        match self {
+            Self::Normal | Self::Batched => Self::Normal,
+            Self::HeavilyBatched => Self::Batched,
+            Self::MicOnly => Self::HeavilyBatched,
+        }
+    }
+}
+
+/// Picks the load level from how long passes take relative to the audio they cover
+#[derive(Debug, Clone)]
+struct LoadGovernor {
+    level: LiveLoadLevel,
+    relaxed_passes: u32,
+}
+
+impl LoadGovernor {
+    fn new() -> Self {
+        Self {
+            level: LiveLoadLevel::Normal,
+            relaxed_passes: 0,
+        }
+    }
+
+    /// Record a pass that spent `elapsed_secs` on `audio_secs` of audio. Returns the new
+    /// level when it changed.
+    fn record(&mut self, elapsed_secs: f64, audio_secs: f64) -> Option<LiveLoadLevel> {
+        if audio_secs <= 0.0 {
+            return None;
+        }
+        let rtf = elapsed_secs / audio_secs;
+        let next = if rtf > OVERLOADED_RTF {
+            self.relaxed_passes = 0;
+            self.level.up()
+        } else if rtf < RELAXED_RTF {
+            self.relaxed_passes += 1;
+            if self.relaxed_passes >= RELAX_AFTER_PASSES {
+                self.relaxed_passes = 0;
+                self.level.down()
+            } else {
+                self.level
+            }
+        } else {
+            self.relaxed_passes = 0;
+            self.level
+        };
+        (next != self.level).then(|| {
+            self.level = next;
+            next
+        })
+    }
+}
+
+/// Payload of "live-transcription-degraded", emitted whenever the load level changes
+/// (including back to normal)
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveDegradedEvent {
+    pub note_id: String,
+    pub level: LiveLoadLevel,
+    /// Time spent transcribing / length of the audio, for the pass that changed the level
+    pub realtime_factor: f64,
+    pub interval_secs: u64,
+    pub skipping_system_audio: bool,
+}
+
 /// Live transcription state
 pub struct LiveTranscriptionState {
     pub is_running: AtomicBool,
+    /// A pass is transcribing audio it already took and hasn't saved the segments yet
+    pub pass_in_flight: AtomicBool,
     /// Offset in seconds for mic segment timestamps
     pub mic_time_offset: Mutex<f64>,
     /// Offset in seconds for system audio segment timestamps
@@ -44,6 +164,7 @@ impl LiveTranscriptionState {
     pub fn new() -> Self {
         Self {
             is_running: AtomicBool::new(false),
+            pass_in_flight: AtomicBool::new(false),
             mic_time_offset: Mutex::new(0.0),
             system_time_offset: Mutex::new(0.0),
             segments: Mutex::new(Vec::new()),
@@ -84,9 +205,10 @@ pub async fn start_live_transcription(
     app: AppHandle,
     note_id: String,
     language: Option<String>,
+    translate: bool,
     recording_state: Arc<RecordingState>,
     live_state: Arc<LiveTranscriptionState>,
-    whisper_ctx: Arc<WhisperContext>,
+    model: Arc<SharedModel>,
 ) -> Result<(), TranscriptionError> {
     if live_state.is_running.swap(true, Ordering::SeqCst) {
         return Err(TranscriptionError::AlreadyTranscribing);
@@ -97,18 +219,28 @@ pub async fn start_live_transcription(
     *live_state.system_time_offset.lock().await = 0.0;
     live_state.segments.lock().await.clear();
     live_state.recent_system_segments.lock().await.clear();
+    // Echo paths differ between sessions; live audio is 16kHz mono
+    aec::init_aec(16000);
 
     let app_clone = app.clone();
     let note_id_clone = note_id.clone();
     let language_clone = language.clone();
     let recording_state_clone = recording_state.clone();
     let live_state_clone = live_state.clone();
-    let whisper_ctx_clone = whisper_ctx.clone();
+    let model_clone = model.clone();
 
     // Spawn the live transcription task
     tokio::spawn(async move {
         let lang = language_clone;
-        let mut ticker = interval(Duration::from_secs(3));
+        let mut ticker = interval(Duration::from_secs(TICK_SECS));
+        let mut governor = LoadGovernor::new();
+        // Ticks skipped since the last pass; their audio waits for the next one
+        let mut skipped_ticks = 0;
+        // The language last stored on the note, which is stored again when it changes
+        let mut recorded_language: Option<String> = None;
+        // Only audio dropped while live transcription runs is worth a warning
+        recording_state_clone.audio_buffer.take_dropped();
+        take_system_audio_dropped();
 
         loop {
             ticker.tick().await;
@@ -125,9 +257,32 @@ pub async fn start_live_transcription(
                 break;
             }
 
+            // Under load, and with battery saver, passes are spread over several ticks
+            let mut ticks_per_pass = governor.level.ticks_per_pass();
+            if power::throttled() {
+                ticks_per_pass = ticks_per_pass.max(2);
+            }
+            if skipped_ticks + 1 < ticks_per_pass {
+                skipped_ticks += 1;
+                continue;
+            }
+            skipped_ticks = 0;
+            // Not entered (this task awaits); the trace times it from here to the end of the pass
+            let _pass = tracing::info_span!("live.pass", level = ?governor.level);
+            live_state_clone.pass_in_flight.store(true, Ordering::SeqCst);
+
             // Get audio buffers - both mic and system audio
             let mic_samples = recording_state_clone.take_audio_buffer();
             let system_samples = take_system_audio_samples();
+            let mic_dropped = recording_state_clone.audio_buffer.take_dropped();
+            let system_dropped = take_system_audio_dropped();
+            if mic_dropped > 0 || system_dropped > 0 {
+                tracing::warn!(
+                    "Live transcription fell behind: {} mic and {} system audio samples dropped",
+                    mic_dropped,
+                    system_dropped
+                );
+            }
 
             // Track how much audio (in seconds) each stream actually consumed this
             // pass, so the time offsets advance by real elapsed audio rather than by
@@ -159,21 +314,34 @@ pub async fn start_live_transcription(
                         mic_samples
                     };
 
+                    // Resample mic to 16kHz for Whisper
+                    let mut mic_16k = if rate != 16000 {
+                        resample(&mono_mic, rate, 16000)
+                    } else {
+                        mono_mic
+                    };
+
+                    // Take the speakers out before VAD, so echo alone doesn't pass for speech.
+                    // Silent passes go through too, keeping the filter adapted.
+                    if aec::is_aec_enabled() {
+                        mic_16k = aec::apply_aec(&mic_16k, &system_samples);
+                    }
+
                     // Only process if there's voice activity (RMS > 0.02)
                     // This filters out silence and low background noise
-                    if has_voice_activity(&mono_mic, 0.02) {
-                        // Resample mic to 16kHz for Whisper
-                        let mic_16k = if rate != 16000 {
-                            resample(&mono_mic, rate, 16000)
-                        } else {
-                            mono_mic
-                        };
-
+                    if has_voice_activity(&mic_16k, 0.02) {
                         audio_sources.push((mic_16k, 16000_u32, 1_usize, AudioSource::Mic));
                     }
                 }
             }
 
+            // Dropped when overloaded; the offset still advances so timestamps stay aligned
+            let system_samples = if governor.level.skips_system_audio() {
+                Vec::new()
+            } else {
+                system_samples
+            };
+
             // Extract mic audio data if available
             let mic_data = if let Some((samples, _, _, _)) = audio_sources
                 .iter()
@@ -193,19 +361,35 @@ pub async fn start_live_transcription(
                 None
             };
 
+            // Both run at once, so the pass covers the longer of the two
+            let transcribed_secs = [mic_data.as_ref(), system_data.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|(samples, _)| samples.len() as f64 / 16000.0)
+                .fold(0.0, f64::max);
+            let pass_started = Instant::now();
+
             // Process mic and system audio in PARALLEL
-            let whisper_ctx_mic = whisper_ctx_clone.clone();
-            let whisper_ctx_sys = whisper_ctx_clone.clone();
+            let model_mic = model_clone.clone();
+            let model_sys = model_clone.clone();
 
             let lang_mic = lang.clone();
             let lang_sys = lang.clone();
 
             let mic_future = async {
                 if let Some((samples, time_offset)) = mic_data {
-                    let ctx = whisper_ctx_mic;
+                    let model = model_mic;
                     let language = lang_mic;
                     tokio::task::spawn_blocking(move || {
-                        transcribe_samples(&ctx, &samples, 16000, 1, time_offset, language.as_deref())
+                        transcribe_samples(
+                            &model,
+                            &samples,
+                            16000,
+                            1,
+                            time_offset,
+                            language.as_deref(),
+                            translate,
+                        )
                     })
                     .await
                     .ok()
@@ -217,10 +401,18 @@ pub async fn start_live_transcription(
 
             let system_future = async {
                 if let Some((samples, time_offset)) = system_data {
-                    let ctx = whisper_ctx_sys;
+                    let model = model_sys;
                     let language = lang_sys;
                     tokio::task::spawn_blocking(move || {
-                        transcribe_samples(&ctx, &samples, 16000, 1, time_offset, language.as_deref())
+                        transcribe_samples(
+                            &model,
+                            &samples,
+                            16000,
+                            1,
+                            time_offset,
+                            language.as_deref(),
+                            translate,
+                        )
                     })
                     .await
                     .ok()
@@ -233,8 +425,35 @@ pub async fn start_live_transcription(
             // Run both transcriptions in parallel
             let (mic_result, system_result) = tokio::join!(mic_future, system_future);
 
+            if let Some(result) = mic_result.as_ref().or(system_result.as_ref())
+                && result.language != recorded_language
+            {
+                language::record(&app_clone.state::<Database>(), &note_id_clone, result);
+                recorded_language = result.language.clone();
+            }
+
+            let elapsed_secs = pass_started.elapsed().as_secs_f64();
+            if let Some(level) = governor.record(elapsed_secs, transcribed_secs) {
+                let realtime_factor = elapsed_secs / transcribed_secs;
+                tracing::info!(
+                    "Live transcription load: {:?} (realtime factor {:.2})",
+                    level,
+                    realtime_factor
+                );
+                let _ = app_clone.emit(
+                    "live-transcription-degraded",
+                    LiveDegradedEvent {
+                        note_id: note_id_clone.clone(),
+                        level,
+                        realtime_factor,
+                        interval_secs: TICK_SECS * level.ticks_per_pass() as u64,
+                        skipping_system_audio: level.skips_system_audio(),
+                    },
+                );
+            }
+
             // Collect all segments for batch DB insert
-            let mut db_segments: Vec<(String, f64, f64, String, Option<String>, Option<String>, Option<i64>)> = Vec::new();
+            let mut db_segments: Vec<NewSegmentRow> = Vec::new();
             let mut all_events: Vec<TranscriptionUpdateEvent> = Vec::new();
 
             // Process system results FIRST and update rolling history for echo detection
@@ -271,6 +490,7 @@ pub async fn start_live_transcription(
             // Process mic results with echo filtering
             if let Some(transcription) = mic_result {
                 if !transcription.segments.is_empty() {
+                    let original_language = transcription.original_language().map(str::to_string);
                     // Filter out blank segments AND echo duplicates
                     let valid_segments: Vec<_> = transcription
                         .segments
@@ -289,6 +509,8 @@ pub async fn start_live_transcription(
                                 Some("You".to_string()),
                                 Some("live".to_string()),
                                 None,
+                                original_language.clone(),
+                                segment.words.clone(),
                             ));
                         }
 
@@ -310,6 +532,10 @@ pub async fn start_live_transcription(
 
             // Now add system results to state and events (using already-filtered current_system_segments)
             if !current_system_segments.is_empty() {
+                let original_language = system_result
+                    .as_ref()
+                    .and_then(|result| result.original_language())
+                    .map(str::to_string);
                 for segment in &current_system_segments {
                     db_segments.push((
                         note_id_clone.clone(),
@@ -319,6 +545,8 @@ pub async fn start_live_transcription(
                         Some("Others".to_string()),
                         Some("live".to_string()),
                         None,
+                        original_language.clone(),
+                        segment.words.clone(),
                     ));
                 }
 
@@ -340,13 +568,18 @@ pub async fn start_live_transcription(
             if !db_segments.is_empty() {
                 let db = app_clone.state::<Database>();
                 if let Err(e) = db.add_transcript_segments_batch(&db_segments) {
-                    eprintln!("Failed to batch save transcript segments: {}", e);
+                    tracing::warn!("Failed to batch save transcript segments: {}", e);
                 }
             }
 
             // Emit all events
             for event in all_events {
-                let _ = app_clone.emit("transcription-update", event);
+                note_windows::emit_to_note(
+                    &app_clone,
+                    &note_id_clone,
+                    "transcription-update",
+                    event,
+                );
             }
 
             // Advance per-stream time offsets by the audio actually consumed this
@@ -359,6 +592,7 @@ pub async fn start_live_transcription(
             if system_consumed_secs > 0.0 {
                 *live_state_clone.system_time_offset.lock().await += system_consumed_secs;
             }
+            live_state_clone.pass_in_flight.store(false, Ordering::SeqCst);
         }
 
         live_state_clone.is_running.store(false, Ordering::SeqCst);
@@ -383,19 +617,24 @@ pub async fn stop_live_transcription(
     TranscriptionResult {
         segments,
         full_text,
-        language: Some("en".to_string()),
+        language: None,
+        translated: false,
+        voices: Vec::new(),
     }
 }
 
 /// Transcribe raw audio samples
 fn transcribe_samples(
-    ctx: &WhisperContext,
+    model: &SharedModel,
     samples: &[f32],
     sample_rate: u32,
     channels: usize,
     time_offset: f64,
     language: Option<&str>,
+    translate: bool,
 ) -> Result<TranscriptionResult, TranscriptionError> {
+    let _span = tracing::info_span!("whisper.transcribe_live", samples = samples.len()).entered();
+
     // Convert to mono if needed
     let mono_samples: Vec<f32> = if channels > 1 {
         samples
@@ -415,25 +654,20 @@ fn transcribe_samples(
     };
 
     // Create whisper state
-    let mut state = ctx
-        .create_state()
-        .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+    let mut state = model.create_state()?;
 
     // Set up transcription parameters
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
     params.set_language(language); // None = auto-detect
-    params.set_translate(false);
+    params.set_translate(translate);
     params.set_print_special(false);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
     params.set_token_timestamps(true);
-    params.set_n_threads(num_cpus());
 
     // Run transcription
-    state
-        .full(params, &resampled)
-        .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+    model.full(&mut state, params, &resampled)?;
 
     // Extract segments
     let num_segments = state
@@ -469,10 +703,13 @@ fn transcribe_samples(
             }
             full_text.push_str(&text);
 
+            let words = words::segment_words(&state, i, &text, time_offset);
             segments.push(TranscriptionSegment {
                 start_time,
                 end_time,
                 text,
+                speaker: None,
+                words,
             });
         }
     }
@@ -480,17 +717,14 @@ fn transcribe_samples(
     Ok(TranscriptionResult {
         segments,
         full_text,
-        language: language.map(|s| s.to_string()),
+        language: language
+            .map(str::to_string)
+            .or_else(|| detected_language(&state)),
+        translated: translate,
+        voices: Vec::new(),
     })
 }
 
-fn num_cpus() -> i32 {
-    std::thread::available_parallelism()
-        .map(|n| n.get() as i32)
-        .unwrap_or(4)
-        .min(8)
-}
-
 fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     let ratio = to_rate as f64 / from_rate as f64;
     let new_len = (samples.len() as f64 * ratio) as usize;
@@ -511,3 +745,32 @@ fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_governor() {
+        let mut governor = LoadGovernor::new();
+        assert_eq!(governor.record(1.0, 3.0), None);
+        assert_eq!(governor.record(5.0, 0.0), None);
+
+        // Falling behind steps up one level per slow pass, up to mic only
+        assert_eq!(governor.record(4.0, 3.0), Some(LiveLoadLevel::Batched));
+        assert_eq!(governor.record(7.0, 6.0), Some(LiveLoadLevel::HeavilyBatched));
+        assert_eq!(governor.record(10.0, 9.0), Some(LiveLoadLevel::MicOnly));
+        assert_eq!(governor.record(10.0, 9.0), None);
+        assert!(governor.level.skips_system_audio());
+
+        // Steps back down only after several fast passes in a row
+        assert_eq!(governor.record(1.0, 9.0), None);
+        assert_eq!(governor.record(1.0, 9.0), None);
+        assert_eq!(governor.record(6.0, 9.0), None);
+        assert_eq!(governor.record(1.0, 9.0), None);
+        assert_eq!(governor.record(1.0, 9.0), None);
+        assert_eq!(governor.record(1.0, 9.0), Some(LiveLoadLevel::HeavilyBatched));
+        assert_eq!(governor.level.ticks_per_pass(), 3);
+        assert!(!governor.level.skips_system_audio());
+    }
+}
@@ -1,9 +1,16 @@
+pub mod diarization;
+pub mod language;
 pub mod live;
 pub mod model;
+pub mod shared;
+pub mod threads;
 pub mod transcriber;
+pub mod voices;
+pub mod words;
 
 pub use live::{AudioSource, LiveTranscriptionState, TranscriptionUpdateEvent};
 pub use model::{ModelInfo, ModelManager, ModelSize};
+pub use shared::{ModelHost, SharedModel};
 pub use transcriber::{TranscriptionResult, TranscriptionSegment, Transcriber};
 
 /// Whether a transcript segment should be dropped rather than saved/displayed.
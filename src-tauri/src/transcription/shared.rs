@@ -0,0 +1,127 @@
+//! The loaded Whisper model, shared by file and live transcription. Loading a model reads
+//! its weights once into a single context, and every run creates its own state from it;
+//! states are independent, so a file can be transcribed while live transcription runs.
+//! Consumers hold an `Arc<SharedModel>` for as long as they need it, so loading another
+//! model or unloading never pulls the weights out from under a run: the old model is
+//! freed when its last consumer lets go. Runs that overlap split the thread budget.
+//!
+//! Models are read from the file as they are: whisper.cpp copies the weights into its own
+//! buffers while loading, so mapping the file instead would not save any memory.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use whisper_rs::{FullParams, WhisperContext, WhisperContextParameters, WhisperState};
+
+use super::{ModelSize, TranscriptionError, threads};
+
+/// Whisper runs in progress, across all consumers
+static ACTIVE_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a run as active until dropped, also when the run panics
+struct ActiveRun;
+
+impl ActiveRun {
+    /// Start a run; returns the guard and how many runs are now active
+    fn start() -> (Self, usize) {
+        (ActiveRun, ACTIVE_RUNS.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+}
+
+impl Drop for ActiveRun {
+    fn drop(&mut self) {
+        ACTIVE_RUNS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A loaded model that runs create their states from
+pub struct SharedModel {
+    pub size: ModelSize,
+    ctx: WhisperContext,
+}
+
+impl SharedModel {
+    pub fn create_state(&self) -> Result<WhisperState, TranscriptionError> {
+        self.ctx
+            .create_state()
+            .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))
+    }
+
+    /// Run Whisper over `samples` on `state`, sharing the thread budget with other runs
+    pub fn full(
+        &self,
+        state: &mut WhisperState,
+        mut params: FullParams,
+        samples: &[f32],
+    ) -> Result<(), TranscriptionError> {
+        let (_run, active) = ActiveRun::start();
+        params.set_n_threads(threads::thread_count(active));
+        threads::run(|| state.full(params, samples))
+            .map(|_| ())
+            .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))
+    }
+}
+
+/// Owns the loaded model and hands it out to consumers
+#[derive(Default)]
+pub struct ModelHost {
+    loaded: Mutex<Option<Arc<SharedModel>>>,
+}
+
+impl ModelHost {
+    fn slot(&self) -> std::sync::MutexGuard<'_, Option<Arc<SharedModel>>> {
+        self.loaded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Load the model at `path`, replacing the current one. Nothing happens when `size`
+    /// is already loaded.
+    pub fn load(&self, size: ModelSize, path: &Path) -> Result<(), TranscriptionError> {
+        if self.current() == Some(size) {
+            return Ok(());
+        }
+        if !path.exists() {
+            return Err(TranscriptionError::ModelNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        // Loading takes a while; don't hold the slot meanwhile
+        let ctx = WhisperContext::new_with_params(
+            &path.to_string_lossy(),
+            WhisperContextParameters::default(),
+        )
+        .map_err(|e| TranscriptionError::ModelLoadError(e.to_string()))?;
+        *self.slot() = Some(Arc::new(SharedModel { size, ctx }));
+        Ok(())
+    }
+
+    /// Let go of the loaded model; it is freed once no run holds it anymore
+    pub fn unload(&self) {
+        *self.slot() = None;
+    }
+
+    /// Unload the model if it is `size` (e.g. before deleting its file)
+    pub fn unload_if(&self, size: ModelSize) {
+        let mut slot = self.slot();
+        if slot.as_ref().is_some_and(|model| model.size == size) {
+            *slot = None;
+        }
+    }
+
+    pub fn current(&self) -> Option<ModelSize> {
+        self.slot().as_ref().map(|model| model.size)
+    }
+
+    /// The loaded model, for a consumer to hold while it transcribes
+    pub fn get(&self) -> Option<Arc<SharedModel>> {
+        self.slot().clone()
+    }
+
+    /// Consumers holding the loaded model right now
+    pub fn consumers(&self) -> usize {
+        self.slot()
+            .as_ref()
+            .map_or(0, |model| Arc::strong_count(model) - 1)
+    }
+}
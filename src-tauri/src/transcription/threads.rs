@@ -0,0 +1,174 @@
+//! How much of the machine Whisper may use, for live and file transcription alike. Runs
+//! share a budget of threads: the configured maximum, or by default half the cores (at most
+//! 8) so the rest of the system stays responsive. With the "background" priority each run
+//! happens on a thread the OS schedules after interactive work:
+//!   macOS: the utility QoS class, which Whisper's worker threads inherit
+//!   Linux: nice 10, which Whisper's worker threads inherit
+//!   Windows: below-normal priority for the thread driving the run
+//! The run gets a thread of its own because a lowered priority can't always be raised
+//! again (Linux needs a privilege for that), and blocking-pool threads are reused.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::db::Database;
+
+/// Maximum Whisper threads; 0 picks automatically
+pub const SETTING_MAX_THREADS: &str = "transcription_max_threads";
+/// One of `PRIORITIES`
+pub const SETTING_PRIORITY: &str = "transcription_priority";
+pub const PRIORITIES: &[&str] = &["normal", "background"];
+
+/// Upper bound for the automatic thread count
+const AUTO_MAX_THREADS: usize = 8;
+
+static MAX_THREADS: AtomicUsize = AtomicUsize::new(0);
+static BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+fn apply(key: &str, value: Option<&Value>) {
+    if key == SETTING_MAX_THREADS {
+        let max = value.and_then(Value::as_u64).unwrap_or(0) as usize;
+        MAX_THREADS.store(max, Ordering::Relaxed);
+    } else if key == SETTING_PRIORITY {
+        let background = value.and_then(Value::as_str) == Some("background");
+        BACKGROUND.store(background, Ordering::Relaxed);
+    }
+}
+
+/// Load both settings and follow changes to them. Call once the database is managed.
+pub fn init(app: &AppHandle) {
+    let db = app.state::<Database>();
+    if let Some(max) = db.get_setting(SETTING_MAX_THREADS).ok().flatten() {
+        apply(
+            SETTING_MAX_THREADS,
+            max.parse::<u64>().ok().map(Value::from).as_ref(),
+        );
+    }
+    if let Some(priority) = db.get_setting(SETTING_PRIORITY).ok().flatten() {
+        apply(SETTING_PRIORITY, Some(&Value::String(priority)));
+    }
+
+    #[derive(Deserialize)]
+    struct Changed {
+        key: String,
+        value: Option<Value>,
+    }
+    app.listen("settings-changed", |event| {
+        if let Ok(Changed { key, value }) = serde_json::from_str(event.payload()) {
+            apply(&key, value.as_ref());
+        }
+    });
+}
+
+/// Threads for each of `runs` simultaneous runs given the configured maximum
+/// (0 = automatic) and the core count
+fn threads_for(max: usize, cores: usize, runs: usize) -> usize {
+    let cores = cores.max(1);
+    let budget = if max == 0 {
+        (cores / 2).clamp(1, AUTO_MAX_THREADS)
+    } else {
+        max.min(cores)
+    };
+    (budget / runs.max(1)).max(1)
+}
+
+/// Threads to hand to Whisper for one of `runs` runs happening at once
+pub fn thread_count(runs: usize) -> i32 {
+    let cores = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4);
+    threads_for(MAX_THREADS.load(Ordering::Relaxed), cores, runs) as i32
+}
+
+/// Run a Whisper call at the configured priority
+pub fn run<T: Send>(job: impl FnOnce() -> T + Send) -> T {
+    if !BACKGROUND.load(Ordering::Relaxed) {
+        return job();
+    }
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                platform::lower_priority();
+                job()
+            })
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    pub fn lower_priority() {
+        let result =
+            unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_UTILITY, 0) };
+        if result != 0 {
+            tracing::debug!("Failed to lower the transcription QoS class: {}", result);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL,
+    };
+
+    pub fn lower_priority() {
+        if unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL) } == 0 {
+            tracing::debug!("Failed to lower the transcription thread priority");
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    /// Niceness for background runs (0 is normal, 19 the lowest priority)
+    const BACKGROUND_NICE: libc::c_int = 10;
+
+    pub fn lower_priority() {
+        // With PRIO_PROCESS, a thread id sets the niceness of just that thread
+        let result = unsafe {
+            libc::setpriority(
+                libc::PRIO_PROCESS,
+                libc::gettid() as libc::id_t,
+                BACKGROUND_NICE,
+            )
+        };
+        if result != 0 {
+            tracing::debug!(
+                "Failed to lower the transcription thread priority: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+mod platform {
+    pub fn lower_priority() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threads_for() {
+        // Automatic: half the cores, between 1 and 8
+        assert_eq!(threads_for(0, 8, 1), 4);
+        assert_eq!(threads_for(0, 1, 1), 1);
+        assert_eq!(threads_for(0, 32, 1), 8);
+        // Configured: never more than there are cores
+        assert_eq!(threads_for(2, 8, 1), 2);
+        assert_eq!(threads_for(16, 8, 1), 8);
+        assert_eq!(threads_for(3, 0, 1), 1);
+        // Simultaneous runs split the budget, keeping at least one thread each
+        assert_eq!(threads_for(0, 16, 2), 4);
+        assert_eq!(threads_for(3, 8, 2), 1);
+        assert_eq!(threads_for(1, 8, 3), 1);
+    }
+}
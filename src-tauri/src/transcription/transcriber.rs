@@ -1,9 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use std::sync::Arc;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperState};
 
-use super::TranscriptionError;
+use super::diarization::{self, Voice};
+use super::{words, SharedModel, TranscriptionError};
+use crate::audio::converter;
+use crate::audio::encoder::RecordingFormat;
+use crate::db::models::Word;
 
 /// A segment of transcribed text with timestamps
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +15,12 @@ pub struct TranscriptionSegment {
     pub start_time: f64,
     pub end_time: f64,
     pub text: String,
+    /// Who spoke it, when the file was diarized and more than one voice was heard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// Its words, timed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
 }
 
 /// Result of a transcription
@@ -19,53 +29,76 @@ pub struct TranscriptionResult {
     pub segments: Vec<TranscriptionSegment>,
     pub full_text: String,
     pub language: Option<String>,
+    /// Whether the text is the English translation of `language`
+    #[serde(default)]
+    pub translated: bool,
+    /// The speakers told apart, when the file was diarized
+    #[serde(skip)]
+    pub voices: Vec<Voice>,
 }
 
-/// Transcriber for audio files using Whisper
+impl TranscriptionResult {
+    /// The language spoken, when the text is its translation into English
+    pub fn original_language(&self) -> Option<&str> {
+        self.language
+            .as_deref()
+            .filter(|language| self.translated && *language != "en")
+    }
+}
+
+/// Transcriber for audio files using the shared Whisper model
+#[derive(Clone)]
 pub struct Transcriber {
-    ctx: WhisperContext,
-    is_transcribing: AtomicBool,
+    model: Arc<SharedModel>,
+    /// None to detect it
+    language: Option<String>,
+    /// Translate into English
+    translate: bool,
 }
 
 impl Transcriber {
-    /// Create a new transcriber with the specified model
-    pub fn new(model_path: &Path) -> Result<Self, TranscriptionError> {
-        if !model_path.exists() {
-            return Err(TranscriptionError::ModelNotFound(
-                model_path.to_string_lossy().to_string(),
-            ));
+    /// Create a transcriber that holds `model` until it is dropped, and detects the language
+    pub fn new(model: Arc<SharedModel>) -> Self {
+        Self {
+            model,
+            language: None,
+            translate: false,
         }
+    }
 
-        let ctx = WhisperContext::new_with_params(
-            model_path.to_str().unwrap(),
-            WhisperContextParameters::default(),
-        )
-        .map_err(|e| TranscriptionError::ModelLoadError(e.to_string()))?;
-
-        Ok(Self {
-            ctx,
-            is_transcribing: AtomicBool::new(false),
-        })
+    /// Transcribe in `language` (see `language::for_note`); None to detect it
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
     }
 
-    /// Check if currently transcribing
-    #[allow(dead_code)]
-    pub fn is_transcribing(&self) -> bool {
-        self.is_transcribing.load(Ordering::SeqCst)
+    /// Translate what is said into English rather than transcribe it as spoken
+    pub fn with_translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
     }
 
     /// Transcribe an audio file
     pub fn transcribe(&self, audio_path: &Path) -> Result<TranscriptionResult, TranscriptionError> {
-        if self.is_transcribing.swap(true, Ordering::SeqCst) {
-            return Err(TranscriptionError::AlreadyTranscribing);
-        }
+        self.run(audio_path, false)
+    }
 
-        let result = self.transcribe_internal(audio_path);
-        self.is_transcribing.store(false, Ordering::SeqCst);
-        result
+    /// Transcribe an audio file of several voices, labelling each segment with its speaker
+    /// when more than one is heard
+    pub fn transcribe_speakers(
+        &self,
+        audio_path: &Path,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        self.run(audio_path, true)
     }
 
-    fn transcribe_internal(&self, audio_path: &Path) -> Result<TranscriptionResult, TranscriptionError> {
+    fn run(
+        &self,
+        audio_path: &Path,
+        diarize: bool,
+    ) -> Result<TranscriptionResult, TranscriptionError> {
+        let _span = tracing::info_span!("whisper.transcribe", path = %audio_path.display()).entered();
+
         if !audio_path.exists() {
             return Err(TranscriptionError::AudioNotFound(
                 audio_path.to_string_lossy().to_string(),
@@ -76,28 +109,22 @@ impl Transcriber {
         let samples = self.load_audio(audio_path)?;
 
         // Create whisper state
-        let mut state = self
-            .ctx
-            .create_state()
-            .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+        let mut state = self.model.create_state()?;
 
         // Set up transcription parameters
         let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
 
         // Configure for better meeting transcription
-        params.set_language(Some("en")); // Default to English, can be made configurable
-        params.set_translate(false);
+        params.set_language(self.language.as_deref()); // None = auto-detect
+        params.set_translate(self.translate);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
         params.set_token_timestamps(true);
-        params.set_n_threads(num_cpus());
 
         // Run the transcription
-        state
-            .full(params, &samples)
-            .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+        self.model.full(&mut state, params, &samples)?;
 
         // Extract segments
         let num_segments = state.full_n_segments().map_err(|e| {
@@ -127,23 +154,50 @@ impl Transcriber {
                 }
                 full_text.push_str(&text);
 
+                let words = words::segment_words(&state, i, &text, 0.0);
                 segments.push(TranscriptionSegment {
                     start_time,
                     end_time,
                     text,
+                    speaker: None,
+                    words,
                 });
             }
         }
 
+        let language = self.language.clone().or_else(|| detected_language(&state));
+
+        let mut voices = Vec::new();
+        if diarize && let Some(diarization) = diarization::diarize(&samples, &segments) {
+            for (segment, label) in segments.iter_mut().zip(diarization.labels) {
+                segment.speaker = Some(label);
+            }
+            voices = diarization.voices;
+        }
+
         Ok(TranscriptionResult {
             segments,
             full_text,
-            language: Some("en".to_string()),
+            language,
+            translated: self.translate,
+            voices,
         })
     }
 
     /// Load audio file and convert to 16kHz mono f32 samples
     fn load_audio(&self, audio_path: &Path) -> Result<Vec<f32>, TranscriptionError> {
+        // Compressed recordings are decoded whole; WAV is read directly
+        if RecordingFormat::of_path(audio_path) != Some(RecordingFormat::Wav) {
+            let decoded = converter::decode(audio_path).map_err(|e| {
+                TranscriptionError::TranscriptionFailed(format!("Failed to decode audio: {}", e))
+            })?;
+            return Ok(to_whisper_input(
+                decoded.samples,
+                decoded.sample_rate,
+                decoded.channels as usize,
+            ));
+        }
+
         let reader = hound::WavReader::open(audio_path)
             .map_err(|e| TranscriptionError::TranscriptionFailed(format!("Failed to open WAV: {}", e)))?;
 
@@ -170,34 +224,35 @@ impl Transcriber {
             }
         };
 
-        // Convert to mono if stereo
-        let mono_samples: Vec<f32> = if channels > 1 {
-            samples
-                .chunks(channels)
-                .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
-                .collect()
-        } else {
-            samples
-        };
-
-        // Resample to 16kHz if needed (Whisper requires 16kHz)
-        let target_rate = 16000;
-        let resampled = if sample_rate != target_rate {
-            resample(&mono_samples, sample_rate, target_rate)
-        } else {
-            mono_samples
-        };
-
-        Ok(resampled)
+        Ok(to_whisper_input(samples, sample_rate, channels))
     }
 }
 
-/// Get the number of CPU threads to use
-fn num_cpus() -> i32 {
-    std::thread::available_parallelism()
-        .map(|n| n.get() as i32)
-        .unwrap_or(4)
-        .min(8) // Cap at 8 threads for transcription
+/// The language Whisper detected in the audio `state` transcribed
+pub(crate) fn detected_language(state: &WhisperState) -> Option<String> {
+    let id = state.full_lang_id_from_state().ok()?;
+    whisper_rs::get_lang_str(id).map(str::to_string)
+}
+
+/// Mix interleaved samples down to mono and resample them to the 16kHz Whisper expects
+fn to_whisper_input(samples: Vec<f32>, sample_rate: u32, channels: usize) -> Vec<f32> {
+    // Convert to mono if stereo
+    let mono_samples: Vec<f32> = if channels > 1 {
+        samples
+            .chunks(channels)
+            .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
+            .collect()
+    } else {
+        samples
+    };
+
+    // Resample to 16kHz if needed (Whisper requires 16kHz)
+    let target_rate = 16000;
+    if sample_rate != target_rate {
+        resample(&mono_samples, sample_rate, target_rate)
+    } else {
+        mono_samples
+    }
 }
 
 /// Simple linear resampling (for basic use; a proper resampler would be better for production)
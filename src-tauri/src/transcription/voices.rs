@@ -0,0 +1,148 @@
+//! Voice profiles: speakers named once are named again in later transcripts.
+//!
+//! Every voice diarization tells apart is kept with its print (`speaker_embeddings`), for
+//! the user to name with `label_speaker`; its print then joins the profile of that name.
+//! Voices of later transcripts whose print is within `MATCH_DISTANCE` of a profile's take
+//! its name, the closest pairs first and each profile at most once per transcript.
+
+use std::collections::HashMap;
+
+use super::diarization::Voice;
+use super::TranscriptionResult;
+use crate::db::models::Speaker;
+use crate::db::Database;
+
+/// Farthest a voice's print may be from a profile's and still be taken for it
+const MATCH_DISTANCE: f64 = 1.0;
+
+/// Name the diarized voices of a note's transcript after the profiles they match, keep
+/// them for naming later, and relabel the segments to suit. `source` is the (type, id) the
+/// segments are saved with. Call before saving them; the voices kept from an earlier
+/// transcript of the same source are replaced.
+pub fn identify(
+    db: &Database,
+    note_id: &str,
+    source: Option<(&str, i64)>,
+    result: &mut TranscriptionResult,
+) {
+    let (source_type, source_id) = source.unzip();
+    if let Err(e) = db.delete_speaker_embeddings_by_source(note_id, source_type, source_id) {
+        tracing::warn!("Failed to clear the voices of {}: {}", note_id, e);
+    }
+    if result.voices.is_empty() {
+        return;
+    }
+
+    let profiles = db.list_speakers().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load voice profiles: {}", e);
+        Vec::new()
+    });
+    let matches = match_profiles(&result.voices, &profiles);
+
+    let mut names = HashMap::new();
+    for (voice, matched) in result.voices.iter().zip(matches) {
+        let profile = matched.map(|i| &profiles[i]);
+        let name = profile.map_or(&voice.label, |profile| &profile.name);
+        let speaker_id = profile.map(|profile| profile.id);
+        let kept = db.add_speaker_embedding(
+            note_id,
+            name,
+            &voice.print,
+            speaker_id,
+            source_type,
+            source_id,
+        );
+        if let Err(e) = kept {
+            tracing::warn!("Failed to keep a voice of {}: {}", note_id, e);
+        }
+        names.insert(voice.label.clone(), name.clone());
+    }
+    for segment in result.segments.iter_mut() {
+        if let Some(name) = segment.speaker.as_ref().and_then(|label| names.get(label)) {
+            segment.speaker = Some(name.clone());
+        }
+    }
+}
+
+/// Per voice, the profile it is taken for
+fn match_profiles(voices: &[Voice], profiles: &[Speaker]) -> Vec<Option<usize>> {
+    let mut pairs = Vec::new();
+    for (v, voice) in voices.iter().enumerate() {
+        for (p, profile) in profiles.iter().enumerate() {
+            if profile.embedding.len() != voice.print.len() {
+                continue;
+            }
+            let distance = distance(&voice.print, &profile.embedding);
+            if distance <= MATCH_DISTANCE {
+                pairs.push((distance, v, p));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut matched = vec![None; voices.len()];
+    let mut taken = vec![false; profiles.len()];
+    for (_, v, p) in pairs {
+        if matched[v].is_none() && !taken[p] {
+            matched[v] = Some(p);
+            taken[p] = true;
+        }
+    }
+    matched
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (*x as f64 - *y as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn voice(print: Vec<f32>) -> Voice {
+        Voice {
+            label: String::new(),
+            print,
+        }
+    }
+
+    fn profile(embedding: Vec<f32>) -> Speaker {
+        Speaker {
+            id: 0,
+            name: String::new(),
+            embedding,
+            sample_count: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_closest_pairs_first() {
+        let voices = [
+            voice(vec![0.0, 0.0]),
+            voice(vec![0.5, 0.0]),
+            voice(vec![5.0, 5.0]),
+        ];
+        let profiles = [profile(vec![0.4, 0.0]), profile(vec![0.0, 0.6])];
+        // The second voice is closest to the first profile, so the first voice takes the
+        // second; the third is near neither
+        assert_eq!(
+            match_profiles(&voices, &profiles),
+            vec![Some(1), Some(0), None]
+        );
+    }
+
+    #[test]
+    fn test_each_profile_once() {
+        let voices = [voice(vec![0.0, 0.0]), voice(vec![0.1, 0.0])];
+        let profiles = [profile(vec![0.0, 0.0]), profile(vec![0.0])];
+        assert_eq!(match_profiles(&voices, &profiles), vec![Some(0), None]);
+    }
+}
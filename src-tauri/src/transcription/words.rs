@@ -0,0 +1,126 @@
+//! Word timings: Whisper's token timestamps joined into the words of each segment.
+//!
+//! A token starting with a space starts a word; the rest carry on the word before. Tokens
+//! may split a character in two, leaving their text garbled, so the words take the
+//! segment's text instead whenever it splits into as many words.
+
+use whisper_rs::WhisperState;
+
+use crate::db::models::Word;
+
+/// The words of `segment` of a finished run, timed from `time_offset` seconds. `text` is
+/// the segment's text.
+pub fn segment_words(
+    state: &WhisperState,
+    segment: i32,
+    text: &str,
+    time_offset: f64,
+) -> Vec<Word> {
+    let count = state.full_n_tokens(segment).unwrap_or(0);
+    let tokens: Vec<(String, f64, f64)> = (0..count)
+        .filter_map(|token| {
+            let data = state.full_get_token_data(segment, token).ok()?;
+            let text = state.full_get_token_text_lossy(segment, token).ok()?;
+            // Token timestamps are in centiseconds
+            let start = data.t0 as f64 / 100.0 + time_offset;
+            let end = data.t1 as f64 / 100.0 + time_offset;
+            Some((text, start, end))
+        })
+        .collect();
+    join_tokens(&tokens, text)
+}
+
+/// Join (text, start, end) tokens into words
+fn join_tokens(tokens: &[(String, f64, f64)], text: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut starts_word = true;
+    for (token, start, end) in tokens {
+        if is_special(token) {
+            continue;
+        }
+        starts_word |= token.starts_with(char::is_whitespace);
+        let piece = token.trim();
+        if piece.is_empty() {
+            continue;
+        }
+        match words.last_mut() {
+            Some(word) if !starts_word => {
+                word.text.push_str(piece);
+                word.end_time = word.end_time.max(*end);
+            }
+            _ => words.push(Word {
+                text: piece.to_string(),
+                start_time: *start,
+                end_time: end.max(*start),
+            }),
+        }
+        starts_word = token.ends_with(char::is_whitespace);
+    }
+
+    let spoken: Vec<&str> = text.split_whitespace().collect();
+    if spoken.len() == words.len() {
+        for (word, text) in words.iter_mut().zip(spoken) {
+            word.text = text.to_string();
+        }
+    }
+    words
+}
+
+/// Whether a token is one of Whisper's own ("[_BEG_]", "[_TT_150]", ...) rather than text
+fn is_special(token: &str) -> bool {
+    token.starts_with("[_") && token.ends_with(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokens: &[(&str, f64, f64)]) -> Vec<(String, f64, f64)> {
+        tokens
+            .iter()
+            .map(|(text, start, end)| (text.to_string(), *start, *end))
+            .collect()
+    }
+
+    #[test]
+    fn test_joins_tokens_into_words() {
+        let tokens = tokens(&[
+            ("[_BEG_]", 0.0, 0.0),
+            (" Hel", 0.1, 0.3),
+            ("lo", 0.3, 0.5),
+            (",", 0.5, 0.5),
+            (" world", 0.6, 1.0),
+            ("[_TT_100]", 1.0, 1.0),
+        ]);
+        let words = join_tokens(&tokens, "Hello, world");
+        assert_eq!(
+            words,
+            vec![
+                Word {
+                    text: "Hello,".to_string(),
+                    start_time: 0.1,
+                    end_time: 0.5,
+                },
+                Word {
+                    text: "world".to_string(),
+                    start_time: 0.6,
+                    end_time: 1.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_takes_text_of_segment() {
+        // "é" split across two tokens, each garbled on its own
+        let tokens = tokens(&[
+            (" caf", 0.0, 0.2),
+            ("\u{FFFD}", 0.2, 0.3),
+            ("\u{FFFD}", 0.3, 0.4),
+        ]);
+        let words = join_tokens(&tokens, "café");
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].text, "café");
+        assert_eq!((words[0].start_time, words[0].end_time), (0.0, 0.4));
+    }
+}
@@ -0,0 +1,485 @@
+//! System tray menu and icon. The menu is rebuilt from `TrayStatus` whenever an update
+//! becomes available or a recording starts/stops; while recording, a status line at the
+//! top shows the note title and elapsed time, and the icon carries a red dot (on macOS
+//! the elapsed time is also shown as the tray title, since the icon is a template image).
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::{
+    AppHandle, Emitter, Listener, Manager, Wry,
+    image::Image,
+    menu::{Menu, MenuItem, PredefinedMenuItem, Submenu},
+};
+
+use crate::audio::RecordingPhase;
+use crate::commands::{self, AudioState};
+use crate::db::Database;
+use crate::notifications;
+use crate::shortcuts;
+
+pub const TRAY_ID: &str = "main-tray";
+
+/// Menu id prefix of the "Recent Notes" entries, followed by the note id
+pub const RECENT_NOTE_PREFIX: &str = "recent_note:";
+const RECENT_NOTES_LIMIT: usize = 10;
+const RECENT_NOTE_TITLE_LEN: usize = 40;
+
+/// Set while a debounced menu rebuild is scheduled
+static REFRESH_PENDING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecordingIndicator {
+    pub title: String,
+    pub paused: bool,
+    pub elapsed: Duration,
+}
+
+#[derive(Default)]
+struct TrayStatus {
+    /// Version of an available update ("new" when unknown)
+    update: Option<String>,
+    recording: Option<RecordingIndicator>,
+    /// The recording status line, kept so the elapsed time can be updated in place
+    status_item: Option<MenuItem<Wry>>,
+}
+
+static STATUS: Mutex<TrayStatus> = Mutex::new(TrayStatus {
+    update: None,
+    recording: None,
+    status_item: None,
+});
+
+/// The recording shown in the tray, as of the monitor's last poll
+pub(crate) fn recording_indicator() -> Option<RecordingIndicator> {
+    STATUS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .recording
+        .clone()
+}
+
+/// Build the tray menu for the current status
+pub fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    // Menu items are created on the main thread, so don't hold the lock meanwhile
+    let (update, recording) = {
+        let status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+        (status.update.clone(), status.recording.clone())
+    };
+    let menu = Menu::new(app)?;
+
+    let mut status_item = None;
+    if let Some(recording) = &recording {
+        let item = MenuItem::with_id(
+            app,
+            "recording_status",
+            recording_label(recording),
+            false,
+            None::<&str>,
+        )?;
+        menu.append(&item)?;
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+        status_item = Some(item);
+    }
+    STATUS.lock().unwrap_or_else(|e| e.into_inner()).status_item = status_item;
+
+    if let Some(version) = &update {
+        let install_update = MenuItem::with_id(
+            app,
+            "install_update",
+            format!("Install Update (v{})", version),
+            true,
+            None::<&str>,
+        )?;
+        menu.append(&install_update)?;
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+
+    let record = if recording.is_some() {
+        shortcuts::menu_item(
+            app,
+            "stop_recording",
+            "Stop Recording",
+            true,
+            "tray.toggle_recording",
+        )?
+    } else {
+        shortcuts::menu_item(
+            app,
+            "start_recording",
+            "Start Recording",
+            true,
+            "tray.toggle_recording",
+        )?
+    };
+    menu.append(&record)?;
+
+    let recent = Submenu::with_id(app, "recent_notes", "Recent Notes", true)?;
+    let notes = recent_notes(app);
+    if notes.is_empty() {
+        recent.append(&MenuItem::with_id(
+            app,
+            "recent_notes_empty",
+            "No notes yet",
+            false,
+            None::<&str>,
+        )?)?;
+    }
+    for (id, title) in notes {
+        recent.append(&MenuItem::with_id(
+            app,
+            format!("{}{}", RECENT_NOTE_PREFIX, id),
+            menu_title(&title),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    menu.append(&recent)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+
+    let open = shortcuts::menu_item(app, "open", "Open", true, "tray.open")?;
+    let new_note = shortcuts::menu_item(app, "new_note", "New Note", true, "tray.new_note")?;
+    let quick_note =
+        shortcuts::menu_item(app, "quick_note", "Quick Note…", true, "tray.quick_note")?;
+    let quick_note_clipboard = MenuItem::with_id(
+        app,
+        "quick_note_clipboard",
+        "Quick Note from Clipboard",
+        true,
+        None::<&str>,
+    )?;
+    let settings = shortcuts::menu_item(app, "settings", "Settings", true, "tray.settings")?;
+    let exit = MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)?;
+    menu.append(&open)?;
+    menu.append(&new_note)?;
+    menu.append(&quick_note)?;
+    menu.append(&quick_note_clipboard)?;
+    menu.append(&settings)?;
+    menu.append(&exit)?;
+
+    Ok(menu)
+}
+
+/// The tray icon for the current status
+pub fn icon() -> tauri::Result<Image<'static>> {
+    let (update, recording) = {
+        let status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+        (status.update.is_some(), status.recording.is_some())
+    };
+
+    // Windows: use colored icon.png (visible on both dark/light taskbars)
+    // macOS: use template icons for automatic dark/light adaptation
+    #[cfg(target_os = "windows")]
+    let icon = Image::from_bytes(include_bytes!("../icons/icon.png"))?;
+
+    #[cfg(not(target_os = "windows"))]
+    let icon = if update {
+        Image::from_bytes(include_bytes!("../icons/icon_tray_update.png"))?
+    } else {
+        Image::from_bytes(include_bytes!("../icons/icon_tray.png"))?
+    };
+
+    // A template image is drawn in a single colour, so macOS shows recording in the title
+    if recording && !cfg!(target_os = "macos") {
+        return Ok(with_recording_dot(&icon));
+    }
+    Ok(icon)
+}
+
+/// Rebuild the menu and icon
+pub(crate) fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Ok(menu) = build_menu(app) {
+        let _ = tray.set_menu(Some(menu));
+    }
+    if let Ok(icon) = icon() {
+        let _ = tray.set_icon(Some(icon));
+        // Re-apply template mode for proper dark/light mode support on macOS
+        #[cfg(target_os = "macos")]
+        let _ = tray.set_icon_as_template(true);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let recording = STATUS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .recording
+            .clone();
+        let _ = tray.set_title(recording.as_ref().map(recording_title));
+    }
+}
+
+/// Updates the system tray icon and menu based on update availability
+pub fn set_update_available(app: &AppHandle, available: bool, version: Option<String>) {
+    {
+        let mut status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+        status.update = available.then(|| version.unwrap_or_else(|| "new".to_string()));
+    }
+    refresh(app);
+}
+
+fn set_recording(app: &AppHandle, recording: Option<RecordingIndicator>) {
+    let (structural_change, status_item) = {
+        let mut status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = match (&status.recording, &recording) {
+            (Some(old), Some(new)) => old.title != new.title || old.paused != new.paused,
+            (None, None) => false,
+            _ => true,
+        };
+        status.recording = recording.clone();
+        (changed, status.status_item.clone())
+    };
+
+    if structural_change {
+        refresh(app);
+    } else {
+        // Only the elapsed time moved; update the line without rebuilding the menu
+        // (rebuilding closes the menu if it's open)
+        if let (Some(item), Some(recording)) = (status_item, &recording) {
+            let _ = item.set_text(recording_label(recording));
+        }
+        #[cfg(target_os = "macos")]
+        if let (Some(tray), Some(recording)) = (app.tray_by_id(TRAY_ID), &recording) {
+            let _ = tray.set_title(Some(recording_title(recording)));
+        }
+    }
+}
+
+/// "Start Recording": record into a new note without opening the main window
+pub fn start_recording(app: &AppHandle) {
+    let app = app.clone();
+    // Off the main thread: starting capture and creating the note can block
+    thread::spawn(move || {
+        if let Err(e) = commands::start_recording_in_new_note(&app) {
+            tracing::warn!("Failed to start recording: {}", e);
+            notifications::notify("Recording failed", &e);
+        }
+    });
+}
+
+/// "Stop Recording": stop whatever is recording and end its note
+pub fn stop_recording(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || match commands::stop_active_recording(&app) {
+        Ok(Some(stopped)) => {
+            let _ = app.emit("tray-recording-stopped", stopped);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!("Failed to stop recording: {}", e);
+            notifications::notify("Recording failed", &e);
+        }
+    });
+}
+
+/// A "Recent Notes" entry: show the main window on that note
+pub fn open_recent_note(app: &AppHandle, note_id: &str) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = window.emit("tray-open-note", note_id);
+    }
+}
+
+/// Keep "Recent Notes" current as notes are created, renamed and deleted
+pub fn watch_notes(app: &AppHandle) {
+    for event in ["note-created", "note-updated", "note-deleted"] {
+        let app_handle = app.clone();
+        app.listen(event, move |_| schedule_refresh(&app_handle));
+    }
+}
+
+/// Rebuild the menu shortly, coalescing bursts of note events (e.g. autosave). Also keeps
+/// the rebuild off the emitting thread, which may still hold the database lock.
+fn schedule_refresh(app: &AppHandle) {
+    if REFRESH_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        REFRESH_PENDING.store(false, Ordering::SeqCst);
+        refresh(&app);
+    });
+}
+
+fn recent_notes(app: &AppHandle) -> Vec<(String, String)> {
+    let db = app.state::<Database>();
+    let Ok(conn) = db.conn.lock() else {
+        return Vec::new();
+    };
+    let Ok(mut stmt) =
+        conn.prepare("SELECT id, title FROM notes ORDER BY updated_at DESC LIMIT ?1")
+    else {
+        return Vec::new();
+    };
+    stmt.query_map([RECENT_NOTES_LIMIT as i64], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })
+    .map(|rows| rows.filter_map(|r| r.ok()).collect())
+    .unwrap_or_default()
+}
+
+fn menu_title(title: &str) -> String {
+    let title = title.trim();
+    if title.is_empty() {
+        return "Untitled".to_string();
+    }
+    if title.chars().count() > RECENT_NOTE_TITLE_LEN {
+        let truncated: String = title.chars().take(RECENT_NOTE_TITLE_LEN - 1).collect();
+        return format!("{}…", truncated.trim_end());
+    }
+    title.to_string()
+}
+
+/// Watch the recording state and keep the tray indicator current. Every recording mode
+/// (mic, dual, listen-only) goes through `RecordingState`'s phase, so polling it covers them all.
+pub fn start_recording_monitor(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        // (note id, time recorded before the current run, when the current run started)
+        let mut session: Option<(Option<String>, Duration, Option<Instant>)> = None;
+        let mut title = String::new();
+
+        loop {
+            thread::sleep(Duration::from_secs(1));
+
+            let state = app.state::<AudioState>();
+            let recording = &state.recording;
+            let phase = recording.get_phase();
+            if phase == RecordingPhase::Idle {
+                if session.take().is_some() {
+                    title.clear();
+                    set_recording(&app, None);
+                }
+                continue;
+            }
+
+            let note_id = recording
+                .current_note_id
+                .lock()
+                .ok()
+                .and_then(|id| id.clone());
+            let session = session.get_or_insert_with(|| {
+                // Continued recordings start from the note's existing duration
+                let offset = recording
+                    .segment_start_offset_ms
+                    .load(std::sync::atomic::Ordering::SeqCst);
+                (None, Duration::from_millis(offset.max(0) as u64), None)
+            });
+            if session.0 != note_id || title.is_empty() {
+                title = note_id
+                    .as_deref()
+                    .and_then(|id| note_title(&app, id))
+                    .unwrap_or_else(|| "Untitled".to_string());
+                session.0 = note_id;
+            }
+
+            match (phase, session.2) {
+                (RecordingPhase::Recording, None) => session.2 = Some(Instant::now()),
+                (RecordingPhase::Paused, Some(started)) => {
+                    session.1 += started.elapsed();
+                    session.2 = None;
+                }
+                _ => {}
+            }
+
+            set_recording(
+                &app,
+                Some(RecordingIndicator {
+                    title: title.clone(),
+                    paused: phase == RecordingPhase::Paused,
+                    elapsed: session.1 + session.2.map(|s| s.elapsed()).unwrap_or_default(),
+                }),
+            );
+        }
+    });
+}
+
+fn note_title(app: &AppHandle, note_id: &str) -> Option<String> {
+    let db = app.state::<Database>();
+    let conn = db.conn.lock().ok()?;
+    conn.query_row("SELECT title FROM notes WHERE id = ?1", [note_id], |row| {
+        row.get(0)
+    })
+    .ok()
+}
+
+fn recording_label(recording: &RecordingIndicator) -> String {
+    let state = if recording.paused {
+        "Paused"
+    } else {
+        "● Recording"
+    };
+    format!(
+        "{} · {} · {}",
+        state,
+        recording.title,
+        format_elapsed(recording.elapsed)
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn recording_title(recording: &RecordingIndicator) -> String {
+    let state = if recording.paused { "❚❚" } else { "●" };
+    format!("{} {}", state, format_elapsed(recording.elapsed))
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Copy of `icon` with a red dot in the bottom-right corner
+fn with_recording_dot(icon: &Image<'_>) -> Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+
+    let radius = (width.min(height) as f32) * 0.22;
+    let (cx, cy) = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance <= radius + 1.0 {
+                let i = ((y * width + x) * 4) as usize;
+                // A one-pixel transparent ring keeps the dot readable over the glyph
+                let pixel = if distance <= radius {
+                    [0xE5, 0x39, 0x35, 0xFF]
+                } else {
+                    [0, 0, 0, 0]
+                };
+                rgba[i..i + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+
+    Image::new_owned(rgba, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_title() {
+        assert_eq!(menu_title("  "), "Untitled");
+        assert_eq!(menu_title("Standup"), "Standup");
+        let long = "a".repeat(60);
+        assert_eq!(menu_title(&long).chars().count(), RECENT_NOTE_TITLE_LEN);
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(65)), "01:05");
+        assert_eq!(format_elapsed(Duration::from_secs(3725)), "1:02:05");
+    }
+}
@@ -0,0 +1,276 @@
+//! Update policy on top of the updater plugin: which channel to follow, whether to download
+//! in the background, and versions the user chose to skip. A scheduler checks the selected
+//! channel periodically; what it finds is announced with "update-available" (and in the tray),
+//! and with auto-download on the package is fetched ahead of time so `install_update` only
+//! has to apply it.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::db::Database;
+use crate::power;
+use crate::settings;
+use crate::shutdown;
+use crate::tray;
+
+/// "stable" or "beta"
+pub const SETTING_CHANNEL: &str = "update_channel";
+/// Download updates in the background as soon as they are found
+pub const SETTING_AUTO_DOWNLOAD: &str = "update_auto_download";
+/// Version the user chose to skip; newer versions are still offered
+pub const SETTING_SKIPPED_VERSION: &str = "update_skipped_version";
+/// Hours between background checks; "0" disables them
+pub const SETTING_CHECK_INTERVAL_HOURS: &str = "update_check_interval_hours";
+/// RFC 3339 time of the last background check
+pub const SETTING_LAST_CHECK: &str = "update_last_check_at";
+
+pub const CHANNELS: &[&str] = &["stable", "beta"];
+
+const STABLE_ENDPOINT: &str =
+    "https://github.com/ZapYap-com/note67/releases/latest/download/latest.json";
+/// Beta builds publish their manifest to a rolling "beta" release
+const BETA_ENDPOINT: &str =
+    "https://github.com/ZapYap-com/note67/releases/download/beta/latest.json";
+
+/// How often the scheduler checks whether a background check is due
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Give startup some room before the first check
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+/// The update found by the last check, with its package once downloaded
+struct PendingUpdate {
+    update: Update,
+    bytes: Option<Vec<u8>>,
+}
+
+static PENDING: Mutex<Option<PendingUpdate>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePolicy {
+    pub channel: String,
+    pub auto_download: bool,
+    pub skipped_version: Option<String>,
+    /// Hours between background checks; 0 disables them
+    pub check_interval_hours: u32,
+    #[serde(default)]
+    pub last_check_at: Option<String>,
+}
+
+/// Payload of "update-available" and the result of `check_for_updates_now`
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: String,
+    pub body: Option<String>,
+    pub date: Option<String>,
+    /// The package is already downloaded and `install_update` will not hit the network
+    pub downloaded: bool,
+}
+
+fn load_policy(db: &Database) -> UpdatePolicy {
+    let get = |key: &str| {
+        db.get_setting(key)
+            .ok()
+            .flatten()
+            .or_else(|| settings::find(key).and_then(|def| def.default.map(String::from)))
+            .unwrap_or_default()
+    };
+    UpdatePolicy {
+        channel: get(SETTING_CHANNEL),
+        auto_download: get(SETTING_AUTO_DOWNLOAD) == "true",
+        skipped_version: Some(get(SETTING_SKIPPED_VERSION)).filter(|v| !v.is_empty()),
+        check_interval_hours: get(SETTING_CHECK_INTERVAL_HOURS).parse().unwrap_or(0),
+        last_check_at: db.get_setting(SETTING_LAST_CHECK).ok().flatten(),
+    }
+}
+
+/// Check the policy's channel. Stable ignores pre-releases; the skipped version is never
+/// reported.
+async fn check(app: &AppHandle, policy: &UpdatePolicy) -> Result<Option<Update>, String> {
+    let beta = policy.channel == "beta";
+    let endpoint = if beta { BETA_ENDPOINT } else { STABLE_ENDPOINT };
+    let skipped = policy.skipped_version.clone();
+
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![
+            endpoint.parse::<tauri::Url>().map_err(|e| e.to_string())?,
+        ])
+        .map_err(|e| e.to_string())?
+        .version_comparator(move |current, release| {
+            release.version > current
+                && (beta || release.version.pre.is_empty())
+                && skipped.as_deref() != Some(release.version.to_string().as_str())
+        })
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    updater.check().await.map_err(|e| e.to_string())
+}
+
+fn info(update: &Update, channel: &str, downloaded: bool) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel: channel.to_string(),
+        body: update.body.clone(),
+        date: update.date.map(|d| d.to_string()),
+        downloaded,
+    }
+}
+
+/// Check now and remember the result; downloads it too when `download` is set
+async fn check_and_store(
+    app: &AppHandle,
+    policy: &UpdatePolicy,
+    download: bool,
+) -> Result<Option<UpdateInfo>, String> {
+    let Some(update) = check(app, policy).await? else {
+        *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        tray::set_update_available(app, false, None);
+        return Ok(None);
+    };
+
+    // Keep a package already downloaded for the same version
+    let already = PENDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .filter(|p| p.update.version == update.version)
+        .and_then(|p| p.bytes.clone());
+    let bytes = match already {
+        Some(bytes) => Some(bytes),
+        None if download => match update.download(|_, _| {}, || {}).await {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                tracing::warn!("Failed to download {}: {}", update.version, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let info = info(&update, &policy.channel, bytes.is_some());
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some(PendingUpdate { update, bytes });
+    tray::set_update_available(app, true, Some(info.version.clone()));
+    let _ = app.emit("update-available", info.clone());
+    Ok(Some(info))
+}
+
+/// Check in the background as often as the policy asks
+pub fn start_update_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            let policy = app.try_state::<Database>().map(|db| load_policy(&db));
+            if let Some(policy) = policy
+                && is_due(&policy)
+            {
+                let download = policy.auto_download && !power::defer(&app, "update_download");
+                if let Err(e) = check_and_store(&app, &policy, download).await {
+                    tracing::warn!("Scheduled check failed: {}", e);
+                }
+                // Record the attempt either way so an offline machine doesn't retry every tick
+                if let Some(db) = app.try_state::<Database>() {
+                    let _ = db.set_setting(SETTING_LAST_CHECK, &Utc::now().to_rfc3339());
+                }
+            }
+            tokio::time::sleep(SCHEDULER_INTERVAL).await;
+        }
+    });
+}
+
+fn is_due(policy: &UpdatePolicy) -> bool {
+    let last = policy
+        .last_check_at
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok());
+    policy.check_interval_hours > 0
+        && last.is_none_or(|t| {
+            Utc::now().signed_duration_since(t)
+                >= chrono::Duration::hours(policy.check_interval_hours.into())
+        })
+}
+
+#[tauri::command]
+pub fn get_update_policy(db: State<'_, Database>) -> UpdatePolicy {
+    load_policy(&db)
+}
+
+#[tauri::command]
+pub fn set_update_policy(app: AppHandle, policy: UpdatePolicy) -> Result<(), String> {
+    let set =
+        |key: &str, value: String| settings::set(&app, key, &value).map_err(|e| e.to_string());
+    set(SETTING_CHANNEL, policy.channel)?;
+    set(SETTING_AUTO_DOWNLOAD, policy.auto_download.to_string())?;
+    set(
+        SETTING_SKIPPED_VERSION,
+        policy.skipped_version.unwrap_or_default(),
+    )?;
+    set(
+        SETTING_CHECK_INTERVAL_HOURS,
+        policy.check_interval_hours.to_string(),
+    )
+}
+
+/// Check the selected channel right away. Downloads in the background afterwards when
+/// auto-download is on, so the result comes back without waiting for the package.
+#[tauri::command]
+pub async fn check_for_updates_now(app: AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let policy = load_policy(&app.state::<Database>());
+    let found = check_and_store(&app, &policy, false).await?;
+
+    if policy.auto_download && found.as_ref().is_some_and(|info| !info.downloaded) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = check_and_store(&app, &policy, true).await {
+                tracing::warn!("Background download failed: {}", e);
+            }
+        });
+    }
+    Ok(found)
+}
+
+/// Skip a version: it is no longer offered, but newer ones are
+#[tauri::command]
+pub fn skip_update_version(app: AppHandle, version: String) -> Result<(), String> {
+    settings::set(&app, SETTING_SKIPPED_VERSION, &version).map_err(|e| e.to_string())?;
+
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    if pending
+        .as_ref()
+        .is_some_and(|p| p.update.version == version)
+    {
+        *pending = None;
+        drop(pending);
+        tray::set_update_available(&app, false, None);
+    }
+    Ok(())
+}
+
+/// Install the update found by the last check (downloading it first if needed) and restart
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<(), String> {
+    let pending = PENDING.lock().unwrap_or_else(|e| e.into_inner()).take();
+    let Some(PendingUpdate { update, bytes }) = pending else {
+        return Err("No update available".to_string());
+    };
+
+    let bytes = match bytes {
+        Some(bytes) => bytes,
+        None => update
+            .download(|_, _| {}, || {})
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    update.install(bytes).map_err(|e| e.to_string())?;
+    shutdown::prepare(&app);
+    app.restart()
+}
@@ -0,0 +1,336 @@
+//! Outgoing webhooks fired on app events (note ended, transcription completed, summary generated)
+//! Deliveries run in the background with exponential backoff and are logged in `webhook_deliveries`
+//! Signing secrets are kept in the OS keychain, one entry per webhook
+//!
+//! The body is the JSON envelope `{"event", "timestamp", "data"}` unless the webhook has a
+//! payload template, which is rendered with `templates` against the envelope plus a `flat`
+//! object (envelope fields and data fields at one level, nested keys joined with `_`), e.g.
+//! `{"text": {{json data.title}}, "fields": {{json flat}}}`.
+
+use std::time::Duration;
+
+use ring::hmac;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::db::Database;
+use crate::db::models::Webhook;
+use crate::{secrets, templates};
+
+pub const EVENT_NOTE_ENDED: &str = "note.ended";
+pub const EVENT_TRANSCRIPTION_COMPLETED: &str = "transcription.completed";
+pub const EVENT_SUMMARY_GENERATED: &str = "summary.generated";
+
+/// All events a webhook can subscribe to
+pub const EVENTS: &[&str] = &[
+    EVENT_NOTE_ENDED,
+    EVENT_TRANSCRIPTION_COMPLETED,
+    EVENT_SUMMARY_GENERATED,
+];
+
+/// Total attempts per delivery (1 initial + retries at 1s, 2s, 4s, 8s)
+const MAX_ATTEMPTS: i64 = 5;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keychain entry holding a webhook's signing secret
+fn secret_key(id: i64) -> String {
+    format!("webhook_secret_{}", id)
+}
+
+/// A webhook's signing secret, if it has one
+pub fn get_secret(id: i64) -> Result<Option<String>, String> {
+    Ok(secrets::get_secret(&secret_key(id))?.filter(|s| !s.is_empty()))
+}
+
+/// Store a webhook's signing secret; an empty one removes it
+pub fn set_secret(id: i64, secret: &str) -> Result<(), String> {
+    match secret {
+        "" => secrets::delete_secret(&secret_key(id)),
+        secret => secrets::set_secret(&secret_key(id), secret),
+    }
+}
+
+/// Move signing secrets earlier versions kept in the webhooks table into the keychain
+pub fn migrate_secrets(db: &Database) -> Result<(), String> {
+    for (id, secret) in db.get_stored_webhook_secrets().map_err(|e| e.to_string())? {
+        set_secret(id, &secret)?;
+        db.clear_stored_webhook_secret(id).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Fire `event` to every enabled webhook subscribed to it. Returns immediately;
+/// deliveries happen on the async runtime.
+pub fn dispatch(app: &AppHandle, event: &str, data: serde_json::Value) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+
+    let hooks = match db.get_webhooks_for_event(event) {
+        Ok(hooks) => hooks,
+        Err(e) => {
+            tracing::warn!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+    if hooks.is_empty() {
+        return;
+    }
+
+    let envelope = envelope(event, data);
+    for hook in hooks {
+        let app = app.clone();
+        let event = event.to_string();
+        let payload = build_payload(&hook, &envelope);
+        tauri::async_runtime::spawn(async move {
+            match payload {
+                Ok(payload) => {
+                    deliver(&app, &hook, &event, &payload, MAX_ATTEMPTS, false).await;
+                }
+                Err(e) => log_template_failure(&app, &hook, &event, &e),
+            }
+        });
+    }
+}
+
+/// Send a sample `event` payload to a webhook once, without retries.
+/// Returns the id of the logged delivery.
+pub async fn test_fire(app: &AppHandle, hook: &Webhook, event: &str) -> Result<i64, String> {
+    let payload = build_payload(hook, &envelope(event, sample_data(event)))?;
+    deliver(app, hook, event, &payload, 1, true)
+        .await
+        .ok_or_else(|| "Failed to log the test delivery".to_string())
+}
+
+/// Render a payload template against a sample `event`, for previews in settings
+pub fn preview_payload(template: Option<&str>, event: &str) -> Result<String, String> {
+    let envelope = envelope(event, sample_data(event));
+    match template.filter(|t| !t.trim().is_empty()) {
+        Some(template) => render_payload(template, &envelope),
+        None => serde_json::to_string_pretty(&envelope).map_err(|e| e.to_string()),
+    }
+}
+
+/// Data resembling what `event` carries, for test deliveries and previews
+pub fn sample_data(event: &str) -> Value {
+    match event {
+        EVENT_NOTE_ENDED => serde_json::json!({
+            "note_id": "00000000-0000-0000-0000-000000000000",
+            "title": "Weekly Sync",
+            "ended_at": chrono::Utc::now().to_rfc3339(),
+        }),
+        EVENT_TRANSCRIPTION_COMPLETED => serde_json::json!({
+            "note_id": "00000000-0000-0000-0000-000000000000",
+            "segment_count": 42,
+        }),
+        EVENT_SUMMARY_GENERATED => serde_json::json!({
+            "note_id": "00000000-0000-0000-0000-000000000000",
+            "summary_id": 1,
+            "summary_type": "overview",
+            "content": "The team agreed to ship the release on Friday.",
+        }),
+        _ => Value::Object(Map::new()),
+    }
+}
+
+fn envelope(event: &str, data: Value) -> Value {
+    serde_json::json!({
+        "event": event,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    })
+}
+
+fn build_payload(hook: &Webhook, envelope: &Value) -> Result<String, String> {
+    match hook.payload_template.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(template) => render_payload(template, envelope),
+        None => Ok(envelope.to_string()),
+    }
+}
+
+/// Render a payload template. The output must be valid JSON since it is sent as such.
+pub fn render_payload(template: &str, envelope: &Value) -> Result<String, String> {
+    let mut flat = Map::new();
+    for key in ["event", "timestamp"] {
+        if let Some(value) = envelope.get(key) {
+            flat.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(data) = envelope.get("data") {
+        flatten_into(&mut flat, "", data);
+    }
+
+    let mut context = envelope.clone();
+    if let Value::Object(map) = &mut context {
+        map.insert("flat".to_string(), Value::Object(flat));
+    }
+
+    let body = templates::render(template, &context).map_err(|e| e.to_string())?;
+    serde_json::from_str::<Value>(&body)
+        .map_err(|e| format!("Payload template does not produce valid JSON: {}", e))?;
+    Ok(body)
+}
+
+/// Copy nested object fields into `out` with their keys joined by `_`
+fn flatten_into(out: &mut Map<String, Value>, prefix: &str, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}_{}", prefix, key)
+                };
+                flatten_into(out, &key, value);
+            }
+        }
+        _ if !prefix.is_empty() => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+        _ => {}
+    }
+}
+
+/// Log a delivery that could not be built because the template failed to render
+fn log_template_failure(app: &AppHandle, hook: &Webhook, event: &str, error: &str) {
+    // Not the URL: it often carries a token
+    tracing::warn!("Payload template for webhook {} failed: {}", hook.id, error);
+    let db = app.state::<Database>();
+    if let Ok(id) = db.add_webhook_delivery(hook.id, event, "") {
+        let _ = db.update_webhook_delivery(id, None, false, 0, Some(error));
+    }
+}
+
+/// Deliver one payload to one webhook, retrying with exponential backoff on failure.
+/// Returns the id of the logged delivery.
+async fn deliver(
+    app: &AppHandle,
+    hook: &Webhook,
+    event: &str,
+    payload: &str,
+    max_attempts: i64,
+    test: bool,
+) -> Option<i64> {
+    let db = app.state::<Database>();
+    let delivery_id = match db.add_webhook_delivery(hook.id, event, payload) {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::warn!("Failed to log delivery: {}", e);
+            return None;
+        }
+    };
+
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            let _ = db.update_webhook_delivery(delivery_id, None, false, 0, Some(&e.to_string()));
+            return Some(delivery_id);
+        }
+    };
+    // Unsigned requests would be turned away by a receiver that checks, so don't send any
+    let secret = match get_secret(hook.id) {
+        Ok(secret) => secret,
+        Err(e) => {
+            let _ = db.update_webhook_delivery(delivery_id, None, false, 0, Some(&e));
+            return Some(delivery_id);
+        }
+    };
+
+    for attempt in 1..=max_attempts {
+        let mut request = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", "Note67-Webhooks")
+            .header("X-Note67-Event", event)
+            .header("X-Note67-Delivery", delivery_id.to_string())
+            .body(payload.to_string());
+
+        if test {
+            request = request.header("X-Note67-Test", "true");
+        }
+        if let Some(secret) = secret.as_deref() {
+            request = request.header("X-Note67-Signature", sign_payload(secret, payload));
+        }
+
+        let (status_code, error) = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                let status = response.status().as_u16() as i64;
+                let _ = db.update_webhook_delivery(delivery_id, Some(status), true, attempt, None);
+                return Some(delivery_id);
+            }
+            Ok(response) => {
+                let status = response.status();
+                (Some(status.as_u16() as i64), format!("HTTP {}", status))
+            }
+            Err(e) => (None, e.to_string()),
+        };
+
+        let _ = db.update_webhook_delivery(delivery_id, status_code, false, attempt, Some(&error));
+
+        // Client errors (other than rate limiting) won't succeed on retry
+        if let Some(code) = status_code
+            && (400..500).contains(&code)
+            && code != 408
+            && code != 429
+        {
+            break;
+        }
+
+        if attempt < max_attempts {
+            tokio::time::sleep(Duration::from_secs(1 << (attempt - 1))).await;
+        }
+    }
+
+    tracing::warn!("Delivery {} to webhook {} failed", delivery_id, hook.id);
+    Some(delivery_id)
+}
+
+/// HMAC-SHA256 signature of the request body, sent as "sha256=<hex>"
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, payload.as_bytes());
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        let signature = sign_payload("Jefe", "what do ya want for nothing?");
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_render_payload() {
+        let envelope = serde_json::json!({
+            "event": "note.ended",
+            "timestamp": "2026-01-15T10:00:00+00:00",
+            "data": {"note_id": "n1", "title": "Sync \"Q1\"", "meta": {"tags": ["a"]}},
+        });
+        let body = render_payload(r#"{"text": {{json data.title}}, "fields": {{json flat}}}"#, &envelope).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            serde_json::json!({
+                "text": "Sync \"Q1\"",
+                "fields": {
+                    "event": "note.ended",
+                    "timestamp": "2026-01-15T10:00:00+00:00",
+                    "note_id": "n1",
+                    "title": "Sync \"Q1\"",
+                    "meta_tags": ["a"],
+                },
+            })
+        );
+
+        // Unquoted string values would break the JSON
+        assert!(render_payload(r#"{"text": {{data.title}}}"#, &envelope).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Remember where windows were. Size, position and maximized state are saved per window
+//! label (`window_state:<label>` in settings) shortly after the window stops moving or
+//! resizing, and put back when the window is created. Note windows share one entry, since
+//! their labels carry the note id. A saved position that is no longer on any monitor (a
+//! display was unplugged) is ignored and only the size is restored.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, WindowEvent,
+};
+
+use crate::db::Database;
+use crate::note_windows;
+
+const KEY_PREFIX: &str = "window_state:";
+/// Moves and resizes arrive continuously while dragging; save once they settle
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+/// How much of the title bar must be on a monitor for a saved position to be used
+const VISIBLE_MARGIN: i32 = 40;
+
+/// Labels with a save scheduled
+static PENDING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct WindowState {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+/// A monitor's work area: x, y, width, height
+type Area = (i32, i32, u32, u32);
+
+fn key(label: &str) -> String {
+    if label.starts_with(note_windows::LABEL_PREFIX) {
+        format!("{}note", KEY_PREFIX)
+    } else {
+        format!("{}{}", KEY_PREFIX, label)
+    }
+}
+
+/// Whether the top of a window at `state` lands on one of `monitors`, so it can be dragged
+fn on_screen(state: &WindowState, monitors: &[Area]) -> bool {
+    let x = state.x + (state.width as i32 / 2).min(VISIBLE_MARGIN);
+    let y = state.y + VISIBLE_MARGIN / 2;
+    monitors
+        .iter()
+        .any(|&(mx, my, mw, mh)| x >= mx && x < mx + mw as i32 && y >= my && y < my + mh as i32)
+}
+
+fn load(app: &AppHandle<impl Runtime>, label: &str) -> Option<WindowState> {
+    let json = app
+        .try_state::<Database>()?
+        .get_setting(&key(label))
+        .ok()
+        .flatten()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Restore the window's saved state, then keep it up to date
+pub fn track<R: Runtime>(window: &WebviewWindow<R>) {
+    restore(window);
+
+    let handle = window.clone();
+    window.on_window_event(move |event| match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => schedule_save(&handle),
+        // A window being closed is gone by the time a scheduled save runs
+        WindowEvent::CloseRequested { .. } => save(&handle),
+        _ => {}
+    });
+}
+
+fn restore<R: Runtime>(window: &WebviewWindow<R>) {
+    let Some(state) = load(window.app_handle(), window.label()) else {
+        return;
+    };
+    let monitors: Vec<Area> = window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|m| {
+            let area = m.work_area();
+            (
+                area.position.x,
+                area.position.y,
+                area.size.width,
+                area.size.height,
+            )
+        })
+        .collect();
+
+    if window.is_resizable().unwrap_or(true) {
+        let _ = window.set_size(PhysicalSize::new(state.width, state.height));
+    }
+    if on_screen(&state, &monitors) {
+        let _ = window.set_position(PhysicalPosition::new(state.x, state.y));
+    }
+    if state.maximized {
+        let _ = window.maximize();
+    }
+}
+
+fn schedule_save<R: Runtime>(window: &WebviewWindow<R>) {
+    let label = window.label().to_string();
+    if !PENDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(label.clone())
+    {
+        return;
+    }
+    let app = window.app_handle().clone();
+    thread::spawn(move || {
+        thread::sleep(SAVE_DELAY);
+        PENDING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&label);
+        if let Some(window) = app.get_webview_window(&label) {
+            save(&window);
+        }
+    });
+}
+
+fn save<R: Runtime>(window: &WebviewWindow<R>) {
+    if window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let maximized = window.is_maximized().unwrap_or(false);
+    let state = if maximized {
+        // Keep the size and position to return to when unmaximized
+        match load(window.app_handle(), window.label()) {
+            Some(previous) => WindowState {
+                maximized: true,
+                ..previous
+            },
+            None => return,
+        }
+    } else {
+        let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+            return;
+        };
+        WindowState {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized: false,
+        }
+    };
+
+    let Some(db) = window.app_handle().try_state::<Database>() else {
+        return;
+    };
+    if let Ok(json) = serde_json::to_string(&state)
+        && let Err(e) = db.set_setting(&key(window.label()), &json)
+    {
+        tracing::warn!("Failed to save the {} window state: {}", window.label(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(x: i32, y: i32) -> WindowState {
+        WindowState {
+            x,
+            y,
+            width: 800,
+            height: 600,
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn test_on_screen() {
+        let monitors = [(0, 0, 1920, 1080), (1920, 0, 1280, 1024)];
+        assert!(on_screen(&state(100, 100), &monitors));
+        assert!(on_screen(&state(2000, 50), &monitors));
+        // Mostly off the left edge but the title bar is still reachable
+        assert!(on_screen(&state(-20, 0), &monitors));
+        // Left behind on a display that was unplugged
+        assert!(!on_screen(&state(3400, 100), &monitors));
+        assert!(!on_screen(&state(100, -500), &monitors));
+        assert!(!on_screen(&state(100, 100), &[]));
+    }
+
+    #[test]
+    fn test_note_windows_share_a_key() {
+        assert_eq!(key("main"), "window_state:main");
+        assert_eq!(key("note-abc"), key("note-def"));
+    }
+}